@@ -0,0 +1,380 @@
+// Manual adjustments of sales rows
+//
+// Sometimes a row is plain wrong (a known Valve reporting bug, a refund Steam booked twice) and
+// internal reports need it corrected. The sales row itself is never changed: an adjustment stores
+// the new values of some of its unit and amount columns in `adjustments`, next to the values they
+// replace and a reason, and queries overlay them on the rows when `apply_adjustments` is set in
+// QueryFilters. Reverting an adjustment keeps it in the table with the time it was reverted, so
+// the table is the audit trail of every correction.
+//
+// Adjustments refer to a row by its rowid and remember its key columns (see change_log.rs). If a
+// fetch replaces the row, the key no longer matches and the adjustment stops applying rather than
+// landing on another row.
+
+use crate::change_log;
+use crate::database::begin_write;
+use crate::itch;
+use crate::quarantine;
+use crate::queries::{self, get_connection, get_source_column, get_write_connection, QueryFilters};
+use crate::schema::{column_exists, table_exists};
+use crate::types::{AppError, AppResult};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+// Columns an adjustment can set; the unit columns take whole numbers
+const UNIT_COLUMNS: &[&str] = &["gross_units_sold", "gross_units_returned", "net_units_sold"];
+const AMOUNT_COLUMNS: &[&str] = &["gross_sales_usd", "net_sales_usd", "net_tax_usd"];
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Adjustment {
+    pub id: i64,
+    /// rowid of the adjusted row in sales_data
+    pub record_id: i64,
+    /// Key columns of the row when it was adjusted
+    pub row_key: serde_json::Value,
+    /// New values by column
+    pub fields: BTreeMap<String, f64>,
+    /// Values of the same columns before the adjustment
+    pub original: BTreeMap<String, Option<f64>>,
+    pub reason: String,
+    pub created_at: String,
+    pub reverted_at: Option<String>,
+}
+
+fn ensure_table(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS adjustments (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            record_id INTEGER NOT NULL,
+            row_key TEXT NOT NULL,
+            fields TEXT NOT NULL,
+            original TEXT NOT NULL,
+            reason TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+            reverted_at TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_adjustments_record ON adjustments(record_id);",
+    )
+}
+
+fn row_to_adjustment(r: &rusqlite::Row) -> SqliteResult<Adjustment> {
+    let row_key: String = r.get(2)?;
+    let fields: String = r.get(3)?;
+    let original: String = r.get(4)?;
+    Ok(Adjustment {
+        id: r.get(0)?,
+        record_id: r.get(1)?,
+        row_key: serde_json::from_str(&row_key).unwrap_or(serde_json::Value::Null),
+        fields: serde_json::from_str(&fields).unwrap_or_default(),
+        original: serde_json::from_str(&original).unwrap_or_default(),
+        reason: r.get(5)?,
+        created_at: r.get(6)?,
+        reverted_at: r.get(7)?,
+    })
+}
+
+const SELECT_ADJUSTMENT: &str =
+    "SELECT id, record_id, row_key, fields, original, reason, created_at, reverted_at
+     FROM adjustments";
+
+fn validate_fields(conn: &Connection, fields: &BTreeMap<String, f64>) -> AppResult<()> {
+    if fields.is_empty() {
+        return Err(AppError::InvalidInput(
+            "An adjustment must set at least one column".to_string(),
+        ));
+    }
+    for (column, value) in fields {
+        let is_units = UNIT_COLUMNS.contains(&column.as_str());
+        if !is_units && !AMOUNT_COLUMNS.contains(&column.as_str()) {
+            return Err(AppError::InvalidInput(format!(
+                "'{}' can't be adjusted; expected one of {}, {}",
+                column,
+                UNIT_COLUMNS.join(", "),
+                AMOUNT_COLUMNS.join(", ")
+            )));
+        }
+        if !column_exists(conn, "sales_data", column) {
+            return Err(AppError::InvalidInput(format!(
+                "The database has no {} column",
+                column
+            )));
+        }
+        if !value.is_finite() || (is_units && value.fract() != 0.0) {
+            return Err(AppError::InvalidInput(format!(
+                "Invalid value {} for {}",
+                value, column
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Adjusts the columns in `fields` of the sales row with rowid `record_id`. An earlier adjustment
+/// of the row still in effect is reverted, and the columns it set that `fields` doesn't are
+/// carried over, so each row has at most one adjustment in effect.
+pub fn apply(record_id: i64, fields: BTreeMap<String, f64>, reason: &str) -> AppResult<Adjustment> {
+    if reason.trim().is_empty() {
+        return Err(AppError::InvalidInput(
+            "An adjustment needs a reason".to_string(),
+        ));
+    }
+    let conn = get_write_connection()?;
+    validate_fields(&conn, &fields)?;
+    ensure_table(&conn)?;
+
+    let tx = begin_write(&conn)?;
+    let row_key: Option<String> = tx
+        .query_row(
+            &format!(
+                "SELECT {} FROM sales_data WHERE rowid = ?1",
+                change_log::key_expr(&tx, "sales_data")
+            ),
+            [record_id],
+            |r| r.get(0),
+        )
+        .optional()?;
+    let Some(row_key) = row_key else {
+        return Err(AppError::NotFound(format!(
+            "No sales row with id {}",
+            record_id
+        )));
+    };
+
+    let previous = tx
+        .query_row(
+            &format!(
+                "{} WHERE record_id = ?1 AND row_key = ?2 AND reverted_at IS NULL",
+                SELECT_ADJUSTMENT
+            ),
+            params![record_id, row_key],
+            row_to_adjustment,
+        )
+        .optional()?;
+    let mut merged = previous
+        .as_ref()
+        .map(|p| p.fields.clone())
+        .unwrap_or_default();
+    merged.extend(fields);
+
+    let mut original = BTreeMap::new();
+    for column in merged.keys() {
+        let value: Option<f64> = tx.query_row(
+            &format!(
+                "SELECT CAST({} AS REAL) FROM sales_data WHERE rowid = ?1",
+                column
+            ),
+            [record_id],
+            |r| r.get(0),
+        )?;
+        original.insert(column.clone(), value);
+    }
+
+    if let Some(previous) = &previous {
+        tx.execute(
+            "UPDATE adjustments SET reverted_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+             WHERE id = ?1",
+            [previous.id],
+        )?;
+    }
+    tx.execute(
+        "INSERT INTO adjustments (record_id, row_key, fields, original, reason)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            record_id,
+            row_key,
+            serde_json::json!(merged).to_string(),
+            serde_json::json!(original).to_string(),
+            reason.trim()
+        ],
+    )?;
+    let id = tx.last_insert_rowid();
+    let adjustment = tx.query_row(
+        &format!("{} WHERE id = ?1", SELECT_ADJUSTMENT),
+        [id],
+        row_to_adjustment,
+    )?;
+    tx.commit()?;
+    Ok(adjustment)
+}
+
+/// Stops an adjustment from applying; it stays in the table as reverted.
+pub fn revert(adjustment_id: i64) -> AppResult<()> {
+    let conn = get_write_connection()?;
+    ensure_table(&conn)?;
+    let reverted = conn.execute(
+        "UPDATE adjustments SET reverted_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+         WHERE id = ?1 AND reverted_at IS NULL",
+        [adjustment_id],
+    )?;
+    if reverted == 0 {
+        return Err(AppError::NotFound(format!(
+            "No adjustment {} in effect",
+            adjustment_id
+        )));
+    }
+    Ok(())
+}
+
+/// Adjustments, newest first; of one row if `record_id` is given, reverted ones too if asked.
+pub fn list(record_id: Option<i64>, include_reverted: bool) -> AppResult<Vec<Adjustment>> {
+    let conn = get_connection()?;
+    if !table_exists(&conn, "adjustments") {
+        return Ok(Vec::new());
+    }
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE (?1 IS NULL OR record_id = ?1) AND (?2 OR reverted_at IS NULL)
+         ORDER BY id DESC",
+        SELECT_ADJUSTMENT
+    ))?;
+    let adjustments = stmt
+        .query_map(params![record_id, include_reverted], row_to_adjustment)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(adjustments)
+}
+
+/// What queries read rows from (see `sales_source`). Formats as the table or subquery to put
+/// after FROM.
+pub(crate) struct SalesSource {
+    from: String,
+    /// Storefront column or expression of the rows, for `build_where_clause`
+    pub source_column: &'static str,
+}
+
+impl fmt::Display for SalesSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.from)
+    }
+}
+
+/// What queries read rows from: `sales_data`, or a subquery of the same columns (and rowid)
+/// when rows are added or changed. With `apply_adjustments` set and adjustments in effect, it has
+/// the adjusted values, and the source's itch.io purchases are added as rows with a NULL rowid
+/// (see itch.rs). Quarantined rows are left out either way.
+pub(crate) fn sales_source(conn: &Connection, filters: &QueryFilters) -> SalesSource {
+    let source_id = queries::source_id_for(filters);
+    let quarantined = quarantine::exclusion(conn, &source_id, "s");
+    let in_effect = filters.apply_adjustments == Some(true)
+        && table_exists(conn, "adjustments")
+        && conn
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM adjustments WHERE reverted_at IS NULL)",
+                [],
+                |r| r.get::<_, bool>(0),
+            )
+            .unwrap_or(false);
+    let with_itch = itch::has_sales_rows(conn, &source_id);
+    if !in_effect && !with_itch {
+        let from = match quarantined {
+            Some(condition) => format!(
+                "(SELECT s.rowid AS rowid, s.* FROM sales_data s WHERE {})",
+                condition
+            ),
+            None => "sales_data".to_string(),
+        };
+        return SalesSource {
+            from,
+            source_column: get_source_column(conn),
+        };
+    }
+
+    let columns: Vec<String> = conn
+        .prepare("SELECT name FROM pragma_table_info('sales_data')")
+        .and_then(|mut stmt| {
+            stmt.query_map([], |r| r.get::<_, String>(0))?
+                .collect::<SqliteResult<Vec<_>>>()
+        })
+        .unwrap_or_default();
+    let mut select: Vec<String> = columns
+        .iter()
+        .map(|column| {
+            // Cast to the column's usual type, since the values are read back as such
+            let cast = if !in_effect {
+                None
+            } else if UNIT_COLUMNS.contains(&column.as_str()) {
+                Some("INTEGER")
+            } else if AMOUNT_COLUMNS.contains(&column.as_str()) {
+                Some("TEXT")
+            } else {
+                None
+            };
+            if let Some(cast) = cast {
+                format!(
+                    "COALESCE(CAST(json_extract(adj.fields, '$.{c}') AS {t}), s.\"{c}\") AS \"{c}\"",
+                    c = column,
+                    t = cast
+                )
+            } else {
+                format!("s.\"{c}\" AS \"{c}\"", c = column)
+            }
+        })
+        .collect();
+    if with_itch && !columns.iter().any(|column| column == "source") {
+        select.push("'steam' AS source".to_string());
+    }
+    let adjusted = if in_effect {
+        format!(
+            "LEFT JOIN adjustments adj ON adj.record_id = s.rowid AND adj.reverted_at IS NULL
+               AND adj.row_key = {}",
+            change_log::key_expr(conn, "s")
+        )
+    } else {
+        String::new()
+    };
+    let mut from = format!(
+        "SELECT s.rowid AS rowid, {}
+         FROM sales_data s
+         {}
+         {}",
+        select.join(", "),
+        adjusted,
+        quarantined
+            .map(|condition| format!("WHERE {}", condition))
+            .unwrap_or_default()
+    );
+    if with_itch {
+        from = format!(
+            "{} UNION ALL {}",
+            from,
+            itch::sales_rows(conn, &source_id, &columns)
+        );
+    }
+    SalesSource {
+        from: format!("({})", from),
+        source_column: if with_itch {
+            "source"
+        } else {
+            get_source_column(conn)
+        },
+    }
+}
+
+// ==================== Tauri Commands ====================
+
+/// Overrides columns of a sales row (by `SalesRecord.rowId`) in queries that apply adjustments.
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn apply_manual_adjustment(
+    recordId: i64,
+    fields: BTreeMap<String, f64>,
+    reason: String,
+) -> AppResult<Adjustment> {
+    tokio::task::spawn_blocking(move || apply(recordId, fields, &reason)).await?
+}
+
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn revert_manual_adjustment(adjustmentId: i64) -> AppResult<()> {
+    tokio::task::spawn_blocking(move || revert(adjustmentId)).await?
+}
+
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn list_manual_adjustments(
+    recordId: Option<i64>,
+    includeReverted: Option<bool>,
+) -> AppResult<Vec<Adjustment>> {
+    tokio::task::spawn_blocking(move || list(recordId, includeReverted.unwrap_or(false))).await?
+}
@@ -0,0 +1,219 @@
+// Date annotations ("launched v1.2 patch", "featured in Daily Deal") stored with the sales data
+
+use crate::queries::{get_connection, get_write_connection, DailySummary};
+use crate::schema::table_exists;
+use crate::types::{AppError, AppResult, Date, Granularity};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Annotation {
+    pub id: i64,
+    pub date: String,
+    /// None for an annotation that applies to every app
+    pub app_id: Option<i64>,
+    pub label: String,
+    pub note: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnotationInput {
+    pub date: String,
+    pub app_id: Option<i64>,
+    pub label: String,
+    pub note: Option<String>,
+}
+
+pub(crate) fn ensure_table(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS annotations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date TEXT NOT NULL,
+            app_id INTEGER,
+            label TEXT NOT NULL,
+            note TEXT,
+            created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+        );
+        CREATE INDEX IF NOT EXISTS idx_annotations_date ON annotations(date);",
+    )
+}
+
+pub(crate) fn is_valid_date(date: &str) -> bool {
+    date.parse::<Date>().is_ok()
+}
+
+fn validate(input: &AnnotationInput) -> AppResult<()> {
+    if input.label.trim().is_empty() {
+        return Err(AppError::InvalidInput(
+            "Annotation label must not be empty".to_string(),
+        ));
+    }
+    if !is_valid_date(&input.date) {
+        return Err(AppError::InvalidInput(format!(
+            "Invalid date '{}', expected YYYY-MM-DD",
+            input.date
+        )));
+    }
+    Ok(())
+}
+
+fn row_to_annotation(r: &rusqlite::Row) -> SqliteResult<Annotation> {
+    Ok(Annotation {
+        id: r.get(0)?,
+        date: r.get(1)?,
+        app_id: r.get(2)?,
+        label: r.get(3)?,
+        note: r.get(4)?,
+        created_at: r.get(5)?,
+    })
+}
+
+/// Annotations between two dates (inclusive, either bound optional). With `app_ids`, only
+/// global annotations and those for the given apps are returned.
+pub fn list_annotations_in_range(
+    conn: &Connection,
+    start_date: Option<&str>,
+    end_date: Option<&str>,
+    app_ids: Option<&[i64]>,
+) -> SqliteResult<Vec<Annotation>> {
+    if !table_exists(conn, "annotations") {
+        return Ok(Vec::new());
+    }
+    let mut stmt = conn.prepare(
+        "SELECT id, date, app_id, label, note, created_at FROM annotations
+         WHERE (?1 IS NULL OR date >= ?1) AND (?2 IS NULL OR date <= ?2)
+         ORDER BY date, id",
+    )?;
+    let rows = stmt.query_map(params![start_date, end_date], row_to_annotation)?;
+
+    let mut annotations = Vec::new();
+    for row in rows {
+        let annotation = row?;
+        let matches_app = match (app_ids, annotation.app_id) {
+            (Some(ids), Some(app_id)) if !ids.is_empty() => ids.contains(&app_id),
+            _ => true,
+        };
+        if matches_app {
+            annotations.push(annotation);
+        }
+    }
+    Ok(annotations)
+}
+
+pub fn get_annotation(conn: &Connection, id: i64) -> SqliteResult<Option<Annotation>> {
+    if !table_exists(conn, "annotations") {
+        return Ok(None);
+    }
+    conn.query_row(
+        "SELECT id, date, app_id, label, note, created_at FROM annotations WHERE id = ?1",
+        [id],
+        row_to_annotation,
+    )
+    .optional()
+}
+
+/// Attaches matching annotations to each day of a daily series.
+/// Attaches annotations to the summaries of the periods their dates fall in.
+pub fn attach_to_daily(
+    conn: &Connection,
+    days: &mut [DailySummary],
+    app_ids: Option<&[i64]>,
+    granularity: Granularity,
+) -> SqliteResult<()> {
+    let (Some(first), Some(last)) = (days.first(), days.last()) else {
+        return Ok(());
+    };
+    let last_date = match last.date.parse::<Date>() {
+        Ok(date) => granularity.period_end(date).to_string(),
+        Err(_) => last.date.clone(),
+    };
+    let annotations =
+        list_annotations_in_range(conn, Some(&first.date), Some(&last_date), app_ids)?;
+
+    let mut by_date: HashMap<String, Vec<Annotation>> = HashMap::new();
+    for annotation in annotations {
+        let period = match annotation.date.parse::<Date>() {
+            Ok(date) => granularity.period_start(date).to_string(),
+            Err(_) => annotation.date.clone(),
+        };
+        by_date.entry(period).or_default().push(annotation);
+    }
+    for day in days.iter_mut() {
+        if let Some(annotations) = by_date.remove(&day.date) {
+            day.annotations = annotations;
+        }
+    }
+    Ok(())
+}
+
+pub fn insert_annotation(input: &AnnotationInput) -> SqliteResult<i64> {
+    let conn = get_write_connection()?;
+    ensure_table(&conn)?;
+    conn.execute(
+        "INSERT INTO annotations (date, app_id, label, note) VALUES (?1, ?2, ?3, ?4)",
+        params![input.date, input.app_id, input.label.trim(), input.note],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn update_annotation_by_id(id: i64, input: &AnnotationInput) -> SqliteResult<bool> {
+    let conn = get_write_connection()?;
+    ensure_table(&conn)?;
+    let changed = conn.execute(
+        "UPDATE annotations SET date = ?2, app_id = ?3, label = ?4, note = ?5 WHERE id = ?1",
+        params![id, input.date, input.app_id, input.label.trim(), input.note],
+    )?;
+    Ok(changed > 0)
+}
+
+pub fn delete_annotation_by_id(id: i64) -> SqliteResult<bool> {
+    let conn = get_write_connection()?;
+    ensure_table(&conn)?;
+    Ok(conn.execute("DELETE FROM annotations WHERE id = ?1", [id])? > 0)
+}
+
+// ==================== Tauri Commands ====================
+
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn list_annotations(
+    startDate: Option<String>,
+    endDate: Option<String>,
+    appIds: Option<Vec<i64>>,
+) -> AppResult<Vec<Annotation>> {
+    let conn = get_connection()?;
+    list_annotations_in_range(
+        &conn,
+        startDate.as_deref(),
+        endDate.as_deref(),
+        appIds.as_deref(),
+    )
+    .map_err(AppError::from)
+}
+
+#[tauri::command]
+pub async fn create_annotation(annotation: AnnotationInput) -> AppResult<i64> {
+    validate(&annotation)?;
+    insert_annotation(&annotation).map_err(AppError::from)
+}
+
+#[tauri::command]
+pub async fn update_annotation(id: i64, annotation: AnnotationInput) -> AppResult<()> {
+    validate(&annotation)?;
+    match update_annotation_by_id(id, &annotation)? {
+        true => Ok(()),
+        false => Err(AppError::NotFound(format!("Annotation {} not found", id))),
+    }
+}
+
+#[tauri::command]
+pub async fn delete_annotation(id: i64) -> AppResult<()> {
+    match delete_annotation_by_id(id)? {
+        true => Ok(()),
+        false => Err(AppError::NotFound(format!("Annotation {} not found", id))),
+    }
+}
@@ -0,0 +1,323 @@
+// Anomaly detection over daily revenue
+//
+// After each sync, the latest day's revenue and units per app are compared against that app's
+// trailing baseline. Days that deviate by more than ALERT_Z_SCORE standard deviations are
+// recorded in the `alerts` table of the app database with the data source they are about, so
+// they survive the CLI recreating the sales database. Earlier versions kept the table in the
+// sales database; it is moved over the first time alerts are used.
+
+use crate::database::{self, get_app_connection};
+use crate::presentation;
+use crate::queries::{get_app_id_column, get_connection};
+use crate::sources;
+use crate::types::{AppError, AppResult};
+use crate::webhooks;
+use rusqlite::{params, Connection, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Once;
+use tauri::{AppHandle, Emitter};
+use tracing::warn;
+
+// Trailing window (in days with data) used as the baseline
+const BASELINE_DAYS: i64 = 28;
+// Minimum number of baseline days before an app is evaluated at all
+const MIN_BASELINE_DAYS: usize = 7;
+const ALERT_Z_SCORE: f64 = 3.0;
+const CRITICAL_Z_SCORE: f64 = 4.0;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Alert {
+    pub id: i64,
+    pub created_at: String,
+    pub date: String,
+    pub app_id: i64,
+    pub metric: String,
+    pub value: f64,
+    pub baseline_mean: f64,
+    pub baseline_stddev: f64,
+    pub z_score: f64,
+    pub severity: String,
+    pub acknowledged: bool,
+}
+
+// Columns copied from the sales database's alerts table, which had no source_id
+const MOVED_COLUMNS: &[&str] = &[
+    "created_at",
+    "date",
+    "app_id",
+    "metric",
+    "value",
+    "baseline_mean",
+    "baseline_stddev",
+    "z_score",
+    "severity",
+    "acknowledged",
+];
+
+static MOVE_FROM_SALES_DATABASES: Once = Once::new();
+
+fn ensure_table(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS alerts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            source_id TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+            date TEXT NOT NULL,
+            app_id INTEGER NOT NULL,
+            metric TEXT NOT NULL,
+            value REAL NOT NULL,
+            baseline_mean REAL NOT NULL,
+            baseline_stddev REAL NOT NULL,
+            z_score REAL NOT NULL,
+            severity TEXT NOT NULL,
+            acknowledged INTEGER NOT NULL DEFAULT 0,
+            UNIQUE(source_id, date, app_id, metric)
+        );",
+    )
+}
+
+fn alerts_connection() -> SqliteResult<Connection> {
+    let conn = get_app_connection()?;
+    ensure_table(&conn)?;
+    MOVE_FROM_SALES_DATABASES.call_once(|| {
+        if let Err(e) = database::move_table_from_sales_databases(&conn, "alerts", MOVED_COLUMNS) {
+            warn!("Failed to move alerts to the app database: {}", e);
+        }
+    });
+    Ok(conn)
+}
+
+fn row_to_alert(r: &rusqlite::Row) -> SqliteResult<Alert> {
+    Ok(Alert {
+        id: r.get(0)?,
+        created_at: r.get(1)?,
+        date: r.get(2)?,
+        app_id: r.get(3)?,
+        metric: r.get(4)?,
+        value: r.get(5)?,
+        baseline_mean: r.get(6)?,
+        baseline_stddev: r.get(7)?,
+        z_score: r.get(8)?,
+        severity: r.get(9)?,
+        acknowledged: r.get(10)?,
+    })
+}
+
+fn mean_and_stddev(values: &[f64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+    (mean, variance.sqrt())
+}
+
+/// Evaluates the latest date in the database and stores any anomalies found.
+/// Returns the number of new alerts.
+pub fn detect_anomalies() -> SqliteResult<usize> {
+    let conn = get_connection()?;
+    let alerts = alerts_connection()?;
+    let source_id = sources::active_source_id();
+    let app_id_col = get_app_id_column(&conn);
+
+    let latest_date: Option<String> =
+        conn.query_row("SELECT MAX(date) FROM sales_data", [], |r| r.get(0))?;
+    let Some(latest_date) = latest_date else {
+        return Ok(0);
+    };
+
+    // Baseline dates: the days with any data before the latest date
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT date FROM sales_data WHERE date < ?1 ORDER BY date DESC LIMIT ?2",
+    )?;
+    let baseline_dates: Vec<String> = stmt
+        .query_map(params![latest_date, BASELINE_DAYS], |r| r.get(0))?
+        .collect::<Result<_, _>>()?;
+    let Some(window_start) = baseline_dates.last().cloned() else {
+        return Ok(0);
+    };
+
+    // Per app and date totals over the whole window (latest date included)
+    let sql = format!(
+        "SELECT {col}, date,
+            COALESCE(SUM(CAST(gross_sales_usd AS REAL)), 0),
+            COALESCE(SUM(net_units_sold), 0)
+         FROM sales_data
+         WHERE date >= ?1 AND date <= ?2 AND {col} IS NOT NULL AND {col} != 0
+         GROUP BY {col}, date",
+        col = app_id_col
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params![window_start, latest_date], |r| {
+        Ok((
+            r.get::<_, i64>(0)?,
+            r.get::<_, String>(1)?,
+            r.get::<_, f64>(2)?,
+            r.get::<_, i64>(3)?,
+        ))
+    })?;
+
+    let mut by_app: HashMap<i64, HashMap<String, (f64, f64)>> = HashMap::new();
+    for row in rows {
+        let (app_id, date, revenue, units) = row?;
+        by_app
+            .entry(app_id)
+            .or_default()
+            .insert(date, (revenue, units as f64));
+    }
+
+    // First sale date per app, so days before launch don't count as zero-revenue baseline days
+    let sql = format!(
+        "SELECT {col}, MIN(date) FROM sales_data WHERE {col} IS NOT NULL GROUP BY {col}",
+        col = app_id_col
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let first_dates: HashMap<i64, String> = stmt
+        .query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)))?
+        .collect::<Result<_, _>>()?;
+
+    let mut inserted = 0;
+    for (app_id, days) in &by_app {
+        let first_date = first_dates.get(app_id).map(String::as_str).unwrap_or("");
+        let baseline: Vec<(f64, f64)> = baseline_dates
+            .iter()
+            .filter(|d| d.as_str() >= first_date)
+            .map(|d| days.get(d).copied().unwrap_or((0.0, 0.0)))
+            .collect();
+        if baseline.len() < MIN_BASELINE_DAYS {
+            continue;
+        }
+        let latest = days.get(&latest_date).copied().unwrap_or((0.0, 0.0));
+
+        let metrics = [
+            (
+                "revenue",
+                latest.0,
+                baseline.iter().map(|b| b.0).collect::<Vec<_>>(),
+            ),
+            (
+                "units",
+                latest.1,
+                baseline.iter().map(|b| b.1).collect::<Vec<_>>(),
+            ),
+        ];
+        for (metric, value, history) in metrics {
+            let (mean, stddev) = mean_and_stddev(&history);
+            if stddev <= f64::EPSILON {
+                continue;
+            }
+            let z_score = (value - mean) / stddev;
+            if z_score.abs() < ALERT_Z_SCORE {
+                continue;
+            }
+            let severity = if z_score.abs() >= CRITICAL_Z_SCORE {
+                "critical"
+            } else {
+                "warning"
+            };
+            inserted += alerts.execute(
+                "INSERT OR IGNORE INTO alerts
+                    (source_id, date, app_id, metric, value, baseline_mean, baseline_stddev,
+                     z_score, severity)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    source_id,
+                    latest_date,
+                    app_id,
+                    metric,
+                    value,
+                    mean,
+                    stddev,
+                    z_score,
+                    severity
+                ],
+            )?;
+        }
+    }
+
+    Ok(inserted)
+}
+
+/// Runs detection after a successful fetch and notifies the frontend and webhooks about new
+/// alerts.
+pub async fn detect_after_sync(app: &AppHandle) {
+    match tokio::task::spawn_blocking(detect_anomalies).await {
+        Ok(Ok(count)) if count > 0 => {
+            let _ = app.emit("alerts-detected", count);
+            match tokio::task::spawn_blocking(move || newest_alerts(count)).await {
+                Ok(Ok(alerts)) => webhooks::notify_alerts(&alerts).await,
+                Ok(Err(e)) => tracing::error!("Failed to read new alerts: {}", e),
+                Err(e) => tracing::error!("Failed to read new alerts: {}", e),
+            }
+        }
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => tracing::error!("Anomaly detection failed: {}", e),
+        Err(e) => tracing::error!("Anomaly detection task failed: {}", e),
+    }
+}
+
+/// Alerts about the active data source, newest date first.
+pub fn get_alerts_list(limit: u32, include_acknowledged: bool) -> SqliteResult<Vec<Alert>> {
+    let conn = alerts_connection()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, created_at, date, app_id, metric, value, baseline_mean, baseline_stddev,
+                z_score, severity, acknowledged
+         FROM alerts
+         WHERE source_id = ?1 AND (?2 OR acknowledged = 0)
+         ORDER BY date DESC, ABS(z_score) DESC
+         LIMIT ?3",
+    )?;
+    let rows = stmt.query_map(
+        params![
+            sources::active_source_id(),
+            include_acknowledged,
+            limit as i64
+        ],
+        row_to_alert,
+    )?;
+    rows.collect()
+}
+
+/// The `count` most recently inserted alerts of the active data source.
+fn newest_alerts(count: usize) -> SqliteResult<Vec<Alert>> {
+    let conn = alerts_connection()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, created_at, date, app_id, metric, value, baseline_mean, baseline_stddev,
+                z_score, severity, acknowledged
+         FROM alerts WHERE source_id = ?1 ORDER BY id DESC LIMIT ?2",
+    )?;
+    let rows = stmt.query_map(
+        params![sources::active_source_id(), count as i64],
+        row_to_alert,
+    )?;
+    rows.collect()
+}
+
+pub fn acknowledge(id: i64) -> SqliteResult<()> {
+    let conn = alerts_connection()?;
+    conn.execute("UPDATE alerts SET acknowledged = 1 WHERE id = ?1", [id])?;
+    Ok(())
+}
+
+// ==================== Tauri Commands ====================
+
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn get_alerts(
+    limit: Option<u32>,
+    includeAcknowledged: Option<bool>,
+) -> AppResult<Vec<Alert>> {
+    get_alerts_list(limit.unwrap_or(100), includeAcknowledged.unwrap_or(false))
+        .map_err(AppError::from)
+        .and_then(presentation::present)
+}
+
+#[tauri::command]
+pub async fn acknowledge_alert(id: i64) -> AppResult<()> {
+    acknowledge(id).map_err(AppError::from)
+}
+
+#[tauri::command]
+pub async fn run_anomaly_detection() -> AppResult<usize> {
+    detect_anomalies().map_err(AppError::from)
+}
@@ -0,0 +1,389 @@
+// Local read-only HTTP API
+//
+// When enabled in the settings, the query functions are served as JSON on 127.0.0.1 so tools
+// like Grafana or scripts can read the active data source without the app's window:
+//
+//   GET /api/stats  /api/sales  /api/daily  /api/apps  /api/countries  /api/lookup/apps
+//   GET /api/sales/since
+//
+// Filters are query parameters (start_date, end_date, app_ids=1,2, country_code, source, limit,
+// offset, sort_by=date|revenue|units, sort_order=asc|desc, exclude_partial_day,
+// include_non_sale_items, revenue_basis=gross|net|net_after_tax, sale_types=a,b,
+// data_source=<source id>, apply_adjustments, and granularity for /api/daily); other values are
+// rejected with 400. Every request needs the token, as `Authorization: Bearer <token>` or
+// `?token=<token>`. The token is generated on first use and kept in app_state.
+//
+// /api/sales/since pages through the raw rows by rowid for replication (cursor, limit); see
+// replication.rs.
+
+use crate::app_state;
+use crate::queries::{self, QueryFilters};
+use crate::replication;
+use crate::settings;
+use crate::types::{
+    AppError, AppResult, Date, Granularity, RelativeRange, RevenueBasis, SortField, SortOrder,
+};
+use axum::extract::{Query, Request};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use tracing::{error, info, warn};
+
+// app_state key of the access token
+const API_TOKEN_KEY: &str = "api_server_token";
+
+const BIND_ATTEMPTS: u32 = 5;
+
+// Shuts the running server down when sent to or dropped
+static SHUTDOWN: Mutex<Option<(u16, oneshot::Sender<()>)>> = Mutex::new(None);
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiServerInfo {
+    pub enabled: bool,
+    pub running: bool,
+    pub url: String,
+    pub token: String,
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            AppError::InvalidInput(_) => StatusCode::BAD_REQUEST,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::DatabaseLocked(_) | AppError::Busy(_) => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(self)).into_response()
+    }
+}
+
+fn generate_token() -> AppResult<String> {
+    let mut bytes = [0u8; 24];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|e| AppError::Internal(format!("Failed to generate API token: {}", e)))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+fn api_token() -> AppResult<String> {
+    if let Some(token) = app_state::get_value(API_TOKEN_KEY)? {
+        return Ok(token);
+    }
+    let token = generate_token()?;
+    app_state::set_value(API_TOKEN_KEY, &token)?;
+    Ok(token)
+}
+
+// Compares without bailing out at the first differing byte
+fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+async fn require_token(request: Request, next: Next) -> Response {
+    let from_header = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_string);
+    let from_query = request.uri().query().and_then(|query| {
+        query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("token="))
+            .map(str::to_string)
+    });
+    let authorized = match (from_header.or(from_query), api_token()) {
+        (Some(given), Ok(expected)) => tokens_match(&given, &expected),
+        _ => false,
+    };
+    if authorized {
+        next.run(request).await
+    } else {
+        (StatusCode::UNAUTHORIZED, "Missing or invalid token").into_response()
+    }
+}
+
+/// Query parameters of the data endpoints; `QueryFilters` with app ids, metrics and sale types as
+/// comma lists.
+#[derive(Debug, Default, Deserialize)]
+struct ApiFilters {
+    start_date: Option<String>,
+    end_date: Option<String>,
+    date_range: Option<RelativeRange>,
+    app_ids: Option<String>,
+    country_code: Option<String>,
+    source: Option<String>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+    sort_by: Option<SortField>,
+    sort_order: Option<SortOrder>,
+    exclude_partial_day: Option<bool>,
+    granularity: Option<Granularity>,
+    group_products: Option<bool>,
+    metrics: Option<String>,
+    include_non_sale_items: Option<bool>,
+    revenue_basis: Option<RevenueBasis>,
+    sale_types: Option<String>,
+    data_source: Option<String>,
+    api_key_id: Option<String>,
+    apply_adjustments: Option<bool>,
+}
+
+impl TryFrom<ApiFilters> for QueryFilters {
+    type Error = AppError;
+
+    fn try_from(api: ApiFilters) -> AppResult<Self> {
+        let app_ids = api
+            .app_ids
+            .filter(|ids| !ids.is_empty())
+            .map(|ids| {
+                ids.split(',')
+                    .map(|id| id.trim().parse::<i64>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| AppError::InvalidInput(format!("Invalid app_ids '{}'", ids)))
+            })
+            .transpose()?;
+        let defaults = QueryFilters::default();
+        QueryFilters {
+            start_date: api.start_date.map(|d| d.parse::<Date>()).transpose()?,
+            end_date: api.end_date.map(|d| d.parse::<Date>()).transpose()?,
+            date_range: api.date_range,
+            app_ids,
+            country_code: api.country_code,
+            source: api.source,
+            limit: api.limit.or(defaults.limit),
+            offset: api.offset.or(defaults.offset),
+            sort_by: api.sort_by.or(defaults.sort_by),
+            sort_order: api.sort_order.or(defaults.sort_order),
+            exclude_partial_day: api.exclude_partial_day,
+            granularity: api.granularity,
+            group_products: api.group_products,
+            metrics: api
+                .metrics
+                .filter(|names| !names.is_empty())
+                .map(|names| names.split(',').map(|n| n.trim().to_string()).collect()),
+            include_non_sale_items: api.include_non_sale_items,
+            revenue_basis: api.revenue_basis,
+            sale_types: api
+                .sale_types
+                .filter(|types| !types.is_empty())
+                .map(|types| types.split(',').map(|t| t.trim().to_string()).collect()),
+            data_source: api.data_source,
+            api_key_id: api.api_key_id,
+            apply_adjustments: api.apply_adjustments,
+        }
+        .resolve_date_range()
+    }
+}
+
+/// Query parameters of /api/sales/since
+#[derive(Debug, Default, Deserialize)]
+struct SinceParams {
+    cursor: Option<i64>,
+    limit: Option<u32>,
+}
+
+// Runs a blocking query with the request's filters
+async fn with_filters<T, F>(api: ApiFilters, query: F) -> Result<Json<T>, AppError>
+where
+    T: Serialize + Send + 'static,
+    F: FnOnce(QueryFilters) -> rusqlite::Result<T> + Send + 'static,
+{
+    let filters = QueryFilters::try_from(api)?;
+    let result = tokio::task::spawn_blocking(move || query(filters)).await??;
+    Ok(Json(result))
+}
+
+fn router() -> Router {
+    Router::new()
+        .route(
+            "/api/stats",
+            get(|Query(f): Query<ApiFilters>| with_filters(f, queries::get_stats)),
+        )
+        .route(
+            "/api/sales",
+            get(|Query(f): Query<ApiFilters>| with_filters(f, queries::get_sales)),
+        )
+        .route(
+            "/api/sales/since",
+            get(|Query(p): Query<SinceParams>| async move {
+                let page = tokio::task::spawn_blocking(move || {
+                    replication::get_sales_since(p.cursor.unwrap_or(0), p.limit)
+                })
+                .await??;
+                Ok::<_, AppError>(Json(page))
+            }),
+        )
+        .route(
+            "/api/daily",
+            get(|Query(f): Query<ApiFilters>| with_filters(f, queries::get_daily_summaries)),
+        )
+        .route(
+            "/api/apps",
+            get(|Query(f): Query<ApiFilters>| with_filters(f, queries::get_app_summaries)),
+        )
+        .route(
+            "/api/countries",
+            get(|Query(f): Query<ApiFilters>| with_filters(f, queries::get_country_summaries)),
+        )
+        .route(
+            "/api/lookup/apps",
+            get(|| async {
+                let apps = tokio::task::spawn_blocking(queries::get_apps_lookup).await??;
+                Ok::<_, AppError>(Json(apps))
+            }),
+        )
+        .layer(middleware::from_fn(require_token))
+}
+
+/// Starts the server on `port`, replacing one that is already running.
+pub async fn start(port: u16) -> AppResult<()> {
+    stop();
+    // Make sure a token exists before the first request needs one
+    api_token()?;
+    let address = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    // A server that was just stopped may still hold the port for a moment
+    let mut attempts = 0;
+    let listener = loop {
+        match TcpListener::bind(address).await {
+            Ok(listener) => break listener,
+            Err(e) if attempts < BIND_ATTEMPTS => {
+                attempts += 1;
+                warn!("Failed to listen on {} ({}), retrying", address, e);
+                tokio::time::sleep(Duration::from_millis(200)).await;
+            }
+            Err(e) => {
+                return Err(AppError::Io(format!(
+                    "Failed to listen on {}: {}",
+                    address, e
+                )))
+            }
+        }
+    };
+
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    *SHUTDOWN.lock().unwrap() = Some((port, shutdown_tx));
+    tauri::async_runtime::spawn(async move {
+        info!("API server listening on http://{}", address);
+        let result = axum::serve(listener, router())
+            .with_graceful_shutdown(async {
+                let _ = shutdown_rx.await;
+            })
+            .await;
+        match result {
+            Ok(()) => info!("API server stopped"),
+            Err(e) => error!("API server failed: {}", e),
+        }
+    });
+    Ok(())
+}
+
+pub fn stop() {
+    if let Some((_, shutdown)) = SHUTDOWN.lock().unwrap().take() {
+        let _ = shutdown.send(());
+    }
+}
+
+fn running_port() -> Option<u16> {
+    SHUTDOWN.lock().unwrap().as_ref().map(|(port, _)| *port)
+}
+
+/// Starts the server at launch if it is enabled.
+pub fn start_if_enabled() {
+    let settings = settings::load_settings();
+    if !settings.api_server_enabled {
+        return;
+    }
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = start(settings.api_server_port).await {
+            warn!("Failed to start API server: {}", e);
+        }
+    });
+}
+
+fn server_info() -> AppResult<ApiServerInfo> {
+    let settings = settings::load_settings();
+    let port = running_port().unwrap_or(settings.api_server_port);
+    Ok(ApiServerInfo {
+        enabled: settings.api_server_enabled,
+        running: running_port().is_some(),
+        url: format!("http://127.0.0.1:{}/api", port),
+        token: api_token()?,
+    })
+}
+
+// ==================== Tauri Commands ====================
+
+#[tauri::command]
+pub async fn get_api_server_info() -> AppResult<ApiServerInfo> {
+    server_info()
+}
+
+/// Turns the server on or off, optionally on a new port, and remembers the choice.
+#[tauri::command]
+pub async fn set_api_server_enabled(enabled: bool, port: Option<u16>) -> AppResult<ApiServerInfo> {
+    let settings = settings::update_settings(|s| {
+        s.api_server_enabled = enabled;
+        if let Some(port) = port {
+            s.api_server_port = port;
+        }
+    })?;
+    if enabled {
+        start(settings.api_server_port).await?;
+    } else {
+        stop();
+    }
+    server_info()
+}
+
+/// Replaces the token; clients using the old one are rejected from now on.
+#[tauri::command]
+pub async fn regenerate_api_server_token() -> AppResult<ApiServerInfo> {
+    app_state::set_value(API_TOKEN_KEY, &generate_token()?)?;
+    server_info()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Uri;
+
+    fn parse(query: &str) -> Result<ApiFilters, StatusCode> {
+        let uri: Uri = format!("http://127.0.0.1/api/sales?{}", query)
+            .parse()
+            .unwrap();
+        Query::<ApiFilters>::try_from_uri(&uri)
+            .map(|Query(filters)| filters)
+            .map_err(|rejection| rejection.status())
+    }
+
+    #[test]
+    fn sort_parameters_are_whitelisted() {
+        let api = parse("start_date=2024-01-01&sort_by=revenue&sort_order=asc").unwrap();
+        let filters = QueryFilters::try_from(api).unwrap();
+        assert_eq!(filters.sort_by, Some(SortField::Revenue));
+        assert_eq!(filters.sort_order, Some(SortOrder::Asc));
+
+        for bad in [
+            "sort_order=DESC,%20(SELECT%201)",
+            "sort_order=down",
+            "sort_by=date%20DESC",
+            "sort_by=",
+        ] {
+            assert_eq!(parse(bad).err(), Some(StatusCode::BAD_REQUEST), "{}", bad);
+        }
+    }
+}
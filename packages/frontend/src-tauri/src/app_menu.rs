@@ -0,0 +1,153 @@
+// Native application menu
+//
+// The window menu bar (the global menu bar on macOS): File → Sync Now / Export…, Edit with the
+// usual clipboard items, View → Refresh and Help → Open Logs, each with a keyboard shortcut.
+// Sync Now does what the tray's "Sync now" does and Export… saves the sales records report (see
+// export.rs) through a native save dialog. Refresh is up to the frontend, so it is emitted to the
+// windows as a `menu-action` event. On macOS the menu also gets the standard application submenu
+// (About, Hide, Quit), and Cmd+W closes the window instead of quitting.
+
+use crate::export::{self, ExportFormat, Report};
+use crate::logging;
+use crate::tray;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use tauri::menu::{Menu, MenuBuilder, MenuEvent, MenuItemBuilder, SubmenuBuilder};
+use tauri::{AppHandle, Emitter, Wry};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+use tracing::{info, warn};
+
+// Prefixed so they can't be mistaken for the tray menu's ids
+const SYNC_NOW: &str = "menu_sync_now";
+const EXPORT: &str = "menu_export";
+const REFRESH: &str = "menu_refresh";
+const OPEN_LOGS: &str = "menu_open_logs";
+
+/// Builds the menu. Pass to `tauri::Builder::menu`.
+pub fn build(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
+    let mut menu = MenuBuilder::new(app);
+
+    #[cfg(target_os = "macos")]
+    {
+        let app_menu = SubmenuBuilder::new(app, "Steam Sales Analyzer")
+            .about(None)
+            .separator()
+            .services()
+            .separator()
+            .hide()
+            .hide_others()
+            .show_all()
+            .separator()
+            .quit()
+            .build()?;
+        menu = menu.item(&app_menu);
+    }
+
+    let file = SubmenuBuilder::new(app, "File")
+        .item(
+            &MenuItemBuilder::with_id(SYNC_NOW, "Sync Now")
+                .accelerator("CmdOrCtrl+Shift+S")
+                .build(app)?,
+        )
+        .item(
+            &MenuItemBuilder::with_id(EXPORT, "Export…")
+                .accelerator("CmdOrCtrl+E")
+                .build(app)?,
+        )
+        .separator();
+    #[cfg(target_os = "macos")]
+    let file = file.close_window();
+    #[cfg(not(target_os = "macos"))]
+    let file = file.quit();
+    let file = file.build()?;
+
+    let edit = SubmenuBuilder::new(app, "Edit")
+        .undo()
+        .redo()
+        .separator()
+        .cut()
+        .copy()
+        .paste()
+        .select_all()
+        .build()?;
+
+    let view = SubmenuBuilder::new(app, "View")
+        .item(
+            &MenuItemBuilder::with_id(REFRESH, "Refresh")
+                .accelerator("CmdOrCtrl+R")
+                .build(app)?,
+        )
+        .build()?;
+
+    let help = SubmenuBuilder::new(app, "Help")
+        .item(&MenuItemBuilder::with_id(OPEN_LOGS, "Open Logs").build(app)?)
+        .build()?;
+
+    menu = menu.items(&[&file, &edit, &view, &help]);
+    menu.build()
+}
+
+fn show_error(app: &AppHandle, title: &str, message: String) {
+    app.dialog()
+        .message(message)
+        .title(title)
+        .kind(MessageDialogKind::Error)
+        .show(|_| {});
+}
+
+fn write_export(path: &Path) -> Result<u64, String> {
+    let format = match path.extension().and_then(|e| e.to_str()) {
+        Some(e) if e.eq_ignore_ascii_case("json") => ExportFormat::Json,
+        _ => ExportFormat::Csv,
+    };
+    let mut writer = BufWriter::new(File::create(path).map_err(|e| e.to_string())?);
+    export::write_report(&mut writer, Report::Sales, format, None, None).map_err(|e| e.to_string())
+}
+
+// Asks where to save the sales records report, then writes it
+fn export(app: &AppHandle) {
+    let handle = app.clone();
+    app.dialog()
+        .file()
+        .set_title("Export sales records")
+        .set_file_name(Report::Sales.file_name(ExportFormat::Csv))
+        .add_filter("CSV", &["csv"])
+        .add_filter("JSON", &["json"])
+        .save_file(move |path| {
+            let Some(path) = path.and_then(|p| p.into_path().ok()) else {
+                return;
+            };
+            tauri::async_runtime::spawn_blocking(move || match write_export(&path) {
+                Ok(rows) => info!("Exported {} sales records to {}", rows, path.display()),
+                Err(e) => {
+                    warn!("Export to {} failed: {}", path.display(), e);
+                    show_error(&handle, "Export failed", e);
+                }
+            });
+        });
+}
+
+/// Handles a click or shortcut of the menu. Pass to `tauri::Builder::on_menu_event`.
+pub fn handle(app: &AppHandle, event: MenuEvent) {
+    match event.id.as_ref() {
+        SYNC_NOW => {
+            tray::show_main_window(app);
+            tauri::async_runtime::spawn(tray::sync_all(app.clone()));
+        }
+        EXPORT => export(app),
+        REFRESH => {
+            if let Err(e) = app.emit("menu-action", "refresh") {
+                warn!("Failed to emit menu-action: {}", e);
+            }
+        }
+        OPEN_LOGS => {
+            tauri::async_runtime::spawn(async {
+                if let Err(e) = logging::open_log_folder().await {
+                    warn!("Failed to open the log folder: {}", e);
+                }
+            });
+        }
+        _ => {}
+    }
+}
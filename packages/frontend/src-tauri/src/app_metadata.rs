@@ -0,0 +1,340 @@
+// Steam store metadata
+//
+// Name, header image, release date, genres and price of each app are read from the public store
+// appdetails endpoint and cached in the app database, so apps missing from the CLI's lookup
+// tables still get a name and a picture. The cache is shared by all data sources. Entries are
+// refreshed after a week; apps without a store page are remembered as unavailable for as long.
+//
+// The store allows roughly 200 requests per 5 minutes, so apps are looked up one at a time with
+// a pause in between, in the background after each fetch or when asked to.
+
+use crate::database::get_app_connection;
+use crate::queries;
+use crate::types::{AppError, AppResult};
+use rusqlite::{params, params_from_iter, Connection, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tracing::{info, warn};
+
+const APPDETAILS_URL: &str = "https://store.steampowered.com/api/appdetails";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+const REQUEST_DELAY: Duration = Duration::from_millis(1500);
+const REFRESH_AFTER_DAYS: u32 = 7;
+
+// Only one enrichment pass at a time
+static ENRICHING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppMetadata {
+    pub app_id: i64,
+    /// False when the store has no page for the app (e.g. removed or never released)
+    pub available: bool,
+    pub name: Option<String>,
+    pub header_image: Option<String>,
+    /// As shown on the store, e.g. "10 Oct, 2007" or "Coming soon"
+    pub release_date: Option<String>,
+    pub coming_soon: bool,
+    pub genres: Vec<String>,
+    pub is_free: bool,
+    /// US store price
+    pub price_currency: Option<String>,
+    pub price_initial_cents: Option<i64>,
+    pub price_final_cents: Option<i64>,
+    pub discount_percent: Option<i64>,
+    pub fetched_at: String,
+}
+
+// ==================== appdetails response ====================
+
+#[derive(Debug, Deserialize)]
+struct AppDetails {
+    success: bool,
+    data: Option<AppDetailsData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AppDetailsData {
+    name: Option<String>,
+    header_image: Option<String>,
+    release_date: Option<ReleaseDate>,
+    #[serde(default)]
+    genres: Vec<Genre>,
+    #[serde(default)]
+    is_free: bool,
+    price_overview: Option<PriceOverview>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseDate {
+    #[serde(default)]
+    coming_soon: bool,
+    date: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Genre {
+    description: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PriceOverview {
+    currency: Option<String>,
+    initial: Option<i64>,
+    #[serde(rename = "final")]
+    final_price: Option<i64>,
+    discount_percent: Option<i64>,
+}
+
+fn ensure_table(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS app_metadata (
+            app_id INTEGER PRIMARY KEY,
+            available INTEGER NOT NULL,
+            name TEXT,
+            header_image TEXT,
+            release_date TEXT,
+            coming_soon INTEGER NOT NULL DEFAULT 0,
+            genres TEXT NOT NULL DEFAULT '[]',
+            is_free INTEGER NOT NULL DEFAULT 0,
+            price_currency TEXT,
+            price_initial_cents INTEGER,
+            price_final_cents INTEGER,
+            discount_percent INTEGER,
+            fetched_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+        );",
+    )
+}
+
+fn row_to_metadata(r: &rusqlite::Row) -> SqliteResult<AppMetadata> {
+    let genres: String = r.get(6)?;
+    Ok(AppMetadata {
+        app_id: r.get(0)?,
+        available: r.get(1)?,
+        name: r.get(2)?,
+        header_image: r.get(3)?,
+        release_date: r.get(4)?,
+        coming_soon: r.get(5)?,
+        genres: serde_json::from_str(&genres).unwrap_or_default(),
+        is_free: r.get(7)?,
+        price_currency: r.get(8)?,
+        price_initial_cents: r.get(9)?,
+        price_final_cents: r.get(10)?,
+        discount_percent: r.get(11)?,
+        fetched_at: r.get(12)?,
+    })
+}
+
+const SELECT_COLUMNS: &str = "app_id, available, name, header_image, release_date, coming_soon,
+    genres, is_free, price_currency, price_initial_cents, price_final_cents, discount_percent,
+    fetched_at";
+
+/// Cached metadata of the given apps, or of every cached app.
+pub fn load_metadata(app_ids: Option<&[i64]>) -> SqliteResult<Vec<AppMetadata>> {
+    let conn = get_app_connection()?;
+    ensure_table(&conn)?;
+    match app_ids {
+        Some(ids) => {
+            let placeholders = vec!["?"; ids.len()].join(", ");
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {} FROM app_metadata WHERE app_id IN ({}) ORDER BY app_id",
+                SELECT_COLUMNS, placeholders
+            ))?;
+            let rows = stmt.query_map(params_from_iter(ids), row_to_metadata)?;
+            rows.collect()
+        }
+        None => {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {} FROM app_metadata ORDER BY app_id",
+                SELECT_COLUMNS
+            ))?;
+            let rows = stmt.query_map([], row_to_metadata)?;
+            rows.collect()
+        }
+    }
+}
+
+/// Store names of cached apps, for lookups that only have "App <id>".
+pub fn cached_names() -> HashMap<i64, String> {
+    let names = get_app_connection().and_then(|conn| {
+        ensure_table(&conn)?;
+        let mut stmt =
+            conn.prepare("SELECT app_id, name FROM app_metadata WHERE name IS NOT NULL")?;
+        let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?;
+        rows.collect::<SqliteResult<HashMap<i64, String>>>()
+    });
+    names.unwrap_or_else(|e| {
+        warn!("Failed to read cached app names: {}", e);
+        HashMap::new()
+    })
+}
+
+// Apps of the active source whose metadata is missing or older than REFRESH_AFTER_DAYS
+fn stale_app_ids(force: bool) -> AppResult<Vec<i64>> {
+    let app_ids: Vec<i64> = queries::get_apps_lookup()?
+        .into_iter()
+        .map(|app| app.app_id)
+        .collect();
+    if force {
+        return Ok(app_ids);
+    }
+    let conn = get_app_connection()?;
+    ensure_table(&conn)?;
+    let mut stmt = conn.prepare(
+        "SELECT app_id FROM app_metadata
+         WHERE julianday('now') - julianday(fetched_at) < ?1",
+    )?;
+    let fresh = stmt
+        .query_map([REFRESH_AFTER_DAYS], |r| r.get::<_, i64>(0))?
+        .collect::<SqliteResult<std::collections::HashSet<_>>>()?;
+    Ok(app_ids
+        .into_iter()
+        .filter(|id| !fresh.contains(id))
+        .collect())
+}
+
+async fn fetch_details(client: &reqwest::Client, app_id: i64) -> AppResult<Option<AppDetailsData>> {
+    let response = client
+        .get(APPDETAILS_URL)
+        .query(&[
+            ("appids", app_id.to_string()),
+            ("cc", "us".to_string()),
+            ("l", "english".to_string()),
+        ])
+        .send()
+        .await?;
+    if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Err(AppError::RateLimited(
+            "The Steam store is rate limiting metadata requests".to_string(),
+        ));
+    }
+    if !response.status().is_success() {
+        return Err(AppError::Network(format!(
+            "Steam store returned HTTP {}",
+            response.status()
+        )));
+    }
+    // {"440": {"success": true, "data": {...}}}
+    let mut body: HashMap<String, AppDetails> = response
+        .json()
+        .await
+        .map_err(|e| AppError::Network(format!("Unexpected appdetails response: {}", e)))?;
+    Ok(body
+        .remove(&app_id.to_string())
+        .filter(|details| details.success)
+        .and_then(|details| details.data))
+}
+
+fn save_details(app_id: i64, details: Option<AppDetailsData>) -> SqliteResult<()> {
+    let conn = get_app_connection()?;
+    ensure_table(&conn)?;
+    let Some(data) = details else {
+        conn.execute(
+            "INSERT INTO app_metadata (app_id, available) VALUES (?1, 0)
+             ON CONFLICT(app_id) DO UPDATE SET
+                available = 0,
+                fetched_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now')",
+            [app_id],
+        )?;
+        return Ok(());
+    };
+    let genres: Vec<String> = data.genres.into_iter().map(|g| g.description).collect();
+    let (release_date, coming_soon) = match data.release_date {
+        Some(release) => (release.date.filter(|d| !d.is_empty()), release.coming_soon),
+        None => (None, false),
+    };
+    let price = data.price_overview;
+    conn.execute(
+        "INSERT OR REPLACE INTO app_metadata (app_id, available, name, header_image,
+            release_date, coming_soon, genres, is_free, price_currency, price_initial_cents,
+            price_final_cents, discount_percent)
+         VALUES (?1, 1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            app_id,
+            data.name,
+            data.header_image,
+            release_date,
+            coming_soon,
+            serde_json::to_string(&genres).unwrap_or_else(|_| "[]".to_string()),
+            data.is_free,
+            price.as_ref().and_then(|p| p.currency.clone()),
+            price.as_ref().and_then(|p| p.initial),
+            price.as_ref().and_then(|p| p.final_price),
+            price.as_ref().and_then(|p| p.discount_percent),
+        ],
+    )?;
+    Ok(())
+}
+
+/// Looks up the active source's apps that need it and returns how many were updated.
+pub async fn enrich(force: bool) -> AppResult<u32> {
+    if ENRICHING.swap(true, Ordering::SeqCst) {
+        return Err(AppError::Busy(
+            "App metadata is already being updated".to_string(),
+        ));
+    }
+    let result = enrich_apps(force).await;
+    ENRICHING.store(false, Ordering::SeqCst);
+    result
+}
+
+async fn enrich_apps(force: bool) -> AppResult<u32> {
+    let app_ids = tokio::task::spawn_blocking(move || stale_app_ids(force)).await??;
+    if app_ids.is_empty() {
+        return Ok(0);
+    }
+    info!("Updating store metadata of {} app(s)", app_ids.len());
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()?;
+    let mut updated = 0;
+    for (i, app_id) in app_ids.into_iter().enumerate() {
+        if i > 0 {
+            tokio::time::sleep(REQUEST_DELAY).await;
+        }
+        match fetch_details(&client, app_id).await {
+            Ok(details) => {
+                tokio::task::spawn_blocking(move || save_details(app_id, details)).await??;
+                updated += 1;
+            }
+            // Later apps would be rejected too; they are picked up by the next pass
+            Err(e @ AppError::RateLimited(_)) => {
+                warn!("Stopping metadata update after {} app(s): {}", updated, e);
+                break;
+            }
+            Err(e) => warn!("Failed to read store metadata of app {}: {}", app_id, e),
+        }
+    }
+    Ok(updated)
+}
+
+/// Starts an enrichment pass without waiting for it, e.g. after a fetch.
+pub fn enrich_in_background() {
+    tauri::async_runtime::spawn(async {
+        match enrich(false).await {
+            Ok(0) | Err(AppError::Busy(_)) => {}
+            Ok(updated) => info!("Updated store metadata of {} app(s)", updated),
+            Err(e) => warn!("Failed to update store metadata: {}", e),
+        }
+    });
+}
+
+// ==================== Tauri Commands ====================
+
+/// Cached store metadata of the given apps, or of all cached apps.
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn get_app_metadata(appIds: Option<Vec<i64>>) -> AppResult<Vec<AppMetadata>> {
+    tokio::task::spawn_blocking(move || load_metadata(appIds.as_deref()))
+        .await?
+        .map_err(AppError::from)
+}
+
+/// Looks up the active source's apps on the store now. With `force`, fresh entries are
+/// refreshed too. Returns the number of apps updated.
+#[tauri::command]
+pub async fn refresh_app_metadata(force: Option<bool>) -> AppResult<u32> {
+    enrich(force.unwrap_or(false)).await
+}
@@ -0,0 +1,44 @@
+// Small key/value store in the app database for internal runtime flags that must survive restarts
+
+use crate::database::get_app_connection;
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+
+fn ensure_table(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS app_state (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+        );",
+    )
+}
+
+pub fn get_value(key: &str) -> SqliteResult<Option<String>> {
+    let conn = get_app_connection()?;
+    ensure_table(&conn)?;
+    conn.query_row("SELECT value FROM app_state WHERE key = ?1", [key], |row| {
+        row.get(0)
+    })
+    .optional()
+}
+
+pub fn set_value(key: &str, value: &str) -> SqliteResult<()> {
+    let conn = get_app_connection()?;
+    ensure_table(&conn)?;
+    conn.execute(
+        "INSERT INTO app_state (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET
+            value = excluded.value,
+            updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now')",
+        params![key, value],
+    )?;
+    Ok(())
+}
+
+pub fn get_flag(key: &str) -> bool {
+    matches!(get_value(key).ok().flatten().as_deref(), Some("true"))
+}
+
+pub fn set_flag(key: &str, value: bool) -> SqliteResult<()> {
+    set_value(key, if value { "true" } else { "false" })
+}
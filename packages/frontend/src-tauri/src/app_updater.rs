@@ -0,0 +1,256 @@
+// Desktop app updates
+//
+// Built on tauri-plugin-updater, configured under `plugins.updater` in tauri.conf.json: the
+// endpoint there serves the update manifest (`latest.json`) of the stable channel, and `pubkey`
+// is the public key the release bundles are signed with. The "beta" channel chosen in the
+// settings reads its manifest from BETA_ENDPOINT instead. Builds with an empty `pubkey` only
+// announce updates; the plugin never installs an unsigned one.
+//
+// Checks emit `app-update-available` once per new version. With `appAutoUpdate` on, the update
+// is then downloaded in the background and its signature checked by the plugin. A verified update
+// is installed when the app quits, or right away with `restart_to_update`, so the app is never
+// replaced while in use; `app-update-ready` tells the frontend one is waiting.
+
+use crate::logging::new_operation_id;
+use crate::settings;
+use crate::types::{AppError, AppResult};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Url};
+use tauri_plugin_updater::{Update, Updater, UpdaterExt};
+use tracing::{error, info, warn};
+
+// Update manifest of the beta channel, which lists prereleases as well as stable releases
+const BETA_ENDPOINT: &str =
+    "https://github.com/RadialGames/SteamSalesReporter/releases/download/beta/latest.json";
+// Delay before the first check, so startup isn't slowed down
+const INITIAL_CHECK_DELAY: Duration = Duration::from_secs(90);
+const CHECK_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+// The verified update waiting to be installed
+static PENDING: Mutex<Option<PendingUpdate>> = Mutex::new(None);
+// Set while an update downloads, so checks don't start a second download
+static DOWNLOADING: AtomicBool = AtomicBool::new(false);
+
+struct PendingUpdate {
+    update: Update,
+    bytes: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppUpdateCheck {
+    pub current_version: String,
+    /// Newest version on the channel; None if there is no newer one
+    pub latest_version: Option<String>,
+    pub update_available: bool,
+    /// "stable" or "beta"
+    pub channel: String,
+    /// Release notes of the latest version
+    pub notes: Option<String>,
+    /// Whether this build can install the update itself (it has the signing key); if not, it has
+    /// to be downloaded from the releases page
+    pub can_install: bool,
+    /// Version downloaded, verified and installed on the next restart
+    pub ready_version: Option<String>,
+}
+
+fn updater_error(e: tauri_plugin_updater::Error) -> AppError {
+    use tauri_plugin_updater::Error;
+    match e {
+        Error::Reqwest(e) => AppError::Network(e.to_string()),
+        Error::Network(m) => AppError::Network(m),
+        Error::TargetNotFound(_) | Error::TargetsNotFound(_) => {
+            AppError::NotFound("The latest release has no update for this platform".to_string())
+        }
+        Error::Minisign(_)
+        | Error::Base64(_)
+        | Error::SignatureUtf8(_)
+        | Error::SignedVersionMismatch { .. }
+        | Error::MissingSignedVersion => {
+            AppError::VerificationFailed(format!("Update signature verification failed: {}", e))
+        }
+        e => AppError::Internal(format!("App update failed: {}", e)),
+    }
+}
+
+// Whether tauri.conf.json has the key to verify updates with
+fn has_signing_key(app: &AppHandle) -> bool {
+    app.config()
+        .plugins
+        .0
+        .get("updater")
+        .and_then(|config| config.get("pubkey"))
+        .and_then(|key| key.as_str())
+        .is_some_and(|key| !key.trim().is_empty())
+}
+
+fn updater(app: &AppHandle, channel: &str) -> AppResult<Updater> {
+    let mut builder = app.updater_builder().timeout(REQUEST_TIMEOUT);
+    if channel == "beta" {
+        let endpoint = Url::parse(BETA_ENDPOINT)
+            .map_err(|e| AppError::Internal(format!("Invalid update endpoint: {}", e)))?;
+        builder = builder.endpoints(vec![endpoint]).map_err(updater_error)?;
+    }
+    builder.build().map_err(updater_error)
+}
+
+fn ready_version() -> Option<String> {
+    PENDING
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|p| p.update.version.clone())
+}
+
+async fn check(app: &AppHandle) -> AppResult<(AppUpdateCheck, Option<Update>)> {
+    let channel = settings::load_settings().app_update_channel;
+    let update = updater(app, &channel)?
+        .check()
+        .await
+        .map_err(updater_error)?;
+    let check = AppUpdateCheck {
+        current_version: app.package_info().version.to_string(),
+        latest_version: update.as_ref().map(|u| u.version.clone()),
+        update_available: update.is_some(),
+        channel,
+        notes: update.as_ref().and_then(|u| u.body.clone()),
+        can_install: has_signing_key(app),
+        ready_version: ready_version(),
+    };
+    Ok((check, update))
+}
+
+// Downloads and verifies the update and makes it the pending one
+async fn prepare(update: Update) -> AppResult<String> {
+    let version = update.version.clone();
+    if ready_version().as_deref() == Some(version.as_str()) {
+        return Ok(version);
+    }
+    let bytes = update
+        .download(|_, _| {}, || {})
+        .await
+        .map_err(updater_error)?;
+    *PENDING.lock().unwrap() = Some(PendingUpdate { update, bytes });
+    Ok(version)
+}
+
+async fn prepare_once(update: Update) -> AppResult<String> {
+    if DOWNLOADING.swap(true, Ordering::SeqCst) {
+        return Err(AppError::Busy(
+            "An app update is already being downloaded".to_string(),
+        ));
+    }
+    let result = prepare(update).await;
+    DOWNLOADING.store(false, Ordering::SeqCst);
+    result
+}
+
+// Installs the pending update; it is gone afterwards either way. On Windows the plugin starts
+// the installer and exits the app; `restart` decides whether the installer starts it again.
+fn take_and_install(restart: bool) -> AppResult<Option<String>> {
+    let Some(pending) = PENDING.lock().unwrap().take() else {
+        return Ok(None);
+    };
+    let version = pending.update.version.clone();
+    info!("Installing app update {}", version);
+    pending
+        .update
+        .restart_after_install(restart)
+        .install(&pending.bytes)
+        .map_err(updater_error)?;
+    info!("Installed app update {}", version);
+    Ok(Some(version))
+}
+
+/// Installs the pending update, if any. Call when the app exits.
+pub fn install_pending() {
+    if let Err(e) = take_and_install(false) {
+        error!("Failed to install app update: {}", e);
+    }
+}
+
+/// One background check. Returns the version it announced, so each version is only announced
+/// once per run of the app.
+#[tracing::instrument(skip_all, fields(op_id = %new_operation_id("app-update")))]
+async fn check_once(app: &AppHandle, last_notified: Option<&str>) -> Option<String> {
+    let (check, update) = match check(app).await {
+        Ok(result) => result,
+        Err(e) => {
+            warn!("App update check failed: {}", e);
+            return None;
+        }
+    };
+    let (Some(latest), Some(update)) = (check.latest_version.clone(), update) else {
+        return None;
+    };
+    if last_notified != Some(latest.as_str()) {
+        let _ = app.emit("app-update-available", &check);
+    }
+    if settings::load_settings().app_auto_update && check.can_install {
+        match prepare_once(update).await {
+            Ok(version) => {
+                info!("App update {} is ready and installs on restart", version);
+                let _ = app.emit("app-update-ready", &version);
+            }
+            Err(e) => warn!("Failed to download app update {}: {}", latest, e),
+        }
+    }
+    Some(latest)
+}
+
+/// Starts the background update loop.
+pub fn start(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(INITIAL_CHECK_DELAY).await;
+        let mut last_notified: Option<String> = None;
+        loop {
+            if let Some(version) = check_once(&app, last_notified.as_deref()).await {
+                last_notified = Some(version);
+            }
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
+
+// ==================== Tauri Commands ====================
+
+/// Looks for a newer version of the app on the configured channel.
+#[tauri::command]
+pub async fn check_app_update(app: AppHandle) -> AppResult<AppUpdateCheck> {
+    check(&app).await.map(|(check, _)| check)
+}
+
+/// Downloads and verifies the latest update now (e.g. with auto-update off) so it installs on
+/// the next restart. Returns its version.
+#[tauri::command]
+pub async fn download_app_update(app: AppHandle) -> AppResult<String> {
+    let (check, update) = check(&app).await?;
+    let Some(update) = update else {
+        return Err(AppError::NotFound("The app is up to date".to_string()));
+    };
+    if !check.can_install {
+        return Err(AppError::VerificationFailed(
+            "This build has no update signing key; download updates from the releases page"
+                .to_string(),
+        ));
+    }
+    let version = prepare_once(update).await?;
+    let _ = app.emit("app-update-ready", &version);
+    Ok(version)
+}
+
+/// Installs the downloaded update and starts the app again.
+#[tauri::command]
+pub async fn restart_to_update(app: AppHandle) -> AppResult<()> {
+    // On Windows the installer exits this app and starts the new version itself
+    if take_and_install(true)?.is_none() {
+        return Err(AppError::NotFound(
+            "No app update has been downloaded".to_string(),
+        ));
+    }
+    app.restart();
+}
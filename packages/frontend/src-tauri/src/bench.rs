@@ -0,0 +1,203 @@
+// Query benchmarks
+//
+// `steam-sales-analyzer --bench` times the hot paths of the dashboard on generated data, so a
+// change made for speed (an index, a rollup, a rewritten query) can be measured before and
+// after, and a slowdown shows up before a release. It writes the demo data of a profile (see
+// demo_data.rs) to a temporary database, which times the bulk insert, and points this process's
+// queries at it without changing the active source. Queries are called directly rather than
+// through the query cache; each runs once to warm SQLite's page cache and then `iterations`
+// times, and the report has p50/p95/max per query.
+// The data always ends on the same date, so every run of a profile measures the same rows, and
+// `--out` writes the report as JSON to compare two builds.
+
+use crate::demo_data::{self, DemoProfile};
+use crate::perf::{percentile, RowCount};
+use crate::queries::{self, QueryFilters};
+use crate::sources;
+use crate::types::{AppError, AppResult, Date, Granularity, RevenueBasis, SortField};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tracing::info;
+
+pub const DEFAULT_ITERATIONS: u32 = 10;
+// Last day of the generated data
+const END_DATE: &str = "2026-06-30";
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchResult {
+    pub query: String,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+    /// Rows the query returned
+    pub rows: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchReport {
+    pub profile: DemoProfile,
+    pub iterations: u32,
+    /// Rows of sales_data
+    pub database_rows: u64,
+    /// Time taken to generate and insert them
+    pub insert_ms: f64,
+    pub results: Vec<BenchResult>,
+}
+
+fn millis(start: Instant) -> f64 {
+    start.elapsed().as_secs_f64() * 1000.0
+}
+
+// Runs `query` once to warm up and then `iterations` times
+fn measure<T: RowCount>(
+    name: &str,
+    iterations: u32,
+    query: impl Fn() -> rusqlite::Result<T>,
+) -> AppResult<BenchResult> {
+    let rows = query()?.row_count();
+    let mut durations = Vec::with_capacity(iterations as usize);
+    for _ in 0..iterations {
+        let start = Instant::now();
+        query()?;
+        durations.push(millis(start));
+    }
+    durations.sort_by(f64::total_cmp);
+    Ok(BenchResult {
+        query: name.to_string(),
+        p50_ms: percentile(&durations, 50.0),
+        p95_ms: percentile(&durations, 95.0),
+        max_ms: durations.last().copied().unwrap_or(0.0),
+        rows,
+    })
+}
+
+fn run_queries(end: Date, rows: u64, iterations: u32) -> AppResult<Vec<BenchResult>> {
+    let top_app = queries::get_app_summaries(QueryFilters::default())?
+        .first()
+        .map(|app| app.app_id)
+        .ok_or_else(|| AppError::Internal("The benchmark database has no apps".to_string()))?;
+    let last_90_days = QueryFilters {
+        start_date: Some(end.add_days(-89)),
+        end_date: Some(end),
+        ..QueryFilters::default()
+    };
+
+    let mut results = vec![
+        measure("get_sales", iterations, || {
+            queries::get_sales(QueryFilters::default())
+        })?,
+        measure("get_sales filtered", iterations, || {
+            queries::get_sales(QueryFilters {
+                app_ids: Some(vec![top_app]),
+                country_code: Some("US".to_string()),
+                sort_by: Some(SortField::Revenue),
+                ..last_90_days.clone()
+            })
+        })?,
+        measure("get_sales deep page", iterations, || {
+            queries::get_sales(QueryFilters {
+                offset: Some((rows / 2) as u32),
+                ..QueryFilters::default()
+            })
+        })?,
+        measure("get_stats", iterations, || {
+            queries::get_stats(QueryFilters::default())
+        })?,
+    ];
+    results.push(measure("get_daily_summaries", iterations, || {
+        queries::get_daily_summaries(last_90_days.clone())
+    })?);
+    results.push(measure("get_daily_summaries weekly", iterations, || {
+        queries::get_daily_summaries(QueryFilters {
+            granularity: Some(Granularity::Week),
+            ..QueryFilters::default()
+        })
+    })?);
+    results.push(measure("get_app_summaries", iterations, || {
+        queries::get_app_summaries(QueryFilters::default())
+    })?);
+    results.push(measure("get_country_summaries", iterations, || {
+        queries::get_country_summaries(QueryFilters::default())
+    })?);
+    results.push(measure("get_product_stats", iterations, || {
+        queries::get_product_stats("app", top_app, RevenueBasis::Gross)
+    })?);
+    results.push(measure("get_launch_comparison", iterations, || {
+        queries::get_launch_comparison(365, "app", Some(false), false, RevenueBasis::Gross, None)
+    })?);
+    Ok(results)
+}
+
+fn print_report(report: &BenchReport) {
+    println!(
+        "{} profile: {} rows inserted in {:.0} ms; {} runs per query\n",
+        report.profile.label(),
+        report.database_rows,
+        report.insert_ms,
+        report.iterations
+    );
+    println!(
+        "{:<28} {:>10} {:>10} {:>10} {:>8}",
+        "query", "p50 ms", "p95 ms", "max ms", "rows"
+    );
+    for result in &report.results {
+        println!(
+            "{:<28} {:>10.2} {:>10.2} {:>10.2} {:>8}",
+            result.query,
+            result.p50_ms,
+            result.p95_ms,
+            result.max_ms,
+            result.rows.map_or("-".to_string(), |r| r.to_string())
+        );
+    }
+}
+
+fn write_json(report: &BenchReport, out: &Path) -> AppResult<()> {
+    let json = serde_json::to_string_pretty(report)
+        .map_err(|e| AppError::Internal(format!("Failed to encode the report: {}", e)))?;
+    fs::write(out, json)?;
+    info!("Wrote the benchmark report to {}", out.display());
+    Ok(())
+}
+
+/// Benchmarks the queries on the demo data of `profile`, prints the report and writes it as
+/// JSON to `out` if given.
+pub fn run(profile: DemoProfile, iterations: u32, out: Option<PathBuf>) -> AppResult<()> {
+    if iterations == 0 {
+        return Err(AppError::InvalidInput(
+            "--iterations must be at least 1".to_string(),
+        ));
+    }
+    let end: Date = END_DATE.parse()?;
+    let path = std::env::temp_dir().join(format!(
+        "steam-sales-bench-{}-{}.db",
+        profile.label(),
+        std::process::id()
+    ));
+    let _ = fs::remove_file(&path);
+
+    info!("Generating {} demo data", profile.label());
+    let start = Instant::now();
+    let database_rows = demo_data::generate_database(profile, end, &path)?;
+    let insert_ms = millis(start);
+    sources::use_database_for_process(&path);
+
+    let results = run_queries(end, database_rows, iterations);
+    let _ = fs::remove_file(&path);
+    let report = BenchReport {
+        profile,
+        iterations,
+        database_rows,
+        insert_ms,
+        results: results?,
+    };
+    print_report(&report);
+    match out {
+        Some(out) => write_json(&report, &out),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,243 @@
+// Launch benchmarks
+//
+// A benchmark is a product's launch series (revenue and units per day since launch) saved under
+// a name, e.g. "Game A launch, first 60 days, net", so later launches can be held against it.
+// The days are copied into the app database when the benchmark is saved and never recomputed:
+// the benchmark stays as it was even after the product's sales data is archived, restated or
+// lives in another data source. `query_launch_comparison` returns the benchmarks of the
+// requested product type and revenue basis after the live series when asked to, marked by
+// `benchmarkId`.
+
+use crate::database::get_app_connection;
+use crate::presentation;
+use crate::queries::{self, LaunchComparisonApp, LaunchDay};
+use crate::types::{AppError, AppResult, Date, RevenueBasis};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+
+const MAX_DAYS: u32 = 3660;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Benchmark {
+    pub id: i64,
+    pub name: String,
+    /// "app" or "package"
+    pub product_type: String,
+    pub product_id: i64,
+    pub product_name: Option<String>,
+    pub revenue_basis: RevenueBasis,
+    pub launch_date: Date,
+    /// Days since launch the series covers; fewer than asked for if the product was younger
+    pub days_count: u32,
+    pub created_at: String,
+    pub days: Vec<LaunchDay>,
+}
+
+fn ensure_table(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS benchmarks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            product_type TEXT NOT NULL,
+            product_id INTEGER NOT NULL,
+            product_name TEXT,
+            revenue_basis TEXT NOT NULL,
+            launch_date TEXT NOT NULL,
+            days TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+        );",
+    )
+}
+
+// As in the query parameters, e.g. "net_after_tax"
+fn basis_name(basis: RevenueBasis) -> String {
+    serde_json::to_value(basis)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn row_to_benchmark(row: &rusqlite::Row) -> SqliteResult<Benchmark> {
+    let basis: String = row.get(5)?;
+    let days: String = row.get(7)?;
+    let days: Vec<LaunchDay> = serde_json::from_str(&days).unwrap_or_default();
+    Ok(Benchmark {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        product_type: row.get(2)?,
+        product_id: row.get(3)?,
+        product_name: row.get(4)?,
+        revenue_basis: serde_json::from_value(serde_json::Value::String(basis)).unwrap_or_default(),
+        launch_date: row.get(6)?,
+        days_count: days.len() as u32,
+        created_at: row.get(8)?,
+        days,
+    })
+}
+
+pub fn list(conn: &Connection) -> SqliteResult<Vec<Benchmark>> {
+    ensure_table(conn)?;
+    let mut stmt = conn.prepare(
+        "SELECT id, name, product_type, product_id, product_name, revenue_basis, launch_date,
+                days, created_at
+         FROM benchmarks ORDER BY name",
+    )?;
+    let benchmarks = stmt
+        .query_map([], row_to_benchmark)?
+        .collect::<SqliteResult<Vec<_>>>()?;
+    Ok(benchmarks)
+}
+
+/// The benchmarks of `product_type` and `revenue_basis` as launch comparison series, cut to
+/// the first `max_days` days after launch like the live series.
+pub fn launch_series(
+    product_type: &str,
+    revenue_basis: RevenueBasis,
+    max_days: u32,
+) -> AppResult<Vec<LaunchComparisonApp>> {
+    let conn = get_app_connection()?;
+    let is_package = product_type == "package";
+    Ok(list(&conn)?
+        .into_iter()
+        .filter(|b| b.product_type == product_type && b.revenue_basis == revenue_basis)
+        .map(|b| LaunchComparisonApp {
+            app_id: (!is_package).then_some(b.product_id),
+            package_id: is_package.then_some(b.product_id),
+            app_name: b.product_name.clone().filter(|_| !is_package),
+            package_name: b.product_name.clone().filter(|_| is_package),
+            group_id: None,
+            benchmark_id: Some(b.id),
+            benchmark_name: Some(b.name),
+            launch_date: b.launch_date,
+            days: b.days.into_iter().filter(|d| d.day <= max_days).collect(),
+        })
+        .collect())
+}
+
+fn save(
+    name: &str,
+    product_type: &str,
+    product_id: i64,
+    max_days: u32,
+    revenue_basis: RevenueBasis,
+) -> AppResult<Benchmark> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::InvalidInput("Name must not be empty".to_string()));
+    }
+    if product_type != "app" && product_type != "package" {
+        return Err(AppError::InvalidInput(format!(
+            "Unknown product type '{}', expected app or package",
+            product_type
+        )));
+    }
+    if !(1..=MAX_DAYS).contains(&max_days) {
+        return Err(AppError::InvalidInput(format!(
+            "A benchmark covers between 1 and {} days",
+            MAX_DAYS
+        )));
+    }
+
+    let conn = get_app_connection()?;
+    ensure_table(&conn)?;
+    let taken = conn
+        .query_row("SELECT 1 FROM benchmarks WHERE name = ?1", [name], |_| {
+            Ok(())
+        })
+        .optional()?
+        .is_some();
+    if taken {
+        return Err(AppError::InvalidInput(format!(
+            "There already is a benchmark named '{}'",
+            name
+        )));
+    }
+
+    // Only complete days, so the frozen series doesn't end in a day Steam was still reporting
+    let series = queries::get_launch_comparison(
+        max_days,
+        product_type,
+        Some(true),
+        false,
+        revenue_basis,
+        Some(&[product_id]),
+    )?
+    .into_iter()
+    .next()
+    .ok_or_else(|| {
+        AppError::NotFound(format!(
+            "{} {} has no sales to save as a benchmark",
+            if product_type == "app" {
+                "App"
+            } else {
+                "Package"
+            },
+            product_id
+        ))
+    })?;
+    let product_name = series.app_name.or(series.package_name);
+    let days = serde_json::to_string(&series.days)
+        .map_err(|e| AppError::Internal(format!("Failed to encode benchmark: {}", e)))?;
+
+    conn.execute(
+        "INSERT INTO benchmarks
+            (name, product_type, product_id, product_name, revenue_basis, launch_date, days)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            name,
+            product_type,
+            product_id,
+            product_name,
+            basis_name(revenue_basis),
+            series.launch_date,
+            days
+        ],
+    )?;
+    let id = conn.last_insert_rowid();
+    list(&conn)?
+        .into_iter()
+        .find(|b| b.id == id)
+        .ok_or_else(|| AppError::Internal("Saved benchmark not found".to_string()))
+}
+
+// ==================== Tauri Commands ====================
+
+#[tauri::command]
+pub async fn list_benchmarks() -> AppResult<Vec<Benchmark>> {
+    let conn = get_app_connection()?;
+    presentation::present(list(&conn)?)
+}
+
+/// Saves the first `maxDays` days since launch of a product of the active data source as a
+/// benchmark named `name`.
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn save_benchmark(
+    name: String,
+    productType: String,
+    productId: i64,
+    maxDays: u32,
+    revenueBasis: Option<RevenueBasis>,
+) -> AppResult<Benchmark> {
+    tokio::task::spawn_blocking(move || {
+        save(
+            &name,
+            &productType,
+            productId,
+            maxDays,
+            revenueBasis.unwrap_or_default(),
+        )
+    })
+    .await?
+}
+
+#[tauri::command]
+pub async fn delete_benchmark(id: i64) -> AppResult<()> {
+    let conn = get_app_connection()?;
+    ensure_table(&conn)?;
+    match conn.execute("DELETE FROM benchmarks WHERE id = ?1", [id])? {
+        0 => Err(AppError::NotFound(format!("Benchmark {} not found", id))),
+        _ => Ok(()),
+    }
+}
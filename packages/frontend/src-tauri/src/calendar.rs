@@ -0,0 +1,157 @@
+// Calendar heatmap of daily sales
+//
+// One value per day of a year, for a GitHub-style calendar view. Next to the raw revenue or
+// units, every day gets its percentile among the year's days with sales and a level from 0 (no
+// sales) to 4 (top quarter), so the frontend can color the cells without loading the year's
+// daily summaries. Days after the last complete day are left out.
+
+use crate::presentation;
+use crate::queries::{self, get_app_id_column, get_connection};
+use crate::types::{AppError, AppResult, Date};
+use rusqlite::params_from_iter;
+use rusqlite::types::Value;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HeatmapMetric {
+    /// Gross revenue (USD)
+    #[default]
+    Revenue,
+    /// Net units sold
+    Units,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeatmapDay {
+    pub date: Date,
+    pub value: f64,
+    /// Share of the year's days with sales that sold at most as much, 0-1; 0 without sales
+    pub percentile: f64,
+    /// 0 without sales, otherwise 1-4 by quarter of the percentile
+    pub level: u8,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarHeatmap {
+    pub year: i32,
+    /// None for all apps
+    pub app_id: Option<i64>,
+    pub metric: HeatmapMetric,
+    pub total: f64,
+    pub max_value: f64,
+    /// January 1st up to December 31st or the last complete day
+    pub days: Vec<HeatmapDay>,
+}
+
+fn level_of(value: f64, percentile: f64) -> u8 {
+    if value <= 0.0 {
+        0
+    } else {
+        (percentile * 4.0).ceil().clamp(1.0, 4.0) as u8
+    }
+}
+
+pub fn get_calendar_heatmap(
+    year: i32,
+    app_id: Option<i64>,
+    metric: HeatmapMetric,
+) -> AppResult<CalendarHeatmap> {
+    if !(1000..=9999).contains(&year) {
+        return Err(AppError::InvalidInput(format!("Invalid year {}", year)));
+    }
+    let start: Date = format!("{}-01-01", year).parse()?;
+    let mut end: Date = format!("{}-12-31", year).parse()?;
+    if let Some(last) = queries::last_complete_day(None) {
+        end = end.min(last);
+    }
+    let mut heatmap = CalendarHeatmap {
+        year,
+        app_id,
+        metric,
+        total: 0.0,
+        max_value: 0.0,
+        days: Vec::new(),
+    };
+    if end < start {
+        return Ok(heatmap);
+    }
+
+    let conn = get_connection()?;
+    let value_expr = match metric {
+        HeatmapMetric::Revenue => "CAST(gross_sales_usd AS REAL)",
+        HeatmapMetric::Units => "net_units_sold",
+    };
+    let mut params: Vec<Value> = vec![start.to_string().into(), end.to_string().into()];
+    let app_condition = match app_id {
+        Some(app_id) => {
+            params.push(app_id.into());
+            format!("AND {} = ?3", get_app_id_column(&conn))
+        }
+        None => String::new(),
+    };
+
+    // CUME_DIST over the days with sales only, so a year with few sales still spreads its levels
+    let sql = format!(
+        "WITH RECURSIVE days(day) AS (
+            SELECT ?1
+            UNION ALL
+            SELECT date(day, '+1 day') FROM days WHERE day < ?2
+         ),
+         daily AS (
+            SELECT date, SUM({}) AS value
+            FROM sales_data
+            WHERE date >= ?1 AND date <= ?2 {}
+            GROUP BY date
+         ),
+         calendar AS (
+            SELECT day, COALESCE(daily.value, 0) AS value
+            FROM days LEFT JOIN daily ON daily.date = days.day
+         )
+         SELECT day, value,
+            CASE WHEN value > 0
+                THEN CUME_DIST() OVER (PARTITION BY value > 0 ORDER BY value)
+                ELSE 0
+            END
+         FROM calendar
+         ORDER BY day",
+        value_expr, app_condition
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params_from_iter(params), |r| {
+        let value: f64 = r.get(1)?;
+        let percentile: f64 = r.get(2)?;
+        Ok(HeatmapDay {
+            date: r.get(0)?,
+            value,
+            percentile,
+            level: level_of(value, percentile),
+        })
+    })?;
+    for day in rows {
+        let day = day?;
+        heatmap.total += day.value;
+        heatmap.max_value = heatmap.max_value.max(day.value);
+        heatmap.days.push(day);
+    }
+    Ok(heatmap)
+}
+
+// ==================== Tauri Commands ====================
+
+/// Revenue or units per day of `year`, with percentiles for coloring a calendar heatmap.
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn query_calendar_heatmap(
+    year: i32,
+    appId: Option<i64>,
+    metric: Option<HeatmapMetric>,
+) -> AppResult<CalendarHeatmap> {
+    tokio::task::spawn_blocking(move || {
+        get_calendar_heatmap(year, appId, metric.unwrap_or_default())
+    })
+    .await?
+    .and_then(presentation::present)
+}
@@ -0,0 +1,220 @@
+// Change log of sales rows
+//
+// The CLI writes sales_data in place, so after a fetch nothing tells which rows changed. With the
+// change log enabled in the settings, triggers on sales_data record every insert, update and
+// delete in `sales_changes`: the operation, the row's rowid, its key columns (date, line item
+// type, app, package, country, ...) as JSON and the time. External exports ask for the changes
+// since the time of their last run and only process those.
+//
+// The triggers are (re)created before every fetch, since the key columns depend on the schema
+// the CLI created. Rows replaced by INSERT OR REPLACE show up as inserts.
+
+use crate::database::{begin_write, BUSY_TIMEOUT};
+use crate::queries::{get_app_id_column, get_connection};
+use crate::schema::{column_exists, table_exists};
+use crate::settings;
+use crate::sources;
+use crate::types::{AppError, AppResult};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const TRIGGERS: [(&str, &str, &str); 3] = [
+    ("sales_changes_insert", "INSERT", "NEW"),
+    ("sales_changes_update", "UPDATE", "NEW"),
+    ("sales_changes_delete", "DELETE", "OLD"),
+];
+// Columns that identify a sales row, if sales_data has them; the app id column is added too
+pub(crate) const KEY_COLUMNS: &[&str] = &[
+    "date",
+    "line_item_type",
+    "partnerid",
+    "packageid",
+    "bundleid",
+    "country_code",
+    "platform",
+    "currency",
+    "key_request_id",
+    "source",
+];
+// Changes older than this are removed when the triggers are recreated
+const RETENTION_DAYS: u32 = 90;
+const DEFAULT_LIMIT: u32 = 10_000;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SalesChange {
+    pub id: i64,
+    /// "insert", "update" or "delete"
+    pub op: String,
+    /// rowid of the row in sales_data; deleted rows no longer have it
+    pub row_id: i64,
+    /// Key columns of the row, after the change (before it for deletes)
+    pub key: serde_json::Value,
+    /// UTC, e.g. 2024-05-01T12:00:00.000Z
+    pub changed_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SalesChanges {
+    /// Changes after the given time, oldest first
+    pub changes: Vec<SalesChange>,
+    /// True if more changes follow; pass the last id as `afterId` to get them
+    pub has_more: bool,
+}
+
+fn open_database(db_path: &Path) -> SqliteResult<Connection> {
+    let conn = Connection::open(db_path)?;
+    // The CLI may be writing to the same database
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    Ok(conn)
+}
+
+fn ensure_table(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS sales_changes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            op TEXT NOT NULL,
+            row_id INTEGER NOT NULL,
+            row_key TEXT NOT NULL,
+            changed_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+        );
+        CREATE INDEX IF NOT EXISTS idx_sales_changes_changed_at ON sales_changes(changed_at);",
+    )
+}
+
+fn drop_triggers(conn: &Connection) -> SqliteResult<()> {
+    for (name, _, _) in TRIGGERS {
+        conn.execute_batch(&format!("DROP TRIGGER IF EXISTS {};", name))?;
+    }
+    Ok(())
+}
+
+// json_object(...) of the key columns of the NEW or OLD row
+pub(crate) fn key_expr(conn: &Connection, row: &str) -> String {
+    let app_id_col = get_app_id_column(conn);
+    let mut columns: Vec<&str> = KEY_COLUMNS.to_vec();
+    columns.insert(2, &app_id_col);
+    let pairs: Vec<String> = columns
+        .iter()
+        .filter(|column| column_exists(conn, "sales_data", column))
+        .map(|column| format!("'{c}', {row}.\"{c}\"", c = column, row = row))
+        .collect();
+    format!("json_object({})", pairs.join(", "))
+}
+
+/// Creates or drops the change log triggers of the database at `db_path`, as the setting says.
+/// Does nothing if the database has no sales_data yet.
+pub fn apply_setting(db_path: &Path) -> AppResult<()> {
+    if !db_path.exists() {
+        return Ok(());
+    }
+    let conn = open_database(db_path)?;
+    if !table_exists(&conn, "sales_data") {
+        return Ok(());
+    }
+    let tx = begin_write(&conn)?;
+    drop_triggers(&tx)?;
+    if settings::load_settings().sales_change_log_enabled {
+        ensure_table(&tx)?;
+        for (name, event, row) in TRIGGERS {
+            tx.execute_batch(&format!(
+                "CREATE TRIGGER {name} AFTER {event} ON sales_data BEGIN
+                    INSERT INTO sales_changes (op, row_id, row_key)
+                    VALUES ('{op}', {row}.rowid, {key});
+                END;",
+                name = name,
+                event = event,
+                op = event.to_lowercase(),
+                row = row,
+                key = key_expr(&tx, row),
+            ))?;
+        }
+        tx.execute(
+            "DELETE FROM sales_changes
+             WHERE changed_at < strftime('%Y-%m-%dT%H:%M:%fZ', 'now', ?1)",
+            [format!("-{} days", RETENTION_DAYS)],
+        )?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+pub fn changes_since(
+    since: &str,
+    after_id: Option<i64>,
+    limit: Option<u32>,
+) -> AppResult<SalesChanges> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT);
+    if limit == 0 {
+        return Err(AppError::InvalidInput(
+            "Limit must be at least 1".to_string(),
+        ));
+    }
+    // Same format as changed_at, so the two compare as text
+    let since = DateTime::parse_from_rfc3339(since)
+        .map_err(|_| {
+            AppError::InvalidInput(format!(
+                "Invalid time '{}', expected e.g. 2024-05-01T12:00:00Z",
+                since
+            ))
+        })?
+        .with_timezone(&Utc)
+        .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+        .to_string();
+    let conn = get_connection()?;
+    if !table_exists(&conn, "sales_changes") {
+        return Ok(SalesChanges {
+            changes: Vec::new(),
+            has_more: false,
+        });
+    }
+
+    // One more change than asked for tells whether more follow
+    let mut stmt = conn.prepare(
+        "SELECT id, op, row_id, row_key, changed_at FROM sales_changes
+         WHERE changed_at > ?1 AND id > ?2
+         ORDER BY id
+         LIMIT ?3",
+    )?;
+    let mut changes = stmt
+        .query_map(
+            params![since, after_id.unwrap_or(0), limit as i64 + 1],
+            |r| {
+                let key: String = r.get(3)?;
+                Ok(SalesChange {
+                    id: r.get(0)?,
+                    op: r.get(1)?,
+                    row_id: r.get(2)?,
+                    key: serde_json::from_str(&key).unwrap_or(serde_json::Value::Null),
+                    changed_at: r.get(4)?,
+                })
+            },
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+    let has_more = changes.len() > limit as usize;
+    changes.truncate(limit as usize);
+    Ok(SalesChanges { changes, has_more })
+}
+
+// ==================== Tauri Commands ====================
+
+/// Changes of sales rows recorded after `since` (a UTC timestamp like 2024-05-01T12:00:00Z).
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn get_changes_since(
+    since: String,
+    afterId: Option<i64>,
+    limit: Option<u32>,
+) -> AppResult<SalesChanges> {
+    tokio::task::spawn_blocking(move || changes_since(&since, afterId, limit)).await?
+}
+
+/// Turns the change log on or off for the active data source and remembers the choice.
+#[tauri::command]
+pub async fn set_sales_change_log_enabled(enabled: bool) -> AppResult<()> {
+    settings::update_settings(|s| s.sales_change_log_enabled = enabled)?;
+    tokio::task::spawn_blocking(|| apply_setting(&sources::active_database_path())).await?
+}
@@ -1,15 +1,42 @@
+use crate::anomalies;
+use crate::app_metadata;
+use crate::app_state;
+use crate::change_log;
 use crate::database;
+use crate::discount_calendar;
+use crate::goals;
+use crate::keys;
+use crate::ledger;
+use crate::logging::new_operation_id;
+use crate::perf;
+use crate::precompute;
+use crate::quarantine;
+use crate::query_cache::QueryCache;
+use crate::read_snapshot;
+use crate::reviews;
+use crate::settings;
+use crate::sources;
+use crate::sync::{self, SyncState};
+use crate::types::{AppError, AppResult, ResultExt};
+use crate::updater;
+use crate::webhooks;
+use semver::{BuildMetadata, Version};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::fs;
+use std::io::{self, Read, Write};
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
-use std::time::Duration;
-use tauri::{AppHandle, Emitter};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager};
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command as TokioCommand;
+use tokio::sync::Notify;
 use tokio::time::timeout;
+use tracing::{debug, error, info, warn, Instrument};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CliStatus {
@@ -18,75 +45,568 @@ pub struct CliStatus {
     pub database_exists: bool,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VersionInfo {
+    pub version: String,
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    pub prerelease: Option<String>,
+    pub build: Option<String>,
+}
+
+impl From<&Version> for VersionInfo {
+    fn from(version: &Version) -> Self {
+        VersionInfo {
+            version: version.to_string(),
+            major: version.major,
+            minor: version.minor,
+            patch: version.patch,
+            prerelease: (!version.pre.is_empty()).then(|| version.pre.to_string()),
+            build: (!version.build.is_empty()).then(|| version.build.to_string()),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct VersionCheck {
     pub current_version: Option<String>,
     pub latest_version: String,
     pub update_available: bool,
+    /// "stable", or "prerelease" when prerelease CLI releases are included
+    pub channel: String,
+    pub current: Option<VersionInfo>,
+    pub latest: Option<VersionInfo>,
+    /// latest_version came from the local cache because GitHub could not be reached
+    pub stale: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadProgress {
+    pub downloaded_bytes: u64,
+    pub total_bytes: Option<u64>,
+    pub percent: Option<f64>,
 }
 
 #[derive(Debug, Deserialize)]
 struct GitHubRelease {
     tag_name: String,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    assets: Vec<GitHubAsset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubAsset {
+    name: String,
+    /// "sha256:<hex>", computed by GitHub for uploaded release assets
+    #[serde(default)]
+    digest: Option<String>,
 }
 
 const CLI_RELEASES_BASE: &str =
     "https://github.com/RadialGames/steam-financial-cli/releases/download";
 const CLI_RELEASES_API: &str =
     "https://api.github.com/repos/RadialGames/steam-financial-cli/releases/latest";
-const CLI_BINARY_NAME: &str = "steam-financial";
+// Release list, including prereleases (releases/latest never returns one)
+const CLI_RELEASES_LIST_API: &str =
+    "https://api.github.com/repos/RadialGames/steam-financial-cli/releases?per_page=30";
+const CLI_RELEASES_TAG_API: &str =
+    "https://api.github.com/repos/RadialGames/steam-financial-cli/releases/tags";
+pub(crate) const CLI_BINARY_NAME: &str = "steam-financial";
+// app_state keys of the cached latest release and of when GitHub's rate limit resets
+const LATEST_RELEASE_CACHE_KEY: &str = "cli_latest_release";
+const LATEST_PRERELEASE_CACHE_KEY: &str = "cli_latest_prerelease";
+const GITHUB_RATE_LIMIT_RESET_KEY: &str = "github_rate_limit_reset";
+// app_state keys recording the installed CLI version and how it was installed
+const CLI_VERSION_KEY: &str = "cli_installed_version";
+const CLI_SOURCE_KEY: &str = "cli_install_source";
+// Checksum manifests a release may publish next to its assets
+const CLI_CHECKSUM_FILES: &[&str] = &["SHA256SUMS", "checksums.txt"];
+// Time `fetch --help` gets to print the CLI's fetch options
+const FETCH_HELP_TIMEOUT: Duration = Duration::from_secs(5);
+// Time the CLI gets to exit after SIGTERM before it is killed
+const FETCH_TERMINATE_GRACE: Duration = Duration::from_secs(5);
+// Largest CLI archive we are willing to download
+const MAX_CLI_DOWNLOAD_BYTES: u64 = 256 * 1024 * 1024;
+// Attempts (resuming where the previous one stopped) before a download is given up
+const MAX_DOWNLOAD_ATTEMPTS: u32 = 5;
+// Minimum time between byte-level progress events
+const DOWNLOAD_PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+// Base64 minisign public key for release signatures; set at build time to require signed releases
+const CLI_MINISIGN_PUBLIC_KEY: Option<&str> = option_env!("CLI_MINISIGN_PUBLIC_KEY");
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedRelease {
+    version: String,
+    etag: Option<String>,
+    /// Unix timestamp (seconds) of the last successful check
+    fetched_at: u64,
+}
+
+struct LatestVersion {
+    version: String,
+    /// True when GitHub could not be asked and the cached answer was used
+    stale: bool,
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn load_cached_release(cache_key: &str) -> Option<CachedRelease> {
+    app_state::get_value(cache_key)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+}
+
+fn store_cached_release(cache_key: &str, release: &CachedRelease) {
+    if let Ok(json) = serde_json::to_string(release) {
+        if let Err(e) = app_state::set_value(cache_key, &json) {
+            warn!("Failed to cache latest release: {}", e);
+        }
+    }
+}
+
+// Falls back to the cached release when GitHub can't be asked right now
+fn cached_or(cache_key: &str, error: AppError) -> AppResult<LatestVersion> {
+    match load_cached_release(cache_key) {
+        Some(cached) => {
+            warn!("Using cached CLI version {} ({})", cached.version, error);
+            Ok(LatestVersion {
+                version: cached.version,
+                stale: true,
+            })
+        }
+        None => Err(error),
+    }
+}
+
+/// Fetches the latest CLI version from GitHub releases. Uses a conditional request against the
+/// cached release (unchanged releases don't count against the rate limit) and falls back to the
+/// cache while rate-limited or offline.
+async fn fetch_latest_cli_release(include_prerelease: bool) -> AppResult<LatestVersion> {
+    let (url, cache_key) = if include_prerelease {
+        (CLI_RELEASES_LIST_API, LATEST_PRERELEASE_CACHE_KEY)
+    } else {
+        (CLI_RELEASES_API, LATEST_RELEASE_CACHE_KEY)
+    };
+    let rate_limit_reset = app_state::get_value(GITHUB_RATE_LIMIT_RESET_KEY)
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(0);
+    if rate_limit_reset > unix_now() {
+        return cached_or(cache_key, AppError::RateLimited(format!(
+            "GitHub API rate limit exceeded; try again in {} minutes",
+            (rate_limit_reset - unix_now()).div_ceil(60)
+        )));
+    }
+    let cached = load_cached_release(cache_key);
 
-/// Fetches the latest CLI version from GitHub releases.
-async fn fetch_latest_cli_version() -> Result<String, String> {
-    println!("[fetch_latest_cli_version] Creating HTTP client...");
     let client = reqwest::Client::builder()
         .timeout(Duration::from_secs(10))
         .connect_timeout(Duration::from_secs(5))
         .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+        .map_err(|e| AppError::Internal(format!("Failed to create HTTP client: {}", e)))?;
     
-    println!("[fetch_latest_cli_version] Sending request to {}...", CLI_RELEASES_API);
+    debug!("Checking latest CLI release at {}", url);
+    let mut request = client
+        .get(url)
+        .header("User-Agent", "steam-sales-analyzer");
+    if let Some(etag) = cached.as_ref().and_then(|c| c.etag.as_deref()) {
+        request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+    }
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(e) => {
+            warn!("Release check request failed: {}", e);
+            return cached_or(
+                cache_key,
+                AppError::Network(format!("Failed to check for updates: {}", e)),
+            );
+        }
+    };
+
+    debug!("Release check response: HTTP {}", response.status());
+
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+    };
+    let status = response.status();
+
+    if status == reqwest::StatusCode::NOT_MODIFIED {
+        if let Some(mut cached) = cached {
+            cached.fetched_at = unix_now();
+            store_cached_release(cache_key, &cached);
+            return Ok(LatestVersion {
+                version: cached.version,
+                stale: false,
+            });
+        }
+    }
+
+    let rate_limited = status == reqwest::StatusCode::TOO_MANY_REQUESTS
+        || (status == reqwest::StatusCode::FORBIDDEN
+            && header("x-ratelimit-remaining").as_deref() == Some("0"));
+    if rate_limited {
+        let reset = header("x-ratelimit-reset")
+            .and_then(|v| v.parse::<u64>().ok())
+            .or_else(|| {
+                header("retry-after")
+                    .and_then(|v| v.parse::<u64>().ok())
+                    .map(|secs| unix_now() + secs)
+            })
+            .unwrap_or_else(|| unix_now() + 60);
+        let _ = app_state::set_value(GITHUB_RATE_LIMIT_RESET_KEY, &reset.to_string());
+        return cached_or(
+            cache_key,
+            AppError::RateLimited("GitHub API rate limit exceeded".to_string()),
+        );
+    }
+
+    if !status.is_success() {
+        return cached_or(
+            cache_key,
+            AppError::Network(format!("Failed to check for updates: HTTP {}", status)),
+        );
+    }
+
+    let etag = header("etag");
+    let tag_name = if include_prerelease {
+        // The newest release by semver precedence, prereleases included
+        let releases: Vec<GitHubRelease> = response
+            .json()
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to parse release info: {}", e)))?;
+        releases
+            .into_iter()
+            .filter(|r| !r.draft)
+            .filter_map(|r| parse_version(&r.tag_name).map(|v| (v, r.tag_name)))
+            .max_by(|(a, _), (b, _)| a.cmp_precedence(b))
+            .map(|(_, tag_name)| tag_name)
+            .ok_or_else(|| AppError::NotFound("No CLI releases found".to_string()))?
+    } else {
+        let release: GitHubRelease = response
+            .json()
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to parse release info: {}", e)))?;
+        release.tag_name
+    };
+
+    debug!("Latest CLI release tag: {}", tag_name);
+    let version = tag_name.trim_start_matches('v').to_string();
+    store_cached_release(cache_key, &CachedRelease {
+        version: version.clone(),
+        etag,
+        fetched_at: unix_now(),
+    });
+    Ok(LatestVersion {
+        version,
+        stale: false,
+    })
+}
+
+async fn fetch_latest_cli_version() -> AppResult<String> {
+    fetch_latest_cli_release(include_prereleases())
+        .await
+        .map(|latest| latest.version)
+}
+
+async fn fetch_text(client: &reqwest::Client, url: &str) -> Option<String> {
     let response = client
-        .get(CLI_RELEASES_API)
+        .get(url)
         .header("User-Agent", "steam-sales-analyzer")
         .send()
         .await
-        .map_err(|e| {
-            println!("[fetch_latest_cli_version] Request failed: {}", e);
-            format!("Failed to check for updates: {}", e)
+        .ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    response.text().await.ok()
+}
+
+/// Finds the published SHA-256 of a release asset: the GitHub asset digest first, then a
+/// `<asset>.sha256` file, then a checksum manifest listing the asset.
+async fn fetch_expected_sha256(
+    client: &reqwest::Client,
+    version: &str,
+    asset_name: &str,
+) -> AppResult<String> {
+    let release_url = format!("{}/v{}", CLI_RELEASES_TAG_API, version);
+    if let Some(release) = fetch_text(client, &release_url)
+        .await
+        .and_then(|body| serde_json::from_str::<GitHubRelease>(&body).ok())
+    {
+        let digest = release
+            .assets
+            .iter()
+            .find(|a| a.name == asset_name)
+            .and_then(|a| a.digest.as_deref())
+            .and_then(|d| d.strip_prefix("sha256:"));
+        if let Some(digest) = digest {
+            return Ok(digest.to_lowercase());
+        }
+    }
+
+    let sidecar_url = format!("{}/v{}/{}.sha256", CLI_RELEASES_BASE, version, asset_name);
+    if let Some(body) = fetch_text(client, &sidecar_url).await {
+        if let Some(hash) = body.split_whitespace().next() {
+            return Ok(hash.to_lowercase());
+        }
+    }
+
+    for manifest in CLI_CHECKSUM_FILES {
+        let manifest_url = format!("{}/v{}/{}", CLI_RELEASES_BASE, version, manifest);
+        let Some(body) = fetch_text(client, &manifest_url).await else {
+            continue;
+        };
+        // "<hash>  <file>" or "<hash> *<file>" (sha256sum format)
+        let hash = body.lines().find_map(|line| {
+            let (hash, file) = line.trim().split_once(char::is_whitespace)?;
+            (file.trim().trim_start_matches('*') == asset_name).then(|| hash.to_lowercase())
+        });
+        if let Some(hash) = hash {
+            return Ok(hash);
+        }
+    }
+
+    Err(AppError::VerificationFailed(format!(
+        "No checksum is published for {} v{}; refusing to install an unverified binary",
+        asset_name, version
+    )))
+}
+
+/// Verifies the release's minisign signature when a public key was configured at build time.
+async fn verify_signature(
+    client: &reqwest::Client,
+    download_url: &str,
+    archive_path: &Path,
+) -> AppResult<()> {
+    let Some(public_key) = CLI_MINISIGN_PUBLIC_KEY else {
+        return Ok(());
+    };
+    let bytes = fs::read(archive_path).map_err(|e| AppError::Io(format!("Failed to read download: {}", e)))?;
+    let public_key = minisign_verify::PublicKey::from_base64(public_key)
+        .map_err(|e| AppError::Internal(format!("Invalid CLI signing key: {}", e)))?;
+    let signature_text = fetch_text(client, &format!("{}.minisig", download_url))
+        .await
+        .ok_or_else(|| {
+            AppError::VerificationFailed(
+                "The CLI release is not signed; refusing to install it".to_string(),
+            )
         })?;
+    let signature = minisign_verify::Signature::decode(&signature_text)
+        .map_err(|e| AppError::VerificationFailed(format!("Invalid CLI signature: {}", e)))?;
+    public_key
+        .verify(&bytes, &signature, false)
+        .map_err(|e| AppError::VerificationFailed(format!("CLI signature verification failed: {}", e)))
+}
 
-    println!("[fetch_latest_cli_version] Got response: HTTP {}", response.status());
-    
-    if !response.status().is_success() {
-        return Err(format!(
-            "Failed to check for updates: HTTP {}",
-            response.status()
+fn sha256_file(path: &Path) -> AppResult<String> {
+    let mut file = fs::File::open(path).map_err(|e| AppError::Io(format!("Failed to open download: {}", e)))?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher).map_err(|e| AppError::Io(format!("Failed to read download: {}", e)))?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+enum DownloadError {
+    /// Connection problems; the next attempt resumes from the bytes already on disk
+    Retryable(String),
+    Fatal(AppError),
+}
+
+fn emit_download_progress(app: &AppHandle, downloaded_bytes: u64, total_bytes: Option<u64>) {
+    let percent = total_bytes
+        .filter(|total| *total > 0)
+        .map(|total| (downloaded_bytes as f64 / total as f64 * 100.0).min(100.0));
+    let _ = app.emit(
+        "download-bytes-progress",
+        DownloadProgress {
+            downloaded_bytes,
+            total_bytes,
+            percent,
+        },
+    );
+}
+
+/// One download attempt, appending to `path` via an HTTP range request when it already holds
+/// part of the file. Returns the complete file size.
+async fn download_attempt(
+    app: &AppHandle,
+    client: &reqwest::Client,
+    url: &str,
+    path: &Path,
+) -> Result<u64, DownloadError> {
+    let existing = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    let mut request = client.get(url).header("User-Agent", "steam-sales-analyzer");
+    if existing > 0 {
+        request = request.header(reqwest::header::RANGE, format!("bytes={}-", existing));
+    }
+    let mut response = request
+        .send()
+        .await
+        .map_err(|e| DownloadError::Retryable(format!("Failed to download: {}", e)))?;
+
+    let status = response.status();
+    let (mut downloaded, total, append) = if status == reqwest::StatusCode::PARTIAL_CONTENT {
+        let _ = app.emit(
+            "download-progress",
+            format!("Resuming download at {} bytes...", existing),
+        );
+        (
+            existing,
+            response.content_length().map(|len| len + existing),
+            true,
+        )
+    } else if status == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+        // Either the partial file is already complete or it is stale; the server tells us which
+        let total = response
+            .headers()
+            .get(reqwest::header::CONTENT_RANGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.rsplit('/').next())
+            .and_then(|v| v.parse::<u64>().ok());
+        if total == Some(existing) {
+            return Ok(existing);
+        }
+        let _ = fs::remove_file(path);
+        return Err(DownloadError::Retryable(
+            "Discarded a stale partial download".to_string(),
         ));
+    } else if status.is_success() {
+        (0, response.content_length(), false)
+    } else {
+        return Err(DownloadError::Fatal(AppError::Network(format!(
+            "Failed to download: HTTP {}",
+            status
+        ))));
+    };
+
+    if total.is_some_and(|total| total > MAX_CLI_DOWNLOAD_BYTES) {
+        let _ = fs::remove_file(path);
+        return Err(DownloadError::Fatal(AppError::VerificationFailed(format!(
+            "Download is larger than the {} MB limit",
+            MAX_CLI_DOWNLOAD_BYTES / 1024 / 1024
+        ))));
+    }
+    if downloaded == 0 {
+        if let Some(total) = total {
+            let _ = app.emit("download-progress", format!("Downloading {} bytes...", total));
+        }
     }
 
-    println!("[fetch_latest_cli_version] Parsing JSON...");
-    let release: GitHubRelease = response
-        .json()
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)
+        .map_err(|e| DownloadError::Fatal(AppError::Io(format!("Failed to save download: {}", e))))?;
+
+    let mut last_emit = Instant::now();
+    emit_download_progress(app, downloaded, total);
+    while let Some(chunk) = response
+        .chunk()
         .await
-        .map_err(|e| format!("Failed to parse release info: {}", e))?;
+        .map_err(|e| DownloadError::Retryable(format!("Download interrupted: {}", e)))?
+    {
+        file.write_all(&chunk)
+            .map_err(|e| DownloadError::Fatal(AppError::Io(format!("Failed to save download: {}", e))))?;
+        downloaded += chunk.len() as u64;
+        if downloaded > MAX_CLI_DOWNLOAD_BYTES {
+            drop(file);
+            let _ = fs::remove_file(path);
+            return Err(DownloadError::Fatal(AppError::VerificationFailed(format!(
+                "Download is larger than the {} MB limit",
+                MAX_CLI_DOWNLOAD_BYTES / 1024 / 1024
+            ))));
+        }
+        if last_emit.elapsed() >= DOWNLOAD_PROGRESS_INTERVAL {
+            emit_download_progress(app, downloaded, total);
+            last_emit = Instant::now();
+        }
+    }
+    file.flush()
+        .map_err(|e| DownloadError::Fatal(AppError::Io(format!("Failed to save download: {}", e))))?;
+    emit_download_progress(app, downloaded, total);
+
+    if total.is_some_and(|total| downloaded < total) {
+        return Err(DownloadError::Retryable(format!(
+            "Download ended early ({} of {} bytes)",
+            downloaded,
+            total.unwrap_or_default()
+        )));
+    }
+    Ok(downloaded)
+}
 
-    println!("[fetch_latest_cli_version] Got tag_name: {}", release.tag_name);
-    Ok(release.tag_name.trim_start_matches('v').to_string())
+/// Streams `url` to `path`, resuming after dropped connections. Returns the file size.
+async fn download_to_file(
+    app: &AppHandle,
+    client: &reqwest::Client,
+    url: &str,
+    path: &Path,
+) -> AppResult<u64> {
+    let mut last_error = String::new();
+    for attempt in 1..=MAX_DOWNLOAD_ATTEMPTS {
+        match download_attempt(app, client, url, path).await {
+            Ok(size) => return Ok(size),
+            Err(DownloadError::Fatal(e)) => return Err(e),
+            Err(DownloadError::Retryable(e)) => {
+                if attempt < MAX_DOWNLOAD_ATTEMPTS {
+                    let _ = app.emit(
+                        "download-progress",
+                        format!(
+                            "{}; retrying (attempt {}/{})...",
+                            e,
+                            attempt + 1,
+                            MAX_DOWNLOAD_ATTEMPTS
+                        ),
+                    );
+                    tokio::time::sleep(Duration::from_secs(attempt as u64)).await;
+                }
+                last_error = e;
+            }
+        }
+    }
+    Err(AppError::Network(format!(
+        "Failed to download after {} attempts: {}",
+        MAX_DOWNLOAD_ATTEMPTS, last_error
+    )))
 }
 
 /// Tauri command to get just the latest version from GitHub (no local version check)
 #[tauri::command]
-pub async fn get_latest_github_version() -> Result<String, String> {
+pub async fn get_latest_github_version() -> AppResult<String> {
     fetch_latest_cli_version().await
 }
 
-fn get_cli_dir() -> PathBuf {
+pub(crate) fn get_cli_dir() -> PathBuf {
     let home = dirs::home_dir().expect("Failed to get home directory");
     home.join(".steamsales").join("cli")
 }
 
-fn get_cli_binary_path() -> PathBuf {
+fn get_download_dir() -> PathBuf {
+    let home = dirs::home_dir().expect("Failed to get home directory");
+    home.join(".steamsales").join("downloads")
+}
+
+pub(crate) fn get_cli_binary_path() -> PathBuf {
     let mut path = get_cli_dir();
     #[cfg(windows)]
     path.push(format!("{}.exe", CLI_BINARY_NAME));
@@ -112,7 +632,7 @@ fn get_binary_name() -> String {
 }
 
 #[tauri::command]
-pub async fn get_cli_status() -> Result<CliStatus, String> {
+pub async fn get_cli_status() -> AppResult<CliStatus> {
     let binary_path = get_cli_binary_path();
     let installed = binary_path.exists();
 
@@ -137,7 +657,11 @@ pub async fn get_cli_status() -> Result<CliStatus, String> {
             }
             _ => {
                 // Timeout or error - version check failed, but we still know CLI is installed
-                // Just leave version as None
+                // Fall back to the version recorded at install time, if any
+                version = app_state::get_value(CLI_VERSION_KEY)
+                    .ok()
+                    .flatten()
+                    .filter(|v| !v.is_empty());
             }
         }
     }
@@ -156,64 +680,52 @@ pub async fn get_cli_status() -> Result<CliStatus, String> {
     })
 }
 
-// Extract version number from a string (handles formats like "1.0.0", "v1.0.0", "steam-financial 1.0.0", etc.)
-fn extract_version(version_str: &str) -> String {
-    let cleaned = version_str.trim_start_matches('v').trim();
-    
-    // Try to find a version pattern (x.y.z where x, y, z are numbers)
-    // Look for the first sequence that matches the pattern
-    for word in cleaned.split_whitespace() {
-        let parts: Vec<&str> = word.split('.').collect();
-        if parts.len() >= 2 && parts.iter().all(|p| p.parse::<u32>().is_ok()) {
-            // Found a version-like pattern, return it
-            return parts.join(".");
-        }
-    }
-    
-    // Fallback: if the whole string looks like a version, use it
-    if cleaned.split('.').all(|p| p.parse::<u32>().is_ok()) {
-        return cleaned.to_string();
-    }
-    
-    // Last resort: return as-is
-    cleaned.to_string()
+// Parses a version out of strings like "1.0.0", "v1.10.0-beta.1", "steam-financial 1.0.0+build.5".
+// Two-part versions ("1.2") are read as "1.2.0".
+fn parse_version(version_str: &str) -> Option<Version> {
+    version_str
+        .split_whitespace()
+        .chain(std::iter::once(version_str.trim()))
+        .find_map(|word| {
+            let word = word.trim_start_matches(['v', 'V']);
+            Version::parse(word).ok().or_else(|| {
+                let (core, rest) = match word.find(['-', '+']) {
+                    Some(i) => word.split_at(i),
+                    None => (word, ""),
+                };
+                let parts: Vec<&str> = core.split('.').collect();
+                let numeric = parts.iter().all(|p| p.parse::<u64>().is_ok());
+                (parts.len() == 2 && numeric)
+                    .then(|| Version::parse(&format!("{}.0{}", core, rest)).ok())
+                    .flatten()
+            })
+        })
 }
 
-// Compare semantic versions (simple implementation for x.y.z format)
+// Compares by semver precedence; build metadata is ignored and unparsable versions sort first
 fn compare_versions(current: &str, latest: &str) -> std::cmp::Ordering {
-    let parse_version = |v: &str| -> Vec<u32> {
-        let clean = extract_version(v);
-        clean
-            .split('.')
-            .map(|s| s.parse::<u32>().unwrap_or(0))
-            .collect()
+    let precedence = |v: &str| {
+        parse_version(v).map(|mut version| {
+            version.build = BuildMetadata::EMPTY;
+            version
+        })
     };
+    precedence(current).cmp(&precedence(latest))
+}
 
-    let current_parts = parse_version(current);
-    let latest_parts = parse_version(latest);
-
-    for (c, l) in current_parts.iter().zip(latest_parts.iter()) {
-        match c.cmp(l) {
-            std::cmp::Ordering::Equal => continue,
-            other => return other,
-        }
-    }
-
-    current_parts.len().cmp(&latest_parts.len())
+fn include_prereleases() -> bool {
+    settings::load_settings().cli_include_prereleases
 }
 
 #[tauri::command]
-pub async fn check_cli_update() -> Result<VersionCheck, String> {
-    println!("[check_cli_update] Starting...");
-    
+pub async fn check_cli_update() -> AppResult<VersionCheck> {
     // Get current installed version via `steam-financial --version`
     // Run in spawn_blocking with timeout to avoid blocking async runtime
     let binary_path = get_cli_binary_path();
-    println!("[check_cli_update] Binary path: {:?}, exists: {}", binary_path, binary_path.exists());
+    debug!("CLI binary path: {:?}, exists: {}", binary_path, binary_path.exists());
     
     let mut current_version = None;
     if binary_path.exists() {
-        println!("[check_cli_update] Running --version check with 5s timeout...");
         let binary_path_clone = binary_path.clone();
         let version_result = timeout(
             Duration::from_secs(5),
@@ -229,28 +741,29 @@ pub async fn check_cli_update() -> Result<VersionCheck, String> {
         match &version_result {
             Ok(Ok(Some(output))) if output.status.success() => {
                 let version_str = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                println!("[check_cli_update] Got version: {}", version_str);
+                debug!("Installed CLI version: {}", version_str);
                 current_version = Some(version_str);
             }
             Ok(Ok(Some(output))) => {
-                println!("[check_cli_update] Version command failed with status: {:?}", output.status);
+                warn!("CLI --version failed with status: {:?}", output.status);
             }
             Ok(Ok(None)) => {
-                println!("[check_cli_update] Version command returned None");
+                warn!("CLI --version could not be run");
             }
             Ok(Err(e)) => {
-                println!("[check_cli_update] spawn_blocking error: {:?}", e);
+                error!("CLI --version task failed: {:?}", e);
             }
             Err(_) => {
-                println!("[check_cli_update] Version check timed out after 5s");
+                warn!("CLI --version timed out after 5s");
             }
         }
     }
 
     // Fetch latest version from GitHub releases
-    println!("[check_cli_update] Fetching latest version from GitHub...");
-    let latest_version = fetch_latest_cli_version().await?;
-    println!("[check_cli_update] Got latest version: {}", latest_version);
+    let include_prerelease = include_prereleases();
+    let latest = fetch_latest_cli_release(include_prerelease).await?;
+    let latest_version = latest.version;
+    debug!("Latest CLI version: {}", latest_version);
 
     let update_available = match &current_version {
         None => true, // Not installed; offer to install latest
@@ -260,97 +773,99 @@ pub async fn check_cli_update() -> Result<VersionCheck, String> {
     };
 
     Ok(VersionCheck {
+        current: current_version
+            .as_deref()
+            .and_then(parse_version)
+            .as_ref()
+            .map(VersionInfo::from),
+        latest: parse_version(&latest_version).as_ref().map(VersionInfo::from),
         current_version,
         latest_version,
         update_available,
+        channel: if include_prerelease { "prerelease" } else { "stable" }.to_string(),
+        stale: latest.stale,
     })
 }
 
 #[tauri::command]
-pub async fn download_cli(app: AppHandle, version: Option<String>) -> Result<String, String> {
-    let binary_path = get_cli_binary_path();
-    let version_to_download = match version {
-        Some(v) => v.clone(),
-        None => fetch_latest_cli_version().await?,
-    };
+pub async fn get_cli_include_prereleases() -> AppResult<bool> {
+    Ok(include_prereleases())
+}
 
-    // Emit progress: Starting download
-    let _ = app.emit("download-progress", format!("Downloading CLI tool v{}...", version_to_download));
+/// Opts in to (or out of) prerelease CLI versions for update checks and downloads.
+#[tauri::command]
+pub async fn set_cli_include_prereleases(enabled: bool) -> AppResult<()> {
+    settings::update_settings(|s| s.cli_include_prereleases = enabled)?;
+    Ok(())
+}
 
+/// Replaces the CLI directory with the contents of a zip archive. The archive is moved into
+/// place unless `keep_source` is set, in which case it is copied.
+async fn install_cli_archive(
+    app: &AppHandle,
+    archive_path: PathBuf,
+    keep_source: bool,
+) -> AppResult<()> {
     let cli_dir = get_cli_dir();
-    fs::create_dir_all(&cli_dir).map_err(|e| format!("Failed to create directory: {}", e))?;
-
-    let zip_name = get_binary_name();
-    let download_url = format!("{}/v{}/{}", CLI_RELEASES_BASE, version_to_download, zip_name);
-    let zip_path = cli_dir.join(&zip_name);
-
-    // Emit progress: Connecting to download server
-    let _ = app.emit("download-progress", format!("Connecting to {}...", download_url));
-
-    // Download the zip file (async)
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&download_url)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to download: {}", e))?;
-
-    // Emit progress: Downloading file
-    if let Some(content_length) = response.content_length() {
-        let _ = app.emit("download-progress", format!("Downloading {} bytes...", content_length));
-    } else {
-        let _ = app.emit("download-progress", "Downloading file...");
-    }
-
-    let bytes = response
-        .bytes()
-        .await
-        .map_err(|e| format!("Failed to read response: {}", e))?;
-
-    // Emit progress: Download complete, extracting
-    let _ = app.emit("download-progress", format!("Download complete ({} bytes). Extracting...", bytes.len()));
+    let zip_path = cli_dir.join(get_binary_name());
 
     // Write file and extract zip (blocking operations, run in spawn_blocking)
-    let zip_path_clone = zip_path.clone();
-    let cli_dir_clone = cli_dir.clone();
     let app_handle = app.clone();
     tokio::task::spawn_blocking(move || {
         // Delete entire cli directory (binary, zip, README, any cruft) then recreate fresh
         let _ = app_handle.emit("download-progress", "Preparing install directory...");
-        if cli_dir_clone.exists() {
-            fs::remove_dir_all(&cli_dir_clone)
-                .map_err(|e| format!("Failed to remove existing CLI directory: {}", e))?;
+        if cli_dir.exists() {
+            fs::remove_dir_all(&cli_dir)
+                .map_err(|e| AppError::Io(format!("Failed to remove existing CLI directory: {}", e)))?;
         }
-        fs::create_dir_all(&cli_dir_clone)
-            .map_err(|e| format!("Failed to create CLI directory: {}", e))?;
+        fs::create_dir_all(&cli_dir)
+            .map_err(|e| AppError::Io(format!("Failed to create CLI directory: {}", e)))?;
 
         // Emit progress: Saving file
         let _ = app_handle.emit("download-progress", "Saving downloaded file...");
-        
-        fs::write(&zip_path_clone, bytes).map_err(|e| format!("Failed to save zip: {}", e))?;
+
+        if keep_source || fs::rename(&archive_path, &zip_path).is_err() {
+            fs::copy(&archive_path, &zip_path).map_err(|e| AppError::Io(format!("Failed to save zip: {}", e)))?;
+            if !keep_source {
+                let _ = fs::remove_file(&archive_path);
+            }
+        }
 
         // Emit progress: Extracting
         let _ = app_handle.emit("download-progress", "Extracting archive...");
 
         // Extract zip file (blocking operation)
-        let file = std::fs::File::open(&zip_path_clone)
-            .map_err(|e| format!("Failed to open zip: {}", e))?;
+        let file = std::fs::File::open(&zip_path)
+            .map_err(|e| AppError::Io(format!("Failed to open zip: {}", e)))?;
         let mut archive =
-            zip::ZipArchive::new(file).map_err(|e| format!("Failed to read zip: {}", e))?;
+            zip::ZipArchive::new(file).map_err(|e| AppError::InvalidInput(format!("Failed to read zip: {}", e)))?;
 
         archive
-            .extract(&cli_dir_clone)
-            .map_err(|e| format!("Failed to extract zip: {}", e))?;
+            .extract(&cli_dir)
+            .map_err(|e| AppError::Io(format!("Failed to extract zip: {}", e)))?;
 
         // Clean up zip file
         let _ = app_handle.emit("download-progress", "Cleaning up temporary files...");
-        let _ = fs::remove_file(&zip_path_clone);
+        let _ = fs::remove_file(&zip_path);
 
-        Ok::<(), String>(())
+        Ok::<(), AppError>(())
     })
     .await
-    .map_err(|e| format!("Task join error: {}", e))?
-    .map_err(|e| e)?;
+    .map_err(|e| AppError::Internal(format!("Task join error: {}", e)))?
+}
+
+/// Makes the installed binary runnable and records its version and where it came from.
+async fn finish_cli_install(
+    app: &AppHandle,
+    binary_path: &Path,
+    source: &str,
+) -> AppResult<()> {
+    if !binary_path.exists() {
+        return Err(AppError::InvalidInput(format!(
+            "The CLI binary ({}) was not found after installing",
+            binary_path.file_name().unwrap_or_default().to_string_lossy()
+        )));
+    }
 
     // Make binary executable and remove macOS quarantine (Unix only)
     #[cfg(unix)]
@@ -365,24 +880,130 @@ pub async fn download_cli(app: AppHandle, version: Option<String>) -> Result<Str
                 .args(&["-d", "com.apple.quarantine", &binary_path_str])
                 .output();
             if let Err(e) = output {
-                println!("Warning: Failed to remove quarantine attribute: {:?}", e);
+                warn!("Failed to remove quarantine attribute: {:?}", e);
                 // Continue anyway - might not have quarantine attribute
             } else if let Ok(output) = output {
                 if !output.status.success() {
-                    println!("Warning: xattr command failed (might not have quarantine): {:?}", output.status);
+                    debug!("xattr command failed (might not have quarantine): {:?}", output.status);
                     // Continue anyway - file might not have quarantine attribute
                 }
             }
         }
         
         // Set executable permissions
-        let mut perms = fs::metadata(&binary_path)
-            .map_err(|e| format!("Failed to get file metadata: {}", e))?
+        let mut perms = fs::metadata(binary_path)
+            .map_err(|e| AppError::Io(format!("Failed to get file metadata: {}", e)))?
             .permissions();
         perms.set_mode(0o755);
-        fs::set_permissions(&binary_path, perms)
-            .map_err(|e| format!("Failed to set permissions: {}", e))?;
+        fs::set_permissions(binary_path, perms)
+            .map_err(|e| AppError::Io(format!("Failed to set permissions: {}", e)))?;
+    }
+
+
+    let _ = app.emit("download-progress", "Checking installed version...");
+    let version = read_cli_version(binary_path).await;
+    if let Some(ref version) = version {
+        let _ = app.emit("download-progress", format!("Installed {}", version));
+    }
+    app_state::set_value(CLI_VERSION_KEY, version.as_deref().unwrap_or(""))
+        .and_then(|_| app_state::set_value(CLI_SOURCE_KEY, source))
+        .map_err(|e| AppError::Database(format!("Failed to record installed version: {}", e)))
+}
+
+// Runs `steam-financial --version` with a timeout
+async fn read_cli_version(binary_path: &Path) -> Option<String> {
+    let binary_path = binary_path.to_path_buf();
+    let output = timeout(
+        Duration::from_secs(5),
+        tokio::task::spawn_blocking(move || Command::new(&binary_path).arg("--version").output()),
+    )
+    .await;
+    match output {
+        Ok(Ok(Ok(output))) if output.status.success() => {
+            Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        }
+        _ => None,
+    }
+}
+
+#[tauri::command]
+#[tracing::instrument(skip(app), fields(op_id = %new_operation_id("install")))]
+pub async fn download_cli(app: AppHandle, version: Option<String>) -> AppResult<String> {
+    let version_to_download = match version {
+        Some(v) => v.clone(),
+        None => fetch_latest_cli_version().await?,
+    };
+    let previous_version = installed_cli_version().await;
+    let result = install_cli_release(&app, &version_to_download).await;
+    match &result {
+        Ok(_) => info!("Installed CLI v{}", version_to_download),
+        Err(e) => error!("Installing CLI v{} failed: {}", version_to_download, e),
+    }
+    updater::record_update(
+        previous_version.as_deref(),
+        &version_to_download,
+        "manual",
+        &result,
+    );
+    result
+}
+
+/// Version of the installed CLI as reported by `--version`, if it is installed and runs.
+pub async fn installed_cli_version() -> Option<String> {
+    let binary_path = get_cli_binary_path();
+    if !binary_path.exists() {
+        return None;
+    }
+    read_cli_version(&binary_path).await
+}
+
+/// Downloads, verifies and installs a CLI release. Returns the installed binary's path.
+pub async fn install_cli_release(app: &AppHandle, version_to_download: &str) -> AppResult<String> {
+    let binary_path = get_cli_binary_path();
+
+    // Emit progress: Starting download
+    let _ = app.emit("download-progress", format!("Downloading CLI tool v{}...", version_to_download));
+
+    let cli_dir = get_cli_dir();
+    fs::create_dir_all(&cli_dir).map_err(|e| AppError::Io(format!("Failed to create directory: {}", e)))?;
+
+    let zip_name = get_binary_name();
+    let download_url = format!("{}/v{}/{}", CLI_RELEASES_BASE, version_to_download, zip_name);
+
+    // Emit progress: Connecting to download server
+    let _ = app.emit("download-progress", format!("Connecting to {}...", download_url));
+
+    // Stream the zip to a partial file outside the install directory, so an interrupted
+    // download can be resumed and a failed one never touches the existing install
+    let download_dir = get_download_dir();
+    fs::create_dir_all(&download_dir)
+        .map_err(|e| AppError::Io(format!("Failed to create download directory: {}", e)))?;
+    let part_path = download_dir.join(format!("{}.v{}.part", zip_name, version_to_download));
+
+    let client = reqwest::Client::new();
+    let size = download_to_file(app, &client, &download_url, &part_path).await?;
+
+    // Verify integrity before touching the existing install
+    let _ = app.emit("download-progress", "Verifying checksum...");
+    let expected_sha256 = fetch_expected_sha256(&client, version_to_download, &zip_name).await?;
+    let actual_sha256 = sha256_file(&part_path)?;
+    if actual_sha256 != expected_sha256 {
+        let _ = fs::remove_file(&part_path);
+        return Err(AppError::VerificationFailed(format!(
+            "Checksum mismatch for {}: expected {}, got {}. The download may be corrupted or tampered with; nothing was installed.",
+            zip_name, expected_sha256, actual_sha256
+        )));
     }
+    if let Err(e) = verify_signature(&client, &download_url, &part_path).await {
+        let _ = fs::remove_file(&part_path);
+        return Err(e);
+    }
+
+    // Emit progress: Download complete, extracting
+    let _ = app.emit("download-progress", format!("Download complete ({} bytes). Extracting...", size));
+
+    install_cli_archive(app, part_path, false).await?;
+    finish_cli_install(app, &binary_path, "github").await?;
 
     // Emit completion event
     let _ = app.emit("download-complete", ());
@@ -390,90 +1011,428 @@ pub async fn download_cli(app: AppHandle, version: Option<String>) -> Result<Str
     Ok(binary_path.to_string_lossy().to_string())
 }
 
+/// Installs the CLI from a locally downloaded release zip or a bare binary, for machines that
+/// cannot reach GitHub.
 #[tauri::command]
-pub async fn init_cli(api_key: String) -> Result<(), String> {
+#[tracing::instrument(skip(app), fields(op_id = %new_operation_id("install")))]
+pub async fn install_cli_from_file(app: AppHandle, path: String) -> AppResult<String> {
+    let source_path = PathBuf::from(&path);
+    if !source_path.is_file() {
+        return Err(AppError::NotFound(format!("File not found: {}", path)));
+    }
+    let binary_path = get_cli_binary_path();
+    let _ = app.emit("download-progress", format!("Installing CLI tool from {}...", path));
+
+    // Release archives are zips; anything else is taken to be the binary itself
+    let mut magic = [0u8; 4];
+    let is_zip = fs::File::open(&source_path)
+        .and_then(|mut f| io::Read::read_exact(&mut f, &mut magic))
+        .map(|_| magic == *b"PK\x03\x04")
+        .unwrap_or(false);
+
+    if is_zip {
+        install_cli_archive(&app, source_path, true).await?;
+    } else {
+        let cli_dir = get_cli_dir();
+        let target = binary_path.clone();
+        let app_handle = app.clone();
+        tokio::task::spawn_blocking(move || {
+            let _ = app_handle.emit("download-progress", "Preparing install directory...");
+            if cli_dir.exists() {
+                fs::remove_dir_all(&cli_dir)
+                    .map_err(|e| AppError::Io(format!("Failed to remove existing CLI directory: {}", e)))?;
+            }
+            fs::create_dir_all(&cli_dir)
+                .map_err(|e| AppError::Io(format!("Failed to create CLI directory: {}", e)))?;
+            let _ = app_handle.emit("download-progress", "Copying binary...");
+            fs::copy(&source_path, &target)
+                .map_err(|e| AppError::Io(format!("Failed to copy binary: {}", e)))?;
+            Ok::<(), AppError>(())
+        })
+        .await
+        .map_err(|e| AppError::Internal(format!("Task join error: {}", e)))??;
+    }
+
+    finish_cli_install(&app, &binary_path, "file").await?;
+    info!("Installed CLI from {}", path);
+    let _ = app.emit("download-complete", ());
+
+    Ok(binary_path.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+#[allow(non_snake_case)]
+// The API key must never end up in the logs
+#[tracing::instrument(skip_all, fields(op_id = %new_operation_id("init"), source = ?sourceId))]
+pub async fn init_cli(api_key: String, sourceId: Option<String>) -> AppResult<()> {
     let binary_path = get_cli_binary_path();
 
     if !binary_path.exists() {
-        return Err("CLI tool not installed. Please download it first.".to_string());
+        return Err(AppError::CliNotInstalled);
     }
 
-    let db_path = database::get_database_path();
-    let db_path_str = db_path.to_string_lossy().to_string();
+    // Each data source has its own database, and the CLI keeps the key inside it
+    let source = sources::resolve_writable_source(sourceId.as_deref())?;
+    let db_path_str = source.db_path.clone();
 
     // v2.0.0+ format: API key is a positional argument after "init"
     // Command: steam-financial --db <path> --color never init <api_key>
-    let output = Command::new(&binary_path)
-        .args(&["--db", &db_path_str, "--color", "never", "init", &api_key])
+    let result = Command::new(&binary_path)
+        .args(["--db", &db_path_str, "--color", "never", "init", &api_key])
         .output()
-        .map_err(|e| format!("Failed to execute CLI: {}", e))?;
-
-    if !output.status.success() {
-        let error = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("CLI init failed: {}", error));
+        .map_err(|e| AppError::Io(format!("Failed to execute CLI: {}", e)))
+        .and_then(|output| {
+            if output.status.success() {
+                Ok(())
+            } else {
+                let error = String::from_utf8_lossy(&output.stderr);
+                Err(cli_failure(format!("CLI init failed: {}", error)))
+            }
+        })
+        .context(format!("Initializing data source '{}'", source.name));
+    if let Err(e) = &result {
+        error!("{}", e);
+        return result;
+    }
+    info!("Initialized data source {}", source.id);
+
+    // Audit trail: remember which key the CLI was initialized with. A key the CLI rejected is
+    // not registered, so the registry only lists keys a data source actually uses.
+    match keys::register_key(&api_key) {
+        Ok(key_id) => {
+            if let Err(e) = keys::record_key_usage(&key_id, "init", &result) {
+                warn!("Failed to record key usage for init: {}", e);
+            }
+            if let Err(e) = sources::set_source_key(&source.id, &key_id) {
+                warn!("Failed to record key for data source {}: {}", source.id, e);
+            }
+            if let Err(e) = keys::store_key_secret(&key_id, &api_key) {
+                warn!("Failed to save API key to the credential store: {}", e);
+            }
+        }
+        Err(e) => warn!("Failed to register API key: {}", e),
     }
 
-    Ok(())
+    result
 }
 
 #[tauri::command]
-pub async fn fetch_data(app: AppHandle, force: Option<bool>) -> Result<(), String> {
-    let binary_path = get_cli_binary_path();
+#[allow(non_snake_case)]
+#[tracing::instrument(skip(app), fields(op_id = %new_operation_id("fetch")))]
+pub async fn fetch_data(
+    app: AppHandle,
+    force: Option<bool>,
+    inactivityTimeoutSecs: Option<u64>,
+    sourceId: Option<String>,
+) -> AppResult<()> {
+    if sync::is_sync_paused() {
+        return Err(AppError::Busy(
+            "Sync is paused. Resume syncing to fetch new data.".to_string(),
+        ));
+    }
+    let source = sources::resolve_writable_source(sourceId.as_deref())?;
+
+    let sync_state = app.state::<SyncState>();
+    let _ = app.emit("sync-queue-progress", sync_state.start()?);
+    // Checked after marking the fetch as running; the updater checks in the opposite order
+    if updater::is_update_in_progress() {
+        let _ = app.emit("sync-queue-progress", sync_state.finish());
+        return Err(AppError::Busy(
+            "The CLI is being updated. Try again in a moment.".to_string(),
+        ));
+    }
 
-    if !binary_path.exists() {
-        return Err("CLI tool not installed. Please download it first.".to_string());
+    let settings = settings::load_settings();
+    // Queries keep reading the data as it was until the fetch is over
+    if settings.snapshot_reads_during_sync {
+        let db_path = PathBuf::from(&source.db_path);
+        match tokio::task::spawn_blocking(move || read_snapshot::take(&db_path)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!("Failed to take a read snapshot: {}", e),
+            Err(e) => warn!("Failed to take a read snapshot: {}", e),
+        }
+    }
+    let totals_before = webhooks::snapshot_before_sync(&source.db_path);
+    // The triggers must match the schema before the CLI writes
+    let db_path = PathBuf::from(&source.db_path);
+    match tokio::task::spawn_blocking(move || change_log::apply_setting(&db_path)).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => warn!("Failed to set up the sales change log: {}", e),
+        Err(e) => warn!("Failed to set up the sales change log: {}", e),
+    }
+    let inactivity_timeout = Duration::from_secs(
+        inactivityTimeoutSecs.unwrap_or(settings.fetch_inactivity_timeout_secs),
+    );
+    let started = Instant::now();
+    let result = run_fetch(
+        &app,
+        &source.db_path,
+        force.unwrap_or(false),
+        settings.fetch_concurrency,
+        inactivity_timeout,
+    )
+    .await
+    .context(format!("Fetching data source '{}'", source.name));
+    match &result {
+        Ok(()) => info!("Fetch for data source {} finished", source.id),
+        Err(e) => error!("Fetch for data source {} failed: {}", source.id, e),
+    }
+
+    let status = sync_state.finish();
+    perf::record(
+        "fetch_data",
+        started.elapsed(),
+        Some(status.records_processed),
+        result.is_ok(),
+    );
+    let _ = app.emit("sync-queue-progress", status);
+    match &source.key_id {
+        Some(key_id) => {
+            if let Err(e) = keys::record_key_usage(key_id, "fetch", &result) {
+                warn!("Failed to record key usage for fetch: {}", e);
+            }
+        }
+        None => keys::record_current_key_usage("fetch", &result),
+    }
+
+    // Malformed rows would otherwise count as zero or break the views
+    if result.is_ok() {
+        let checked = source.clone();
+        match tokio::task::spawn_blocking(move || quarantine::quarantine_invalid_rows(&checked))
+            .await
+        {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => warn!("Failed to check fetched rows: {}", e),
+            Err(e) => warn!("Failed to check fetched rows: {}", e),
+        }
+    }
+
+    if result.is_ok() {
+        let db_path = PathBuf::from(&source.db_path);
+        match tokio::task::spawn_blocking(move || discount_calendar::rebuild_after_sync(&db_path))
+            .await
+        {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!("Failed to rebuild the discount calendar: {}", e),
+            Err(e) => warn!("Failed to rebuild the discount calendar: {}", e),
+        }
     }
 
-    let db_path = database::get_database_path();
-    let db_path_str = db_path.to_string_lossy().to_string();
+    if result.is_ok() {
+        let ledger_source = source.clone();
+        match tokio::task::spawn_blocking(move || ledger::record_batch(&ledger_source, "fetch"))
+            .await
+        {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => warn!("Failed to record the fetch in the ledger: {}", e),
+            Err(e) => warn!("Failed to record the fetch in the ledger: {}", e),
+        }
+    }
 
+    // Queries read the fetched data from here on, the checks below included; results cached
+    // meanwhile may be of the snapshot. Even a failed fetch may have written some days.
+    read_snapshot::release();
+    app.state::<QueryCache>().clear();
+
+    // Alerts and milestones describe the active source's data
+    if result.is_ok() && source.active {
+        anomalies::detect_after_sync(&app).await;
+        goals::check_milestones_after_sync(&app).await;
+        app_metadata::enrich_in_background();
+        reviews::update_in_background();
+    }
+    if result.is_ok() {
+        webhooks::notify_after_sync(&source, totals_before).await;
+    }
+    // Last, since the steps above may still write to the database
+    if result.is_ok() && source.active {
+        precompute::after_sync(&app);
+    }
+    result
+}
+
+// Words in the CLI's error output that mean Steam rejected the API key
+const INVALID_KEY_MARKERS: &[&str] = &[
+    "unauthorized",
+    "forbidden",
+    "invalid key",
+    "invalid api key",
+    "access denied",
+];
+
+// A failed CLI run; rejected API keys get their own kind so the UI can ask for a new key
+fn cli_failure(message: String) -> AppError {
+    let lower = message.to_lowercase();
+    if INVALID_KEY_MARKERS.iter().any(|marker| lower.contains(marker)) {
+        AppError::InvalidApiKey(message)
+    } else {
+        AppError::CliFailed(message)
+    }
+}
+
+// How a CLI fetch ended
+enum FetchOutcome {
+    Exited(std::process::ExitStatus),
+    Cancelled,
+    TimedOut,
+}
+
+/// Stops the CLI: SIGTERM to its process group first, SIGKILL if it does not exit in time.
+async fn stop_fetch_process(child: &mut tokio::process::Child) {
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        let _ = sync::signal_process_group(pid, "TERM");
+        if timeout(FETCH_TERMINATE_GRACE, child.wait()).await.is_ok() {
+            return;
+        }
+        let _ = sync::signal_process_group(pid, "KILL");
+    }
+    let _ = child.kill().await;
+}
+
+// CLI binary (path and modification time) -> whether its fetch takes --concurrency
+static CONCURRENCY_SUPPORT: Mutex<Option<(PathBuf, Option<SystemTime>, bool)>> = Mutex::new(None);
+
+// Output of the CLI's `fetch --help`; None if it fails or doesn't finish in time
+fn fetch_help(binary_path: &Path) -> Option<String> {
+    let mut child = Command::new(binary_path)
+        .args(["fetch", "--help"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let deadline = Instant::now() + FETCH_HELP_TIMEOUT;
+    while child.try_wait().ok()?.is_none() {
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return None;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    let mut help = String::new();
+    child.stdout.take()?.read_to_string(&mut help).ok()?;
+    Some(help)
+}
+
+/// `concurrency` if the installed CLI's fetch takes `--concurrency`, else None. CLI versions
+/// without the option refuse to fetch with it, so it is looked up in `fetch --help` once per
+/// binary. Runs the CLI, so call it from a blocking context.
+pub(crate) fn supported_concurrency(concurrency: Option<u32>) -> Option<u32> {
+    let concurrency = concurrency?;
+    let binary_path = get_cli_binary_path();
+    let modified = fs::metadata(&binary_path).and_then(|m| m.modified()).ok();
+    let mut cached = CONCURRENCY_SUPPORT.lock().unwrap();
+    let supported = match cached.as_ref() {
+        Some((path, at, supported)) if *path == binary_path && *at == modified => *supported,
+        _ => {
+            let supported =
+                fetch_help(&binary_path).is_some_and(|help| help.contains("--concurrency"));
+            *cached = Some((binary_path, modified, supported));
+            supported
+        }
+    };
+    if !supported {
+        warn!("The installed CLI has no --concurrency option; ignoring the fetch concurrency");
+    }
+    supported.then_some(concurrency)
+}
+
+/// Arguments of a CLI fetch into `db_path`.
+pub(crate) fn fetch_args(db_path: &str, force: bool, concurrency: Option<u32>) -> Vec<String> {
     // v2.0.0+ format: --force flag (also supports -f shorthand)
-    // Command: steam-financial --db <path> --color never fetch [--force]
-    let mut args = vec!["--db", &db_path_str, "--color", "never", "fetch"];
-    if force.unwrap_or(false) {
-        args.push("--force");
+    // Command: steam-financial --db <path> --color never fetch [--force] [--concurrency <n>]
+    let mut args: Vec<String> = ["--db", db_path, "--color", "never", "fetch"]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+    if force {
+        args.push("--force".to_string());
     }
+    if let Some(concurrency) = concurrency {
+        args.push("--concurrency".to_string());
+        args.push(concurrency.to_string());
+    }
+    args
+}
 
+async fn run_fetch(
+    app: &AppHandle,
+    db_path: &str,
+    force: bool,
+    concurrency: Option<u32>,
+    inactivity_timeout: Duration,
+) -> AppResult<()> {
+    let binary_path = get_cli_binary_path();
+
+    if !binary_path.exists() {
+        return Err(AppError::CliNotInstalled);
+    }
+
+    let concurrency = tokio::task::spawn_blocking(move || supported_concurrency(concurrency))
+        .await
+        .unwrap_or(None);
+    let args = fetch_args(db_path, force, concurrency);
+
+    info!("Starting CLI fetch (force: {})", force);
     // Spawn process with piped stdout/stderr to capture progress
-    let mut child = TokioCommand::new(&binary_path)
+    let mut command = TokioCommand::new(&binary_path);
+    command
         .args(&args)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    // Own process group, so cancelling reaches everything the CLI spawns
+    #[cfg(unix)]
+    command.process_group(0);
+    let mut child = command
         .spawn()
-        .map_err(|e| format!("Failed to execute CLI: {}", e))?;
+        .map_err(|e| AppError::Io(format!("Failed to execute CLI: {}", e)))?;
+    let mut cancel_rx = app.state::<SyncState>().set_process(child.id());
+    // Signalled by the output readers on every line; resets the inactivity timer
+    let activity = Arc::new(Notify::new());
 
     // Read stdout line by line and emit progress events
     let stdout = child.stdout.take().ok_or("Failed to capture stdout")?;
     let stderr = child.stderr.take().ok_or("Failed to capture stderr")?;
 
     let app_handle = app.clone();
+    let stdout_activity = activity.clone();
     let stdout_handle: tokio::task::JoinHandle<()> = tokio::spawn(async move {
         let mut reader = BufReader::new(stdout).lines();
         loop {
             match reader.next_line().await {
                 Ok(Some(line)) => {
+                    stdout_activity.notify_one();
                     let trimmed = line.trim();
                     if !trimmed.is_empty() {
+                        debug!(target: "steam_sales_analyzer::cli::output", "{}", trimmed);
                         // Emit progress event with the line
                         let _ = app_handle.emit("fetch-progress", trimmed);
+                        // And the structured progress derived from it
+                        let status = app_handle.state::<SyncState>().observe_line(trimmed);
+                        let _ = app_handle.emit("sync-queue-progress", status);
                     }
                 }
                 Ok(None) => break,
                 Err(_) => break,
             }
         }
-    });
+    }.in_current_span());
 
     // Read stderr for errors
     let app_handle_err = app.clone();
+    let stderr_activity = activity.clone();
     let stderr_handle: tokio::task::JoinHandle<Vec<String>> = tokio::spawn(async move {
         let mut reader = BufReader::new(stderr).lines();
         let mut error_lines = Vec::new();
         loop {
             match reader.next_line().await {
                 Ok(Some(line)) => {
+                    stderr_activity.notify_one();
                     let trimmed = line.trim();
                     if !trimmed.is_empty() {
+                        warn!(target: "steam_sales_analyzer::cli::output", "{}", trimmed);
                         error_lines.push(trimmed.to_string());
                         // Also emit as progress so user can see errors in real-time
                         let _ = app_handle_err.emit("fetch-progress", trimmed);
@@ -484,19 +1443,41 @@ pub async fn fetch_data(app: AppHandle, force: Option<bool>) -> Result<(), Strin
             }
         }
         error_lines
-    });
-
-    // Wait for process to complete
-    let status = child
-        .wait()
-        .await
-        .map_err(|e| format!("Failed to wait for CLI: {}", e))?;
+    }.in_current_span());
+
+    // Wait for the process to complete, a cancel request, or too long without output
+    let outcome = loop {
+        tokio::select! {
+            status = child.wait() => {
+                break FetchOutcome::Exited(
+                    status.map_err(|e| AppError::Io(format!("Failed to wait for CLI: {}", e)))?,
+                );
+            }
+            Ok(()) = &mut cancel_rx => break FetchOutcome::Cancelled,
+            _ = activity.notified() => continue,
+            _ = tokio::time::sleep(inactivity_timeout) => break FetchOutcome::TimedOut,
+        }
+    };
+    if !matches!(outcome, FetchOutcome::Exited(_)) {
+        stop_fetch_process(&mut child).await;
+    }
 
     // Wait for readers to finish
     let _ = stdout_handle.await;
     let error_lines = stderr_handle
         .await
-        .map_err(|e| format!("Failed to read stderr: {}", e))?;
+        .map_err(|e| AppError::Internal(format!("Failed to read stderr: {}", e)))?;
+
+    let status = match outcome {
+        FetchOutcome::Exited(status) => status,
+        FetchOutcome::Cancelled => return Err(AppError::Cancelled("Fetch cancelled".to_string())),
+        FetchOutcome::TimedOut => {
+            return Err(AppError::Timeout(format!(
+                "CLI fetch timed out: no output for {} seconds",
+                inactivity_timeout.as_secs()
+            )))
+        }
+    };
 
     if !status.success() {
         let error = if error_lines.is_empty() {
@@ -504,7 +1485,7 @@ pub async fn fetch_data(app: AppHandle, force: Option<bool>) -> Result<(), Strin
         } else {
             error_lines.join("\n")
         };
-        return Err(format!("CLI fetch failed: {}", error));
+        return Err(cli_failure(format!("CLI fetch failed: {}", error)));
     }
 
     // Emit completion event
@@ -512,3 +1493,69 @@ pub async fn fetch_data(app: AppHandle, force: Option<bool>) -> Result<(), Strin
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cmp::Ordering;
+
+    #[test]
+    fn parse_version_reads_cli_output_and_tags() {
+        let parsed = |s: &str| parse_version(s).map(|v| v.to_string());
+        assert_eq!(parsed("1.0.0").as_deref(), Some("1.0.0"));
+        assert_eq!(parsed("v1.10.0-beta.1").as_deref(), Some("1.10.0-beta.1"));
+        assert_eq!(
+            parsed("steam-financial 1.0.0+build.5").as_deref(),
+            Some("1.0.0+build.5")
+        );
+        assert_eq!(parsed("V2.1").as_deref(), Some("2.1.0"));
+        assert_eq!(parsed("2.1-rc.1").as_deref(), Some("2.1.0-rc.1"));
+    }
+
+    #[test]
+    fn parse_version_rejects_empty_and_invalid_input() {
+        for invalid in ["", "   ", "steam-financial", "1", "1.2.3.4", "1.x.0", "v"] {
+            assert_eq!(parse_version(invalid), None, "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn compare_versions_uses_semver_precedence() {
+        assert_eq!(compare_versions("1.9.0", "1.10.0"), Ordering::Less);
+        assert_eq!(compare_versions("1.0.0-beta.2", "1.0.0"), Ordering::Less);
+        assert_eq!(
+            compare_versions("1.0.0-beta.2", "1.0.0-beta.10"),
+            Ordering::Less
+        );
+        assert_eq!(compare_versions("v2.0.0", "1.99.99"), Ordering::Greater);
+        // Build metadata doesn't make a version newer
+        assert_eq!(
+            compare_versions("1.0.0+build.1", "1.0.0+build.2"),
+            Ordering::Equal
+        );
+        // An unreadable installed version is older than any release
+        assert_eq!(compare_versions("unknown", "0.0.1"), Ordering::Less);
+        assert_eq!(compare_versions("", ""), Ordering::Equal);
+    }
+
+    #[test]
+    fn fetch_args_only_add_the_options_given() {
+        assert_eq!(
+            fetch_args("/data/sales.db", false, None),
+            ["--db", "/data/sales.db", "--color", "never", "fetch"]
+        );
+        assert_eq!(
+            fetch_args("/data/sales.db", true, Some(4)),
+            [
+                "--db",
+                "/data/sales.db",
+                "--color",
+                "never",
+                "fetch",
+                "--force",
+                "--concurrency",
+                "4"
+            ]
+        );
+    }
+}
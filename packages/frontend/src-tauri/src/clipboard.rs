@@ -0,0 +1,116 @@
+// Copying a report to the clipboard
+//
+// Pasting this week's numbers into Slack or Notion shouldn't need a file export.
+// `copy_query_to_clipboard` runs one of the summary reports of export.rs (daily, apps or
+// countries) with the dashboard's filters and puts it on the system clipboard, as TSV to paste
+// into spreadsheet cells or as a Markdown table for chat and note apps. In presentation mode the
+// copy is masked like the dashboard it is taken from (see presentation.rs), so pasting it into a
+// shared channel gives away no more than the screen did.
+
+use crate::export::{write_summary, ExportFormat, Report, SummaryRows, SUMMARY_LIMIT};
+use crate::presentation::{self, Monetary};
+use crate::queries::QueryFilters;
+use crate::types::{AppError, AppResult};
+use tauri::AppHandle;
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+// The report as text and its number of rows, with amounts of money multiplied by
+// `money_factor` in presentation mode
+fn report_text(
+    mut rows: SummaryRows,
+    format: ExportFormat,
+    money_factor: Option<f64>,
+) -> AppResult<(String, u64)> {
+    if let Some(factor) = money_factor {
+        rows.scale_money(factor);
+    }
+    let mut buffer = Vec::new();
+    let count = write_summary(&mut buffer, format, &rows)?;
+    let text = String::from_utf8(buffer).map_err(|e| AppError::Internal(e.to_string()))?;
+    Ok((text, count))
+}
+
+// ==================== Tauri Commands ====================
+
+/// Copies the `kind` summary ("daily", "apps" or "countries") with `filters` to the clipboard as
+/// "tsv" or "markdown" and returns the number of rows copied. The filters' limit applies, e.g.
+/// to copy the top ten apps; without one every row is copied. Amounts are masked in presentation
+/// mode.
+#[tauri::command]
+pub async fn copy_query_to_clipboard(
+    app: AppHandle,
+    kind: String,
+    filters: Option<QueryFilters>,
+    format: String,
+) -> AppResult<u64> {
+    let report = Report::parse(&kind)?;
+    if report == Report::Sales {
+        return Err(AppError::InvalidInput(
+            "Sales records can't be copied to the clipboard; export them to a file instead"
+                .to_string(),
+        ));
+    }
+    let format = match ExportFormat::parse(&format)? {
+        format @ (ExportFormat::Tsv | ExportFormat::Markdown) => format,
+        _ => {
+            return Err(AppError::InvalidInput(
+                "Copy as tsv or markdown; csv and json are for exports to a file".to_string(),
+            ))
+        }
+    };
+    let filters = filters.unwrap_or_default().resolve_date_range()?;
+    let filters = QueryFilters {
+        limit: filters.limit.or(Some(SUMMARY_LIMIT)),
+        ..filters
+    };
+
+    let (text, rows) = tokio::task::spawn_blocking(move || {
+        let rows = SummaryRows::query(report, filters)?;
+        report_text(rows, format, presentation::money_factor()?)
+    })
+    .await??;
+    app.clipboard()
+        .write_text(text)
+        .map_err(|e| AppError::Internal(format!("Failed to copy to the clipboard: {}", e)))?;
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::queries::DailySummary;
+
+    fn days() -> SummaryRows {
+        SummaryRows::Daily(vec![DailySummary {
+            date: "2024-03-01".to_string(),
+            total_revenue: 1234.5,
+            total_units: 42,
+            record_count: 7,
+            annotations: Vec::new(),
+            partial: false,
+            metrics: Default::default(),
+            metric_money_powers: Default::default(),
+        }])
+    }
+
+    #[test]
+    fn copies_real_amounts_outside_presentation_mode() {
+        let (text, rows) = report_text(days(), ExportFormat::Tsv, None).unwrap();
+        assert_eq!(rows, 1);
+        assert_eq!(
+            text,
+            "date\ttotal_revenue\ttotal_units\trecord_count\n2024-03-01\t1234.50\t42\t7\n"
+        );
+    }
+
+    #[test]
+    fn a_masked_report_copies_masked_values() {
+        let (text, _) = report_text(days(), ExportFormat::Markdown, Some(0.1)).unwrap();
+        assert!(
+            text.contains("| 2024-03-01 | 123.45 | 42 | 7 |"),
+            "{}",
+            text
+        );
+        assert!(!text.contains("1234.50"), "{}", text);
+    }
+}
@@ -0,0 +1,258 @@
+// Revenue cohorts since launch
+//
+// Splits an app's revenue by week since launch (its first day with revenue) and by a cohort of
+// the purchase: the buyer's country or region, or the discount tier the purchase was made at.
+// The result is a matrix of cohorts by weeks, e.g. for a heatmap of how the long tail of revenue
+// is composed. Countries and regions beyond the largest MAX_COHORTS are merged into "other".
+
+use crate::countries;
+use crate::presentation;
+use crate::queries::{self, get_app_id_column, get_connection};
+use crate::schema::first_existing_column;
+use crate::types::{AppError, AppResult, Date};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const DEFAULT_MAX_WEEKS: u32 = 104;
+const MAX_COHORTS: usize = 15;
+const OTHER_COHORT: &str = "other";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CohortBy {
+    Country,
+    Region,
+    /// none, 1-24%, 25-49%, 50-74% and 75%+
+    DiscountTier,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Cohort {
+    /// Country code, region name or discount tier
+    pub key: String,
+    pub label: String,
+    pub total_revenue: f64,
+    /// Gross revenue per week since launch; index 0 is the launch week
+    pub revenue_by_week: Vec<f64>,
+    pub units_by_week: Vec<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CohortMatrix {
+    pub app_id: i64,
+    pub cohort_by: CohortBy,
+    pub launch_date: Option<Date>,
+    /// Number of week columns
+    pub weeks: u32,
+    /// Largest first; discount tiers in tier order
+    pub cohorts: Vec<Cohort>,
+}
+
+// Discount tiers in display order, with their labels
+pub(crate) const DISCOUNT_TIERS: &[(&str, &str)] = &[
+    ("none", "No discount"),
+    ("1-24", "1-24% off"),
+    ("25-49", "25-49% off"),
+    ("50-74", "50-74% off"),
+    ("75+", "75%+ off"),
+];
+
+pub(crate) fn discount_tier_expr(column: &str) -> String {
+    format!(
+        "CASE
+            WHEN COALESCE({c}, 0) <= 0 THEN 'none'
+            WHEN {c} < 25 THEN '1-24'
+            WHEN {c} < 50 THEN '25-49'
+            WHEN {c} < 75 THEN '50-74'
+            ELSE '75+'
+         END",
+        c = column
+    )
+}
+
+pub fn get_cohorts(
+    app_id: i64,
+    cohort_by: CohortBy,
+    max_weeks: Option<u32>,
+) -> AppResult<CohortMatrix> {
+    let conn = get_connection()?;
+    let app_id_col = get_app_id_column(&conn);
+    let max_weeks = max_weeks.unwrap_or(DEFAULT_MAX_WEEKS).max(1);
+
+    let launch_date: Option<Date> = conn
+        .query_row(
+            &format!(
+                "SELECT MIN(date) FROM sales_data
+                 WHERE {} = ?1 AND CAST(gross_sales_usd AS REAL) > 0",
+                app_id_col
+            ),
+            [app_id],
+            |r| r.get(0),
+        )
+        .optional()?
+        .flatten();
+    let Some(launch) = launch_date else {
+        return Ok(CohortMatrix {
+            app_id,
+            cohort_by,
+            launch_date: None,
+            weeks: 0,
+            cohorts: Vec::new(),
+        });
+    };
+    // The day Steam is still reporting would show as a drop in the current week
+    let last_day = queries::last_complete_day(None)
+        .map(|d| d.to_string())
+        .unwrap_or_else(|| "9999-12-31".to_string());
+
+    let (cohort_expr, join) = match cohort_by {
+        CohortBy::Country => ("COALESCE(NULLIF(s.country_code, ''), '??')".to_string(), ""),
+        CohortBy::Region => {
+            countries::ensure_lookup_table(&conn)?;
+            (
+                "COALESCE(c.region, 'Unknown')".to_string(),
+                "LEFT JOIN lookup_countries c ON c.country_code = s.country_code",
+            )
+        }
+        CohortBy::DiscountTier => {
+            let column = first_existing_column(
+                &conn,
+                "sales_data",
+                &["total_discount_percentage", "discount_percentage"],
+            )
+            .ok_or_else(|| {
+                AppError::InvalidInput(
+                    "The database has no discount column to group by".to_string(),
+                )
+            })?;
+            (discount_tier_expr(&format!("s.{}", column)), "")
+        }
+    };
+
+    let sql = format!(
+        "SELECT cohort, week, SUM(revenue), SUM(units) FROM (
+            SELECT
+                {} AS cohort,
+                CAST((julianday(s.date) - julianday(?2)) / 7 AS INTEGER) AS week,
+                CAST(s.gross_sales_usd AS REAL) AS revenue,
+                s.net_units_sold AS units
+            FROM sales_data s {}
+            WHERE s.{} = ?1 AND s.date >= ?2 AND s.date <= ?3
+         )
+         WHERE week < ?4
+         GROUP BY cohort, week",
+        cohort_expr, join, app_id_col
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params![app_id, launch, last_day, max_weeks], |r| {
+        Ok((
+            r.get::<_, String>(0)?,
+            r.get::<_, i64>(1)?,
+            r.get::<_, Option<f64>>(2)?.unwrap_or(0.0),
+            r.get::<_, Option<i64>>(3)?.unwrap_or(0),
+        ))
+    })?;
+
+    let mut by_key: HashMap<String, (Vec<f64>, Vec<i64>)> = HashMap::new();
+    let mut weeks = 0;
+    for row in rows {
+        let (key, week, revenue, units) = row?;
+        let week = week.max(0) as usize;
+        weeks = weeks.max(week + 1);
+        let (revenue_by_week, units_by_week) = by_key.entry(key).or_default();
+        if revenue_by_week.len() <= week {
+            revenue_by_week.resize(week + 1, 0.0);
+            units_by_week.resize(week + 1, 0);
+        }
+        revenue_by_week[week] += revenue;
+        units_by_week[week] += units;
+    }
+
+    let mut cohorts: Vec<Cohort> = by_key
+        .into_iter()
+        .map(|(key, (mut revenue_by_week, mut units_by_week))| {
+            revenue_by_week.resize(weeks, 0.0);
+            units_by_week.resize(weeks, 0);
+            Cohort {
+                label: key.clone(),
+                key,
+                total_revenue: revenue_by_week.iter().sum(),
+                revenue_by_week,
+                units_by_week,
+            }
+        })
+        .collect();
+
+    if cohort_by == CohortBy::DiscountTier {
+        cohorts.sort_by_key(|c| DISCOUNT_TIERS.iter().position(|(key, _)| *key == c.key));
+        for cohort in &mut cohorts {
+            if let Some((_, label)) = DISCOUNT_TIERS.iter().find(|(key, _)| *key == cohort.key) {
+                cohort.label = label.to_string();
+            }
+        }
+    } else {
+        cohorts.sort_by(|a, b| b.total_revenue.total_cmp(&a.total_revenue));
+        if cohorts.len() > MAX_COHORTS {
+            let rest = cohorts.split_off(MAX_COHORTS - 1);
+            let mut other = Cohort {
+                key: OTHER_COHORT.to_string(),
+                label: "Other".to_string(),
+                total_revenue: 0.0,
+                revenue_by_week: vec![0.0; weeks],
+                units_by_week: vec![0; weeks],
+            };
+            for cohort in rest {
+                other.total_revenue += cohort.total_revenue;
+                for week in 0..weeks {
+                    other.revenue_by_week[week] += cohort.revenue_by_week[week];
+                    other.units_by_week[week] += cohort.units_by_week[week];
+                }
+            }
+            cohorts.push(other);
+        }
+        if cohort_by == CohortBy::Country {
+            let names = country_names(&conn)?;
+            for cohort in &mut cohorts {
+                if let Some(name) = names.get(&cohort.key) {
+                    cohort.label = name.clone();
+                }
+            }
+        }
+    }
+
+    Ok(CohortMatrix {
+        app_id,
+        cohort_by,
+        launch_date: Some(launch),
+        weeks: weeks as u32,
+        cohorts,
+    })
+}
+
+fn country_names(conn: &rusqlite::Connection) -> AppResult<HashMap<String, String>> {
+    countries::ensure_lookup_table(conn)?;
+    let mut stmt = conn.prepare("SELECT country_code, country_name FROM lookup_countries")?;
+    let names = stmt
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(names)
+}
+
+// ==================== Tauri Commands ====================
+
+/// Gross revenue and units of an app per cohort and week since launch. `cohortBy` is
+/// "country", "region" or "discount_tier"; `maxWeeks` defaults to 104.
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn query_cohorts(
+    appId: i64,
+    cohortBy: CohortBy,
+    maxWeeks: Option<u32>,
+) -> AppResult<CohortMatrix> {
+    tokio::task::spawn_blocking(move || get_cohorts(appId, cohortBy, maxWeeks))
+        .await?
+        .and_then(presentation::present)
+}
@@ -0,0 +1,229 @@
+// Side-by-side comparison of products and date ranges
+//
+// Each selector picks a product (an app, a package, or everything) and a date range, either as
+// dates or as the product's first days after launch. All series are returned in one call and
+// indexed by day offset from their own start, so "DLC A's first month vs DLC B's first month" or
+// "this June vs last June" line up without the frontend matching dates.
+
+use crate::presentation;
+use crate::queries::{self, get_app_id_column, get_connection};
+use crate::types::{AppError, AppResult, Date};
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const MAX_SELECTORS: usize = 10;
+const MAX_DAYS: i64 = 3660;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompareSelector {
+    /// Shown in the legend; defaults to the product and dates
+    pub label: Option<String>,
+    /// At most one of app_id and package_id; neither compares all products
+    pub app_id: Option<i64>,
+    pub package_id: Option<i64>,
+    pub start_date: Option<Date>,
+    pub end_date: Option<Date>,
+    /// Instead of start and end date: the product's first `launch_days` days, starting on its
+    /// first day with revenue
+    pub launch_days: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompareDay {
+    /// Days since the start of the series
+    pub offset: u32,
+    pub date: Date,
+    pub revenue: f64,
+    pub units: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompareSeries {
+    pub label: String,
+    pub app_id: Option<i64>,
+    pub package_id: Option<i64>,
+    /// None if a launch-relative product has no revenue yet
+    pub start_date: Option<Date>,
+    pub end_date: Option<Date>,
+    pub total_revenue: f64,
+    pub total_units: i64,
+    /// Every day from the start up to the end or the last complete day, whichever is earlier
+    pub days: Vec<CompareDay>,
+}
+
+// The column and id the selector's product is filtered by, if any
+fn product_filter(conn: &Connection, selector: &CompareSelector) -> Option<(String, i64)> {
+    match (selector.app_id, selector.package_id) {
+        (Some(app_id), _) => Some((get_app_id_column(conn), app_id)),
+        (None, Some(package_id)) => Some(("packageid".to_string(), package_id)),
+        (None, None) => None,
+    }
+}
+
+fn validate(selector: &CompareSelector) -> AppResult<()> {
+    if selector.app_id.is_some() && selector.package_id.is_some() {
+        return Err(AppError::InvalidInput(
+            "A selector can't have both an app and a package".to_string(),
+        ));
+    }
+    match selector.launch_days {
+        Some(_) if selector.start_date.is_some() || selector.end_date.is_some() => {
+            Err(AppError::InvalidInput(
+                "A selector has either launch days or start and end dates".to_string(),
+            ))
+        }
+        Some(_) if selector.app_id.is_none() && selector.package_id.is_none() => Err(
+            AppError::InvalidInput("Launch days need an app or a package".to_string()),
+        ),
+        Some(0) => Err(AppError::InvalidInput(
+            "Launch days must be at least 1".to_string(),
+        )),
+        Some(_) => Ok(()),
+        None => match (selector.start_date, selector.end_date) {
+            (Some(start), Some(end)) if end < start => Err(AppError::InvalidInput(
+                "End date must not be before start date".to_string(),
+            )),
+            (Some(_), Some(_)) => Ok(()),
+            _ => Err(AppError::InvalidInput(
+                "A selector needs a start and end date, or launch days".to_string(),
+            )),
+        },
+    }
+}
+
+fn default_label(selector: &CompareSelector, start: Option<Date>, end: Option<Date>) -> String {
+    let product = match (selector.app_id, selector.package_id) {
+        (Some(app_id), _) => format!("App {}", app_id),
+        (None, Some(package_id)) => format!("Package {}", package_id),
+        (None, None) => "All products".to_string(),
+    };
+    match (selector.launch_days, start, end) {
+        (Some(days), _, _) => format!("{}, first {} days", product, days),
+        (None, Some(start), Some(end)) => format!("{}, {} to {}", product, start, end),
+        _ => product,
+    }
+}
+
+fn compare_series(
+    conn: &Connection,
+    selector: &CompareSelector,
+    last_day: Option<Date>,
+) -> AppResult<CompareSeries> {
+    let product = product_filter(conn, selector);
+    let (start, end) = match selector.launch_days {
+        Some(days) => {
+            let (column, id) = product.as_ref().expect("validated");
+            let launch: Option<Date> = conn
+                .query_row(
+                    &format!(
+                        "SELECT MIN(date) FROM sales_data
+                         WHERE {} = ?1 AND CAST(gross_sales_usd AS REAL) > 0",
+                        column
+                    ),
+                    [id],
+                    |r| r.get(0),
+                )
+                .optional()?
+                .flatten();
+            (launch, launch.map(|l| l.add_days(days as i64 - 1)))
+        }
+        None => (selector.start_date, selector.end_date),
+    };
+
+    let mut series = CompareSeries {
+        label: selector
+            .label
+            .clone()
+            .unwrap_or_else(|| default_label(selector, start, end)),
+        app_id: selector.app_id,
+        package_id: selector.package_id,
+        start_date: start,
+        end_date: end,
+        total_revenue: 0.0,
+        total_units: 0,
+        days: Vec::new(),
+    };
+    let (Some(start), Some(end)) = (start, end) else {
+        return Ok(series);
+    };
+    if end.days_since(start) >= MAX_DAYS {
+        return Err(AppError::InvalidInput(format!(
+            "A selector can span at most {} days",
+            MAX_DAYS
+        )));
+    }
+    // The day Steam is still reporting would show as a drop at the end of the series
+    let last = last_day.map_or(end, |last| end.min(last));
+
+    let mut params: Vec<Value> = vec![start.to_string().into(), last.to_string().into()];
+    let product_condition = match product {
+        Some((column, id)) => {
+            params.push(id.into());
+            format!("AND {} = ?3", column)
+        }
+        None => String::new(),
+    };
+    let sql = format!(
+        "SELECT date, COALESCE(SUM(CAST(gross_sales_usd AS REAL)), 0),
+            COALESCE(SUM(net_units_sold), 0)
+         FROM sales_data
+         WHERE date >= ?1 AND date <= ?2 {}
+         GROUP BY date",
+        product_condition
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let by_date: HashMap<Date, (f64, i64)> = stmt
+        .query_map(params_from_iter(params), |r| {
+            Ok((r.get(0)?, (r.get(1)?, r.get(2)?)))
+        })?
+        // Rows with malformed dates are skipped rather than counted on the wrong day
+        .flatten()
+        .collect();
+
+    for offset in 0..=last.days_since(start) {
+        let date = start.add_days(offset);
+        let (revenue, units) = by_date.get(&date).copied().unwrap_or((0.0, 0));
+        series.total_revenue += revenue;
+        series.total_units += units;
+        series.days.push(CompareDay {
+            offset: offset as u32,
+            date,
+            revenue,
+            units,
+        });
+    }
+    Ok(series)
+}
+
+pub fn compare(selectors: &[CompareSelector]) -> AppResult<Vec<CompareSeries>> {
+    if selectors.is_empty() || selectors.len() > MAX_SELECTORS {
+        return Err(AppError::InvalidInput(format!(
+            "Compare needs 1 to {} selectors",
+            MAX_SELECTORS
+        )));
+    }
+    for selector in selectors {
+        validate(selector)?;
+    }
+    let conn = get_connection()?;
+    let last_day = queries::last_complete_day(None);
+    selectors
+        .iter()
+        .map(|selector| compare_series(&conn, selector, last_day))
+        .collect()
+}
+
+// ==================== Tauri Commands ====================
+
+/// Daily revenue and units for each selector, in the order given, aligned by day offset.
+#[tauri::command]
+pub async fn query_compare(selectors: Vec<CompareSelector>) -> AppResult<Vec<CompareSeries>> {
+    tokio::task::spawn_blocking(move || compare(&selectors))
+        .await?
+        .and_then(presentation::present)
+}
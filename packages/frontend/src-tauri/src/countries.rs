@@ -0,0 +1,71 @@
+// Country names and regions
+//
+// sales_data only has ISO 3166-1 alpha-2 codes. The app ships a table of every code with its
+// English name, UN M49 region and subregion, and EU membership (data/countries.csv), and loads it
+// into `lookup_countries` in the sales database so country queries can join it like the CLI's
+// lookup_apps and lookup_packages tables.
+
+use crate::database::begin_write;
+use rusqlite::{params, Connection, Result as SqliteResult};
+
+const COUNTRIES_CSV: &str = include_str!("../data/countries.csv");
+
+struct Country<'a> {
+    code: &'a str,
+    name: &'a str,
+    region: &'a str,
+    /// Empty for Antarctica
+    subregion: &'a str,
+    eu_member: bool,
+}
+
+fn bundled_countries() -> impl Iterator<Item = Country<'static>> {
+    COUNTRIES_CSV.lines().skip(1).filter_map(|line| {
+        let mut fields = line.split(',');
+        Some(Country {
+            code: fields.next()?,
+            name: fields.next()?,
+            region: fields.next()?,
+            subregion: fields.next()?,
+            eu_member: fields.next()? == "1",
+        })
+    })
+}
+
+/// Creates `lookup_countries` and fills it with the bundled countries if it has fewer rows, e.g.
+/// because it was just created or an app update added countries.
+pub(crate) fn ensure_lookup_table(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS lookup_countries (
+            country_code TEXT PRIMARY KEY,
+            country_name TEXT NOT NULL,
+            region TEXT NOT NULL,
+            subregion TEXT,
+            eu_member INTEGER NOT NULL DEFAULT 0
+        );",
+    )?;
+    let loaded: usize =
+        conn.query_row("SELECT COUNT(*) FROM lookup_countries", [], |r| r.get(0))?;
+    if loaded >= bundled_countries().count() {
+        return Ok(());
+    }
+
+    let tx = begin_write(conn)?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT OR REPLACE INTO lookup_countries
+                (country_code, country_name, region, subregion, eu_member)
+             VALUES (?1, ?2, ?3, NULLIF(?4, ''), ?5)",
+        )?;
+        for country in bundled_countries() {
+            stmt.execute(params![
+                country.code,
+                country.name,
+                country.region,
+                country.subregion,
+                country.eu_member
+            ])?;
+        }
+    }
+    tx.commit()
+}
@@ -0,0 +1,683 @@
+// Custom computed metrics
+//
+// Users define their own KPIs as arithmetic over sales_data columns, e.g.
+// `net_per_unit = net_sales_usd / net_units_sold`. A metric is stored in `custom_metrics` and
+// compiled to SQL whenever it is used: every column stands for its sum over the rows of a summary
+// (`record_count` for their number), so ratios are ratios of totals, and a division by zero gives
+// no value instead of an error. Summary queries evaluate the metrics named in their filters'
+// `metrics` and return them per row.
+//
+// Expressions only allow numbers, column names, + - * /, unary minus and parentheses; column
+// names are checked against the table, so nothing a user types reaches SQL as is.
+
+use crate::adjustments;
+use crate::queries::{
+    build_where_clause, get_app_id_column, get_connection, get_write_connection, QueryFilters,
+};
+use crate::schema::{column_exists, table_exists};
+use crate::types::{AppError, AppResult};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+// Metrics evaluated by one summary query at most
+const MAX_METRICS_PER_QUERY: usize = 20;
+const MAX_NAME_LENGTH: usize = 64;
+
+/// Values of custom metrics by name; None where the expression has no value (division by zero)
+pub type MetricValues = BTreeMap<String, Option<f64>>;
+
+/// How custom metrics scale with amounts of money, by name: the power of the money columns in
+/// the expression, e.g. 1 for `net_sales_usd / net_units_sold` and 0 for `net_sales_usd /
+/// gross_sales_usd`. None where it isn't known: the expression adds money to other quantities
+/// or uses a column that is neither money nor a count.
+pub type MoneyPowers = BTreeMap<String, Option<i32>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomMetric {
+    pub id: i64,
+    pub name: String,
+    pub expression: String,
+    pub description: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomMetricInput {
+    /// Lowercase letters, digits and underscores, starting with a letter
+    pub name: String,
+    pub expression: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(String),
+    Column(String),
+    Operator(char),
+    Open,
+    Close,
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut number = String::new();
+            while let Some(&d) = chars.peek().filter(|d| d.is_ascii_digit() || **d == '.') {
+                number.push(d);
+                chars.next();
+            }
+            tokens.push(Token::Number(number));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut name = String::new();
+            while let Some(&d) = chars
+                .peek()
+                .filter(|d| d.is_ascii_alphanumeric() || **d == '_')
+            {
+                name.push(d);
+                chars.next();
+            }
+            tokens.push(Token::Column(name));
+        } else {
+            tokens.push(match c {
+                '+' | '-' | '*' | '/' => Token::Operator(c),
+                '(' => Token::Open,
+                ')' => Token::Close,
+                _ => return Err(format!("Unexpected '{}'", c)),
+            });
+            chars.next();
+        }
+    }
+    Ok(tokens)
+}
+
+// Recursive descent over the tokens, emitting SQL as it goes
+struct Compiler<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    column: &'a dyn Fn(&str) -> bool,
+}
+
+impl Compiler<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        self.position += 1;
+        self.tokens.get(self.position - 1)
+    }
+
+    fn expression(&mut self) -> Result<String, String> {
+        let mut sql = self.term()?;
+        while let Some(Token::Operator(op @ ('+' | '-'))) = self.peek() {
+            let op = *op;
+            self.next();
+            sql = format!("{} {} {}", sql, op, self.term()?);
+        }
+        Ok(sql)
+    }
+
+    fn term(&mut self) -> Result<String, String> {
+        let mut sql = self.factor()?;
+        while let Some(Token::Operator(op @ ('*' | '/'))) = self.peek() {
+            let op = *op;
+            self.next();
+            let right = self.factor()?;
+            sql = match op {
+                '/' => format!("{} / NULLIF({}, 0)", sql, right),
+                _ => format!("{} * {}", sql, right),
+            };
+        }
+        Ok(sql)
+    }
+
+    fn factor(&mut self) -> Result<String, String> {
+        match self.next().cloned() {
+            // Written with a fraction so SQLite never does integer division
+            Some(Token::Number(number)) => match number.parse::<f64>() {
+                Ok(value) if value.is_finite() => Ok(format!("{:?}", value)),
+                _ => Err(format!("Invalid number '{}'", number)),
+            },
+            Some(Token::Column(name)) if name == "record_count" => {
+                Ok("CAST(COUNT(*) AS REAL)".to_string())
+            }
+            Some(Token::Column(name)) if (self.column)(&name) => {
+                Ok(format!("COALESCE(SUM(CAST(\"{}\" AS REAL)), 0)", name))
+            }
+            Some(Token::Column(name)) => Err(format!("Unknown column '{}'", name)),
+            Some(Token::Operator('-')) => Ok(format!("(-{})", self.factor()?)),
+            Some(Token::Open) => {
+                let sql = self.expression()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(format!("({})", sql)),
+                    _ => Err("Missing ')'".to_string()),
+                }
+            }
+            Some(token) => Err(format!("Unexpected {:?}", token)),
+            None => Err("Unexpected end of expression".to_string()),
+        }
+    }
+}
+
+// Power of the money columns in an expression, with the same grammar as the compiler. Amounts
+// are the `_usd` columns; units and record counts are not money.
+struct MoneyPower<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl MoneyPower<'_> {
+    fn next(&mut self) -> Option<&Token> {
+        self.position += 1;
+        self.tokens.get(self.position - 1)
+    }
+
+    fn expression(&mut self) -> Option<i32> {
+        let mut power = self.term();
+        while let Some(Token::Operator('+' | '-')) = self.tokens.get(self.position) {
+            self.next();
+            let right = self.term();
+            power = power.filter(|p| Some(*p) == right);
+        }
+        power
+    }
+
+    fn term(&mut self) -> Option<i32> {
+        let mut power = self.factor();
+        while let Some(Token::Operator(op @ ('*' | '/'))) = self.tokens.get(self.position) {
+            let sign = if *op == '/' { -1 } else { 1 };
+            self.next();
+            let right = self.factor();
+            power = power.zip(right).map(|(left, right)| left + sign * right);
+        }
+        power
+    }
+
+    fn factor(&mut self) -> Option<i32> {
+        match self.next().cloned()? {
+            Token::Number(_) => Some(0),
+            Token::Column(name) if name.ends_with("_usd") => Some(1),
+            Token::Column(name) if name == "record_count" || name.contains("units") => Some(0),
+            Token::Column(_) => None,
+            Token::Operator('-') => self.factor(),
+            Token::Open => {
+                let power = self.expression();
+                self.next();
+                power
+            }
+            _ => None,
+        }
+    }
+}
+
+fn money_power(expression: &str) -> Option<i32> {
+    let tokens = tokenize(expression).ok()?;
+    MoneyPower {
+        tokens: &tokens,
+        position: 0,
+    }
+    .expression()
+}
+
+/// Compiles `expression` to an aggregate SQL expression over sales_data.
+fn compile(conn: &Connection, expression: &str) -> Result<String, String> {
+    let tokens = tokenize(expression)?;
+    if tokens.is_empty() {
+        return Err("Expression must not be empty".to_string());
+    }
+    let column = |name: &str| column_exists(conn, "sales_data", name);
+    let mut compiler = Compiler {
+        tokens: &tokens,
+        position: 0,
+        column: &column,
+    };
+    let sql = compiler.expression()?;
+    match compiler.peek() {
+        Some(token) => Err(format!("Unexpected {:?}", token)),
+        None => Ok(sql),
+    }
+}
+
+pub(crate) fn ensure_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS custom_metrics (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            expression TEXT NOT NULL,
+            description TEXT,
+            created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+        );",
+    )
+}
+
+fn validate(conn: &Connection, input: &CustomMetricInput, id: Option<i64>) -> AppResult<()> {
+    let name = input.name.trim();
+    let valid_name = name.len() <= MAX_NAME_LENGTH
+        && name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if !valid_name {
+        return Err(AppError::InvalidInput(format!(
+            "Metric names are up to {} lowercase letters, digits and underscores, starting with \
+             a letter",
+            MAX_NAME_LENGTH
+        )));
+    }
+    let taken: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM custom_metrics WHERE name = ?1 AND id IS NOT ?2)",
+        params![name, id],
+        |r| r.get(0),
+    )?;
+    if taken {
+        return Err(AppError::InvalidInput(format!(
+            "There already is a metric named '{}'",
+            name
+        )));
+    }
+
+    let sql = compile(conn, &input.expression)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid expression: {}", e)))?;
+    // Catches anything the compiler let through that SQLite doesn't accept
+    conn.prepare(&format!("SELECT {} FROM sales_data LIMIT 0", sql))
+        .map_err(|e| AppError::InvalidInput(format!("Invalid expression: {}", e)))?;
+    Ok(())
+}
+
+fn row_to_metric(r: &rusqlite::Row) -> SqliteResult<CustomMetric> {
+    Ok(CustomMetric {
+        id: r.get(0)?,
+        name: r.get(1)?,
+        expression: r.get(2)?,
+        description: r.get(3)?,
+        created_at: r.get(4)?,
+    })
+}
+
+const METRIC_COLUMNS: &str = "id, name, expression, description, created_at";
+
+fn list_metrics(conn: &Connection) -> SqliteResult<Vec<CustomMetric>> {
+    if !table_exists(conn, "custom_metrics") {
+        return Ok(Vec::new());
+    }
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM custom_metrics ORDER BY name",
+        METRIC_COLUMNS
+    ))?;
+    let rows = stmt.query_map([], row_to_metric)?;
+    rows.collect()
+}
+
+pub fn list_custom_metrics_all() -> SqliteResult<Vec<CustomMetric>> {
+    let conn = get_connection()?;
+    list_metrics(&conn)
+}
+
+pub fn insert_metric(input: &CustomMetricInput) -> AppResult<i64> {
+    let conn = get_write_connection()?;
+    ensure_tables(&conn)?;
+    validate(&conn, input, None)?;
+    conn.execute(
+        "INSERT INTO custom_metrics (name, expression, description) VALUES (?1, ?2, ?3)",
+        params![
+            input.name.trim(),
+            input.expression.trim(),
+            input.description
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn update_metric_by_id(id: i64, input: &CustomMetricInput) -> AppResult<bool> {
+    let conn = get_write_connection()?;
+    ensure_tables(&conn)?;
+    validate(&conn, input, Some(id))?;
+    let changed = conn.execute(
+        "UPDATE custom_metrics SET name = ?2, expression = ?3, description = ?4 WHERE id = ?1",
+        params![
+            id,
+            input.name.trim(),
+            input.expression.trim(),
+            input.description
+        ],
+    )?;
+    Ok(changed > 0)
+}
+
+pub fn delete_metric_by_id(id: i64) -> SqliteResult<bool> {
+    let conn = get_write_connection()?;
+    ensure_tables(&conn)?;
+    Ok(conn.execute("DELETE FROM custom_metrics WHERE id = ?1", [id])? > 0)
+}
+
+/// Evaluates the metrics named in `filters.metrics` per value of `key_expr` (the column or
+/// expression a summary query groups by), over the rows the filters select. Empty without
+/// metrics.
+pub(crate) fn evaluate(
+    conn: &Connection,
+    filters: &QueryFilters,
+    key_expr: &str,
+) -> SqliteResult<HashMap<String, MetricValues>> {
+    let names = match filters.metrics.as_deref() {
+        Some(names) if !names.is_empty() => names,
+        _ => return Ok(HashMap::new()),
+    };
+    if names.len() > MAX_METRICS_PER_QUERY {
+        return Err(rusqlite::Error::InvalidParameterName(format!(
+            "At most {} custom metrics per query",
+            MAX_METRICS_PER_QUERY
+        )));
+    }
+    let metrics = list_metrics(conn)?;
+    let mut expressions = Vec::with_capacity(names.len());
+    for name in names {
+        let metric = metrics.iter().find(|m| &m.name == name).ok_or_else(|| {
+            rusqlite::Error::InvalidParameterName(format!("Unknown custom metric '{}'", name))
+        })?;
+        // Compiled again, as the active database may lack a column the metric was defined with
+        let sql = compile(conn, &metric.expression).map_err(|e| {
+            rusqlite::Error::InvalidParameterName(format!("Custom metric '{}': {}", name, e))
+        })?;
+        expressions.push(sql);
+    }
+
+    let app_id_col = get_app_id_column(conn);
+    let sales = adjustments::sales_source(conn, filters);
+    let where_clause = build_where_clause(filters, &app_id_col, sales.source_column)
+        .sale_items_only(conn, filters);
+    let mut params: Vec<Value> = Vec::new();
+    if let Some(start_date) = filters.start_date {
+        params.push(start_date.to_string().into());
+    }
+    if let Some(end_date) = filters.end_date {
+        params.push(end_date.to_string().into());
+    }
+    for app_id in filters.app_ids.iter().flatten() {
+        params.push((*app_id).into());
+    }
+    if let Some(country_code) = &filters.country_code {
+        params.push(country_code.clone().into());
+    }
+    if let Some(source) = &filters.source {
+        params.push(source.clone().into());
+    }
+    for sale_type in filters.sale_types.iter().flatten() {
+        params.push(sale_type.clone().into());
+    }
+
+    let sql = format!(
+        "SELECT CAST({} AS TEXT) as key, {} FROM {} {} GROUP BY key",
+        key_expr,
+        expressions.join(", "),
+        sales,
+        where_clause.clause
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params_from_iter(params), |r| {
+        let key: Option<String> = r.get(0)?;
+        let mut values = MetricValues::new();
+        for (i, name) in names.iter().enumerate() {
+            values.insert(name.clone(), r.get::<_, Option<f64>>(i + 1)?);
+        }
+        Ok((key.unwrap_or_default(), values))
+    })?;
+    rows.collect()
+}
+
+/// How the metrics named in `filters.metrics` scale with amounts of money; see MoneyPowers.
+pub(crate) fn money_powers(conn: &Connection, filters: &QueryFilters) -> SqliteResult<MoneyPowers> {
+    let names = match filters.metrics.as_deref() {
+        Some(names) if !names.is_empty() => names,
+        _ => return Ok(MoneyPowers::new()),
+    };
+    let metrics = list_metrics(conn)?;
+    Ok(names
+        .iter()
+        .map(|name| {
+            let power = metrics
+                .iter()
+                .find(|m| &m.name == name)
+                .and_then(|m| money_power(&m.expression));
+            (name.clone(), power)
+        })
+        .collect())
+}
+
+/// Multiplies the metric values that are amounts of money by `factor` as often as their
+/// expressions multiply amounts, for presentation mode. Values without a known power are hidden.
+pub(crate) fn scale_money(values: &mut MetricValues, powers: &MoneyPowers, factor: f64) {
+    for (name, value) in values.iter_mut() {
+        *value = match powers.get(name).copied().flatten() {
+            Some(power) => value
+                .map(|v| v * factor.powi(power))
+                .filter(|v| v.is_finite()),
+            None => None,
+        };
+    }
+}
+
+// ==================== Tauri Commands ====================
+
+#[tauri::command]
+pub async fn list_custom_metrics() -> AppResult<Vec<CustomMetric>> {
+    list_custom_metrics_all().map_err(AppError::from)
+}
+
+#[tauri::command]
+pub async fn create_custom_metric(metric: CustomMetricInput) -> AppResult<i64> {
+    insert_metric(&metric)
+}
+
+#[tauri::command]
+pub async fn update_custom_metric(id: i64, metric: CustomMetricInput) -> AppResult<()> {
+    match update_metric_by_id(id, &metric)? {
+        true => Ok(()),
+        false => Err(AppError::NotFound(format!(
+            "Custom metric {} not found",
+            id
+        ))),
+    }
+}
+
+#[tauri::command]
+pub async fn delete_custom_metric(id: i64) -> AppResult<()> {
+    match delete_metric_by_id(id)? {
+        true => Ok(()),
+        false => Err(AppError::NotFound(format!(
+            "Custom metric {} not found",
+            id
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amounts_per_unit_are_money() {
+        assert_eq!(money_power("net_sales_usd / net_units_sold"), Some(1));
+        assert_eq!(
+            money_power("(gross_sales_usd - net_sales_usd) * 0.7"),
+            Some(1)
+        );
+        assert_eq!(money_power("-net_tax_usd / record_count"), Some(1));
+    }
+
+    #[test]
+    fn ratios_of_amounts_and_counts_are_not_money() {
+        assert_eq!(money_power("net_sales_usd / gross_sales_usd"), Some(0));
+        assert_eq!(money_power("net_units_sold / record_count * 100"), Some(0));
+        assert_eq!(money_power("gross_sales_usd * net_sales_usd"), Some(2));
+    }
+
+    #[test]
+    fn mixed_and_unknown_expressions_have_no_power() {
+        assert_eq!(money_power("gross_sales_usd + net_units_sold"), None);
+        assert_eq!(money_power("net_sales_usd + 5"), None);
+        assert_eq!(money_power("base_price * net_units_sold"), None);
+        assert_eq!(money_power(""), None);
+    }
+
+    #[test]
+    fn scales_values_by_their_power() {
+        let powers = MoneyPowers::from([
+            ("per_unit".to_string(), Some(1)),
+            ("margin".to_string(), Some(0)),
+            ("mixed".to_string(), None),
+        ]);
+        let mut values = MetricValues::from([
+            ("per_unit".to_string(), Some(20.0)),
+            ("margin".to_string(), Some(0.7)),
+            ("mixed".to_string(), Some(3.0)),
+            ("unknown".to_string(), Some(1.0)),
+        ]);
+        scale_money(&mut values, &powers, 0.5);
+        assert_eq!(values["per_unit"], Some(10.0));
+        assert_eq!(values["margin"], Some(0.7));
+        assert_eq!(values["mixed"], None);
+        assert_eq!(values["unknown"], None);
+    }
+
+    #[test]
+    fn a_zero_factor_hides_values_it_would_divide_by() {
+        let powers = MoneyPowers::from([("units_per_dollar".to_string(), Some(-1))]);
+        let mut values = MetricValues::from([("units_per_dollar".to_string(), Some(2.0))]);
+        scale_money(&mut values, &powers, 0.0);
+        assert_eq!(values["units_per_dollar"], None);
+    }
+
+    fn sales_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE sales_data (date TEXT, net_sales_usd TEXT, net_units_sold INTEGER);",
+        )
+        .unwrap();
+        ensure_tables(&conn).unwrap();
+        conn
+    }
+
+    fn input(name: &str, expression: &str) -> CustomMetricInput {
+        CustomMetricInput {
+            name: name.to_string(),
+            expression: expression.to_string(),
+            description: None,
+        }
+    }
+
+    // Value of `expression` over all of sales_data
+    fn value(conn: &Connection, expression: &str) -> Option<f64> {
+        let sql = compile(conn, expression).unwrap();
+        conn.query_row(&format!("SELECT {} FROM sales_data", sql), [], |r| r.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn compiles_ratios_of_totals() {
+        let conn = sales_db();
+        conn.execute_batch(
+            "INSERT INTO sales_data VALUES ('2024-01-01', '10.00', 2), ('2024-01-02', '5.00', 1);",
+        )
+        .unwrap();
+        assert_eq!(value(&conn, "net_sales_usd / net_units_sold"), Some(5.0));
+        assert_eq!(value(&conn, "record_count"), Some(2.0));
+        // Numbers are never divided as integers
+        assert_eq!(value(&conn, "1 / 2"), Some(0.5));
+        assert_eq!(value(&conn, "-(net_units_sold - 1) * 2"), Some(-4.0));
+    }
+
+    #[test]
+    fn division_by_zero_and_empty_tables_have_no_value() {
+        let conn = sales_db();
+        assert_eq!(value(&conn, "net_sales_usd / net_units_sold"), None);
+        assert_eq!(value(&conn, "net_sales_usd"), Some(0.0));
+        assert_eq!(value(&conn, "record_count"), Some(0.0));
+        assert_eq!(value(&conn, "1 / 0"), None);
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        let conn = sales_db();
+        for (expression, error) in [
+            ("", "Expression must not be empty"),
+            ("   ", "Expression must not be empty"),
+            ("gross_sales_usd", "Unknown column 'gross_sales_usd'"),
+            ("net_sales_usd; DROP TABLE sales_data", "Unexpected ';'"),
+            ("\"net_sales_usd\"", "Unexpected '\"'"),
+            ("1.2.3", "Invalid number '1.2.3'"),
+            ("(net_sales_usd", "Missing ')'"),
+            ("net_sales_usd)", "Unexpected Close"),
+            (
+                "net_sales_usd net_units_sold",
+                "Unexpected Column(\"net_units_sold\")",
+            ),
+            ("net_sales_usd /", "Unexpected end of expression"),
+            ("* 2", "Unexpected Operator('*')"),
+        ] {
+            assert_eq!(
+                compile(&conn, expression),
+                Err(error.to_string()),
+                "{}",
+                expression
+            );
+        }
+    }
+
+    #[test]
+    fn validates_names() {
+        let conn = sales_db();
+        assert!(validate(&conn, &input("net_per_unit", "net_sales_usd"), None).is_ok());
+        let too_long = "m".repeat(MAX_NAME_LENGTH + 1);
+        for name in [
+            "",
+            "Net",
+            "1st",
+            "_net",
+            "net-per-unit",
+            "net per unit",
+            &too_long,
+        ] {
+            let err = validate(&conn, &input(name, "net_sales_usd"), None).unwrap_err();
+            assert_eq!(err.kind(), "invalidInput", "{:?}", name);
+        }
+        assert!(validate(&conn, &input(&"m".repeat(MAX_NAME_LENGTH), "1"), None).is_ok());
+        assert!(validate(&conn, &input("net", ""), None).is_err());
+    }
+
+    #[test]
+    fn names_are_unique_except_for_the_metric_itself() {
+        let conn = sales_db();
+        conn.execute(
+            "INSERT INTO custom_metrics (id, name, expression) VALUES (1, 'net', 'net_sales_usd')",
+            [],
+        )
+        .unwrap();
+        assert!(validate(&conn, &input("net", "net_sales_usd"), None).is_err());
+        assert!(validate(&conn, &input(" net ", "net_sales_usd"), Some(2)).is_err());
+        assert!(validate(&conn, &input("net", "net_sales_usd * 2"), Some(1)).is_ok());
+    }
+
+    #[test]
+    fn evaluates_nothing_without_metrics() {
+        let conn = sales_db();
+        let mut filters = QueryFilters::default();
+        assert!(evaluate(&conn, &filters, "date").unwrap().is_empty());
+        filters.metrics = Some(Vec::new());
+        assert!(evaluate(&conn, &filters, "date").unwrap().is_empty());
+        assert!(money_powers(&conn, &filters).unwrap().is_empty());
+
+        filters.metrics = Some(vec!["missing".to_string()]);
+        assert!(evaluate(&conn, &filters, "date").is_err());
+        assert_eq!(money_powers(&conn, &filters).unwrap()["missing"], None);
+        filters.metrics = Some(vec!["net".to_string(); MAX_METRICS_PER_QUERY + 1]);
+        assert!(evaluate(&conn, &filters, "date").is_err());
+    }
+}
@@ -0,0 +1,91 @@
+// Dashboard JSON export
+//
+// `export_dashboard_json` writes what the dashboard shows for a set of filters as one JSON
+// document: the stats, the daily (or weekly, monthly, ...) series, and the totals per app,
+// country and platform. Static report generators (e.g. the investor report) read that single
+// file instead of making the six query calls themselves. Like the other exports it has real
+// amounts, also in presentation mode. The document is written next to `path` first and then
+// renamed, so a generator watching the file never reads half of it.
+
+use crate::export::SUMMARY_LIMIT;
+use crate::queries::{
+    self, AppSummary, CountrySummary, DailySummary, DashboardStats, PlatformSummary, QueryFilters,
+};
+use crate::types::{AppError, AppResult};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+// Bumped when a field is removed or changes meaning; new fields don't change it
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DashboardJson {
+    pub format_version: u32,
+    pub generated_at: String,
+    /// The filters the numbers are for, with a named date range resolved to dates
+    pub filters: QueryFilters,
+    pub stats: DashboardStats,
+    /// One entry per day, or per period with `granularity` in the filters
+    pub daily: Vec<DailySummary>,
+    pub apps: Vec<AppSummary>,
+    pub countries: Vec<CountrySummary>,
+    pub platforms: Vec<PlatformSummary>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DashboardJsonFile {
+    pub path: String,
+    pub size_bytes: u64,
+}
+
+/// Runs the dashboard's queries with `filters`, whose date range must have been resolved.
+/// The summaries have every app and country unless the filters set a limit.
+pub fn build(filters: QueryFilters) -> AppResult<DashboardJson> {
+    let summaries = QueryFilters {
+        limit: filters.limit.or(Some(SUMMARY_LIMIT)),
+        ..filters.clone()
+    };
+    Ok(DashboardJson {
+        format_version: FORMAT_VERSION,
+        generated_at: Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+        stats: queries::get_stats(filters.clone())?,
+        daily: queries::get_daily_summaries(filters.clone())?,
+        apps: queries::get_app_summaries(summaries.clone())?,
+        countries: queries::get_country_summaries(summaries)?,
+        platforms: queries::get_platform_summaries(filters.clone())?,
+        filters,
+    })
+}
+
+fn write(document: &DashboardJson, path: &Path) -> AppResult<DashboardJsonFile> {
+    let json = serde_json::to_vec_pretty(document)
+        .map_err(|e| AppError::Internal(format!("Failed to encode the dashboard: {}", e)))?;
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    fs::write(&partial, &json)?;
+    if let Err(e) = fs::rename(&partial, path) {
+        let _ = fs::remove_file(&partial);
+        return Err(e.into());
+    }
+    Ok(DashboardJsonFile {
+        path: path.to_string_lossy().to_string(),
+        size_bytes: json.len() as u64,
+    })
+}
+
+// ==================== Tauri Commands ====================
+
+/// Writes the stats, series and summaries of `filters` to `path` as one JSON document.
+/// Replaces any file at `path`.
+#[tauri::command]
+pub async fn export_dashboard_json(
+    filters: QueryFilters,
+    path: String,
+) -> AppResult<DashboardJsonFile> {
+    let filters = filters.resolve_date_range()?;
+    tokio::task::spawn_blocking(move || write(&build(filters)?, Path::new(&path))).await?
+}
@@ -1,8 +1,141 @@
-use std::path::PathBuf;
+use crate::types::{AppError, AppResult};
+use rusqlite::{Connection, Transaction, TransactionBehavior};
+#[cfg(test)]
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-pub fn get_database_path() -> PathBuf {
+/// How long a connection to a sales database waits for another one's lock before failing with
+/// SQLITE_BUSY. The CLI, the app's own writers and the dashboard's queries all use the same file,
+/// and a writer holds its lock for one batch at a time.
+pub const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Starts a write transaction with BEGIN IMMEDIATE, so that it waits for other writers up to
+/// the busy timeout. A deferred transaction takes the write lock only at its first write, and
+/// SQLite fails that upgrade with SQLITE_BUSY right away, without waiting, when another
+/// connection wrote meanwhile.
+pub fn begin_write(conn: &Connection) -> rusqlite::Result<Transaction<'_>> {
+    Transaction::new_unchecked(conn, TransactionBehavior::Immediate)
+}
+
+/// Moves `table`, which the app used to create in each sales database, into the app database
+/// `app_conn`, where a table of that name with a `source_id` column must already exist. The
+/// `columns` of every writable source's rows are copied with the source's id, and the table is
+/// dropped from its sales database. Sources that don't have the table are skipped.
+pub(crate) fn move_table_from_sales_databases(
+    app_conn: &Connection,
+    table: &str,
+    columns: &[&str],
+) -> AppResult<()> {
+    let columns = columns.join(", ");
+    for source in crate::sources::list_sources()? {
+        if source.read_only || !Path::new(&source.db_path).exists() {
+            continue;
+        }
+        app_conn.execute("ATTACH DATABASE ?1 AS sales", [&source.db_path])?;
+        let moved = (|| -> rusqlite::Result<bool> {
+            let exists = app_conn
+                .query_row(
+                    "SELECT 1 FROM sales.sqlite_master WHERE type = 'table' AND name = ?1",
+                    [table],
+                    |_| Ok(()),
+                )
+                .is_ok();
+            if !exists {
+                return Ok(false);
+            }
+            let tx = begin_write(app_conn)?;
+            tx.execute(
+                &format!(
+                    "INSERT OR IGNORE INTO main.{table} (source_id, {columns})
+                     SELECT ?1, {columns} FROM sales.{table}",
+                    table = table,
+                    columns = columns
+                ),
+                [&source.id],
+            )?;
+            tx.execute_batch(&format!("DROP TABLE sales.{}", table))?;
+            tx.commit()?;
+            Ok(true)
+        })();
+        app_conn.execute_batch("DETACH DATABASE sales")?;
+        if moved? {
+            tracing::info!(
+                "Moved {} of data source {} to the app database",
+                table,
+                source.id
+            );
+        }
+    }
+    Ok(())
+}
+
+// Data directory of the tests running on this thread, instead of ~/.steamsales
+#[cfg(test)]
+thread_local! {
+    static TEST_DATA_DIR: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
+/// Points the databases of the current thread at `dir` until dropped: the app database, the
+/// default sales database, and the default sales database as the active one. Tests that
+/// spawn threads enter it on each of them.
+#[cfg(test)]
+pub(crate) struct TestDataDir {
+    previous: Option<PathBuf>,
+}
+
+#[cfg(test)]
+impl TestDataDir {
+    pub(crate) fn enter(dir: &Path) -> Self {
+        let previous = TEST_DATA_DIR.with(|d| d.replace(Some(dir.to_path_buf())));
+        TestDataDir { previous }
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestDataDir {
+    fn drop(&mut self) {
+        TEST_DATA_DIR.with(|d| *d.borrow_mut() = self.previous.take());
+    }
+}
+
+fn data_dir() -> PathBuf {
+    #[cfg(test)]
+    if let Some(dir) = TEST_DATA_DIR.with(|d| d.borrow().clone()) {
+        return dir;
+    }
     let home = dirs::home_dir().expect("Failed to get home directory");
-    home.join(".steamsales").join("steam-financial.db")
+    home.join(".steamsales")
+}
+
+/// Path of the sales database of the active data source.
+pub fn get_database_path() -> PathBuf {
+    // The active source is cached for the whole process, so tests don't go through it
+    #[cfg(test)]
+    if TEST_DATA_DIR.with(|d| d.borrow().is_some()) {
+        return get_default_database_path();
+    }
+    crate::sources::active_database_path()
+}
+
+/// Path of the built-in "default" data source's sales database.
+pub fn get_default_database_path() -> PathBuf {
+    data_dir().join("steam-financial.db")
+}
+
+/// Path of the app-owned database. It holds state the app itself manages (API key registry,
+/// usage logs, ...) so that it survives the CLI recreating or the user deleting the sales database.
+pub fn get_app_database_path() -> PathBuf {
+    data_dir().join("steam-sales-app.db")
+}
+
+/// Opens (and creates if needed) the app-owned database.
+pub fn get_app_connection() -> rusqlite::Result<rusqlite::Connection> {
+    let path = get_app_database_path();
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    rusqlite::Connection::open(&path)
 }
 
 /// Returns true if the database file exists and has the expected schema (sales_data table).
@@ -47,21 +180,21 @@ pub fn ensure_database_usable() -> bool {
 }
 
 #[tauri::command]
-pub async fn get_database_path_str() -> Result<String, String> {
+pub async fn get_database_path_str() -> AppResult<String> {
     let path = get_database_path();
     if !path.exists() {
-        return Err("Database file not found".to_string());
+        return Err(AppError::NotFound("Database file not found".to_string()));
     }
     Ok(path.to_string_lossy().to_string())
 }
 
 #[tauri::command]
-pub async fn database_exists() -> Result<bool, String> {
+pub async fn database_exists() -> AppResult<bool> {
     Ok(get_database_path().exists())
 }
 
 #[tauri::command]
-pub async fn delete_database() -> Result<(), String> {
+pub async fn delete_database() -> AppResult<()> {
     let db_path = get_database_path();
 
     // Close any open connections first by ensuring the path is correct
@@ -70,7 +203,7 @@ pub async fn delete_database() -> Result<(), String> {
     // Delete main database file
     if db_path.exists() {
         std::fs::remove_file(&db_path)
-            .map_err(|e| format!("Failed to delete database file: {}", e))?;
+            .map_err(|e| AppError::Io(format!("Failed to delete database file: {}", e)))?;
     }
 
     // Delete SQLite WAL file (Write-Ahead Log)
@@ -82,8 +215,8 @@ pub async fn delete_database() -> Result<(), String> {
         .join(format!("{}-wal", db_filename));
     if wal_path.exists() {
         if let Err(e) = std::fs::remove_file(&wal_path) {
-            eprintln!(
-                "Warning: Failed to delete WAL file {}: {}",
+            tracing::warn!(
+                "Failed to delete WAL file {}: {}",
                 wal_path.display(),
                 e
             );
@@ -98,8 +231,8 @@ pub async fn delete_database() -> Result<(), String> {
         .join(format!("{}-shm", db_filename));
     if shm_path.exists() {
         if let Err(e) = std::fs::remove_file(&shm_path) {
-            eprintln!(
-                "Warning: Failed to delete SHM file {}: {}",
+            tracing::warn!(
+                "Failed to delete SHM file {}: {}",
                 shm_path.display(),
                 e
             );
@@ -108,7 +241,9 @@ pub async fn delete_database() -> Result<(), String> {
 
     // Verify the main database file is actually gone
     if db_path.exists() {
-        return Err("Database file still exists after deletion attempt".to_string());
+        return Err(AppError::Io(
+            "Database file still exists after deletion attempt".to_string(),
+        ));
     }
 
     Ok(())
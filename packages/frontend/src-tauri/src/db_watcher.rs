@@ -0,0 +1,154 @@
+// Database change notifications
+//
+// The CLI can also be run outside the app, e.g. from cron, so the app doesn't always know when
+// the data changed. This watches the directory of the active database for writes to the
+// database file and its WAL. Once they have stopped for QUIET_PERIOD (or after MAX_DELAY while
+// a long fetch keeps writing) it reads the dashboard stats again and emits `database-updated`
+// with them if they differ from the last ones, so open dashboards can reload. Writes that leave
+// the stats as they were (annotations, SQLite checkpoints, ...) are not reported, which also
+// keeps the reload from triggering itself.
+
+use crate::database::get_database_path;
+use crate::queries::{self, DashboardStats, QueryFilters};
+use notify::event::ModifyKind;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tracing::{info, warn};
+
+// How long writes must have stopped before they are reported
+const QUIET_PERIOD: Duration = Duration::from_secs(2);
+// Longest a stream of writes goes unreported
+const MAX_DELAY: Duration = Duration::from_secs(30);
+// How often the active database is looked up while nothing is written, to follow source switches
+const RECHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+type Events = Receiver<notify::Result<Event>>;
+
+/// Payload of the `database-updated` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseUpdated {
+    pub database_path: String,
+    pub stats: DashboardStats,
+}
+
+struct WatchedDatabase {
+    path: PathBuf,
+    /// None if the directory couldn't be watched
+    dir: Option<PathBuf>,
+    stats: Option<DashboardStats>,
+}
+
+// Whether `event` wrote to the database at `db_path` or its WAL. Reading the database changes
+// the WAL's metadata, so that doesn't count, or reading the stats would trigger the next read.
+fn touches(event: &Event, db_path: &Path) -> bool {
+    if matches!(
+        event.kind,
+        EventKind::Access(_) | EventKind::Modify(ModifyKind::Metadata(_))
+    ) {
+        return false;
+    }
+    let Some(name) = db_path.file_name() else {
+        return false;
+    };
+    let mut wal = name.to_owned();
+    wal.push("-wal");
+    event
+        .paths
+        .iter()
+        .filter_map(|path| path.file_name())
+        .any(|file| file == name || file == wal)
+}
+
+// None until the CLI has created the database; opening it would create an empty one
+fn read_stats(db_path: &Path) -> Option<DashboardStats> {
+    if !db_path.exists() {
+        return None;
+    }
+    queries::get_stats(QueryFilters::default()).ok()
+}
+
+fn watch(watcher: &mut impl Watcher, db_path: PathBuf) -> WatchedDatabase {
+    let mut dir = db_path.parent().map(Path::to_path_buf);
+    if let Some(path) = &dir {
+        if let Err(e) = watcher.watch(path, RecursiveMode::NonRecursive) {
+            warn!("Failed to watch {} for changes: {}", path.display(), e);
+            dir = None;
+        }
+    }
+    WatchedDatabase {
+        stats: read_stats(&db_path),
+        path: db_path,
+        dir,
+    }
+}
+
+// Waits until the database wasn't written to for QUIET_PERIOD, or at most MAX_DELAY
+fn wait_until_quiet(events: &Events, db_path: &Path) {
+    let started = Instant::now();
+    let mut last_write = started;
+    while started.elapsed() < MAX_DELAY {
+        let Some(wait) = (last_write + QUIET_PERIOD).checked_duration_since(Instant::now()) else {
+            return;
+        };
+        match events.recv_timeout(wait) {
+            Ok(Ok(event)) if touches(&event, db_path) => last_write = Instant::now(),
+            Ok(_) => {}
+            Err(_) => return,
+        }
+    }
+}
+
+fn run(app: AppHandle) -> notify::Result<()> {
+    let (sender, events) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    let mut watched = watch(&mut watcher, get_database_path());
+    loop {
+        let db_path = get_database_path();
+        if db_path != watched.path {
+            if let Some(dir) = &watched.dir {
+                let _ = watcher.unwatch(dir);
+            }
+            watched = watch(&mut watcher, db_path);
+            continue;
+        }
+        match events.recv_timeout(RECHECK_INTERVAL) {
+            Ok(Ok(event)) if touches(&event, &db_path) => {}
+            Ok(_) | Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+        wait_until_quiet(&events, &db_path);
+
+        let Some(stats) = read_stats(&db_path) else {
+            continue;
+        };
+        if watched.stats.as_ref() == Some(&stats) {
+            continue;
+        }
+        info!(
+            "{} was updated; it now has {} records",
+            db_path.display(),
+            stats.record_count
+        );
+        let payload = DatabaseUpdated {
+            database_path: db_path.to_string_lossy().to_string(),
+            stats: stats.clone(),
+        };
+        let _ = app.emit("database-updated", &payload);
+        watched.stats = Some(stats);
+    }
+}
+
+/// Starts watching the active database for changes made outside the app.
+pub fn start(app: AppHandle) {
+    thread::spawn(move || {
+        if let Err(e) = run(app) {
+            warn!("Failed to watch the database for changes: {}", e);
+        }
+    });
+}
@@ -0,0 +1,209 @@
+// Long-tail revenue decay
+//
+// After launch, a game's weekly revenue tends to fall off like a power law: revenue in week t is
+// about a * t^-b. The curve is fitted by least squares on log(revenue) against log(t) over the
+// complete weeks since launch, leaving out the launch week itself (week 0, the launch spike) and
+// weeks without revenue. From the fit follow the weeks it currently takes weekly revenue to halve
+// and a projection of the next year, e.g. for back-catalog forecasts.
+
+use crate::presentation;
+use crate::queries::{self, get_app_id_column, get_connection};
+use crate::types::{AppResult, Date};
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+
+// Fewer fitted weeks than this give no fit
+const MIN_FIT_WEEKS: usize = 4;
+const PROJECTION_WEEKS: u32 = 52;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecayWeek {
+    /// Weeks since launch; week 0 starts on the launch date
+    pub week: u32,
+    pub revenue: f64,
+    /// Revenue per the fitted curve; None for week 0 and without a fit
+    pub fitted: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PowerLawFit {
+    /// a in revenue = a * week^-b
+    pub coefficient: f64,
+    /// b in revenue = a * week^-b; larger decays faster
+    pub exponent: f64,
+    /// Of the log-log fit, 0-1
+    pub r_squared: f64,
+    pub fitted_weeks: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecayCurve {
+    pub app_id: i64,
+    /// First day with revenue; None if the app has none
+    pub launch_date: Option<Date>,
+    pub lifetime_revenue: f64,
+    /// Complete weeks since launch
+    pub weeks: Vec<DecayWeek>,
+    pub fit: Option<PowerLawFit>,
+    /// Weeks until weekly revenue halves from the latest week, per the fit
+    pub current_half_life_weeks: Option<f64>,
+    /// Weeks since launch it took to earn half of the revenue so far
+    pub weeks_to_half_revenue: Option<u32>,
+    /// Revenue of the next 52 weeks per the fit
+    pub projected_next_year: Option<f64>,
+}
+
+// Least squares fit of log(revenue) = log(a) - b * log(week)
+fn fit_power_law(weeks: &[DecayWeek]) -> Option<PowerLawFit> {
+    let points: Vec<(f64, f64)> = weeks
+        .iter()
+        .filter(|w| w.week > 0 && w.revenue > 0.0)
+        .map(|w| ((w.week as f64).ln(), w.revenue.ln()))
+        .collect();
+    if points.len() < MIN_FIT_WEEKS {
+        return None;
+    }
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let sxx: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    let sxy: f64 = points
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let syy: f64 = points.iter().map(|(_, y)| (y - mean_y).powi(2)).sum();
+    if sxx == 0.0 {
+        return None;
+    }
+    let slope = sxy / sxx;
+    let intercept = mean_y - slope * mean_x;
+    let r_squared = if syy == 0.0 {
+        1.0
+    } else {
+        (sxy * sxy) / (sxx * syy)
+    };
+    Some(PowerLawFit {
+        coefficient: intercept.exp(),
+        exponent: -slope,
+        r_squared,
+        fitted_weeks: points.len(),
+    })
+}
+
+fn fitted(fit: &PowerLawFit, week: u32) -> f64 {
+    fit.coefficient * (week as f64).powf(-fit.exponent)
+}
+
+pub fn get_decay_curve(app_id: i64) -> AppResult<DecayCurve> {
+    let conn = get_connection()?;
+    let app_id_col = get_app_id_column(&conn);
+
+    let launch_date: Option<Date> = conn
+        .query_row(
+            &format!(
+                "SELECT MIN(date) FROM sales_data
+                 WHERE {} = ?1 AND CAST(gross_sales_usd AS REAL) > 0",
+                app_id_col
+            ),
+            [app_id],
+            |r| r.get(0),
+        )
+        .optional()?
+        .flatten();
+    let mut curve = DecayCurve {
+        app_id,
+        launch_date,
+        lifetime_revenue: 0.0,
+        weeks: Vec::new(),
+        fit: None,
+        current_half_life_weeks: None,
+        weeks_to_half_revenue: None,
+        projected_next_year: None,
+    };
+    let Some(launch) = launch_date else {
+        return Ok(curve);
+    };
+
+    // Only complete weeks, so the newest one doesn't look like a drop
+    let last_day =
+        queries::last_complete_day(None).unwrap_or_else(|| queries::reporting_today().add_days(-1));
+    let complete_weeks = (last_day.days_since(launch) + 1).max(0) / 7;
+    if complete_weeks == 0 {
+        return Ok(curve);
+    }
+    let end = launch.add_days(complete_weeks * 7 - 1);
+
+    let mut revenue_by_week = vec![0.0; complete_weeks as usize];
+    let mut stmt = conn.prepare(&format!(
+        "SELECT CAST((julianday(date) - julianday(?2)) / 7 AS INTEGER) AS week,
+            COALESCE(SUM(CAST(gross_sales_usd AS REAL)), 0)
+         FROM sales_data
+         WHERE {} = ?1 AND date >= ?2 AND date <= ?3
+         GROUP BY week",
+        app_id_col
+    ))?;
+    let rows = stmt.query_map(rusqlite::params![app_id, launch, end], |r| {
+        Ok((r.get::<_, i64>(0)?, r.get::<_, f64>(1)?))
+    })?;
+    for row in rows {
+        let (week, revenue) = row?;
+        if let Some(slot) = usize::try_from(week)
+            .ok()
+            .and_then(|w| revenue_by_week.get_mut(w))
+        {
+            *slot += revenue;
+        }
+    }
+
+    curve.lifetime_revenue = revenue_by_week.iter().sum();
+    let mut cumulative = 0.0;
+    for (week, revenue) in revenue_by_week.iter().enumerate() {
+        cumulative += revenue;
+        if cumulative >= curve.lifetime_revenue / 2.0 && curve.lifetime_revenue > 0.0 {
+            curve.weeks_to_half_revenue = Some(week as u32 + 1);
+            break;
+        }
+    }
+    curve.weeks = revenue_by_week
+        .into_iter()
+        .enumerate()
+        .map(|(week, revenue)| DecayWeek {
+            week: week as u32,
+            revenue,
+            fitted: None,
+        })
+        .collect();
+
+    curve.fit = fit_power_law(&curve.weeks);
+    if let Some(fit) = &curve.fit {
+        for week in curve.weeks.iter_mut().filter(|w| w.week > 0) {
+            week.fitted = Some(fitted(fit, week.week));
+        }
+        let latest = complete_weeks as u32 - 1;
+        // a * (t + h)^-b = a * t^-b / 2  =>  h = t * (2^(1/b) - 1); no halving without decay
+        if fit.exponent > 0.0 && latest > 0 {
+            curve.current_half_life_weeks =
+                Some(latest as f64 * (2f64.powf(1.0 / fit.exponent) - 1.0));
+        }
+        curve.projected_next_year = Some(
+            (latest + 1..=latest + PROJECTION_WEEKS)
+                .map(|week| fitted(fit, week))
+                .sum(),
+        );
+    }
+    Ok(curve)
+}
+
+// ==================== Tauri Commands ====================
+
+/// Weekly revenue of an app since launch with a fitted power-law decay curve.
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn query_decay_curve(appId: i64) -> AppResult<DecayCurve> {
+    tokio::task::spawn_blocking(move || get_decay_curve(appId))
+        .await?
+        .and_then(presentation::present)
+}
@@ -0,0 +1,243 @@
+// steamsales:// links
+//
+// Links like steamsales://app/440?range=30d (from alert notifications or report emails) are
+// turned into a `NavigationRequest` and emitted as `deep-link-navigate`. A link that opened the
+// app arrives before the frontend listens, so the last one is also kept until the frontend asks
+// for it with `take_pending_deep_link`.
+//
+//   steamsales://dashboard
+//   steamsales://app/<app id>          steamsales://package/<package id>
+//   steamsales://raw[?date=YYYY-MM-DD]  steamsales://launches
+//
+// Every route accepts `range` (e.g. 7d, 30d, 365d or all) or `from` / `to` dates. A range is
+// turned into dates ending today in the reporting timezone before it is emitted, so the frontend
+// only applies dates; `from` / `to` take precedence over it.
+
+use crate::annotations::is_valid_date;
+use crate::queries;
+use crate::tray;
+use crate::types::{AppError, AppResult, Date};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, Url};
+use tauri_plugin_deep_link::DeepLinkExt;
+use tracing::{info, warn};
+
+pub const SCHEME: &str = "steamsales";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NavigationRequest {
+    /// Dashboard tab to show
+    pub tab: String,
+    pub app_id: Option<i64>,
+    pub package_id: Option<i64>,
+    /// Relative range like "30d", or "all" for no date filter
+    pub range: Option<String>,
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    /// The link as received
+    pub url: String,
+}
+
+/// The most recent link the frontend has not picked up yet.
+#[derive(Default)]
+pub struct PendingDeepLink(Mutex<Option<NavigationRequest>>);
+
+fn parse_id(segment: Option<&str>, what: &str) -> AppResult<i64> {
+    segment
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| AppError::InvalidInput(format!("Link is missing a valid {} id", what)))
+}
+
+// Days of a range like "30d"; None for "all" or anything else
+fn range_days(range: &str) -> Option<u32> {
+    range
+        .strip_suffix('d')
+        .and_then(|days| days.parse::<u32>().ok())
+        .filter(|&days| days > 0)
+}
+
+fn is_valid_range(range: &str) -> bool {
+    range == "all" || range_days(range).is_some()
+}
+
+impl NavigationRequest {
+    /// Sets the dates of a "30d" range to the 30 days up to `today`. Dates given in the link
+    /// are kept.
+    pub fn resolve_range(&mut self, today: Date) {
+        let Some(days) = self.range.as_deref().and_then(range_days) else {
+            return;
+        };
+        if self.start_date.is_none() {
+            self.start_date = Some(today.add_days(1 - i64::from(days)).to_string());
+        }
+        if self.end_date.is_none() {
+            self.end_date = Some(today.to_string());
+        }
+    }
+}
+
+/// Maps a steamsales:// link to the view it points at.
+pub fn route(url: &Url) -> AppResult<NavigationRequest> {
+    if url.scheme() != SCHEME {
+        return Err(AppError::InvalidInput(format!(
+            "Not a {}:// link: {}",
+            SCHEME, url
+        )));
+    }
+    let mut segments = url
+        .path_segments()
+        .map(|s| s.filter(|s| !s.is_empty()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    // steamsales://app/440 has "app" as its host; steamsales:/app/440 has it in the path
+    let view = match url.host_str() {
+        Some(host) => host.to_string(),
+        None if !segments.is_empty() => segments.remove(0).to_string(),
+        None => "dashboard".to_string(),
+    };
+
+    let mut request = NavigationRequest {
+        tab: "charts".to_string(),
+        app_id: None,
+        package_id: None,
+        range: None,
+        start_date: None,
+        end_date: None,
+        url: url.to_string(),
+    };
+    match view.as_str() {
+        "" | "dashboard" => {}
+        "app" => {
+            request.tab = "packageMetrics".to_string();
+            request.app_id = Some(parse_id(segments.first().copied(), "app")?);
+        }
+        "package" => {
+            request.tab = "packageMetrics".to_string();
+            request.package_id = Some(parse_id(segments.first().copied(), "package")?);
+        }
+        "raw" => request.tab = "rawDataBrowser".to_string(),
+        "launches" => request.tab = "launchComparison".to_string(),
+        other => {
+            return Err(AppError::InvalidInput(format!(
+                "Unknown link target '{}'",
+                other
+            )))
+        }
+    }
+
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "range" if is_valid_range(&value) => request.range = Some(value.to_string()),
+            "from" if is_valid_date(&value) => request.start_date = Some(value.to_string()),
+            "to" if is_valid_date(&value) => request.end_date = Some(value.to_string()),
+            // A single day: the raw data browser's date
+            "date" if is_valid_date(&value) => {
+                request.start_date = Some(value.to_string());
+                request.end_date = Some(value.to_string());
+            }
+            _ => warn!("Ignoring link parameter {}={}", key, value),
+        }
+    }
+    Ok(request)
+}
+
+pub(crate) fn handle_urls(app: &AppHandle, urls: Vec<Url>) {
+    // Several links at once are possible (macOS); the last one wins
+    let Some(mut request) = urls.iter().rev().find_map(|url| match route(url) {
+        Ok(request) => Some(request),
+        Err(e) => {
+            warn!("Ignoring link {}: {}", url, e);
+            None
+        }
+    }) else {
+        return;
+    };
+    request.resolve_range(queries::reporting_today());
+    info!("Opening link {}", request.url);
+    *app.state::<PendingDeepLink>().0.lock().unwrap() = Some(request.clone());
+    // Extra view windows keep their own view
+    let _ = app.emit_to("main", "deep-link-navigate", &request);
+    tray::show_main_window(app);
+}
+
+/// Starts listening for links. Call from `setup`, after the deep-link plugin is registered.
+pub fn init(app: &AppHandle) {
+    app.manage(PendingDeepLink::default());
+
+    // Linux and Windows only know about the scheme once it is registered at runtime in dev
+    // builds; installers register it for release builds
+    #[cfg(all(debug_assertions, any(target_os = "linux", windows)))]
+    if let Err(e) = app.deep_link().register_all() {
+        warn!("Failed to register {}:// links: {}", SCHEME, e);
+    }
+
+    let handle = app.clone();
+    app.deep_link()
+        .on_open_url(move |event| handle_urls(&handle, event.urls()));
+    match app.deep_link().get_current() {
+        Ok(Some(urls)) => handle_urls(app, urls),
+        Ok(None) => {}
+        Err(e) => warn!("Failed to read the link the app was opened with: {}", e),
+    }
+}
+
+// ==================== Tauri Commands ====================
+
+/// The link that opened the app, if the frontend has not handled it yet.
+#[tauri::command]
+pub async fn take_pending_deep_link(
+    state: tauri::State<'_, PendingDeepLink>,
+) -> AppResult<Option<NavigationRequest>> {
+    Ok(state.0.lock().unwrap().take())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route_str(link: &str) -> AppResult<NavigationRequest> {
+        route(&Url::parse(link).unwrap())
+    }
+
+    #[test]
+    fn app_link_with_range_selects_the_app_and_its_dates() {
+        let mut request = route_str("steamsales://app/440?range=30d").unwrap();
+        request.resolve_range("2024-03-31".parse().unwrap());
+        assert_eq!(request.tab, "packageMetrics");
+        assert_eq!(request.app_id, Some(440));
+        assert_eq!(request.package_id, None);
+        assert_eq!(request.range.as_deref(), Some("30d"));
+        assert_eq!(request.start_date.as_deref(), Some("2024-03-02"));
+        assert_eq!(request.end_date.as_deref(), Some("2024-03-31"));
+    }
+
+    #[test]
+    fn link_dates_take_precedence_over_the_range() {
+        let mut request =
+            route_str("steamsales://package/12?range=7d&from=2024-01-01&to=2024-01-31").unwrap();
+        request.resolve_range("2024-03-31".parse().unwrap());
+        assert_eq!(request.package_id, Some(12));
+        assert_eq!(request.start_date.as_deref(), Some("2024-01-01"));
+        assert_eq!(request.end_date.as_deref(), Some("2024-01-31"));
+
+        let mut request = route_str("steamsales://raw?date=2024-02-29&range=all").unwrap();
+        request.resolve_range("2024-03-31".parse().unwrap());
+        assert_eq!(request.tab, "rawDataBrowser");
+        assert_eq!(request.start_date.as_deref(), Some("2024-02-29"));
+        assert_eq!(request.end_date.as_deref(), Some("2024-02-29"));
+    }
+
+    #[test]
+    fn invalid_links_and_parameters() {
+        assert!(route_str("steamsales://app/abc").is_err());
+        assert!(route_str("steamsales://nowhere").is_err());
+        assert!(route_str("https://example.com/app/440").is_err());
+
+        let mut request = route_str("steamsales://dashboard?range=0d&from=2024-13-01").unwrap();
+        request.resolve_range("2024-03-31".parse().unwrap());
+        assert_eq!(request.range, None);
+        assert_eq!(request.start_date, None);
+        assert_eq!(request.end_date, None);
+    }
+}
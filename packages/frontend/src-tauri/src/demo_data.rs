@@ -0,0 +1,546 @@
+// Demo data
+//
+// `generate_demo_data` adds a data source filled with made-up sales, so the dashboard can be
+// explored before an API key is connected and queries can be timed on data of a known shape.
+// The rows follow the shapes real sales have: a launch spike decaying into a long tail, more
+// sales at weekends, discounts during Steam's seasonal sales that get deeper as a game ages,
+// refunds (more in launch week), regional prices and VAT, three platforms, a deluxe edition and
+// retail key activations. The games of a profile launch one after another over its range.
+//
+// Generation is deterministic: the same profile and end date give the same rows, so timings
+// taken on them can be compared. The database has the tables of a CLI database the queries
+// read (sales_data, lookup_apps, lookup_packages) and is added as a read-only source like an
+// imported share bundle, so it is never fetched into. Product ids start at 9,000,000, far above
+// Steam's, so demo products can't be taken for real ones.
+
+use crate::settings;
+use crate::sources::{self, DataSource};
+use crate::types::{AppError, AppResult, Date};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+use std::fs;
+use std::path::Path;
+use tracing::info;
+
+const SEED: u64 = 0x5EED_DA7A_5A1E_5000;
+const PARTNER_ID: i64 = 1000;
+const FIRST_APP_ID: i64 = 9_000_000;
+// Share of a game's units sold as its deluxe edition, which costs half as much again
+const DELUXE_SHARE: f64 = 0.15;
+const DELUXE_PRICE_FACTOR: f64 = 1.5;
+// Retail keys of the first game activated each Monday, on average
+const WEEKLY_ACTIVATIONS: f64 = 3.0;
+
+// (name, price in USD)
+const GAMES: &[(&str, f64)] = &[
+    ("Starfall Tactics", 19.99),
+    ("Hollow Lantern", 14.99),
+    ("Tidebound", 24.99),
+    ("Pocket Foundry", 9.99),
+    ("Ashen Crown", 29.99),
+    ("Little Orbit", 4.99),
+    ("Deepwater Signal", 17.99),
+    ("Copper Valley", 12.99),
+    ("Nightrail", 19.99),
+    ("Glasswing", 7.99),
+    ("Ironbloom", 24.99),
+    ("Quiet Harbor", 11.99),
+];
+
+// (country, share of sales, price relative to the US, VAT rate, currency), biggest markets
+// first; a profile takes the first few
+const COUNTRIES: &[(&str, f64, f64, f64, &str)] = &[
+    ("US", 0.30, 1.00, 0.00, "USD"),
+    ("DE", 0.08, 1.00, 0.19, "EUR"),
+    ("GB", 0.06, 0.95, 0.20, "GBP"),
+    ("CN", 0.06, 0.45, 0.00, "CNY"),
+    ("FR", 0.05, 1.00, 0.20, "EUR"),
+    ("CA", 0.04, 0.85, 0.05, "CAD"),
+    ("RU", 0.04, 0.40, 0.20, "RUB"),
+    ("BR", 0.03, 0.45, 0.00, "BRL"),
+    ("AU", 0.03, 0.90, 0.10, "AUD"),
+    ("JP", 0.03, 0.85, 0.10, "JPY"),
+    ("PL", 0.02, 0.70, 0.23, "PLN"),
+    ("KR", 0.02, 0.75, 0.10, "KRW"),
+    ("ES", 0.02, 1.00, 0.21, "EUR"),
+    ("IT", 0.02, 1.00, 0.22, "EUR"),
+    ("NL", 0.015, 1.00, 0.21, "EUR"),
+    ("SE", 0.015, 1.00, 0.25, "SEK"),
+    ("TR", 0.012, 0.30, 0.20, "USD"),
+    ("UA", 0.01, 0.35, 0.20, "UAH"),
+    ("MX", 0.01, 0.50, 0.16, "MXN"),
+    ("AR", 0.01, 0.30, 0.21, "USD"),
+    ("IN", 0.01, 0.35, 0.18, "INR"),
+    ("CH", 0.008, 1.05, 0.081, "CHF"),
+    ("NO", 0.008, 1.05, 0.25, "NOK"),
+    ("DK", 0.007, 1.00, 0.25, "EUR"),
+    ("FI", 0.007, 1.00, 0.24, "EUR"),
+    ("AT", 0.007, 1.00, 0.20, "EUR"),
+    ("BE", 0.007, 1.00, 0.21, "EUR"),
+    ("CZ", 0.006, 0.75, 0.21, "EUR"),
+    ("NZ", 0.005, 0.85, 0.15, "NZD"),
+    ("SG", 0.005, 0.75, 0.09, "SGD"),
+    ("HK", 0.005, 0.70, 0.00, "HKD"),
+    ("TW", 0.005, 0.65, 0.05, "TWD"),
+    ("TH", 0.004, 0.50, 0.07, "THB"),
+    ("PH", 0.004, 0.45, 0.12, "PHP"),
+    ("ID", 0.004, 0.40, 0.11, "IDR"),
+    ("ZA", 0.003, 0.55, 0.15, "ZAR"),
+    ("CL", 0.003, 0.50, 0.19, "CLP"),
+    ("PT", 0.003, 0.90, 0.23, "EUR"),
+    ("IL", 0.003, 0.85, 0.17, "ILS"),
+    ("SA", 0.003, 0.65, 0.15, "SAR"),
+];
+
+const PLATFORMS: &[(&str, f64)] = &[("Windows", 0.88), ("Linux", 0.07), ("Mac", 0.05)];
+
+// (month, day of the month)
+type MonthDay = (u32, u32);
+
+// Steam's seasonal sales as first and last day; the winter sale runs into January
+const SEASONAL_SALES: &[(MonthDay, MonthDay)] = &[
+    ((3, 14), (3, 21)),
+    ((6, 26), (7, 10)),
+    ((11, 26), (12, 3)),
+    ((12, 19), (1, 2)),
+];
+
+/// How much demo data to generate.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DemoProfile {
+    /// Two games over a year in ten countries
+    Small,
+    /// Four games over three years in twenty countries
+    #[default]
+    Standard,
+    /// Twelve games over five years in forty countries, about 430,000 rows, for timing queries
+    Large,
+}
+
+struct Shape {
+    games: usize,
+    days: i64,
+    countries: usize,
+    // Units the first game sells on launch day, before noise
+    launch_units: f64,
+}
+
+impl DemoProfile {
+    fn shape(self) -> Shape {
+        match self {
+            DemoProfile::Small => Shape {
+                games: 2,
+                days: 365,
+                countries: 10,
+                launch_units: 400.0,
+            },
+            DemoProfile::Standard => Shape {
+                games: 4,
+                days: 3 * 365,
+                countries: 20,
+                launch_units: 1500.0,
+            },
+            DemoProfile::Large => Shape {
+                games: 12,
+                days: 5 * 365,
+                countries: COUNTRIES.len(),
+                launch_units: 6000.0,
+            },
+        }
+    }
+
+    pub fn parse(profile: &str) -> AppResult<Self> {
+        match profile {
+            "small" => Ok(DemoProfile::Small),
+            "standard" => Ok(DemoProfile::Standard),
+            "large" => Ok(DemoProfile::Large),
+            other => Err(AppError::InvalidInput(format!(
+                "Unknown profile '{}', expected small, standard or large",
+                other
+            ))),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DemoProfile::Small => "small",
+            DemoProfile::Standard => "standard",
+            DemoProfile::Large => "large",
+        }
+    }
+}
+
+// SplitMix64: tiny, and gives the same numbers everywhere, which is all demo data needs
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in [0, 1)
+    fn uniform(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // A Poisson-distributed count with the given mean
+    fn poisson(&mut self, mean: f64) -> i64 {
+        if mean <= 0.0 {
+            return 0;
+        }
+        if mean < 30.0 {
+            // Knuth's: multiply uniforms until the product drops below e^-mean
+            let limit = (-mean).exp();
+            let mut product = self.uniform();
+            let mut count = 0;
+            while product > limit {
+                product *= self.uniform();
+                count += 1;
+            }
+            count
+        } else {
+            // Close enough to a normal distribution at this size (Box-Muller)
+            let gauss =
+                (-2.0 * (1.0 - self.uniform()).ln()).sqrt() * (2.0 * PI * self.uniform()).cos();
+            (mean + mean.sqrt() * gauss).round().max(0.0) as i64
+        }
+    }
+}
+
+struct Package {
+    id: i64,
+    name: String,
+    price: f64,
+    // Share of the game's units
+    share: f64,
+}
+
+struct Game {
+    app_id: i64,
+    name: &'static str,
+    launch: Date,
+    // Relative to the first game
+    popularity: f64,
+    packages: Vec<Package>,
+    // Package of the retail keys; only the first game has them
+    retail_package: Option<i64>,
+}
+
+fn games(shape: &Shape, start: Date) -> Vec<Game> {
+    // The last game still gets three months of sales
+    let launch_spacing = (shape.days - 90).max(0) / shape.games as i64;
+    GAMES
+        .iter()
+        .take(shape.games)
+        .enumerate()
+        .map(|(i, &(name, price))| {
+            let app_id = FIRST_APP_ID + 10 * (i as i64 + 1);
+            let package_id = FIRST_APP_ID + 1000 + 10 * (i as i64 + 1);
+            let mut packages = vec![Package {
+                id: package_id,
+                name: name.to_string(),
+                price,
+                share: 1.0,
+            }];
+            if i == 0 {
+                packages[0].share = 1.0 - DELUXE_SHARE;
+                packages.push(Package {
+                    id: package_id + 1,
+                    name: format!("{} Deluxe Edition", name),
+                    price: price * DELUXE_PRICE_FACTOR,
+                    share: DELUXE_SHARE,
+                });
+            }
+            Game {
+                app_id,
+                name,
+                launch: start.add_days(i as i64 * launch_spacing),
+                popularity: 1.0 / (1.0 + 0.5 * i as f64),
+                packages,
+                retail_package: (i == 0).then_some(package_id + 2),
+            }
+        })
+        .collect()
+}
+
+fn within(day: MonthDay, first: MonthDay, last: MonthDay) -> bool {
+    if first <= last {
+        first <= day && day <= last
+    } else {
+        day >= first || day <= last
+    }
+}
+
+// Discount in percent of a game `age` days after its launch: none in the first month, then in
+// Steam's seasonal sales and a week of its own each quarter, deeper the older the game is
+fn discount(game: usize, age: i64, date: Date) -> u32 {
+    if age < 30 {
+        return 0;
+    }
+    let depth = match age {
+        ..=180 => 10,
+        181..=365 => 25,
+        366..=730 => 40,
+        _ => 60,
+    };
+    let seasonal = SEASONAL_SALES
+        .iter()
+        .any(|&(first, last)| within(date.month_day(), first, last));
+    let own_week = (age + 17 * game as i64) % 91 < 7;
+    if seasonal || own_week {
+        depth
+    } else {
+        0
+    }
+}
+
+// Units a game is expected to sell in a day: a launch spike decaying into a tail of about a
+// tenth of launch sales, more at weekends and during discounts
+fn expected_units(launch_units: f64, game: &Game, age: i64, date: Date, discount: u32) -> f64 {
+    let age = age as f64;
+    // 5.0 on launch day
+    let lifecycle = 0.12 + 0.88 * (-age / 45.0).exp() + 4.0 * (-age / 4.0).exp();
+    let weekday = match date.weekday() {
+        5 | 6 => 1.3,
+        4 => 1.1,
+        _ => 0.9,
+    };
+    let discount = 1.0 + 8.0 * (discount as f64 / 100.0).powf(1.5);
+    launch_units * game.popularity * lifecycle / 5.0 * weekday * discount
+}
+
+fn usd(amount: f64) -> String {
+    // No "-0.00"
+    format!("{:.2}", amount + 0.0)
+}
+
+fn create_schema(conn: &Connection) -> rusqlite::Result<()> {
+    // A failed generation deletes the file anyway
+    conn.pragma_update(None, "journal_mode", "OFF")?;
+    conn.pragma_update(None, "synchronous", "OFF")?;
+    conn.execute_batch(
+        "CREATE TABLE sales_data (
+            date TEXT NOT NULL,
+            line_item_type TEXT,
+            partnerid INTEGER,
+            primary_appid INTEGER,
+            appid INTEGER,
+            packageid INTEGER,
+            bundleid INTEGER,
+            package_sale_type TEXT,
+            key_request_id INTEGER,
+            platform TEXT,
+            country_code TEXT,
+            base_price TEXT,
+            sale_price TEXT,
+            currency TEXT,
+            gross_units_sold INTEGER,
+            gross_units_returned INTEGER,
+            gross_sales_usd TEXT,
+            gross_returns_usd TEXT,
+            net_tax_usd TEXT,
+            gross_units_activated INTEGER,
+            view_grant_partnerid INTEGER,
+            net_units_sold INTEGER,
+            net_sales_usd TEXT,
+            avg_sale_price_usd TEXT,
+            total_discount_percentage INTEGER,
+            discount_percentage INTEGER
+         );
+         CREATE TABLE lookup_apps (appid INTEGER PRIMARY KEY, app_name TEXT);
+         CREATE TABLE lookup_packages (packageid INTEGER PRIMARY KEY, package_name TEXT);",
+    )
+}
+
+// Fills an empty database with the sales of `profile` ending on `end`; returns the row count
+fn fill(conn: &mut Connection, profile: DemoProfile, end: Date) -> rusqlite::Result<u64> {
+    let shape = profile.shape();
+    let start = end.add_days(1 - shape.days);
+    let games = games(&shape, start);
+    let countries = &COUNTRIES[..shape.countries];
+    let country_total: f64 = countries.iter().map(|c| c.1).sum();
+    let mut rng = Rng(SEED ^ profile as u64);
+
+    create_schema(conn)?;
+    let tx = conn.transaction()?;
+    for game in &games {
+        tx.execute(
+            "INSERT INTO lookup_apps (appid, app_name) VALUES (?1, ?2)",
+            params![game.app_id, game.name],
+        )?;
+        for package in &game.packages {
+            tx.execute(
+                "INSERT INTO lookup_packages (packageid, package_name) VALUES (?1, ?2)",
+                params![package.id, package.name],
+            )?;
+        }
+        if let Some(retail) = game.retail_package {
+            tx.execute(
+                "INSERT INTO lookup_packages (packageid, package_name) VALUES (?1, ?2)",
+                params![retail, format!("{} Retail Key", game.name)],
+            )?;
+        }
+    }
+
+    let mut rows = 0;
+    {
+        let mut insert_sale = tx.prepare(
+            "INSERT INTO sales_data (
+                date, line_item_type, partnerid, primary_appid, appid, packageid, bundleid,
+                package_sale_type, key_request_id, platform, country_code, base_price, sale_price,
+                currency, gross_units_sold, gross_units_returned, gross_sales_usd,
+                gross_returns_usd, net_tax_usd, gross_units_activated, view_grant_partnerid,
+                net_units_sold, net_sales_usd, avg_sale_price_usd, total_discount_percentage
+             ) VALUES (?1, 'Package', ?2, ?3, 0, ?4, 0, 'Steam', 0, ?5, ?6, ?7, ?8, ?9, ?10,
+                       ?11, ?12, ?13, ?14, 0, 0, ?15, ?16, ?8, ?17)",
+        )?;
+        let mut insert_activation = tx.prepare(
+            "INSERT INTO sales_data (
+                date, line_item_type, partnerid, primary_appid, appid, packageid, bundleid,
+                package_sale_type, key_request_id, platform, country_code, currency,
+                gross_units_sold, gross_units_returned, gross_units_activated,
+                view_grant_partnerid, net_units_sold
+             ) VALUES (?1, 'Package', ?2, ?3, 0, ?4, 0, 'Retail', 1, '', 'DE', 'EUR', 0, 0, ?5,
+                       0, 0)",
+        )?;
+
+        for day in 0..shape.days {
+            let date = start.add_days(day);
+            for (index, game) in games.iter().enumerate() {
+                let age = date.days_since(game.launch);
+                if age < 0 {
+                    continue;
+                }
+                let discount = discount(index, age, date);
+                let units = expected_units(shape.launch_units, game, age, date, discount);
+                let refund_rate = if age < 14 { 0.07 } else { 0.04 };
+                for package in &game.packages {
+                    for &(country, share, price_factor, vat, currency) in countries {
+                        let list_price = package.price * price_factor;
+                        let sale_price = list_price * (1.0 - discount as f64 / 100.0);
+                        for &(platform, platform_share) in PLATFORMS {
+                            let mean =
+                                units * package.share * share / country_total * platform_share;
+                            let sold = rng.poisson(mean);
+                            // Refunds of earlier days' purchases too, so some rows are returns only
+                            let returned = rng.poisson(mean * refund_rate);
+                            if sold == 0 && returned == 0 {
+                                continue;
+                            }
+                            let gross = sold as f64 * sale_price;
+                            let returns = -(returned as f64 * sale_price);
+                            let net = gross + returns;
+                            insert_sale.execute(params![
+                                date,
+                                PARTNER_ID,
+                                game.app_id,
+                                package.id,
+                                platform,
+                                country,
+                                format!("{:.0}", list_price * 100.0),
+                                usd(sale_price),
+                                currency,
+                                sold,
+                                returned,
+                                usd(gross),
+                                usd(returns),
+                                usd(net * vat / (1.0 + vat)),
+                                sold - returned,
+                                usd(net),
+                                (discount > 0).then_some(discount),
+                            ])?;
+                            rows += 1;
+                        }
+                    }
+                }
+                if let Some(retail) = game.retail_package.filter(|_| date.weekday() == 0) {
+                    let activated = rng.poisson(WEEKLY_ACTIVATIONS);
+                    if activated > 0 {
+                        insert_activation.execute(params![
+                            date,
+                            PARTNER_ID,
+                            game.app_id,
+                            retail,
+                            activated
+                        ])?;
+                        rows += 1;
+                    }
+                }
+            }
+        }
+    }
+    tx.execute_batch(
+        "CREATE INDEX idx_sales_data_date ON sales_data (date);
+         CREATE INDEX idx_sales_data_app ON sales_data (primary_appid, date);",
+    )?;
+    tx.commit()?;
+    Ok(rows)
+}
+
+/// Generates the demo data of `profile` ending on `end` into a new database at `path` and returns
+/// its row count. The file is removed again if that fails.
+pub fn generate_database(profile: DemoProfile, end: Date, path: &Path) -> AppResult<u64> {
+    let filled = Connection::open(path).and_then(|mut conn| fill(&mut conn, profile, end));
+    match filled {
+        Ok(rows) => {
+            info!(
+                "Generated {} rows of {} demo data up to {}",
+                rows,
+                profile.label(),
+                end
+            );
+            Ok(rows)
+        }
+        Err(e) => {
+            let _ = fs::remove_file(path);
+            Err(e.into())
+        }
+    }
+}
+
+/// Generates the demo data of `profile` into a new database and adds it as a read-only data
+/// source named `name`.
+pub fn generate(profile: DemoProfile, name: Option<&str>, end: Date) -> AppResult<DataSource> {
+    let name = match name.map(str::trim).filter(|n| !n.is_empty()) {
+        Some(name) => name.to_string(),
+        None => format!("Demo ({})", profile.label()),
+    };
+    let db_path = sources::managed_database_path(&name);
+    if db_path.exists() {
+        return Err(AppError::InvalidInput(
+            "A data source with this name or database already exists".to_string(),
+        ));
+    }
+    if let Some(parent) = db_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    generate_database(profile, end, &db_path)?;
+    sources::insert_read_only_source(&name, &db_path.to_string_lossy()).inspect_err(|_| {
+        let _ = fs::remove_file(&db_path);
+    })
+}
+
+// ==================== Tauri Commands ====================
+
+/// Adds a read-only data source with made-up sales of `profile` ("standard" by default) ending
+/// on `endDate` (yesterday by default). It can be made active like any other source.
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn generate_demo_data(
+    profile: Option<DemoProfile>,
+    name: Option<String>,
+    endDate: Option<Date>,
+) -> AppResult<DataSource> {
+    let end = endDate.unwrap_or_else(|| {
+        Date::today_in(&settings::load_settings().reporting_timezone).add_days(-1)
+    });
+    tokio::task::spawn_blocking(move || generate(profile.unwrap_or_default(), name.as_deref(), end))
+        .await?
+}
@@ -0,0 +1,386 @@
+// CLI health check
+//
+// `diagnose_cli` runs every check that tends to explain a "fetch does nothing" report (binary
+// present and runnable, permissions, macOS quarantine, other copies on PATH, database readable,
+// disk space) and returns them all in one structured report, so users can paste it in a ticket.
+
+use crate::cli::{get_cli_binary_path, get_cli_dir, CLI_BINARY_NAME};
+use crate::database::get_database_path;
+use crate::types::AppResult;
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command as TokioCommand;
+use tokio::time::timeout;
+
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+// Free space below which fetches are likely to fail (warning) or will fail (error)
+const LOW_DISK_SPACE_BYTES: u64 = 500 * 1024 * 1024;
+const CRITICAL_DISK_SPACE_BYTES: u64 = 50 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CheckStatus {
+    Ok,
+    Warning,
+    Error,
+    Skipped,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    pub message: String,
+    pub details: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsReport {
+    pub platform: String,
+    pub arch: String,
+    pub binary_path: String,
+    pub database_path: String,
+    /// False if any check failed with an error
+    pub healthy: bool,
+    pub checks: Vec<DiagnosticCheck>,
+}
+
+fn check(name: &str, status: CheckStatus, message: impl Into<String>) -> DiagnosticCheck {
+    DiagnosticCheck {
+        name: name.to_string(),
+        status,
+        message: message.into(),
+        details: None,
+    }
+}
+
+fn with_details(mut check: DiagnosticCheck, details: impl Into<String>) -> DiagnosticCheck {
+    let details = details.into();
+    if !details.trim().is_empty() {
+        check.details = Some(details.trim().to_string());
+    }
+    check
+}
+
+// Runs the CLI with a timeout; returns (success, stdout, stderr)
+async fn run_cli(binary_path: &Path, args: &[&str]) -> Result<(bool, String, String), String> {
+    let output = timeout(
+        COMMAND_TIMEOUT,
+        TokioCommand::new(binary_path)
+            .args(args)
+            .stdin(Stdio::null())
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .map_err(|_| format!("timed out after {} seconds", COMMAND_TIMEOUT.as_secs()))?
+    .map_err(|e| e.to_string())?;
+    Ok((
+        output.status.success(),
+        String::from_utf8_lossy(&output.stdout).to_string(),
+        String::from_utf8_lossy(&output.stderr).to_string(),
+    ))
+}
+
+#[cfg(unix)]
+fn check_permissions(binary_path: &Path) -> DiagnosticCheck {
+    use std::os::unix::fs::PermissionsExt;
+    match std::fs::metadata(binary_path) {
+        Ok(meta) if meta.permissions().mode() & 0o111 != 0 => check(
+            "permissions",
+            CheckStatus::Ok,
+            format!("Executable (mode {:o})", meta.permissions().mode() & 0o777),
+        ),
+        Ok(meta) => check(
+            "permissions",
+            CheckStatus::Error,
+            format!(
+                "Binary is not executable (mode {:o}); reinstall the CLI",
+                meta.permissions().mode() & 0o777
+            ),
+        ),
+        Err(e) => check("permissions", CheckStatus::Error, e.to_string()),
+    }
+}
+
+#[cfg(not(unix))]
+fn check_permissions(_binary_path: &Path) -> DiagnosticCheck {
+    check(
+        "permissions",
+        CheckStatus::Skipped,
+        "Not applicable on this platform",
+    )
+}
+
+#[cfg(target_os = "macos")]
+fn check_quarantine(binary_path: &Path) -> DiagnosticCheck {
+    let output = std::process::Command::new("xattr")
+        .args(["-p", "com.apple.quarantine"])
+        .arg(binary_path)
+        .output();
+    match output {
+        Ok(output) if output.status.success() => with_details(
+            check(
+                "quarantine",
+                CheckStatus::Error,
+                "Binary is quarantined by Gatekeeper and will be killed on launch; reinstall the CLI",
+            ),
+            String::from_utf8_lossy(&output.stdout),
+        ),
+        Ok(_) => check("quarantine", CheckStatus::Ok, "Not quarantined"),
+        Err(e) => check(
+            "quarantine",
+            CheckStatus::Warning,
+            format!("Could not run xattr: {}", e),
+        ),
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn check_quarantine(_binary_path: &Path) -> DiagnosticCheck {
+    check("quarantine", CheckStatus::Skipped, "Only applies to macOS")
+}
+
+// Other copies of the CLI on PATH, which users may be running by hand instead of the app's copy
+fn check_path_conflicts() -> DiagnosticCheck {
+    let file_name = if cfg!(windows) {
+        format!("{}.exe", CLI_BINARY_NAME)
+    } else {
+        CLI_BINARY_NAME.to_string()
+    };
+    let own_dir = get_cli_dir();
+    let others: Vec<PathBuf> = std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|dir| dir != &own_dir)
+        .map(|dir| dir.join(&file_name))
+        .filter(|candidate| candidate.is_file())
+        .collect();
+
+    if others.is_empty() {
+        check(
+            "pathConflicts",
+            CheckStatus::Ok,
+            "No other copies of the CLI on PATH",
+        )
+    } else {
+        let list = others
+            .iter()
+            .map(|p| p.display().to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        with_details(
+            check(
+                "pathConflicts",
+                CheckStatus::Warning,
+                format!(
+                    "{} other cop{} of the CLI on PATH; running it by hand may use a different version than the app",
+                    others.len(),
+                    if others.len() == 1 { "y" } else { "ies" }
+                ),
+            ),
+            list,
+        )
+    }
+}
+
+fn check_database(db_path: &Path) -> DiagnosticCheck {
+    if !db_path.exists() {
+        return check(
+            "database",
+            CheckStatus::Warning,
+            "Database does not exist yet; initialize the CLI and fetch data",
+        );
+    }
+    let conn = match Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY) {
+        Ok(conn) => conn,
+        Err(e) => {
+            return check(
+                "database",
+                CheckStatus::Error,
+                format!("Cannot open database: {}", e),
+            )
+        }
+    };
+    let summary = conn.query_row("SELECT COUNT(*), MAX(date) FROM sales_data", [], |r| {
+        Ok((r.get::<_, i64>(0)?, r.get::<_, Option<String>>(1)?))
+    });
+    match summary {
+        Ok((rows, latest)) => check(
+            "database",
+            CheckStatus::Ok,
+            format!(
+                "Readable: {} sales rows, latest date {}",
+                rows,
+                latest.as_deref().unwrap_or("none")
+            ),
+        ),
+        Err(e) => check(
+            "database",
+            CheckStatus::Error,
+            format!("Database is not readable: {}", e),
+        ),
+    }
+}
+
+// The CLI writes the database and its WAL files next to each other
+fn check_database_dir_writable(db_path: &Path) -> DiagnosticCheck {
+    let Some(dir) = db_path.parent() else {
+        return check(
+            "databaseDirectory",
+            CheckStatus::Error,
+            "Invalid database path",
+        );
+    };
+    if let Err(e) = std::fs::create_dir_all(dir) {
+        return check(
+            "databaseDirectory",
+            CheckStatus::Error,
+            format!("Cannot create {}: {}", dir.display(), e),
+        );
+    }
+    let probe = dir.join(format!(".write-test-{}", std::process::id()));
+    match std::fs::write(&probe, b"") {
+        Ok(()) => {
+            let _ = std::fs::remove_file(&probe);
+            check("databaseDirectory", CheckStatus::Ok, "Writable")
+        }
+        Err(e) => check(
+            "databaseDirectory",
+            CheckStatus::Error,
+            format!("{} is not writable: {}", dir.display(), e),
+        ),
+    }
+}
+
+fn check_disk_space(db_path: &Path) -> DiagnosticCheck {
+    let dir = db_path.parent().unwrap_or(db_path);
+    match fs4::available_space(dir) {
+        Ok(bytes) => {
+            let status = if bytes < CRITICAL_DISK_SPACE_BYTES {
+                CheckStatus::Error
+            } else if bytes < LOW_DISK_SPACE_BYTES {
+                CheckStatus::Warning
+            } else {
+                CheckStatus::Ok
+            };
+            check(
+                "diskSpace",
+                status,
+                format!("{} MB free", bytes / 1024 / 1024),
+            )
+        }
+        Err(e) => check(
+            "diskSpace",
+            CheckStatus::Warning,
+            format!("Could not determine free space: {}", e),
+        ),
+    }
+}
+
+pub async fn run_diagnostics() -> DiagnosticsReport {
+    let binary_path = get_cli_binary_path();
+    let db_path = get_database_path();
+    let db_path_str = db_path.to_string_lossy().to_string();
+    let mut checks = Vec::new();
+
+    let installed = binary_path.is_file();
+    checks.push(if installed {
+        check("binary", CheckStatus::Ok, "CLI binary found")
+    } else {
+        check(
+            "binary",
+            CheckStatus::Error,
+            "CLI binary not found; download it first",
+        )
+    });
+
+    if installed {
+        checks.push(check_permissions(&binary_path));
+        checks.push(check_quarantine(&binary_path));
+
+        checks.push(match run_cli(&binary_path, &["--version"]).await {
+            Ok((true, stdout, _)) => check("version", CheckStatus::Ok, stdout.trim()),
+            Ok((false, stdout, stderr)) => with_details(
+                check("version", CheckStatus::Error, "`--version` failed"),
+                format!("{}\n{}", stdout, stderr),
+            ),
+            Err(e) => check(
+                "version",
+                CheckStatus::Error,
+                format!("CLI could not be run: {}", e),
+            ),
+        });
+
+        // Parses the same global arguments a real fetch uses, without fetching anything
+        let args = [
+            "--db",
+            db_path_str.as_str(),
+            "--color",
+            "never",
+            "fetch",
+            "--help",
+        ];
+        checks.push(match run_cli(&binary_path, &args).await {
+            Ok((true, _, _)) => check(
+                "databaseCommand",
+                CheckStatus::Ok,
+                "CLI accepts the database arguments",
+            ),
+            Ok((false, stdout, stderr)) => with_details(
+                check(
+                    "databaseCommand",
+                    CheckStatus::Error,
+                    "CLI rejected the database arguments; it may be too old for this app",
+                ),
+                format!("{}\n{}", stdout, stderr),
+            ),
+            Err(e) => check(
+                "databaseCommand",
+                CheckStatus::Error,
+                format!("CLI could not be run: {}", e),
+            ),
+        });
+    } else {
+        for name in ["permissions", "quarantine", "version", "databaseCommand"] {
+            checks.push(check(name, CheckStatus::Skipped, "CLI not installed"));
+        }
+    }
+
+    let db_checks = tokio::task::spawn_blocking({
+        let db_path = db_path.clone();
+        move || {
+            vec![
+                check_path_conflicts(),
+                check_database(&db_path),
+                check_database_dir_writable(&db_path),
+                check_disk_space(&db_path),
+            ]
+        }
+    })
+    .await
+    .unwrap_or_default();
+    checks.extend(db_checks);
+
+    DiagnosticsReport {
+        platform: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        binary_path: binary_path.to_string_lossy().to_string(),
+        database_path: db_path_str,
+        healthy: checks.iter().all(|c| c.status != CheckStatus::Error),
+        checks,
+    }
+}
+
+// ==================== Tauri Commands ====================
+
+#[tauri::command]
+pub async fn diagnose_cli() -> AppResult<DiagnosticsReport> {
+    Ok(run_diagnostics().await)
+}
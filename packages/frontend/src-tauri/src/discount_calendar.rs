@@ -0,0 +1,234 @@
+// Historical discount calendar
+//
+// Steam's own UI only shows upcoming discounts. The past ones can be read back from the sales
+// rows: each day a package sold, its discount is the reported discount percentage, or else the
+// gap between sale_price and base_price. Consecutive days at the same depth form a discount
+// period; a day or two without sales in between doesn't end it, since a sale without buyers on
+// some day is still the same sale. `rebuild_discount_calendar` stores the periods in
+// `discount_periods` in the sales database, so they can also be joined in the SQL console. Once
+// built, the calendar is rebuilt after every fetch.
+
+use crate::database::{begin_write, BUSY_TIMEOUT};
+use crate::presentation;
+use crate::queries::{get_app_id_column, get_connection, get_write_connection};
+use crate::schema::{column_exists, first_existing_column, table_exists};
+use crate::types::{AppResult, Date};
+use rusqlite::{params, Connection, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// Days without sales that still continue a period at the same depth
+const MAX_GAP_DAYS: i64 = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscountPeriod {
+    pub package_id: i64,
+    pub app_id: Option<i64>,
+    pub start_date: Date,
+    /// Last day with sales at this depth
+    pub end_date: Date,
+    /// Whole percent off, 1-100
+    pub discount_percentage: i64,
+    pub gross_units: i64,
+    pub revenue: f64,
+}
+
+fn ensure_table(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS discount_periods (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            package_id INTEGER NOT NULL,
+            app_id INTEGER,
+            start_date TEXT NOT NULL,
+            end_date TEXT NOT NULL,
+            discount_percentage INTEGER NOT NULL,
+            gross_units INTEGER NOT NULL,
+            revenue REAL NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_discount_periods_app ON discount_periods(app_id);",
+    )
+}
+
+// SQL expression for a row's discount in whole percent, NULL if it can't be told
+fn discount_expr(conn: &Connection) -> Option<String> {
+    let reported = first_existing_column(
+        conn,
+        "sales_data",
+        &["total_discount_percentage", "discount_percentage"],
+    );
+    let from_prices = (column_exists(conn, "sales_data", "sale_price")
+        && column_exists(conn, "sales_data", "base_price"))
+    .then(|| {
+        "CASE WHEN CAST(base_price AS REAL) > 0
+            THEN 100 * (1 - CAST(sale_price AS REAL) / CAST(base_price AS REAL)) END"
+            .to_string()
+    });
+    let expr = match (reported, from_prices) {
+        (Some(column), Some(prices)) => format!("COALESCE({}, {})", column, prices),
+        (Some(column), None) => column,
+        (None, Some(prices)) => prices,
+        (None, None) => return None,
+    };
+    Some(format!("CAST(ROUND({}) AS INTEGER)", expr))
+}
+
+// (package id, app id, date, discount, gross units, revenue) of a package's day with sales
+type PackageDay = (i64, Option<i64>, Date, i64, i64, f64);
+
+// Merges the days of one package, in date order, into periods
+fn periods_of(days: &[PackageDay]) -> Vec<DiscountPeriod> {
+    let mut periods: Vec<DiscountPeriod> = Vec::new();
+    let mut open = false;
+    for &(package_id, app_id, date, discount, units, revenue) in days {
+        if discount <= 0 {
+            open = false;
+            continue;
+        }
+        match periods.last_mut() {
+            Some(period)
+                if open
+                    && period.discount_percentage == discount
+                    && date.days_since(period.end_date) <= MAX_GAP_DAYS + 1 =>
+            {
+                period.end_date = date;
+                period.gross_units += units;
+                period.revenue += revenue;
+            }
+            _ => {
+                periods.push(DiscountPeriod {
+                    package_id,
+                    app_id,
+                    start_date: date,
+                    end_date: date,
+                    discount_percentage: discount.min(100),
+                    gross_units: units,
+                    revenue,
+                });
+                open = true;
+            }
+        }
+    }
+    periods
+}
+
+/// Rebuilds `discount_periods` from sales_data and returns how many periods were found.
+pub fn rebuild(conn: &Connection) -> AppResult<usize> {
+    ensure_table(conn)?;
+    let tx = begin_write(conn)?;
+    tx.execute("DELETE FROM discount_periods", [])?;
+    let Some(discount) = discount_expr(&tx) else {
+        tx.commit()?;
+        return Ok(0);
+    };
+    let app_id_col = get_app_id_column(&tx);
+
+    // A day's depth is the largest one reported for the package that day; regional prices that
+    // round differently would otherwise split one sale into several
+    let sql = format!(
+        "SELECT packageid, MAX({app}), date, MAX(COALESCE({discount}, 0)),
+            COALESCE(SUM(gross_units_sold), 0), COALESCE(SUM(CAST(gross_sales_usd AS REAL)), 0)
+         FROM sales_data
+         WHERE packageid IS NOT NULL AND packageid != 0 AND gross_units_sold > 0
+         GROUP BY packageid, date
+         ORDER BY packageid, date",
+        app = app_id_col,
+        discount = discount
+    );
+    let days: Vec<PackageDay> = {
+        let mut stmt = tx.prepare(&sql)?;
+        let rows = stmt.query_map([], |r| {
+            Ok((
+                r.get(0)?,
+                r.get(1)?,
+                r.get(2)?,
+                r.get(3)?,
+                r.get(4)?,
+                r.get(5)?,
+            ))
+        })?;
+        // Rows with malformed dates are skipped rather than put in the wrong period
+        rows.flatten().collect()
+    };
+
+    let mut count = 0;
+    {
+        let mut insert = tx.prepare(
+            "INSERT INTO discount_periods
+                (package_id, app_id, start_date, end_date, discount_percentage, gross_units, revenue)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )?;
+        for package_days in days.chunk_by(|a, b| a.0 == b.0) {
+            for period in periods_of(package_days) {
+                insert.execute(params![
+                    period.package_id,
+                    period.app_id,
+                    period.start_date,
+                    period.end_date,
+                    period.discount_percentage,
+                    period.gross_units,
+                    period.revenue
+                ])?;
+                count += 1;
+            }
+        }
+    }
+    tx.commit()?;
+    Ok(count)
+}
+
+/// Rebuilds the calendar of the database at `db_path` if it was built before.
+pub fn rebuild_after_sync(db_path: &Path) -> AppResult<()> {
+    let conn = Connection::open(db_path)?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    if table_exists(&conn, "discount_periods") {
+        rebuild(&conn)?;
+    }
+    Ok(())
+}
+
+/// Discount periods of the packages of `app_id`, oldest first. Builds the calendar on first use.
+pub fn get_discount_calendar(app_id: i64) -> AppResult<Vec<DiscountPeriod>> {
+    let mut conn = get_connection()?;
+    if !table_exists(&conn, "discount_periods") {
+        // Built in the live database, which is then read too
+        conn = get_write_connection()?;
+        rebuild(&conn)?;
+    }
+    let mut stmt = conn.prepare(
+        "SELECT package_id, app_id, start_date, end_date, discount_percentage, gross_units, revenue
+         FROM discount_periods
+         WHERE app_id = ?1
+         ORDER BY start_date, package_id",
+    )?;
+    let periods = stmt
+        .query_map([app_id], |r| {
+            Ok(DiscountPeriod {
+                package_id: r.get(0)?,
+                app_id: r.get(1)?,
+                start_date: r.get(2)?,
+                end_date: r.get(3)?,
+                discount_percentage: r.get(4)?,
+                gross_units: r.get(5)?,
+                revenue: r.get(6)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(periods)
+}
+
+// ==================== Tauri Commands ====================
+
+/// Reconstructs past discount periods from the sales rows; returns how many were found.
+#[tauri::command]
+pub async fn rebuild_discount_calendar() -> AppResult<usize> {
+    tokio::task::spawn_blocking(|| rebuild(&get_write_connection()?)).await?
+}
+
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn query_discount_calendar(appId: i64) -> AppResult<Vec<DiscountPeriod>> {
+    tokio::task::spawn_blocking(move || get_discount_calendar(appId))
+        .await?
+        .and_then(presentation::present)
+}
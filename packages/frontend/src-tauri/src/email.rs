@@ -0,0 +1,416 @@
+// Emailed reports
+//
+// A report (see export.rs) of the active data source is sent as a CSV attachment over SMTP,
+// either on request or on a schedule kept in the settings. The SMTP server, account and password
+// are stored together in the OS credential store (secure_storage.rs), never in the app database.
+//
+// Scheduled reports are checked by the scheduler. A schedule sending every N days covers the days
+// since its last successful send up to yesterday, the last day whose sales Steam has reported in
+// full, so days before a failed or missed send are in the next one. Its first report covers the N
+// days up to yesterday.
+//
+// Without TLS ("none"), the password would cross the network in plain text, so credentials are
+// only sent to a relay on this computer.
+
+use crate::database::get_app_connection;
+use crate::export::{self, ExportFormat, Report};
+use crate::secure_storage;
+use crate::settings;
+use crate::types::{AppError, AppResult, Date, EmailSchedule};
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+// Credential store entry holding the JSON encoded `SmtpSettings`
+pub(crate) const SMTP_SECRET: &str = "smtp";
+
+pub const SMTP_SECURITY: &[&str] = &["tls", "starttls", "none"];
+
+const SEND_TIMEOUT: Duration = Duration::from_secs(30);
+// A scheduled report that failed to send is tried again after this long, not on every poll
+const RETRY_DELAY: Duration = Duration::from_secs(60 * 60);
+
+// Schedule id -> when sending it last failed
+static FAILED_ATTEMPTS: Mutex<Option<HashMap<String, Instant>>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SmtpSettings {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    /// None when saving keeps the stored password
+    pub password: Option<String>,
+    /// Sender address, e.g. "Reports <reports@studio.example>"
+    pub from: String,
+    /// "tls" (usually port 465), "starttls" (usually 587) or "none" for relays on this computer
+    pub security: String,
+}
+
+/// SMTP settings as shown in the app; the password never leaves the credential store.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SmtpSettingsInfo {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub from: String,
+    pub security: String,
+    pub has_password: bool,
+}
+
+impl SmtpSettings {
+    fn validate(&self) -> AppResult<()> {
+        if self.host.trim().is_empty() {
+            return Err(AppError::InvalidInput(
+                "SMTP server is required".to_string(),
+            ));
+        }
+        if self.port == 0 {
+            return Err(AppError::InvalidInput("Invalid SMTP port".to_string()));
+        }
+        if !SMTP_SECURITY.contains(&self.security.as_str()) {
+            return Err(AppError::InvalidInput(format!(
+                "Unknown SMTP security '{}', expected one of {}",
+                self.security,
+                SMTP_SECURITY.join(", ")
+            )));
+        }
+        parse_mailbox(&self.from)?;
+        self.check_plaintext_credentials()
+    }
+
+    fn has_credentials(&self) -> bool {
+        self.username.as_ref().is_some_and(|u| !u.is_empty())
+    }
+
+    // Refuses to send credentials without TLS to anything but this computer
+    fn check_plaintext_credentials(&self) -> AppResult<()> {
+        if self.security == "none" && self.has_credentials() && !is_local_host(&self.host) {
+            return Err(AppError::InvalidInput(format!(
+                "SMTP security 'none' sends the password unencrypted; use 'tls' or 'starttls' \
+                 for {}, or remove the username",
+                self.host.trim()
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn is_local_host(host: &str) -> bool {
+    let host = host.trim().trim_start_matches('[').trim_end_matches(']');
+    host.eq_ignore_ascii_case("localhost")
+        || host
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback())
+}
+
+fn parse_mailbox(address: &str) -> AppResult<Mailbox> {
+    address
+        .trim()
+        .parse()
+        .map_err(|_| AppError::InvalidInput(format!("Invalid email address '{}'", address)))
+}
+
+fn load_smtp_settings() -> AppResult<Option<SmtpSettings>> {
+    let Some(raw) = secure_storage::get_secret(SMTP_SECRET)? else {
+        return Ok(None);
+    };
+    serde_json::from_str(&raw)
+        .map(Some)
+        .map_err(|e| AppError::Internal(format!("Stored SMTP settings are invalid: {}", e)))
+}
+
+fn transport(smtp: &SmtpSettings) -> AppResult<AsyncSmtpTransport<Tokio1Executor>> {
+    // Settings saved before the check was added may still have credentials for a remote relay
+    smtp.check_plaintext_credentials()?;
+    let host = smtp.host.trim();
+    let builder = match smtp.security.as_str() {
+        "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(host),
+        "starttls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host),
+        _ => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+            host,
+        )),
+    }
+    .map_err(|e| AppError::Network(format!("Invalid SMTP server '{}': {}", host, e)))?;
+    let mut builder = builder.port(smtp.port).timeout(Some(SEND_TIMEOUT));
+    if let Some(username) = smtp.username.as_ref().filter(|u| !u.is_empty()) {
+        builder = builder.credentials(Credentials::new(
+            username.clone(),
+            smtp.password.clone().unwrap_or_default(),
+        ));
+    }
+    Ok(builder.build())
+}
+
+fn date_range_text(start_date: Option<Date>, end_date: Option<Date>) -> String {
+    match (start_date, end_date) {
+        (Some(start), Some(end)) => format!("{} to {}", start, end),
+        (Some(start), None) => format!("since {}", start),
+        (None, Some(end)) => format!("up to {}", end),
+        (None, None) => "all dates".to_string(),
+    }
+}
+
+/// Emails a report as a CSV attachment and returns the number of rows it has.
+pub async fn send_report(
+    report: Report,
+    recipients: &[String],
+    start_date: Option<Date>,
+    end_date: Option<Date>,
+) -> AppResult<u64> {
+    let smtp = load_smtp_settings()?.ok_or_else(|| {
+        AppError::InvalidInput("Email is not set up; add SMTP settings first".to_string())
+    })?;
+    if recipients.is_empty() {
+        return Err(AppError::InvalidInput(
+            "At least one recipient is required".to_string(),
+        ));
+    }
+
+    let (csv, rows) = tokio::task::spawn_blocking(move || {
+        let mut csv = Vec::new();
+        let rows =
+            export::write_report(&mut csv, report, ExportFormat::Csv, start_date, end_date)?;
+        Ok::<_, AppError>((csv, rows))
+    })
+    .await??;
+
+    let period = date_range_text(start_date, end_date);
+    let mut builder = Message::builder()
+        .from(parse_mailbox(&smtp.from)?)
+        .subject(format!("Steam sales: {} ({})", report.title(), period));
+    for recipient in recipients {
+        builder = builder.to(parse_mailbox(recipient)?);
+    }
+    let body = format!(
+        "{} for {}, {} row(s), attached as CSV.\n\nSent by Steam Sales Analyzer.",
+        report.title(),
+        period,
+        rows
+    );
+    let attachment = Attachment::new(report.file_name(ExportFormat::Csv)).body(
+        csv,
+        ContentType::parse("text/csv").expect("valid content type"),
+    );
+    let message = builder
+        .multipart(
+            MultiPart::mixed()
+                .singlepart(SinglePart::plain(body))
+                .singlepart(attachment),
+        )
+        .map_err(|e| AppError::Internal(format!("Failed to build email: {}", e)))?;
+
+    transport(&smtp)?
+        .send(message)
+        .await
+        .map_err(|e| AppError::Network(format!("Failed to send email: {}", e)))?;
+    info!(
+        "Emailed {} report ({} rows) to {} recipient(s)",
+        report.id(),
+        rows,
+        recipients.len()
+    );
+    Ok(rows)
+}
+
+// The dates a schedule's report covers, and whether it is due, in SQLite's idea of today (UTC).
+// The last report ended the day before it was sent, so the next one starts on that day.
+fn schedule_window(schedule: &EmailSchedule) -> AppResult<(bool, Date, Date)> {
+    let conn = get_app_connection()?;
+    let window = conn.query_row(
+        "SELECT ?1 IS NULL OR julianday('now') - julianday(?1) >= ?2,
+                COALESCE(date(?1), date('now', '-' || ?2 || ' day')),
+                date('now', '-1 day')",
+        rusqlite::params![schedule.last_sent_at, schedule.every_days],
+        |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+    )?;
+    Ok(window)
+}
+
+fn recently_failed(id: &str) -> bool {
+    FAILED_ATTEMPTS
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|failed| failed.get(id))
+        .is_some_and(|at| at.elapsed() < RETRY_DELAY)
+}
+
+fn record_attempt(id: &str, failed: bool) {
+    let mut attempts = FAILED_ATTEMPTS.lock().unwrap();
+    let attempts = attempts.get_or_insert_with(HashMap::new);
+    if failed {
+        attempts.insert(id.to_string(), Instant::now());
+    } else {
+        attempts.remove(id);
+    }
+}
+
+/// Sends the scheduled reports that are due. Called by the scheduler.
+pub async fn send_due_reports() {
+    let schedules = settings::load_settings().email_schedules;
+    if schedules.is_empty() {
+        return;
+    }
+    if !matches!(load_smtp_settings(), Ok(Some(_))) {
+        return;
+    }
+    for schedule in schedules {
+        if recently_failed(&schedule.id) {
+            continue;
+        }
+        let (due, start_date, end_date) = match schedule_window(&schedule) {
+            Ok(window) => window,
+            Err(e) => {
+                warn!("Failed to check email schedule {}: {}", schedule.id, e);
+                continue;
+            }
+        };
+        if !due {
+            continue;
+        }
+        let result = match Report::parse(&schedule.report_id) {
+            Ok(report) => {
+                send_report(
+                    report,
+                    &schedule.recipients,
+                    Some(start_date),
+                    Some(end_date),
+                )
+                .await
+            }
+            Err(e) => Err(e),
+        };
+        record_attempt(&schedule.id, result.is_err());
+        match result {
+            Ok(_) => {
+                let sent = settings::update_settings(|s| {
+                    for entry in s.email_schedules.iter_mut().filter(|e| e.id == schedule.id) {
+                        entry.last_sent_at = Some(now_iso());
+                    }
+                });
+                if let Err(e) = sent {
+                    warn!("Failed to record email schedule {}: {}", schedule.id, e);
+                }
+            }
+            Err(e) => warn!("Failed to send scheduled report {}: {}", schedule.id, e),
+        }
+    }
+}
+
+fn now_iso() -> String {
+    get_app_connection()
+        .and_then(|conn| {
+            conn.query_row("SELECT strftime('%Y-%m-%dT%H:%M:%fZ', 'now')", [], |r| {
+                r.get(0)
+            })
+        })
+        .unwrap_or_default()
+}
+
+fn new_schedule_id() -> AppResult<String> {
+    let mut bytes = [0u8; 6];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|e| AppError::Internal(format!("Failed to generate schedule id: {}", e)))?;
+    Ok(format!(
+        "es-{}",
+        bytes
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>()
+    ))
+}
+
+// ==================== Tauri Commands ====================
+
+#[tauri::command]
+pub async fn get_smtp_settings() -> AppResult<Option<SmtpSettingsInfo>> {
+    Ok(load_smtp_settings()?.map(|smtp| SmtpSettingsInfo {
+        has_password: smtp.password.as_ref().is_some_and(|p| !p.is_empty()),
+        host: smtp.host,
+        port: smtp.port,
+        username: smtp.username,
+        from: smtp.from,
+        security: smtp.security,
+    }))
+}
+
+/// Saves the SMTP settings. Without a password, the stored one is kept.
+#[tauri::command]
+pub async fn set_smtp_settings(mut settings: SmtpSettings) -> AppResult<()> {
+    settings.validate()?;
+    if settings.password.is_none() {
+        settings.password = load_smtp_settings()?.and_then(|old| old.password);
+    }
+    let raw = serde_json::to_string(&settings).map_err(|e| AppError::Internal(e.to_string()))?;
+    secure_storage::set_secret(SMTP_SECRET, &raw)
+}
+
+#[tauri::command]
+pub async fn clear_smtp_settings() -> AppResult<()> {
+    secure_storage::delete_secret(SMTP_SECRET)
+}
+
+/// Emails a report now. Returns the number of rows in the attached CSV.
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn email_report(
+    reportId: String,
+    recipients: Vec<String>,
+    startDate: Option<Date>,
+    endDate: Option<Date>,
+) -> AppResult<u64> {
+    send_report(Report::parse(&reportId)?, &recipients, startDate, endDate).await
+}
+
+#[tauri::command]
+pub async fn list_email_schedules() -> AppResult<Vec<EmailSchedule>> {
+    Ok(settings::load_settings().email_schedules)
+}
+
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn add_email_schedule(
+    reportId: String,
+    recipients: Vec<String>,
+    everyDays: u32,
+) -> AppResult<EmailSchedule> {
+    for recipient in &recipients {
+        parse_mailbox(recipient)?;
+    }
+    let schedule = EmailSchedule {
+        id: new_schedule_id()?,
+        report_id: reportId,
+        recipients: recipients.iter().map(|r| r.trim().to_string()).collect(),
+        every_days: everyDays,
+        last_sent_at: None,
+    };
+    schedule.validate()?;
+    let added = schedule.clone();
+    settings::update_settings(|s| s.email_schedules.push(added))?;
+    Ok(schedule)
+}
+
+#[tauri::command]
+pub async fn remove_email_schedule(id: String) -> AppResult<()> {
+    let mut found = false;
+    settings::update_settings(|s| {
+        let before = s.email_schedules.len();
+        s.email_schedules.retain(|schedule| schedule.id != id);
+        found = s.email_schedules.len() != before;
+    })?;
+    match found {
+        true => Ok(()),
+        false => Err(AppError::NotFound(format!(
+            "Email schedule {} not found",
+            id
+        ))),
+    }
+}
@@ -0,0 +1,405 @@
+// Report exports
+//
+// A report is one of the query results of the active data source (sales records, daily, per app
+// or per country totals) written as CSV, JSON, TSV or a Markdown table. Headless `--export`,
+// emailed reports and copies to the clipboard (clipboard.rs) all go through `write_report_with`.
+// TSV and Markdown are meant for people, so they round money to cents; CSV and JSON keep the
+// values as stored.
+
+use crate::queries::{self, AppSummary, CountrySummary, DailySummary, QueryFilters, SalesRecord};
+use crate::types::{AppError, AppResult, Date, SortOrder};
+use serde::Serialize;
+use std::io::Write;
+
+// Records read from the database per query while exporting sales records
+const EXPORT_PAGE_SIZE: u32 = 5000;
+// Summary queries return everything in one go; this only lifts their default limits
+pub(crate) const SUMMARY_LIMIT: u32 = 1_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    Tsv,
+    Markdown,
+}
+
+impl ExportFormat {
+    pub fn parse(format: &str) -> AppResult<Self> {
+        match format {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            "tsv" => Ok(ExportFormat::Tsv),
+            "md" | "markdown" => Ok(ExportFormat::Markdown),
+            other => Err(AppError::InvalidInput(format!(
+                "Unknown export format '{}', expected csv, json, tsv or markdown",
+                other
+            ))),
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Tsv => "tsv",
+            ExportFormat::Markdown => "md",
+        }
+    }
+
+    // A row of cells as a line of the format; not used for JSON
+    fn line(self, cells: &[String]) -> String {
+        match self {
+            ExportFormat::Csv | ExportFormat::Json => cells
+                .iter()
+                .map(|c| csv_field(c))
+                .collect::<Vec<_>>()
+                .join(","),
+            // Tabs and line breaks would start a new cell or row
+            ExportFormat::Tsv => cells
+                .iter()
+                .map(|c| c.replace(['\t', '\n', '\r'], " "))
+                .collect::<Vec<_>>()
+                .join("\t"),
+            ExportFormat::Markdown => {
+                let cells: Vec<String> = cells
+                    .iter()
+                    .map(|c| c.replace('|', "\\|").replace(['\n', '\r'], " "))
+                    .collect();
+                format!("| {} |", cells.join(" | "))
+            }
+        }
+    }
+
+    fn money(self, value: f64) -> String {
+        match self {
+            ExportFormat::Csv | ExportFormat::Json => value.to_string(),
+            ExportFormat::Tsv | ExportFormat::Markdown => format!("{:.2}", value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Report {
+    Sales,
+    Daily,
+    Apps,
+    Countries,
+}
+
+pub const REPORT_IDS: &[&str] = &["sales", "daily", "apps", "countries"];
+
+impl Report {
+    pub fn parse(id: &str) -> AppResult<Self> {
+        match id {
+            "sales" => Ok(Report::Sales),
+            "daily" => Ok(Report::Daily),
+            "apps" => Ok(Report::Apps),
+            "countries" => Ok(Report::Countries),
+            other => Err(AppError::InvalidInput(format!(
+                "Unknown report '{}', expected one of {}",
+                other,
+                REPORT_IDS.join(", ")
+            ))),
+        }
+    }
+
+    pub fn id(self) -> &'static str {
+        match self {
+            Report::Sales => "sales",
+            Report::Daily => "daily",
+            Report::Apps => "apps",
+            Report::Countries => "countries",
+        }
+    }
+
+    /// e.g. "steam-sales-daily.csv"
+    pub fn file_name(self, format: ExportFormat) -> String {
+        format!("steam-sales-{}.{}", self.id(), format.extension())
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Report::Sales => "Sales records",
+            Report::Daily => "Daily revenue",
+            Report::Apps => "Revenue by app",
+            Report::Countries => "Revenue by country",
+        }
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn text(value: &Option<String>) -> String {
+    value.clone().unwrap_or_default()
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+const SALES_COLUMNS: &[&str] = &[
+    "date",
+    "line_item_type",
+    "app_id",
+    "app_name",
+    "package_id",
+    "package_name",
+    "country_code",
+    "platform",
+    "currency",
+    "gross_units_sold",
+    "gross_units_returned",
+    "net_units_sold",
+    "gross_sales_usd",
+    "net_sales_usd",
+    "discount_percentage",
+];
+
+fn sales_cells(record: &SalesRecord, format: ExportFormat) -> Vec<String> {
+    vec![
+        record.date.clone(),
+        record.line_item_type.clone(),
+        optional(record.app_id),
+        text(&record.app_name),
+        optional(record.package_id),
+        text(&record.package_name),
+        text(&record.country_code),
+        text(&record.platform),
+        text(&record.currency),
+        record.gross_units_sold.to_string(),
+        record.gross_units_returned.to_string(),
+        record.net_units_sold.to_string(),
+        format.money(record.gross_sales_usd),
+        format.money(record.net_sales_usd),
+        optional(record.discount_percentage),
+    ]
+}
+
+// Writes rows as lines of the format or as elements of a JSON array that `write_report_with`
+// opens and closes
+fn write_rows<T: Serialize>(
+    writer: &mut impl Write,
+    format: ExportFormat,
+    rows: &[T],
+    to_cells: impl Fn(&T, ExportFormat) -> Vec<String>,
+    written: &mut u64,
+) -> AppResult<()> {
+    for row in rows {
+        match format {
+            ExportFormat::Csv | ExportFormat::Tsv | ExportFormat::Markdown => {
+                writeln!(writer, "{}", format.line(&to_cells(row, format)))?
+            }
+            ExportFormat::Json => {
+                if *written > 0 {
+                    write!(writer, ",")?;
+                }
+                serde_json::to_writer(&mut *writer, row)
+                    .map_err(|e| AppError::Internal(e.to_string()))?;
+            }
+        }
+        *written += 1;
+    }
+    Ok(())
+}
+
+/// Writes a report for the active data source and returns the number of rows written.
+pub fn write_report(
+    writer: &mut impl Write,
+    report: Report,
+    format: ExportFormat,
+    start_date: Option<Date>,
+    end_date: Option<Date>,
+) -> AppResult<u64> {
+    let filters = QueryFilters {
+        start_date,
+        end_date,
+        limit: Some(SUMMARY_LIMIT),
+        ..Default::default()
+    }
+    .resolve_date_range()?;
+    write_report_with(writer, report, format, filters)
+}
+
+/// The rows of a summary report, so they can be changed (e.g. masked) before they are written.
+pub enum SummaryRows {
+    Daily(Vec<DailySummary>),
+    Apps(Vec<AppSummary>),
+    Countries(Vec<CountrySummary>),
+}
+
+impl SummaryRows {
+    /// Runs the `report` summary with the given filters, whose date range must have been
+    /// resolved already. Sales records are not a summary.
+    pub fn query(report: Report, filters: QueryFilters) -> AppResult<Self> {
+        match report {
+            Report::Sales => Err(AppError::InvalidInput(
+                "Sales records are not a summary report".to_string(),
+            )),
+            Report::Daily => Ok(SummaryRows::Daily(queries::get_daily_summaries(filters)?)),
+            Report::Apps => Ok(SummaryRows::Apps(queries::get_app_summaries(filters)?)),
+            Report::Countries => Ok(SummaryRows::Countries(queries::get_country_summaries(
+                filters,
+            )?)),
+        }
+    }
+
+    fn report(&self) -> Report {
+        match self {
+            SummaryRows::Daily(_) => Report::Daily,
+            SummaryRows::Apps(_) => Report::Apps,
+            SummaryRows::Countries(_) => Report::Countries,
+        }
+    }
+}
+
+fn columns(report: Report) -> &'static [&'static str] {
+    match report {
+        Report::Sales => SALES_COLUMNS,
+        Report::Daily => &["date", "total_revenue", "total_units", "record_count"],
+        Report::Apps => &[
+            "app_id",
+            "app_name",
+            "total_revenue",
+            "total_units",
+            "record_count",
+            "first_sale",
+            "last_sale",
+        ],
+        Report::Countries => &[
+            "country_code",
+            "country_name",
+            "region",
+            "total_revenue",
+            "total_units",
+            "record_count",
+        ],
+    }
+}
+
+fn write_header(writer: &mut impl Write, report: Report, format: ExportFormat) -> AppResult<()> {
+    let header: Vec<String> = columns(report).iter().map(|c| c.to_string()).collect();
+    match format {
+        ExportFormat::Csv | ExportFormat::Tsv => writeln!(writer, "{}", format.line(&header))?,
+        ExportFormat::Markdown => {
+            writeln!(writer, "{}", format.line(&header))?;
+            writeln!(
+                writer,
+                "{}",
+                format.line(&vec!["---".to_string(); header.len()])
+            )?;
+        }
+        ExportFormat::Json => write!(writer, "[")?,
+    }
+    Ok(())
+}
+
+fn write_footer(writer: &mut impl Write, format: ExportFormat) -> AppResult<()> {
+    if format == ExportFormat::Json {
+        writeln!(writer, "]")?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes a report with the given filters, whose date range must have been resolved already,
+/// and returns the number of rows written.
+pub fn write_report_with(
+    writer: &mut impl Write,
+    report: Report,
+    format: ExportFormat,
+    filters: QueryFilters,
+) -> AppResult<u64> {
+    if report != Report::Sales {
+        return write_summary(writer, format, &SummaryRows::query(report, filters)?);
+    }
+    write_header(writer, report, format)?;
+    let mut written = 0u64;
+    loop {
+        let page = queries::get_sales(QueryFilters {
+            limit: Some(EXPORT_PAGE_SIZE),
+            offset: Some(written as u32),
+            sort_order: Some(SortOrder::Asc),
+            ..filters.clone()
+        })?;
+        write_rows(writer, format, &page.records, sales_cells, &mut written)?;
+        if !page.pagination.has_more || page.records.is_empty() {
+            break;
+        }
+    }
+    write_footer(writer, format)?;
+    Ok(written)
+}
+
+/// Writes the rows of a summary report and returns the number of rows written.
+pub fn write_summary(
+    writer: &mut impl Write,
+    format: ExportFormat,
+    rows: &SummaryRows,
+) -> AppResult<u64> {
+    write_header(writer, rows.report(), format)?;
+    let mut written = 0u64;
+    match rows {
+        SummaryRows::Daily(days) => {
+            write_rows(
+                writer,
+                format,
+                days,
+                |d, format| {
+                    vec![
+                        d.date.clone(),
+                        format.money(d.total_revenue),
+                        d.total_units.to_string(),
+                        d.record_count.to_string(),
+                    ]
+                },
+                &mut written,
+            )?;
+        }
+        SummaryRows::Apps(apps) => {
+            write_rows(
+                writer,
+                format,
+                apps,
+                |a, format| {
+                    vec![
+                        a.app_id.to_string(),
+                        text(&a.app_name),
+                        format.money(a.total_revenue),
+                        a.total_units.to_string(),
+                        a.record_count.to_string(),
+                        a.first_sale.clone(),
+                        a.last_sale.clone(),
+                    ]
+                },
+                &mut written,
+            )?;
+        }
+        SummaryRows::Countries(countries) => {
+            write_rows(
+                writer,
+                format,
+                countries,
+                |c, format| {
+                    vec![
+                        c.country_code.clone(),
+                        text(&c.country_name),
+                        text(&c.region),
+                        format.money(c.total_revenue),
+                        c.total_units.to_string(),
+                        c.record_count.to_string(),
+                    ]
+                },
+                &mut written,
+            )?;
+        }
+    }
+    write_footer(writer, format)?;
+    Ok(written)
+}
@@ -0,0 +1,323 @@
+// Factory reset
+//
+// `factory_reset` removes everything the app has stored on this computer, for handing the
+// machine on or a data deletion request: the ~/.steamsales directory (sales and app databases,
+// the CLI binary, downloads, staged updates, read snapshots), the sales databases of data
+// sources kept elsewhere, the app's platform directories (logs, window geometry, webview
+// storage) and its entries in the credential store (Steam and itch.io API keys, SMTP
+// password). Databases opened read-only as data sources were made by someone else and are left
+// alone, as are exports and reports written to folders the user picked.
+//
+// A dry run only lists what would be removed. Files are overwritten with zeros before they are
+// deleted; on SSDs and copy-on-write file systems the old blocks may survive that, so full-disk
+// encryption is what really protects deleted data. Afterwards every location is checked again
+// and the report says whether anything is left. The app keeps running on in-memory state and
+// recreates files as soon as it needs them, so it should be quit right after a reset.
+
+use crate::database::get_app_database_path;
+use crate::email;
+use crate::itch;
+use crate::keys;
+use crate::query_cache::QueryCache;
+use crate::read_snapshot;
+use crate::secure_storage;
+use crate::sources;
+use crate::sync::SyncState;
+use crate::types::{AppError, AppResult};
+use crate::window_state::WindowStates;
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use tracing::{info, warn};
+
+const OVERWRITE_CHUNK: usize = 1024 * 1024;
+// Files SQLite keeps next to a database
+const DATABASE_SIDECARS: &[&str] = &["-wal", "-shm", "-journal"];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResetArtifact {
+    /// "file", "directory" or "credential"
+    pub kind: String,
+    /// Path, or the name of the credential store entry
+    pub location: String,
+    pub description: String,
+    /// Size on disk; a directory counts everything in it
+    pub bytes: u64,
+    /// None in a dry run
+    pub removed: Option<bool>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FactoryResetReport {
+    pub dry_run: bool,
+    pub artifacts: Vec<ResetArtifact>,
+    pub total_bytes: u64,
+    /// Whether none of the artifacts exists any more; None in a dry run
+    pub verified: Option<bool>,
+    /// Locations that still exist after the reset
+    pub remaining: Vec<String>,
+}
+
+fn artifact(kind: &str, location: String, description: String, bytes: u64) -> ResetArtifact {
+    ResetArtifact {
+        kind: kind.to_string(),
+        location,
+        description,
+        bytes,
+        removed: None,
+        error: None,
+    }
+}
+
+// Bytes of a file, or of everything under a directory; symlinks count as themselves
+fn size_on_disk(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| size_on_disk(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+// The app's directories under the platform's data, config, cache and log locations
+fn platform_dirs(app: &AppHandle) -> Vec<PathBuf> {
+    let resolver = app.path();
+    let mut dirs: Vec<PathBuf> = [
+        resolver.app_data_dir(),
+        resolver.app_local_data_dir(),
+        resolver.app_config_dir(),
+        resolver.app_cache_dir(),
+        resolver.app_log_dir(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    dirs.sort();
+    dirs.dedup();
+    dirs
+}
+
+// Everything there is to remove, as (artifact, path) with no path for credentials
+fn collect(app: &AppHandle) -> AppResult<Vec<(ResetArtifact, Option<PathBuf>)>> {
+    let app_dir = get_app_database_path()
+        .parent()
+        .map(Path::to_path_buf)
+        .ok_or_else(|| AppError::Internal("The app directory has no parent".to_string()))?;
+    let home = dirs::home_dir();
+    let mut dirs = vec![(
+        app_dir.clone(),
+        "App directory: databases, CLI, downloads, updates",
+    )];
+    for dir in platform_dirs(app) {
+        dirs.push((dir, "App data: logs, window geometry, webview storage"));
+    }
+
+    let mut artifacts = Vec::new();
+    let mut dir_paths: Vec<PathBuf> = Vec::new();
+    for (dir, description) in dirs {
+        // Never the home directory or anything above it, whatever the platform paths say
+        let too_wide = home.as_deref().is_some_and(|home| home.starts_with(&dir));
+        if too_wide || !dir.exists() || dir_paths.iter().any(|d| dir.starts_with(d)) {
+            continue;
+        }
+        let bytes = size_on_disk(&dir);
+        artifacts.push((
+            artifact(
+                "directory",
+                dir.display().to_string(),
+                description.to_string(),
+                bytes,
+            ),
+            Some(dir.clone()),
+        ));
+        dir_paths.push(dir);
+    }
+
+    // Sales databases of data sources outside the directories above
+    for source in sources::list_sources()? {
+        if source.read_only {
+            continue;
+        }
+        let db_path = PathBuf::from(&source.db_path);
+        let mut files = vec![db_path.clone()];
+        for sidecar in DATABASE_SIDECARS {
+            let mut path = db_path.clone().into_os_string();
+            path.push(sidecar);
+            files.push(PathBuf::from(path));
+        }
+        for file in files {
+            if !file.exists() || dir_paths.iter().any(|d| file.starts_with(d)) {
+                continue;
+            }
+            let bytes = size_on_disk(&file);
+            artifacts.push((
+                artifact(
+                    "file",
+                    file.display().to_string(),
+                    format!("Sales database of data source '{}'", source.name),
+                    bytes,
+                ),
+                Some(file),
+            ));
+        }
+    }
+
+    let mut secrets: Vec<(String, String)> = keys::list_keys()?
+        .into_iter()
+        .map(|key| {
+            (
+                keys::secret_name(&key.id),
+                format!("Steam API key {}", key.key_hint),
+            )
+        })
+        .collect();
+    secrets.push((
+        itch::API_KEY_SECRET.to_string(),
+        "itch.io API key".to_string(),
+    ));
+    secrets.push((
+        email::SMTP_SECRET.to_string(),
+        "SMTP server and password".to_string(),
+    ));
+    for (name, description) in secrets {
+        match secure_storage::get_secret(&name) {
+            Ok(None) => {}
+            Ok(Some(_)) => artifacts.push((artifact("credential", name, description, 0), None)),
+            // Listed anyway, so a broken credential store doesn't hide a secret
+            Err(e) => {
+                let mut unreadable = artifact("credential", name, description, 0);
+                unreadable.error = Some(e.to_string());
+                artifacts.push((unreadable, None));
+            }
+        }
+    }
+    Ok(artifacts)
+}
+
+// Overwrites the file with zeros and deletes it
+fn shred_file(path: &Path) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(path)?;
+    if metadata.is_file() {
+        let mut file = OpenOptions::new().write(true).open(path)?;
+        let zeros = vec![0u8; OVERWRITE_CHUNK];
+        let mut left = metadata.len();
+        while left > 0 {
+            let n = left.min(OVERWRITE_CHUNK as u64) as usize;
+            file.write_all(&zeros[..n])?;
+            left -= n as u64;
+        }
+        file.sync_all()?;
+    }
+    fs::remove_file(path)
+}
+
+// shred_file on everything under the directory, then removes it; continues past failures and
+// returns the first
+fn shred_dir(path: &Path) -> io::Result<()> {
+    let mut first_error = None;
+    for entry in fs::read_dir(path)? {
+        let entry_path = entry?.path();
+        let result = match fs::symlink_metadata(&entry_path) {
+            Ok(metadata) if metadata.is_dir() => shred_dir(&entry_path),
+            Ok(_) => shred_file(&entry_path),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            first_error.get_or_insert(e);
+        }
+    }
+    if let Some(e) = first_error {
+        return Err(e);
+    }
+    fs::remove_dir(path)
+}
+
+fn remove(artifact: &ResetArtifact, path: Option<&Path>) -> AppResult<()> {
+    match path {
+        Some(path) if artifact.kind == "directory" => shred_dir(path)?,
+        Some(path) => shred_file(path)?,
+        None => secure_storage::delete_secret(&artifact.location)?,
+    }
+    Ok(())
+}
+
+fn still_exists(artifact: &ResetArtifact, path: Option<&Path>) -> bool {
+    match path {
+        Some(path) => fs::symlink_metadata(path).is_ok(),
+        // An entry that can't be read can't be shown to be gone either
+        None => !matches!(secure_storage::get_secret(&artifact.location), Ok(None)),
+    }
+}
+
+fn reset(app: &AppHandle, dry_run: bool) -> AppResult<FactoryResetReport> {
+    let mut collected = collect(app)?;
+    let total_bytes = collected.iter().map(|(a, _)| a.bytes).sum();
+    if dry_run {
+        return Ok(FactoryResetReport {
+            dry_run,
+            artifacts: collected.into_iter().map(|(a, _)| a).collect(),
+            total_bytes,
+            verified: None,
+            remaining: Vec::new(),
+        });
+    }
+
+    info!("Factory reset: removing {} item(s)", collected.len());
+    // Nothing the app holds in memory may be written back
+    app.state::<WindowStates>().stop_saving();
+    app.state::<QueryCache>().clear();
+    read_snapshot::release();
+
+    // Credentials first: which keys exist is only known from the app database
+    collected.sort_by_key(|(a, _)| a.kind != "credential");
+    let mut artifacts = Vec::new();
+    let mut remaining = Vec::new();
+    for (mut artifact, path) in collected {
+        let result = remove(&artifact, path.as_deref());
+        artifact.error = result.err().map(|e| e.to_string());
+        let exists = still_exists(&artifact, path.as_deref());
+        artifact.removed = Some(!exists);
+        if exists {
+            remaining.push(artifact.location.clone());
+        }
+        artifacts.push(artifact);
+    }
+    if !remaining.is_empty() {
+        warn!("Factory reset left {} item(s) behind", remaining.len());
+    }
+    Ok(FactoryResetReport {
+        dry_run,
+        artifacts,
+        total_bytes,
+        verified: Some(remaining.is_empty()),
+        remaining,
+    })
+}
+
+// ==================== Tauri Commands ====================
+
+/// Removes everything the app stored on this computer, or with `dryRun` only lists it. The
+/// report lists each item and whether it is gone; quit the app right after a reset.
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn factory_reset(app: AppHandle, dryRun: bool) -> AppResult<FactoryResetReport> {
+    if !dryRun && app.state::<SyncState>().snapshot().running {
+        return Err(AppError::Busy(
+            "A fetch is running. Cancel it or wait until it has finished.".to_string(),
+        ));
+    }
+    tokio::task::spawn_blocking(move || reset(&app, dryRun)).await?
+}
@@ -0,0 +1,487 @@
+// Revenue goals and lifetime milestones
+//
+// Goals are user-defined targets (global or per app) over an optional date period. Progress is
+// computed from the sales data, with a projected completion date based on the recent run-rate.
+// Milestones are fixed lifetime net-revenue thresholds; crossing one fires a `milestone-reached`
+// event once per scope and threshold.
+//
+// Both are kept in the app database per data source, so they survive the CLI recreating the
+// sales database; goals and milestones of the active source are the ones shown. Earlier versions
+// kept them in the sales database, and they are moved over the first time they are used.
+
+use crate::database::{self, get_app_connection};
+use crate::presentation;
+use crate::queries::{get_app_id_column, get_connection};
+use crate::sources;
+use crate::types::{AppError, AppResult, Date};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::sync::Once;
+use tauri::{AppHandle, Emitter};
+use tracing::warn;
+
+// Days used to compute the run-rate for projections
+const RUN_RATE_DAYS: i64 = 30;
+
+// Lifetime net revenue thresholds (USD) that fire a milestone event
+const MILESTONE_THRESHOLDS: &[f64] = &[
+    1_000.0,
+    10_000.0,
+    50_000.0,
+    100_000.0,
+    250_000.0,
+    500_000.0,
+    1_000_000.0,
+    2_500_000.0,
+    5_000_000.0,
+    10_000_000.0,
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Goal {
+    pub id: i64,
+    pub name: String,
+    /// None for a goal over all apps
+    pub app_id: Option<i64>,
+    /// "revenue" (gross USD), "net_revenue" (net USD) or "units"
+    pub metric: String,
+    pub target: f64,
+    pub period_start: Option<Date>,
+    pub period_end: Option<Date>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoalInput {
+    pub name: String,
+    pub app_id: Option<i64>,
+    pub metric: String,
+    pub target: f64,
+    pub period_start: Option<Date>,
+    pub period_end: Option<Date>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoalProgress {
+    pub goal: Goal,
+    pub current: f64,
+    pub percent: f64,
+    pub completed: bool,
+    /// Average per day over the last RUN_RATE_DAYS days of the period
+    pub daily_run_rate: f64,
+    pub projected_completion_date: Option<Date>,
+    /// True if the projection lands after the end of the goal's period
+    pub at_risk: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Milestone {
+    pub app_id: Option<i64>,
+    pub threshold: f64,
+    pub lifetime_net_revenue: f64,
+    pub reached_at: String,
+}
+
+// Columns copied from the sales database's tables, which had no source_id
+const MOVED_GOAL_COLUMNS: &[&str] = &[
+    "name",
+    "app_id",
+    "metric",
+    "target",
+    "period_start",
+    "period_end",
+    "created_at",
+];
+const MOVED_MILESTONE_COLUMNS: &[&str] =
+    &["scope", "threshold", "lifetime_net_revenue", "reached_at"];
+
+static MOVE_FROM_SALES_DATABASES: Once = Once::new();
+
+fn ensure_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS goals (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            source_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            app_id INTEGER,
+            metric TEXT NOT NULL,
+            target REAL NOT NULL,
+            period_start TEXT,
+            period_end TEXT,
+            created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+        );
+        CREATE INDEX IF NOT EXISTS idx_goals_source ON goals(source_id);
+        CREATE TABLE IF NOT EXISTS milestones (
+            source_id TEXT NOT NULL,
+            scope INTEGER NOT NULL,
+            threshold REAL NOT NULL,
+            lifetime_net_revenue REAL NOT NULL,
+            reached_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+            PRIMARY KEY (source_id, scope, threshold)
+        );",
+    )
+}
+
+fn goals_connection() -> SqliteResult<Connection> {
+    let conn = get_app_connection()?;
+    ensure_tables(&conn)?;
+    MOVE_FROM_SALES_DATABASES.call_once(|| {
+        for (table, columns) in [
+            ("goals", MOVED_GOAL_COLUMNS),
+            ("milestones", MOVED_MILESTONE_COLUMNS),
+        ] {
+            if let Err(e) = database::move_table_from_sales_databases(&conn, table, columns) {
+                warn!("Failed to move {} to the app database: {}", table, e);
+            }
+        }
+    });
+    Ok(conn)
+}
+
+fn metric_expr(metric: &str) -> Option<&'static str> {
+    match metric {
+        "revenue" => Some("CAST(gross_sales_usd AS REAL)"),
+        "net_revenue" => Some("CAST(net_sales_usd AS REAL)"),
+        "units" => Some("net_units_sold"),
+        _ => None,
+    }
+}
+
+fn validate(input: &GoalInput) -> AppResult<()> {
+    if input.name.trim().is_empty() {
+        return Err(AppError::InvalidInput("Goal name must not be empty".to_string()));
+    }
+    if metric_expr(&input.metric).is_none() {
+        return Err(AppError::InvalidInput(
+            "metric must be 'revenue', 'net_revenue' or 'units'".to_string(),
+        ));
+    }
+    if input.target <= 0.0 {
+        return Err(AppError::InvalidInput(
+            "target must be greater than zero".to_string(),
+        ));
+    }
+    if let (Some(start), Some(end)) = (&input.period_start, &input.period_end) {
+        if start > end {
+            return Err(AppError::InvalidInput(
+                "period_start must not be after period_end".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn row_to_goal(r: &rusqlite::Row) -> SqliteResult<Goal> {
+    Ok(Goal {
+        id: r.get(0)?,
+        name: r.get(1)?,
+        app_id: r.get(2)?,
+        metric: r.get(3)?,
+        target: r.get(4)?,
+        period_start: r.get(5)?,
+        period_end: r.get(6)?,
+        created_at: r.get(7)?,
+    })
+}
+
+const GOAL_COLUMNS: &str = "id, name, app_id, metric, target, period_start, period_end, created_at";
+
+/// Goals of the active data source.
+pub fn list_goals_all() -> SqliteResult<Vec<Goal>> {
+    let conn = goals_connection()?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM goals WHERE source_id = ?1 ORDER BY created_at",
+        GOAL_COLUMNS
+    ))?;
+    let rows = stmt.query_map([sources::active_source_id()], row_to_goal)?;
+    rows.collect()
+}
+
+fn get_goal(conn: &Connection, id: i64) -> SqliteResult<Option<Goal>> {
+    conn.query_row(
+        &format!("SELECT {} FROM goals WHERE id = ?1", GOAL_COLUMNS),
+        [id],
+        row_to_goal,
+    )
+    .optional()
+}
+
+pub fn insert_goal(input: &GoalInput) -> SqliteResult<i64> {
+    let conn = goals_connection()?;
+    conn.execute(
+        "INSERT INTO goals (source_id, name, app_id, metric, target, period_start, period_end)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            sources::active_source_id(),
+            input.name.trim(),
+            input.app_id,
+            input.metric,
+            input.target,
+            input.period_start,
+            input.period_end
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn update_goal_by_id(id: i64, input: &GoalInput) -> SqliteResult<bool> {
+    let conn = goals_connection()?;
+    let changed = conn.execute(
+        "UPDATE goals SET name = ?2, app_id = ?3, metric = ?4, target = ?5,
+            period_start = ?6, period_end = ?7
+         WHERE id = ?1",
+        params![
+            id,
+            input.name.trim(),
+            input.app_id,
+            input.metric,
+            input.target,
+            input.period_start,
+            input.period_end
+        ],
+    )?;
+    Ok(changed > 0)
+}
+
+pub fn delete_goal_by_id(id: i64) -> SqliteResult<bool> {
+    let conn = goals_connection()?;
+    Ok(conn.execute("DELETE FROM goals WHERE id = ?1", [id])? > 0)
+}
+
+fn compute_progress(conn: &Connection, app_id_col: &str, goal: Goal) -> SqliteResult<GoalProgress> {
+    let expr = metric_expr(&goal.metric).unwrap_or("CAST(gross_sales_usd AS REAL)");
+
+    let mut conditions = vec!["(?1 IS NULL OR date >= ?1)", "(?2 IS NULL OR date <= ?2)"];
+    let app_condition = format!("{} = ?3", app_id_col);
+    if goal.app_id.is_some() {
+        conditions.push(&app_condition);
+    } else {
+        conditions.push("?3 IS NULL");
+    }
+    let where_clause = conditions.join(" AND ");
+
+    let (current, latest_date): (f64, Option<Date>) = conn.query_row(
+        &format!(
+            "SELECT COALESCE(SUM({}), 0), MAX(date) FROM sales_data WHERE {}",
+            expr, where_clause
+        ),
+        params![goal.period_start, goal.period_end, goal.app_id],
+        |r| Ok((r.get(0)?, r.get(1)?)),
+    )?;
+
+    // Run-rate over the last RUN_RATE_DAYS calendar days up to the latest data in the period
+    let daily_run_rate: f64 = match latest_date {
+        Some(latest) => conn.query_row(
+            &format!(
+                "SELECT COALESCE(SUM({}), 0) / CAST(?6 AS REAL) FROM sales_data
+                 WHERE {} AND date > ?5 AND date <= ?4",
+                expr, where_clause
+            ),
+            params![
+                goal.period_start,
+                goal.period_end,
+                goal.app_id,
+                latest,
+                latest.add_days(-RUN_RATE_DAYS),
+                RUN_RATE_DAYS
+            ],
+            |r| r.get(0),
+        )?,
+        None => 0.0,
+    };
+
+    let completed = current >= goal.target;
+    let projected_completion_date = if completed {
+        // Date on which the cumulative total crossed the target
+        conn.query_row(
+            &format!(
+                "SELECT date FROM (
+                    SELECT date, SUM(SUM({})) OVER (ORDER BY date) AS cumulative
+                    FROM sales_data WHERE {} GROUP BY date
+                 ) WHERE cumulative >= ?4 ORDER BY date LIMIT 1",
+                expr, where_clause
+            ),
+            params![goal.period_start, goal.period_end, goal.app_id, goal.target],
+            |r| r.get(0),
+        )
+        .optional()?
+    } else if daily_run_rate > 0.0 {
+        let days_needed = ((goal.target - current) / daily_run_rate).ceil() as i64;
+        latest_date.map(|latest| latest.add_days(days_needed))
+    } else {
+        None
+    };
+
+    let at_risk = !completed
+        && match (goal.period_end, projected_completion_date) {
+            (Some(end), Some(projected)) => projected > end,
+            (Some(_), None) => true,
+            _ => false,
+        };
+
+    Ok(GoalProgress {
+        percent: (current / goal.target * 100.0).min(100.0),
+        current,
+        completed,
+        daily_run_rate,
+        projected_completion_date,
+        at_risk,
+        goal,
+    })
+}
+
+pub fn get_goal_progress(goal_id: Option<i64>) -> AppResult<Vec<GoalProgress>> {
+    let conn = get_connection()?;
+    let app_id_col = get_app_id_column(&conn);
+
+    let goals = match goal_id {
+        Some(id) => vec![get_goal(&goals_connection()?, id)?
+            .ok_or_else(|| AppError::NotFound(format!("Goal {} not found", id)))?],
+        None => list_goals_all()?,
+    };
+
+    goals
+        .into_iter()
+        .map(|goal| compute_progress(&conn, &app_id_col, goal).map_err(AppError::from))
+        .collect()
+}
+
+/// Records lifetime net-revenue thresholds crossed since the last check (globally and per app)
+/// and returns the newly reached ones.
+pub fn check_milestones() -> SqliteResult<Vec<Milestone>> {
+    let conn = get_connection()?;
+    let milestones = goals_connection()?;
+    let source_id = sources::active_source_id();
+    let app_id_col = get_app_id_column(&conn);
+
+    // Scope 0 is the whole catalog, otherwise the app id
+    let mut totals: Vec<(i64, f64)> = vec![(
+        0,
+        conn.query_row(
+            "SELECT COALESCE(SUM(CAST(net_sales_usd AS REAL)), 0) FROM sales_data",
+            [],
+            |r| r.get(0),
+        )?,
+    )];
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {col}, COALESCE(SUM(CAST(net_sales_usd AS REAL)), 0) FROM sales_data
+         WHERE {col} IS NOT NULL AND {col} != 0 GROUP BY {col}",
+        col = app_id_col
+    ))?;
+    for row in stmt.query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, f64>(1)?)))? {
+        totals.push(row?);
+    }
+
+    let mut reached = Vec::new();
+    for (scope, total) in totals {
+        for threshold in MILESTONE_THRESHOLDS.iter().filter(|t| total >= **t) {
+            let inserted = milestones.execute(
+                "INSERT OR IGNORE INTO milestones
+                    (source_id, scope, threshold, lifetime_net_revenue)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![source_id, scope, threshold, total],
+            )?;
+            if inserted > 0 {
+                let reached_at = milestones.query_row(
+                    "SELECT reached_at FROM milestones
+                     WHERE source_id = ?1 AND scope = ?2 AND threshold = ?3",
+                    params![source_id, scope, threshold],
+                    |r| r.get(0),
+                )?;
+                reached.push(Milestone {
+                    app_id: (scope != 0).then_some(scope),
+                    threshold: *threshold,
+                    lifetime_net_revenue: total,
+                    reached_at,
+                });
+            }
+        }
+    }
+    Ok(reached)
+}
+
+/// Runs the milestone check after a successful fetch and notifies the frontend.
+pub async fn check_milestones_after_sync(app: &AppHandle) {
+    match tokio::task::spawn_blocking(check_milestones).await {
+        Ok(Ok(reached)) if !reached.is_empty() => {
+            // Shown on screen like the milestones list
+            match presentation::present(reached) {
+                Ok(reached) => {
+                    let _ = app.emit("milestone-reached", reached);
+                }
+                Err(e) => tracing::error!("Failed to present reached milestones: {}", e),
+            }
+        }
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => tracing::error!("Milestone check failed: {}", e),
+        Err(e) => tracing::error!("Milestone check task failed: {}", e),
+    }
+}
+
+/// Milestones the active data source has reached, newest first.
+pub fn list_milestones_all() -> SqliteResult<Vec<Milestone>> {
+    let conn = goals_connection()?;
+    let mut stmt = conn.prepare(
+        "SELECT scope, threshold, lifetime_net_revenue, reached_at
+         FROM milestones WHERE source_id = ?1 ORDER BY reached_at DESC, threshold DESC",
+    )?;
+    let rows = stmt.query_map([sources::active_source_id()], |r| {
+        let scope: i64 = r.get(0)?;
+        Ok(Milestone {
+            app_id: (scope != 0).then_some(scope),
+            threshold: r.get(1)?,
+            lifetime_net_revenue: r.get(2)?,
+            reached_at: r.get(3)?,
+        })
+    })?;
+    rows.collect()
+}
+
+// ==================== Tauri Commands ====================
+
+#[tauri::command]
+pub async fn list_goals() -> AppResult<Vec<Goal>> {
+    list_goals_all()
+        .map_err(AppError::from)
+        .and_then(presentation::present)
+}
+
+#[tauri::command]
+pub async fn create_goal(goal: GoalInput) -> AppResult<i64> {
+    validate(&goal)?;
+    insert_goal(&goal).map_err(AppError::from)
+}
+
+#[tauri::command]
+pub async fn update_goal(id: i64, goal: GoalInput) -> AppResult<()> {
+    validate(&goal)?;
+    match update_goal_by_id(id, &goal)? {
+        true => Ok(()),
+        false => Err(AppError::NotFound(format!("Goal {} not found", id))),
+    }
+}
+
+#[tauri::command]
+pub async fn delete_goal(id: i64) -> AppResult<()> {
+    match delete_goal_by_id(id)? {
+        true => Ok(()),
+        false => Err(AppError::NotFound(format!("Goal {} not found", id))),
+    }
+}
+
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn query_goal_progress(goalId: Option<i64>) -> AppResult<Vec<GoalProgress>> {
+    get_goal_progress(goalId).and_then(presentation::present)
+}
+
+#[tauri::command]
+pub async fn list_milestones() -> AppResult<Vec<Milestone>> {
+    list_milestones_all()
+        .map_err(AppError::from)
+        .and_then(presentation::present)
+}
@@ -0,0 +1,457 @@
+// Headless mode
+//
+// `steam-sales-analyzer --sync`, `--export` or `--bench` runs one job without opening a window
+// and exits with a status code, so syncs can be driven from cron or Task Scheduler:
+//
+//   steam-sales-analyzer --sync [--key <id> | --source <id>] [--force]
+//   steam-sales-analyzer --export <csv|json|tsv|md> [--report <id>] [--out <path>]
+//                        [--from <date>] [--to <date>]
+//   steam-sales-analyzer --bench [--profile small|standard|large] [--iterations <n>] [--out <path>]
+//
+// Exit codes: 0 on success, 1 if the job failed, 2 for invalid arguments.
+
+use crate::bench;
+use crate::cli::{fetch_args, get_cli_binary_path, supported_concurrency};
+use crate::demo_data::DemoProfile;
+use crate::export::{write_report, ExportFormat, Report};
+use crate::keys;
+use crate::ledger;
+use crate::queries;
+use crate::settings;
+use crate::single_instance;
+use crate::sources::{self, DataSource};
+use crate::sync;
+use crate::types::{AppError, AppResult, Date, RelativeRange, ResultExt};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::process::Command;
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
+
+const USAGE: &str = "Usage:
+  steam-sales-analyzer --sync [--key <id> | --source <id>] [--force]
+  steam-sales-analyzer --export <csv|json|tsv|md> [--report sales|daily|apps|countries]
+                       [--out <path>] [--from <date>] [--to <date> | --range <name>]
+  steam-sales-analyzer --bench [--profile small|standard|large] [--iterations <n>] [--out <path>]";
+
+#[derive(Debug, PartialEq)]
+enum HeadlessCommand {
+    Sync {
+        key_id: Option<String>,
+        source_id: Option<String>,
+        force: bool,
+    },
+    Export {
+        report: Report,
+        format: ExportFormat,
+        out: Option<PathBuf>,
+        start_date: Option<Date>,
+        end_date: Option<Date>,
+        /// Named range (e.g. "last_quarter") instead of the dates
+        range: Option<RelativeRange>,
+    },
+    Bench {
+        profile: DemoProfile,
+        iterations: u32,
+        out: Option<PathBuf>,
+    },
+    Help,
+}
+
+fn parse_args(args: &[String]) -> AppResult<Option<HeadlessCommand>> {
+    let mut sync = false;
+    let mut export = None;
+    let mut report = Report::Sales;
+    let mut key_id = None;
+    let mut source_id = None;
+    let mut force = false;
+    let mut out = None;
+    let mut start_date = None;
+    let mut end_date = None;
+    let mut range = None;
+    let mut bench = false;
+    let mut profile = DemoProfile::default();
+    let mut iterations = bench::DEFAULT_ITERATIONS;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = |name: &str| {
+            iter.next()
+                .cloned()
+                .ok_or_else(|| AppError::InvalidInput(format!("{} needs a value", name)))
+        };
+        match arg.as_str() {
+            "--sync" => sync = true,
+            "--export" => export = Some(ExportFormat::parse(&value("--export")?)?),
+            "--report" => report = Report::parse(&value("--report")?)?,
+            "--key" => key_id = Some(value("--key")?),
+            "--source" => source_id = Some(value("--source")?),
+            "--force" => force = true,
+            "--out" => out = Some(PathBuf::from(value("--out")?)),
+            "--from" => start_date = Some(value("--from")?.parse::<Date>()?),
+            "--to" => end_date = Some(value("--to")?.parse::<Date>()?),
+            "--range" => range = Some(value("--range")?.parse::<RelativeRange>()?),
+            "--bench" => bench = true,
+            "--profile" => profile = DemoProfile::parse(&value("--profile")?)?,
+            "--iterations" => {
+                iterations = value("--iterations")?.parse().map_err(|_| {
+                    AppError::InvalidInput("--iterations must be a number".to_string())
+                })?
+            }
+            "--help" | "-h" => return Ok(Some(HeadlessCommand::Help)),
+            // Anything else (e.g. -psn_ arguments macOS adds) is left to the GUI
+            _ => {}
+        }
+    }
+
+    if bench {
+        if sync || export.is_some() {
+            return Err(AppError::InvalidInput(
+                "--bench can't be combined with --sync or --export".to_string(),
+            ));
+        }
+        return Ok(Some(HeadlessCommand::Bench {
+            profile,
+            iterations,
+            out,
+        }));
+    }
+    match (sync, export) {
+        (true, Some(_)) => Err(AppError::InvalidInput(
+            "--sync and --export can't be combined".to_string(),
+        )),
+        (true, None) if key_id.is_some() && source_id.is_some() => Err(AppError::InvalidInput(
+            "--key and --source can't be combined".to_string(),
+        )),
+        (false, Some(_)) if range.is_some() && (start_date.is_some() || end_date.is_some()) => {
+            Err(AppError::InvalidInput(
+                "--range can't be combined with --from or --to".to_string(),
+            ))
+        }
+        (true, None) => Ok(Some(HeadlessCommand::Sync {
+            key_id,
+            source_id,
+            force,
+        })),
+        (false, Some(format)) => Ok(Some(HeadlessCommand::Export {
+            report,
+            format,
+            out,
+            start_date,
+            end_date,
+            range,
+        })),
+        (false, None) => Ok(None),
+    }
+}
+
+// Logs go to stderr; there is no app data directory to write log files to before Tauri runs
+fn init_logging() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .try_init();
+}
+
+/// Runs the job given on the command line, if any, and returns the process exit code. Returns
+/// None when the app should start normally.
+pub fn run_from_args() -> Option<i32> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = match parse_args(&args) {
+        Ok(command) => command?,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return Some(2);
+        }
+    };
+    init_logging();
+
+    let result = match command {
+        HeadlessCommand::Help => {
+            println!("{}", USAGE);
+            return Some(0);
+        }
+        HeadlessCommand::Sync {
+            key_id,
+            source_id,
+            force,
+        } => sync_sources(key_id.as_deref(), source_id.as_deref(), force),
+        HeadlessCommand::Export {
+            report,
+            format,
+            out,
+            start_date,
+            end_date,
+            range,
+        } => {
+            let (start_date, end_date) = match range {
+                Some(range) => queries::date_range_bounds(range),
+                None => (start_date, end_date),
+            };
+            export_report(report, format, out, start_date, end_date)
+        }
+        HeadlessCommand::Bench {
+            profile,
+            iterations,
+            out,
+        } => bench::run(profile, iterations, out),
+    };
+    match result {
+        Ok(()) => Some(0),
+        Err(e) => {
+            error!("{}", e);
+            Some(1)
+        }
+    }
+}
+
+// ==================== Sync ====================
+
+// The sources a sync covers: those using the key, the given source, or the active one
+fn sources_to_sync(key_id: Option<&str>, source_id: Option<&str>) -> AppResult<Vec<DataSource>> {
+    let Some(key_id) = key_id else {
+        return Ok(vec![sources::resolve_writable_source(source_id)?]);
+    };
+    let matching: Vec<DataSource> = sources::list_sources()?
+        .into_iter()
+        .filter(|source| source.key_id.as_deref() == Some(key_id))
+        .collect();
+    if matching.is_empty() {
+        return Err(AppError::NotFound(format!(
+            "No data source has been synced with key '{}'",
+            key_id
+        )));
+    }
+    Ok(matching)
+}
+
+fn fetch_source(source: &DataSource, force: bool, concurrency: Option<u32>) -> AppResult<()> {
+    let binary_path = get_cli_binary_path();
+    if !binary_path.exists() {
+        return Err(AppError::CliNotInstalled);
+    }
+    info!("Fetching data source {} into {}", source.id, source.db_path);
+    // The CLI's own output goes straight to our stdout/stderr
+    let status = Command::new(&binary_path)
+        .args(fetch_args(&source.db_path, force, supported_concurrency(concurrency)))
+        .status()
+        .map_err(|e| AppError::Io(format!("Failed to execute CLI: {}", e)))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(AppError::CliFailed(format!(
+            "CLI fetch failed ({})",
+            status
+        )))
+    }
+}
+
+fn sync_sources(key_id: Option<&str>, source_id: Option<&str>, force: bool) -> AppResult<()> {
+    // Held until the sync is done, so the app doesn't start writing the same databases meanwhile
+    let _lock = match single_instance::lock() {
+        Ok(Some(lock)) => Some(lock),
+        Ok(None) => {
+            return Err(AppError::Busy(
+                "Steam Sales Analyzer is running. Sync from the app or close it first.".to_string(),
+            ))
+        }
+        Err(e) => {
+            warn!("Failed to take the instance lock: {}", e);
+            None
+        }
+    };
+    let concurrency = settings::load_settings().fetch_concurrency;
+    let mut failed = 0;
+    for source in sources_to_sync(key_id, source_id)? {
+        // Checked before every CLI run, so pausing in the app stops the sources not fetched yet
+        if sync::is_sync_paused() {
+            return Err(AppError::Busy(
+                "Sync is paused. Resume syncing in the app to fetch new data.".to_string(),
+            ));
+        }
+        let result = fetch_source(&source, force, concurrency)
+            .context(format!("Fetching data source '{}'", source.name));
+        match &source.key_id {
+            Some(key_id) => {
+                if let Err(e) = keys::record_key_usage(key_id, "fetch", &result) {
+                    error!("Failed to record key usage for fetch: {}", e);
+                }
+            }
+            None => keys::record_current_key_usage("fetch", &result),
+        }
+        match result {
+            Ok(()) => {
+                info!("Fetch for data source {} finished", source.id);
+                ledger::record_batch_or_warn(&source, "fetch");
+            }
+            Err(e) => {
+                error!("{}", e);
+                failed += 1;
+            }
+        }
+    }
+    match failed {
+        0 => Ok(()),
+        n => Err(AppError::CliFailed(format!(
+            "{} data source(s) failed to sync",
+            n
+        ))),
+    }
+}
+
+// ==================== Export ====================
+
+// --out, or a file in the export directory from the settings
+fn export_path(report: Report, format: ExportFormat, out: Option<PathBuf>) -> AppResult<PathBuf> {
+    if let Some(out) = out {
+        return Ok(out);
+    }
+    settings::load_settings()
+        .export_directory
+        .map(|dir| PathBuf::from(dir).join(report.file_name(format)))
+        .ok_or_else(|| {
+            AppError::InvalidInput("--out is required when no export directory is set".to_string())
+        })
+}
+
+fn export_report(
+    report: Report,
+    format: ExportFormat,
+    out: Option<PathBuf>,
+    start_date: Option<Date>,
+    end_date: Option<Date>,
+) -> AppResult<()> {
+    let path = export_path(report, format, out)?;
+    let mut writer = BufWriter::new(
+        File::create(&path).context(format!("Creating {}", path.display()))?,
+    );
+    let written = write_report(&mut writer, report, format, start_date, end_date)?;
+    info!("Exported {} rows to {}", written, path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> AppResult<Option<HeadlessCommand>> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        parse_args(&args)
+    }
+
+    fn invalid(args: &[&str]) -> String {
+        match parse(args) {
+            Err(AppError::InvalidInput(message)) => message,
+            other => panic!("{:?} parsed to {:?}", args, other),
+        }
+    }
+
+    #[test]
+    fn no_headless_flags_start_the_gui() {
+        assert_eq!(parse(&[]).unwrap(), None);
+        assert_eq!(parse(&["-psn_0_12345", "--force"]).unwrap(), None);
+        assert_eq!(
+            parse(&["--sync", "--help"]).unwrap(),
+            Some(HeadlessCommand::Help)
+        );
+    }
+
+    #[test]
+    fn parses_sync_and_export() {
+        assert_eq!(
+            parse(&["--sync", "--key", "k1", "--force"]).unwrap(),
+            Some(HeadlessCommand::Sync {
+                key_id: Some("k1".to_string()),
+                source_id: None,
+                force: true,
+            })
+        );
+        assert_eq!(
+            parse(&[
+                "--export",
+                "md",
+                "--report",
+                "daily",
+                "--from",
+                "2024-01-01",
+                "--to",
+                "2024-12-31",
+                "--out",
+                "/tmp/daily.md",
+            ])
+            .unwrap(),
+            Some(HeadlessCommand::Export {
+                report: Report::Daily,
+                format: ExportFormat::Markdown,
+                out: Some(PathBuf::from("/tmp/daily.md")),
+                start_date: Some("2024-01-01".parse().unwrap()),
+                end_date: Some("2024-12-31".parse().unwrap()),
+                range: None,
+            })
+        );
+        assert_eq!(
+            parse(&["--export", "csv", "--range", "last_quarter"]).unwrap(),
+            Some(HeadlessCommand::Export {
+                report: Report::Sales,
+                format: ExportFormat::Csv,
+                out: None,
+                start_date: None,
+                end_date: None,
+                range: Some(RelativeRange::LastQuarter),
+            })
+        );
+        assert_eq!(
+            parse(&["--bench"]).unwrap(),
+            Some(HeadlessCommand::Bench {
+                profile: DemoProfile::Standard,
+                iterations: bench::DEFAULT_ITERATIONS,
+                out: None,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_missing_and_invalid_values() {
+        assert_eq!(invalid(&["--sync", "--key"]), "--key needs a value");
+        assert_eq!(invalid(&["--export"]), "--export needs a value");
+        invalid(&["--export", "xlsx"]);
+        invalid(&["--export", "csv", "--report", "refunds"]);
+        invalid(&["--export", "csv", "--from", "2024-02-30"]);
+        invalid(&["--export", "csv", "--to", "2024-1-31"]);
+        invalid(&["--export", "csv", "--range", "last_week"]);
+        invalid(&["--bench", "--profile", "huge"]);
+        assert_eq!(
+            invalid(&["--bench", "--iterations", "-1"]),
+            "--iterations must be a number"
+        );
+    }
+
+    #[test]
+    fn rejects_conflicting_flags() {
+        assert_eq!(
+            invalid(&["--sync", "--export", "csv"]),
+            "--sync and --export can't be combined"
+        );
+        assert_eq!(
+            invalid(&["--sync", "--key", "k1", "--source", "s1"]),
+            "--key and --source can't be combined"
+        );
+        assert_eq!(
+            invalid(&[
+                "--export",
+                "csv",
+                "--range",
+                "this_month",
+                "--to",
+                "2024-01-31"
+            ]),
+            "--range can't be combined with --from or --to"
+        );
+        assert_eq!(
+            invalid(&["--bench", "--sync"]),
+            "--bench can't be combined with --sync or --export"
+        );
+    }
+}
@@ -0,0 +1,526 @@
+// Price and event impact
+//
+// Compares daily revenue and units during an event (a sale, a price change, a feature) against
+// two baselines: the equally long window right before the event, and the same weekdays in the
+// weeks before it. Lift is the relative change in the daily mean; significance comes from a
+// Welch t-test on the daily values.
+
+use crate::annotations::{self, Annotation};
+use crate::presentation;
+use crate::queries::{self, get_app_id_column, get_connection};
+use crate::types::{AppError, AppResult, Date};
+use rusqlite::{params_from_iter, Connection, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+
+// Weeks of same-weekday history used for the weekday baseline
+const SAME_WEEKDAY_WEEKS: i64 = 4;
+// Two-sided p-value below which a lift is reported as significant
+const SIGNIFICANCE_LEVEL: f64 = 0.05;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricImpact {
+    pub event_total: f64,
+    pub event_daily_mean: f64,
+    pub baseline_daily_mean: f64,
+    /// Relative change of the daily mean (0.25 = +25%); None when the baseline is zero
+    pub lift: Option<f64>,
+    pub t_statistic: Option<f64>,
+    pub p_value: Option<f64>,
+    pub significant: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImpactComparison {
+    pub baseline_start: String,
+    pub baseline_end: String,
+    pub baseline_days: usize,
+    pub revenue: MetricImpact,
+    pub units: MetricImpact,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventImpact {
+    pub start_date: Date,
+    pub end_date: Date,
+    pub event_days: usize,
+    pub annotation: Option<Annotation>,
+    pub app_ids: Option<Vec<i64>>,
+    pub vs_previous: ImpactComparison,
+    pub vs_same_weekdays: ImpactComparison,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImpactRange {
+    /// Use an annotation's date as the event start (and its end when `end_date` is not set)
+    pub annotation_id: Option<i64>,
+    pub start_date: Option<Date>,
+    pub end_date: Option<Date>,
+    /// Leave out the day Steam is still reporting; None uses the setting
+    pub exclude_partial_day: Option<bool>,
+}
+
+// One calendar day of totals; days without sales are zero
+struct DayTotals {
+    date: Date,
+    revenue: f64,
+    units: f64,
+}
+
+fn load_daily_totals(
+    conn: &Connection,
+    start_date: Date,
+    end_date: Date,
+    app_ids: Option<&[i64]>,
+) -> SqliteResult<Vec<DayTotals>> {
+    let app_id_col = get_app_id_column(conn);
+    let app_filter = match app_ids {
+        Some(ids) if !ids.is_empty() => {
+            let placeholders: Vec<String> = ids.iter().map(|_| "?".to_string()).collect();
+            format!("AND s.{} IN ({})", app_id_col, placeholders.join(", "))
+        }
+        _ => String::new(),
+    };
+    let sql = format!(
+        "WITH RECURSIVE days(d) AS (
+            SELECT date(?1)
+            UNION ALL
+            SELECT date(d, '+1 day') FROM days WHERE d < date(?2)
+        )
+        SELECT days.d,
+            COALESCE(SUM(CAST(s.gross_sales_usd AS REAL)), 0),
+            COALESCE(SUM(s.net_units_sold), 0)
+        FROM days
+        LEFT JOIN sales_data s ON s.date = days.d {}
+        GROUP BY days.d
+        ORDER BY days.d",
+        app_filter
+    );
+
+    let mut params: Vec<rusqlite::types::Value> =
+        vec![start_date.to_string().into(), end_date.to_string().into()];
+    for id in app_ids.unwrap_or_default() {
+        params.push((*id).into());
+    }
+
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params_from_iter(params), |r| {
+        Ok(DayTotals {
+            date: r.get(0)?,
+            revenue: r.get(1)?,
+            units: r.get::<_, i64>(2)? as f64,
+        })
+    })?;
+    rows.collect()
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn sample_variance(values: &[f64], mean: f64) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64
+}
+
+// Continued fraction for the regularized incomplete beta function (Numerical Recipes betacf)
+fn beta_continued_fraction(a: f64, b: f64, x: f64) -> f64 {
+    const MAX_ITERATIONS: usize = 200;
+    const EPS: f64 = 3e-14;
+    const FPMIN: f64 = 1e-300;
+
+    let qab = a + b;
+    let qap = a + 1.0;
+    let qam = a - 1.0;
+    let mut c = 1.0;
+    let mut d = 1.0 - qab * x / qap;
+    if d.abs() < FPMIN {
+        d = FPMIN;
+    }
+    d = 1.0 / d;
+    let mut h = d;
+    for m in 1..=MAX_ITERATIONS {
+        let m = m as f64;
+        let m2 = 2.0 * m;
+        let aa = m * (b - m) * x / ((qam + m2) * (a + m2));
+        d = 1.0 + aa * d;
+        if d.abs() < FPMIN {
+            d = FPMIN;
+        }
+        c = 1.0 + aa / c;
+        if c.abs() < FPMIN {
+            c = FPMIN;
+        }
+        d = 1.0 / d;
+        h *= d * c;
+        let aa = -(a + m) * (qab + m) * x / ((a + m2) * (qap + m2));
+        d = 1.0 + aa * d;
+        if d.abs() < FPMIN {
+            d = FPMIN;
+        }
+        c = 1.0 + aa / c;
+        if c.abs() < FPMIN {
+            c = FPMIN;
+        }
+        d = 1.0 / d;
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < EPS {
+            break;
+        }
+    }
+    h
+}
+
+// Lanczos approximation of ln(Gamma(x))
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.180_091_729_471_46,
+        -86.505_320_329_416_77,
+        24.014_098_240_830_91,
+        -1.231_739_572_450_155,
+        0.001_208_650_973_866_179,
+        -0.000_005_395_239_384_953,
+    ];
+    let tmp = x + 5.5;
+    let tmp = tmp - (x + 0.5) * tmp.ln();
+    let mut series = 1.000_000_000_190_015;
+    for (i, c) in COEFFICIENTS.iter().enumerate() {
+        series += c / (x + 1.0 + i as f64);
+    }
+    -tmp + (2.506_628_274_631_000_5 * series / x).ln()
+}
+
+fn regularized_incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_continued_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_continued_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+// Two-sided p-value of a t statistic with `df` degrees of freedom
+fn t_test_p_value(t: f64, df: f64) -> f64 {
+    regularized_incomplete_beta(df / 2.0, 0.5, df / (df + t * t))
+}
+
+fn compare_metric(event: &[f64], baseline: &[f64]) -> MetricImpact {
+    let event_mean = mean(event);
+    let baseline_mean = mean(baseline);
+    let lift =
+        (baseline_mean.abs() > f64::EPSILON).then(|| (event_mean - baseline_mean) / baseline_mean);
+
+    // Welch's t-test: does not assume equal variance in the two windows
+    let (mut t_statistic, mut p_value) = (None, None);
+    if event.len() >= 2 && baseline.len() >= 2 {
+        let se_event = sample_variance(event, event_mean) / event.len() as f64;
+        let se_baseline = sample_variance(baseline, baseline_mean) / baseline.len() as f64;
+        let se = se_event + se_baseline;
+        if se > f64::EPSILON {
+            let t = (event_mean - baseline_mean) / se.sqrt();
+            let df = se.powi(2)
+                / (se_event.powi(2) / (event.len() - 1) as f64
+                    + se_baseline.powi(2) / (baseline.len() - 1) as f64);
+            t_statistic = Some(t);
+            p_value = Some(t_test_p_value(t, df));
+        }
+    }
+
+    MetricImpact {
+        event_total: event.iter().sum(),
+        event_daily_mean: event_mean,
+        baseline_daily_mean: baseline_mean,
+        lift,
+        t_statistic,
+        p_value,
+        significant: p_value.is_some_and(|p| p < SIGNIFICANCE_LEVEL),
+    }
+}
+
+fn build_comparison(event: &[&DayTotals], baseline: &[&DayTotals]) -> ImpactComparison {
+    let values = |days: &[&DayTotals], f: fn(&DayTotals) -> f64| -> Vec<f64> {
+        days.iter().map(|d| f(d)).collect()
+    };
+    ImpactComparison {
+        baseline_start: baseline.first().map(|d| d.date.to_string()).unwrap_or_default(),
+        baseline_end: baseline.last().map(|d| d.date.to_string()).unwrap_or_default(),
+        baseline_days: baseline.len(),
+        revenue: compare_metric(
+            &values(event, |d| d.revenue),
+            &values(baseline, |d| d.revenue),
+        ),
+        units: compare_metric(&values(event, |d| d.units), &values(baseline, |d| d.units)),
+    }
+}
+
+// Splits `days` (consecutive, ending with the event) into the event days and the two baselines:
+// the `baseline_len` days right before `start_date`, and for each event day the same weekday in
+// each of the SAME_WEEKDAY_WEEKS weeks before. Baselines are shorter when `days` starts later.
+fn split_windows(
+    days: &[DayTotals],
+    start_date: Date,
+    baseline_len: usize,
+) -> (Vec<&DayTotals>, Vec<&DayTotals>, Vec<&DayTotals>) {
+    let event: Vec<&DayTotals> = days.iter().filter(|d| d.date >= start_date).collect();
+    let before: Vec<&DayTotals> = days.iter().filter(|d| d.date < start_date).collect();
+    let previous = before[before.len().saturating_sub(baseline_len)..].to_vec();
+
+    let event_count = event.len();
+    let mut same_weekdays: Vec<&DayTotals> = Vec::new();
+    for week in 1..=SAME_WEEKDAY_WEEKS as usize {
+        for i in 0..event_count {
+            let index = days.len() - event_count + i;
+            if let Some(day) = index.checked_sub(week * 7).map(|j| &days[j]) {
+                if day.date < start_date && !same_weekdays.iter().any(|d| d.date == day.date) {
+                    same_weekdays.push(day);
+                }
+            }
+        }
+    }
+    same_weekdays.sort_by_key(|d| d.date);
+    (event, previous, same_weekdays)
+}
+
+/// Computes the impact of an event window. `baseline_days` defaults to the event length.
+pub fn get_event_impact(
+    range: &ImpactRange,
+    baseline_days: Option<u32>,
+    app_ids: Option<Vec<i64>>,
+) -> AppResult<EventImpact> {
+    let conn = get_connection()?;
+
+    let annotation = match range.annotation_id {
+        Some(id) => Some(
+            annotations::get_annotation(&conn, id)?
+                .ok_or_else(|| AppError::NotFound(format!("Annotation {} not found", id)))?,
+        ),
+        None => None,
+    };
+    let start_date = match (range.start_date, &annotation) {
+        (Some(date), _) => date,
+        (None, Some(annotation)) => annotation.date.parse()?,
+        (None, None) => {
+            return Err(AppError::InvalidInput(
+                "Either an annotation or a start date is required".to_string(),
+            ))
+        }
+    };
+    let end_date = range.end_date.unwrap_or(start_date);
+    if end_date < start_date {
+        return Err(AppError::InvalidInput(
+            "End date must not be before start date".to_string(),
+        ));
+    }
+    let end_date = match queries::last_complete_day(range.exclude_partial_day) {
+        Some(last_complete) if last_complete < start_date => {
+            return Err(AppError::InvalidInput(
+                "The event has no days Steam has finished reporting yet".to_string(),
+            ))
+        }
+        Some(last_complete) => end_date.min(last_complete),
+        None => end_date,
+    };
+    // A per-app annotation scopes the comparison to that app unless apps were given explicitly
+    let app_ids = app_ids.or_else(|| {
+        annotation
+            .as_ref()
+            .and_then(|a| a.app_id)
+            .map(|id| vec![id])
+    });
+
+    let event_len = end_date.days_since(start_date) + 1;
+    let baseline_len = baseline_days.map(i64::from).unwrap_or(event_len).max(1);
+    let lookback = baseline_len.max(SAME_WEEKDAY_WEEKS * 7);
+
+    let load_start = start_date.add_days(-lookback);
+    let days = load_daily_totals(&conn, load_start, end_date, app_ids.as_deref())?;
+
+    let (event, previous, same_weekdays) = split_windows(&days, start_date, baseline_len as usize);
+
+    Ok(EventImpact {
+        start_date,
+        end_date,
+        event_days: event.len(),
+        annotation,
+        vs_previous: build_comparison(&event, &previous),
+        vs_same_weekdays: build_comparison(&event, &same_weekdays),
+        app_ids,
+    })
+}
+
+// ==================== Tauri Commands ====================
+
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn query_event_impact(
+    range: ImpactRange,
+    baselineDays: Option<u32>,
+    appIds: Option<Vec<i64>>,
+) -> AppResult<EventImpact> {
+    get_event_impact(&range, baselineDays, appIds).and_then(presentation::present)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> Date {
+        s.parse().unwrap()
+    }
+
+    // Consecutive days from `start`, with revenue = units = the day's index
+    fn days_from(start: &str, count: i64) -> Vec<DayTotals> {
+        (0..count)
+            .map(|i| DayTotals {
+                date: date(start).add_days(i),
+                revenue: i as f64,
+                units: i as f64,
+            })
+            .collect()
+    }
+
+    fn dates(days: &[&DayTotals]) -> Vec<String> {
+        days.iter().map(|d| d.date.to_string()).collect()
+    }
+
+    #[test]
+    fn mean_and_variance_of_short_input() {
+        assert_eq!(mean(&[]), 0.0);
+        assert_eq!(sample_variance(&[], 0.0), 0.0);
+        assert_eq!(sample_variance(&[5.0], 5.0), 0.0);
+        assert_eq!(mean(&[1.0, 2.0, 6.0]), 3.0);
+        assert_eq!(sample_variance(&[1.0, 2.0, 6.0], 3.0), 7.0);
+    }
+
+    #[test]
+    fn p_values_match_t_distribution() {
+        assert!((t_test_p_value(0.0, 10.0) - 1.0).abs() < 1e-9);
+        // Two-sided 5% critical values
+        assert!((t_test_p_value(2.228, 10.0) - 0.05).abs() < 1e-3);
+        assert!((t_test_p_value(-2.228, 10.0) - 0.05).abs() < 1e-3);
+        assert!((t_test_p_value(1.96, 1e6) - 0.05).abs() < 1e-3);
+        assert!(t_test_p_value(50.0, 5.0) < 1e-6);
+    }
+
+    #[test]
+    fn compare_metric_without_enough_data() {
+        let empty = compare_metric(&[], &[]);
+        assert_eq!(empty.event_total, 0.0);
+        assert_eq!(empty.lift, None);
+        assert_eq!(empty.p_value, None);
+        assert!(!empty.significant);
+
+        // One day per window: a lift, but no test
+        let single = compare_metric(&[15.0], &[10.0]);
+        assert_eq!(single.lift, Some(0.5));
+        assert_eq!(single.t_statistic, None);
+
+        // No variance in either window
+        let flat = compare_metric(&[4.0, 4.0], &[4.0, 4.0, 4.0]);
+        assert_eq!(flat.lift, Some(0.0));
+        assert_eq!(flat.p_value, None);
+
+        // A zero baseline has no relative change
+        assert_eq!(compare_metric(&[3.0, 5.0], &[0.0, 0.0]).lift, None);
+    }
+
+    #[test]
+    fn compare_metric_flags_clear_lifts() {
+        let impact = compare_metric(&[20.0, 22.0, 21.0, 23.0], &[10.0, 11.0, 9.0, 10.0]);
+        assert_eq!(impact.event_total, 86.0);
+        assert!((impact.lift.unwrap() - 1.15).abs() < 1e-9);
+        assert!(impact.t_statistic.unwrap() > 0.0);
+        assert!(impact.significant);
+
+        let noise = compare_metric(&[10.0, 12.0, 9.0], &[11.0, 9.0, 12.0]);
+        assert!(!noise.significant);
+    }
+
+    #[test]
+    fn windows_end_the_day_before_the_event() {
+        // 2024-01-01 .. 2024-02-09; event 2024-02-08 .. 2024-02-09
+        let days = days_from("2024-01-01", 40);
+        let (event, previous, same_weekdays) = split_windows(&days, date("2024-02-08"), 3);
+        assert_eq!(dates(&event), ["2024-02-08", "2024-02-09"]);
+        assert_eq!(dates(&previous), ["2024-02-05", "2024-02-06", "2024-02-07"]);
+        assert_eq!(
+            dates(&same_weekdays),
+            [
+                "2024-01-11",
+                "2024-01-12",
+                "2024-01-18",
+                "2024-01-19",
+                "2024-01-25",
+                "2024-01-26",
+                "2024-02-01",
+                "2024-02-02"
+            ]
+        );
+    }
+
+    #[test]
+    fn windows_are_cut_short_at_the_first_day() {
+        // The event starts on the first loaded day: no history at all
+        let days = days_from("2024-03-01", 3);
+        let (event, previous, same_weekdays) = split_windows(&days, date("2024-03-01"), 7);
+        assert_eq!(event.len(), 3);
+        assert!(previous.is_empty());
+        assert!(same_weekdays.is_empty());
+
+        // Eight days of history: all of it before, one week of same weekdays
+        let days = days_from("2024-03-01", 9);
+        let (_, previous, same_weekdays) = split_windows(&days, date("2024-03-09"), 28);
+        assert_eq!(previous.len(), 8);
+        assert_eq!(dates(&same_weekdays), ["2024-03-02"]);
+    }
+
+    #[test]
+    fn daily_totals_fill_days_without_sales() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE sales_data (date TEXT, primary_app_id INTEGER,
+                 gross_sales_usd TEXT, net_units_sold INTEGER);
+             INSERT INTO sales_data VALUES
+                 ('2024-02-28', 10, '5.00', 1),
+                 ('2024-02-29', 10, '2.50', 1),
+                 ('2024-02-29', 20, '7.50', 3),
+                 ('2024-03-02', 10, '1.00', 1);",
+        )
+        .unwrap();
+
+        let days = load_daily_totals(&conn, date("2024-02-29"), date("2024-03-02"), None).unwrap();
+        let totals: Vec<(String, f64, f64)> = days
+            .iter()
+            .map(|d| (d.date.to_string(), d.revenue, d.units))
+            .collect();
+        assert_eq!(
+            totals,
+            [
+                ("2024-02-29".to_string(), 10.0, 4.0),
+                ("2024-03-01".to_string(), 0.0, 0.0),
+                ("2024-03-02".to_string(), 1.0, 1.0)
+            ]
+        );
+
+        let app_days =
+            load_daily_totals(&conn, date("2024-02-29"), date("2024-02-29"), Some(&[20])).unwrap();
+        assert_eq!(app_days.len(), 1);
+        assert_eq!(app_days[0].revenue, 7.5);
+    }
+}
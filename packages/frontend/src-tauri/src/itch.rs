@@ -0,0 +1,685 @@
+// itch.io sales
+//
+// Purchases of the user's itch.io games are fetched from the itch.io server-side API, so revenue
+// from both storefronts can be viewed together. They are kept in the app database, next to the
+// data source they were fetched into, since the CLI owns the sales database's schema. Queries
+// that read sales through `adjustments::sales_source` add one row per date and game to the Steam
+// rows, with `source = 'itch'`; reports that read sales_data directly (tax, reconciliation, the
+// BI views) stay Steam-only.
+//
+// A game counts under the Steam app the user maps it to, if any, and under no app otherwise.
+// Only purchases in USD are included: the API has no exchange rates, so purchases in other
+// currencies are left out and reported by each sync. itch.io's own cut is not part of the API,
+// so net sales only have the tax removed.
+
+use crate::database::{self, begin_write, get_app_connection, get_app_database_path, BUSY_TIMEOUT};
+use crate::queries::get_app_id_column;
+use crate::query_cache::QueryCache;
+use crate::schema::{column_exists, table_exists};
+use crate::secure_storage;
+use crate::sources;
+use crate::sync::SyncState;
+use crate::types::{AppError, AppResult};
+use rusqlite::{params, Connection, Result as SqliteResult};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Once;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tracing::{info, warn};
+
+const API_BASE: &str = "https://itch.io/api/1";
+// Credential store entry of the itch.io API key
+pub(crate) const API_KEY_SECRET: &str = "itch_api_key";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+// Stops paging through a game's purchases if the API keeps returning pages
+const MAX_PAGES: u32 = 10_000;
+
+/// Value of the `source` column of itch.io rows.
+pub const SOURCE: &str = "itch";
+const LINE_ITEM_TYPE: &str = "Itch.io Purchase";
+// The only currency purchases are counted in
+const CURRENCY: &str = "USD";
+
+// Columns copied when the tables move out of a sales database; the app_id mapping is new
+const MOVED_GAME_COLUMNS: &[&str] = &["game_id", "title", "updated_at"];
+const MOVED_PURCHASE_COLUMNS: &[&str] = &[
+    "purchase_id",
+    "game_id",
+    "date",
+    "amount_cents",
+    "tax_cents",
+    "currency",
+    "sale_rate",
+    "fetched_at",
+];
+
+#[derive(Debug, Deserialize)]
+struct ItchGame {
+    id: i64,
+    title: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GamesResponse {
+    #[serde(default)]
+    games: Vec<ItchGame>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ItchPurchase {
+    id: i64,
+    /// e.g. "2024-03-01 17:02:11" (UTC)
+    created_at: String,
+    /// Cents
+    #[serde(default)]
+    amount: i64,
+    #[serde(default)]
+    tax_amount: i64,
+    currency: Option<String>,
+    /// Discount in percent, if the game was on sale
+    sale_rate: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PurchasesResponse {
+    #[serde(default)]
+    purchases: Vec<ItchPurchase>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItchSyncResult {
+    pub source_id: String,
+    pub games: usize,
+    pub new_purchases: u64,
+    pub total_purchases: u64,
+    /// Stored purchases not in USD, which the sales figures leave out
+    pub skipped_purchases: u64,
+    /// Currencies of those purchases, e.g. ["EUR", "GBP"]
+    pub skipped_currencies: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItchGameLink {
+    pub game_id: i64,
+    pub title: String,
+    /// Steam app the game's sales count under
+    pub app_id: Option<i64>,
+}
+
+fn api_key() -> AppResult<String> {
+    secure_storage::get_secret(API_KEY_SECRET)?.ok_or_else(|| {
+        AppError::InvalidInput("No itch.io API key set; add one in the settings first".to_string())
+    })
+}
+
+// The key is part of the URL, so URLs are never logged
+async fn get_json<T: DeserializeOwned>(
+    client: &reqwest::Client,
+    key: &str,
+    path: &str,
+) -> AppResult<T> {
+    let response = client
+        .get(format!("{}/{}/{}", API_BASE, key, path))
+        .send()
+        .await?;
+    let status = response.status();
+    let body: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| AppError::Network(format!("Invalid response from itch.io: {}", e)))?;
+    // Errors come back as {"errors": ["invalid key"]}, not always with an error status
+    let errors = body
+        .get("errors")
+        .and_then(|e| e.as_array())
+        .map(|errors| {
+            errors
+                .iter()
+                .filter_map(|e| e.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .filter(|errors| !errors.is_empty());
+    if let Some(errors) = errors {
+        return Err(if errors.contains("key") {
+            AppError::InvalidApiKey(format!("itch.io rejected the API key: {}", errors))
+        } else {
+            AppError::Network(format!("itch.io returned an error: {}", errors))
+        });
+    }
+    if !status.is_success() {
+        return Err(AppError::Network(format!(
+            "itch.io returned HTTP {}",
+            status
+        )));
+    }
+    serde_json::from_value(body)
+        .map_err(|e| AppError::Network(format!("Unexpected response from itch.io: {}", e)))
+}
+
+fn client() -> AppResult<reqwest::Client> {
+    Ok(reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()?)
+}
+
+static MOVE_FROM_SALES_DATABASES: Once = Once::new();
+
+fn ensure_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS itch_games (
+            source_id TEXT NOT NULL,
+            game_id INTEGER NOT NULL,
+            title TEXT NOT NULL,
+            app_id INTEGER,
+            updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+            PRIMARY KEY (source_id, game_id)
+        );
+        CREATE TABLE IF NOT EXISTS itch_purchases (
+            source_id TEXT NOT NULL,
+            purchase_id INTEGER NOT NULL,
+            game_id INTEGER NOT NULL,
+            date TEXT NOT NULL,
+            amount_cents INTEGER NOT NULL,
+            tax_cents INTEGER NOT NULL,
+            currency TEXT,
+            sale_rate REAL,
+            fetched_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+            PRIMARY KEY (source_id, purchase_id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_itch_purchases_date ON itch_purchases (source_id, date);",
+    )
+}
+
+// Earlier versions kept the purchases in each sales database and wrote their totals into
+// sales_data, with negated game ids as app ids and game names in lookup_apps. Those rows go.
+fn remove_sales_rows(db_path: &str) -> SqliteResult<()> {
+    let conn = Connection::open(db_path)?;
+    // The CLI may be writing to the same database
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    let mut removed = 0;
+    if column_exists(&conn, "sales_data", "source") {
+        removed += conn.execute("DELETE FROM sales_data WHERE source = ?1", [SOURCE])?;
+    }
+    if table_exists(&conn, "lookup_apps") {
+        conn.execute("DELETE FROM lookup_apps WHERE appid < 0", [])?;
+    }
+    if removed > 0 {
+        info!(
+            "Removed {} itch.io rows from sales_data of {}",
+            removed, db_path
+        );
+    }
+    Ok(())
+}
+
+fn itch_connection() -> SqliteResult<Connection> {
+    let conn = get_app_connection()?;
+    ensure_tables(&conn)?;
+    MOVE_FROM_SALES_DATABASES.call_once(|| {
+        for (table, columns) in [
+            ("itch_games", MOVED_GAME_COLUMNS),
+            ("itch_purchases", MOVED_PURCHASE_COLUMNS),
+        ] {
+            if let Err(e) = database::move_table_from_sales_databases(&conn, table, columns) {
+                warn!("Failed to move {} to the app database: {}", table, e);
+            }
+        }
+        let sources = sources::list_sources().unwrap_or_default();
+        for source in sources {
+            if source.read_only || !Path::new(&source.db_path).exists() {
+                continue;
+            }
+            if let Err(e) = remove_sales_rows(&source.db_path) {
+                warn!(
+                    "Failed to remove itch.io rows of data source {}: {}",
+                    source.id, e
+                );
+            }
+        }
+    });
+    Ok(conn)
+}
+
+/// Moves itch.io data out of the sales databases of earlier versions. Call at startup, so the
+/// queries never count those rows next to the ones they read from the app database.
+pub fn move_from_sales_databases() {
+    if let Err(e) = itch_connection() {
+        warn!("Failed to open the itch.io tables: {}", e);
+    }
+}
+
+/// Whether the source has purchases the sales queries include. Attaches the app database to
+/// `conn` as `app` for `sales_rows`; without one, there are none.
+pub(crate) fn has_sales_rows(conn: &Connection, source_id: &str) -> bool {
+    let attached = conn
+        .query_row(
+            "SELECT 1 FROM pragma_database_list WHERE name = 'app'",
+            [],
+            |_| Ok(()),
+        )
+        .is_ok();
+    let path = get_app_database_path();
+    if !attached
+        && (!path.exists()
+            || conn
+                .execute("ATTACH DATABASE ?1 AS app", [path.to_string_lossy()])
+                .is_err())
+    {
+        return false;
+    }
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM app.itch_purchases
+                        WHERE source_id = ?1 AND UPPER(currency) = ?2)",
+        params![source_id, CURRENCY],
+        |r| r.get(0),
+    )
+    .unwrap_or(false)
+}
+
+/// SELECT of the source's purchase totals as sales rows, one per date and game: `columns` of
+/// sales_data in that order after a NULL rowid, then `source` if sales_data has no such column.
+/// Columns itch.io has no value for are NULL, and so is the app id of games not mapped to a
+/// Steam app. Needs the app database attached by `has_sales_rows`.
+pub(crate) fn sales_rows(conn: &Connection, source_id: &str, columns: &[String]) -> String {
+    let app_id_col = get_app_id_column(conn);
+    let mut values: Vec<String> = columns
+        .iter()
+        .map(|column| {
+            let value = match column.as_str() {
+                "date" => "p.date".to_string(),
+                "line_item_type" => format!("'{}'", LINE_ITEM_TYPE),
+                "source" => format!("'{}'", SOURCE),
+                "currency" => format!("'{}'", CURRENCY),
+                "gross_units_sold" | "net_units_sold" => "COUNT(*)".to_string(),
+                "gross_units_returned" => "0".to_string(),
+                "gross_sales_usd" => "printf('%.2f', SUM(p.amount_cents) / 100.0)".to_string(),
+                "net_sales_usd" => {
+                    "printf('%.2f', SUM(p.amount_cents - p.tax_cents) / 100.0)".to_string()
+                }
+                "net_tax_usd" => "printf('%.2f', SUM(p.tax_cents) / 100.0)".to_string(),
+                "discount_percentage" | "total_discount_percentage" => {
+                    "AVG(p.sale_rate)".to_string()
+                }
+                "primary_app_name" | "primary_appname" | "app_name" | "appname" | "appName" => {
+                    "g.title".to_string()
+                }
+                column if column == app_id_col => "g.app_id".to_string(),
+                _ => "NULL".to_string(),
+            };
+            format!("{} AS \"{}\"", value, column)
+        })
+        .collect();
+    if !columns.iter().any(|column| column == "source") {
+        values.push(format!("'{}' AS source", SOURCE));
+    }
+    format!(
+        "SELECT NULL AS rowid, {values}
+         FROM app.itch_purchases p
+         LEFT JOIN app.itch_games g ON g.source_id = p.source_id AND g.game_id = p.game_id
+         WHERE p.source_id = '{source_id}' AND UPPER(p.currency) = '{currency}'
+         GROUP BY p.date, p.game_id",
+        values = values.join(", "),
+        source_id = source_id.replace('\'', "''"),
+        currency = CURRENCY
+    )
+}
+
+fn known_purchase_ids(source_id: &str) -> AppResult<HashSet<i64>> {
+    let conn = itch_connection()?;
+    let mut stmt = conn.prepare("SELECT purchase_id FROM itch_purchases WHERE source_id = ?1")?;
+    let ids = stmt
+        .query_map([source_id], |r| r.get(0))?
+        .collect::<SqliteResult<HashSet<i64>>>()?;
+    Ok(ids)
+}
+
+// Counts the stored purchases the sales figures leave out, and their currencies
+fn skipped_purchases(conn: &Connection, source_id: &str) -> SqliteResult<(u64, Vec<String>)> {
+    let mut stmt = conn.prepare(
+        "SELECT COALESCE(UPPER(currency), ''), COUNT(*) FROM itch_purchases
+         WHERE source_id = ?1 AND COALESCE(UPPER(currency), '') != ?2
+         GROUP BY 1 ORDER BY 1",
+    )?;
+    let rows = stmt
+        .query_map(params![source_id, CURRENCY], |r| {
+            Ok((r.get::<_, String>(0)?, r.get::<_, u64>(1)?))
+        })?
+        .collect::<SqliteResult<Vec<_>>>()?;
+    let count = rows.iter().map(|(_, count)| count).sum();
+    let currencies = rows
+        .into_iter()
+        .map(|(currency, _)| {
+            if currency.is_empty() {
+                "unknown".to_string()
+            } else {
+                currency
+            }
+        })
+        .collect();
+    Ok((count, currencies))
+}
+
+fn store_purchases(
+    source_id: &str,
+    games: &[ItchGame],
+    purchases: &[(i64, ItchPurchase)],
+) -> AppResult<(u64, u64, u64, Vec<String>)> {
+    let conn = itch_connection()?;
+    let tx = begin_write(&conn)?;
+    for game in games {
+        tx.execute(
+            "INSERT INTO itch_games (source_id, game_id, title) VALUES (?1, ?2, ?3)
+             ON CONFLICT(source_id, game_id) DO UPDATE SET
+                title = excluded.title,
+                updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now')",
+            params![source_id, game.id, game.title],
+        )?;
+    }
+    let mut added = 0;
+    for (game_id, purchase) in purchases {
+        added += tx.execute(
+            "INSERT OR IGNORE INTO itch_purchases
+                (source_id, purchase_id, game_id, date, amount_cents, tax_cents, currency,
+                 sale_rate)
+             VALUES (?1, ?2, ?3, substr(?4, 1, 10), ?5, ?6, ?7, ?8)",
+            params![
+                source_id,
+                purchase.id,
+                game_id,
+                purchase.created_at,
+                purchase.amount,
+                purchase.tax_amount,
+                purchase.currency,
+                purchase.sale_rate
+            ],
+        )? as u64;
+    }
+    tx.commit()?;
+    let total = conn.query_row(
+        "SELECT COUNT(*) FROM itch_purchases WHERE source_id = ?1",
+        [source_id],
+        |r| r.get(0),
+    )?;
+    let (skipped, currencies) = skipped_purchases(&conn, source_id)?;
+    Ok((added, total, skipped, currencies))
+}
+
+/// The source's itch.io games, by title.
+pub fn list_games(source_id: &str) -> SqliteResult<Vec<ItchGameLink>> {
+    let conn = itch_connection()?;
+    let mut stmt = conn.prepare(
+        "SELECT game_id, title, app_id FROM itch_games WHERE source_id = ?1
+         ORDER BY title COLLATE NOCASE",
+    )?;
+    let games = stmt
+        .query_map([source_id], |r| {
+            Ok(ItchGameLink {
+                game_id: r.get(0)?,
+                title: r.get(1)?,
+                app_id: r.get(2)?,
+            })
+        })?
+        .collect::<SqliteResult<Vec<_>>>()?;
+    Ok(games)
+}
+
+/// Counts a game's sales under a Steam app, or under no app with None.
+pub fn set_game_app(source_id: &str, game_id: i64, app_id: Option<i64>) -> AppResult<()> {
+    if app_id.is_some_and(|id| id <= 0) {
+        return Err(AppError::InvalidInput("Invalid Steam app id".to_string()));
+    }
+    let conn = itch_connection()?;
+    let updated = conn.execute(
+        "UPDATE itch_games SET app_id = ?3 WHERE source_id = ?1 AND game_id = ?2",
+        params![source_id, game_id, app_id],
+    )?;
+    if updated == 0 {
+        return Err(AppError::NotFound(format!("No itch.io game {}", game_id)));
+    }
+    Ok(())
+}
+
+/// Fetches purchases of all the account's games for the source. Without `force`,
+/// paging through a game stops at the first page whose purchases are all stored already.
+pub async fn sync_source(source_id: Option<&str>, force: bool) -> AppResult<ItchSyncResult> {
+    let source = sources::resolve_writable_source(source_id)?;
+    if !Path::new(&source.db_path).exists() {
+        return Err(AppError::NotFound(format!(
+            "Data source '{}' has no database yet; initialize it first",
+            source.name
+        )));
+    }
+    let key = api_key()?;
+    let client = client()?;
+
+    let source_id = source.id.clone();
+    let known = tokio::task::spawn_blocking(move || known_purchase_ids(&source_id)).await??;
+
+    let games = get_json::<GamesResponse>(&client, &key, "my-games")
+        .await?
+        .games;
+    let mut purchases = Vec::new();
+    for game in &games {
+        for page in 1..=MAX_PAGES {
+            let batch = get_json::<PurchasesResponse>(
+                &client,
+                &key,
+                &format!("game/{}/purchases?page={}", game.id, page),
+            )
+            .await?
+            .purchases;
+            let all_known = batch.iter().all(|p| known.contains(&p.id));
+            let done = batch.is_empty() || (all_known && !force);
+            purchases.extend(batch.into_iter().map(|p| (game.id, p)));
+            if done {
+                break;
+            }
+        }
+    }
+
+    let games_count = games.len();
+    let source_id = source.id.clone();
+    let (new_purchases, total_purchases, skipped_purchases, skipped_currencies) =
+        tokio::task::spawn_blocking(move || store_purchases(&source_id, &games, &purchases))
+            .await??;
+    info!(
+        "itch.io sync for data source {}: {} game(s), {} new purchase(s)",
+        source.id, games_count, new_purchases
+    );
+    if skipped_purchases > 0 {
+        warn!(
+            "{} itch.io purchase(s) of data source {} are not in USD ({}) and are left out",
+            skipped_purchases,
+            source.id,
+            skipped_currencies.join(", ")
+        );
+    }
+    Ok(ItchSyncResult {
+        source_id: source.id,
+        games: games_count,
+        new_purchases,
+        total_purchases,
+        skipped_purchases,
+        skipped_currencies,
+    })
+}
+
+// ==================== Tauri Commands ====================
+
+#[tauri::command]
+pub async fn has_itch_api_key() -> AppResult<bool> {
+    Ok(secure_storage::get_secret(API_KEY_SECRET)?.is_some())
+}
+
+/// Checks the key against the API and saves it in the credential store.
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn set_itch_api_key(apiKey: String) -> AppResult<()> {
+    let key = apiKey.trim();
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(AppError::InvalidInput(
+            "Invalid itch.io API key".to_string(),
+        ));
+    }
+    get_json::<GamesResponse>(&client()?, key, "my-games").await?;
+    secure_storage::set_secret(API_KEY_SECRET, key)
+}
+
+#[tauri::command]
+pub async fn clear_itch_api_key() -> AppResult<()> {
+    secure_storage::delete_secret(API_KEY_SECRET)
+}
+
+/// Fetches itch.io purchases into a data source (the active one by default).
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn sync_itch(
+    app: AppHandle,
+    sourceId: Option<String>,
+    force: Option<bool>,
+) -> AppResult<ItchSyncResult> {
+    if app.state::<SyncState>().snapshot().running {
+        return Err(AppError::Busy(
+            "A Steam fetch is running. Try again when it has finished.".to_string(),
+        ));
+    }
+    let result = sync_source(sourceId.as_deref(), force.unwrap_or(false)).await?;
+    app.state::<QueryCache>().clear();
+    Ok(result)
+}
+
+/// The itch.io games of a data source (the active one by default) and their Steam apps.
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn list_itch_games(sourceId: Option<String>) -> AppResult<Vec<ItchGameLink>> {
+    let source = sources::resolve_source(sourceId.as_deref())?;
+    Ok(list_games(&source.id)?)
+}
+
+/// Counts an itch.io game's sales under a Steam app, or under none with `appId` null.
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn set_itch_game_app(
+    app: AppHandle,
+    gameId: i64,
+    appId: Option<i64>,
+    sourceId: Option<String>,
+) -> AppResult<()> {
+    let source = sources::resolve_writable_source(sourceId.as_deref())?;
+    set_game_app(&source.id, gameId, appId)?;
+    // The sales database didn't change, so cached results wouldn't be dropped otherwise
+    app.state::<QueryCache>().clear();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add_purchases(conn: &Connection) {
+        ensure_tables(conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO itch_games (source_id, game_id, title, app_id) VALUES
+                ('default', 1, 'Mapped', 480), ('default', 2, 'Unmapped', NULL);
+             INSERT INTO itch_purchases
+                (source_id, purchase_id, game_id, date, amount_cents, tax_cents, currency)
+             VALUES
+                ('default', 10, 1, '2024-03-01', 1000, 100, 'USD'),
+                ('default', 11, 1, '2024-03-01', 500, 0, 'usd'),
+                ('default', 12, 2, '2024-03-01', 700, 0, 'USD'),
+                ('default', 13, 2, '2024-03-02', 900, 0, 'EUR'),
+                ('default', 14, 2, '2024-03-02', 900, 0, NULL),
+                ('other', 15, 1, '2024-03-01', 300, 0, 'GBP');",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn non_usd_purchases_are_counted_as_skipped() {
+        let conn = Connection::open_in_memory().unwrap();
+        add_purchases(&conn);
+        assert_eq!(
+            skipped_purchases(&conn, "default").unwrap(),
+            (2, vec!["unknown".to_string(), "EUR".to_string()])
+        );
+        assert_eq!(skipped_purchases(&conn, "none").unwrap(), (0, vec![]));
+    }
+
+    #[test]
+    fn sales_rows_total_usd_purchases_per_date_and_game() {
+        let path = std::env::temp_dir().join(format!("itch-test-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        add_purchases(&Connection::open(&path).unwrap());
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE sales_data (date TEXT NOT NULL, primary_appid INTEGER NOT NULL,
+                 platform TEXT NOT NULL, gross_units_sold INTEGER, gross_sales_usd TEXT);",
+        )
+        .unwrap();
+        conn.execute("ATTACH DATABASE ?1 AS app", [path.to_string_lossy()])
+            .unwrap();
+
+        let columns: Vec<String> = [
+            "date",
+            "primary_appid",
+            "platform",
+            "gross_units_sold",
+            "gross_sales_usd",
+        ]
+        .iter()
+        .map(|c| c.to_string())
+        .collect();
+        let sql = format!(
+            "SELECT date, primary_appid, platform, gross_units_sold, gross_sales_usd, source
+             FROM ({}) ORDER BY date, primary_appid",
+            sales_rows(&conn, "default", &columns)
+        );
+        type Row = (String, Option<i64>, Option<String>, i64, String, String);
+        let rows: Vec<Row> = conn
+            .prepare(&sql)
+            .unwrap()
+            .query_map([], |r| {
+                Ok((
+                    r.get(0)?,
+                    r.get(1)?,
+                    r.get(2)?,
+                    r.get(3)?,
+                    r.get(4)?,
+                    r.get(5)?,
+                ))
+            })
+            .unwrap()
+            .collect::<SqliteResult<_>>()
+            .unwrap();
+        drop(conn);
+        let _ = std::fs::remove_file(&path);
+
+        // The EUR and unknown-currency purchases of 2024-03-02 are left out
+        assert_eq!(
+            rows,
+            vec![
+                (
+                    "2024-03-01".into(),
+                    None,
+                    None,
+                    1,
+                    "7.00".into(),
+                    "itch".into()
+                ),
+                (
+                    "2024-03-01".into(),
+                    Some(480),
+                    None,
+                    2,
+                    "15.00".into(),
+                    "itch".into()
+                ),
+            ]
+        );
+    }
+}
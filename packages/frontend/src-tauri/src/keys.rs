@@ -0,0 +1,259 @@
+// API key registry and usage audit trail
+//
+// The CLI stores the actual API key in its own database. The app only keeps a fingerprint of each
+// key it has handed to the CLI, plus a log of every operation that made the CLI use that key, so a
+// rate-limited or revoked key can be traced back to the machine and feature that was using it.
+// For the few features that call Steam directly (reconciliation), the key itself is also kept in
+// the operating system's credential store, never in the app database.
+
+use crate::database::get_app_connection;
+use crate::secure_storage;
+use crate::types::{AppError, AppResult};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeyInfo {
+    pub id: String,
+    pub key_hint: String,
+    pub created_at: String,
+    pub initialized_at: String,
+    pub last_used_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeySyncStatus {
+    pub id: String,
+    pub key_hint: String,
+    /// Last fetch that succeeded with this key
+    pub last_sync_at: Option<String>,
+    /// Last fetch attempt with this key, successful or not
+    pub last_fetch_at: Option<String>,
+    pub last_fetch_result: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyUsageEntry {
+    pub id: i64,
+    pub key_id: String,
+    pub timestamp: String,
+    pub operation: String,
+    pub result: String,
+    pub error: Option<String>,
+    pub machine: Option<String>,
+}
+
+fn ensure_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS api_keys (
+            id TEXT PRIMARY KEY,
+            key_hint TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+            initialized_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+        );
+        CREATE TABLE IF NOT EXISTS key_usage_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            key_id TEXT NOT NULL,
+            timestamp TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+            operation TEXT NOT NULL,
+            result TEXT NOT NULL,
+            error TEXT,
+            machine TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_key_usage_log_key ON key_usage_log(key_id, timestamp);",
+    )
+}
+
+// Stable identifier for a key: first 16 hex chars of its SHA-256, never the key itself
+pub fn key_fingerprint(api_key: &str) -> String {
+    let digest = Sha256::digest(api_key.trim().as_bytes());
+    digest[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn key_hint(api_key: &str) -> String {
+    let trimmed = api_key.trim();
+    let tail: String = trimmed
+        .chars()
+        .rev()
+        .take(4)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    format!("…{}", tail)
+}
+
+fn machine_name() -> Option<String> {
+    hostname::get()
+        .ok()
+        .map(|name| name.to_string_lossy().to_string())
+}
+
+/// Registers a key handed to the CLI and marks it as the most recently initialized one.
+/// Returns the key's id.
+pub fn register_key(api_key: &str) -> SqliteResult<String> {
+    let conn = get_app_connection()?;
+    ensure_tables(&conn)?;
+
+    let id = key_fingerprint(api_key);
+    conn.execute(
+        "INSERT INTO api_keys (id, key_hint) VALUES (?1, ?2)
+         ON CONFLICT(id) DO UPDATE SET initialized_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now')",
+        params![id, key_hint(api_key)],
+    )?;
+    Ok(id)
+}
+
+// Credential store entry of the key with `key_id`
+pub(crate) fn secret_name(key_id: &str) -> String {
+    format!("steam_api_key_{}", key_id)
+}
+
+/// Keeps the key with `key_id` in the credential store, for calls to Steam outside the CLI.
+pub fn store_key_secret(key_id: &str, api_key: &str) -> AppResult<()> {
+    secure_storage::set_secret(&secret_name(key_id), api_key)
+}
+
+pub fn get_key_secret(key_id: &str) -> AppResult<Option<String>> {
+    secure_storage::get_secret(&secret_name(key_id))
+}
+
+/// The key the CLI database was last initialized with, if any.
+pub fn get_current_key_id() -> SqliteResult<Option<String>> {
+    let conn = get_app_connection()?;
+    ensure_tables(&conn)?;
+
+    conn.query_row(
+        "SELECT id FROM api_keys ORDER BY initialized_at DESC LIMIT 1",
+        [],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+/// Appends an entry to the usage log. `result` is the outcome of the operation that used the key.
+pub fn record_key_usage(
+    key_id: &str,
+    operation: &str,
+    result: &AppResult<()>,
+) -> SqliteResult<()> {
+    let conn = get_app_connection()?;
+    ensure_tables(&conn)?;
+
+    let (status, error) = match result {
+        Ok(()) => ("ok", None),
+        Err(e) => ("error", Some(e.to_string())),
+    };
+    conn.execute(
+        "INSERT INTO key_usage_log (key_id, operation, result, error, machine)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![key_id, operation, status, error, machine_name()],
+    )?;
+    Ok(())
+}
+
+/// Records usage against the currently initialized key. Failures are only logged so that
+/// auditing never breaks the operation being audited.
+pub fn record_current_key_usage(operation: &str, result: &AppResult<()>) {
+    let recorded = get_current_key_id().and_then(|key_id| match key_id {
+        Some(key_id) => record_key_usage(&key_id, operation, result),
+        None => Ok(()),
+    });
+    if let Err(e) = recorded {
+        tracing::warn!("Failed to record key usage for {}: {}", operation, e);
+    }
+}
+
+pub fn list_keys() -> SqliteResult<Vec<ApiKeyInfo>> {
+    let conn = get_app_connection()?;
+    ensure_tables(&conn)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT k.id, k.key_hint, k.created_at, k.initialized_at,
+                (SELECT MAX(timestamp) FROM key_usage_log l WHERE l.key_id = k.id)
+         FROM api_keys k
+         ORDER BY k.initialized_at DESC",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(ApiKeyInfo {
+            id: row.get(0)?,
+            key_hint: row.get(1)?,
+            created_at: row.get(2)?,
+            initialized_at: row.get(3)?,
+            last_used_at: row.get(4)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Last fetch times of every registered key, most recently initialized first.
+pub fn list_key_sync_status() -> SqliteResult<Vec<KeySyncStatus>> {
+    let conn = get_app_connection()?;
+    ensure_tables(&conn)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT k.id, k.key_hint,
+                (SELECT MAX(timestamp) FROM key_usage_log l
+                 WHERE l.key_id = k.id AND l.operation = 'fetch' AND l.result = 'ok'),
+                last.timestamp, last.result
+         FROM api_keys k
+         LEFT JOIN key_usage_log last ON last.id = (
+             SELECT id FROM key_usage_log l
+             WHERE l.key_id = k.id AND l.operation = 'fetch'
+             ORDER BY timestamp DESC, id DESC
+             LIMIT 1
+         )
+         ORDER BY k.initialized_at DESC",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(KeySyncStatus {
+            id: row.get(0)?,
+            key_hint: row.get(1)?,
+            last_sync_at: row.get(2)?,
+            last_fetch_at: row.get(3)?,
+            last_fetch_result: row.get(4)?,
+        })
+    })?;
+    rows.collect()
+}
+
+pub fn get_usage(key_id: &str, limit: u32) -> SqliteResult<Vec<KeyUsageEntry>> {
+    let conn = get_app_connection()?;
+    ensure_tables(&conn)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT id, key_id, timestamp, operation, result, error, machine
+         FROM key_usage_log
+         WHERE key_id = ?1
+         ORDER BY timestamp DESC, id DESC
+         LIMIT ?2",
+    )?;
+    let rows = stmt.query_map(params![key_id, limit as i64], |row| {
+        Ok(KeyUsageEntry {
+            id: row.get(0)?,
+            key_id: row.get(1)?,
+            timestamp: row.get(2)?,
+            operation: row.get(3)?,
+            result: row.get(4)?,
+            error: row.get(5)?,
+            machine: row.get(6)?,
+        })
+    })?;
+    rows.collect()
+}
+
+// ==================== Tauri Commands ====================
+
+#[tauri::command]
+pub async fn list_api_keys() -> AppResult<Vec<ApiKeyInfo>> {
+    list_keys().map_err(AppError::from)
+}
+
+#[tauri::command]
+pub async fn get_key_usage(id: String, limit: Option<u32>) -> AppResult<Vec<KeyUsageEntry>> {
+    get_usage(&id, limit.unwrap_or(500)).map_err(AppError::from)
+}
@@ -0,0 +1,377 @@
+// Tamper-evident ledger of the sales data
+//
+// For audits, `ledgerEnabled` in the settings records every batch of writes to a source's
+// sales_data (a fetch) in an append-only hash chain. Each batch becomes an entry in `ledger` in
+// the app database, with a digest of every date whose rows the batch changed in
+// `ledger_dates`. A date's digest is the SHA-256 of the sorted SHA-256s of its rows (every
+// column), so it doesn't depend on row order or rowids. An entry's hash covers its dates and the
+// hash of the entry before it, so altering, removing or slipping in an entry breaks the chain
+// from there on.
+//
+// `verify_ledger` walks the chain and compares the digests of the database as it is now with
+// the ones the last batches recorded. A date that differs was changed outside a recorded batch,
+// e.g. by editing the database file. The chain lives next to the data it protects, so someone
+// able to rewrite both consistently can't be detected; keep the latest entry hash elsewhere
+// (it's part of the verification result) to guard against that.
+
+use crate::database::get_app_connection;
+use crate::settings;
+use crate::sources::{self, DataSource};
+use crate::types::{AppError, AppResult};
+use chrono::Utc;
+use rusqlite::types::ValueRef;
+use rusqlite::{params, Connection, OpenFlags, Result as SqliteResult};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::Path;
+use tracing::{info, warn};
+
+// prev_hash of a source's first entry
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+#[derive(Debug, Clone, PartialEq)]
+struct DateDigest {
+    rows: i64,
+    hash: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LedgerVerification {
+    pub source_id: String,
+    pub entries: u64,
+    /// False if an entry was altered, removed or inserted after it was recorded
+    pub chain_intact: bool,
+    /// First entry whose hash doesn't match its contents or the entry before it
+    pub broken_at_entry: Option<i64>,
+    /// Dates whose rows differ from what the last batch that touched them recorded
+    pub modified_dates: Vec<String>,
+    pub last_recorded_at: Option<String>,
+    /// Hash of the newest entry, to keep somewhere the database can't be changed
+    pub last_entry_hash: Option<String>,
+}
+
+fn ensure_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS ledger (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            source_id TEXT NOT NULL,
+            recorded_at TEXT NOT NULL,
+            batch TEXT NOT NULL,
+            prev_hash TEXT NOT NULL,
+            entry_hash TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_ledger_source ON ledger(source_id, id);
+        CREATE TABLE IF NOT EXISTS ledger_dates (
+            entry_id INTEGER NOT NULL,
+            date TEXT NOT NULL,
+            rows INTEGER NOT NULL,
+            hash TEXT NOT NULL,
+            PRIMARY KEY (entry_id, date)
+        );
+        CREATE TRIGGER IF NOT EXISTS ledger_no_update BEFORE UPDATE ON ledger
+            BEGIN SELECT RAISE(ABORT, 'The ledger is append-only'); END;
+        CREATE TRIGGER IF NOT EXISTS ledger_no_delete BEFORE DELETE ON ledger
+            BEGIN SELECT RAISE(ABORT, 'The ledger is append-only'); END;
+        CREATE TRIGGER IF NOT EXISTS ledger_dates_no_update BEFORE UPDATE ON ledger_dates
+            BEGIN SELECT RAISE(ABORT, 'The ledger is append-only'); END;
+        CREATE TRIGGER IF NOT EXISTS ledger_dates_no_delete BEFORE DELETE ON ledger_dates
+            BEGIN SELECT RAISE(ABORT, 'The ledger is append-only'); END;",
+    )
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Type-tagged, length-prefixed, so no two different rows hash the same bytes
+fn hash_row(row: &rusqlite::Row, columns: usize) -> SqliteResult<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    for i in 0..columns {
+        match row.get_ref(i)? {
+            ValueRef::Null => hasher.update(b"N"),
+            ValueRef::Integer(n) => {
+                hasher.update(b"I");
+                hasher.update(n.to_le_bytes());
+            }
+            ValueRef::Real(f) => {
+                hasher.update(b"R");
+                hasher.update(f.to_bits().to_le_bytes());
+            }
+            ValueRef::Text(bytes) => {
+                hasher.update(b"T");
+                hasher.update((bytes.len() as u64).to_le_bytes());
+                hasher.update(bytes);
+            }
+            ValueRef::Blob(bytes) => {
+                hasher.update(b"B");
+                hasher.update((bytes.len() as u64).to_le_bytes());
+                hasher.update(bytes);
+            }
+        }
+    }
+    Ok(hasher.finalize().into())
+}
+
+// Digest of every date in the database's sales_data
+fn digest_dates(db_path: &Path) -> SqliteResult<BTreeMap<String, DateDigest>> {
+    let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut stmt = conn.prepare("SELECT date, * FROM sales_data")?;
+    let columns = stmt.column_count();
+    let mut row_hashes: BTreeMap<String, Vec<[u8; 32]>> = BTreeMap::new();
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let date: Option<String> = row.get(0)?;
+        row_hashes
+            .entry(date.unwrap_or_default())
+            .or_default()
+            .push(hash_row(row, columns)?);
+    }
+    Ok(row_hashes
+        .into_iter()
+        .map(|(date, mut hashes)| {
+            hashes.sort_unstable();
+            let mut hasher = Sha256::new();
+            for hash in &hashes {
+                hasher.update(hash);
+            }
+            let digest = DateDigest {
+                rows: hashes.len() as i64,
+                hash: hex(&hasher.finalize()),
+            };
+            (date, digest)
+        })
+        .collect())
+}
+
+// What a date without rows is recorded as once it had some
+fn empty_digest() -> DateDigest {
+    DateDigest {
+        rows: 0,
+        hash: hex(&Sha256::digest(b"")),
+    }
+}
+
+fn entry_hash(
+    prev_hash: &str,
+    source_id: &str,
+    recorded_at: &str,
+    batch: &str,
+    dates: &BTreeMap<String, DateDigest>,
+) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(format!(
+        "{}\n{}\n{}\n{}\n",
+        prev_hash, source_id, recorded_at, batch
+    ));
+    for (date, digest) in dates {
+        hasher.update(format!("{} {} {}\n", date, digest.rows, digest.hash));
+    }
+    hex(&hasher.finalize())
+}
+
+struct Entry {
+    id: i64,
+    recorded_at: String,
+    batch: String,
+    prev_hash: String,
+    entry_hash: String,
+    dates: BTreeMap<String, DateDigest>,
+}
+
+// The source's entries, oldest first
+fn load_entries(conn: &Connection, source_id: &str) -> SqliteResult<Vec<Entry>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, recorded_at, batch, prev_hash, entry_hash FROM ledger
+         WHERE source_id = ?1 ORDER BY id",
+    )?;
+    let mut entries = stmt
+        .query_map([source_id], |r| {
+            Ok(Entry {
+                id: r.get(0)?,
+                recorded_at: r.get(1)?,
+                batch: r.get(2)?,
+                prev_hash: r.get(3)?,
+                entry_hash: r.get(4)?,
+                dates: BTreeMap::new(),
+            })
+        })?
+        .collect::<SqliteResult<Vec<_>>>()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT d.entry_id, d.date, d.rows, d.hash FROM ledger_dates d
+         JOIN ledger l ON l.id = d.entry_id
+         WHERE l.source_id = ?1",
+    )?;
+    let rows = stmt.query_map([source_id], |r| {
+        Ok((
+            r.get::<_, i64>(0)?,
+            r.get::<_, String>(1)?,
+            DateDigest {
+                rows: r.get(2)?,
+                hash: r.get(3)?,
+            },
+        ))
+    })?;
+    for row in rows {
+        let (entry_id, date, digest) = row?;
+        if let Some(entry) = entries.iter_mut().find(|e| e.id == entry_id) {
+            entry.dates.insert(date, digest);
+        }
+    }
+    Ok(entries)
+}
+
+// The digest each date had after the last entry that recorded it
+fn recorded_digests(entries: &[Entry]) -> BTreeMap<String, DateDigest> {
+    let mut digests = BTreeMap::new();
+    for entry in entries {
+        for (date, digest) in &entry.dates {
+            digests.insert(date.clone(), digest.clone());
+        }
+    }
+    digests
+}
+
+// Dates whose digest now differs from the recorded one, with the current digest
+fn changed_dates(
+    recorded: &BTreeMap<String, DateDigest>,
+    current: &BTreeMap<String, DateDigest>,
+) -> BTreeMap<String, DateDigest> {
+    let mut changed = BTreeMap::new();
+    for (date, digest) in current {
+        if recorded.get(date) != Some(digest) {
+            changed.insert(date.clone(), digest.clone());
+        }
+    }
+    for (date, digest) in recorded {
+        if digest.rows > 0 && !current.contains_key(date) {
+            changed.insert(date.clone(), empty_digest());
+        }
+    }
+    changed
+}
+
+/// Appends an entry for the dates of the source's sales data that changed since the last one,
+/// if the ledger is enabled. Returns the new entry's id; None if nothing changed.
+pub fn record_batch(source: &DataSource, batch: &str) -> AppResult<Option<i64>> {
+    if !settings::load_settings().ledger_enabled || !Path::new(&source.db_path).exists() {
+        return Ok(None);
+    }
+    let current = digest_dates(Path::new(&source.db_path))?;
+
+    let mut conn = get_app_connection()?;
+    ensure_tables(&conn)?;
+    let tx = conn.transaction()?;
+    let entries = load_entries(&tx, &source.id)?;
+    let changed = changed_dates(&recorded_digests(&entries), &current);
+    if changed.is_empty() {
+        return Ok(None);
+    }
+    let prev_hash = entries
+        .last()
+        .map_or(GENESIS_HASH.to_string(), |e| e.entry_hash.clone());
+    let recorded_at = Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+    let hash = entry_hash(&prev_hash, &source.id, &recorded_at, batch, &changed);
+    tx.execute(
+        "INSERT INTO ledger (source_id, recorded_at, batch, prev_hash, entry_hash)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![source.id, recorded_at, batch, prev_hash, hash],
+    )?;
+    let entry_id = tx.last_insert_rowid();
+    {
+        let mut insert = tx.prepare(
+            "INSERT INTO ledger_dates (entry_id, date, rows, hash) VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for (date, digest) in &changed {
+            insert.execute(params![entry_id, date, digest.rows, digest.hash])?;
+        }
+    }
+    tx.commit()?;
+    info!(
+        "Recorded ledger entry {} for data source {}: {} changed date(s)",
+        entry_id,
+        source.id,
+        changed.len()
+    );
+    Ok(Some(entry_id))
+}
+
+/// `record_batch` for callers that must not fail because of the ledger.
+pub fn record_batch_or_warn(source: &DataSource, batch: &str) {
+    if let Err(e) = record_batch(source, batch) {
+        warn!("Failed to record the {} in the ledger: {}", batch, e);
+    }
+}
+
+pub fn verify(source: &DataSource) -> AppResult<LedgerVerification> {
+    let conn = get_app_connection()?;
+    ensure_tables(&conn)?;
+    let entries = load_entries(&conn, &source.id)?;
+
+    let mut expected_prev = GENESIS_HASH.to_string();
+    let mut broken_at_entry = None;
+    for entry in &entries {
+        let recomputed = entry_hash(
+            &entry.prev_hash,
+            &source.id,
+            &entry.recorded_at,
+            &entry.batch,
+            &entry.dates,
+        );
+        if entry.prev_hash != expected_prev || entry.entry_hash != recomputed {
+            broken_at_entry = Some(entry.id);
+            break;
+        }
+        expected_prev = entry.entry_hash.clone();
+    }
+
+    let modified_dates = if entries.is_empty() {
+        Vec::new()
+    } else {
+        let current = if Path::new(&source.db_path).exists() {
+            digest_dates(Path::new(&source.db_path))?
+        } else {
+            BTreeMap::new()
+        };
+        changed_dates(&recorded_digests(&entries), &current)
+            .into_keys()
+            .collect()
+    };
+
+    Ok(LedgerVerification {
+        source_id: source.id.clone(),
+        entries: entries.len() as u64,
+        chain_intact: broken_at_entry.is_none(),
+        broken_at_entry,
+        modified_dates,
+        last_recorded_at: entries.last().map(|e| e.recorded_at.clone()),
+        last_entry_hash: entries.last().map(|e| e.entry_hash.clone()),
+    })
+}
+
+// ==================== Tauri Commands ====================
+
+/// Checks the ledger of a data source (the active one by default): whether its hash chain is
+/// intact, and which dates changed since the batches that last recorded them.
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn verify_ledger(sourceId: Option<String>) -> AppResult<LedgerVerification> {
+    let source = sources::resolve_source(sourceId.as_deref())?;
+    tokio::task::spawn_blocking(move || verify(&source)).await?
+}
+
+/// Records the current state of a data source's sales data, e.g. right after turning the
+/// ledger on, so later changes are checked against it.
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn record_ledger_entry(sourceId: Option<String>) -> AppResult<Option<i64>> {
+    if !settings::load_settings().ledger_enabled {
+        return Err(AppError::InvalidInput(
+            "The ledger is turned off in the settings".to_string(),
+        ));
+    }
+    let source = sources::resolve_source(sourceId.as_deref())?;
+    tokio::task::spawn_blocking(move || record_batch(&source, "manual")).await?
+}
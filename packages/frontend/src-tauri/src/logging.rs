@@ -0,0 +1,212 @@
+// Application logging
+//
+// Everything the backend logs goes through `tracing`. Log lines are written as JSON to a daily
+// rotating file under <app data>/logs (the last 7 days are kept), and to stdout in debug builds.
+// Long-running operations (fetch, init, CLI install, update checks) run inside a span carrying
+// an `op_id`, so all lines of one failed sync can be found after the fact.
+
+use crate::types::{AppError, AppResult};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter};
+
+const LOG_FILE_PREFIX: &str = "steam-sales";
+const LOG_FILE_SUFFIX: &str = "log";
+const MAX_LOG_FILES: usize = 7;
+// Used unless RUST_LOG is set
+const DEFAULT_FILTER: &str = "info,steam_sales_analyzer=debug";
+
+static LOG_DIR: OnceLock<PathBuf> = OnceLock::new();
+// Flushes the background writer when dropped, so it lives for the whole run
+static LOG_GUARD: OnceLock<WorkerGuard> = OnceLock::new();
+static OPERATION_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    pub operation_id: Option<String>,
+    /// Structured fields of the event other than the message
+    pub fields: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Sets up the global subscriber. Must run before anything logs.
+pub fn init(app: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
+    let log_dir = app.path().app_data_dir()?.join("logs");
+    fs::create_dir_all(&log_dir)?;
+
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .max_log_files(MAX_LOG_FILES)
+        .build(&log_dir)?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let file_layer = fmt::layer()
+        .json()
+        .with_current_span(true)
+        .with_span_list(true)
+        .with_writer(writer);
+    let stdout_layer = cfg!(debug_assertions).then(fmt::layer);
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(file_layer)
+        .with(stdout_layer)
+        .try_init()?;
+
+    let _ = LOG_GUARD.set(guard);
+    let _ = LOG_DIR.set(log_dir);
+    Ok(())
+}
+
+/// A short id for one operation, e.g. "fetch-1a2b3c0007". Operations record it as the `op_id`
+/// field of their span: `#[tracing::instrument(fields(op_id = %new_operation_id("fetch")))]`.
+pub fn new_operation_id(kind: &str) -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let seq = OPERATION_COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("{}-{:06x}{:04x}", kind, secs & 0xff_ffff, seq & 0xffff)
+}
+
+// Lower is more verbose; unknown levels sort with INFO
+fn level_rank(level: &str) -> u8 {
+    match level.to_ascii_uppercase().as_str() {
+        "TRACE" => 0,
+        "DEBUG" => 1,
+        "WARN" => 3,
+        "ERROR" => 4,
+        _ => 2,
+    }
+}
+
+fn parse_line(line: &str) -> Option<LogEntry> {
+    let value: serde_json::Value = serde_json::from_str(line).ok()?;
+    let mut fields = value.get("fields")?.as_object()?.clone();
+    let message = match fields.remove("message") {
+        Some(serde_json::Value::String(s)) => s,
+        Some(other) => other.to_string(),
+        None => String::new(),
+    };
+    // The innermost span with an op_id is the operation the line belongs to
+    let operation_id = value
+        .get("spans")
+        .and_then(|spans| spans.as_array())
+        .and_then(|spans| {
+            spans
+                .iter()
+                .rev()
+                .find_map(|span| span.get("op_id").and_then(|id| id.as_str()))
+        })
+        .map(str::to_string);
+    let text = |key: &str| {
+        value
+            .get(key)
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string()
+    };
+    Some(LogEntry {
+        timestamp: text("timestamp"),
+        level: text("level"),
+        target: text("target"),
+        message,
+        operation_id,
+        fields,
+    })
+}
+
+fn log_files_newest_first(dir: &PathBuf) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| {
+                    p.file_name()
+                        .and_then(|n| n.to_str())
+                        .is_some_and(|n| n.starts_with(LOG_FILE_PREFIX))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    // File names end in the date, so name order is age order
+    files.sort();
+    files.reverse();
+    files
+}
+
+/// The most recent entries at or above `min_level`, newest first.
+pub fn read_recent_logs(min_level: &str, limit: usize) -> AppResult<Vec<LogEntry>> {
+    let dir = LOG_DIR
+        .get()
+        .ok_or_else(|| AppError::Internal("Logging is not initialized".to_string()))?;
+    let min_rank = level_rank(min_level);
+    let mut entries = Vec::new();
+
+    for path in log_files_newest_first(dir) {
+        let file = fs::File::open(&path)?;
+        let lines: Vec<String> = BufReader::new(file).lines().map_while(Result::ok).collect();
+        for line in lines.iter().rev() {
+            if let Some(entry) = parse_line(line) {
+                if level_rank(&entry.level) >= min_rank {
+                    entries.push(entry);
+                    if entries.len() >= limit {
+                        return Ok(entries);
+                    }
+                }
+            }
+        }
+    }
+    Ok(entries)
+}
+
+// ==================== Tauri Commands ====================
+
+#[tauri::command]
+pub async fn get_recent_logs(
+    level: Option<String>,
+    limit: Option<u32>,
+) -> AppResult<Vec<LogEntry>> {
+    let level = level.unwrap_or_else(|| "info".to_string());
+    let limit = limit.unwrap_or(200) as usize;
+    tokio::task::spawn_blocking(move || read_recent_logs(&level, limit))
+        .await?
+}
+
+/// Opens the log directory in the system file manager and returns its path.
+#[tauri::command]
+pub async fn open_log_folder() -> AppResult<String> {
+    let dir = LOG_DIR
+        .get()
+        .ok_or_else(|| AppError::Internal("Logging is not initialized".to_string()))?;
+    let opener = if cfg!(target_os = "macos") {
+        "open"
+    } else if cfg!(windows) {
+        "explorer"
+    } else {
+        "xdg-open"
+    };
+    std::process::Command::new(opener)
+        .arg(dir)
+        .spawn()
+        .map_err(|e| AppError::Io(format!("Failed to open log folder: {}", e)))?;
+    Ok(dir.to_string_lossy().to_string())
+}
@@ -0,0 +1,266 @@
+// Refreshing product names from Steam
+//
+// lookup_apps and lookup_packages are written by the CLI when it first sees a product and keep
+// that name, so a package Steam renamed later shows its old name forever. `refresh_lookups`
+// fetches the detailed sales of the last few days from the partner API (see reconcile.rs) and
+// upserts the app_info/package_info names it returns into lookup_app_names and
+// lookup_package_names of every data source synced with the key. These tables belong to the app
+// (the CLI never touches them), and every query that shows a product name prefers them over the
+// CLI's tables: see `app_names_table` and `package_names_table`, and getAppsLookup in the
+// frontend's sqlite-client.ts for databases opened in the browser.
+//
+// Only names that changed are written, so refreshing often is cheap for the database; the API
+// calls are what takes time, one or more per date.
+
+use crate::database::{begin_write, BUSY_TIMEOUT};
+use crate::keys;
+use crate::reconcile::{PartnerApi, MAX_PAGES};
+use crate::reporting_views;
+use crate::schema::table_exists;
+use crate::settings;
+use crate::sources::{self, DataSource};
+use crate::types::{AppError, AppResult, Date};
+use rusqlite::{params, Connection, OpenFlags};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use tracing::info;
+
+pub const APP_NAMES_TABLE: &str = "lookup_app_names";
+pub const PACKAGE_NAMES_TABLE: &str = "lookup_package_names";
+
+const DEFAULT_DAYS: u32 = 7;
+const MAX_DAYS: u32 = 92;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LookupRefreshSummary {
+    pub start_date: Date,
+    pub end_date: Date,
+    /// Distinct names Steam returned for the dates
+    pub apps_seen: usize,
+    pub packages_seen: usize,
+    /// Names added or changed, summed over the updated sources
+    pub apps_updated: usize,
+    pub packages_updated: usize,
+    /// Ids of the data sources whose databases were updated
+    pub sources: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+struct SteamNames {
+    apps: BTreeMap<i64, String>,
+    packages: BTreeMap<i64, String>,
+}
+
+fn ensure_tables(conn: &Connection) -> rusqlite::Result<()> {
+    let existed = table_exists(conn, APP_NAMES_TABLE);
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS lookup_app_names (
+            appid INTEGER PRIMARY KEY,
+            app_name TEXT NOT NULL,
+            updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+        );
+        CREATE TABLE IF NOT EXISTS lookup_package_names (
+            packageid INTEGER PRIMARY KEY,
+            package_name TEXT NOT NULL,
+            updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+        );",
+    )?;
+    // The reporting views join the names from the tables they found when they were created
+    if !existed {
+        reporting_views::refresh_if_created(conn);
+    }
+    Ok(())
+}
+
+// A table expression with the columns `id_col` and `name_col`: the refreshed names, and the
+// CLI's names for the products that weren't refreshed
+fn names_table(
+    conn: &Connection,
+    refreshed: &str,
+    cli: &str,
+    id_col: &str,
+    name_col: &str,
+) -> Option<String> {
+    match (table_exists(conn, refreshed), table_exists(conn, cli)) {
+        (true, true) => Some(format!(
+            "(SELECT {id}, {name} FROM {refreshed}
+              UNION ALL
+              SELECT {id}, {name} FROM {cli} WHERE {id} NOT IN (SELECT {id} FROM {refreshed}))",
+            id = id_col,
+            name = name_col,
+            refreshed = refreshed,
+            cli = cli
+        )),
+        (true, false) => Some(refreshed.to_string()),
+        (false, true) => Some(cli.to_string()),
+        (false, false) => None,
+    }
+}
+
+/// What to join for app names (columns `appid`, `app_name`) in place of lookup_apps, or None if
+/// the database has no app names.
+pub(crate) fn app_names_table(conn: &Connection) -> Option<String> {
+    names_table(conn, APP_NAMES_TABLE, "lookup_apps", "appid", "app_name")
+}
+
+/// What to join for package names (columns `packageid`, `package_name`) in place of
+/// lookup_packages, or None if the database has no package names.
+pub(crate) fn package_names_table(conn: &Connection) -> Option<String> {
+    names_table(
+        conn,
+        PACKAGE_NAMES_TABLE,
+        "lookup_packages",
+        "packageid",
+        "package_name",
+    )
+}
+
+async fn fetch_names(key: &str, start_date: Date, end_date: Date) -> AppResult<SteamNames> {
+    let api = PartnerApi::new()?;
+    let mut names = SteamNames::default();
+
+    let mut date = start_date;
+    while date <= end_date {
+        let mut highwatermark_id = 0;
+        for _ in 0..MAX_PAGES {
+            let page = api.fetch_page(key, date, highwatermark_id).await?;
+            for app in &page.app_info {
+                names
+                    .apps
+                    .insert(app.appid, app.app_name.trim().to_string());
+            }
+            for package in &page.package_info {
+                names
+                    .packages
+                    .insert(package.packageid, package.package_name.trim().to_string());
+            }
+            match page.next_page(highwatermark_id) {
+                Some(next) => highwatermark_id = next,
+                None => break,
+            }
+        }
+        date = date.add_days(1);
+    }
+    names.apps.retain(|_, name| !name.is_empty());
+    names.packages.retain(|_, name| !name.is_empty());
+    Ok(names)
+}
+
+// Upserts the names into the source's database; returns how many apps and packages changed
+fn store_names(db_path: &Path, names: &SteamNames) -> AppResult<(usize, usize)> {
+    let conn = Connection::open_with_flags(
+        db_path,
+        OpenFlags::default().difference(OpenFlags::SQLITE_OPEN_CREATE),
+    )?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    ensure_tables(&conn)?;
+    let tx = begin_write(&conn)?;
+    let mut apps_updated = 0;
+    let mut packages_updated = 0;
+    {
+        let mut upsert_app = tx.prepare(
+            "INSERT INTO lookup_app_names (appid, app_name) VALUES (?1, ?2)
+             ON CONFLICT(appid) DO UPDATE SET
+                app_name = excluded.app_name,
+                updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+             WHERE app_name != excluded.app_name",
+        )?;
+        for (appid, name) in &names.apps {
+            apps_updated += upsert_app.execute(params![appid, name])?;
+        }
+        let mut upsert_package = tx.prepare(
+            "INSERT INTO lookup_package_names (packageid, package_name) VALUES (?1, ?2)
+             ON CONFLICT(packageid) DO UPDATE SET
+                package_name = excluded.package_name,
+                updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+             WHERE package_name != excluded.package_name",
+        )?;
+        for (packageid, name) in &names.packages {
+            packages_updated += upsert_package.execute(params![packageid, name])?;
+        }
+    }
+    tx.commit()?;
+    Ok((apps_updated, packages_updated))
+}
+
+// The writable sources synced with the key whose databases exist
+fn sources_for_key(key_id: &str) -> AppResult<Vec<DataSource>> {
+    let matching: Vec<DataSource> = sources::list_sources()?
+        .into_iter()
+        .filter(|s| s.key_id.as_deref() == Some(key_id) && !s.read_only && s.database_exists)
+        .collect();
+    if matching.is_empty() {
+        return Err(AppError::NotFound(format!(
+            "No data source has been synced with key '{}'",
+            key_id
+        )));
+    }
+    Ok(matching)
+}
+
+// ==================== Tauri Commands ====================
+
+/// Fetches the product names Steam reports for the last `days` days (7 by default) with the key
+/// `apiKeyId` (the key the CLI was last initialized with if omitted), and stores the new and
+/// renamed ones in every data source synced with that key.
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn refresh_lookups(
+    apiKeyId: Option<String>,
+    days: Option<u32>,
+) -> AppResult<LookupRefreshSummary> {
+    let days = days.unwrap_or(DEFAULT_DAYS);
+    if !(1..=MAX_DAYS).contains(&days) {
+        return Err(AppError::InvalidInput(format!(
+            "Refresh between 1 and {} days of names",
+            MAX_DAYS
+        )));
+    }
+    let key_id = match apiKeyId {
+        Some(key_id) => key_id,
+        None => keys::get_current_key_id()?
+            .ok_or_else(|| AppError::InvalidInput("No API key has been set up yet".to_string()))?,
+    };
+    let targets = sources_for_key(&key_id)?;
+    let key = keys::get_key_secret(&key_id)?.ok_or_else(|| {
+        AppError::InvalidInput(format!(
+            "API key '{}' isn't saved on this computer; initialize with it again first",
+            key_id
+        ))
+    })?;
+
+    let end_date = Date::today_in(&settings::load_settings().reporting_timezone);
+    let start_date = end_date.add_days(-(days as i64 - 1));
+    let fetched = fetch_names(&key, start_date, end_date).await;
+    let usage = fetched.as_ref().map(|_| ()).map_err(AppError::clone);
+    if let Err(e) = keys::record_key_usage(&key_id, "refresh_lookups", &usage) {
+        tracing::warn!("Failed to record key usage for refresh_lookups: {}", e);
+    }
+    let names = fetched?;
+
+    tokio::task::spawn_blocking(move || {
+        let mut summary = LookupRefreshSummary {
+            start_date,
+            end_date,
+            apps_seen: names.apps.len(),
+            packages_seen: names.packages.len(),
+            apps_updated: 0,
+            packages_updated: 0,
+            sources: Vec::new(),
+        };
+        for source in targets {
+            let (apps, packages) = store_names(Path::new(&source.db_path), &names)?;
+            summary.apps_updated += apps;
+            summary.packages_updated += packages;
+            summary.sources.push(source.id);
+        }
+        info!(
+            "Refreshed product names: {} apps and {} packages changed",
+            summary.apps_updated, summary.packages_updated
+        );
+        Ok(summary)
+    })
+    .await?
+}
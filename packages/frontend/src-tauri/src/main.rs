@@ -1,122 +1,177 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod adjustments;
+mod annotations;
+mod api_server;
+mod anomalies;
+mod app_menu;
+mod app_metadata;
+mod app_updater;
+mod app_state;
+mod bench;
+mod benchmarks;
+mod calendar;
+mod change_log;
 mod cli;
+mod clipboard;
+mod cohorts;
+mod compare;
+mod countries;
+mod custom_metrics;
+mod dashboard_json;
 mod database;
+mod db_watcher;
+mod decay;
+mod demo_data;
+mod deeplink;
+mod diagnostics;
+mod discount_calendar;
+mod email;
+mod export;
+mod factory_reset;
+mod goals;
+mod headless;
+mod impact;
+mod itch;
+mod keys;
+mod ledger;
+mod logging;
+mod lookup_refresh;
+mod package_economics;
+mod perf;
+mod platform_trend;
+mod player_stats;
+mod portfolio;
+mod precompute;
+mod presentation;
+mod price_regions;
+mod product_groups;
+mod product_search;
+mod quarantine;
 mod queries;
+mod query_cache;
+mod query_control;
+mod read_snapshot;
+mod reconcile;
+mod replication;
+mod reporting_views;
+mod reviews;
+mod royalties;
+mod sale_types;
+mod schema;
+mod scheduler;
+mod seasonality;
+mod secure_storage;
+mod settings;
+mod single_instance;
+mod share_bundle;
+mod snapshot_diff;
+mod sources;
+mod sql_console;
+mod status;
+mod sync;
+mod tax;
+mod traffic;
+mod tray;
+mod types;
+mod updater;
+mod view_windows;
+mod webhooks;
+mod window_state;
 
+use adjustments::*;
+use annotations::*;
+use api_server::*;
+use anomalies::*;
+use app_metadata::*;
+use app_updater::*;
+use benchmarks::*;
+use calendar::*;
+use change_log::*;
 use cli::*;
+use clipboard::*;
+use cohorts::*;
+use compare::*;
+use custom_metrics::*;
+use dashboard_json::*;
 use database::*;
+use decay::*;
+use demo_data::*;
+use deeplink::*;
+use diagnostics::*;
+use discount_calendar::*;
+use email::*;
+use factory_reset::*;
+use goals::*;
+use impact::*;
+use itch::*;
+use keys::*;
+use ledger::*;
+use logging::*;
+use lookup_refresh::*;
+use package_economics::*;
+use perf::*;
+use platform_trend::*;
+use player_stats::*;
+use portfolio::*;
+use price_regions::*;
+use product_groups::*;
+use product_search::*;
+use quarantine::*;
 use queries::*;
-use serde_json::json;
-use std::fs;
-use std::time::Duration;
-use tauri::{async_runtime, Manager, WindowEvent};
-use tauri_plugin_window_state::StateFlags;
-use tokio::time::sleep;
-
-// Helper function to manually save window state with outer_size
-fn save_window_state_with_outer_size(
-    app: &tauri::AppHandle,
-    window: &tauri::WebviewWindow,
-) -> Result<(), Box<dyn std::error::Error>> {
-    // Get outer_size and position
-    let outer_size = window.outer_size()?;
-    let outer_position = window.outer_position()?;
-
-    // Get app data directory
-    let app_data_dir = app.path().app_data_dir()?;
-    fs::create_dir_all(&app_data_dir)?;
-
-    // Construct state file path (plugin uses "window-state.json" by default)
-    let state_file = app_data_dir.join("window-state.json");
-
-    // Read existing state or create new
-    let mut state: serde_json::Value = if state_file.exists() {
-        let content = fs::read_to_string(&state_file)?;
-        serde_json::from_str(&content).unwrap_or(json!({}))
-    } else {
-        json!({})
-    };
-
-    // Get window label
-    let window_label = window.label();
-
-    // Update state for this window with outer_size
-    state[window_label] = json!({
-        "width": outer_size.width,
-        "height": outer_size.height,
-        "x": outer_position.x,
-        "y": outer_position.y,
-    });
-
-    // Write state file
-    fs::write(&state_file, serde_json::to_string_pretty(&state)?)?;
-
-    Ok(())
-}
-
-// Helper function to manually restore window state with outer_size
-fn restore_window_state_with_outer_size(
-    app: &tauri::AppHandle,
-    window: &tauri::WebviewWindow,
-) -> Result<(), Box<dyn std::error::Error>> {
-    // Get app data directory
-    let app_data_dir = app.path().app_data_dir()?;
-    let state_file = app_data_dir.join("window-state.json");
-
-    // Read state file
-    if !state_file.exists() {
-        return Ok(()); // No saved state
-    }
-
-    let content = fs::read_to_string(&state_file)?;
-    let state: serde_json::Value = serde_json::from_str(&content)?;
-
-    // Get window label
-    let window_label = window.label();
-
-    // Get saved state for this window
-    if let Some(window_state) = state.get(window_label) {
-        if let (Some(width), Some(height), Some(x), Some(y)) = (
-            window_state
-                .get("width")
-                .and_then(|v| v.as_u64().map(|n| n as u32)),
-            window_state
-                .get("height")
-                .and_then(|v| v.as_u64().map(|n| n as u32)),
-            window_state
-                .get("x")
-                .and_then(|v| v.as_i64().map(|n| n as i32)),
-            window_state
-                .get("y")
-                .and_then(|v| v.as_i64().map(|n| n as i32)),
-        ) {
-            // Restore window size and position using outer_size values (physical pixels)
-            use tauri::PhysicalSize;
-            window.set_size(PhysicalSize::new(width, height))?;
-            use tauri::PhysicalPosition;
-            window.set_position(PhysicalPosition::new(x, y))?;
-        }
-    }
-
-    Ok(())
-}
+use query_cache::*;
+use query_control::*;
+use read_snapshot::*;
+use reconcile::*;
+use replication::*;
+use reporting_views::*;
+use reviews::*;
+use royalties::*;
+use sale_types::*;
+use schema::*;
+use seasonality::*;
+use settings::*;
+use share_bundle::*;
+use snapshot_diff::*;
+use sources::*;
+use sql_console::*;
+use status::*;
+use sync::*;
+use tax::*;
+use traffic::*;
+use updater::*;
+use view_windows::*;
+use webhooks::*;
+use tauri::{Manager, RunEvent, WindowEvent};
+use window_state::WindowStates;
 
 fn main() {
+    // `--sync` / `--export` run without a window
+    if let Some(code) = headless::run_from_args() {
+        std::process::exit(code);
+    }
     tauri::Builder::default()
+        // First: a second instance hands its arguments (e.g. a steamsales:// link) to the
+        // running one and exits here
+        .plugin(single_instance::plugin())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
-        .plugin(
-            tauri_plugin_window_state::Builder::default()
-                .with_state_flags(StateFlags::SIZE | StateFlags::POSITION)
-                .build(),
-        )
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(tauri_plugin_deep_link::init())
+        .plugin(tauri_plugin_updater::Builder::new().build())
+        .manage(SyncState::default())
+        .manage(QueryCache::default())
+        .manage(RunningQueries::default())
+        .manage(PendingWindowViews::default())
         .invoke_handler(tauri::generate_handler![
             get_cli_status,
             check_cli_update,
+            get_cli_include_prereleases,
+            set_cli_include_prereleases,
             get_latest_github_version,
             download_cli,
+            install_cli_from_file,
             init_cli,
             fetch_data,
             get_database_path_str,
@@ -131,34 +186,164 @@ fn main() {
             query_countries_lookup,
             query_dates_list,
             query_raw_data_by_date,
+            stream_raw_data_by_date,
+            query_sources,
             query_packages_lookup,
             query_packages_by_app,
             query_product_stats,
-            query_launch_comparison
+            query_launch_comparison,
+            list_api_keys,
+            get_key_usage,
+            get_sync_queue_status,
+            cancel_fetch,
+            pause_sync,
+            resume_sync,
+            get_alerts,
+            acknowledge_alert,
+            run_anomaly_detection,
+            list_goals,
+            create_goal,
+            update_goal,
+            delete_goal,
+            query_goal_progress,
+            list_milestones,
+            list_annotations,
+            create_annotation,
+            update_annotation,
+            delete_annotation,
+            query_event_impact,
+            list_data_sources,
+            add_data_source,
+            remove_data_source,
+            set_active_data_source,
+            get_cli_auto_update,
+            set_cli_auto_update,
+            get_cli_update_history,
+            diagnose_cli,
+            get_recent_logs,
+            open_log_folder,
+            get_app_status,
+            get_all_settings,
+            get_setting,
+            set_setting,
+            reset_settings,
+            take_pending_deep_link,
+            get_api_server_info,
+            set_api_server_enabled,
+            regenerate_api_server_token,
+            list_webhooks,
+            add_webhook,
+            set_webhook_enabled,
+            remove_webhook,
+            test_webhook,
+            get_smtp_settings,
+            set_smtp_settings,
+            clear_smtp_settings,
+            email_report,
+            list_email_schedules,
+            add_email_schedule,
+            remove_email_schedule,
+            has_itch_api_key,
+            set_itch_api_key,
+            clear_itch_api_key,
+            sync_itch,
+            list_itch_games,
+            set_itch_game_app,
+            get_app_metadata,
+            refresh_app_metadata,
+            refresh_reviews,
+            query_reviews_vs_sales,
+            query_engagement_vs_revenue,
+            import_traffic_csv,
+            query_funnel,
+            run_readonly_sql,
+            describe_schema,
+            query_tax_summary,
+            clear_query_cache,
+            get_quarantined_rows,
+            reprocess_quarantine,
+            reconcile,
+            refresh_lookups,
+            search_products,
+            list_benchmarks,
+            save_benchmark,
+            delete_benchmark,
+            verify_ledger,
+            record_ledger_entry,
+            factory_reset,
+            list_royalty_rules,
+            create_royalty_rule,
+            update_royalty_rule,
+            delete_royalty_rule,
+            generate_royalty_statement,
+            query_cohorts,
+            query_weekday_profile,
+            query_calendar_heatmap,
+            query_by_price_region,
+            query_compare,
+            list_product_groups,
+            create_product_group,
+            update_product_group,
+            delete_product_group,
+            list_custom_metrics,
+            create_custom_metric,
+            update_custom_metric,
+            delete_custom_metric,
+            query_decay_curve,
+            query_portfolio,
+            query_sales_since,
+            get_changes_since,
+            set_sales_change_log_enabled,
+            query_package_economics,
+            rebuild_discount_calendar,
+            query_discount_calendar,
+            query_platform_share_trend,
+            query_sale_type_summary,
+            export_share_bundle,
+            import_share_bundle,
+            export_dashboard_json,
+            create_reporting_views,
+            generate_demo_data,
+            copy_query_to_clipboard,
+            diff_snapshots,
+            apply_manual_adjustment,
+            revert_manual_adjustment,
+            list_manual_adjustments,
+            open_product_window,
+            open_raw_data_window,
+            take_window_view,
+            check_app_update,
+            download_app_update,
+            restart_to_update,
+            get_performance_report,
+            set_perf_log_enabled,
+            clear_performance_log,
+            cancel_query,
+            get_read_snapshot
         ])
+        .menu(app_menu::build)
+        .on_menu_event(app_menu::handle)
         .setup(|app| {
-            if let Some(window) = app.get_webview_window("main") {
-                // The plugin will restore the window state automatically, but it uses inner_size
-                // So we need to manually restore with outer_size after the plugin does its thing
-                // We'll do this after a short delay to ensure the plugin has finished restoring
-                let app_handle = app.handle().clone();
-                let window_handle = window.clone();
-
-                // Restore window state manually with outer_size after plugin restoration
-                // The plugin restores with inner_size, so we override it with outer_size
-                async_runtime::spawn(async move {
-                    // Wait longer for plugin to finish restoring and window to be fully initialized
-                    sleep(Duration::from_millis(300)).await;
-                    // Now manually restore with the correct outer_size from our saved state
-                    if let Err(e) =
-                        restore_window_state_with_outer_size(&app_handle, &window_handle)
-                    {
-                        eprintln!("Failed to manually restore window state: {:?}", e);
-                    }
-                });
+            // Before anything else, so startup problems end up in the log file too
+            if let Err(e) = logging::init(app.handle()) {
+                eprintln!("Failed to initialize logging: {}", e);
+            }
+            updater::start(app.handle().clone());
+            app_updater::start(app.handle().clone());
+            scheduler::start(app.handle().clone());
+            db_watcher::start(app.handle().clone());
+            deeplink::init(app.handle());
+            single_instance::hold_lock(app.handle());
+            tauri::async_runtime::spawn_blocking(itch::move_from_sales_databases);
+            api_server::start_if_enabled();
+            if let Err(e) = tray::init(app.handle()) {
+                tracing::warn!("Failed to create tray icon: {}", e);
+            }
 
-                // Restore window state (plugin handles this automatically, but we ensure it's visible)
-                window.show().unwrap_or_default();
+            app.manage(WindowStates::load(app.handle()));
+            if let Some(window) = app.get_webview_window("main") {
+                // The window is created hidden, so it only shows up once in its saved place
+                window_state::track(&window);
 
                 // Only open devtools if DEBUG_DEVTOOLS environment variable is set
                 #[cfg(debug_assertions)]
@@ -168,41 +353,25 @@ fn main() {
                     }
                 }
 
-                // Save window state using outer_size to include devtools
-                let app_handle = app.handle().clone();
                 let window_handle = window.clone();
-
                 window.on_window_event(move |event| {
-                    match event {
-                        WindowEvent::Resized(_) => {
-                            let handle = app_handle.clone();
-                            let win = window_handle.clone();
-                            // Use async_runtime to save after a delay, ensuring we capture outer_size
-                            async_runtime::spawn(async move {
-                                // Wait longer to ensure window has finished resizing (including devtools)
-                                // Devtools can take time to render and affect the window layout
-                                sleep(Duration::from_millis(500)).await;
-                                // Manually save with outer_size (bypassing plugin's inner_size issue)
-                                if let Err(e) = save_window_state_with_outer_size(&handle, &win) {
-                                    eprintln!("Failed to manually save window state: {:?}", e);
-                                }
-                            });
-                        }
-                        WindowEvent::CloseRequested { .. } => {
-                            // Save state immediately when app is closing to capture final size
-                            // Manually save with outer_size to ensure we capture the total window bounds including devtools
-                            if let Err(e) =
-                                save_window_state_with_outer_size(&app_handle, &window_handle)
-                            {
-                                eprintln!("Failed to save window state on close: {:?}", e);
-                            }
+                    if let WindowEvent::CloseRequested { api, .. } = event {
+                        if tray::should_close_to_tray() {
+                            api.prevent_close();
+                            let _ = window_handle.hide();
                         }
-                        _ => {}
                     }
                 });
             }
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Quitting from the tray or the menu closes no window first
+            if let RunEvent::Exit = event {
+                app.state::<WindowStates>().save();
+                app_updater::install_pending();
+            }
+        });
 }
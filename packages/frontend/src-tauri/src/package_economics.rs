@@ -0,0 +1,229 @@
+// Unit economics of a package
+//
+// Everything needed to decide whether to retire or re-price an edition, for one package: units
+// and the price actually paid per discount tier, the share of units returned, and revenue per
+// Steam price region (see price_regions.rs). Prices are gross revenue per gross unit sold, i.e.
+// what buyers paid including tax. Grant and activation line items are left out, as in the
+// summaries, and so is the day Steam is still reporting.
+
+use crate::cohorts::{discount_tier_expr, DISCOUNT_TIERS};
+use crate::presentation;
+use crate::price_regions;
+use crate::queries::{self, get_connection, get_package_name_column, line_item_category_expr};
+use crate::schema::{first_existing_column, table_exists};
+use crate::types::{AppError, AppResult};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscountTierEconomics {
+    /// "none", "1-24", "25-49", "50-74" or "75+"
+    pub tier: String,
+    pub label: String,
+    pub gross_units: i64,
+    pub net_units: i64,
+    pub revenue: f64,
+    /// Gross revenue per gross unit sold; None without units
+    pub average_price: Option<f64>,
+    /// Share of the package's gross units, 0-1
+    pub unit_share: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegionEconomics {
+    /// e.g. "EUR" or "LATAM - USD"; "Unknown" for rows without a known country
+    pub price_region: String,
+    pub currency: Option<String>,
+    pub gross_units: i64,
+    pub revenue: f64,
+    pub average_price: Option<f64>,
+    /// Share of the package's revenue, 0-1
+    pub revenue_share: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageEconomics {
+    pub package_id: i64,
+    pub package_name: Option<String>,
+    pub first_sale: Option<String>,
+    pub last_sale: Option<String>,
+    pub total_revenue: f64,
+    pub net_revenue: f64,
+    pub gross_units: i64,
+    pub returned_units: i64,
+    pub net_units: i64,
+    /// Gross revenue per gross unit sold; None without units
+    pub average_price: Option<f64>,
+    /// Returned units per gross unit sold, 0-1; None without units
+    pub return_rate: Option<f64>,
+    /// In tier order; empty if the database has no discount column
+    pub by_discount: Vec<DiscountTierEconomics>,
+    /// Largest revenue first
+    pub by_region: Vec<RegionEconomics>,
+}
+
+fn per_unit(revenue: f64, units: i64) -> Option<f64> {
+    (units > 0).then(|| revenue / units as f64)
+}
+
+fn package_name(conn: &Connection, package_id: i64) -> AppResult<Option<String>> {
+    let sql = if table_exists(conn, "lookup_packages") {
+        "SELECT package_name FROM lookup_packages WHERE packageid = ?1".to_string()
+    } else if let Some(column) = get_package_name_column(conn) {
+        format!(
+            "SELECT MAX(NULLIF({}, '')) FROM sales_data WHERE packageid = ?1",
+            column
+        )
+    } else {
+        return Ok(None);
+    };
+    Ok(conn
+        .query_row(&sql, [package_id], |r| r.get(0))
+        .optional()?
+        .flatten())
+}
+
+pub fn get_package_economics(package_id: i64) -> AppResult<PackageEconomics> {
+    let conn = get_connection()?;
+    let last_day = queries::last_complete_day(None)
+        .map(|d| d.to_string())
+        .unwrap_or_else(|| "9999-12-31".to_string());
+    let condition = format!(
+        "packageid = ?1 AND date <= ?2 AND {} IN ('sale', 'return')",
+        line_item_category_expr(&conn)
+    );
+
+    let (total_revenue, net_revenue, gross_units, returned_units, net_units, first, last) = conn
+        .query_row(
+            &format!(
+                "SELECT COALESCE(SUM(CAST(gross_sales_usd AS REAL)), 0),
+                    COALESCE(SUM(CAST(net_sales_usd AS REAL)), 0),
+                    COALESCE(SUM(gross_units_sold), 0), COALESCE(SUM(gross_units_returned), 0),
+                    COALESCE(SUM(net_units_sold), 0), MIN(date), MAX(date)
+                 FROM sales_data WHERE {}",
+                condition
+            ),
+            params![package_id, last_day],
+            |r| {
+                Ok((
+                    r.get::<_, f64>(0)?,
+                    r.get::<_, f64>(1)?,
+                    r.get::<_, i64>(2)?,
+                    r.get::<_, i64>(3)?,
+                    r.get::<_, i64>(4)?,
+                    r.get::<_, Option<String>>(5)?,
+                    r.get::<_, Option<String>>(6)?,
+                ))
+            },
+        )?;
+    if first.is_none() {
+        return Err(AppError::NotFound(format!(
+            "No sales of package {}",
+            package_id
+        )));
+    }
+
+    let mut by_discount = Vec::new();
+    if let Some(column) = first_existing_column(
+        &conn,
+        "sales_data",
+        &["total_discount_percentage", "discount_percentage"],
+    ) {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} AS tier, COALESCE(SUM(gross_units_sold), 0), COALESCE(SUM(net_units_sold), 0),
+                COALESCE(SUM(CAST(gross_sales_usd AS REAL)), 0)
+             FROM sales_data WHERE {}
+             GROUP BY tier",
+            discount_tier_expr(&column),
+            condition
+        ))?;
+        let rows = stmt
+            .query_map(params![package_id, last_day], |r| {
+                Ok((
+                    r.get::<_, String>(0)?,
+                    r.get::<_, i64>(1)?,
+                    r.get::<_, i64>(2)?,
+                    r.get::<_, f64>(3)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        for (tier, label) in DISCOUNT_TIERS {
+            let Some((_, tier_gross, tier_net, revenue)) = rows.iter().find(|row| row.0 == *tier)
+            else {
+                continue;
+            };
+            by_discount.push(DiscountTierEconomics {
+                tier: tier.to_string(),
+                label: label.to_string(),
+                gross_units: *tier_gross,
+                net_units: *tier_net,
+                revenue: *revenue,
+                average_price: per_unit(*revenue, *tier_gross),
+                unit_share: if gross_units > 0 {
+                    *tier_gross as f64 / gross_units as f64
+                } else {
+                    0.0
+                },
+            });
+        }
+    }
+
+    price_regions::ensure_lookup_table(&conn)?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT COALESCE(p.price_region, 'Unknown') AS region, MAX(p.currency),
+            COALESCE(SUM(s.gross_units_sold), 0), COALESCE(SUM(CAST(s.gross_sales_usd AS REAL)), 0)
+         FROM (SELECT * FROM sales_data WHERE {}) s
+         LEFT JOIN lookup_price_regions p ON p.country_code = s.country_code
+         GROUP BY region
+         ORDER BY 4 DESC",
+        condition
+    ))?;
+    let by_region = stmt
+        .query_map(params![package_id, last_day], |r| {
+            let gross_units: i64 = r.get(2)?;
+            let revenue: f64 = r.get(3)?;
+            Ok(RegionEconomics {
+                price_region: r.get(0)?,
+                currency: r.get(1)?,
+                gross_units,
+                revenue,
+                average_price: per_unit(revenue, gross_units),
+                revenue_share: if total_revenue > 0.0 {
+                    revenue / total_revenue
+                } else {
+                    0.0
+                },
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(PackageEconomics {
+        package_id,
+        package_name: package_name(&conn, package_id)?,
+        first_sale: first,
+        last_sale: last,
+        total_revenue,
+        net_revenue,
+        gross_units,
+        returned_units,
+        net_units,
+        average_price: per_unit(total_revenue, gross_units),
+        return_rate: (gross_units > 0).then(|| returned_units as f64 / gross_units as f64),
+        by_discount,
+        by_region,
+    })
+}
+
+// ==================== Tauri Commands ====================
+
+/// Units by discount tier, average price, return rate and revenue by price region of a package.
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn query_package_economics(packageId: i64) -> AppResult<PackageEconomics> {
+    tokio::task::spawn_blocking(move || get_package_economics(packageId))
+        .await?
+        .and_then(presentation::present)
+}
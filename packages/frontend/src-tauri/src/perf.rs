@@ -0,0 +1,276 @@
+// Local performance log
+//
+// Opt-in timing of the dashboard queries and CLI fetches, so a slow dashboard can be looked into
+// with numbers instead of guesses. It's off unless `perfLogEnabled` is set, best through
+// `set_perf_log_enabled`: through `set_setting` the change takes up to half a minute to apply. Every timed call is one row
+// of `perf_log` in the app database: the command, how long it took, how many rows it returned
+// and whether it failed. Nothing leaves the machine; `get_performance_report` summarizes the log
+// per command (p50/p95/max latency, average row count) for the user to look at or paste into a
+// bug report.
+//
+// Results served from the query cache (see query_cache.rs) aren't timed: they never touch the
+// database, and would only pull the percentiles down. Rows older than `RETENTION_DAYS` are
+// dropped once per run.
+
+use crate::database::get_app_connection;
+use crate::portfolio::Portfolio;
+use crate::queries::{DashboardStats, ProductStats, RawDataStream, SalesResponse};
+use crate::settings;
+use crate::types::AppResult;
+use rusqlite::{params, Connection, Result as SqliteResult};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Mutex, Once};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+const RETENTION_DAYS: u32 = 30;
+// How long the setting is trusted before it's read again; queries run many times a second
+const SETTING_TTL: Duration = Duration::from_secs(30);
+
+static ENABLED: Mutex<Option<(Instant, bool)>> = Mutex::new(None);
+static PRUNE: Once = Once::new();
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandTiming {
+    pub command: String,
+    pub count: u64,
+    pub errors: u64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+    /// Average rows returned by successful calls, if the command reports any
+    pub avg_rows: Option<f64>,
+    pub last_at: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PerformanceReport {
+    pub enabled: bool,
+    /// Only entries from this many days back; None covers the whole log
+    pub days: Option<u32>,
+    /// Slowest p95 first
+    pub commands: Vec<CommandTiming>,
+}
+
+/// Rows a timed call returned, for the report's row counts.
+pub trait RowCount {
+    fn row_count(&self) -> Option<u64>;
+}
+
+impl<T> RowCount for Vec<T> {
+    fn row_count(&self) -> Option<u64> {
+        Some(self.len() as u64)
+    }
+}
+
+impl RowCount for SalesResponse {
+    fn row_count(&self) -> Option<u64> {
+        Some(self.records.len() as u64)
+    }
+}
+
+impl RowCount for DashboardStats {
+    fn row_count(&self) -> Option<u64> {
+        Some(self.record_count)
+    }
+}
+
+impl RowCount for ProductStats {
+    fn row_count(&self) -> Option<u64> {
+        Some(self.record_count)
+    }
+}
+
+impl RowCount for Portfolio {
+    fn row_count(&self) -> Option<u64> {
+        Some(self.titles.len() as u64)
+    }
+}
+
+impl RowCount for RawDataStream {
+    fn row_count(&self) -> Option<u64> {
+        Some(self.total_records)
+    }
+}
+
+fn ensure_table(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS perf_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            timestamp TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+            command TEXT NOT NULL,
+            duration_ms REAL NOT NULL,
+            row_count INTEGER,
+            success INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_perf_log_command ON perf_log(command, timestamp);",
+    )
+}
+
+/// Whether timings are being recorded.
+pub fn is_enabled() -> bool {
+    let mut cached = ENABLED.lock().unwrap();
+    match *cached {
+        Some((read_at, enabled)) if read_at.elapsed() < SETTING_TTL => enabled,
+        _ => {
+            let enabled = settings::load_settings().perf_log_enabled;
+            *cached = Some((Instant::now(), enabled));
+            enabled
+        }
+    }
+}
+
+// Picks up a changed setting right away instead of after the TTL
+fn forget_setting() {
+    *ENABLED.lock().unwrap() = None;
+}
+
+fn insert(command: &str, duration: Duration, rows: Option<u64>, success: bool) -> SqliteResult<()> {
+    let conn = get_app_connection()?;
+    ensure_table(&conn)?;
+    PRUNE.call_once(|| {
+        if let Err(e) = conn.execute(
+            "DELETE FROM perf_log WHERE timestamp < strftime('%Y-%m-%dT%H:%M:%fZ', 'now', ?1)",
+            [format!("-{} days", RETENTION_DAYS)],
+        ) {
+            warn!("Failed to prune the performance log: {}", e);
+        }
+    });
+    conn.execute(
+        "INSERT INTO perf_log (command, duration_ms, row_count, success) VALUES (?1, ?2, ?3, ?4)",
+        params![
+            command,
+            duration.as_secs_f64() * 1000.0,
+            rows.map(|r| r as i64),
+            success
+        ],
+    )?;
+    Ok(())
+}
+
+/// Adds a timing to the log if it's enabled. Failures are only logged so that measuring never
+/// breaks the command being measured.
+pub fn record(command: &str, duration: Duration, rows: Option<u64>, success: bool) {
+    if !is_enabled() {
+        return;
+    }
+    if let Err(e) = insert(command, duration, rows, success) {
+        warn!("Failed to record timing of {}: {}", command, e);
+    }
+}
+
+/// Runs `run` and records how long it took under `command`.
+pub fn measure<T: RowCount>(command: &str, run: impl FnOnce() -> AppResult<T>) -> AppResult<T> {
+    let started = Instant::now();
+    let result = run();
+    let rows = result.as_ref().ok().and_then(RowCount::row_count);
+    record(command, started.elapsed(), rows, result.is_ok());
+    result
+}
+
+// Nearest-rank percentile of sorted values
+pub(crate) fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[derive(Default)]
+struct Samples {
+    durations: Vec<f64>,
+    rows: Vec<i64>,
+    errors: u64,
+    last_at: String,
+}
+
+pub fn get_report(days: Option<u32>) -> SqliteResult<PerformanceReport> {
+    let conn = get_app_connection()?;
+    ensure_table(&conn)?;
+
+    let since = days.map(|d| format!("-{} days", d));
+    let mut stmt = conn.prepare(
+        "SELECT command, duration_ms, row_count, success, timestamp FROM perf_log
+         WHERE ?1 IS NULL OR timestamp >= strftime('%Y-%m-%dT%H:%M:%fZ', 'now', ?1)
+         ORDER BY timestamp",
+    )?;
+    let rows = stmt.query_map([since], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, f64>(1)?,
+            row.get::<_, Option<i64>>(2)?,
+            row.get::<_, bool>(3)?,
+            row.get::<_, String>(4)?,
+        ))
+    })?;
+
+    let mut by_command: BTreeMap<String, Samples> = BTreeMap::new();
+    for row in rows {
+        let (command, duration_ms, row_count, success, timestamp) = row?;
+        let samples = by_command.entry(command).or_default();
+        samples.durations.push(duration_ms);
+        if success {
+            samples.rows.extend(row_count);
+        } else {
+            samples.errors += 1;
+        }
+        samples.last_at = timestamp;
+    }
+
+    let mut commands: Vec<CommandTiming> = by_command
+        .into_iter()
+        .map(|(command, mut samples)| {
+            samples.durations.sort_by(f64::total_cmp);
+            let avg_rows = (!samples.rows.is_empty())
+                .then(|| samples.rows.iter().sum::<i64>() as f64 / samples.rows.len() as f64);
+            CommandTiming {
+                command,
+                count: samples.durations.len() as u64,
+                errors: samples.errors,
+                p50_ms: percentile(&samples.durations, 50.0),
+                p95_ms: percentile(&samples.durations, 95.0),
+                max_ms: samples.durations.last().copied().unwrap_or(0.0),
+                avg_rows,
+                last_at: samples.last_at,
+            }
+        })
+        .collect();
+    commands.sort_by(|a, b| b.p95_ms.total_cmp(&a.p95_ms));
+
+    Ok(PerformanceReport {
+        enabled: is_enabled(),
+        days,
+        commands,
+    })
+}
+
+pub fn clear_log() -> SqliteResult<()> {
+    let conn = get_app_connection()?;
+    ensure_table(&conn)?;
+    conn.execute("DELETE FROM perf_log", [])?;
+    Ok(())
+}
+
+// ==================== Tauri Commands ====================
+
+/// Latency per timed command over the last `days` days (all of the log if omitted).
+#[tauri::command]
+pub async fn get_performance_report(days: Option<u32>) -> AppResult<PerformanceReport> {
+    Ok(tokio::task::spawn_blocking(move || get_report(days)).await??)
+}
+
+#[tauri::command]
+pub async fn set_perf_log_enabled(enabled: bool) -> AppResult<()> {
+    settings::update_settings(|s| s.perf_log_enabled = enabled)?;
+    forget_setting();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn clear_performance_log() -> AppResult<()> {
+    Ok(tokio::task::spawn_blocking(clear_log).await??)
+}
@@ -0,0 +1,133 @@
+// Platform share over time
+//
+// The share of an app's units per platform (Windows, Mac, Linux, Steam Deck, ...) per day, week,
+// month, quarter or year, e.g. to follow Steam Deck adoption without exporting the raw rows.
+// Shares are of gross units sold, since net units can be negative in a period with many
+// returns. Every platform is listed in every period, with 0 where it sold nothing.
+
+use crate::queries::{self, get_app_id_column, get_connection};
+use crate::types::{AppResult, Date, Granularity};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlatformShare {
+    pub platform: String,
+    pub units: i64,
+    /// Share of the period's units, 0-1
+    pub share: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlatformSharePeriod {
+    /// First day of the period
+    pub period_start: Date,
+    pub total_units: i64,
+    /// In the order of `platforms`
+    pub shares: Vec<PlatformShare>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlatformShareTrend {
+    pub app_id: i64,
+    pub granularity: Granularity,
+    /// Platforms by units over the whole range, largest first; "Unknown" for rows without one
+    pub platforms: Vec<String>,
+    pub periods: Vec<PlatformSharePeriod>,
+}
+
+pub fn get_platform_share_trend(
+    app_id: i64,
+    granularity: Granularity,
+) -> AppResult<PlatformShareTrend> {
+    let conn = get_connection()?;
+    let app_id_col = get_app_id_column(&conn);
+    // The day Steam is still reporting is left out if the settings say so
+    let last_day = queries::last_complete_day(None)
+        .map(|d| d.to_string())
+        .unwrap_or_else(|| "9999-12-31".to_string());
+
+    let sql = format!(
+        "SELECT {} AS period, COALESCE(NULLIF(platform, ''), 'Unknown') AS platform,
+            COALESCE(SUM(gross_units_sold), 0)
+         FROM sales_data
+         WHERE {} = ?1 AND date <= ?2
+         GROUP BY period, 2
+         ORDER BY period",
+        granularity.sql_period_start("date"),
+        app_id_col
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows: Vec<(Date, String, i64)> = stmt
+        .query_map(params![app_id, last_day], |r| {
+            Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+        })?
+        // Rows with malformed dates are skipped rather than counted in the wrong period
+        .flatten()
+        .collect();
+
+    let mut platform_totals: HashMap<&str, i64> = HashMap::new();
+    for (_, platform, units) in &rows {
+        *platform_totals.entry(platform.as_str()).or_default() += units;
+    }
+    let mut platforms: Vec<String> = platform_totals.keys().map(|p| p.to_string()).collect();
+    platforms.sort_by(|a, b| {
+        platform_totals[b.as_str()]
+            .cmp(&platform_totals[a.as_str()])
+            .then(a.cmp(b))
+    });
+
+    let mut periods: Vec<PlatformSharePeriod> = Vec::new();
+    for period_rows in rows.chunk_by(|a, b| a.0 == b.0) {
+        let total_units: i64 = period_rows.iter().map(|(_, _, units)| units).sum();
+        let shares = platforms
+            .iter()
+            .map(|platform| {
+                let units = period_rows
+                    .iter()
+                    .find(|(_, p, _)| p == platform)
+                    .map_or(0, |(_, _, units)| *units);
+                PlatformShare {
+                    platform: platform.clone(),
+                    units,
+                    share: if total_units > 0 {
+                        units as f64 / total_units as f64
+                    } else {
+                        0.0
+                    },
+                }
+            })
+            .collect();
+        periods.push(PlatformSharePeriod {
+            period_start: period_rows[0].0,
+            total_units,
+            shares,
+        });
+    }
+
+    Ok(PlatformShareTrend {
+        app_id,
+        granularity,
+        platforms,
+        periods,
+    })
+}
+
+// ==================== Tauri Commands ====================
+
+/// Units per platform and their shares for each period; `granularity` defaults to month.
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn query_platform_share_trend(
+    appId: i64,
+    granularity: Option<Granularity>,
+) -> AppResult<PlatformShareTrend> {
+    tokio::task::spawn_blocking(move || {
+        get_platform_share_trend(appId, granularity.unwrap_or(Granularity::Month))
+    })
+    .await?
+}
@@ -0,0 +1,304 @@
+// Player counts next to revenue
+//
+// When enabled in the settings, daily peak concurrent players of the active source's apps are
+// recorded in `player_stats` in the source database, so revenue from in-game purchases can be
+// related to the number of people playing. Two sources feed the daily peak:
+//
+//   - SteamSpy's "ccu", yesterday's peak, read once a day
+//   - the Steam Web API's current player count, sampled every hour while the app runs
+//
+// A day keeps the highest count seen from either.
+
+use crate::presentation;
+use crate::queries::{self, get_app_id_column, get_connection, get_write_connection};
+use crate::schema::table_exists;
+use crate::reviews::pearson;
+use crate::settings;
+use crate::sources;
+use crate::types::{AppError, AppResult, Date};
+use rusqlite::{params, Connection, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+const CURRENT_PLAYERS_URL: &str =
+    "https://api.steampowered.com/ISteamUserStats/GetNumberOfCurrentPlayers/v1/";
+const STEAMSPY_URL: &str = "https://steamspy.com/api.php";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+// SteamSpy allows one request per second
+const STEAMSPY_DELAY: Duration = Duration::from_millis(1100);
+// Days a correlation needs before it is reported
+const MIN_CORRELATION_DAYS: usize = 7;
+
+// When players were last sampled, and the UTC date SteamSpy was last read for
+static LAST_SAMPLE: Mutex<Option<Instant>> = Mutex::new(None);
+static LAST_STEAMSPY_DATE: Mutex<Option<String>> = Mutex::new(None);
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EngagementDay {
+    pub date: String,
+    pub peak_players: i64,
+    pub revenue: f64,
+    pub units: i64,
+    /// Revenue divided by the peak player count
+    pub revenue_per_player: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EngagementVsRevenue {
+    pub app_id: i64,
+    pub days: Vec<EngagementDay>,
+    /// Pearson correlation of peak players with revenue the same day
+    pub correlation: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CurrentPlayersResponse {
+    response: CurrentPlayers,
+}
+
+#[derive(Debug, Deserialize)]
+struct CurrentPlayers {
+    player_count: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SteamSpyApp {
+    /// Peak concurrent players yesterday
+    ccu: Option<i64>,
+}
+
+fn ensure_table(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS player_stats (
+            app_id INTEGER NOT NULL,
+            date TEXT NOT NULL,
+            peak_players INTEGER NOT NULL,
+            updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+            PRIMARY KEY (app_id, date)
+        );",
+    )
+}
+
+// `date_modifier` is a SQLite date modifier relative to today (UTC), e.g. '-1 day'
+fn record_peak(app_id: i64, date_modifier: &str, players: i64) -> SqliteResult<()> {
+    let conn = get_write_connection()?;
+    ensure_table(&conn)?;
+    conn.execute(
+        "INSERT INTO player_stats (app_id, date, peak_players)
+         VALUES (?1, date('now', ?2), ?3)
+         ON CONFLICT(app_id, date) DO UPDATE SET
+            peak_players = MAX(peak_players, excluded.peak_players),
+            updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now')",
+        params![app_id, date_modifier, players],
+    )?;
+    Ok(())
+}
+
+async fn current_players(client: &reqwest::Client, app_id: i64) -> AppResult<Option<i64>> {
+    let response = client
+        .get(CURRENT_PLAYERS_URL)
+        .query(&[("appid", app_id)])
+        .send()
+        .await?;
+    // Apps that are not released or have no players return 404
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !response.status().is_success() {
+        return Err(AppError::Network(format!(
+            "Steam Web API returned HTTP {}",
+            response.status()
+        )));
+    }
+    let body: CurrentPlayersResponse = response
+        .json()
+        .await
+        .map_err(|e| AppError::Network(format!("Unexpected player count response: {}", e)))?;
+    Ok(body.response.player_count)
+}
+
+async fn steamspy_peak(client: &reqwest::Client, app_id: i64) -> AppResult<Option<i64>> {
+    let response = client
+        .get(STEAMSPY_URL)
+        .query(&[
+            ("request", "appdetails".to_string()),
+            ("appid", app_id.to_string()),
+        ])
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(AppError::Network(format!(
+            "SteamSpy returned HTTP {}",
+            response.status()
+        )));
+    }
+    let body: SteamSpyApp = response
+        .json()
+        .await
+        .map_err(|e| AppError::Network(format!("Unexpected SteamSpy response: {}", e)))?;
+    // SteamSpy reports 0 for apps it does not track
+    Ok(body.ccu.filter(|&ccu| ccu > 0))
+}
+
+fn today() -> SqliteResult<String> {
+    get_connection()?.query_row("SELECT date('now')", [], |r| r.get(0))
+}
+
+// Steam app ids of the active source
+fn app_ids() -> SqliteResult<Vec<i64>> {
+    Ok(queries::get_apps_lookup()?
+        .into_iter()
+        .map(|app| app.app_id)
+        .collect())
+}
+
+async fn record(steamspy: bool) -> AppResult<()> {
+    let app_ids = tokio::task::spawn_blocking(app_ids).await??;
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()?;
+    for app_id in app_ids {
+        match current_players(&client, app_id).await {
+            Ok(Some(players)) => {
+                tokio::task::spawn_blocking(move || record_peak(app_id, "+0 day", players))
+                    .await??
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Failed to read player count of app {}: {}", app_id, e),
+        }
+        if steamspy {
+            tokio::time::sleep(STEAMSPY_DELAY).await;
+            match steamspy_peak(&client, app_id).await {
+                Ok(Some(peak)) => {
+                    tokio::task::spawn_blocking(move || record_peak(app_id, "-1 day", peak))
+                        .await??
+                }
+                Ok(None) => {}
+                Err(e) => warn!("Failed to read SteamSpy peak of app {}: {}", app_id, e),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Records player counts if enabled and an hour has passed since the last sample. Called by the
+/// scheduler.
+pub async fn sample_if_due() {
+    if !settings::load_settings().player_stats_enabled {
+        return;
+    }
+    if !sources::active_database_path().exists() {
+        return;
+    }
+    {
+        let mut last = LAST_SAMPLE.lock().unwrap();
+        if last.is_some_and(|at| at.elapsed() < SAMPLE_INTERVAL) {
+            return;
+        }
+        *last = Some(Instant::now());
+    }
+    let today = match tokio::task::spawn_blocking(today).await {
+        Ok(Ok(today)) => today,
+        Ok(Err(e)) => {
+            warn!("Failed to read the date: {}", e);
+            return;
+        }
+        Err(e) => {
+            warn!("Failed to read the date: {}", e);
+            return;
+        }
+    };
+    let steamspy = LAST_STEAMSPY_DATE.lock().unwrap().as_deref() != Some(today.as_str());
+    match record(steamspy).await {
+        Ok(()) => {
+            if steamspy {
+                *LAST_STEAMSPY_DATE.lock().unwrap() = Some(today);
+            }
+            info!("Recorded player counts");
+        }
+        Err(e) => warn!("Failed to record player counts: {}", e),
+    }
+}
+
+/// Peak players of an app per day next to its revenue. Without dates, covers the days player
+/// counts are recorded for.
+pub fn get_engagement_vs_revenue(
+    app_id: i64,
+    start_date: Option<Date>,
+    end_date: Option<Date>,
+) -> SqliteResult<EngagementVsRevenue> {
+    let conn = get_connection()?;
+    if !table_exists(&conn, "player_stats") {
+        return Ok(EngagementVsRevenue {
+            app_id,
+            days: Vec::new(),
+            correlation: None,
+        });
+    }
+    let app_id_col = get_app_id_column(&conn);
+
+    let sql = format!(
+        "SELECT p.date, p.peak_players,
+            COALESCE((SELECT SUM(CAST(s.gross_sales_usd AS REAL)) FROM sales_data s
+                      WHERE s.date = p.date AND s.{col} = p.app_id), 0),
+            COALESCE((SELECT SUM(s.net_units_sold) FROM sales_data s
+                      WHERE s.date = p.date AND s.{col} = p.app_id), 0)
+         FROM player_stats p
+         WHERE p.app_id = ?1
+           AND (?2 IS NULL OR p.date >= ?2)
+           AND (?3 IS NULL OR p.date <= ?3)
+         ORDER BY p.date",
+        col = app_id_col
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let days = stmt
+        .query_map(params![app_id, start_date, end_date], |r| {
+            let peak_players: i64 = r.get(1)?;
+            let revenue: f64 = r.get(2)?;
+            Ok(EngagementDay {
+                date: r.get(0)?,
+                peak_players,
+                revenue,
+                units: r.get(3)?,
+                revenue_per_player: match peak_players {
+                    0 => None,
+                    players => Some(revenue / players as f64),
+                },
+            })
+        })?
+        .collect::<SqliteResult<Vec<_>>>()?;
+
+    let correlation = if days.len() >= MIN_CORRELATION_DAYS {
+        let players: Vec<f64> = days.iter().map(|d| d.peak_players as f64).collect();
+        let revenue: Vec<f64> = days.iter().map(|d| d.revenue).collect();
+        pearson(&players, &revenue)
+    } else {
+        None
+    };
+
+    Ok(EngagementVsRevenue {
+        app_id,
+        days,
+        correlation,
+    })
+}
+
+// ==================== Tauri Commands ====================
+
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn query_engagement_vs_revenue(
+    appId: i64,
+    startDate: Option<Date>,
+    endDate: Option<Date>,
+) -> AppResult<EngagementVsRevenue> {
+    tokio::task::spawn_blocking(move || get_engagement_vs_revenue(appId, startDate, endDate))
+        .await?
+        .map_err(AppError::from)
+        .and_then(presentation::present)
+}
@@ -0,0 +1,232 @@
+// Portfolio overview
+//
+// One response for a portfolio dashboard: each app's revenue in a period and its share of the
+// total, its rank compared with the period of the same length just before, and how concentrated
+// the revenue is, e.g. how much of it depends on the top title. Both periods are summed in a
+// single pass over sales_data.
+
+use crate::database::get_database_path;
+use crate::presentation;
+use crate::queries::{self, get_app_id_column, get_app_name_column, get_connection};
+use crate::query_cache::QueryCache;
+use crate::types::{AppError, AppResult, Date};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::{AppHandle, Manager};
+
+const DEFAULT_PERIOD_DAYS: i64 = 30;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PortfolioPeriod {
+    /// Defaults to 30 days before the end date
+    pub start_date: Option<Date>,
+    /// Defaults to the last complete day
+    pub end_date: Option<Date>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PortfolioTitle {
+    pub app_id: i64,
+    pub app_name: Option<String>,
+    pub revenue: f64,
+    pub units: i64,
+    /// Share of the portfolio's revenue in the period, 0-1
+    pub revenue_share: f64,
+    /// 1 for the title with the most revenue in the period
+    pub rank: u32,
+    pub previous_revenue: f64,
+    /// None if the title had no revenue in the previous period
+    pub previous_rank: Option<u32>,
+    /// Places moved up since the previous period; negative if down
+    pub rank_change: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PortfolioConcentration {
+    /// Revenue share of the top title, 0-1
+    pub top_title_share: f64,
+    /// Revenue share of the top three titles, 0-1
+    pub top_three_share: f64,
+    /// Sum of squared revenue shares, 0-1; 1 when a single title earns everything
+    pub herfindahl_index: f64,
+    /// Fewest titles that together earn at least half of the revenue
+    pub titles_for_half_revenue: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Portfolio {
+    pub start_date: Date,
+    pub end_date: Date,
+    /// The period of the same length just before
+    pub previous_start_date: Date,
+    pub previous_end_date: Date,
+    pub total_revenue: f64,
+    pub previous_total_revenue: f64,
+    /// Relative change of the total revenue, e.g. 0.1 for +10%; None without previous revenue
+    pub revenue_change: Option<f64>,
+    /// Titles with revenue in either period, by rank
+    pub titles: Vec<PortfolioTitle>,
+    pub concentration: PortfolioConcentration,
+}
+
+// 1-based ranks by revenue, for titles with revenue
+fn ranks(revenue: impl Iterator<Item = (i64, f64)>) -> HashMap<i64, u32> {
+    let mut ranked: Vec<(i64, f64)> = revenue.filter(|(_, r)| *r > 0.0).collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    ranked
+        .into_iter()
+        .enumerate()
+        .map(|(i, (app_id, _))| (app_id, i as u32 + 1))
+        .collect()
+}
+
+fn concentration(titles: &[PortfolioTitle]) -> PortfolioConcentration {
+    // Titles are sorted by revenue, so the top titles come first
+    let shares: Vec<f64> = titles
+        .iter()
+        .map(|t| t.revenue_share)
+        .filter(|s| *s > 0.0)
+        .collect();
+    let mut cumulative = 0.0;
+    let titles_for_half_revenue = shares
+        .iter()
+        .position(|share| {
+            cumulative += share;
+            cumulative >= 0.5
+        })
+        .map_or(0, |i| i as u32 + 1);
+    PortfolioConcentration {
+        top_title_share: shares.first().copied().unwrap_or(0.0),
+        top_three_share: shares.iter().take(3).sum(),
+        herfindahl_index: shares.iter().map(|s| s * s).sum(),
+        titles_for_half_revenue,
+    }
+}
+
+pub fn get_portfolio(period: PortfolioPeriod) -> AppResult<Portfolio> {
+    let conn = get_connection()?;
+    let app_id_col = get_app_id_column(&conn);
+
+    let end = period
+        .end_date
+        .or_else(|| queries::last_complete_day(None))
+        .unwrap_or_else(|| queries::reporting_today().add_days(-1));
+    let start = period
+        .start_date
+        .unwrap_or_else(|| end.add_days(-(DEFAULT_PERIOD_DAYS - 1)));
+    if end < start {
+        return Err(AppError::InvalidInput(
+            "End date must not be before start date".to_string(),
+        ));
+    }
+    let previous_end = start.add_days(-1);
+    let previous_start = previous_end.add_days(-end.days_since(start));
+
+    let name_expr = get_app_name_column(&conn).map_or("NULL".to_string(), |col| {
+        format!("MAX(NULLIF({}, ''))", col)
+    });
+    let sql = format!(
+        "SELECT {app}, {name},
+            COALESCE(SUM(CASE WHEN date >= ?2 THEN CAST(gross_sales_usd AS REAL) END), 0),
+            COALESCE(SUM(CASE WHEN date >= ?2 THEN net_units_sold END), 0),
+            COALESCE(SUM(CASE WHEN date < ?2 THEN CAST(gross_sales_usd AS REAL) END), 0)
+         FROM sales_data
+         WHERE date >= ?1 AND date <= ?3 AND {app} IS NOT NULL AND {app} != 0
+         GROUP BY {app}",
+        app = app_id_col,
+        name = name_expr
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params![previous_start, start, end], |r| {
+        Ok((
+            r.get::<_, i64>(0)?,
+            r.get::<_, Option<String>>(1)?,
+            r.get::<_, f64>(2)?,
+            r.get::<_, i64>(3)?,
+            r.get::<_, f64>(4)?,
+        ))
+    })?;
+    let mut rows = rows
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter(|(_, _, revenue, _, previous)| *revenue > 0.0 || *previous > 0.0)
+        .collect::<Vec<_>>();
+
+    let total_revenue: f64 = rows.iter().map(|row| row.2).sum();
+    let previous_total_revenue: f64 = rows.iter().map(|row| row.4).sum();
+    let current_ranks = ranks(rows.iter().map(|row| (row.0, row.2)));
+    let previous_ranks = ranks(rows.iter().map(|row| (row.0, row.4)));
+
+    // Titles without revenue in the period go last, by their previous revenue
+    rows.sort_by(|a, b| {
+        b.2.total_cmp(&a.2)
+            .then(b.4.total_cmp(&a.4))
+            .then(a.0.cmp(&b.0))
+    });
+    let titles: Vec<PortfolioTitle> = rows
+        .into_iter()
+        .enumerate()
+        .map(
+            |(i, (app_id, app_name, revenue, units, previous_revenue))| {
+                let rank = current_ranks.get(&app_id).copied().unwrap_or(i as u32 + 1);
+                let previous_rank = previous_ranks.get(&app_id).copied();
+                PortfolioTitle {
+                    app_id,
+                    app_name,
+                    revenue,
+                    units,
+                    revenue_share: if total_revenue > 0.0 {
+                        revenue / total_revenue
+                    } else {
+                        0.0
+                    },
+                    rank,
+                    previous_revenue,
+                    previous_rank,
+                    rank_change: previous_rank.map(|previous| previous as i64 - rank as i64),
+                }
+            },
+        )
+        .collect();
+
+    Ok(Portfolio {
+        start_date: start,
+        end_date: end,
+        previous_start_date: previous_start,
+        previous_end_date: previous_end,
+        total_revenue,
+        previous_total_revenue,
+        revenue_change: (previous_total_revenue > 0.0)
+            .then(|| total_revenue / previous_total_revenue - 1.0),
+        concentration: concentration(&titles),
+        titles,
+    })
+}
+
+/// `get_portfolio` through the query cache, with the argument of `query_portfolio`.
+pub(crate) fn cached_portfolio(
+    cache: &QueryCache,
+    period: Option<PortfolioPeriod>,
+) -> AppResult<Portfolio> {
+    cache.get_or_compute("query_portfolio", &period, &get_database_path(), || {
+        get_portfolio(period.unwrap_or_default())
+    })
+}
+
+// ==================== Tauri Commands ====================
+
+/// Each app's revenue share and rank change in a period, with concentration metrics.
+#[tauri::command]
+pub async fn query_portfolio(
+    app: AppHandle,
+    period: Option<PortfolioPeriod>,
+) -> AppResult<Portfolio> {
+    tokio::task::spawn_blocking(move || cached_portfolio(&app.state::<QueryCache>(), period))
+        .await?
+        .and_then(presentation::present)
+}
@@ -0,0 +1,103 @@
+// Warming the query cache after a sync
+//
+// A fetch clears the query cache, so the first dashboard open after a sync used to start a burst
+// of the heaviest queries at once. With `precomputeAfterSync` on (the default), the launch
+// comparison (apps and packages, as the view first loads them), the portfolio overview and the
+// product stats of every app are computed in the background once a fetch of the active source
+// has finished. They land in the query cache under the same keys the commands use, so opening
+// those views is instant. `precompute-complete` is emitted when done.
+//
+// Nothing is kept if the database changes while they run; see query_cache.rs.
+
+use crate::portfolio;
+use crate::queries::{self, cached_launch_comparison, cached_product_stats};
+use crate::query_cache::QueryCache;
+use crate::settings;
+use crate::types::AppResult;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::{info, warn};
+
+// The window the launch comparison view opens with
+const LAUNCH_COMPARISON_DAYS: u32 = 7;
+
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrecomputeSummary {
+    /// Results now in the cache
+    pub computed: u32,
+    pub failed: u32,
+    pub elapsed_ms: u64,
+}
+
+fn precompute(cache: &QueryCache) -> PrecomputeSummary {
+    let started = Instant::now();
+    let mut summary = PrecomputeSummary {
+        computed: 0,
+        failed: 0,
+        elapsed_ms: 0,
+    };
+    let mut count = |view: &str, result: AppResult<()>| match result {
+        Ok(()) => summary.computed += 1,
+        Err(e) => {
+            warn!("Failed to precompute {}: {}", view, e);
+            summary.failed += 1;
+        }
+    };
+
+    for product_type in ["app", "package"] {
+        let result = cached_launch_comparison(
+            cache,
+            LAUNCH_COMPARISON_DAYS,
+            product_type,
+            None,
+            None,
+            None,
+            None,
+        );
+        count("launch comparison", result.map(|_| ()));
+    }
+    count(
+        "portfolio overview",
+        portfolio::cached_portfolio(cache, None).map(|_| ()),
+    );
+    match queries::get_apps_lookup() {
+        Ok(apps) => {
+            for app in apps {
+                let result = cached_product_stats(cache, "app", app.app_id, None);
+                count("product stats", result.map(|_| ()));
+            }
+        }
+        Err(e) => count("product stats", Err(e.into())),
+    }
+
+    summary.elapsed_ms = started.elapsed().as_millis() as u64;
+    summary
+}
+
+/// Fills the query cache in the background if the setting is on. Call after a fetch of the
+/// active source; a call while the previous run is still going does nothing.
+pub fn after_sync(app: &AppHandle) {
+    if !settings::load_settings().precompute_after_sync {
+        return;
+    }
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let summary = precompute(&app.state::<QueryCache>());
+        RUNNING.store(false, Ordering::SeqCst);
+        info!(
+            "Precomputed {} views in {} ms ({} failed)",
+            summary.computed, summary.elapsed_ms, summary.failed
+        );
+        if let Err(e) = app.emit("precompute-complete", &summary) {
+            warn!("Failed to emit precompute-complete: {}", e);
+        }
+    });
+}
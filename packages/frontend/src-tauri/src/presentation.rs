@@ -0,0 +1,611 @@
+// Presentation mode
+//
+// For screen-sharing the dashboard: with `presentationMode` on in the settings, the query
+// commands return every amount of money indexed so that the average day's gross revenue of the
+// active database is 100. One factor applies to all of them, so charts keep their shape, totals
+// still add up and revenue can be compared across views; units, counts, shares and ratios stay
+// real. The factor changes when the database does (after a fetch), so indexed values are for
+// showing, not for writing down.
+//
+// Each result type says which of its fields are money by implementing `Monetary` below, next to
+// all the others so it's easy to see what is masked. Custom metrics (see custom_metrics.rs) are
+// scaled by the power of money in their expressions, and hidden where that isn't known. Royalty
+// statements, reconciliations and snapshot diffs are masked too, with the active database's
+// factor also where they are about another one. The SQL console can't tell money from other
+// numbers, so it is off in presentation mode; raw sales_data rows (`query_sales_since`) have
+// their `_usd` and price columns masked. Alerts, goals, milestones and benchmarks are masked
+// where they are about revenue. Reports copied to the clipboard are masked like the dashboard
+// they are copied from; exports to files, the API server, webhooks and the command-line reports
+// still see real amounts. The query cache keeps real values, so turning the mode on or off
+// applies to the next query.
+
+use crate::anomalies::Alert;
+use crate::benchmarks::Benchmark;
+use crate::calendar::{CalendarHeatmap, HeatmapMetric};
+use crate::cohorts::CohortMatrix;
+use crate::compare::CompareSeries;
+use crate::custom_metrics;
+use crate::database::get_database_path;
+use crate::decay::DecayCurve;
+use crate::discount_calendar::DiscountPeriod;
+use crate::export::SummaryRows;
+use crate::goals::{Goal, GoalProgress, Milestone};
+use crate::impact::{EventImpact, ImpactComparison};
+use crate::package_economics::PackageEconomics;
+use crate::player_stats::EngagementVsRevenue;
+use crate::portfolio::Portfolio;
+use crate::price_regions::PriceRegionSummary;
+use crate::queries::{
+    get_connection, AppSummary, CountrySummary, DailySummary, DashboardStats, LaunchComparisonApp,
+    ProductStats, SalesRecord, SalesResponse,
+};
+use crate::query_cache::DatabaseVersion;
+use crate::read_snapshot;
+use crate::reconcile::{ReconcileReport, ReconcileTotals};
+use crate::replication::SalesPage;
+use crate::reviews::ReviewsVsSales;
+use crate::royalties::RoyaltyStatement;
+use crate::sale_types::SaleTypeSummary;
+use crate::seasonality::WeekdayProfile;
+use crate::settings;
+use crate::snapshot_diff::SnapshotDiff;
+use crate::tax::TaxPeriod;
+use crate::types::AppResult;
+use std::sync::Mutex;
+
+// What the average day's gross revenue is shown as
+const INDEX_BASE: f64 = 100.0;
+
+// The factor, and the state of the database it was computed from
+static FACTOR: Mutex<Option<(DatabaseVersion, f64)>> = Mutex::new(None);
+
+/// A query result with amounts of money in it.
+pub trait Monetary {
+    /// Multiplies every amount of money by `factor`.
+    fn scale_money(&mut self, factor: f64);
+}
+
+impl<T: Monetary> Monetary for Vec<T> {
+    fn scale_money(&mut self, factor: f64) {
+        for item in self {
+            item.scale_money(factor);
+        }
+    }
+}
+
+impl<T: Monetary> Monetary for Option<T> {
+    fn scale_money(&mut self, factor: f64) {
+        if let Some(item) = self {
+            item.scale_money(factor);
+        }
+    }
+}
+
+// What amounts are multiplied by: INDEX_BASE over the average daily gross revenue
+fn factor() -> AppResult<f64> {
+    let version = DatabaseVersion::of(&read_snapshot::reading_path(&get_database_path()));
+    if let Some((cached_version, factor)) = FACTOR.lock().unwrap().as_ref() {
+        if *cached_version == version {
+            return Ok(*factor);
+        }
+    }
+    let conn = get_connection()?;
+    let (revenue, days): (f64, i64) = conn.query_row(
+        "SELECT COALESCE(SUM(CAST(gross_sales_usd AS REAL)), 0), COUNT(DISTINCT date)
+         FROM sales_data",
+        [],
+        |r| Ok((r.get(0)?, r.get(1)?)),
+    )?;
+    let factor = if revenue > 0.0 {
+        INDEX_BASE * days as f64 / revenue
+    } else {
+        0.0
+    };
+    *FACTOR.lock().unwrap() = Some((version, factor));
+    Ok(factor)
+}
+
+/// What amounts of money are to be multiplied by, or None outside presentation mode. The
+/// setting is read on every call, so that turning the mode on applies to the very next query.
+pub fn money_factor() -> AppResult<Option<f64>> {
+    if settings::load_settings().presentation_mode {
+        Ok(Some(factor()?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Returns `value` as the query commands show it: unchanged, or with its amounts indexed in
+/// presentation mode.
+pub fn present<T: Monetary>(mut value: T) -> AppResult<T> {
+    if let Some(factor) = money_factor()? {
+        value.scale_money(factor);
+    }
+    Ok(value)
+}
+
+// ==================== Result Types ====================
+
+impl Monetary for DashboardStats {
+    fn scale_money(&mut self, factor: f64) {
+        self.total_revenue *= factor;
+    }
+}
+
+impl Monetary for SalesRecord {
+    fn scale_money(&mut self, factor: f64) {
+        self.gross_sales_usd *= factor;
+        self.net_sales_usd *= factor;
+    }
+}
+
+impl Monetary for SalesResponse {
+    fn scale_money(&mut self, factor: f64) {
+        self.records.scale_money(factor);
+    }
+}
+
+impl Monetary for DailySummary {
+    fn scale_money(&mut self, factor: f64) {
+        self.total_revenue *= factor;
+        custom_metrics::scale_money(&mut self.metrics, &self.metric_money_powers, factor);
+    }
+}
+
+impl Monetary for AppSummary {
+    fn scale_money(&mut self, factor: f64) {
+        self.total_revenue *= factor;
+        custom_metrics::scale_money(&mut self.metrics, &self.metric_money_powers, factor);
+    }
+}
+
+impl Monetary for CountrySummary {
+    fn scale_money(&mut self, factor: f64) {
+        self.total_revenue *= factor;
+        custom_metrics::scale_money(&mut self.metrics, &self.metric_money_powers, factor);
+    }
+}
+
+impl Monetary for SummaryRows {
+    fn scale_money(&mut self, factor: f64) {
+        match self {
+            SummaryRows::Daily(days) => days.scale_money(factor),
+            SummaryRows::Apps(apps) => apps.scale_money(factor),
+            SummaryRows::Countries(countries) => countries.scale_money(factor),
+        }
+    }
+}
+
+impl Monetary for ProductStats {
+    fn scale_money(&mut self, factor: f64) {
+        self.total_revenue *= factor;
+        self.daily.scale_money(factor);
+        self.by_country.scale_money(factor);
+        for platform in &mut self.by_platform {
+            platform.total_revenue *= factor;
+        }
+    }
+}
+
+impl Monetary for LaunchComparisonApp {
+    fn scale_money(&mut self, factor: f64) {
+        for day in &mut self.days {
+            day.revenue *= factor;
+        }
+    }
+}
+
+impl Monetary for CalendarHeatmap {
+    fn scale_money(&mut self, factor: f64) {
+        if !matches!(self.metric, HeatmapMetric::Revenue) {
+            return;
+        }
+        self.total *= factor;
+        self.max_value *= factor;
+        for day in &mut self.days {
+            day.value *= factor;
+        }
+    }
+}
+
+impl Monetary for CohortMatrix {
+    fn scale_money(&mut self, factor: f64) {
+        for cohort in &mut self.cohorts {
+            cohort.total_revenue *= factor;
+            for revenue in &mut cohort.revenue_by_week {
+                *revenue *= factor;
+            }
+        }
+    }
+}
+
+impl Monetary for CompareSeries {
+    fn scale_money(&mut self, factor: f64) {
+        self.total_revenue *= factor;
+        for day in &mut self.days {
+            day.revenue *= factor;
+        }
+    }
+}
+
+impl Monetary for DecayCurve {
+    fn scale_money(&mut self, factor: f64) {
+        self.lifetime_revenue *= factor;
+        for week in &mut self.weeks {
+            week.revenue *= factor;
+            week.fitted = week.fitted.map(|r| r * factor);
+        }
+        // revenue = a * week^-b, so only a is money
+        if let Some(fit) = &mut self.fit {
+            fit.coefficient *= factor;
+        }
+        self.projected_next_year = self.projected_next_year.map(|r| r * factor);
+    }
+}
+
+impl Monetary for DiscountPeriod {
+    fn scale_money(&mut self, factor: f64) {
+        self.revenue *= factor;
+    }
+}
+
+impl Monetary for Goal {
+    fn scale_money(&mut self, factor: f64) {
+        if self.metric != "units" {
+            self.target *= factor;
+        }
+    }
+}
+
+impl Monetary for GoalProgress {
+    fn scale_money(&mut self, factor: f64) {
+        if self.goal.metric == "units" {
+            return;
+        }
+        self.goal.scale_money(factor);
+        self.current *= factor;
+        self.daily_run_rate *= factor;
+    }
+}
+
+impl Monetary for Milestone {
+    fn scale_money(&mut self, factor: f64) {
+        self.threshold *= factor;
+        self.lifetime_net_revenue *= factor;
+    }
+}
+
+impl Monetary for Alert {
+    fn scale_money(&mut self, factor: f64) {
+        // The z-score is a ratio, so it stays the same
+        if self.metric == "revenue" {
+            self.value *= factor;
+            self.baseline_mean *= factor;
+            self.baseline_stddev *= factor;
+        }
+    }
+}
+
+impl Monetary for Benchmark {
+    fn scale_money(&mut self, factor: f64) {
+        for day in &mut self.days {
+            day.revenue *= factor;
+        }
+    }
+}
+
+impl Monetary for ImpactComparison {
+    fn scale_money(&mut self, factor: f64) {
+        self.revenue.event_total *= factor;
+        self.revenue.event_daily_mean *= factor;
+        self.revenue.baseline_daily_mean *= factor;
+    }
+}
+
+impl Monetary for EventImpact {
+    fn scale_money(&mut self, factor: f64) {
+        self.vs_previous.scale_money(factor);
+        self.vs_same_weekdays.scale_money(factor);
+    }
+}
+
+impl Monetary for PackageEconomics {
+    fn scale_money(&mut self, factor: f64) {
+        self.total_revenue *= factor;
+        self.net_revenue *= factor;
+        self.average_price = self.average_price.map(|p| p * factor);
+        for tier in &mut self.by_discount {
+            tier.revenue *= factor;
+            tier.average_price = tier.average_price.map(|p| p * factor);
+        }
+        for region in &mut self.by_region {
+            region.revenue *= factor;
+            region.average_price = region.average_price.map(|p| p * factor);
+        }
+    }
+}
+
+impl Monetary for EngagementVsRevenue {
+    fn scale_money(&mut self, factor: f64) {
+        for day in &mut self.days {
+            day.revenue *= factor;
+            day.revenue_per_player = day.revenue_per_player.map(|r| r * factor);
+        }
+    }
+}
+
+impl Monetary for Portfolio {
+    fn scale_money(&mut self, factor: f64) {
+        self.total_revenue *= factor;
+        self.previous_total_revenue *= factor;
+        for title in &mut self.titles {
+            title.revenue *= factor;
+            title.previous_revenue *= factor;
+        }
+    }
+}
+
+impl Monetary for PriceRegionSummary {
+    fn scale_money(&mut self, factor: f64) {
+        self.total_revenue *= factor;
+        self.average_price = self.average_price.map(|p| p * factor);
+    }
+}
+
+impl Monetary for ReviewsVsSales {
+    fn scale_money(&mut self, factor: f64) {
+        for day in &mut self.days {
+            day.revenue *= factor;
+        }
+    }
+}
+
+impl Monetary for SaleTypeSummary {
+    fn scale_money(&mut self, factor: f64) {
+        self.total_revenue *= factor;
+        self.net_revenue *= factor;
+        self.average_price = self.average_price.map(|p| p * factor);
+    }
+}
+
+impl Monetary for WeekdayProfile {
+    fn scale_money(&mut self, factor: f64) {
+        self.average_daily_revenue *= factor;
+        for bucket in self.weekdays.iter_mut().chain(&mut self.months) {
+            bucket.average_revenue *= factor;
+        }
+    }
+}
+
+impl Monetary for TaxPeriod {
+    fn scale_money(&mut self, factor: f64) {
+        self.gross_revenue *= factor;
+        self.tax *= factor;
+        self.revenue_excluding_tax *= factor;
+        for jurisdiction in &mut self.jurisdictions {
+            jurisdiction.gross_revenue *= factor;
+            jurisdiction.tax *= factor;
+            jurisdiction.revenue_excluding_tax *= factor;
+            for country in &mut jurisdiction.countries {
+                country.gross_revenue *= factor;
+                country.tax *= factor;
+                country.revenue_excluding_tax *= factor;
+            }
+        }
+    }
+}
+
+impl Monetary for RoyaltyStatement {
+    fn scale_money(&mut self, factor: f64) {
+        for product in &mut self.products {
+            product.net_revenue *= factor;
+            product.lifetime_net_revenue *= factor;
+            product.unallocated *= factor;
+            for line in &mut product.lines {
+                line.recoup_threshold = line.recoup_threshold.map(|t| t * factor);
+                line.eligible_revenue *= factor;
+                line.amount *= factor;
+            }
+        }
+        for party in &mut self.parties {
+            party.amount *= factor;
+        }
+    }
+}
+
+impl Monetary for ReconcileTotals {
+    fn scale_money(&mut self, factor: f64) {
+        self.gross_sales_usd *= factor;
+        self.net_sales_usd *= factor;
+    }
+}
+
+impl Monetary for ReconcileReport {
+    fn scale_money(&mut self, factor: f64) {
+        for date in &mut self.dates {
+            date.steam.scale_money(factor);
+            date.local.scale_money(factor);
+            for app in &mut date.mismatched_apps {
+                app.steam.scale_money(factor);
+                app.local.scale_money(factor);
+            }
+        }
+    }
+}
+
+impl Monetary for SnapshotDiff {
+    fn scale_money(&mut self, factor: f64) {
+        for change in &mut self.changes {
+            change.gross_revenue_change *= factor;
+            change.net_revenue_change *= factor;
+        }
+    }
+}
+
+impl Monetary for SalesPage {
+    fn scale_money(&mut self, factor: f64) {
+        for row in &mut self.rows {
+            for (column, value) in row.iter_mut() {
+                if !(column.ends_with("_usd") || column.ends_with("price")) {
+                    continue;
+                }
+                // Amounts are stored as text ("12.99") or numbers
+                let amount = match value {
+                    serde_json::Value::Number(n) => n.as_f64(),
+                    serde_json::Value::String(s) => s.parse::<f64>().ok(),
+                    _ => None,
+                };
+                if let Some(amount) = amount {
+                    *value = serde_json::Number::from_f64(amount * factor)
+                        .map_or(serde_json::Value::Null, serde_json::Value::Number);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::queries::LaunchDay;
+    use crate::types::RevenueBasis;
+
+    fn alert(metric: &str) -> Alert {
+        Alert {
+            id: 1,
+            created_at: "2024-03-02T00:00:00.000Z".to_string(),
+            date: "2024-03-01".to_string(),
+            app_id: 10,
+            metric: metric.to_string(),
+            value: 500.0,
+            baseline_mean: 100.0,
+            baseline_stddev: 20.0,
+            z_score: 20.0,
+            severity: "high".to_string(),
+            acknowledged: false,
+        }
+    }
+
+    fn goal(metric: &str) -> Goal {
+        Goal {
+            id: 1,
+            name: "Launch year".to_string(),
+            app_id: None,
+            metric: metric.to_string(),
+            target: 50_000.0,
+            period_start: None,
+            period_end: None,
+            created_at: "2024-01-01T00:00:00.000Z".to_string(),
+        }
+    }
+
+    #[test]
+    fn alerts_mask_revenue_but_not_units_or_z_scores() {
+        let mut alerts = vec![alert("revenue"), alert("units")];
+        alerts.scale_money(0.5);
+        assert_eq!(
+            (
+                alerts[0].value,
+                alerts[0].baseline_mean,
+                alerts[0].baseline_stddev
+            ),
+            (250.0, 50.0, 10.0)
+        );
+        assert_eq!(alerts[0].z_score, 20.0);
+        assert_eq!((alerts[1].value, alerts[1].baseline_mean), (500.0, 100.0));
+    }
+
+    #[test]
+    fn benchmarks_mask_revenue_per_day() {
+        let mut benchmarks = vec![Benchmark {
+            id: 1,
+            name: "Launch".to_string(),
+            product_type: "app".to_string(),
+            product_id: 10,
+            product_name: None,
+            revenue_basis: RevenueBasis::Net,
+            launch_date: "2024-01-01".parse().unwrap(),
+            days_count: 2,
+            created_at: "2024-02-01T00:00:00.000Z".to_string(),
+            days: vec![
+                LaunchDay {
+                    day: 0,
+                    revenue: 1000.0,
+                    units: 50,
+                },
+                LaunchDay {
+                    day: 1,
+                    revenue: 0.0,
+                    units: 0,
+                },
+            ],
+        }];
+        benchmarks.scale_money(0.1);
+        let days: Vec<(f64, i64)> = benchmarks[0]
+            .days
+            .iter()
+            .map(|d| (d.revenue, d.units))
+            .collect();
+        assert_eq!(days, [(100.0, 50), (0.0, 0)]);
+    }
+
+    #[test]
+    fn goals_mask_money_targets_only() {
+        let mut goals = vec![goal("revenue"), goal("net_revenue"), goal("units")];
+        goals.scale_money(0.01);
+        let targets: Vec<f64> = goals.iter().map(|g| g.target).collect();
+        assert_eq!(targets, [500.0, 500.0, 50_000.0]);
+    }
+
+    #[test]
+    fn milestones_mask_thresholds_and_totals() {
+        let mut milestones = vec![Milestone {
+            app_id: Some(10),
+            threshold: 100_000.0,
+            lifetime_net_revenue: 123_456.0,
+            reached_at: "2024-03-01T00:00:00.000Z".to_string(),
+        }];
+        milestones.scale_money(0.001);
+        assert_eq!(milestones[0].threshold, 100.0);
+        assert!((milestones[0].lifetime_net_revenue - 123.456).abs() < 1e-9);
+    }
+
+    #[test]
+    fn sales_pages_mask_amount_columns() {
+        let row = serde_json::json!({
+            "rowid": 7,
+            "date": "2024-03-01",
+            "primary_app_id": 10,
+            "net_units_sold": 4,
+            "gross_sales_usd": "40.00",
+            "net_sales_usd": 36.0,
+            "net_tax_usd": null,
+            "base_price": "9.99",
+            "sale_price": "",
+            "country_code": "US"
+        });
+        let mut page = SalesPage {
+            rows: vec![row.as_object().unwrap().clone()],
+            next_cursor: 7,
+            has_more: false,
+            total_rows: 1,
+        };
+        page.scale_money(0.5);
+        let masked = serde_json::Value::Object(page.rows.remove(0));
+        assert_eq!(
+            masked,
+            serde_json::json!({
+                "rowid": 7,
+                "date": "2024-03-01",
+                "primary_app_id": 10,
+                "net_units_sold": 4,
+                "gross_sales_usd": 20.0,
+                "net_sales_usd": 18.0,
+                "net_tax_usd": null,
+                "base_price": 4.995,
+                "sale_price": "",
+                "country_code": "US"
+            })
+        );
+    }
+}
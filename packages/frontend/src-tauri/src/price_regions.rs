@@ -0,0 +1,178 @@
+// Steam price regions
+//
+// Steam's pricing tool doesn't price per country but per region: a currency like EUR or BRL, or
+// one of the USD regions it recommends lower prices for (LATAM, CIS, South Asia, MENA). The app
+// ships the country to region mapping (data/price_regions.csv, as of Steam's 2023 regional
+// pricing update; countries without a currency or region of their own pay the USD price) and
+// loads it into `lookup_price_regions`, so sales can be compared at the level prices are set.
+
+use crate::database::begin_write;
+use crate::presentation;
+use crate::queries::{
+    build_where_clause, connection_for, get_app_id_column, get_source_column, QueryFilters,
+};
+use crate::types::AppResult;
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+
+const PRICE_REGIONS_CSV: &str = include_str!("../data/price_regions.csv");
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceRegionSummary {
+    /// e.g. "EUR" or "LATAM - USD"; "Unknown" for rows without a known country
+    pub price_region: String,
+    /// Currency the region is priced in
+    pub currency: Option<String>,
+    /// Countries of the region with sales, largest first
+    pub countries: Vec<String>,
+    pub total_revenue: f64,
+    pub total_units: i64,
+    /// Gross revenue per net unit; None without units
+    pub average_price: Option<f64>,
+    /// Share of the revenue of all regions, 0-1
+    pub revenue_share: f64,
+}
+
+fn bundled_price_regions() -> impl Iterator<Item = (&'static str, &'static str, &'static str)> {
+    PRICE_REGIONS_CSV.lines().skip(1).filter_map(|line| {
+        let mut fields = line.split(',');
+        Some((fields.next()?, fields.next()?, fields.next()?))
+    })
+}
+
+/// Creates `lookup_price_regions` and fills it with the bundled mapping if it has fewer rows.
+pub(crate) fn ensure_lookup_table(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS lookup_price_regions (
+            country_code TEXT PRIMARY KEY,
+            price_region TEXT NOT NULL,
+            currency TEXT NOT NULL
+        );",
+    )?;
+    let loaded: usize = conn.query_row("SELECT COUNT(*) FROM lookup_price_regions", [], |r| {
+        r.get(0)
+    })?;
+    if loaded >= bundled_price_regions().count() {
+        return Ok(());
+    }
+
+    let tx = begin_write(conn)?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT OR REPLACE INTO lookup_price_regions (country_code, price_region, currency)
+             VALUES (?1, ?2, ?3)",
+        )?;
+        for (code, price_region, currency) in bundled_price_regions() {
+            stmt.execute(params![code, price_region, currency])?;
+        }
+    }
+    tx.commit()
+}
+
+pub fn get_price_region_summaries(filters: QueryFilters) -> AppResult<Vec<PriceRegionSummary>> {
+    let conn = connection_for(&filters)?;
+    ensure_lookup_table(&conn)?;
+
+    // The country filter, limit and sorting of the filters don't apply to a region report
+    let filters = QueryFilters {
+        country_code: None,
+        ..filters.complete_days_only()
+    };
+    let app_id_col = get_app_id_column(&conn);
+    let where_clause = build_where_clause(&filters, &app_id_col, get_source_column(&conn))
+        .sale_items_only(&conn, &filters);
+
+    let mut params: Vec<Value> = Vec::new();
+    if let Some(start_date) = filters.start_date {
+        params.push(start_date.to_string().into());
+    }
+    if let Some(end_date) = filters.end_date {
+        params.push(end_date.to_string().into());
+    }
+    for app_id in filters.app_ids.iter().flatten() {
+        params.push((*app_id).into());
+    }
+    if let Some(source) = filters.source {
+        params.push(source.into());
+    }
+    for sale_type in filters.sale_types.iter().flatten() {
+        params.push(sale_type.clone().into());
+    }
+
+    let sql = format!(
+        "SELECT COALESCE(p.price_region, 'Unknown'), p.currency, t.country_code, t.revenue,
+            t.units
+        FROM (
+            SELECT
+                COALESCE(country_code, '') as country_code,
+                COALESCE(SUM(CAST(gross_sales_usd AS REAL)), 0) as revenue,
+                COALESCE(SUM(net_units_sold), 0) as units
+            FROM sales_data {}
+            GROUP BY 1
+        ) t
+        LEFT JOIN lookup_price_regions p ON p.country_code = t.country_code
+        ORDER BY t.revenue DESC",
+        where_clause.clause
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params_from_iter(params), |r| {
+        Ok((
+            r.get::<_, String>(0)?,
+            r.get::<_, Option<String>>(1)?,
+            r.get::<_, String>(2)?,
+            r.get::<_, f64>(3)?,
+            r.get::<_, i64>(4)?,
+        ))
+    })?;
+
+    let mut regions: Vec<PriceRegionSummary> = Vec::new();
+    let mut total = 0.0;
+    for row in rows {
+        let (price_region, currency, country_code, revenue, units) = row?;
+        total += revenue;
+        let index = match regions.iter().position(|r| r.price_region == price_region) {
+            Some(index) => index,
+            None => {
+                regions.push(PriceRegionSummary {
+                    price_region,
+                    currency,
+                    countries: Vec::new(),
+                    total_revenue: 0.0,
+                    total_units: 0,
+                    average_price: None,
+                    revenue_share: 0.0,
+                });
+                regions.len() - 1
+            }
+        };
+        let region = &mut regions[index];
+        if !country_code.is_empty() {
+            region.countries.push(country_code);
+        }
+        region.total_revenue += revenue;
+        region.total_units += units;
+    }
+
+    for region in &mut regions {
+        region.average_price =
+            (region.total_units > 0).then(|| region.total_revenue / region.total_units as f64);
+        if total > 0.0 {
+            region.revenue_share = region.total_revenue / total;
+        }
+    }
+    regions.sort_by(|a, b| b.total_revenue.total_cmp(&a.total_revenue));
+    Ok(regions)
+}
+
+// ==================== Tauri Commands ====================
+
+/// Revenue and units per Steam price region. Uses the date range, apps and source of `filters`.
+#[tauri::command]
+pub async fn query_by_price_region(filters: QueryFilters) -> AppResult<Vec<PriceRegionSummary>> {
+    let filters = filters.resolve_date_range()?;
+    tokio::task::spawn_blocking(move || get_price_region_summaries(filters))
+        .await?
+        .and_then(presentation::present)
+}
@@ -0,0 +1,417 @@
+// Product groups
+//
+// Steam reports a game's demo, playtest, soundtrack and DLC under appids and packages of their
+// own, so a free demo shows up as an app without revenue and a game's sales are spread over
+// several entries. A product group names a set of related apps and packages as one product.
+// App summaries and launch comparison merge the members of each group when asked to, and
+// product stats accept a group as product. The first app of a group stands for it, e.g. the
+// full game.
+
+use crate::database::begin_write;
+use crate::queries::{
+    get_connection, get_write_connection, AppSummary, LaunchComparisonApp, LaunchDay,
+};
+use crate::schema::table_exists;
+use crate::types::{AppError, AppResult};
+use rusqlite::types::Value;
+use rusqlite::{params, Connection, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProductGroup {
+    pub id: i64,
+    pub name: String,
+    /// In the order given; the first one stands for the group
+    pub app_ids: Vec<i64>,
+    pub package_ids: Vec<i64>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProductGroupInput {
+    pub name: String,
+    #[serde(default)]
+    pub app_ids: Vec<i64>,
+    #[serde(default)]
+    pub package_ids: Vec<i64>,
+}
+
+impl ProductGroup {
+    fn primary_app_id(&self) -> Option<i64> {
+        self.app_ids.first().copied()
+    }
+}
+
+pub(crate) fn ensure_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS product_groups (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+        );
+        CREATE TABLE IF NOT EXISTS product_group_members (
+            group_id INTEGER NOT NULL,
+            position INTEGER NOT NULL,
+            app_id INTEGER,
+            package_id INTEGER
+        );
+        CREATE INDEX IF NOT EXISTS idx_product_group_members_group
+            ON product_group_members(group_id);",
+    )
+}
+
+fn validate(conn: &Connection, input: &ProductGroupInput, id: Option<i64>) -> AppResult<()> {
+    if input.name.trim().is_empty() {
+        return Err(AppError::InvalidInput("Name must not be empty".to_string()));
+    }
+    if input.app_ids.is_empty() && input.package_ids.is_empty() {
+        return Err(AppError::InvalidInput(
+            "A product group needs at least one app or package".to_string(),
+        ));
+    }
+
+    // A product in two groups would be counted in both
+    for group in list_groups(conn)? {
+        if Some(group.id) == id {
+            continue;
+        }
+        if group.name == input.name.trim() {
+            return Err(AppError::InvalidInput(format!(
+                "There already is a product group named '{}'",
+                group.name
+            )));
+        }
+        if let Some(app_id) = input.app_ids.iter().find(|a| group.app_ids.contains(a)) {
+            return Err(AppError::InvalidInput(format!(
+                "App {} is already in product group '{}'",
+                app_id, group.name
+            )));
+        }
+        if let Some(package_id) = input
+            .package_ids
+            .iter()
+            .find(|p| group.package_ids.contains(p))
+        {
+            return Err(AppError::InvalidInput(format!(
+                "Package {} is already in product group '{}'",
+                package_id, group.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn list_groups(conn: &Connection) -> SqliteResult<Vec<ProductGroup>> {
+    if !table_exists(conn, "product_group_members") {
+        return Ok(Vec::new());
+    }
+    let mut stmt = conn.prepare("SELECT id, name, created_at FROM product_groups ORDER BY name")?;
+    let mut groups = stmt
+        .query_map([], |r| {
+            Ok(ProductGroup {
+                id: r.get(0)?,
+                name: r.get(1)?,
+                app_ids: Vec::new(),
+                package_ids: Vec::new(),
+                created_at: r.get(2)?,
+            })
+        })?
+        .collect::<SqliteResult<Vec<_>>>()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT group_id, app_id, package_id FROM product_group_members
+         ORDER BY group_id, position",
+    )?;
+    let members = stmt.query_map([], |r| {
+        Ok((
+            r.get::<_, i64>(0)?,
+            r.get::<_, Option<i64>>(1)?,
+            r.get::<_, Option<i64>>(2)?,
+        ))
+    })?;
+    for member in members {
+        let (group_id, app_id, package_id) = member?;
+        if let Some(group) = groups.iter_mut().find(|g| g.id == group_id) {
+            group.app_ids.extend(app_id);
+            group.package_ids.extend(package_id);
+        }
+    }
+    Ok(groups)
+}
+
+pub fn list_product_groups_all() -> SqliteResult<Vec<ProductGroup>> {
+    let conn = get_connection()?;
+    list_groups(&conn)
+}
+
+fn insert_members(conn: &Connection, id: i64, input: &ProductGroupInput) -> SqliteResult<()> {
+    let mut stmt = conn.prepare(
+        "INSERT INTO product_group_members (group_id, position, app_id, package_id)
+         VALUES (?1, ?2, ?3, ?4)",
+    )?;
+    let members = input
+        .app_ids
+        .iter()
+        .map(|&app_id| (Some(app_id), None))
+        .chain(
+            input
+                .package_ids
+                .iter()
+                .map(|&package_id| (None, Some(package_id))),
+        );
+    for (position, (app_id, package_id)) in members.enumerate() {
+        stmt.execute(params![id, position as i64, app_id, package_id])?;
+    }
+    Ok(())
+}
+
+pub fn insert_group(input: &ProductGroupInput) -> AppResult<i64> {
+    let conn = get_write_connection()?;
+    ensure_tables(&conn)?;
+    validate(&conn, input, None)?;
+    let tx = begin_write(&conn)?;
+    tx.execute(
+        "INSERT INTO product_groups (name) VALUES (?1)",
+        [input.name.trim()],
+    )?;
+    let id = tx.last_insert_rowid();
+    insert_members(&tx, id, input)?;
+    tx.commit()?;
+    Ok(id)
+}
+
+pub fn update_group_by_id(id: i64, input: &ProductGroupInput) -> AppResult<bool> {
+    let conn = get_write_connection()?;
+    ensure_tables(&conn)?;
+    validate(&conn, input, Some(id))?;
+    let tx = begin_write(&conn)?;
+    let changed = tx.execute(
+        "UPDATE product_groups SET name = ?2 WHERE id = ?1",
+        params![id, input.name.trim()],
+    )?;
+    if changed > 0 {
+        tx.execute(
+            "DELETE FROM product_group_members WHERE group_id = ?1",
+            [id],
+        )?;
+        insert_members(&tx, id, input)?;
+    }
+    tx.commit()?;
+    Ok(changed > 0)
+}
+
+pub fn delete_group_by_id(id: i64) -> SqliteResult<bool> {
+    let conn = get_write_connection()?;
+    ensure_tables(&conn)?;
+    let tx = begin_write(&conn)?;
+    tx.execute(
+        "DELETE FROM product_group_members WHERE group_id = ?1",
+        [id],
+    )?;
+    let changed = tx.execute("DELETE FROM product_groups WHERE id = ?1", [id])?;
+    tx.commit()?;
+    Ok(changed > 0)
+}
+
+/// A WHERE condition matching the rows of group `id`, and its parameters. Fails with
+/// QueryReturnedNoRows if there is no such group.
+pub(crate) fn group_condition(
+    conn: &Connection,
+    id: i64,
+    app_id_col: &str,
+) -> SqliteResult<(String, Vec<Value>)> {
+    let group = list_groups(conn)?
+        .into_iter()
+        .find(|g| g.id == id)
+        .ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+    let placeholders = |n: usize| vec!["?"; n].join(", ");
+    let mut conditions = Vec::new();
+    if !group.app_ids.is_empty() {
+        conditions.push(format!(
+            "{} IN ({})",
+            app_id_col,
+            placeholders(group.app_ids.len())
+        ));
+    }
+    if !group.package_ids.is_empty() {
+        conditions.push(format!(
+            "packageid IN ({})",
+            placeholders(group.package_ids.len())
+        ));
+    }
+    let params = group
+        .app_ids
+        .iter()
+        .chain(&group.package_ids)
+        .map(|&id| Value::from(id))
+        .collect();
+    Ok((format!("({})", conditions.join(" OR ")), params))
+}
+
+/// SQL expression for the app id of a row, with the apps of each group replaced by the group's
+/// first app, for grouping rows the way `merge_app_summaries` merges summaries.
+pub(crate) fn app_key_expr(conn: &Connection, app_id_col: &str) -> SqliteResult<String> {
+    let cases: Vec<String> = list_groups(conn)?
+        .iter()
+        .filter_map(|group| {
+            let primary = group.primary_app_id()?;
+            let ids: Vec<String> = group.app_ids.iter().map(|id| id.to_string()).collect();
+            Some(format!(
+                "WHEN {} IN ({}) THEN {}",
+                app_id_col,
+                ids.join(", "),
+                primary
+            ))
+        })
+        .collect();
+    if cases.is_empty() {
+        return Ok(app_id_col.to_string());
+    }
+    Ok(format!("CASE {} ELSE {} END", cases.join(" "), app_id_col))
+}
+
+/// Merges the summaries of the apps of each group into one, named after the group and with the
+/// group's first app as app id, and sorts by revenue again.
+pub(crate) fn merge_app_summaries(
+    conn: &Connection,
+    summaries: Vec<AppSummary>,
+) -> SqliteResult<Vec<AppSummary>> {
+    let groups = list_groups(conn)?;
+    let mut merged: Vec<AppSummary> = Vec::with_capacity(summaries.len());
+    for summary in summaries {
+        let Some(group) = groups.iter().find(|g| g.app_ids.contains(&summary.app_id)) else {
+            merged.push(summary);
+            continue;
+        };
+        match merged.iter_mut().find(|s| s.group_id == Some(group.id)) {
+            Some(entry) => {
+                entry.total_revenue += summary.total_revenue;
+                entry.total_units += summary.total_units;
+                entry.record_count += summary.record_count;
+                entry.first_sale = entry.first_sale.clone().min(summary.first_sale);
+                entry.last_sale = entry.last_sale.clone().max(summary.last_sale);
+            }
+            None => merged.push(AppSummary {
+                app_id: group.primary_app_id().unwrap_or(summary.app_id),
+                app_name: Some(group.name.clone()),
+                group_id: Some(group.id),
+                ..summary
+            }),
+        }
+    }
+    merged.sort_by(|a, b| b.total_revenue.total_cmp(&a.total_revenue));
+    Ok(merged)
+}
+
+/// Merges the launch series of the members of each group into one that starts at the group's
+/// first launch; a member that launched later adds its days at the matching offset.
+pub(crate) fn merge_launch_series(
+    conn: &Connection,
+    series: Vec<LaunchComparisonApp>,
+    is_package: bool,
+) -> SqliteResult<Vec<LaunchComparisonApp>> {
+    let groups = list_groups(conn)?;
+    let group_of = |entry: &LaunchComparisonApp| {
+        groups
+            .iter()
+            .find(|g| match (is_package, entry.app_id, entry.package_id) {
+                (false, Some(app_id), _) => g.app_ids.contains(&app_id),
+                (true, _, Some(package_id)) => g.package_ids.contains(&package_id),
+                _ => false,
+            })
+    };
+
+    let mut merged: Vec<LaunchComparisonApp> = Vec::with_capacity(series.len());
+    let mut members: Vec<(i64, LaunchComparisonApp)> = Vec::new();
+    for entry in series {
+        match group_of(&entry) {
+            Some(group) => members.push((group.id, entry)),
+            None => merged.push(entry),
+        }
+    }
+    for group in &groups {
+        let group_members: Vec<&LaunchComparisonApp> = members
+            .iter()
+            .filter(|(id, _)| *id == group.id)
+            .map(|(_, entry)| entry)
+            .collect();
+        let Some(launch_date) = group_members.iter().map(|m| m.launch_date).min() else {
+            continue;
+        };
+        let length = group_members
+            .iter()
+            .map(|m| m.days.len())
+            .max()
+            .unwrap_or(0);
+        let mut days: Vec<LaunchDay> = (0..length as u32)
+            .map(|day| LaunchDay {
+                day,
+                revenue: 0.0,
+                units: 0,
+            })
+            .collect();
+        for member in group_members {
+            let offset = member.launch_date.days_since(launch_date) as usize;
+            for (day, member_day) in days.iter_mut().skip(offset).zip(&member.days) {
+                day.revenue += member_day.revenue;
+                day.units += member_day.units;
+            }
+        }
+        merged.push(LaunchComparisonApp {
+            app_id: if is_package {
+                None
+            } else {
+                group.primary_app_id()
+            },
+            package_id: if is_package {
+                group.package_ids.first().copied()
+            } else {
+                None
+            },
+            app_name: (!is_package).then(|| group.name.clone()),
+            package_name: is_package.then(|| group.name.clone()),
+            group_id: Some(group.id),
+            benchmark_id: None,
+            benchmark_name: None,
+            launch_date,
+            days,
+        });
+    }
+    Ok(merged)
+}
+
+// ==================== Tauri Commands ====================
+
+#[tauri::command]
+pub async fn list_product_groups() -> AppResult<Vec<ProductGroup>> {
+    list_product_groups_all().map_err(AppError::from)
+}
+
+#[tauri::command]
+pub async fn create_product_group(group: ProductGroupInput) -> AppResult<i64> {
+    insert_group(&group)
+}
+
+#[tauri::command]
+pub async fn update_product_group(id: i64, group: ProductGroupInput) -> AppResult<()> {
+    match update_group_by_id(id, &group)? {
+        true => Ok(()),
+        false => Err(AppError::NotFound(format!(
+            "Product group {} not found",
+            id
+        ))),
+    }
+}
+
+#[tauri::command]
+pub async fn delete_product_group(id: i64) -> AppResult<()> {
+    match delete_group_by_id(id)? {
+        true => Ok(()),
+        false => Err(AppError::NotFound(format!(
+            "Product group {} not found",
+            id
+        ))),
+    }
+}
@@ -0,0 +1,175 @@
+// Product search
+//
+// Backs the "jump to product" search box: `search_products` matches a query against the names
+// of every app, package and bundle in the lookup tables and returns the best matches, so the
+// frontend doesn't have to load all of them to filter locally. Matching ignores case. A name
+// equal to the query ranks first, then names starting with it, names with a word starting with
+// it and names containing it; names matching none of these are compared by trigram similarity
+// (as in Postgres' pg_trgm), which catches typos like "hollow nite". A query of digits also
+// matches the product with that id.
+
+use crate::lookup_refresh::{app_names_table, package_names_table};
+use crate::perf;
+use crate::queries::get_connection;
+use crate::schema::table_exists;
+use crate::types::{AppError, AppResult};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::HashSet;
+
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 100;
+// Below this, trigram matches are mostly noise
+const MIN_SIMILARITY: f64 = 0.3;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProductMatch {
+    pub id: i64,
+    /// "app", "package" or "bundle"
+    pub product_type: String,
+    pub name: String,
+    /// 1 for an exact match, lower for weaker ones
+    pub score: f64,
+}
+
+// (type, id, name) of every named product
+fn load_products(conn: &Connection) -> rusqlite::Result<Vec<(&'static str, i64, String)>> {
+    let mut sources = Vec::new();
+    if let Some(apps) = app_names_table(conn) {
+        sources.push(("app", format!("SELECT appid, app_name FROM {}", apps)));
+    }
+    if let Some(packages) = package_names_table(conn) {
+        sources.push((
+            "package",
+            format!("SELECT packageid, package_name FROM {}", packages),
+        ));
+    }
+    if table_exists(conn, "lookup_bundles") {
+        sources.push((
+            "bundle",
+            "SELECT bundleid, bundle_name FROM lookup_bundles".to_string(),
+        ));
+    }
+
+    let mut products = Vec::new();
+    for (product_type, sql) in sources {
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?))
+        })?;
+        for row in rows {
+            if let (id, Some(name)) = row? {
+                if !name.trim().is_empty() {
+                    products.push((product_type, id, name));
+                }
+            }
+        }
+    }
+    Ok(products)
+}
+
+// Letters and digits only, lowercased, words separated by single spaces
+fn normalize(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Trigrams of each word padded with two spaces in front and one behind, as pg_trgm does
+fn trigrams(normalized: &str) -> HashSet<[char; 3]> {
+    let mut set = HashSet::new();
+    for word in normalized.split(' ') {
+        let chars: Vec<char> = "  "
+            .chars()
+            .chain(word.chars())
+            .chain(std::iter::once(' '))
+            .collect();
+        for window in chars.windows(3) {
+            set.insert([window[0], window[1], window[2]]);
+        }
+    }
+    set
+}
+
+fn similarity(a: &HashSet<[char; 3]>, b: &HashSet<[char; 3]>) -> f64 {
+    let shared = a.intersection(b).count();
+    let total = a.len() + b.len() - shared;
+    if total == 0 {
+        0.0
+    } else {
+        shared as f64 / total as f64
+    }
+}
+
+fn score(query: &str, query_trigrams: &HashSet<[char; 3]>, name: &str) -> Option<f64> {
+    let name = normalize(name);
+    if name == query {
+        Some(1.0)
+    } else if name.starts_with(query) {
+        Some(0.9)
+    } else if name.contains(&format!(" {}", query)) {
+        Some(0.8)
+    } else if name.contains(query) {
+        Some(0.7)
+    } else {
+        let similarity = similarity(query_trigrams, &trigrams(&name));
+        // Always below a substring match
+        (similarity >= MIN_SIMILARITY).then_some(0.6 * similarity)
+    }
+}
+
+pub fn search(conn: &Connection, query: &str, limit: usize) -> rusqlite::Result<Vec<ProductMatch>> {
+    let normalized = normalize(query);
+    if normalized.is_empty() {
+        return Ok(Vec::new());
+    }
+    let query_trigrams = trigrams(&normalized);
+    let query_id: Option<i64> = query.trim().parse().ok();
+
+    let mut matches: Vec<ProductMatch> = load_products(conn)?
+        .into_iter()
+        .filter_map(|(product_type, id, name)| {
+            let score = if query_id == Some(id) {
+                Some(1.0)
+            } else {
+                score(&normalized, &query_trigrams, &name)
+            };
+            score.map(|score| ProductMatch {
+                id,
+                product_type: product_type.to_string(),
+                name,
+                score,
+            })
+        })
+        .collect();
+    // Best first; among equals the shorter name is the closer match
+    matches.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then(a.name.len().cmp(&b.name.len()))
+            .then(a.name.cmp(&b.name))
+    });
+    matches.truncate(limit);
+    Ok(matches)
+}
+
+// ==================== Tauri Commands ====================
+
+/// The apps, packages and bundles whose names best match `query`, best first (20 by default).
+#[tauri::command]
+pub async fn search_products(query: String, limit: Option<u32>) -> AppResult<Vec<ProductMatch>> {
+    let limit = limit.map_or(DEFAULT_LIMIT, |l| l as usize);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(AppError::InvalidInput(format!(
+            "Limit must be between 1 and {}",
+            MAX_LIMIT
+        )));
+    }
+    perf::measure("search_products", || {
+        let conn = get_connection()?;
+        Ok(search(&conn, &query, limit)?)
+    })
+}
@@ -0,0 +1,439 @@
+// Quarantine of malformed sales rows
+//
+// The queries read amounts as text and turn anything unparseable into 0.0, and a missing unit
+// count or a bad date makes a whole query fail. After every fetch, sales_data is checked and
+// rows with malformed dates, unit counts or amounts are recorded in `quarantine` in the app
+// database, by data source and key columns (see change_log.rs), together with the reason and the
+// full row as JSON. sales_data itself is left as the CLI wrote it; the dashboard queries and the
+// reporting views leave the recorded rows out, so they neither skew totals silently nor break
+// the views. Rows that pass the checks at the next fetch, or at `reprocess_quarantine`, are
+// dropped from the record and counted again.
+//
+// Earlier versions moved the rows out of sales_data into a `quarantine` table of the sales
+// database; those rows are put back the first time the source is checked.
+
+use crate::change_log;
+use crate::database::{begin_write, get_app_database_path, BUSY_TIMEOUT};
+use crate::reporting_views;
+use crate::schema::{column_exists, table_exists};
+use crate::sources::{self, DataSource};
+use crate::types::{AppError, AppResult, Date};
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{params, params_from_iter, Connection, OpenFlags, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use tracing::{info, warn};
+
+const DEFAULT_ROW_LIMIT: u32 = 500;
+
+#[derive(Debug, Clone, Copy)]
+enum Check {
+    Date,
+    Count,
+    /// A USD amount; `required` amounts must not be NULL
+    Amount {
+        required: bool,
+    },
+}
+
+// Columns of sales_data that are checked, if the database has them
+const CHECKS: &[(&str, Check)] = &[
+    ("date", Check::Date),
+    ("gross_units_sold", Check::Count),
+    ("gross_units_returned", Check::Count),
+    ("net_units_sold", Check::Count),
+    ("gross_sales_usd", Check::Amount { required: true }),
+    ("net_sales_usd", Check::Amount { required: true }),
+    ("gross_returns_usd", Check::Amount { required: false }),
+    ("net_tax_usd", Check::Amount { required: false }),
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuarantinedRow {
+    pub id: i64,
+    /// The row's date, if it had one
+    pub date: Option<String>,
+    pub reason: String,
+    /// All columns of the row as they are in sales_data
+    pub raw_json: serde_json::Value,
+    pub quarantined_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReprocessResult {
+    /// Rows that passed the checks and are counted again
+    pub restored: usize,
+    pub still_invalid: usize,
+}
+
+// In the app database
+fn ensure_table(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS quarantine (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            source_id TEXT NOT NULL,
+            row_key TEXT NOT NULL,
+            date TEXT,
+            reason TEXT NOT NULL,
+            raw_json TEXT NOT NULL,
+            quarantined_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+            UNIQUE(source_id, row_key)
+        );",
+    )
+}
+
+fn app_connection() -> SqliteResult<Connection> {
+    let conn = crate::database::get_app_connection()?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    ensure_table(&conn)?;
+    Ok(conn)
+}
+
+fn open_database(db_path: &Path) -> SqliteResult<Connection> {
+    let conn = Connection::open(db_path)?;
+    // The CLI may be writing to the same database
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    Ok(conn)
+}
+
+fn describe(value: ValueRef) -> String {
+    match value {
+        ValueRef::Null => "NULL".to_string(),
+        ValueRef::Integer(i) => i.to_string(),
+        ValueRef::Real(f) => f.to_string(),
+        ValueRef::Text(t) => format!("'{}'", String::from_utf8_lossy(t)),
+        ValueRef::Blob(_) => "a blob".to_string(),
+    }
+}
+
+// Why `value` fails `check`, if it does
+fn problem(column: &str, check: Check, value: ValueRef) -> Option<String> {
+    let valid = match (check, value) {
+        (Check::Date, ValueRef::Text(t)) => std::str::from_utf8(t)
+            .ok()
+            .is_some_and(|s| s.parse::<Date>().is_ok()),
+        (Check::Count, ValueRef::Integer(_)) => true,
+        // Same format the queries' parse_usd accepts
+        (Check::Amount { .. }, ValueRef::Text(t)) => std::str::from_utf8(t).ok().is_some_and(|s| {
+            s.trim()
+                .replace(',', "")
+                .parse::<f64>()
+                .is_ok_and(f64::is_finite)
+        }),
+        (Check::Amount { .. }, ValueRef::Integer(_) | ValueRef::Real(_)) => true,
+        (Check::Amount { required: false }, ValueRef::Null) => true,
+        _ => false,
+    };
+    if valid {
+        return None;
+    }
+    Some(match (check, value) {
+        (_, ValueRef::Null) => format!("{} is missing", column),
+        (Check::Date, _) => format!("{} {} is not a YYYY-MM-DD date", column, describe(value)),
+        (Check::Count, _) => format!("{} {} is not a whole number", column, describe(value)),
+        (Check::Amount { .. }, _) => format!("{} {} is not an amount", column, describe(value)),
+    })
+}
+
+// The checks for the columns the database has
+fn checks_for(conn: &Connection) -> Vec<(&'static str, Check)> {
+    CHECKS
+        .iter()
+        .filter(|(column, _)| column_exists(conn, "sales_data", column))
+        .copied()
+        .collect()
+}
+
+pub(crate) fn to_json(value: ValueRef) -> serde_json::Value {
+    match value {
+        ValueRef::Null => serde_json::Value::Null,
+        ValueRef::Integer(i) => i.into(),
+        ValueRef::Real(f) => serde_json::Number::from_f64(f)
+            .map(serde_json::Value::Number)
+            .unwrap_or(serde_json::Value::Null),
+        ValueRef::Text(t) => String::from_utf8_lossy(t).into_owned().into(),
+        ValueRef::Blob(b) => b
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>()
+            .into(),
+    }
+}
+
+fn from_json(value: &serde_json::Value) -> Value {
+    match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Integer(i64::from(*b)),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Real(n.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(s) => Value::Text(s.clone()),
+        other => Value::Text(other.to_string()),
+    }
+}
+
+// The sales_data row with `rowid` as a JSON object of its columns
+fn row_json(conn: &Connection, rowid: i64) -> SqliteResult<serde_json::Value> {
+    let mut stmt = conn.prepare("SELECT * FROM sales_data WHERE rowid = ?1")?;
+    let names: Vec<String> = stmt.column_names().iter().map(|n| n.to_string()).collect();
+    stmt.query_row([rowid], |row| {
+        let mut object = serde_json::Map::new();
+        for (i, name) in names.iter().enumerate() {
+            object.insert(name.clone(), to_json(row.get_ref(i)?));
+        }
+        Ok(serde_json::Value::Object(object))
+    })
+}
+
+// Inserts a row given as a JSON object of its columns into sales_data. Columns the schema no
+// longer has are dropped.
+fn insert_row(
+    conn: &Connection,
+    raw: &serde_json::Map<String, serde_json::Value>,
+) -> SqliteResult<()> {
+    let (names, values): (Vec<String>, Vec<Value>) = raw
+        .iter()
+        .filter(|(name, _)| column_exists(conn, "sales_data", name))
+        .map(|(name, value)| {
+            (
+                format!("\"{}\"", name.replace('"', "\"\"")),
+                from_json(value),
+            )
+        })
+        .unzip();
+    let placeholders: Vec<&str> = names.iter().map(|_| "?").collect();
+    conn.execute(
+        &format!(
+            "INSERT INTO sales_data ({}) VALUES ({})",
+            names.join(", "),
+            placeholders.join(", ")
+        ),
+        params_from_iter(values),
+    )?;
+    Ok(())
+}
+
+// Puts the rows earlier versions moved out of sales_data back, and drops their table
+fn restore_moved_rows(conn: &Connection) -> SqliteResult<()> {
+    if !table_exists(conn, "quarantine") {
+        return Ok(());
+    }
+    let tx = begin_write(conn)?;
+    let moved: Vec<String> = {
+        let mut stmt = tx.prepare("SELECT raw_json FROM quarantine ORDER BY id")?;
+        let rows = stmt.query_map([], |r| r.get(0))?;
+        rows.collect::<SqliteResult<_>>()?
+    };
+    for raw_json in &moved {
+        if let Ok(serde_json::Value::Object(raw)) = serde_json::from_str(raw_json) {
+            insert_row(&tx, &raw)?;
+        }
+    }
+    tx.execute_batch("DROP TABLE quarantine")?;
+    tx.commit()?;
+    info!("Put {} quarantined rows back into sales_data", moved.len());
+    Ok(())
+}
+
+/// Checks the rows of `source` and records the malformed ones in `quarantine`, replacing what
+/// was recorded for it before. Returns how many rows are quarantined.
+pub fn quarantine_invalid_rows(source: &DataSource) -> AppResult<usize> {
+    let db_path = Path::new(&source.db_path);
+    if !db_path.exists() {
+        return Ok(0);
+    }
+    let conn = open_database(db_path)?;
+    if !source.read_only {
+        restore_moved_rows(&conn)?;
+    }
+    let checks = checks_for(&conn);
+    if checks.is_empty() {
+        return Ok(0);
+    }
+
+    let columns: Vec<&str> = checks.iter().map(|(column, _)| *column).collect();
+    // Key and reason of each malformed row, by rowid
+    let mut invalid: Vec<(i64, String, String)> = Vec::new();
+    {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT rowid, {}, {} FROM sales_data",
+            change_log::key_expr(&conn, "sales_data"),
+            columns.join(", ")
+        ))?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let mut problems = Vec::new();
+            for (i, (column, check)) in checks.iter().enumerate() {
+                if let Some(problem) = problem(column, *check, row.get_ref(i + 2)?) {
+                    problems.push(problem);
+                }
+            }
+            if !problems.is_empty() {
+                invalid.push((row.get(0)?, row.get(1)?, problems.join("; ")));
+            }
+        }
+    }
+
+    let app = app_connection()?;
+    let tx = begin_write(&app)?;
+    let mut previous: HashMap<String, i64> = {
+        let mut stmt = tx.prepare("SELECT row_key, id FROM quarantine WHERE source_id = ?1")?;
+        let rows = stmt.query_map([&source.id], |r| Ok((r.get(0)?, r.get(1)?)))?;
+        rows.collect::<SqliteResult<_>>()?
+    };
+    let mut added = 0;
+    for (rowid, row_key, reason) in &invalid {
+        let raw = row_json(&conn, *rowid)?;
+        let date = raw.get("date").and_then(|d| d.as_str()).map(str::to_string);
+        if previous.remove(row_key).is_none() {
+            added += 1;
+        }
+        // A row the CLI wrote again after an earlier fetch keeps its quarantine time
+        tx.execute(
+            "INSERT INTO quarantine (source_id, row_key, date, reason, raw_json)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(source_id, row_key) DO UPDATE SET
+                date = excluded.date,
+                reason = excluded.reason,
+                raw_json = excluded.raw_json",
+            params![source.id, row_key, date, reason, raw.to_string()],
+        )?;
+    }
+    // Rows that pass now, or that the CLI replaced
+    for id in previous.values() {
+        tx.execute("DELETE FROM quarantine WHERE id = ?1", [id])?;
+    }
+    tx.commit()?;
+    if added > 0 || !previous.is_empty() {
+        reporting_views::refresh_if_created(&conn);
+    }
+    if added > 0 {
+        warn!(
+            "Quarantined {} malformed sales rows of data source {}",
+            added, source.id
+        );
+    }
+    Ok(invalid.len())
+}
+
+/// SQL condition on the sales_data row `alias` that is false for the quarantined rows of
+/// `source_id`, or None if it has none.
+pub(crate) fn exclusion(conn: &Connection, source_id: &str, alias: &str) -> Option<String> {
+    // Nothing is quarantined before the app database exists
+    let app = Connection::open_with_flags(
+        get_app_database_path(),
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .ok()?;
+    if !table_exists(&app, "quarantine") {
+        return None;
+    }
+    let quarantined: Vec<(Option<String>, String)> = app
+        .prepare("SELECT date, row_key FROM quarantine WHERE source_id = ?1")
+        .and_then(|mut stmt| {
+            stmt.query_map([source_id], |r| Ok((r.get(0)?, r.get(1)?)))?
+                .collect()
+        })
+        .ok()?;
+    if quarantined.is_empty() {
+        return None;
+    }
+    let literal = |value: &str| format!("'{}'", value.replace('\'', "''"));
+    let mut dates: Vec<String> = quarantined
+        .iter()
+        .map(|(date, _)| literal(date.as_deref().unwrap_or("")))
+        .collect();
+    dates.sort();
+    dates.dedup();
+    let keys: Vec<String> = quarantined.iter().map(|(_, key)| literal(key)).collect();
+    // The date narrows the rows down before their key is built
+    Some(format!(
+        "NOT (COALESCE({alias}.date, '') IN ({dates}) AND {key} IN ({keys}))",
+        alias = alias,
+        dates = dates.join(", "),
+        key = change_log::key_expr(conn, alias),
+        keys = keys.join(", ")
+    ))
+}
+
+/// `exclusion` for the data source whose database `conn` is connected to.
+pub(crate) fn exclusion_for_database(conn: &Connection, alias: &str) -> Option<String> {
+    let path = conn.path()?;
+    let source = sources::list_sources()
+        .ok()?
+        .into_iter()
+        .find(|source| Path::new(&source.db_path) == Path::new(path))?;
+    exclusion(conn, &source.id, alias)
+}
+
+pub fn get_quarantined(source_id: &str, limit: u32) -> SqliteResult<Vec<QuarantinedRow>> {
+    let conn = app_connection()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, date, reason, raw_json, quarantined_at FROM quarantine
+         WHERE source_id = ?1
+         ORDER BY id DESC LIMIT ?2",
+    )?;
+    let rows = stmt.query_map(params![source_id, limit], |r| {
+        Ok(QuarantinedRow {
+            id: r.get(0)?,
+            date: r.get(1)?,
+            reason: r.get(2)?,
+            raw_json: serde_json::from_str(&r.get::<_, String>(3)?)
+                .unwrap_or(serde_json::Value::Null),
+            quarantined_at: r.get(4)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Checks the rows of `source` again; quarantined rows that pass now are counted again.
+pub fn reprocess(source: &DataSource) -> AppResult<ReprocessResult> {
+    let before = app_connection()?.query_row(
+        "SELECT COUNT(*) FROM quarantine WHERE source_id = ?1",
+        [&source.id],
+        |r| r.get::<_, usize>(0),
+    )?;
+    let still_invalid = quarantine_invalid_rows(source)?;
+    let result = ReprocessResult {
+        restored: before.saturating_sub(still_invalid),
+        still_invalid,
+    };
+    if result.restored > 0 {
+        info!("Restored {} quarantined sales rows", result.restored);
+    }
+    Ok(result)
+}
+
+// ==================== Tauri Commands ====================
+
+/// Quarantined rows of the active source, newest first. `limit` defaults to 500.
+#[tauri::command]
+pub async fn get_quarantined_rows(limit: Option<u32>) -> AppResult<Vec<QuarantinedRow>> {
+    tokio::task::spawn_blocking(move || {
+        let source_id = sources::active_source_id();
+        Ok(get_quarantined(
+            &source_id,
+            limit.unwrap_or(DEFAULT_ROW_LIMIT),
+        )?)
+    })
+    .await?
+}
+
+#[tauri::command]
+pub async fn reprocess_quarantine() -> AppResult<ReprocessResult> {
+    tokio::task::spawn_blocking(|| {
+        let source = sources::resolve_source(None)?;
+        if !Path::new(&source.db_path).exists() {
+            return Err(AppError::NotFound(format!(
+                "Database not found at {}",
+                source.db_path
+            )));
+        }
+        reprocess(&source)
+    })
+    .await?
+}
@@ -1,29 +1,206 @@
 // Database query module - queries SQLite directly from Rust
 
-use rusqlite::{Connection, Result as SqliteResult};
+use crate::adjustments;
+use crate::annotations::{self, Annotation};
+use crate::benchmarks;
+use crate::app_metadata;
+use crate::countries;
+use crate::custom_metrics::{self, MetricValues, MoneyPowers};
+use crate::database::BUSY_TIMEOUT;
+use crate::keys;
+use crate::lookup_refresh::{app_names_table, package_names_table};
+use crate::perf;
+use crate::presentation::{self, Monetary};
+use crate::product_groups;
+use crate::query_cache::QueryCache;
+use crate::query_control::{self, RunningQueries};
+use crate::read_snapshot;
+use crate::schema::{column_exists, first_existing_column, table_exists};
+use crate::settings;
+use crate::sources::{self, DataSource};
+use crate::types::{
+    AppError, AppResult, Date, Granularity, RelativeRange, RevenueBasis, SortField, SortOrder,
+};
+use rusqlite::{params_from_iter, Connection, OpenFlags, Result as SqliteResult};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager};
+
+// Records per `raw-data-chunk` event
+const DEFAULT_RAW_DATA_CHUNK_SIZE: usize = 5000;
+const MAX_RAW_DATA_CHUNK_SIZE: usize = 50_000;
+
+// Opens the database to read for `live_path` with `flags`. A snapshot copy taken during a sync
+// is opened read-only: it is deleted when the sync ends, so writes to it would be lost.
+fn open_for_reading(live_path: &Path, flags: OpenFlags) -> SqliteResult<Connection> {
+    let path = read_snapshot::reading_path(live_path);
+    let flags = if path == live_path {
+        flags
+    } else {
+        flags
+            .difference(OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE)
+            .union(OpenFlags::SQLITE_OPEN_READ_ONLY)
+    };
+    let conn = Connection::open_with_flags(&path, flags)?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    query_control::watch(&conn);
+    Ok(conn)
+}
 
-// Get a new database connection (SQLite handles connection pooling internally)
+// Get a new database connection (SQLite handles connection pooling internally). Read-only while
+// a sync reads a snapshot; use `get_write_connection` to change anything.
 pub fn get_connection() -> SqliteResult<Connection> {
-    use crate::database::get_database_path;
+    open_for_reading(&crate::database::get_database_path(), OpenFlags::default())
+}
+
+/// Connection to the active source's live database, also while queries read a snapshot of it.
+/// Everything that writes to the sales database goes through this.
+pub fn get_write_connection() -> SqliteResult<Connection> {
+    let conn = Connection::open(crate::database::get_database_path())?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    Ok(conn)
+}
+
+// A data source that can't be queried, reported like a database SQLite can't open
+fn cannot_open(message: String) -> rusqlite::Error {
+    rusqlite::Error::SqliteFailure(
+        rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CANTOPEN),
+        Some(message),
+    )
+}
+
+// The data source synced with the key: the active one if it is, otherwise the only one
+fn source_for_key(key_id: &str) -> SqliteResult<DataSource> {
+    let matching: Vec<DataSource> = sources::list_sources()?
+        .into_iter()
+        .filter(|s| s.key_id.as_deref() == Some(key_id))
+        .collect();
+    if let Some(active) = matching.iter().find(|s| s.active) {
+        return Ok(active.clone());
+    }
+    match matching.len() {
+        0 => Err(cannot_open(format!(
+            "No data source has been synced with API key '{}'",
+            key_id
+        ))),
+        1 => Ok(matching.into_iter().next().unwrap()),
+        _ => Err(cannot_open(format!(
+            "Several data sources are synced with API key '{}'; choose one with data_source",
+            key_id
+        ))),
+    }
+}
+
+// `filters.data_source`, else the source synced with `filters.api_key_id`, or None for the
+// active source
+fn source_for(filters: &QueryFilters) -> SqliteResult<Option<DataSource>> {
+    let source = match (
+        filters.data_source.as_deref(),
+        filters.api_key_id.as_deref(),
+    ) {
+        (None, None) => return Ok(None),
+        (None, Some(key_id)) => source_for_key(key_id)?,
+        (Some(id), key_id) => {
+            let source = sources::get_source(id)?
+                .ok_or_else(|| cannot_open(format!("Data source '{}' not found", id)))?;
+            if let Some(key_id) = key_id {
+                if source.key_id.as_deref() != Some(key_id) {
+                    return Err(cannot_open(format!(
+                        "Data source '{}' isn't synced with API key '{}'",
+                        id, key_id
+                    )));
+                }
+            }
+            source
+        }
+    };
+    Ok(Some(source))
+}
+
+// Database of the source `source_for` picks
+pub(crate) fn database_path_for(filters: &QueryFilters) -> SqliteResult<PathBuf> {
+    Ok(match source_for(filters)? {
+        Some(source) => PathBuf::from(source.db_path),
+        None => crate::database::get_database_path(),
+    })
+}
+
+/// Id of the data source `filters` pick.
+pub(crate) fn source_id_for(filters: &QueryFilters) -> String {
+    match source_for(filters) {
+        Ok(Some(source)) => source.id,
+        _ => sources::active_source_id(),
+    }
+}
 
-    let path = get_database_path();
-    Connection::open(&path)
+// Connection to the database `database_path_for` picks
+pub(crate) fn connection_for(filters: &QueryFilters) -> SqliteResult<Connection> {
+    if filters.data_source.is_none() && filters.api_key_id.is_none() {
+        return get_connection();
+    }
+    // A source whose database is missing is an error, not a new empty database
+    open_for_reading(
+        &database_path_for(filters)?,
+        OpenFlags::default().difference(OpenFlags::SQLITE_OPEN_CREATE),
+    )
 }
 
 // ==================== Query Parameters ====================
 
+/// Filters of the query commands. Field names are camelCase like in the results; the
+/// snake_case names the frontend used to send are still accepted.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct QueryFilters {
-    pub start_date: Option<String>,
-    pub end_date: Option<String>,
+    #[serde(alias = "start_date")]
+    pub start_date: Option<Date>,
+    #[serde(alias = "end_date")]
+    pub end_date: Option<Date>,
+    /// Named range instead of the dates, e.g. "last_90d" or "fy2024"; see `resolve_date_range`
+    #[serde(alias = "date_range")]
+    pub date_range: Option<RelativeRange>,
+    #[serde(alias = "app_ids")]
     pub app_ids: Option<Vec<i64>>,
+    #[serde(alias = "country_code")]
     pub country_code: Option<String>,
+    /// Storefront: "steam" or "itch"
+    pub source: Option<String>,
     pub limit: Option<u32>,
     pub offset: Option<u32>,
-    pub sort_by: Option<String>,
-    pub sort_order: Option<String>,
+    #[serde(alias = "sort_by")]
+    pub sort_by: Option<SortField>,
+    #[serde(alias = "sort_order")]
+    pub sort_order: Option<SortOrder>,
+    /// Leave out the day Steam is still reporting; None uses the setting
+    #[serde(alias = "exclude_partial_day")]
+    pub exclude_partial_day: Option<bool>,
+    /// Period daily summaries are grouped by; None groups by day
+    pub granularity: Option<Granularity>,
+    /// Merge the apps of each product group into one app summary
+    #[serde(alias = "group_products")]
+    pub group_products: Option<bool>,
+    /// Custom metrics (by name) to evaluate per daily, app and country summary
+    pub metrics: Option<Vec<String>>,
+    /// Count grant and activation line items in summaries; None leaves them out
+    #[serde(alias = "include_non_sale_items")]
+    pub include_non_sale_items: Option<bool>,
+    /// Revenue figure summaries add up; None uses gross
+    #[serde(alias = "revenue_basis")]
+    pub revenue_basis: Option<RevenueBasis>,
+    /// Only rows with these `package_sale_type`s, e.g. to leave retail key sales out of
+    /// storefront figures; None or empty keeps all
+    #[serde(alias = "sale_types")]
+    pub sale_types: Option<Vec<String>>,
+    /// Id of the data source to query instead of the active one, e.g. an imported share bundle
+    #[serde(alias = "data_source")]
+    pub data_source: Option<String>,
+    /// Query the data source synced with this API key (see keys.rs) instead of the active one;
+    /// with `data_source`, the source must be synced with it
+    #[serde(alias = "api_key_id")]
+    pub api_key_id: Option<String>,
+    /// Overlay manual adjustments (see adjustments.rs) on the rows; None uses the rows as stored
+    #[serde(alias = "apply_adjustments")]
+    pub apply_adjustments: Option<bool>,
 }
 
 impl Default for QueryFilters {
@@ -31,13 +208,59 @@ impl Default for QueryFilters {
         Self {
             start_date: None,
             end_date: None,
+            date_range: None,
             app_ids: None,
             country_code: None,
+            source: None,
             limit: Some(1000),
             offset: Some(0),
-            sort_by: Some("date".to_string()),
-            sort_order: Some("desc".to_string()),
+            sort_by: Some(SortField::Date),
+            sort_order: Some(SortOrder::Desc),
+            exclude_partial_day: None,
+            granularity: None,
+            group_products: None,
+            metrics: None,
+            include_non_sale_items: None,
+            revenue_basis: None,
+            sale_types: None,
+            data_source: None,
+            api_key_id: None,
+            apply_adjustments: None,
+        }
+    }
+}
+
+impl QueryFilters {
+    /// Replaces `date_range` with the dates it stands for today. Filters without any dates get
+    /// the default range from the settings. Commands call this on the filters they are given,
+    /// so that every query and export draws the same period boundaries.
+    pub(crate) fn resolve_date_range(mut self) -> AppResult<Self> {
+        let range = match self.date_range.take() {
+            Some(_) if self.start_date.is_some() || self.end_date.is_some() => {
+                return Err(AppError::InvalidInput(
+                    "Give either a date range or start and end dates, not both".to_string(),
+                ))
+            }
+            Some(range) => range,
+            None if self.start_date.is_none() && self.end_date.is_none() => {
+                match settings::load_settings().default_date_range {
+                    Some(range) => range,
+                    None => return Ok(self),
+                }
+            }
+            None => return Ok(self),
+        };
+        (self.start_date, self.end_date) = date_range_bounds(range);
+        Ok(self)
+    }
+
+    // Ends the range the day before the one Steam is still reporting, if the current day is
+    // excluded by the request or the settings
+    pub(crate) fn complete_days_only(mut self) -> Self {
+        if let Some(last_complete) = last_complete_day(self.exclude_partial_day) {
+            self.end_date = Some(self.end_date.map_or(last_complete, |d| d.min(last_complete)));
         }
+        self
     }
 }
 
@@ -48,44 +271,39 @@ impl Default for QueryFilters {
 pub struct SalesRecord {
     pub id: u64,
     pub date: String,
-    #[serde(rename = "lineItemType")]
     pub line_item_type: String,
-    #[serde(rename = "appId")]
     pub app_id: Option<i64>,
-    #[serde(rename = "appName")]
     pub app_name: Option<String>,
-    #[serde(rename = "packageId")]
     pub package_id: Option<i64>,
-    #[serde(rename = "packageName")]
     pub package_name: Option<String>,
-    #[serde(rename = "countryCode")]
     pub country_code: Option<String>,
-    #[serde(rename = "countryName")]
     pub country_name: Option<String>,
     pub region: Option<String>,
     pub platform: Option<String>,
     pub currency: Option<String>,
-    #[serde(rename = "grossUnitsSold")]
     pub gross_units_sold: i64,
-    #[serde(rename = "grossUnitsReturned")]
     pub gross_units_returned: i64,
-    #[serde(rename = "netUnitsSold")]
     pub net_units_sold: i64,
-    #[serde(rename = "grossSalesUsd")]
     pub gross_sales_usd: f64,
-    #[serde(rename = "netSalesUsd")]
     pub net_sales_usd: f64,
-    #[serde(rename = "discountPercentage")]
     pub discount_percentage: Option<f64>,
+    /// "sale", "return", "grant" or "activation"; see `line_item_category_expr`
+    pub line_item_category: String,
+    /// rowid of the row in sales_data, which manual adjustments refer to; None for itch.io rows
+    pub row_id: Option<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct SalesResponse {
     pub records: Vec<SalesRecord>,
     pub pagination: Pagination,
+    /// Set while a fetch runs and the records come from a snapshot; see read_snapshot.rs
+    pub data_as_of: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Pagination {
     pub total: u64,
     pub limit: u32,
@@ -93,7 +311,7 @@ pub struct Pagination {
     pub has_more: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DashboardStats {
     pub total_revenue: f64,
@@ -102,81 +320,139 @@ pub struct DashboardStats {
     pub app_count: u64,
     pub country_count: u64,
     pub date_range: Option<DateRange>,
+    /// Set while a fetch runs and the stats come from a snapshot; see read_snapshot.rs
+    pub data_as_of: Option<String>,
 }
 
+/// One `raw-data-chunk` event of `stream_raw_data_by_date`
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RawDataChunk {
+    /// The id the frontend passed, to tell concurrent streams apart
+    pub request_id: String,
+    pub chunk_index: u32,
+    pub records: Vec<SalesRecord>,
+    /// No more chunks follow
+    pub done: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RawDataStream {
+    pub total_records: u64,
+    pub chunks: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DateRange {
     pub min: String,
     pub max: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DailySummary {
+    /// The day, or the first day of the period when grouped by week, month, quarter or year
     pub date: String,
     pub total_revenue: f64,
     pub total_units: i64,
     pub record_count: u64,
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+    /// The day (or period) Steam is still reporting, so its totals will grow
+    #[serde(default)]
+    pub partial: bool,
+    /// Custom metrics requested in the filters
+    #[serde(default, skip_serializing_if = "MetricValues::is_empty")]
+    pub metrics: MetricValues,
+    /// How the metrics scale with amounts of money, for presentation mode
+    #[serde(skip)]
+    pub metric_money_powers: MoneyPowers,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AppSummary {
-    #[serde(rename = "appId")]
     pub app_id: i64,
-    #[serde(rename = "appName")]
     pub app_name: Option<String>,
     pub total_revenue: f64,
     pub total_units: i64,
     pub record_count: u64,
-    #[serde(rename = "firstSale")]
     pub first_sale: String,
-    #[serde(rename = "lastSale")]
     pub last_sale: String,
+    /// Set when the summary merges the apps of a product group
+    pub group_id: Option<i64>,
+    /// Custom metrics requested in the filters
+    #[serde(default, skip_serializing_if = "MetricValues::is_empty")]
+    pub metrics: MetricValues,
+    /// How the metrics scale with amounts of money, for presentation mode
+    #[serde(skip)]
+    pub metric_money_powers: MoneyPowers,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CountrySummary {
-    #[serde(rename = "countryCode")]
     pub country_code: String,
-    #[serde(rename = "countryName")]
     pub country_name: Option<String>,
+    /// UN M49 region, e.g. "Europe"
     pub region: Option<String>,
+    /// UN M49 subregion, e.g. "Western Europe"
+    pub subregion: Option<String>,
+    pub eu_member: bool,
     pub total_revenue: f64,
     pub total_units: i64,
     pub record_count: u64,
+    /// Custom metrics requested in the filters
+    #[serde(default, skip_serializing_if = "MetricValues::is_empty")]
+    pub metrics: MetricValues,
+    /// How the metrics scale with amounts of money, for presentation mode
+    #[serde(skip)]
+    pub metric_money_powers: MoneyPowers,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AppLookup {
-    #[serde(rename = "appId")]
     pub app_id: i64,
-    #[serde(rename = "appName")]
     pub app_name: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CountryLookup {
-    #[serde(rename = "countryCode")]
     pub country_code: String,
-    #[serde(rename = "countryName")]
     pub country_name: String,
     pub region: Option<String>,
+    pub subregion: Option<String>,
+    pub eu_member: bool,
+}
+
+/// A data source that queries can be scoped to, with `data_source` or `api_key_id`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuerySource {
+    pub id: String,
+    pub name: String,
+    /// API key the source is synced with; None for imported and itch.io-only sources
+    pub api_key_id: Option<String>,
+    /// Last characters of that key, to tell accounts apart
+    pub key_hint: Option<String>,
+    pub active: bool,
+    pub read_only: bool,
+    /// None if the database doesn't exist yet or has no sales
+    pub date_range: Option<DateRange>,
+    pub record_count: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PackageLookup {
-    #[serde(rename = "packageId")]
     pub package_id: i64,
-    #[serde(rename = "packageName")]
     pub package_name: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ProductStats {
     pub total_revenue: f64,
@@ -188,7 +464,7 @@ pub struct ProductStats {
     pub by_platform: Vec<PlatformSummary>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PlatformSummary {
     pub platform: Option<String>,
@@ -197,7 +473,7 @@ pub struct PlatformSummary {
     pub record_count: u64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LaunchDay {
     pub day: u32,
@@ -205,18 +481,19 @@ pub struct LaunchDay {
     pub units: i64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LaunchComparisonApp {
-    #[serde(rename = "appId")]
     pub app_id: Option<i64>,
-    #[serde(rename = "packageId")]
     pub package_id: Option<i64>,
-    #[serde(rename = "appName")]
     pub app_name: Option<String>,
-    #[serde(rename = "packageName")]
     pub package_name: Option<String>,
-    pub launch_date: String,
+    /// Set when the series merges the members of a product group
+    pub group_id: Option<i64>,
+    /// Set on a series saved earlier as a benchmark, which is not recomputed; see benchmarks.rs
+    pub benchmark_id: Option<i64>,
+    pub benchmark_name: Option<String>,
+    pub launch_date: Date,
     pub days: Vec<LaunchDay>,
 }
 
@@ -224,26 +501,29 @@ pub struct LaunchComparisonApp {
 
 // Get the app ID column name from the database schema
 // Prioritizes "primary_app_id" as that's the actual column name in the database
-fn get_app_id_column(conn: &Connection) -> String {
+pub(crate) fn get_app_id_column(conn: &Connection) -> String {
     // Check in priority order: primary_app_id first, then fallbacks
-    for col_name in &["primary_app_id", "primary_appid", "appid", "app_id"] {
-        let exists: i64 = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('sales_data') WHERE name = ?",
-                [col_name],
-                |row| row.get(0),
-            )
-            .unwrap_or(0);
-        if exists > 0 {
-            return col_name.to_string();
-        }
-    }
+    first_existing_column(
+        conn,
+        "sales_data",
+        &["primary_app_id", "primary_appid", "appid", "app_id"],
+    )
     // Fallback to appid if nothing found
-    "appid".to_string()
+    .unwrap_or_else(|| "appid".to_string())
+}
+
+// Get the storefront column of sales_data. Only databases earlier versions wrote itch.io rows
+// into have a `source` column; in the others, all rows are Steam sales.
+pub(crate) fn get_source_column(conn: &Connection) -> &'static str {
+    if column_exists(conn, "sales_data", "source") {
+        "source"
+    } else {
+        "'steam'"
+    }
 }
 
 // Get the app name column name from the database schema
-fn get_app_name_column(conn: &Connection) -> Option<String> {
+pub(crate) fn get_app_name_column(conn: &Connection) -> Option<String> {
     // Check for app name columns in priority order
     for col_name in &[
         "primary_app_name",
@@ -252,14 +532,7 @@ fn get_app_name_column(conn: &Connection) -> Option<String> {
         "appname",
         "appName",
     ] {
-        let exists: i64 = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('sales_data') WHERE name = ?",
-                [col_name],
-                |row| row.get(0),
-            )
-            .unwrap_or(0);
-        if exists > 0 {
+        if column_exists(conn, "sales_data", col_name) {
             // Check if column has any non-NULL values
             let has_values: i64 = conn
                 .query_row(
@@ -279,11 +552,93 @@ fn get_app_name_column(conn: &Connection) -> Option<String> {
     None
 }
 
-struct WhereClause {
-    clause: String,
+// Get the package name column name from the database schema
+pub(crate) fn get_package_name_column(conn: &Connection) -> Option<String> {
+    first_existing_column(
+        conn,
+        "sales_data",
+        &[
+            "primary_package_name",
+            "primary_packagename",
+            "package_name",
+            "packagename",
+            "packageName",
+        ],
+    )
+}
+
+pub(crate) struct WhereClause {
+    pub(crate) clause: String,
+}
+
+impl WhereClause {
+    /// Leaves out grant and activation line items unless `filters` asks to include them.
+    pub(crate) fn sale_items_only(mut self, conn: &Connection, filters: &QueryFilters) -> Self {
+        if filters.include_non_sale_items != Some(true) {
+            let condition = format!("{} IN ('sale', 'return')", line_item_category_expr(conn));
+            self.clause = if self.clause.is_empty() {
+                format!("WHERE {}", condition)
+            } else {
+                format!("{} AND {}", self.clause, condition)
+            };
+        }
+        self
+    }
+}
+
+/// SQL expression for the revenue of a sales_data row on `basis`. Net after tax is net if the
+/// database has no net_tax_usd column.
+pub(crate) fn revenue_expr(conn: &Connection, basis: RevenueBasis) -> String {
+    match basis {
+        RevenueBasis::Gross => "CAST(gross_sales_usd AS REAL)".to_string(),
+        RevenueBasis::Net => "CAST(net_sales_usd AS REAL)".to_string(),
+        RevenueBasis::NetAfterTax if column_exists(conn, "sales_data", "net_tax_usd") => {
+            "(CAST(net_sales_usd AS REAL) - COALESCE(CAST(net_tax_usd AS REAL), 0))".to_string()
+        }
+        RevenueBasis::NetAfterTax => "CAST(net_sales_usd AS REAL)".to_string(),
+    }
+}
+
+/// SQL expression classifying a sales_data row as 'sale', 'return', 'grant' or 'activation'.
+///
+/// Grants are rows another partner shares with this one (`viw_grant_partnerid`, sic), whose units
+/// and revenue are theirs; activations are keys activated without a sale. Rows with only returns
+/// stay in revenue summaries, since they offset sales in net revenue.
+pub(crate) fn line_item_category_expr(conn: &Connection) -> String {
+    let has = |column: &str| column_exists(conn, "sales_data", column);
+    let mut cases: Vec<String> = Vec::new();
+    if let Some(grant_col) = first_existing_column(
+        conn,
+        "sales_data",
+        &["viw_grant_partnerid", "view_grant_partnerid"],
+    ) {
+        let own_partner = if has("partnerid") {
+            format!(" AND {} != COALESCE(partnerid, 0)", grant_col)
+        } else {
+            String::new()
+        };
+        cases.push(format!(
+            "WHEN COALESCE({c}, 0) != 0{own} THEN 'grant'",
+            c = grant_col,
+            own = own_partner
+        ));
+    }
+    if has("gross_units_activated") {
+        cases.push(
+            "WHEN COALESCE(gross_units_activated, 0) != 0 AND COALESCE(gross_units_sold, 0) = 0
+                AND COALESCE(CAST(gross_sales_usd AS REAL), 0) = 0 THEN 'activation'"
+                .to_string(),
+        );
+    }
+    cases.push(
+        "WHEN COALESCE(gross_units_returned, 0) != 0 AND COALESCE(gross_units_sold, 0) = 0
+            THEN 'return'"
+            .to_string(),
+    );
+    format!("(CASE {} ELSE 'sale' END)", cases.join(" "))
 }
 
-fn build_where_clause(filters: &QueryFilters, app_id_col: &str) -> WhereClause {
+pub(crate) fn build_where_clause(filters: &QueryFilters, app_id_col: &str, source_col: &str) -> WhereClause {
     let mut conditions: Vec<String> = Vec::new();
 
     if filters.start_date.is_some() {
@@ -302,6 +657,18 @@ fn build_where_clause(filters: &QueryFilters, app_id_col: &str) -> WhereClause {
     if filters.country_code.is_some() {
         conditions.push("country_code = ?".to_string());
     }
+    if filters.source.is_some() {
+        conditions.push(format!("{} = ?", source_col));
+    }
+    if let Some(ref sale_types) = filters.sale_types {
+        if !sale_types.is_empty() {
+            let placeholders: Vec<String> = sale_types.iter().map(|_| "?".to_string()).collect();
+            conditions.push(format!(
+                "COALESCE(package_sale_type, '') IN ({})",
+                placeholders.join(", ")
+            ));
+        }
+    }
 
     let where_clause = if conditions.is_empty() {
         String::new()
@@ -314,6 +681,53 @@ fn build_where_clause(filters: &QueryFilters, app_id_col: &str) -> WhereClause {
     }
 }
 
+/// The dates `range` stands for today, with the fiscal year from the settings.
+pub(crate) fn date_range_bounds(range: RelativeRange) -> (Option<Date>, Option<Date>) {
+    let settings = settings::load_settings();
+    range.bounds(
+        Date::today_in(&settings.reporting_timezone),
+        settings.fiscal_year_start_month,
+    )
+}
+
+// Today in the reporting timezone, the day Steam has not finished reporting
+pub(crate) fn reporting_today() -> Date {
+    Date::today_in(&settings::load_settings().reporting_timezone)
+}
+
+/// The last day Steam has finished reporting, if the partial current day is to be left out.
+/// `exclude` overrides the `exclude_partial_day` setting.
+pub(crate) fn last_complete_day(exclude: Option<bool>) -> Option<Date> {
+    let settings = settings::load_settings();
+    exclude
+        .unwrap_or(settings.exclude_partial_day)
+        .then(|| Date::today_in(&settings.reporting_timezone).add_days(-1))
+}
+
+fn mark_partial_day(days: &mut [DailySummary], granularity: Granularity) {
+    let today = granularity.period_start(reporting_today()).to_string();
+    for day in days.iter_mut() {
+        day.partial = day.date >= today;
+    }
+}
+
+// Row of (code, name, region, subregion, EU member, revenue, units, record count), the columns
+// of an aggregate joined with lookup_countries
+fn row_to_country_summary(row: &rusqlite::Row) -> SqliteResult<CountrySummary> {
+    Ok(CountrySummary {
+        country_code: row.get(0)?,
+        country_name: row.get(1)?,
+        region: row.get(2)?,
+        subregion: row.get(3)?,
+        eu_member: row.get(4)?,
+        total_revenue: row.get(5)?,
+        total_units: row.get(6)?,
+        record_count: row.get::<_, i64>(7)? as u64,
+        metrics: MetricValues::new(),
+        metric_money_powers: MoneyPowers::new(),
+    })
+}
+
 fn parse_usd(value: &str) -> f64 {
     value.trim().replace(',', "").parse().unwrap_or(0.0)
 }
@@ -321,22 +735,27 @@ fn parse_usd(value: &str) -> f64 {
 // ==================== Query Functions ====================
 
 pub fn get_stats(filters: QueryFilters) -> SqliteResult<DashboardStats> {
-    let conn = get_connection()?;
+    let filters = filters.complete_days_only();
+    let conn = connection_for(&filters)?;
     let app_id_col = get_app_id_column(&conn);
+    let sales = adjustments::sales_source(&conn, &filters);
 
-    let where_clause = build_where_clause(&filters, &app_id_col);
+    let where_clause = build_where_clause(&filters, &app_id_col, sales.source_column)
+        .sale_items_only(&conn, &filters);
 
     let sql = format!(
         "SELECT 
-            COALESCE(SUM(CAST(gross_sales_usd AS REAL)), 0) as total_revenue,
+            COALESCE(SUM({revenue}), 0) as total_revenue,
             COALESCE(SUM(net_units_sold), 0) as total_units,
             COUNT(*) as record_count,
             COUNT(DISTINCT {}) as app_count,
             COUNT(DISTINCT country_code) as country_count,
             MIN(date) as min_date,
             MAX(date) as max_date
-        FROM sales_data {}",
-        app_id_col, where_clause.clause
+        FROM {sales} {}",
+        app_id_col,
+        where_clause.clause,
+        revenue = revenue_expr(&conn, filters.revenue_basis.unwrap_or_default())
     );
 
     let mut stmt = conn.prepare(&sql)?;
@@ -357,6 +776,14 @@ pub fn get_stats(filters: QueryFilters) -> SqliteResult<DashboardStats> {
     if let Some(ref country_code) = filters.country_code {
         params.push(country_code);
     }
+    if let Some(ref source) = filters.source {
+        params.push(source);
+    }
+    if let Some(ref sale_types) = filters.sale_types {
+        for sale_type in sale_types {
+            params.push(sale_type);
+        }
+    }
 
     let row = stmt.query_row(params.as_slice(), |row| {
         Ok((
@@ -386,14 +813,43 @@ pub fn get_stats(filters: QueryFilters) -> SqliteResult<DashboardStats> {
         app_count: app_count as u64,
         country_count: country_count as u64,
         date_range,
+        data_as_of: read_snapshot::data_as_of(&database_path_for(&filters)?),
     })
 }
 
 pub fn get_sales(filters: QueryFilters) -> SqliteResult<SalesResponse> {
-    let conn = get_connection()?;
+    let mut records = Vec::new();
+    let total = for_each_sales_record(&filters, |record| {
+        records.push(record);
+        Ok(())
+    })?;
+    let offset = filters.offset.unwrap_or(0) as u64;
+    let has_more = offset + (records.len() as u64) < total;
+
+    Ok(SalesResponse {
+        records,
+        pagination: Pagination {
+            total,
+            limit: filters.limit.unwrap_or(1000),
+            offset: filters.offset.unwrap_or(0),
+            has_more,
+        },
+        data_as_of: read_snapshot::data_as_of(&database_path_for(&filters)?),
+    })
+}
+
+// Passes the records matching `filters` (sorted, limited and offset as requested) to `visit`
+// one at a time and returns the total number of matching records, so callers don't need to
+// hold all of them in memory
+fn for_each_sales_record(
+    filters: &QueryFilters,
+    mut visit: impl FnMut(SalesRecord) -> SqliteResult<()>,
+) -> SqliteResult<u64> {
+    let conn = connection_for(filters)?;
     let app_id_col = get_app_id_column(&conn);
+    let sales = adjustments::sales_source(&conn, filters);
 
-    let where_clause = build_where_clause(&filters, &app_id_col);
+    let where_clause = build_where_clause(filters, &app_id_col, sales.source_column);
 
     // Build params for WHERE clause
     let mut where_params: Vec<&dyn rusqlite::ToSql> = Vec::new();
@@ -411,22 +867,28 @@ pub fn get_sales(filters: QueryFilters) -> SqliteResult<SalesResponse> {
     if let Some(ref country_code) = filters.country_code {
         where_params.push(country_code);
     }
+    if let Some(ref source) = filters.source {
+        where_params.push(source);
+    }
+    if let Some(ref sale_types) = filters.sale_types {
+        for sale_type in sale_types {
+            where_params.push(sale_type);
+        }
+    }
 
     // Get total count
-    let count_sql = format!("SELECT COUNT(*) FROM sales_data {}", where_clause.clause);
+    let count_sql = format!("SELECT COUNT(*) FROM {sales} {}", where_clause.clause);
     let total: i64 = conn.query_row(&count_sql, where_params.as_slice(), |row| row.get(0))?;
 
     // Build ORDER BY
-    let sort_by = filters.sort_by.as_deref().unwrap_or("date");
-    let sort_order = filters
-        .sort_order
-        .as_deref()
-        .unwrap_or("desc")
-        .to_uppercase();
-    let order_by = match sort_by {
-        "revenue" => format!("CAST(gross_sales_usd AS REAL) {}", sort_order),
-        "units" => format!("net_units_sold {}", sort_order),
-        _ => format!("date {}", sort_order),
+    let sort_order = match filters.sort_order.unwrap_or_default() {
+        SortOrder::Asc => "ASC",
+        SortOrder::Desc => "DESC",
+    };
+    let order_by = match filters.sort_by.unwrap_or_default() {
+        SortField::Revenue => format!("CAST(gross_sales_usd AS REAL) {}", sort_order),
+        SortField::Units => format!("net_units_sold {}", sort_order),
+        SortField::Date => format!("date {}", sort_order),
     };
 
     // Get records
@@ -434,11 +896,12 @@ pub fn get_sales(filters: QueryFilters) -> SqliteResult<SalesResponse> {
     let offset = filters.offset.unwrap_or(0) as i64;
 
     // Check if discount column exists - it may not exist in all database versions
-    let has_discount_col = conn.query_row(
-        "SELECT COUNT(*) FROM pragma_table_info('sales_data') WHERE name IN ('total_discount_percentage', 'discount_percentage')",
-        [],
-        |row| row.get::<_, i64>(0)
-    ).unwrap_or(0) > 0;
+    let has_discount_col = first_existing_column(
+        &conn,
+        "sales_data",
+        &["total_discount_percentage", "discount_percentage"],
+    )
+    .is_some();
 
     let discount_col = if has_discount_col {
         "COALESCE(total_discount_percentage, discount_percentage, NULL)"
@@ -446,97 +909,81 @@ pub fn get_sales(filters: QueryFilters) -> SqliteResult<SalesResponse> {
         "NULL"
     };
 
-    // Check if lookup tables exist
-    let lookup_apps_exists = conn
-        .query_row(
-            "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='lookup_apps'",
-            [],
-            |row| row.get::<_, i64>(0),
-        )
-        .unwrap_or(0)
-        > 0;
+    let category = line_item_category_expr(&conn);
 
-    let lookup_packages_exists = conn
-        .query_row(
-            "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='lookup_packages'",
-            [],
-            |row| row.get::<_, i64>(0),
-        )
-        .unwrap_or(0)
-        > 0;
+    // Check if lookup tables exist
+    let app_names = app_names_table(&conn);
+    let package_names = package_names_table(&conn);
 
     // Build SQL with JOINs if lookup tables exist
-    let (sql, has_app_name, has_package_name) = if lookup_apps_exists && lookup_packages_exists {
-        (
+    let (sql, has_app_name, has_package_name) = match (app_names, package_names) {
+        (Some(apps), Some(packages)) => (
             format!(
                 "SELECT 
                     s.date, s.line_item_type, s.{}, s.packageid, s.country_code, s.platform, s.currency,
                     s.gross_units_sold, s.gross_units_returned, s.net_units_sold,
                     s.gross_sales_usd, s.net_sales_usd, {} as discount_percentage,
-                    a.app_name, p.package_name
-                FROM sales_data s
-                LEFT JOIN lookup_apps a ON s.{} = a.appid
-                LEFT JOIN lookup_packages p ON s.packageid = p.packageid
+                    a.app_name, p.package_name, {} as line_item_category, s.rowid
+                FROM {sales} s
+                LEFT JOIN {apps} a ON s.{} = a.appid
+                LEFT JOIN {packages} p ON s.packageid = p.packageid
                 {}
                 ORDER BY {}
                 LIMIT ? OFFSET ?",
-                app_id_col, discount_col, app_id_col, where_clause.clause, order_by
+                app_id_col, discount_col, category, app_id_col, where_clause.clause, order_by
             ),
             true,
             true,
-        )
-    } else if lookup_apps_exists {
-        (
+        ),
+        (Some(apps), None) => (
             format!(
                 "SELECT 
                     s.date, s.line_item_type, s.{}, s.packageid, s.country_code, s.platform, s.currency,
                     s.gross_units_sold, s.gross_units_returned, s.net_units_sold,
                     s.gross_sales_usd, s.net_sales_usd, {} as discount_percentage,
-                    a.app_name, NULL as package_name
-                FROM sales_data s
-                LEFT JOIN lookup_apps a ON s.{} = a.appid
+                    a.app_name, NULL as package_name, {} as line_item_category, s.rowid
+                FROM {sales} s
+                LEFT JOIN {apps} a ON s.{} = a.appid
                 {}
                 ORDER BY {}
                 LIMIT ? OFFSET ?",
-                app_id_col, discount_col, app_id_col, where_clause.clause, order_by
+                app_id_col, discount_col, category, app_id_col, where_clause.clause, order_by
             ),
             true,
             false,
-        )
-    } else if lookup_packages_exists {
-        (
+        ),
+        (None, Some(packages)) => (
             format!(
                 "SELECT 
                     s.date, s.line_item_type, s.{}, s.packageid, s.country_code, s.platform, s.currency,
                     s.gross_units_sold, s.gross_units_returned, s.net_units_sold,
                     s.gross_sales_usd, s.net_sales_usd, {} as discount_percentage,
-                    NULL as app_name, p.package_name
-                FROM sales_data s
-                LEFT JOIN lookup_packages p ON s.packageid = p.packageid
+                    NULL as app_name, p.package_name, {} as line_item_category, s.rowid
+                FROM {sales} s
+                LEFT JOIN {packages} p ON s.packageid = p.packageid
                 {}
                 ORDER BY {}
                 LIMIT ? OFFSET ?",
-                app_id_col, discount_col, where_clause.clause, order_by
+                app_id_col, discount_col, category, where_clause.clause, order_by
             ),
             false,
             true,
-        )
-    } else {
-        (
+        ),
+        (None, None) => (
             format!(
                 "SELECT 
                     date, line_item_type, {}, packageid, country_code, platform, currency,
                     gross_units_sold, gross_units_returned, net_units_sold,
                     gross_sales_usd, net_sales_usd, {} as discount_percentage,
-                    NULL as app_name, NULL as package_name
-                FROM sales_data {}
+                    NULL as app_name, NULL as package_name, {} as line_item_category, rowid
+                FROM {sales} {}
                 ORDER BY {}
                 LIMIT ? OFFSET ?",
-                app_id_col, discount_col, where_clause.clause, order_by
+                app_id_col, discount_col, category, where_clause.clause, order_by
             ),
             false,
             false,
-        )
+        ),
     };
 
     // Combine WHERE params with LIMIT/OFFSET
@@ -565,6 +1012,8 @@ pub fn get_sales(filters: QueryFilters) -> SqliteResult<SalesResponse> {
             gross_sales_usd: parse_usd(&row.get::<_, String>(10)?),
             net_sales_usd: parse_usd(&row.get::<_, String>(11)?),
             discount_percentage: row.get::<_, Option<f64>>(12)?,
+            line_item_category: row.get::<_, String>(15)?,
+            row_id: row.get::<_, Option<i64>>(16)?,
         };
 
         // Set app_name and package_name if available
@@ -579,31 +1028,23 @@ pub fn get_sales(filters: QueryFilters) -> SqliteResult<SalesResponse> {
         Ok(record)
     })?;
 
-    let mut records = Vec::new();
     for (idx, row) in rows.enumerate() {
         let mut record = row?;
         record.id = offset as u64 + idx as u64 + 1;
-        records.push(record);
+        visit(record)?;
     }
 
-    let records_len = records.len() as i64;
-
-    Ok(SalesResponse {
-        records,
-        pagination: Pagination {
-            total: total as u64,
-            limit: filters.limit.unwrap_or(1000),
-            offset: filters.offset.unwrap_or(0),
-            has_more: (offset + records_len) < total,
-        },
-    })
+    Ok(total as u64)
 }
 
 pub fn get_daily_summaries(filters: QueryFilters) -> SqliteResult<Vec<DailySummary>> {
-    let conn = get_connection()?;
+    let filters = filters.complete_days_only();
+    let conn = connection_for(&filters)?;
     let app_id_col = get_app_id_column(&conn);
+    let sales = adjustments::sales_source(&conn, &filters);
 
-    let where_clause = build_where_clause(&filters, &app_id_col);
+    let where_clause = build_where_clause(&filters, &app_id_col, sales.source_column)
+        .sale_items_only(&conn, &filters);
     let limit = filters.limit.unwrap_or(1000) as i64;
 
     // Build params
@@ -622,19 +1063,30 @@ pub fn get_daily_summaries(filters: QueryFilters) -> SqliteResult<Vec<DailySumma
     if let Some(ref country_code) = filters.country_code {
         params.push(country_code);
     }
+    if let Some(ref source) = filters.source {
+        params.push(source);
+    }
+    if let Some(ref sale_types) = filters.sale_types {
+        for sale_type in sale_types {
+            params.push(sale_type);
+        }
+    }
     params.push(&limit);
 
+    let granularity = filters.granularity.unwrap_or_default();
     let sql = format!(
         "SELECT 
-            date,
-            SUM(CAST(gross_sales_usd AS REAL)) as total_revenue,
+            {} as period,
+            SUM({revenue}) as total_revenue,
             SUM(net_units_sold) as total_units,
             COUNT(*) as record_count
-        FROM sales_data {}
-        GROUP BY date
-        ORDER BY date
+        FROM {sales} {}
+        GROUP BY period
+        ORDER BY period
         LIMIT ?",
-        where_clause.clause
+        granularity.sql_period_start("date"),
+        where_clause.clause,
+        revenue = revenue_expr(&conn, filters.revenue_basis.unwrap_or_default())
     );
 
     let mut stmt = conn.prepare(&sql)?;
@@ -644,6 +1096,10 @@ pub fn get_daily_summaries(filters: QueryFilters) -> SqliteResult<Vec<DailySumma
             total_revenue: row.get::<_, f64>(1)?,
             total_units: row.get::<_, i64>(2)?,
             record_count: row.get::<_, i64>(3)? as u64,
+            annotations: Vec::new(),
+            partial: false,
+            metrics: MetricValues::new(),
+            metric_money_powers: MoneyPowers::new(),
         })
     })?;
 
@@ -652,14 +1108,34 @@ pub fn get_daily_summaries(filters: QueryFilters) -> SqliteResult<Vec<DailySumma
         summaries.push(row?);
     }
 
+    // Include date annotations (global ones and those for the filtered apps)
+    annotations::attach_to_daily(
+        &conn,
+        &mut summaries,
+        filters.app_ids.as_deref(),
+        granularity,
+    )?;
+    mark_partial_day(&mut summaries, granularity);
+
+    let mut metrics =
+        custom_metrics::evaluate(&conn, &filters, &granularity.sql_period_start("date"))?;
+    let money_powers = custom_metrics::money_powers(&conn, &filters)?;
+    for summary in summaries.iter_mut() {
+        summary.metrics = metrics.remove(&summary.date).unwrap_or_default();
+        summary.metric_money_powers = money_powers.clone();
+    }
+
     Ok(summaries)
 }
 
 pub fn get_app_summaries(filters: QueryFilters) -> SqliteResult<Vec<AppSummary>> {
-    let conn = get_connection()?;
+    let filters = filters.complete_days_only();
+    let conn = connection_for(&filters)?;
     let app_id_col = get_app_id_column(&conn);
+    let sales = adjustments::sales_source(&conn, &filters);
 
-    let where_clause = build_where_clause(&filters, &app_id_col);
+    let where_clause = build_where_clause(&filters, &app_id_col, sales.source_column)
+        .sale_items_only(&conn, &filters);
     let limit = filters.limit.unwrap_or(100) as i64;
 
     // Build params
@@ -678,21 +1154,32 @@ pub fn get_app_summaries(filters: QueryFilters) -> SqliteResult<Vec<AppSummary>>
     if let Some(ref country_code) = filters.country_code {
         params.push(country_code);
     }
+    if let Some(ref source) = filters.source {
+        params.push(source);
+    }
+    if let Some(ref sale_types) = filters.sale_types {
+        for sale_type in sale_types {
+            params.push(sale_type);
+        }
+    }
     params.push(&limit);
 
     let sql = format!(
         "SELECT 
             {},
-            SUM(CAST(gross_sales_usd AS REAL)) as total_revenue,
+            SUM({revenue}) as total_revenue,
             SUM(net_units_sold) as total_units,
             COUNT(*) as record_count,
             MIN(date) as first_sale,
             MAX(date) as last_sale
-        FROM sales_data {}
+        FROM {sales} {}
         GROUP BY {}
         ORDER BY total_revenue DESC
         LIMIT ?",
-        app_id_col, where_clause.clause, app_id_col
+        app_id_col,
+        where_clause.clause,
+        app_id_col,
+        revenue = revenue_expr(&conn, filters.revenue_basis.unwrap_or_default())
     );
 
     let mut stmt = conn.prepare(&sql)?;
@@ -721,19 +1208,42 @@ pub fn get_app_summaries(filters: QueryFilters) -> SqliteResult<Vec<AppSummary>>
                     record_count: record_count as u64,
                     first_sale,
                     last_sale,
+                    group_id: None,
+                    metrics: MetricValues::new(),
+                    metric_money_powers: MoneyPowers::new(),
                 });
             }
         }
     }
 
+    let group_products = filters.group_products == Some(true);
+    let (mut summaries, app_key) = if group_products {
+        (
+            product_groups::merge_app_summaries(&conn, summaries)?,
+            product_groups::app_key_expr(&conn, &app_id_col)?,
+        )
+    } else {
+        (summaries, app_id_col)
+    };
+    let mut metrics = custom_metrics::evaluate(&conn, &filters, &app_key)?;
+    let money_powers = custom_metrics::money_powers(&conn, &filters)?;
+    for summary in summaries.iter_mut() {
+        summary.metrics = metrics
+            .remove(&summary.app_id.to_string())
+            .unwrap_or_default();
+        summary.metric_money_powers = money_powers.clone();
+    }
     Ok(summaries)
 }
 
 pub fn get_country_summaries(filters: QueryFilters) -> SqliteResult<Vec<CountrySummary>> {
-    let conn = get_connection()?;
+    let filters = filters.complete_days_only();
+    let conn = connection_for(&filters)?;
     let app_id_col = get_app_id_column(&conn);
+    let sales = adjustments::sales_source(&conn, &filters);
 
-    let where_clause = build_where_clause(&filters, &app_id_col);
+    let where_clause = build_where_clause(&filters, &app_id_col, sales.source_column)
+        .sale_items_only(&conn, &filters);
     let limit = filters.limit.unwrap_or(250) as i64;
 
     // Build params
@@ -752,32 +1262,39 @@ pub fn get_country_summaries(filters: QueryFilters) -> SqliteResult<Vec<CountryS
     if let Some(ref country_code) = filters.country_code {
         params.push(country_code);
     }
+    if let Some(ref source) = filters.source {
+        params.push(source);
+    }
+    if let Some(ref sale_types) = filters.sale_types {
+        for sale_type in sale_types {
+            params.push(sale_type);
+        }
+    }
     params.push(&limit);
 
+    countries::ensure_lookup_table(&conn)?;
     let sql = format!(
-        "SELECT 
-            country_code,
-            SUM(CAST(gross_sales_usd AS REAL)) as total_revenue,
-            SUM(net_units_sold) as total_units,
-            COUNT(*) as record_count
-        FROM sales_data {}
-        GROUP BY country_code
-        ORDER BY total_revenue DESC
-        LIMIT ?",
-        where_clause.clause
+        "SELECT t.country_code, c.country_name, c.region, c.subregion, COALESCE(c.eu_member, 0),
+            t.total_revenue, t.total_units, t.record_count
+        FROM (
+            SELECT 
+                country_code,
+                SUM({revenue}) as total_revenue,
+                SUM(net_units_sold) as total_units,
+                COUNT(*) as record_count
+            FROM {sales} {}
+            GROUP BY country_code
+            ORDER BY total_revenue DESC
+            LIMIT ?
+        ) t
+        LEFT JOIN lookup_countries c ON c.country_code = t.country_code
+        ORDER BY t.total_revenue DESC",
+        where_clause.clause,
+        revenue = revenue_expr(&conn, filters.revenue_basis.unwrap_or_default())
     );
 
     let mut stmt = conn.prepare(&sql)?;
-    let rows = stmt.query_map(params.as_slice(), |row| {
-        Ok(CountrySummary {
-            country_code: row.get::<_, String>(0)?,
-            country_name: None,
-            region: None,
-            total_revenue: row.get::<_, f64>(1)?,
-            total_units: row.get::<_, i64>(2)?,
-            record_count: row.get::<_, i64>(3)? as u64,
-        })
-    })?;
+    let rows = stmt.query_map(params.as_slice(), row_to_country_summary)?;
 
     let mut summaries = Vec::new();
     for row in rows {
@@ -787,31 +1304,105 @@ pub fn get_country_summaries(filters: QueryFilters) -> SqliteResult<Vec<CountryS
         }
     }
 
+    let mut metrics = custom_metrics::evaluate(&conn, &filters, "country_code")?;
+    let money_powers = custom_metrics::money_powers(&conn, &filters)?;
+    for summary in summaries.iter_mut() {
+        summary.metrics = metrics
+            .remove(&summary.country_code)
+            .unwrap_or_default();
+        summary.metric_money_powers = money_powers.clone();
+    }
+
+    Ok(summaries)
+}
+
+/// Totals per platform, largest revenue first. Rows without a platform are totalled under None.
+pub fn get_platform_summaries(filters: QueryFilters) -> SqliteResult<Vec<PlatformSummary>> {
+    let filters = filters.complete_days_only();
+    let conn = connection_for(&filters)?;
+    let app_id_col = get_app_id_column(&conn);
+    let sales = adjustments::sales_source(&conn, &filters);
+
+    let where_clause = build_where_clause(&filters, &app_id_col, sales.source_column)
+        .sale_items_only(&conn, &filters);
+
+    let mut params: Vec<&dyn rusqlite::ToSql> = Vec::new();
+    if let Some(ref start_date) = filters.start_date {
+        params.push(start_date);
+    }
+    if let Some(ref end_date) = filters.end_date {
+        params.push(end_date);
+    }
+    if let Some(ref app_ids) = filters.app_ids {
+        for app_id in app_ids {
+            params.push(app_id);
+        }
+    }
+    if let Some(ref country_code) = filters.country_code {
+        params.push(country_code);
+    }
+    if let Some(ref source) = filters.source {
+        params.push(source);
+    }
+    if let Some(ref sale_types) = filters.sale_types {
+        for sale_type in sale_types {
+            params.push(sale_type);
+        }
+    }
+
+    let sql = format!(
+        "SELECT NULLIF(platform, ''), SUM({revenue}) as total_revenue,
+            SUM(net_units_sold) as total_units, COUNT(*) as record_count
+        FROM {sales} {}
+        GROUP BY 1
+        ORDER BY total_revenue DESC",
+        where_clause.clause,
+        revenue = revenue_expr(&conn, filters.revenue_basis.unwrap_or_default())
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let summaries = stmt
+        .query_map(params.as_slice(), |r| {
+            Ok(PlatformSummary {
+                platform: r.get(0)?,
+                total_revenue: r.get(1)?,
+                total_units: r.get(2)?,
+                record_count: r.get::<_, i64>(3)? as u64,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
     Ok(summaries)
 }
 
 pub fn get_apps_lookup() -> SqliteResult<Vec<AppLookup>> {
+    let mut apps = read_apps_lookup()?;
+    // Apps without a name in the database get their store name, if it has been looked up
+    if apps.iter().any(|app| app.app_name == format!("App {}", app.app_id)) {
+        let names = app_metadata::cached_names();
+        for app in &mut apps {
+            if app.app_name == format!("App {}", app.app_id) {
+                if let Some(name) = names.get(&app.app_id) {
+                    app.app_name = name.clone();
+                }
+            }
+        }
+    }
+    Ok(apps)
+}
+
+fn read_apps_lookup() -> SqliteResult<Vec<AppLookup>> {
     let conn = get_connection()?;
     let app_id_col = get_app_id_column(&conn);
 
-    // Check if lookup_apps table exists
-    let lookup_apps_exists: i64 = conn
-        .query_row(
-            "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='lookup_apps'",
-            [],
-            |row| row.get(0),
-        )
-        .unwrap_or(0);
-
-    if lookup_apps_exists > 0 {
-        // Use lookup_apps table
+    // Check if there is a lookup table of app names
+    if let Some(apps) = app_names_table(&conn) {
+        // Use the lookup table
         let sql = format!(
             "SELECT DISTINCT s.{}, a.app_name 
              FROM sales_data s 
-             JOIN lookup_apps a ON s.{} = a.appid 
+             LEFT JOIN {} a ON s.{} = a.appid 
              WHERE s.{} IS NOT NULL AND s.{} != 0 
              ORDER BY s.{}",
-            app_id_col, app_id_col, app_id_col, app_id_col, app_id_col
+            app_id_col, apps, app_id_col, app_id_col, app_id_col, app_id_col
         );
 
         let mut stmt = conn.prepare(&sql)?;
@@ -889,15 +1480,22 @@ pub fn get_apps_lookup() -> SqliteResult<Vec<AppLookup>> {
 
 pub fn get_countries_lookup() -> SqliteResult<Vec<CountryLookup>> {
     let conn = get_connection()?;
+    countries::ensure_lookup_table(&conn)?;
 
-    let sql = "SELECT DISTINCT country_code FROM sales_data WHERE country_code IS NOT NULL ORDER BY country_code";
+    let sql = "SELECT t.country_code, c.country_name, c.region, c.subregion, COALESCE(c.eu_member, 0)
+               FROM (SELECT DISTINCT country_code FROM sales_data WHERE country_code IS NOT NULL) t
+               LEFT JOIN lookup_countries c ON c.country_code = t.country_code
+               ORDER BY t.country_code";
     let mut stmt = conn.prepare(sql)?;
     let rows = stmt.query_map([], |row| {
         let country_code: String = row.get(0)?;
         Ok(CountryLookup {
-            country_code: country_code.clone(),
-            country_name: country_code,
-            region: None,
+            // Codes missing from the bundled list keep the code as their name
+            country_name: row.get::<_, Option<String>>(1)?.unwrap_or_else(|| country_code.clone()),
+            country_code,
+            region: row.get(2)?,
+            subregion: row.get(3)?,
+            eu_member: row.get(4)?,
         })
     })?;
 
@@ -921,37 +1519,118 @@ pub fn get_dates_list() -> SqliteResult<Vec<String>> {
     Ok(dates)
 }
 
-pub fn get_raw_data_by_date(date: &str) -> SqliteResult<Vec<SalesRecord>> {
-    let mut filters = QueryFilters::default();
-    filters.start_date = Some(date.to_string());
-    filters.end_date = Some(date.to_string());
-    filters.limit = Some(100_000);
-    filters.offset = Some(0);
+pub fn get_raw_data_by_date(date: Date) -> SqliteResult<Vec<SalesRecord>> {
+    let filters = QueryFilters {
+        start_date: Some(date),
+        end_date: Some(date),
+        limit: Some(100_000),
+        offset: Some(0),
+        ..QueryFilters::default()
+    };
     let response = get_sales(filters)?;
     Ok(response.records)
 }
 
-pub fn get_packages_lookup() -> SqliteResult<Vec<PackageLookup>> {
-    let conn = get_connection()?;
+/// Reads all records of `date` and passes them to `send_chunk` in chunks of `chunk_size`, so
+/// at most one chunk is held in memory. The last call has `done` set, and may be empty.
+pub fn stream_raw_data(
+    date: Date,
+    chunk_size: usize,
+    mut send_chunk: impl FnMut(Vec<SalesRecord>, bool),
+) -> SqliteResult<RawDataStream> {
+    let filters = QueryFilters {
+        start_date: Some(date),
+        end_date: Some(date),
+        limit: Some(u32::MAX),
+        offset: Some(0),
+        ..QueryFilters::default()
+    };
+    let mut chunk = Vec::with_capacity(chunk_size);
+    let mut chunks = 0;
+    let total_records = for_each_sales_record(&filters, |record| {
+        chunk.push(record);
+        if chunk.len() == chunk_size {
+            send_chunk(
+                std::mem::replace(&mut chunk, Vec::with_capacity(chunk_size)),
+                false,
+            );
+            chunks += 1;
+        }
+        Ok(())
+    })?;
+    send_chunk(chunk, true);
 
-    // Check if lookup_packages table exists
-    let lookup_packages_exists: i64 = conn
-        .query_row(
-            "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='lookup_packages'",
-            [],
-            |row| row.get(0),
-        )
-        .unwrap_or(0);
+    Ok(RawDataStream {
+        total_records,
+        chunks: chunks + 1,
+    })
+}
 
-    if lookup_packages_exists > 0 {
-        // Use lookup_packages table
-        let sql = "SELECT DISTINCT s.packageid, p.package_name 
-                   FROM sales_data s 
-                   JOIN lookup_packages p ON s.packageid = p.packageid 
-                   WHERE s.packageid IS NOT NULL AND s.packageid != 0 
-                   ORDER BY s.packageid";
+// First and last date and row count of the database's sales
+fn source_extent(db_path: &str) -> SqliteResult<(Option<DateRange>, u64)> {
+    let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    if !table_exists(&conn, "sales_data") {
+        return Ok((None, 0));
+    }
+    conn.query_row(
+        "SELECT MIN(date), MAX(date), COUNT(*) FROM sales_data",
+        [],
+        |row| {
+            let range = match (row.get(0)?, row.get(1)?) {
+                (Some(min), Some(max)) => Some(DateRange { min, max }),
+                _ => None,
+            };
+            Ok((range, row.get::<_, i64>(2)? as u64))
+        },
+    )
+}
 
-        let mut stmt = conn.prepare(sql)?;
+/// Every data source, with the API key it's synced with and the span of its data.
+pub fn get_query_sources() -> AppResult<Vec<QuerySource>> {
+    let keys = keys::list_keys()?;
+    sources::list_sources()?
+        .into_iter()
+        .map(|source| {
+            let (date_range, record_count) = if source.database_exists {
+                source_extent(&source.db_path)?
+            } else {
+                (None, 0)
+            };
+            let key_hint = source.key_id.as_deref().and_then(|id| {
+                keys.iter()
+                    .find(|key| key.id == id)
+                    .map(|key| key.key_hint.clone())
+            });
+            Ok(QuerySource {
+                id: source.id,
+                name: source.name,
+                api_key_id: source.key_id,
+                key_hint,
+                active: source.active,
+                read_only: source.read_only,
+                date_range,
+                record_count,
+            })
+        })
+        .collect()
+}
+
+pub fn get_packages_lookup() -> SqliteResult<Vec<PackageLookup>> {
+    let conn = get_connection()?;
+
+    // Check if there is a lookup table of package names
+    if let Some(packages) = package_names_table(&conn) {
+        // Use the lookup table
+        let sql = format!(
+            "SELECT DISTINCT s.packageid, p.package_name 
+             FROM sales_data s 
+             JOIN {} p ON s.packageid = p.packageid 
+             WHERE s.packageid IS NOT NULL AND s.packageid != 0 
+             ORDER BY s.packageid",
+            packages
+        );
+
+        let mut stmt = conn.prepare(&sql)?;
         let rows = stmt.query_map([], |row| {
             let package_id: Option<i64> = row.get(0)?;
             let package_name: Option<String> = row.get(1)?;
@@ -974,11 +1653,7 @@ pub fn get_packages_lookup() -> SqliteResult<Vec<PackageLookup>> {
     }
 
     // Fallback: check for package name column in sales_data
-    let package_name_col = conn.query_row(
-        "SELECT name FROM pragma_table_info('sales_data') WHERE name IN ('primary_package_name', 'primary_packagename', 'package_name', 'packagename', 'packageName') LIMIT 1",
-        [],
-        |row| row.get::<_, String>(0)
-    ).ok();
+    let package_name_col = get_package_name_column(&conn);
 
     if let Some(ref name_col) = package_name_col {
         // Include package name in query
@@ -1027,32 +1702,41 @@ pub fn get_packages_lookup() -> SqliteResult<Vec<PackageLookup>> {
     }
 }
 
-pub fn get_product_stats(product_type: &str, product_id: i64) -> SqliteResult<ProductStats> {
+/// `product_type` is "app", "package" or "group" (a product group, with its id as
+/// `product_id`).
+pub fn get_product_stats(
+    product_type: &str,
+    product_id: i64,
+    revenue_basis: RevenueBasis,
+) -> SqliteResult<ProductStats> {
     let conn = get_connection()?;
+    let revenue = revenue_expr(&conn, revenue_basis);
     let app_id_col = get_app_id_column(&conn);
-    let (col, param): (String, &dyn rusqlite::ToSql) = match product_type {
-        "app" => (app_id_col.clone(), &product_id as &dyn rusqlite::ToSql),
-        "package" => ("packageid".to_string(), &product_id as &dyn rusqlite::ToSql),
+    let (condition, params): (String, Vec<rusqlite::types::Value>) = match product_type {
+        "app" => (format!("{} = ?", app_id_col), vec![product_id.into()]),
+        "package" => ("packageid = ?".to_string(), vec![product_id.into()]),
+        "group" => product_groups::group_condition(&conn, product_id, &app_id_col)?,
         _ => {
             return Err(rusqlite::Error::InvalidParameterName(
-                "product_type must be 'app' or 'package'".to_string(),
+                "product_type must be 'app', 'package' or 'group'".to_string(),
             ))
         }
     };
-    let filter = format!("WHERE {} = ?", col);
+    let filter = format!("WHERE {}", condition);
 
     // Totals + date range
     let sql = format!(
         "SELECT
-            COALESCE(SUM(CAST(gross_sales_usd AS REAL)), 0) as total_revenue,
+            COALESCE(SUM({revenue}), 0) as total_revenue,
             COALESCE(SUM(net_units_sold), 0) as total_units,
             COUNT(*) as record_count,
             MIN(date) as min_date,
             MAX(date) as max_date
         FROM sales_data {}",
-        filter
+        filter,
+        revenue = revenue
     );
-    let row = conn.query_row(&sql, [param], |r| {
+    let row = conn.query_row(&sql, params_from_iter(&params), |r| {
         Ok((
             r.get::<_, f64>(0)?,
             r.get::<_, i64>(1)?,
@@ -1069,65 +1753,72 @@ pub fn get_product_stats(product_type: &str, product_id: i64) -> SqliteResult<Pr
 
     // Daily breakdown
     let sql_daily = format!(
-        "SELECT date, SUM(CAST(gross_sales_usd AS REAL)) as tr, SUM(net_units_sold) as tu, COUNT(*) as rc
+        "SELECT date, SUM({revenue}) as tr, SUM(net_units_sold) as tu, COUNT(*) as rc
          FROM sales_data {} GROUP BY date ORDER BY date",
-        filter
+        filter,
+        revenue = revenue
     );
     let mut stmt = conn.prepare(&sql_daily)?;
     let daily: Vec<DailySummary> = stmt
-        .query_map([param], |r| {
+        .query_map(params_from_iter(&params), |r| {
             Ok(DailySummary {
                 date: r.get(0)?,
                 total_revenue: r.get(1)?,
                 total_units: r.get(2)?,
                 record_count: r.get::<_, i64>(3)? as u64,
+                annotations: Vec::new(),
+                partial: false,
+                metrics: MetricValues::new(),
+                metric_money_powers: MoneyPowers::new(),
             })
         })?
         .collect::<Result<Vec<_>, _>>()?;
 
-    // By country
-    let country_sql = if product_type == "app" {
-        format!("SELECT country_code, SUM(CAST(gross_sales_usd AS REAL)) as tr, SUM(net_units_sold) as tu, COUNT(*) as rc
-         FROM sales_data WHERE {} = ? AND country_code IS NOT NULL AND country_code != ''
-         GROUP BY country_code ORDER BY tr DESC LIMIT 500", app_id_col)
-    } else {
-        "SELECT country_code, SUM(CAST(gross_sales_usd AS REAL)) as tr, SUM(net_units_sold) as tu, COUNT(*) as rc
-         FROM sales_data WHERE packageid = ? AND country_code IS NOT NULL AND country_code != ''
-         GROUP BY country_code ORDER BY tr DESC LIMIT 500".to_string()
+    let mut daily = daily;
+    let annotation_apps = match product_type {
+        "app" => Some(vec![product_id]),
+        "group" => Some(
+            product_groups::list_groups(&conn)?
+                .into_iter()
+                .find(|g| g.id == product_id)
+                .map(|g| g.app_ids)
+                .unwrap_or_default(),
+        ),
+        _ => None,
     };
+    let annotation_filter = annotation_apps.as_deref();
+    annotations::attach_to_daily(&conn, &mut daily, annotation_filter, Granularity::Day)?;
+    mark_partial_day(&mut daily, Granularity::Day);
+
+    // By country
+    countries::ensure_lookup_table(&conn)?;
+    let country_sql = format!(
+        "SELECT t.country_code, c.country_name, c.region, c.subregion, COALESCE(c.eu_member, 0), t.tr, t.tu, t.rc
+         FROM (
+             SELECT country_code, SUM({revenue}) as tr, SUM(net_units_sold) as tu, COUNT(*) as rc
+             FROM sales_data {} AND country_code IS NOT NULL AND country_code != ''
+             GROUP BY country_code ORDER BY tr DESC LIMIT 500
+         ) t
+         LEFT JOIN lookup_countries c ON c.country_code = t.country_code
+         ORDER BY t.tr DESC",
+        filter,
+        revenue = revenue
+    );
     let mut stmt_country = conn.prepare(&country_sql)?;
     let by_country: Vec<CountrySummary> = stmt_country
-        .query_map([product_id], |r| {
-            Ok(CountrySummary {
-                country_code: r.get(0)?,
-                country_name: None,
-                region: None,
-                total_revenue: r.get(1)?,
-                total_units: r.get(2)?,
-                record_count: r.get::<_, i64>(3)? as u64,
-            })
-        })?
+        .query_map(params_from_iter(&params), row_to_country_summary)?
         .collect::<Result<Vec<_>, _>>()?;
 
     // By platform
-    let (platform_sql, platform_params): (String, Vec<&dyn rusqlite::ToSql>) = if product_type
-        == "app"
-    {
-        (
-            format!("SELECT platform, SUM(CAST(gross_sales_usd AS REAL)) as tr, SUM(net_units_sold) as tu, COUNT(*) as rc
-             FROM sales_data WHERE {} = ? GROUP BY platform ORDER BY tr DESC LIMIT 100", app_id_col),
-            vec![&product_id],
-        )
-    } else {
-        (
-            "SELECT platform, SUM(CAST(gross_sales_usd AS REAL)) as tr, SUM(net_units_sold) as tu, COUNT(*) as rc
-             FROM sales_data WHERE packageid = ? GROUP BY platform ORDER BY tr DESC LIMIT 100".to_string(),
-            vec![&product_id],
-        )
-    };
+    let platform_sql = format!(
+        "SELECT platform, SUM({revenue}) as tr, SUM(net_units_sold) as tu, COUNT(*) as rc
+         FROM sales_data {} GROUP BY platform ORDER BY tr DESC LIMIT 100",
+        filter,
+        revenue = revenue
+    );
     let mut stmt = conn.prepare(&platform_sql)?;
     let by_platform: Vec<PlatformSummary> = stmt
-        .query_map(platform_params.as_slice(), |r| {
+        .query_map(params_from_iter(&params), |r| {
             Ok(PlatformSummary {
                 platform: r.get(0)?,
                 total_revenue: r.get(1)?,
@@ -1148,442 +1839,447 @@ pub fn get_product_stats(product_type: &str, product_id: i64) -> SqliteResult<Pr
     })
 }
 
-// Helper function to calculate days for a single product efficiently
-fn calculate_product_days(
-    conn: &Connection,
-    product_id: i64,
-    launch_date: &str,
+/// `exclude_partial_day` overrides the setting of the same name. With `group_products`, the
+/// members of each product group are compared as one product. Launch dates are the first day
+/// with gross revenue on any `revenue_basis`. `product_ids` limits the comparison to those apps
+/// or packages; with `group_products`, a group is made of its members among them.
+pub fn get_launch_comparison(
     max_days: u32,
-    id_column: &str,
-    latest_date: &str,
-) -> SqliteResult<Vec<LaunchDay>> {
-    // Calculate actual max days: from launch_date to latest_date in database, capped at max_days
-    // This prevents calculating beyond the data we have
-    let actual_max_days: i64 = conn
-        .query_row(
-            "SELECT MIN(CAST(julianday(?) - julianday(?) AS INTEGER), ?)",
-            rusqlite::params![latest_date, launch_date, max_days as i64],
-            |r| r.get(0),
-        )
-        .unwrap_or(max_days as i64);
-
-    let actual_max = actual_max_days.max(0).min(max_days as i64) as u32;
-
-    // Batch query all days at once instead of one query per day
-    // Only query dates from launch_date to min(launch_date + max_days, latest_date)
-    let batch_sql = format!(
-        "SELECT 
-            CAST(julianday(date) - julianday(?1) AS INTEGER) as day,
-            COALESCE(SUM(CAST(gross_sales_usd AS REAL)), 0) as revenue,
-            COALESCE(SUM(net_units_sold), 0) as units
-         FROM sales_data
-         WHERE {} = ?2
-           AND date >= ?1
-           AND date <= MIN(date(?1, '+' || ?3 || ' days'), ?4)
-           AND CAST(julianday(date) - julianday(?1) AS INTEGER) >= 0
-           AND CAST(julianday(date) - julianday(?1) AS INTEGER) <= ?3
-         GROUP BY day
-         ORDER BY day",
-        id_column
-    );
-
-    let mut day_map: HashMap<u32, (f64, i64)> = HashMap::new();
-    let mut stmt = conn.prepare(&batch_sql)?;
-    let batch_rows = stmt.query_map(
-        rusqlite::params![launch_date, product_id, actual_max, latest_date],
-        |r| {
-            let day: i64 = r.get(0)?;
-            let revenue: f64 = r.get(1)?;
-            let units: i64 = r.get(2)?;
-            Ok((day as u32, (revenue, units)))
-        },
+    product_type: &str,
+    exclude_partial_day: Option<bool>,
+    group_products: bool,
+    revenue_basis: RevenueBasis,
+    product_ids: Option<&[i64]>,
+) -> SqliteResult<Vec<LaunchComparisonApp>> {
+    let conn = get_connection()?;
+    let series = launch_series(
+        &conn,
+        max_days,
+        product_type,
+        exclude_partial_day,
+        revenue_basis,
+        product_ids,
     )?;
-
-    for row in batch_rows {
-        if let Ok((day, data)) = row {
-            day_map.insert(day, data);
-        }
-    }
-
-    // Build days array, filling in missing days with zeros
-    let mut days = Vec::new();
-    for day in 0..=max_days {
-        if day <= actual_max {
-            let (revenue, units) = day_map.get(&day).copied().unwrap_or((0.0, 0));
-            days.push(LaunchDay {
-                day,
-                revenue,
-                units,
-            });
-        } else {
-            // Future days or beyond actual_max - set to zero
-            days.push(LaunchDay {
-                day,
-                revenue: 0.0,
-                units: 0,
-            });
-        }
+    if group_products {
+        return product_groups::merge_launch_series(&conn, series, product_type == "package");
     }
-
-    Ok(days)
+    Ok(series)
 }
 
-pub fn get_launch_comparison(
+// Every product's launch date and its revenue and units per day since launch, in one query:
+// the launch dates are grouped first, then the sales of each product's first `max_days` days
+// are summed per day on top of them. Days without sales are filled in with zeros here.
+fn launch_series(
+    conn: &Connection,
     max_days: u32,
     product_type: &str,
+    exclude_partial_day: Option<bool>,
+    revenue_basis: RevenueBasis,
+    product_ids: Option<&[i64]>,
 ) -> SqliteResult<Vec<LaunchComparisonApp>> {
-    let conn = get_connection()?;
     let is_package = product_type == "package";
+    let revenue = revenue_expr(conn, revenue_basis);
 
     // Get the latest date in the database (most recent date we have data for)
-    let latest_date: String = conn
+    let latest_date: Date = conn
         .query_row("SELECT MAX(date) FROM sales_data", [], |r| r.get(0))
         .unwrap_or_else(|_| {
             // Fallback: if no data exists, use a far future date to avoid issues
             // This should rarely happen as we check for data before calling this
-            "2099-12-31".to_string()
+            "2099-12-31".parse().expect("valid date")
         });
+    // The day Steam is still reporting would show as a drop on the newest launch
+    let latest_date = match last_complete_day(exclude_partial_day) {
+        Some(last_complete) => latest_date.min(last_complete),
+        None => latest_date,
+    };
 
-    if is_package {
-        // Package-based launch comparison
-        // Check if lookup_packages table exists
-        let lookup_packages_exists: i64 = conn
-            .query_row(
-                "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='lookup_packages'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0);
-
-        let mut result = Vec::new();
-
-        if lookup_packages_exists > 0 {
-            // Find launch date as first date with revenue > 0 for each package
-            // Use a subquery to find the minimum date where revenue > 0
-            let sql = "
-                SELECT 
-                    s.packageid,
-                    p.package_name,
-                    MIN(s.date) as launch_date
-                FROM sales_data s
-                LEFT JOIN lookup_packages p ON s.packageid = p.packageid
-                WHERE s.packageid IS NOT NULL 
-                  AND s.packageid != 0
-                  AND CAST(s.gross_sales_usd AS REAL) > 0
-                GROUP BY s.packageid
-                ORDER BY s.packageid
-            ";
-
-            let mut stmt = conn.prepare(sql)?;
-            let package_rows: Vec<(Option<i64>, Option<String>, String)> = stmt
-                .query_map([], |r| {
-                    let package_id: Option<i64> = r.get(0)?;
-                    let package_name: Option<String> = r.get(1)?;
-                    let launch_date: String = r.get(2)?;
-                    Ok((package_id, package_name, launch_date))
-                })?
-                .filter_map(|row| row.ok())
-                .collect();
-
-            for (package_id_opt, package_name_opt, launch_date) in package_rows {
-                if let Some(package_id) = package_id_opt {
-                    let days = calculate_product_days(
-                        &conn,
-                        package_id,
-                        &launch_date,
-                        max_days,
-                        "packageid",
-                        &latest_date,
-                    )?;
-                    result.push(LaunchComparisonApp {
-                        app_id: None,
-                        package_id: Some(package_id),
-                        app_name: None,
-                        package_name: package_name_opt
-                            .filter(|s| !s.is_empty())
-                            .or_else(|| Some(format!("Package {}", package_id))),
-                        launch_date,
-                        days,
-                    });
-                }
-            }
-        } else {
-            // No lookup table - find launch date as first date with revenue > 0
-            let sql = "
-                SELECT 
-                    packageid,
-                    MIN(date) as launch_date
-                FROM sales_data
-                WHERE packageid IS NOT NULL 
-                  AND packageid != 0
-                  AND CAST(gross_sales_usd AS REAL) > 0
-                GROUP BY packageid
-                ORDER BY packageid
-            ";
-
-            let mut stmt = conn.prepare(sql)?;
-            let package_rows: Vec<(i64, String)> = stmt
-                .query_map([], |r| {
-                    let package_id: Option<i64> = r.get(0)?;
-                    let launch_date: String = r.get(1)?;
-                    Ok((package_id, launch_date))
-                })?
-                .filter_map(|row| {
-                    row.ok().and_then(|(package_id_opt, launch_date)| {
-                        package_id_opt.map(|package_id| (package_id, launch_date))
-                    })
-                })
-                .collect();
-
-            for (package_id, launch_date) in package_rows {
-                let days = calculate_product_days(
-                    &conn,
-                    package_id,
-                    &launch_date,
-                    max_days,
-                    "packageid",
-                    &latest_date,
-                )?;
-                result.push(LaunchComparisonApp {
-                    app_id: None,
-                    package_id: Some(package_id),
-                    app_name: None,
-                    package_name: Some(format!("Package {}", package_id)),
-                    launch_date,
-                    days,
-                });
+    // Product names come from the lookup table, else (apps only) from a name column of
+    // sales_data, else they are made up from the id below
+    let id_col = if is_package {
+        "packageid".to_string()
+    } else {
+        get_app_id_column(conn)
+    };
+    let mut sales_name = "NULL".to_string();
+    let names_table = if is_package {
+        package_names_table(conn)
+    } else {
+        app_names_table(conn)
+    };
+    let (name_join, name) = match names_table {
+        Some(packages) if is_package => (
+            format!("LEFT JOIN {} n ON n.packageid = l.product_id", packages),
+            "n.package_name",
+        ),
+        Some(apps) => (
+            format!("LEFT JOIN {} n ON n.appid = l.product_id", apps),
+            "n.app_name",
+        ),
+        None => {
+            if let Some(name_col) = get_app_name_column(conn).filter(|_| !is_package) {
+                sales_name = format!("MAX(NULLIF({}, ''))", name_col);
             }
+            (String::new(), "l.sales_name")
         }
+    };
 
-        return Ok(result);
+    let mut params: Vec<rusqlite::types::Value> =
+        vec![(max_days as i64).into(), latest_date.to_string().into()];
+    let mut product_filter = String::new();
+    if let Some(ids) = product_ids.filter(|ids| !ids.is_empty()) {
+        // Numbered after ?1 and ?2, which the query uses further down
+        let placeholders: Vec<String> = (0..ids.len()).map(|i| format!("?{}", i + 3)).collect();
+        product_filter = format!("AND {} IN ({})", id_col, placeholders.join(", "));
+        params.extend(ids.iter().map(|id| rusqlite::types::Value::from(*id)));
     }
 
-    // App-based launch comparison
-    let app_id_col = get_app_id_column(&conn);
-
-    // Check if lookup_apps table exists
-    let lookup_apps_exists: i64 = conn
-        .query_row(
-            "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='lookup_apps'",
-            [],
-            |row| row.get(0),
-        )
-        .unwrap_or(0);
+    // One row per product and day with sales, or a single row with a NULL day for a product
+    // without any in its window. Rows with malformed dates have no day and are skipped.
+    let sql = format!(
+        "WITH launches AS (
+             SELECT {id} AS product_id, MIN(date) AS launch_date, {sales_name} AS sales_name
+             FROM sales_data
+             WHERE {id} IS NOT NULL AND {id} != 0
+               AND CAST(gross_sales_usd AS REAL) > 0
+               {product_filter}
+             GROUP BY {id}
+         ),
+         days AS (
+             SELECT l.product_id,
+                    CAST(julianday(s.date) - julianday(l.launch_date) AS INTEGER) AS day,
+                    COALESCE(SUM({revenue}), 0) AS revenue,
+                    COALESCE(SUM(s.net_units_sold), 0) AS units
+             FROM launches l
+             JOIN sales_data s ON s.{id} = l.product_id
+             WHERE s.date >= l.launch_date
+               AND s.date <= MIN(date(l.launch_date, '+' || ?1 || ' days'), ?2)
+             GROUP BY l.product_id, s.date
+         )
+         SELECT l.product_id, {name}, l.launch_date, d.day, d.revenue, d.units
+         FROM launches l
+         {name_join}
+         LEFT JOIN days d ON d.product_id = l.product_id AND d.day IS NOT NULL
+         ORDER BY l.product_id, d.day",
+        id = id_col,
+        sales_name = sales_name,
+        product_filter = product_filter,
+        revenue = revenue,
+        name = name,
+        name_join = name_join,
+    );
 
-    let mut result = Vec::new();
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params_from_iter(&params), |r| {
+        Ok((
+            r.get::<_, i64>(0)?,
+            r.get::<_, Option<String>>(1)?,
+            r.get::<_, Date>(2)?,
+            r.get::<_, Option<i64>>(3)?,
+            r.get::<_, Option<f64>>(4)?,
+            r.get::<_, Option<i64>>(5)?,
+        ))
+    })?;
 
-    if lookup_apps_exists > 0 {
-        // Find launch date as first date with revenue > 0 for each app
-        let sql_with_join = format!(
-            "SELECT s.{}, a.app_name, MIN(s.date) as launch_date 
-             FROM sales_data s 
-             LEFT JOIN lookup_apps a ON s.{} = a.appid 
-             WHERE s.{} IS NOT NULL AND s.{} != 0 
-               AND CAST(s.gross_sales_usd AS REAL) > 0
-             GROUP BY s.{} 
-             ORDER BY s.{}",
-            app_id_col, app_id_col, app_id_col, app_id_col, app_id_col, app_id_col
-        );
-        let mut stmt = conn.prepare(&sql_with_join)?;
-        let app_rows: Vec<(Option<i64>, Option<String>, String)> = stmt
-            .query_map([], |r| {
-                let app_id: Option<i64> = r.get(0)?;
-                let app_name: Option<String> = r.get(1)?;
-                let launch_date: String = r.get(2)?;
-                Ok((app_id, app_name, launch_date))
-            })?
-            .filter_map(|row| row.ok())
-            .collect();
-
-        for (app_id_opt, app_name_opt, launch_date) in app_rows {
-            if let Some(app_id) = app_id_opt {
-                let days = calculate_product_days(
-                    &conn,
-                    app_id,
-                    &launch_date,
-                    max_days,
-                    &app_id_col,
-                    &latest_date,
-                )?;
-                result.push(LaunchComparisonApp {
-                    app_id: Some(app_id),
-                    package_id: None,
-                    app_name: app_name_opt
-                        .filter(|s| !s.is_empty())
-                        .or_else(|| Some(format!("App {}", app_id))),
-                    package_name: None,
+    let mut result: Vec<LaunchComparisonApp> = Vec::new();
+    // Launches with a malformed date are skipped rather than counted from the wrong day
+    for (product_id, name, launch_date, day, revenue, units) in rows.flatten() {
+        if result.last().map(|s| s.app_id.or(s.package_id)) != Some(Some(product_id)) {
+            let name = name.filter(|n| !n.is_empty());
+            let days = (0..=max_days)
+                .map(|day| LaunchDay {
+                    day,
+                    revenue: 0.0,
+                    units: 0,
+                })
+                .collect();
+            result.push(if is_package {
+                LaunchComparisonApp {
+                    app_id: None,
+                    package_id: Some(product_id),
+                    app_name: None,
+                    package_name: name.or_else(|| Some(format!("Package {}", product_id))),
+                    group_id: None,
+                    benchmark_id: None,
+                    benchmark_name: None,
                     launch_date,
                     days,
-                });
-            }
-        }
-        return Ok(result);
-    }
-
-    // Fallback: check for app name column in sales_data
-    let app_name_col = get_app_name_column(&conn);
-
-    if app_name_col.is_some() {
-        let name_col = app_name_col.as_ref().unwrap();
-        // Find launch date as first date with revenue > 0
-        let sql_with_name = format!(
-            "SELECT {}, MAX(NULLIF({}, '')) as app_name, MIN(date) as launch_date 
-             FROM sales_data 
-             WHERE {} IS NOT NULL AND {} != 0 
-               AND CAST(gross_sales_usd AS REAL) > 0
-             GROUP BY {} 
-             ORDER BY {}",
-            app_id_col, name_col, app_id_col, app_id_col, app_id_col, app_id_col
-        );
-        let mut stmt = conn.prepare(&sql_with_name)?;
-        let app_rows: Vec<(Option<i64>, Option<String>, String)> = stmt
-            .query_map([], |r| {
-                let app_id: Option<i64> = r.get(0)?;
-                let app_name: Option<String> = r.get(1)?;
-                let launch_date: String = r.get(2)?;
-                Ok((app_id, app_name, launch_date))
-            })?
-            .filter_map(|row| row.ok())
-            .collect();
-
-        for (app_id_opt, app_name_opt, launch_date) in app_rows {
-            if let Some(app_id) = app_id_opt {
-                let days = calculate_product_days(
-                    &conn,
-                    app_id,
-                    &launch_date,
-                    max_days,
-                    &app_id_col,
-                    &latest_date,
-                )?;
-                result.push(LaunchComparisonApp {
-                    app_id: Some(app_id),
+                }
+            } else {
+                LaunchComparisonApp {
+                    app_id: Some(product_id),
                     package_id: None,
-                    app_name: app_name_opt
-                        .filter(|s| !s.is_empty())
-                        .or_else(|| Some(format!("App {}", app_id))),
+                    app_name: name.or_else(|| Some(format!("App {}", product_id))),
                     package_name: None,
+                    group_id: None,
+                    benchmark_id: None,
+                    benchmark_name: None,
                     launch_date,
                     days,
-                });
+                }
+            });
+        }
+        if let (Some(series), Some(day)) = (result.last_mut(), day) {
+            if let Some(entry) = series.days.get_mut(day as usize) {
+                entry.revenue = revenue.unwrap_or(0.0);
+                entry.units = units.unwrap_or(0);
             }
         }
-        return Ok(result);
     }
 
-    // No app name column - find launch date as first date with revenue > 0
-    let sql = format!(
-        "SELECT {}, MIN(date) as launch_date 
-         FROM sales_data 
-         WHERE {} IS NOT NULL AND {} != 0 
-           AND CAST(gross_sales_usd AS REAL) > 0
-         GROUP BY {} 
-         ORDER BY {}",
-        app_id_col, app_id_col, app_id_col, app_id_col, app_id_col
-    );
-    let mut stmt = conn.prepare(&sql)?;
-    let app_rows: Vec<(i64, String)> = stmt
-        .query_map([], |r| {
-            let app_id: Option<i64> = r.get(0)?;
-            let launch_date: String = r.get(1)?;
-            Ok((app_id, launch_date))
-        })?
-        .filter_map(|row| {
-            row.ok().and_then(|(app_id_opt, launch_date)| {
-                app_id_opt.map(|app_id| (app_id, launch_date))
-            })
-        })
-        .collect();
+    Ok(result)
+}
 
-    for (app_id, launch_date) in app_rows {
-        let days = calculate_product_days(
-            &conn,
-            app_id,
-            &launch_date,
-            max_days,
-            &app_id_col,
-            &latest_date,
-        )?;
-        result.push(LaunchComparisonApp {
-            app_id: Some(app_id),
-            package_id: None,
-            app_name: Some(format!("App {}", app_id)),
-            package_name: None,
-            launch_date,
-            days,
-        });
-    }
+/// `get_product_stats` through the query cache, with the arguments of `query_product_stats`.
+pub(crate) fn cached_product_stats(
+    cache: &QueryCache,
+    product_type: &str,
+    product_id: i64,
+    revenue_basis: Option<RevenueBasis>,
+) -> AppResult<ProductStats> {
+    let args = (product_type, product_id, revenue_basis);
+    cache.get_or_compute(
+        "query_product_stats",
+        &args,
+        &crate::database::get_database_path(),
+        || {
+            get_product_stats(product_type, product_id, revenue_basis.unwrap_or_default())
+                .map_err(AppError::from)
+        },
+    )
+}
 
-    Ok(result)
+/// `get_launch_comparison` through the query cache, with the arguments of
+/// `query_launch_comparison`.
+pub(crate) fn cached_launch_comparison(
+    cache: &QueryCache,
+    max_days: u32,
+    product_type: &str,
+    exclude_partial_day: Option<bool>,
+    group_products: Option<bool>,
+    revenue_basis: Option<RevenueBasis>,
+    product_ids: Option<&[i64]>,
+) -> AppResult<Vec<LaunchComparisonApp>> {
+    let args = (
+        max_days,
+        product_type,
+        exclude_partial_day,
+        group_products,
+        revenue_basis,
+        product_ids,
+    );
+    cache.get_or_compute(
+        "query_launch_comparison",
+        &args,
+        &crate::database::get_database_path(),
+        || {
+            get_launch_comparison(
+                max_days,
+                product_type,
+                exclude_partial_day,
+                group_products.unwrap_or(false),
+                revenue_basis.unwrap_or_default(),
+                product_ids,
+            )
+            .map_err(AppError::from)
+        },
+    )
 }
 
 // ==================== Tauri Commands ====================
 
 #[tauri::command]
-pub async fn query_stats(filters: QueryFilters) -> Result<DashboardStats, String> {
-    get_stats(filters).map_err(|e| e.to_string())
+#[allow(non_snake_case)]
+pub async fn query_stats(
+    cache: tauri::State<'_, QueryCache>,
+    running: tauri::State<'_, RunningQueries>,
+    filters: QueryFilters,
+    queryId: Option<String>,
+) -> AppResult<DashboardStats> {
+    let filters = filters.resolve_date_range()?;
+    let stats = running.run(queryId, || {
+        cache.get_or_compute(
+            "query_stats",
+            &filters,
+            &database_path_for(&filters)?,
+            || get_stats(filters.clone()).map_err(AppError::from),
+        )
+    })?;
+    presentation::present(stats)
 }
 
 #[tauri::command]
-pub async fn query_sales(filters: QueryFilters) -> Result<SalesResponse, String> {
-    get_sales(filters).map_err(|e| e.to_string())
+#[allow(non_snake_case)]
+pub async fn query_sales(
+    running: tauri::State<'_, RunningQueries>,
+    filters: QueryFilters,
+    queryId: Option<String>,
+) -> AppResult<SalesResponse> {
+    let filters = filters.resolve_date_range()?;
+    let sales = running.run(queryId, || {
+        perf::measure("query_sales", || get_sales(filters).map_err(AppError::from))
+    })?;
+    presentation::present(sales)
 }
 
 #[tauri::command]
-pub async fn query_daily_summaries(filters: QueryFilters) -> Result<Vec<DailySummary>, String> {
-    get_daily_summaries(filters).map_err(|e| e.to_string())
+#[allow(non_snake_case)]
+pub async fn query_daily_summaries(
+    cache: tauri::State<'_, QueryCache>,
+    running: tauri::State<'_, RunningQueries>,
+    filters: QueryFilters,
+    queryId: Option<String>,
+) -> AppResult<Vec<DailySummary>> {
+    let filters = filters.resolve_date_range()?;
+    let summaries = running.run(queryId, || {
+        cache.get_or_compute(
+            "query_daily_summaries",
+            &filters,
+            &database_path_for(&filters)?,
+            || get_daily_summaries(filters.clone()).map_err(AppError::from),
+        )
+    })?;
+    presentation::present(summaries)
 }
 
 #[tauri::command]
-pub async fn query_app_summaries(filters: QueryFilters) -> Result<Vec<AppSummary>, String> {
-    get_app_summaries(filters).map_err(|e| e.to_string())
+#[allow(non_snake_case)]
+pub async fn query_app_summaries(
+    cache: tauri::State<'_, QueryCache>,
+    running: tauri::State<'_, RunningQueries>,
+    filters: QueryFilters,
+    queryId: Option<String>,
+) -> AppResult<Vec<AppSummary>> {
+    let filters = filters.resolve_date_range()?;
+    let summaries = running.run(queryId, || {
+        cache.get_or_compute(
+            "query_app_summaries",
+            &filters,
+            &database_path_for(&filters)?,
+            || get_app_summaries(filters.clone()).map_err(AppError::from),
+        )
+    })?;
+    presentation::present(summaries)
 }
 
 #[tauri::command]
-pub async fn query_country_summaries(filters: QueryFilters) -> Result<Vec<CountrySummary>, String> {
-    get_country_summaries(filters).map_err(|e| e.to_string())
+#[allow(non_snake_case)]
+pub async fn query_country_summaries(
+    cache: tauri::State<'_, QueryCache>,
+    running: tauri::State<'_, RunningQueries>,
+    filters: QueryFilters,
+    queryId: Option<String>,
+) -> AppResult<Vec<CountrySummary>> {
+    let filters = filters.resolve_date_range()?;
+    let summaries = running.run(queryId, || {
+        cache.get_or_compute(
+            "query_country_summaries",
+            &filters,
+            &database_path_for(&filters)?,
+            || get_country_summaries(filters.clone()).map_err(AppError::from),
+        )
+    })?;
+    presentation::present(summaries)
 }
 
 #[tauri::command]
-pub async fn query_apps_lookup() -> Result<Vec<AppLookup>, String> {
-    get_apps_lookup().map_err(|e| e.to_string())
+pub async fn query_apps_lookup() -> AppResult<Vec<AppLookup>> {
+    perf::measure("query_apps_lookup", || {
+        get_apps_lookup().map_err(AppError::from)
+    })
 }
 
 #[tauri::command]
-pub async fn query_countries_lookup() -> Result<Vec<CountryLookup>, String> {
-    get_countries_lookup().map_err(|e| e.to_string())
+pub async fn query_countries_lookup() -> AppResult<Vec<CountryLookup>> {
+    perf::measure("query_countries_lookup", || {
+        get_countries_lookup().map_err(AppError::from)
+    })
 }
 
 #[tauri::command]
-pub async fn query_dates_list() -> Result<Vec<String>, String> {
-    get_dates_list().map_err(|e| e.to_string())
+pub async fn query_dates_list() -> AppResult<Vec<String>> {
+    perf::measure("query_dates_list", || {
+        get_dates_list().map_err(AppError::from)
+    })
 }
 
 #[tauri::command]
-pub async fn query_raw_data_by_date(date: String) -> Result<Vec<SalesRecord>, String> {
-    get_raw_data_by_date(&date).map_err(|e| e.to_string())
+#[allow(non_snake_case)]
+pub async fn query_raw_data_by_date(
+    running: tauri::State<'_, RunningQueries>,
+    date: Date,
+    queryId: Option<String>,
+) -> AppResult<Vec<SalesRecord>> {
+    let records = running.run(queryId, || {
+        perf::measure("query_raw_data_by_date", || {
+            get_raw_data_by_date(date).map_err(AppError::from)
+        })
+    })?;
+    presentation::present(records)
 }
 
+/// Emits the records of `date` as `raw-data-chunk` events of `chunkSize` records (default
+/// 5,000) instead of returning them in one payload. Resolves once the last chunk was emitted.
+/// `requestId` also works as the query id for `cancel_query`.
 #[tauri::command]
-pub async fn query_packages_lookup() -> Result<Vec<PackageLookup>, String> {
-    get_packages_lookup().map_err(|e| e.to_string())
+#[allow(non_snake_case)]
+pub async fn stream_raw_data_by_date(
+    app: AppHandle,
+    date: Date,
+    requestId: String,
+    chunkSize: Option<usize>,
+) -> AppResult<RawDataStream> {
+    let chunk_size = chunkSize
+        .unwrap_or(DEFAULT_RAW_DATA_CHUNK_SIZE)
+        .clamp(1, MAX_RAW_DATA_CHUNK_SIZE);
+    let money_factor = presentation::money_factor()?;
+    tokio::task::spawn_blocking(move || {
+        let mut chunk_index = 0;
+        let query_id = Some(requestId.clone());
+        app.state::<RunningQueries>().run(query_id, || {
+            perf::measure("stream_raw_data_by_date", || {
+                stream_raw_data(date, chunk_size, |mut records, done| {
+                    if let Some(factor) = money_factor {
+                        records.scale_money(factor);
+                    }
+                    let _ = app.emit(
+                        "raw-data-chunk",
+                        &RawDataChunk {
+                            request_id: requestId.clone(),
+                            chunk_index,
+                            records,
+                            done,
+                        },
+                    );
+                    chunk_index += 1;
+                })
+                .map_err(AppError::from)
+            })
+        })
+    })
+    .await?
 }
 
+/// The data sources queries can be scoped to, and the API key of each.
 #[tauri::command]
-#[allow(non_snake_case)]
-pub async fn query_packages_by_app(appId: i64) -> Result<Vec<PackageLookup>, String> {
-    let conn = get_connection().map_err(|e| e.to_string())?;
+pub async fn query_sources() -> AppResult<Vec<QuerySource>> {
+    tokio::task::spawn_blocking(get_query_sources).await?
+}
 
-    // Check if lookup_packages table exists
-    let lookup_packages_exists: i64 = conn
-        .query_row(
-            "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='lookup_packages'",
-            [],
-            |row| row.get(0),
-        )
-        .unwrap_or(0);
+#[tauri::command]
+pub async fn query_packages_lookup() -> AppResult<Vec<PackageLookup>> {
+    perf::measure("query_packages_lookup", || {
+        get_packages_lookup().map_err(AppError::from)
+    })
+}
+
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn query_packages_by_app(appId: i64) -> AppResult<Vec<PackageLookup>> {
+    let conn = get_connection()?;
 
-    if lookup_packages_exists > 0 {
+    // Check if there is a lookup table of package names
+    if let Some(packages) = package_names_table(&conn) {
         // Get the app ID column name
         let app_id_col = get_app_id_column(&conn);
 
@@ -1591,26 +2287,25 @@ pub async fn query_packages_by_app(appId: i64) -> Result<Vec<PackageLookup>, Str
         let sql = format!(
             "SELECT DISTINCT s.packageid, p.package_name 
              FROM sales_data s 
-             JOIN lookup_packages p ON s.packageid = p.packageid 
+             JOIN {} p ON s.packageid = p.packageid 
              WHERE s.packageid IS NOT NULL 
                AND s.packageid != 0 
                AND s.{} = ?
              ORDER BY s.packageid",
-            app_id_col
+            packages, app_id_col
         );
 
-        let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+        let mut stmt = conn.prepare(&sql)?;
         let rows = stmt
             .query_map([appId], |row| {
                 let package_id: Option<i64> = row.get(0)?;
                 let package_name: Option<String> = row.get(1)?;
                 Ok((package_id, package_name))
-            })
-            .map_err(|e| e.to_string())?;
+            })?;
 
         let mut packages = Vec::new();
         for row in rows {
-            if let (Some(package_id), package_name_opt) = row.map_err(|e| e.to_string())? {
+            if let (Some(package_id), package_name_opt) = row? {
                 if package_id != 0 {
                     packages.push(PackageLookup {
                         package_id,
@@ -1634,17 +2329,16 @@ pub async fn query_packages_by_app(appId: i64) -> Result<Vec<PackageLookup>, Str
             app_id_col
         );
 
-        let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+        let mut stmt = conn.prepare(&sql)?;
         let rows = stmt
             .query_map([appId], |row| {
                 let package_id: Option<i64> = row.get(0)?;
                 Ok(package_id)
-            })
-            .map_err(|e| e.to_string())?;
+            })?;
 
         let mut packages = Vec::new();
         for row in rows {
-            if let Some(package_id) = row.map_err(|e| e.to_string())? {
+            if let Some(package_id) = row? {
                 if package_id != 0 {
                     packages.push(PackageLookup {
                         package_id,
@@ -1662,15 +2356,372 @@ pub async fn query_packages_by_app(appId: i64) -> Result<Vec<PackageLookup>, Str
 pub async fn query_product_stats(
     productType: String,
     productId: i64,
-) -> Result<ProductStats, String> {
-    get_product_stats(&productType, productId).map_err(|e| e.to_string())
+    revenueBasis: Option<RevenueBasis>,
+    cache: tauri::State<'_, QueryCache>,
+    running: tauri::State<'_, RunningQueries>,
+    queryId: Option<String>,
+) -> AppResult<ProductStats> {
+    let stats = running.run(queryId, || {
+        cached_product_stats(&cache, &productType, productId, revenueBasis)
+    })?;
+    presentation::present(stats)
 }
 
 #[tauri::command]
-#[allow(non_snake_case)]
+#[allow(non_snake_case, clippy::too_many_arguments)]
 pub async fn query_launch_comparison(
     maxDays: u32,
     productType: String,
-) -> Result<Vec<LaunchComparisonApp>, String> {
-    get_launch_comparison(maxDays, &productType).map_err(|e| e.to_string())
+    excludePartialDay: Option<bool>,
+    groupProducts: Option<bool>,
+    revenueBasis: Option<RevenueBasis>,
+    productIds: Option<Vec<i64>>,
+    includeBenchmarks: Option<bool>,
+    cache: tauri::State<'_, QueryCache>,
+    running: tauri::State<'_, RunningQueries>,
+    queryId: Option<String>,
+) -> AppResult<Vec<LaunchComparisonApp>> {
+    let mut series = running.run(queryId, || {
+        cached_launch_comparison(
+            &cache,
+            maxDays,
+            &productType,
+            excludePartialDay,
+            groupProducts,
+            revenueBasis,
+            productIds.as_deref(),
+        )
+    })?;
+    if includeBenchmarks.unwrap_or(false) {
+        series.extend(benchmarks::launch_series(
+            &productType,
+            revenueBasis.unwrap_or_default(),
+            maxDays,
+        )?);
+    }
+    presentation::present(series)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sales_db(with_tax: bool) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        let tax_column = if with_tax { ", net_tax_usd TEXT" } else { "" };
+        conn.execute_batch(&format!(
+            "CREATE TABLE sales_data (gross_sales_usd TEXT, net_sales_usd TEXT{});",
+            tax_column
+        ))
+        .unwrap();
+        let rows: [(&str, &str, Option<&str>); 2] =
+            [("10.00", "8.00", Some("1.50")), ("5.00", "4.00", None)];
+        for (gross, net, tax) in rows {
+            if with_tax {
+                conn.execute(
+                    "INSERT INTO sales_data VALUES (?1, ?2, ?3)",
+                    rusqlite::params![gross, net, tax],
+                )
+                .unwrap();
+            } else {
+                conn.execute(
+                    "INSERT INTO sales_data VALUES (?1, ?2)",
+                    rusqlite::params![gross, net],
+                )
+                .unwrap();
+            }
+        }
+        conn
+    }
+
+    fn total(conn: &Connection, basis: RevenueBasis) -> f64 {
+        conn.query_row(
+            &format!(
+                "SELECT SUM({}) FROM sales_data",
+                revenue_expr(conn, basis)
+            ),
+            [],
+            |r| r.get(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn revenue_expr_sums_each_basis() {
+        let conn = sales_db(true);
+        assert_eq!(total(&conn, RevenueBasis::Gross), 15.0);
+        assert_eq!(total(&conn, RevenueBasis::Net), 12.0);
+        // A row without tax counts as taxed at zero
+        assert_eq!(total(&conn, RevenueBasis::NetAfterTax), 10.5);
+    }
+
+    #[test]
+    fn net_after_tax_is_net_without_tax_column() {
+        let conn = sales_db(false);
+        assert_eq!(
+            total(&conn, RevenueBasis::NetAfterTax),
+            total(&conn, RevenueBasis::Net)
+        );
+    }
+
+    #[test]
+    fn filters_default_to_gross() {
+        let filters: QueryFilters = serde_json::from_str("{}").unwrap();
+        assert_eq!(filters.revenue_basis.unwrap_or_default(), RevenueBasis::Gross);
+        assert_eq!(
+            QueryFilters::default().revenue_basis.unwrap_or_default(),
+            RevenueBasis::Gross
+        );
+    }
+
+    #[test]
+    fn filters_parse_every_basis() {
+        for (name, basis) in [
+            ("gross", RevenueBasis::Gross),
+            ("net", RevenueBasis::Net),
+            ("net_after_tax", RevenueBasis::NetAfterTax),
+        ] {
+            let filters: QueryFilters =
+                serde_json::from_str(&format!(r#"{{"revenue_basis": "{}"}}"#, name)).unwrap();
+            assert_eq!(filters.revenue_basis, Some(basis));
+        }
+        assert!(serde_json::from_str::<QueryFilters>(r#"{"revenue_basis": "net_tax"}"#).is_err());
+    }
+
+    #[test]
+    fn filters_only_take_known_sort_values() {
+        let filters: QueryFilters =
+            serde_json::from_str(r#"{"sort_by": "units", "sortOrder": "ASC"}"#).unwrap();
+        assert_eq!(filters.sort_by, Some(SortField::Units));
+        assert_eq!(filters.sort_order, Some(SortOrder::Asc));
+        for bad in [
+            r#"{"sort_order": "DESC, (SELECT 1)"}"#,
+            r#"{"sort_order": "descending"}"#,
+            r#"{"sort_by": "net_sales_usd"}"#,
+            r#"{"sort_by": ""}"#,
+        ] {
+            assert!(
+                serde_json::from_str::<QueryFilters>(bad).is_err(),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn filters_accept_camel_and_snake_case() {
+        let camel: QueryFilters = serde_json::from_str(
+            r#"{"startDate": "2024-01-01", "appIds": [440], "revenueBasis": "net", "excludePartialDay": true}"#,
+        )
+        .unwrap();
+        let snake: QueryFilters = serde_json::from_str(
+            r#"{"start_date": "2024-01-01", "app_ids": [440], "revenue_basis": "net", "exclude_partial_day": true}"#,
+        )
+        .unwrap();
+        for filters in [camel, snake] {
+            assert_eq!(filters.start_date, Some("2024-01-01".parse().unwrap()));
+            assert_eq!(filters.app_ids, Some(vec![440]));
+            assert_eq!(filters.revenue_basis, Some(RevenueBasis::Net));
+            assert_eq!(filters.exclude_partial_day, Some(true));
+        }
+        let json = serde_json::to_value(QueryFilters::default()).unwrap();
+        assert!(json.get("sortBy").is_some() && json.get("sort_by").is_none());
+    }
+
+    fn sales_response() -> SalesResponse {
+        SalesResponse {
+            records: vec![SalesRecord {
+                id: 1,
+                date: "2024-03-01".to_string(),
+                line_item_type: "Package".to_string(),
+                app_id: Some(440),
+                app_name: Some("Game".to_string()),
+                package_id: Some(12),
+                package_name: None,
+                country_code: Some("DE".to_string()),
+                country_name: Some("Germany".to_string()),
+                region: Some("Europe".to_string()),
+                platform: Some("Windows".to_string()),
+                currency: Some("EUR".to_string()),
+                gross_units_sold: 3,
+                gross_units_returned: 1,
+                net_units_sold: 2,
+                gross_sales_usd: 29.97,
+                net_sales_usd: 19.98,
+                discount_percentage: None,
+                line_item_category: "sale".to_string(),
+                row_id: Some(7),
+            }],
+            pagination: Pagination {
+                total: 1,
+                limit: 1000,
+                offset: 0,
+                has_more: false,
+            },
+            data_as_of: None,
+        }
+    }
+
+    // Every key of an object, and of the objects in it, is camelCase
+    fn assert_camel_case(value: &serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                for (key, value) in map {
+                    assert!(!key.contains('_'), "{} is not camelCase", key);
+                    assert_camel_case(value);
+                }
+            }
+            serde_json::Value::Array(items) => items.iter().for_each(assert_camel_case),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn sales_response_is_camel_case_and_round_trips() {
+        let json = serde_json::to_value(sales_response()).unwrap();
+        assert_camel_case(&json);
+        assert_eq!(json["pagination"]["hasMore"], false);
+        assert_eq!(json["records"][0]["lineItemType"], "Package");
+        assert_eq!(json["records"][0]["grossSalesUsd"], 29.97);
+
+        let parsed: SalesResponse = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(parsed).unwrap(), json);
+    }
+
+    fn bounds(range: &str, today: &str, fiscal_start_month: u32) -> (String, String) {
+        let range: RelativeRange = range.parse().unwrap();
+        match range.bounds(today.parse().unwrap(), fiscal_start_month) {
+            (Some(start), Some(end)) => (start.to_string(), end.to_string()),
+            other => panic!("{} is unbounded: {:?}", range, other),
+        }
+    }
+
+    #[test]
+    fn relative_ranges_use_the_fiscal_year() {
+        let range = |start: &str, end: &str| (start.to_string(), end.to_string());
+        assert_eq!(bounds("fy2024", "2024-06-15", 4), range("2023-04-01", "2024-03-31"));
+        assert_eq!(bounds("fy2024", "2024-06-15", 1), range("2024-01-01", "2024-12-31"));
+        assert_eq!(bounds("this_fy", "2024-02-10", 4), range("2023-04-01", "2024-03-31"));
+        assert_eq!(bounds("last_fy", "2024-06-15", 4), range("2023-04-01", "2024-03-31"));
+        assert_eq!(bounds("last_quarter", "2024-05-20", 4), range("2024-01-01", "2024-03-31"));
+        assert_eq!(bounds("this_quarter", "2024-03-31", 2), range("2024-02-01", "2024-04-30"));
+        assert_eq!(bounds("last_quarter", "2024-02-01", 1), range("2023-10-01", "2023-12-31"));
+        assert_eq!(bounds("this_year", "2024-06-15", 4), range("2024-01-01", "2024-12-31"));
+        assert_eq!(bounds("last_month", "2024-03-31", 1), range("2024-02-01", "2024-02-29"));
+        assert_eq!(bounds("last_90d", "2024-03-31", 1), range("2024-01-02", "2024-03-31"));
+    }
+
+    #[test]
+    fn relative_ranges_round_trip() {
+        for name in ["all", "yesterday", "last_7d", "this_quarter", "last_fy", "fy2024"] {
+            assert_eq!(name.parse::<RelativeRange>().unwrap().to_string(), name);
+        }
+        for name in ["last_0d", "last_week", "fy24", "FY2024"] {
+            assert!(name.parse::<RelativeRange>().is_err(), "{}", name);
+        }
+        let filters: QueryFilters = serde_json::from_str(r#"{"date_range": "fy2024"}"#).unwrap();
+        assert_eq!(filters.date_range, Some(RelativeRange::FiscalYear(2024)));
+    }
+
+    #[test]
+    fn date_range_and_dates_are_exclusive() {
+        let filters = QueryFilters {
+            start_date: Some("2024-01-01".parse().unwrap()),
+            date_range: Some(RelativeRange::LastDays(30)),
+            ..QueryFilters::default()
+        };
+        assert!(filters.resolve_date_range().is_err());
+    }
+
+    // Rewrites the given days of sales_data the way a fetch saves them: one write transaction
+    // per day that deletes the day and inserts it again
+    fn rewrite_days(path: &std::path::Path, days: &[String]) -> SqliteResult<()> {
+        let conn = Connection::open(path)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        for day in days {
+            let tx = crate::database::begin_write(&conn)?;
+            tx.execute("DELETE FROM sales_data WHERE date = ?1", [day])?;
+            tx.execute(
+                "INSERT INTO sales_data SELECT * FROM sales_copy WHERE date = ?1",
+                [day],
+            )?;
+            tx.commit()?;
+        }
+        Ok(())
+    }
+
+    // Runs dashboard queries until `done`, at least once; returns how many ran
+    fn query_until(done: &std::sync::atomic::AtomicBool) -> SqliteResult<usize> {
+        let mut queries = 0;
+        while queries == 0 || !done.load(std::sync::atomic::Ordering::Relaxed) {
+            get_stats(QueryFilters::default())?;
+            get_sales(QueryFilters::default())?;
+            get_daily_summaries(QueryFilters::default())?;
+            // Loads lookup_countries into the database on first use
+            get_country_summaries(QueryFilters::default())?;
+            queries += 4;
+        }
+        Ok(queries)
+    }
+
+    #[test]
+    fn queries_run_while_a_fetch_writes() {
+        use crate::database::TestDataDir;
+        use crate::demo_data::{self, DemoProfile};
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        for journal_mode in ["DELETE", "WAL"] {
+            // The app database (settings, sources) and the active sales database live here
+            let dir = std::env::temp_dir().join(format!(
+                "steam-sales-stress-{}-{}",
+                journal_mode,
+                std::process::id()
+            ));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            let _data_dir = TestDataDir::enter(&dir);
+            let path = crate::database::get_database_path();
+            let end = "2026-06-30".parse().unwrap();
+            demo_data::generate_database(DemoProfile::Small, end, &path).unwrap();
+            let conn = Connection::open(&path).unwrap();
+            conn.pragma_update(None, "journal_mode", journal_mode)
+                .unwrap();
+            conn.execute_batch("CREATE TABLE sales_copy AS SELECT * FROM sales_data")
+                .unwrap();
+            let days: Vec<String> = conn
+                .prepare("SELECT DISTINCT date FROM sales_data ORDER BY date DESC LIMIT 90")
+                .unwrap()
+                .query_map([], |r| r.get(0))
+                .unwrap()
+                .collect::<SqliteResult<_>>()
+                .unwrap();
+            drop(conn);
+
+            let done = AtomicBool::new(false);
+            let (fetched, queried) = std::thread::scope(|s| {
+                let readers: Vec<_> = (0..4)
+                    .map(|_| {
+                        s.spawn(|| {
+                            let _data_dir = TestDataDir::enter(&dir);
+                            query_until(&done)
+                        })
+                    })
+                    .collect();
+                let fetched = rewrite_days(&path, &days);
+                done.store(true, Ordering::Relaxed);
+                let queried: Vec<_> = readers.into_iter().map(|r| r.join().unwrap()).collect();
+                (fetched, queried)
+            });
+            let _ = std::fs::remove_dir_all(&dir);
+
+            if let Err(e) = fetched {
+                panic!("The fetch failed in {} mode: {}", journal_mode, e);
+            }
+            for result in queried {
+                if let Err(e) = result {
+                    panic!("A query failed in {} mode: {}", journal_mode, e);
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,209 @@
+// In-memory cache of dashboard query results
+//
+// The dashboard re-runs the same stats and summary queries on every navigation, although the
+// data only changes when a fetch or import writes to the database. Results are kept in managed
+// state, keyed by query and filters, and dropped when a fetch or itch.io sync finishes, when
+// the database they were read from (or its WAL file) has been modified since they were cached,
+// or on `clear_query_cache`. Each database has its own version: queries filtered by data source
+// or API key read another source's database, and writes to it only drop what was read from it.
+
+use crate::perf::{self, RowCount};
+use crate::queries;
+use crate::read_snapshot;
+use crate::types::AppResult;
+use serde::Serialize;
+use std::any::Any;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+// Identifies the state of the database the cached results were computed from
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DatabaseVersion {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    wal_modified: Option<SystemTime>,
+}
+
+impl DatabaseVersion {
+    pub(crate) fn of(path: &Path) -> Self {
+        let modified = |p: &Path| std::fs::metadata(p).and_then(|m| m.modified()).ok();
+        let mut wal = path.as_os_str().to_owned();
+        wal.push("-wal");
+        Self {
+            path: path.to_path_buf(),
+            modified: modified(path),
+            wal_modified: modified(Path::new(&wal)),
+        }
+    }
+}
+
+// Results read from one database, and the state of the database they were read in
+struct CacheEntries {
+    version: DatabaseVersion,
+    results: HashMap<String, Box<dyn Any + Send>>,
+}
+
+#[derive(Default)]
+pub struct QueryCache {
+    // By the path of the live database
+    entries: Mutex<HashMap<PathBuf, CacheEntries>>,
+}
+
+impl QueryCache {
+    /// Returns the cached result of `query` with `filters`, or runs `compute` and caches its
+    /// result. `database` is the live database `compute` reads. Errors are not cached. `query`
+    /// is also the name `compute` is timed under in the performance log.
+    pub fn get_or_compute<T, F>(
+        &self,
+        query: &str,
+        filters: &impl Serialize,
+        database: &Path,
+        compute: F,
+    ) -> AppResult<T>
+    where
+        T: Clone + Send + RowCount + 'static,
+        F: FnOnce() -> AppResult<T>,
+    {
+        // Results also depend on which day Steam is still reporting and whether it's excluded
+        let key = format!(
+            "{}|{}|{}|{:?}",
+            query,
+            serde_json::to_string(filters).unwrap_or_default(),
+            queries::reporting_today(),
+            queries::last_complete_day(None)
+        );
+        self.get_or_compute_keyed(key, database, || perf::measure(query, compute))
+    }
+
+    fn get_or_compute_keyed<T, F>(&self, key: String, database: &Path, compute: F) -> AppResult<T>
+    where
+        T: Clone + Send + 'static,
+        F: FnOnce() -> AppResult<T>,
+    {
+        // The snapshot, while a fetch writes to the live database
+        let version = DatabaseVersion::of(&read_snapshot::reading_path(database));
+        {
+            let mut databases = self.entries.lock().unwrap();
+            let entries = databases
+                .entry(database.to_path_buf())
+                .or_insert_with(|| CacheEntries {
+                    version: version.clone(),
+                    results: HashMap::new(),
+                });
+            if entries.version != version {
+                entries.results.clear();
+                entries.version = version.clone();
+            }
+            if let Some(result) = entries
+                .results
+                .get(&key)
+                .and_then(|r| r.downcast_ref::<T>())
+            {
+                return Ok(result.clone());
+            }
+        }
+
+        let result = compute()?;
+        let mut databases = self.entries.lock().unwrap();
+        // Not cached if the database changed while the query ran
+        if let Some(entries) = databases
+            .get_mut(database)
+            .filter(|entries| entries.version == version)
+        {
+            entries.results.insert(key, Box::new(result.clone()));
+        }
+        Ok(result)
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+// ==================== Tauri Commands ====================
+
+#[tauri::command]
+pub async fn clear_query_cache(cache: tauri::State<'_, QueryCache>) -> AppResult<()> {
+    cache.clear();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+    use std::fs::File;
+    use std::time::Duration;
+
+    // A sales database in a temp dir; removed when dropped
+    struct TempDatabase(PathBuf);
+
+    impl TempDatabase {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "steamsales-query-cache-{}-{}.db",
+                std::process::id(),
+                name
+            ));
+            let _ = std::fs::remove_file(&path);
+            Connection::open(&path)
+                .unwrap()
+                .execute_batch("CREATE TABLE sales_data (date TEXT, net_units_sold INTEGER);")
+                .unwrap();
+            TempDatabase(path)
+        }
+
+        fn units(&self) -> AppResult<i64> {
+            let conn = Connection::open(&self.0)?;
+            Ok(conn.query_row(
+                "SELECT COALESCE(SUM(net_units_sold), 0) FROM sales_data",
+                [],
+                |r| r.get(0),
+            )?)
+        }
+
+        fn insert_units(&self, units: i64) {
+            Connection::open(&self.0)
+                .unwrap()
+                .execute("INSERT INTO sales_data VALUES ('2024-03-01', ?1)", [units])
+                .unwrap();
+            // File systems with coarse timestamps would only show the write a second later
+            let modified = std::fs::metadata(&self.0).unwrap().modified().unwrap();
+            File::options()
+                .write(true)
+                .open(&self.0)
+                .unwrap()
+                .set_modified(modified + Duration::from_secs(1))
+                .unwrap();
+        }
+    }
+
+    impl Drop for TempDatabase {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn writes_to_a_database_drop_only_its_results() {
+        let active = TempDatabase::new("active");
+        let other = TempDatabase::new("other");
+        let cache = QueryCache::default();
+        let query = |db: &TempDatabase, compute: &dyn Fn() -> AppResult<i64>| {
+            cache.get_or_compute_keyed("units".to_string(), &db.0, compute)
+        };
+        assert_eq!(query(&active, &|| active.units()).unwrap(), 0);
+        assert_eq!(query(&other, &|| other.units()).unwrap(), 0);
+        // Cached: not computed again
+        assert_eq!(query(&other, &|| Ok(-1)).unwrap(), 0);
+
+        other.insert_units(3);
+        assert_eq!(query(&other, &|| other.units()).unwrap(), 3);
+        assert_eq!(query(&active, &|| Ok(-1)).unwrap(), 0);
+
+        cache.clear();
+        assert_eq!(query(&active, &|| Ok(-1)).unwrap(), -1);
+    }
+}
@@ -0,0 +1,166 @@
+// Timeouts and cancellation of dashboard queries
+//
+// Some filters make a query command run for minutes (e.g. a launch comparison over hundreds of
+// packages), and nothing used to stop it. Query commands now run through
+// `RunningQueries::run`, which interrupts every connection the query opened (see `watch`, called
+// by `queries::get_connection`) once the `queryTimeoutSecs` setting has passed, or as soon as
+// `cancel_query` is called with the id the frontend passed along. The command then fails with a
+// `timeout` or `cancelled` error instead of whatever SQLite reported for the interrupted
+// statement.
+//
+// The connections are found through a thread-local: query functions run synchronously on the
+// thread that called `run`, so every connection opened on that thread in the meantime belongs to
+// the query.
+
+use crate::settings;
+use crate::types::{AppError, AppResult};
+use rusqlite::{Connection, InterruptHandle};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// How often a stopped query is interrupted again. SQLite forgets an interrupt that arrives
+// between two statements, and a query function may run several.
+const INTERRUPT_INTERVAL: Duration = Duration::from_millis(100);
+
+thread_local! {
+    static CURRENT: RefCell<Option<Arc<RunningQuery>>> = const { RefCell::new(None) };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StopReason {
+    TimedOut,
+    Cancelled,
+}
+
+#[derive(Default)]
+struct RunningQuery {
+    interrupts: Mutex<Vec<InterruptHandle>>,
+    stopped: Mutex<Option<StopReason>>,
+}
+
+impl RunningQuery {
+    fn stop(&self, reason: StopReason) {
+        self.stopped.lock().unwrap().get_or_insert(reason);
+        self.interrupt();
+    }
+
+    fn interrupt(&self) {
+        for handle in self.interrupts.lock().unwrap().iter() {
+            handle.interrupt();
+        }
+    }
+
+    fn stop_reason(&self) -> Option<StopReason> {
+        *self.stopped.lock().unwrap()
+    }
+}
+
+/// Queries in flight that were given an id, so `cancel_query` can stop them.
+#[derive(Default)]
+pub struct RunningQueries {
+    queries: Mutex<HashMap<String, Arc<RunningQuery>>>,
+}
+
+impl RunningQueries {
+    /// Runs `query` with the configured timeout. With a `query_id`, `cancel_query` can stop it
+    /// while it runs.
+    pub fn run<T>(
+        &self,
+        query_id: Option<String>,
+        query: impl FnOnce() -> AppResult<T>,
+    ) -> AppResult<T> {
+        let timeout = Duration::from_secs(settings::load_settings().query_timeout_secs);
+        let running = Arc::new(RunningQuery::default());
+        if let Some(id) = &query_id {
+            self.queries
+                .lock()
+                .unwrap()
+                .insert(id.clone(), running.clone());
+        }
+
+        // Stops the query once the timeout passes, and keeps interrupting a stopped query until
+        // it returns
+        let (done_tx, done_rx) = mpsc::channel::<()>();
+        let watched = running.clone();
+        let started = Instant::now();
+        thread::spawn(move || {
+            while done_rx.recv_timeout(INTERRUPT_INTERVAL) == Err(mpsc::RecvTimeoutError::Timeout) {
+                if started.elapsed() >= timeout {
+                    watched.stop(StopReason::TimedOut);
+                } else if watched.stop_reason().is_some() {
+                    watched.interrupt();
+                }
+            }
+        });
+
+        let previous = CURRENT.with(|current| current.replace(Some(running.clone())));
+        let result = query();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+        let _ = done_tx.send(());
+        if let Some(id) = &query_id {
+            let mut queries = self.queries.lock().unwrap();
+            // A later query may have reused the id
+            if queries.get(id).is_some_and(|q| Arc::ptr_eq(q, &running)) {
+                queries.remove(id);
+            }
+        }
+
+        match (result, running.stop_reason()) {
+            (Err(_), Some(StopReason::TimedOut)) => Err(AppError::Timeout(format!(
+                "Query took longer than {} seconds and was stopped",
+                timeout.as_secs()
+            ))),
+            (Err(_), Some(StopReason::Cancelled)) => {
+                Err(AppError::Cancelled("Query cancelled".to_string()))
+            }
+            (result, _) => result,
+        }
+    }
+
+    /// Stops the query with `query_id`. Returns false if no such query is running.
+    pub fn cancel(&self, query_id: &str) -> bool {
+        match self.queries.lock().unwrap().get(query_id) {
+            Some(query) => {
+                query.stop(StopReason::Cancelled);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Makes `conn` part of the query running on this thread, if there is one, so stopping the
+/// query interrupts it.
+pub fn watch(conn: &Connection) {
+    CURRENT.with(|current| {
+        if let Some(query) = current.borrow().as_ref() {
+            let handle = conn.get_interrupt_handle();
+            if query.stop_reason().is_some() {
+                handle.interrupt();
+            }
+            query.interrupts.lock().unwrap().push(handle);
+        }
+    });
+}
+
+// ==================== Tauri Commands ====================
+
+/// Stops the query command that was called with `queryId`; it fails with a `cancelled` error.
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn cancel_query(
+    running: tauri::State<'_, RunningQueries>,
+    queryId: String,
+) -> AppResult<()> {
+    if running.cancel(&queryId) {
+        Ok(())
+    } else {
+        Err(AppError::NotFound(format!(
+            "No query with id '{}' is running",
+            queryId
+        )))
+    }
+}
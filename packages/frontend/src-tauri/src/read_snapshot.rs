@@ -0,0 +1,124 @@
+// Reading a snapshot while the CLI writes
+//
+// A fetch rewrites the sales database day by day, so a dashboard opened meanwhile either waits
+// on the CLI's locks or shows days that are only half written. With `snapshotReadsDuringSync`
+// on, a fetch first copies the database it is about to write (VACUUM INTO, which reads a single
+// consistent state) to ~/.steamsales/read-snapshot.db, and every query of that database reads
+// the copy until the fetch is over. Responses that carry `data_as_of` then give the time the
+// copy was taken, so the UI can say "data as of 14:32, sync in progress"; it is None when the
+// live database was read. The copy is opened read-only, since it is deleted when the fetch is
+// over; writers use `queries::get_write_connection`, which always opens the live database.
+//
+// The copy costs as much disk space and time as the database is large, which is why it is off
+// by default.
+
+use crate::countries;
+use crate::price_regions;
+use crate::types::{AppError, AppResult};
+use chrono::Utc;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::{info, warn};
+
+const SNAPSHOT_FILE: &str = "read-snapshot.db";
+
+struct Snapshot {
+    /// The database being fetched into
+    live_path: PathBuf,
+    path: PathBuf,
+    taken_at: String,
+}
+
+static SNAPSHOT: Mutex<Option<Snapshot>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadSnapshot {
+    pub database_path: String,
+    pub taken_at: String,
+}
+
+fn snapshot_path() -> AppResult<PathBuf> {
+    let home = dirs::home_dir()
+        .ok_or_else(|| AppError::Io("Failed to get home directory".to_string()))?;
+    Ok(home.join(".steamsales").join(SNAPSHOT_FILE))
+}
+
+/// Copies the database at `live_path` and reads the copy instead until `release`. Blocks for as
+/// long as the copy takes.
+pub fn take(live_path: &Path) -> AppResult<()> {
+    release();
+    // A first fetch has nothing to copy yet
+    if !live_path.exists() {
+        return Ok(());
+    }
+    let path = snapshot_path()?;
+    // VACUUM INTO refuses to overwrite; left over if a query still had the last one open
+    if path.exists() {
+        fs::remove_file(&path)?;
+    }
+    let conn = Connection::open(live_path)?;
+    // Queries fill these on first use, which the read-only copy can't
+    countries::ensure_lookup_table(&conn)?;
+    price_regions::ensure_lookup_table(&conn)?;
+    conn.execute("VACUUM INTO ?1", [path.to_string_lossy()])?;
+    let taken_at = Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+    info!(
+        "Took a read snapshot of {} at {}",
+        live_path.display(),
+        taken_at
+    );
+    *SNAPSHOT.lock().unwrap() = Some(Snapshot {
+        live_path: live_path.to_path_buf(),
+        path,
+        taken_at,
+    });
+    Ok(())
+}
+
+/// Goes back to reading the live database and deletes the copy.
+pub fn release() {
+    let Some(snapshot) = SNAPSHOT.lock().unwrap().take() else {
+        return;
+    };
+    // Fails on Windows while a query still reads it; `take` deletes it next time
+    if let Err(e) = fs::remove_file(&snapshot.path) {
+        warn!("Failed to delete the read snapshot: {}", e);
+    }
+}
+
+/// The database to read for `live_path`: the snapshot while one is taken of it.
+pub fn reading_path(live_path: &Path) -> PathBuf {
+    match SNAPSHOT.lock().unwrap().as_ref() {
+        Some(snapshot) if snapshot.live_path == live_path => snapshot.path.clone(),
+        _ => live_path.to_path_buf(),
+    }
+}
+
+/// When the data read for `live_path` was copied, or None if the live database is read.
+pub fn data_as_of(live_path: &Path) -> Option<String> {
+    SNAPSHOT
+        .lock()
+        .unwrap()
+        .as_ref()
+        .filter(|snapshot| snapshot.live_path == live_path)
+        .map(|snapshot| snapshot.taken_at.clone())
+}
+
+// ==================== Tauri Commands ====================
+
+/// The snapshot queries read from while a fetch runs, if any.
+#[tauri::command]
+pub async fn get_read_snapshot() -> AppResult<Option<ReadSnapshot>> {
+    Ok(SNAPSHOT
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|snapshot| ReadSnapshot {
+            database_path: snapshot.live_path.to_string_lossy().to_string(),
+            taken_at: snapshot.taken_at.clone(),
+        }))
+}
@@ -0,0 +1,602 @@
+// Reconciliation against Steam
+//
+// Fetches the detailed sales of a date range straight from Steam's partner API and compares the
+// daily totals per app with the Steam rows stored locally, so differences (a date the CLI missed,
+// rows Steam revised later) show up before the data is used for statements. Amounts are
+// compared in whole cents, units exactly. Nothing is written; a mismatch is fixed by fetching
+// again with "force".
+//
+// The API key comes from the credential store, where init_cli saves it, or from the request.
+// `PartnerApi` is the client of the partner API; tests point it at a local mock of Steam.
+
+use crate::keys;
+use crate::presentation;
+use crate::queries::{get_app_id_column, get_source_column};
+use crate::sources;
+use crate::types::{lenient_f64, lenient_i64, lenient_u64, AppError, AppResult, Date};
+use rusqlite::{params, Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::time::Duration;
+
+pub(crate) const API_BASE: &str = "https://partner.steam-api.com/IPartnerFinancialsService";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+// A quarter; every date is at least one request
+const MAX_RANGE_DAYS: i64 = 92;
+// Stops paging through a date if the API keeps returning pages
+pub(crate) const MAX_PAGES: u32 = 1000;
+
+#[derive(Debug, Deserialize)]
+struct DetailedSalesEnvelope {
+    #[serde(default)]
+    response: DetailedSalesResponse,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct DetailedSalesResponse {
+    #[serde(default)]
+    results: Vec<SteamSaleItem>,
+    /// A string in practice, a number in some responses
+    #[serde(default, deserialize_with = "lenient_u64")]
+    max_id: Option<u64>,
+    #[serde(default)]
+    pub(crate) app_info: Vec<SteamAppInfo>,
+    #[serde(default)]
+    pub(crate) package_info: Vec<SteamPackageInfo>,
+}
+
+impl DetailedSalesResponse {
+    /// The highwatermark to request the next page of the date with, or None on the last page.
+    pub(crate) fn next_page(&self, highwatermark_id: u64) -> Option<u64> {
+        let max_id = self.max_id.unwrap_or(0);
+        (!self.results.is_empty() && max_id > highwatermark_id).then_some(max_id)
+    }
+}
+
+// Only the fields reconciliation compares; Steam sends numbers as strings or numbers
+#[derive(Debug, Deserialize)]
+struct SteamSaleItem {
+    #[serde(default, deserialize_with = "lenient_i64")]
+    primary_appid: Option<i64>,
+    #[serde(default, deserialize_with = "lenient_i64")]
+    appid: Option<i64>,
+    #[serde(default, deserialize_with = "lenient_i64")]
+    net_units_sold: Option<i64>,
+    #[serde(default, deserialize_with = "lenient_f64")]
+    gross_sales_usd: Option<f64>,
+    #[serde(default, deserialize_with = "lenient_f64")]
+    net_sales_usd: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct SteamAppInfo {
+    pub(crate) appid: i64,
+    pub(crate) app_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct SteamPackageInfo {
+    pub(crate) packageid: i64,
+    pub(crate) package_name: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReconcileTotals {
+    pub gross_sales_usd: f64,
+    pub net_sales_usd: f64,
+    pub net_units_sold: i64,
+    pub rows: u64,
+}
+
+impl ReconcileTotals {
+    fn add(&mut self, other: &ReconcileTotals) {
+        self.gross_sales_usd += other.gross_sales_usd;
+        self.net_sales_usd += other.net_sales_usd;
+        self.net_units_sold += other.net_units_sold;
+        self.rows += other.rows;
+    }
+
+    // Row counts may differ when the CLI merges or splits rows, so they are not compared
+    fn matches(&self, other: &ReconcileTotals) -> bool {
+        let cents = |usd: f64| (usd * 100.0).round() as i64;
+        cents(self.gross_sales_usd) == cents(other.gross_sales_usd)
+            && cents(self.net_sales_usd) == cents(other.net_sales_usd)
+            && self.net_units_sold == other.net_units_sold
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppReconciliation {
+    pub app_id: Option<i64>,
+    pub app_name: Option<String>,
+    pub steam: ReconcileTotals,
+    pub local: ReconcileTotals,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DateReconciliation {
+    pub date: Date,
+    pub steam: ReconcileTotals,
+    pub local: ReconcileTotals,
+    pub matches: bool,
+    /// The apps whose totals differ, for drilling down into a mismatched date
+    pub mismatched_apps: Vec<AppReconciliation>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReconcileReport {
+    pub start_date: Date,
+    pub end_date: Date,
+    pub dates: Vec<DateReconciliation>,
+    pub mismatched_dates: usize,
+}
+
+// Totals per (date, app)
+type TotalsByApp = BTreeMap<(Date, Option<i64>), ReconcileTotals>;
+
+/// Client of Steam's IPartnerFinancialsService.
+pub(crate) struct PartnerApi {
+    client: reqwest::Client,
+    base: String,
+}
+
+impl PartnerApi {
+    pub(crate) fn new() -> AppResult<Self> {
+        Self::with_base(API_BASE)
+    }
+
+    /// A client sending its requests to `base` instead of Steam, e.g. a mock server in tests.
+    pub(crate) fn with_base(base: &str) -> AppResult<Self> {
+        Ok(Self {
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()?,
+            base: base.trim_end_matches('/').to_string(),
+        })
+    }
+
+    // The key is part of the URL, so errors are stripped of it before they are logged or shown
+    pub(crate) async fn fetch_page(
+        &self,
+        key: &str,
+        date: Date,
+        highwatermark_id: u64,
+    ) -> AppResult<DetailedSalesResponse> {
+        let response = self
+            .client
+            .get(format!("{}/GetDetailedSales/v1", self.base))
+            .query(&[
+                ("key", key.to_string()),
+                ("date", date.to_string()),
+                ("highwatermark_id", highwatermark_id.to_string()),
+            ])
+            .send()
+            .await
+            .map_err(|e| AppError::from(e.without_url()))?;
+        let status = response.status();
+        if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+            return Err(AppError::InvalidApiKey(format!(
+                "Steam rejected the API key (HTTP {})",
+                status
+            )));
+        }
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(AppError::RateLimited(
+                "Steam is rate limiting requests; try again later".to_string(),
+            ));
+        }
+        if !status.is_success() {
+            return Err(AppError::Network(format!("Steam returned HTTP {}", status)));
+        }
+        let envelope: DetailedSalesEnvelope = response.json().await.map_err(|e| {
+            AppError::Network(format!(
+                "Unexpected response from Steam: {}",
+                e.without_url()
+            ))
+        })?;
+        Ok(envelope.response)
+    }
+}
+
+async fn fetch_steam_totals(
+    api: &PartnerApi,
+    key: &str,
+    start_date: Date,
+    end_date: Date,
+) -> AppResult<(TotalsByApp, HashMap<i64, String>)> {
+    let mut totals = TotalsByApp::new();
+    let mut app_names = HashMap::new();
+
+    let mut date = start_date;
+    while date <= end_date {
+        let mut highwatermark_id = 0;
+        for _ in 0..MAX_PAGES {
+            let page = api.fetch_page(key, date, highwatermark_id).await?;
+            for item in &page.results {
+                let entry = totals
+                    .entry((date, item.primary_appid.or(item.appid)))
+                    .or_default();
+                entry.add(&ReconcileTotals {
+                    gross_sales_usd: item.gross_sales_usd.unwrap_or(0.0),
+                    net_sales_usd: item.net_sales_usd.unwrap_or(0.0),
+                    net_units_sold: item.net_units_sold.unwrap_or(0),
+                    rows: 1,
+                });
+            }
+            for app in &page.app_info {
+                app_names.insert(app.appid, app.app_name.clone());
+            }
+            match page.next_page(highwatermark_id) {
+                Some(next) => highwatermark_id = next,
+                None => break,
+            }
+        }
+        date = date.add_days(1);
+    }
+    Ok((totals, app_names))
+}
+
+fn local_totals(conn: &Connection, start_date: Date, end_date: Date) -> AppResult<TotalsByApp> {
+    let app_id_col = get_app_id_column(conn);
+    let sql = format!(
+        "SELECT date, {app}, SUM(CAST(gross_sales_usd AS REAL)), SUM(CAST(net_sales_usd AS REAL)),
+                SUM(net_units_sold), COUNT(*)
+         FROM sales_data
+         WHERE date >= ?1 AND date <= ?2 AND {source} = 'steam'
+         GROUP BY date, {app}",
+        app = app_id_col,
+        source = get_source_column(conn)
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params![start_date, end_date], |r| {
+        Ok((
+            (r.get::<_, Date>(0)?, r.get::<_, Option<i64>>(1)?),
+            ReconcileTotals {
+                gross_sales_usd: r.get::<_, Option<f64>>(2)?.unwrap_or(0.0),
+                net_sales_usd: r.get::<_, Option<f64>>(3)?.unwrap_or(0.0),
+                net_units_sold: r.get::<_, Option<i64>>(4)?.unwrap_or(0),
+                rows: r.get::<_, i64>(5)? as u64,
+            },
+        ))
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+fn compare(
+    start_date: Date,
+    end_date: Date,
+    steam: &TotalsByApp,
+    local: &TotalsByApp,
+    app_names: &HashMap<i64, String>,
+) -> ReconcileReport {
+    let mut dates: BTreeMap<Date, DateReconciliation> = BTreeMap::new();
+    let mut date = start_date;
+    while date <= end_date {
+        dates.insert(
+            date,
+            DateReconciliation {
+                date,
+                steam: ReconcileTotals::default(),
+                local: ReconcileTotals::default(),
+                matches: true,
+                mismatched_apps: Vec::new(),
+            },
+        );
+        date = date.add_days(1);
+    }
+
+    let mut keys: Vec<&(Date, Option<i64>)> = steam.keys().chain(local.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        let (date, app_id) = *key;
+        let Some(day) = dates.get_mut(&date) else {
+            continue;
+        };
+        let steam_totals = steam.get(key).copied().unwrap_or_default();
+        let local_totals = local.get(key).copied().unwrap_or_default();
+        day.steam.add(&steam_totals);
+        day.local.add(&local_totals);
+        if !steam_totals.matches(&local_totals) {
+            day.matches = false;
+            day.mismatched_apps.push(AppReconciliation {
+                app_id,
+                app_name: app_id.and_then(|id| app_names.get(&id).cloned()),
+                steam: steam_totals,
+                local: local_totals,
+            });
+        }
+    }
+
+    let dates: Vec<DateReconciliation> = dates.into_values().collect();
+    ReconcileReport {
+        start_date,
+        end_date,
+        mismatched_dates: dates.iter().filter(|d| !d.matches).count(),
+        dates,
+    }
+}
+
+// ==================== Tauri Commands ====================
+
+/// Compares the Steam rows of a data source (the active one by default) with what Steam
+/// reports now, per date and app. Uses `apiKey` if given, otherwise the key the source was
+/// initialized with.
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn reconcile(
+    startDate: Date,
+    endDate: Date,
+    apiKey: Option<String>,
+    sourceId: Option<String>,
+) -> AppResult<ReconcileReport> {
+    if endDate < startDate {
+        return Err(AppError::InvalidInput(
+            "End date must not be before start date".to_string(),
+        ));
+    }
+    if endDate.days_since(startDate) >= MAX_RANGE_DAYS {
+        return Err(AppError::InvalidInput(format!(
+            "Reconcile at most {} days at a time",
+            MAX_RANGE_DAYS
+        )));
+    }
+    let source = sources::resolve_source(sourceId.as_deref())?;
+    let key = match apiKey.filter(|k| !k.trim().is_empty()) {
+        Some(key) => key.trim().to_string(),
+        None => source
+            .key_id
+            .as_deref()
+            .map(keys::get_key_secret)
+            .transpose()?
+            .flatten()
+            .ok_or_else(|| {
+                AppError::InvalidInput(
+                    "The API key of this data source isn't saved on this computer; enter it to reconcile"
+                        .to_string(),
+                )
+            })?,
+    };
+
+    let api = PartnerApi::new()?;
+    let fetched = fetch_steam_totals(&api, &key, startDate, endDate).await;
+    let usage = fetched.as_ref().map(|_| ()).map_err(AppError::clone);
+    let key_id = keys::key_fingerprint(&key);
+    if let Err(e) = keys::record_key_usage(&key_id, "reconcile", &usage) {
+        tracing::warn!("Failed to record key usage for reconcile: {}", e);
+    }
+    // An entered key that works and is the source's own is saved, so it isn't asked for again
+    if usage.is_ok() && source.key_id.as_deref() == Some(key_id.as_str()) {
+        if let Err(e) = keys::store_key_secret(&key_id, &key) {
+            tracing::warn!("Failed to save API key to the credential store: {}", e);
+        }
+    }
+    let (steam, app_names) = fetched?;
+
+    let db_path = source.db_path.clone();
+    let local = tokio::task::spawn_blocking(move || {
+        let path = Path::new(&db_path);
+        if !path.exists() {
+            return Err(AppError::NotFound(format!(
+                "Database not found at {}",
+                path.display()
+            )));
+        }
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        local_totals(&conn, startDate, endDate)
+    })
+    .await??;
+
+    presentation::present(compare(startDate, endDate, &steam, &local, &app_names))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::Query;
+    use axum::http::StatusCode;
+    use axum::response::{IntoResponse, Response};
+    use axum::routing::get;
+    use axum::{Json, Router};
+    use serde_json::{json, Value};
+
+    const KEY: &str = "test-key";
+
+    // Anonymized GetDetailedSales responses, kept as Steam sent them; see fixtures/steam
+    const FULL_PAGE: &str = include_str!("../fixtures/steam/get_detailed_sales.json");
+    const NUMERIC_PAGE: &str = include_str!("../fixtures/steam/get_detailed_sales_numeric.json");
+    const LAST_PAGE: &str = include_str!("../fixtures/steam/get_detailed_sales_last_page.json");
+    const NO_SALES: &str = include_str!("../fixtures/steam/get_detailed_sales_no_sales.json");
+    const BAD_AMOUNT: &str = include_str!("../fixtures/steam/get_detailed_sales_bad_amount.json");
+
+    fn parse(json: &str) -> serde_json::Result<DetailedSalesResponse> {
+        serde_json::from_str::<DetailedSalesEnvelope>(json).map(|envelope| envelope.response)
+    }
+
+    #[test]
+    fn parses_a_full_page() {
+        let page = parse(FULL_PAGE).unwrap();
+        assert_eq!(page.results.len(), 4);
+        assert_eq!(page.next_page(0), Some(1004));
+        assert_eq!(page.next_page(1004), None);
+        let sale = &page.results[1];
+        assert_eq!(sale.primary_appid, Some(10001));
+        assert_eq!(sale.net_units_sold, Some(59));
+        assert_eq!(sale.gross_sales_usd, Some(1199.40));
+        assert_eq!(sale.net_sales_usd, Some(1179.41));
+        // Retail key activations come without amounts
+        let activation = &page.results[3];
+        assert_eq!(
+            (activation.gross_sales_usd, activation.net_sales_usd),
+            (None, None)
+        );
+        assert_eq!(page.app_info[0].app_name, "Example Game");
+        assert_eq!(page.package_info[1].packageid, 20002);
+    }
+
+    #[test]
+    fn parses_numbers_sent_either_way() {
+        let page = parse(NUMERIC_PAGE).unwrap();
+        assert_eq!(page.next_page(0), Some(2002));
+        let sale = &page.results[0];
+        assert_eq!(sale.primary_appid, Some(10001));
+        assert_eq!(sale.net_units_sold, Some(3));
+        assert_eq!(sale.gross_sales_usd, Some(59.97));
+        let refund = &page.results[1];
+        assert_eq!((refund.gross_sales_usd, refund.net_sales_usd), (None, None));
+        assert_eq!(refund.net_units_sold, Some(-1));
+    }
+
+    #[test]
+    fn recognizes_the_last_page() {
+        for json in [LAST_PAGE, NO_SALES, "{}"] {
+            let page = parse(json).unwrap();
+            assert!(page.results.is_empty());
+            assert_eq!(page.next_page(0), None);
+        }
+    }
+
+    #[test]
+    fn rejects_amounts_that_are_not_numbers() {
+        let error = parse(BAD_AMOUNT).unwrap_err();
+        assert!(error.to_string().contains("'n/a'"), "{}", error);
+    }
+
+    fn date(s: &str) -> Date {
+        s.parse().unwrap()
+    }
+
+    fn sale(appid: i64, units: i64, gross: &str, net: &str) -> Value {
+        json!({
+            "primary_appid": appid,
+            "net_units_sold": units,
+            "gross_sales_usd": gross,
+            "net_sales_usd": net,
+        })
+    }
+
+    // GetDetailedSales of a mock Steam: 2024-03-01 takes three pages (the last one empty),
+    // 2024-03-02 has a return, and other dates have no sales. Any other key is rejected, and
+    // 2024-03-05 to 2024-03-07 fail with a rate limit, a server error and a response that
+    // isn't JSON.
+    async fn detailed_sales(Query(params): Query<HashMap<String, String>>) -> Response {
+        if params.get("key").map(String::as_str) != Some(KEY) {
+            return StatusCode::FORBIDDEN.into_response();
+        }
+        let date = params.get("date").map_or("", String::as_str);
+        let page = params.get("highwatermark_id").map_or("0", String::as_str);
+        let body = match (date, page) {
+            ("2024-03-05", _) => return StatusCode::TOO_MANY_REQUESTS.into_response(),
+            ("2024-03-06", _) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+            ("2024-03-07", _) => return "<html></html>".into_response(),
+            ("2024-03-01", "0") => json!({"response": {
+                "results": [sale(440, 2, "19.98", "17.00"), sale(440, 1, "9.99", "8.50")],
+                "max_id": "2",
+                "app_info": [{"appid": 440, "app_name": "Game"}],
+            }}),
+            ("2024-03-01", "2") => json!({"response": {
+                "results": [sale(570, 1, "1,004.99", "854.24")],
+                "max_id": 3,
+                "app_info": [{"appid": 570, "app_name": "Other Game"}],
+            }}),
+            ("2024-03-01", _) => json!({"response": {"results": [], "max_id": "3"}}),
+            ("2024-03-02", "0") => json!({"response": {
+                "results": [sale(440, -1, "-9.99", "-8.50")],
+                "max_id": "4",
+            }}),
+            _ => json!({"response": {}}),
+        };
+        Json(body).into_response()
+    }
+
+    // A client of a mock Steam served on a free local port
+    async fn mock_steam() -> PartnerApi {
+        let router = Router::new().route("/GetDetailedSales/v1", get(detailed_sales));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        PartnerApi::with_base(&base).unwrap()
+    }
+
+    fn cents(usd: f64) -> i64 {
+        (usd * 100.0).round() as i64
+    }
+
+    #[tokio::test]
+    async fn fetches_every_page_of_every_date() {
+        let api = mock_steam().await;
+        let (totals, app_names) =
+            fetch_steam_totals(&api, KEY, date("2024-03-01"), date("2024-03-03"))
+                .await
+                .unwrap();
+
+        let game = totals[&(date("2024-03-01"), Some(440))];
+        assert_eq!(
+            (cents(game.gross_sales_usd), cents(game.net_sales_usd)),
+            (2997, 2550)
+        );
+        assert_eq!((game.net_units_sold, game.rows), (3, 2));
+        // From the second page, with a thousands separator
+        let other = totals[&(date("2024-03-01"), Some(570))];
+        assert_eq!(cents(other.gross_sales_usd), 100499);
+        let returned = totals[&(date("2024-03-02"), Some(440))];
+        assert_eq!(returned.net_units_sold, -1);
+        assert_eq!(totals.len(), 3);
+        assert_eq!(app_names[&570], "Other Game");
+    }
+
+    #[tokio::test]
+    async fn maps_api_errors() {
+        let api = mock_steam().await;
+        let day = date("2024-03-01");
+        let fetch = |key: &'static str, d: Date| fetch_steam_totals(&api, key, d, d);
+
+        let rejected = fetch("other-key", day).await.unwrap_err();
+        assert!(
+            matches!(rejected, AppError::InvalidApiKey(_)),
+            "{:?}",
+            rejected
+        );
+        let limited = fetch(KEY, date("2024-03-05")).await.unwrap_err();
+        assert!(matches!(limited, AppError::RateLimited(_)), "{:?}", limited);
+        for failing in ["2024-03-06", "2024-03-07"] {
+            let failed = fetch(KEY, date(failing)).await.unwrap_err();
+            assert!(matches!(failed, AppError::Network(_)), "{:?}", failed);
+        }
+    }
+
+    #[tokio::test]
+    async fn reports_dates_missing_locally() {
+        let api = mock_steam().await;
+        let (start, end) = (date("2024-03-01"), date("2024-03-03"));
+        let (steam, app_names) = fetch_steam_totals(&api, KEY, start, end).await.unwrap();
+
+        // The local copy has 2024-03-01 as Steam reports it, but not the return a day later
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE sales_data (date TEXT, primary_appid INTEGER, gross_sales_usd TEXT,
+                                      net_sales_usd TEXT, net_units_sold INTEGER);
+             INSERT INTO sales_data VALUES
+                ('2024-03-01', 440, '19.98', '17.00', 2),
+                ('2024-03-01', 440, '9.99', '8.50', 1),
+                ('2024-03-01', 570, '1004.99', '854.24', 1);",
+        )
+        .unwrap();
+        let local = local_totals(&conn, start, end).unwrap();
+
+        let report = compare(start, end, &steam, &local, &app_names);
+        assert_eq!(report.dates.len(), 3);
+        assert_eq!(report.mismatched_dates, 1);
+        assert!(report.dates[0].matches && report.dates[2].matches);
+        let missed = &report.dates[1];
+        assert_eq!(missed.date, date("2024-03-02"));
+        assert_eq!(missed.mismatched_apps.len(), 1);
+        assert_eq!(missed.mismatched_apps[0].app_name.as_deref(), Some("Game"));
+        assert_eq!(missed.mismatched_apps[0].local.rows, 0);
+    }
+}
@@ -0,0 +1,87 @@
+// Incremental reads of sales_data for external ETL
+//
+// External tools that copy the local database into a warehouse page through sales_data in
+// insertion order: every page returns the rows after a rowid cursor, with all columns as they
+// are stored, and the cursor to pass next. A tool keeps the last cursor and only reads rows added
+// since. Rows replaced by a later fetch are re-inserted with a new rowid and come again; deleted
+// rows don't, so `total_rows` is returned for spotting deletions and starting over from 0.
+//
+// The command is masked in presentation mode like the other queries; the API server's
+// `/api/sales/since` returns real amounts.
+
+use crate::presentation;
+use crate::quarantine::to_json;
+use crate::queries::get_connection;
+use crate::types::{AppError, AppResult};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_LIMIT: u32 = 1000;
+const MAX_LIMIT: u32 = 10_000;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SalesPage {
+    /// Rows after the cursor by rowid, as column name to value, with their `rowid`
+    pub rows: Vec<serde_json::Map<String, serde_json::Value>>,
+    /// Cursor for the next page: the last returned rowid, or the given cursor without rows
+    pub next_cursor: i64,
+    /// True if rows after `next_cursor` already exist
+    pub has_more: bool,
+    /// Rows in sales_data; fewer than a replica holds means rows were deleted
+    pub total_rows: u64,
+}
+
+pub fn get_sales_since(cursor: i64, limit: Option<u32>) -> AppResult<SalesPage> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT);
+    if limit == 0 || limit > MAX_LIMIT {
+        return Err(AppError::InvalidInput(format!(
+            "Limit must be between 1 and {}",
+            MAX_LIMIT
+        )));
+    }
+    let conn = get_connection()?;
+
+    // One more row than asked for tells whether another page follows
+    let mut stmt =
+        conn.prepare("SELECT rowid, * FROM sales_data WHERE rowid > ?1 ORDER BY rowid LIMIT ?2")?;
+    let names: Vec<String> = stmt
+        .column_names()
+        .iter()
+        .skip(1)
+        .map(|n| n.to_string())
+        .collect();
+    let mut rows = stmt
+        .query_map(params![cursor, limit as i64 + 1], |row| {
+            let rowid: i64 = row.get(0)?;
+            let mut object = serde_json::Map::new();
+            object.insert("rowid".to_string(), rowid.into());
+            for (i, name) in names.iter().enumerate() {
+                object.insert(name.clone(), to_json(row.get_ref(i + 1)?));
+            }
+            Ok((rowid, object))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let has_more = rows.len() > limit as usize;
+    rows.truncate(limit as usize);
+    let total_rows: i64 = conn.query_row("SELECT COUNT(*) FROM sales_data", [], |r| r.get(0))?;
+    Ok(SalesPage {
+        next_cursor: rows.last().map_or(cursor, |(rowid, _)| *rowid),
+        rows: rows.into_iter().map(|(_, object)| object).collect(),
+        has_more,
+        total_rows: total_rows as u64,
+    })
+}
+
+// ==================== Tauri Commands ====================
+
+/// Up to `limit` sales_data rows after the rowid `rowidCursor` (0 for the first page).
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn query_sales_since(rowidCursor: i64, limit: Option<u32>) -> AppResult<SalesPage> {
+    tokio::task::spawn_blocking(move || {
+        get_sales_since(rowidCursor, limit).and_then(presentation::present)
+    })
+    .await?
+}
@@ -0,0 +1,203 @@
+// Reporting views for BI tools
+//
+// The CLI's sales_data stores amounts as TEXT, names the app id column differently across
+// versions and keeps product and country names in separate lookup tables, so a BI tool (Metabase,
+// Power BI, Excel's ODBC import, ...) attached to the database file would have to rediscover all
+// of that. `create_reporting_views` creates three views in the active sales database instead:
+//
+//   v_sales   one row per sales_data row, with numeric columns cast to INTEGER or REAL and the
+//             app, package and country names joined in
+//   v_daily   sales and returns per day, as in the dashboard (no grants or activations)
+//   v_by_app  the same per app, with its first and last sale date
+//
+// Every column is a CAST, so tools that read the declared type of a view's columns see numbers
+// instead of untyped expressions. The views read sales_data as stored, without the quarantined
+// rows; adjustments are not applied. Which columns, lookup tables and quarantined rows a
+// database has is decided when the views are created, so they are created again when a name
+// refresh adds its tables or the quarantine changes.
+
+use crate::countries;
+use crate::database::{begin_write, get_database_path, BUSY_TIMEOUT};
+use crate::lookup_refresh::{app_names_table, package_names_table};
+use crate::quarantine;
+use crate::queries::{get_app_id_column, get_source_column, line_item_category_expr};
+use crate::schema::column_exists;
+use crate::types::{AppError, AppResult};
+use rusqlite::{Connection, OpenFlags, Result as SqliteResult};
+use serde::Serialize;
+use tracing::{info, warn};
+
+/// Names of the views, in the order they are created
+pub const VIEWS: [&str; 3] = ["v_sales", "v_daily", "v_by_app"];
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportingViews {
+    pub database_path: String,
+    pub views: Vec<String>,
+}
+
+// `column` of sales_data cast to `sql_type`, or a NULL of that type if the database lacks it
+fn optional_column(conn: &Connection, column: &str, sql_type: &str) -> String {
+    if column_exists(conn, "sales_data", column) {
+        format!("CAST(s.{} AS {})", column, sql_type)
+    } else {
+        format!("CAST(NULL AS {})", sql_type)
+    }
+}
+
+fn sales_view_sql(conn: &Connection) -> String {
+    let app_id_col = get_app_id_column(conn);
+    let has = |column: &str| column_exists(conn, "sales_data", column);
+    let discount = match (has("total_discount_percentage"), has("discount_percentage")) {
+        (true, true) => "COALESCE(s.total_discount_percentage, s.discount_percentage)",
+        (true, false) => "s.total_discount_percentage",
+        (false, true) => "s.discount_percentage",
+        (false, false) => "NULL",
+    };
+    let (app_name, app_join) = match app_names_table(conn) {
+        Some(apps) => (
+            "a.app_name",
+            format!("LEFT JOIN {} a ON a.appid = s.{}", apps, app_id_col),
+        ),
+        None => ("NULL", String::new()),
+    };
+    let (package_name, package_join) = match package_names_table(conn) {
+        Some(packages) => (
+            "p.package_name",
+            format!("LEFT JOIN {} p ON p.packageid = s.packageid", packages),
+        ),
+        None => ("NULL", String::new()),
+    };
+    let quarantined = match quarantine::exclusion_for_database(conn, "s") {
+        Some(condition) => format!("WHERE {}", condition),
+        None => String::new(),
+    };
+
+    format!(
+        "CREATE VIEW v_sales AS
+         SELECT
+            CAST(s.date AS TEXT) AS date,
+            CAST(s.{app_id} AS INTEGER) AS app_id,
+            CAST({app_name} AS TEXT) AS app_name,
+            CAST(s.packageid AS INTEGER) AS package_id,
+            CAST({package_name} AS TEXT) AS package_name,
+            CAST(s.line_item_type AS TEXT) AS line_item_type,
+            CAST({category} AS TEXT) AS line_item_category,
+            CAST({source} AS TEXT) AS source,
+            CAST(s.country_code AS TEXT) AS country_code,
+            CAST(c.country_name AS TEXT) AS country_name,
+            CAST(c.region AS TEXT) AS region,
+            CAST(s.platform AS TEXT) AS platform,
+            CAST(s.currency AS TEXT) AS currency,
+            CAST(s.gross_units_sold AS INTEGER) AS gross_units_sold,
+            CAST(s.gross_units_returned AS INTEGER) AS gross_units_returned,
+            CAST(s.net_units_sold AS INTEGER) AS net_units_sold,
+            CAST(s.gross_sales_usd AS REAL) AS gross_sales_usd,
+            {gross_returns} AS gross_returns_usd,
+            CAST(s.net_sales_usd AS REAL) AS net_sales_usd,
+            {net_tax} AS net_tax_usd,
+            CAST({discount} AS REAL) AS discount_percentage
+         FROM sales_data s
+         {app_join}
+         {package_join}
+         LEFT JOIN lookup_countries c ON c.country_code = s.country_code
+         {quarantined}",
+        app_id = app_id_col,
+        app_name = app_name,
+        package_name = package_name,
+        category = line_item_category_expr(conn),
+        source = get_source_column(conn),
+        gross_returns = optional_column(conn, "gross_returns_usd", "REAL"),
+        net_tax = optional_column(conn, "net_tax_usd", "REAL"),
+        discount = discount,
+        app_join = app_join,
+        package_join = package_join,
+        quarantined = quarantined,
+    )
+}
+
+// Totals of the sales and returns in v_sales; SUM and COUNT are cast to keep their types
+const TOTALS: &str = "CAST(SUM(gross_units_sold) AS INTEGER) AS gross_units_sold,
+            CAST(SUM(gross_units_returned) AS INTEGER) AS gross_units_returned,
+            CAST(SUM(net_units_sold) AS INTEGER) AS net_units_sold,
+            CAST(SUM(gross_sales_usd) AS REAL) AS gross_sales_usd,
+            CAST(SUM(gross_returns_usd) AS REAL) AS gross_returns_usd,
+            CAST(SUM(net_sales_usd) AS REAL) AS net_sales_usd,
+            CAST(SUM(net_tax_usd) AS REAL) AS net_tax_usd,
+            CAST(COUNT(*) AS INTEGER) AS record_count";
+
+fn create_views(conn: &Connection) -> SqliteResult<()> {
+    countries::ensure_lookup_table(conn)?;
+    let tx = begin_write(conn)?;
+    for view in VIEWS.iter().rev() {
+        tx.execute_batch(&format!("DROP VIEW IF EXISTS {}", view))?;
+    }
+    tx.execute_batch(&sales_view_sql(&tx))?;
+    tx.execute_batch(&format!(
+        "CREATE VIEW v_daily AS
+         SELECT
+            CAST(date AS TEXT) AS date,
+            {totals}
+         FROM v_sales
+         WHERE line_item_category IN ('sale', 'return')
+         GROUP BY date;
+         CREATE VIEW v_by_app AS
+         SELECT
+            CAST(app_id AS INTEGER) AS app_id,
+            CAST(MAX(app_name) AS TEXT) AS app_name,
+            CAST(MIN(date) AS TEXT) AS first_sale_date,
+            CAST(MAX(date) AS TEXT) AS last_sale_date,
+            {totals}
+         FROM v_sales
+         WHERE line_item_category IN ('sale', 'return')
+         GROUP BY app_id;",
+        totals = TOTALS
+    ))?;
+    tx.commit()
+}
+
+/// Creates the views again if they were created before, e.g. after sales_data gained a column.
+/// Failures are logged; the views are only a convenience for other tools.
+pub(crate) fn refresh_if_created(conn: &Connection) {
+    let created = conn
+        .query_row(
+            "SELECT 1 FROM sqlite_master WHERE type = 'view' AND name = 'v_sales'",
+            [],
+            |_| Ok(()),
+        )
+        .is_ok();
+    if created {
+        if let Err(e) = create_views(conn) {
+            warn!("Failed to recreate the reporting views: {}", e);
+        }
+    }
+}
+
+// ==================== Tauri Commands ====================
+
+/// Creates (or recreates) the reporting views in the active sales database.
+#[tauri::command]
+pub async fn create_reporting_views() -> AppResult<ReportingViews> {
+    tokio::task::spawn_blocking(|| {
+        let db_path = get_database_path();
+        if !db_path.exists() {
+            return Err(AppError::NotFound(
+                "The sales database doesn't exist yet. Fetch data first.".to_string(),
+            ));
+        }
+        // Not through get_connection, which may read a snapshot copy during a sync
+        let conn = Connection::open_with_flags(
+            &db_path,
+            OpenFlags::default().difference(OpenFlags::SQLITE_OPEN_CREATE),
+        )?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        create_views(&conn)?;
+        info!("Created the reporting views in {}", db_path.display());
+        Ok(ReportingViews {
+            database_path: db_path.to_string_lossy().to_string(),
+            views: VIEWS.iter().map(|view| view.to_string()).collect(),
+        })
+    })
+    .await?
+}
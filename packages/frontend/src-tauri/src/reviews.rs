@@ -0,0 +1,456 @@
+// Steam reviews next to sales
+//
+// For each app of the active source, new positive and negative reviews per day are read from the
+// store's review histogram (which has the last 30 days day by day) and the review totals from the
+// appreviews summary. They are kept in `reviews_daily` in the app database per data source, so
+// they survive the CLI recreating the sales database, and review velocity can be lined up with
+// units sold; review spikes often explain sales anomalies. Earlier versions kept the table in the
+// sales database; it is moved over the first time reviews are used.
+//
+// Apps are updated at most every REFRESH_AFTER_HOURS, one request at a time, after each fetch.
+
+use crate::database::{self, begin_write, get_app_connection};
+use crate::presentation;
+use crate::queries::{self, get_app_id_column, get_connection};
+use crate::sources;
+use crate::types::{AppError, AppResult, Date};
+use rusqlite::{params, Connection, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Once;
+use std::time::Duration;
+use tracing::{info, warn};
+
+const STORE_URL: &str = "https://store.steampowered.com";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+const REQUEST_DELAY: Duration = Duration::from_millis(1500);
+const REFRESH_AFTER_HOURS: u32 = 20;
+// Days a correlation needs before it is reported
+const MIN_CORRELATION_DAYS: usize = 7;
+
+// Only one update pass at a time
+static UPDATING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewDay {
+    pub date: String,
+    pub positive: i64,
+    pub negative: i64,
+    /// Share of the day's reviews that were positive; None without reviews
+    pub positive_ratio: Option<f64>,
+    pub units: i64,
+    pub revenue: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewsVsSales {
+    pub app_id: i64,
+    pub total_positive: Option<i64>,
+    pub total_negative: Option<i64>,
+    /// e.g. "Very Positive"
+    pub review_score: Option<String>,
+    pub days: Vec<ReviewDay>,
+    /// Pearson correlation of reviews per day with units sold the same day
+    pub correlation: Option<f64>,
+}
+
+// ==================== Store responses ====================
+
+#[derive(Debug, Deserialize)]
+struct HistogramResponse {
+    results: Option<HistogramResults>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HistogramResults {
+    #[serde(default)]
+    rollup_type: String,
+    #[serde(default)]
+    rollups: Vec<HistogramDay>,
+    #[serde(default)]
+    recent: Vec<HistogramDay>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HistogramDay {
+    /// Unix time of the day's start (UTC)
+    date: i64,
+    recommendations_up: i64,
+    recommendations_down: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReviewsResponse {
+    query_summary: Option<ReviewSummary>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReviewSummary {
+    total_positive: Option<i64>,
+    total_negative: Option<i64>,
+    review_score_desc: Option<String>,
+}
+
+// Columns copied from the sales database's reviews_daily table, which had no source_id
+const MOVED_COLUMNS: &[&str] = &[
+    "app_id",
+    "date",
+    "positive",
+    "negative",
+    "total_positive",
+    "total_negative",
+    "review_score",
+    "updated_at",
+];
+
+static MOVE_FROM_SALES_DATABASES: Once = Once::new();
+
+fn ensure_table(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS reviews_daily (
+            source_id TEXT NOT NULL,
+            app_id INTEGER NOT NULL,
+            date TEXT NOT NULL,
+            positive INTEGER NOT NULL DEFAULT 0,
+            negative INTEGER NOT NULL DEFAULT 0,
+            total_positive INTEGER,
+            total_negative INTEGER,
+            review_score TEXT,
+            updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+            PRIMARY KEY (source_id, app_id, date)
+        );",
+    )
+}
+
+fn reviews_connection() -> SqliteResult<Connection> {
+    let conn = get_app_connection()?;
+    ensure_table(&conn)?;
+    MOVE_FROM_SALES_DATABASES.call_once(|| {
+        if let Err(e) =
+            database::move_table_from_sales_databases(&conn, "reviews_daily", MOVED_COLUMNS)
+        {
+            warn!("Failed to move reviews to the app database: {}", e);
+        }
+    });
+    Ok(conn)
+}
+
+async fn get_json<T: serde::de::DeserializeOwned>(
+    client: &reqwest::Client,
+    url: &str,
+) -> AppResult<T> {
+    let response = client.get(url).send().await?;
+    if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Err(AppError::RateLimited(
+            "The Steam store is rate limiting review requests".to_string(),
+        ));
+    }
+    if !response.status().is_success() {
+        return Err(AppError::Network(format!(
+            "Steam store returned HTTP {}",
+            response.status()
+        )));
+    }
+    response
+        .json()
+        .await
+        .map_err(|e| AppError::Network(format!("Unexpected reviews response: {}", e)))
+}
+
+fn save_reviews(
+    source_id: &str,
+    app_id: i64,
+    days: &[HistogramDay],
+    summary: Option<ReviewSummary>,
+) -> SqliteResult<()> {
+    let conn = reviews_connection()?;
+    let tx = begin_write(&conn)?;
+    for day in days {
+        tx.execute(
+            "INSERT INTO reviews_daily (source_id, app_id, date, positive, negative)
+             VALUES (?1, ?2, date(?3, 'unixepoch'), ?4, ?5)
+             ON CONFLICT(source_id, app_id, date) DO UPDATE SET
+                positive = excluded.positive,
+                negative = excluded.negative,
+                updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now')",
+            params![
+                source_id,
+                app_id,
+                day.date,
+                day.recommendations_up,
+                day.recommendations_down
+            ],
+        )?;
+    }
+    if let Some(summary) = summary {
+        tx.execute(
+            "INSERT INTO reviews_daily
+                (source_id, app_id, date, total_positive, total_negative, review_score)
+             VALUES (?1, ?2, date('now'), ?3, ?4, ?5)
+             ON CONFLICT(source_id, app_id, date) DO UPDATE SET
+                total_positive = excluded.total_positive,
+                total_negative = excluded.total_negative,
+                review_score = excluded.review_score,
+                updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now')",
+            params![
+                source_id,
+                app_id,
+                summary.total_positive,
+                summary.total_negative,
+                summary.review_score_desc
+            ],
+        )?;
+    }
+    tx.commit()
+}
+
+async fn update_app(client: &reqwest::Client, source_id: &str, app_id: i64) -> AppResult<()> {
+    let histogram: HistogramResponse = get_json(
+        client,
+        &format!(
+            "{}/appreviewhistogram/{}?l=english&review_score_preference=0",
+            STORE_URL, app_id
+        ),
+    )
+    .await?;
+    tokio::time::sleep(REQUEST_DELAY).await;
+    let reviews: ReviewsResponse = get_json(
+        client,
+        &format!(
+            "{}/appreviews/{}?json=1&language=all&purchase_type=all&num_per_page=0",
+            STORE_URL, app_id
+        ),
+    )
+    .await?;
+
+    let mut days = Vec::new();
+    if let Some(results) = histogram.results {
+        // Older history is only day by day for apps with few reviews
+        if results.rollup_type == "day" {
+            days.extend(results.rollups);
+        }
+        days.extend(results.recent);
+    }
+    let source_id = source_id.to_string();
+    tokio::task::spawn_blocking(move || {
+        save_reviews(&source_id, app_id, &days, reviews.query_summary)
+    })
+    .await??;
+    Ok(())
+}
+
+// Apps of the active source not updated within REFRESH_AFTER_HOURS, or the given ones
+fn apps_to_update(source_id: &str, app_ids: Option<Vec<i64>>) -> AppResult<Vec<i64>> {
+    if let Some(ids) = app_ids {
+        return Ok(ids);
+    }
+    let app_ids = queries::get_apps_lookup()?
+        .into_iter()
+        .map(|app| app.app_id);
+    let conn = reviews_connection()?;
+    let mut stmt = conn.prepare(
+        "SELECT app_id FROM reviews_daily WHERE source_id = ?1 GROUP BY app_id
+         HAVING (julianday('now') - julianday(MAX(updated_at))) * 24 < ?2",
+    )?;
+    let fresh = stmt
+        .query_map(params![source_id, REFRESH_AFTER_HOURS], |r| {
+            r.get::<_, i64>(0)
+        })?
+        .collect::<SqliteResult<HashSet<_>>>()?;
+    Ok(app_ids.filter(|id| !fresh.contains(id)).collect())
+}
+
+/// Updates the reviews of the given apps, or of the active source's apps that are due.
+/// Returns the number of apps updated.
+pub async fn update_reviews(app_ids: Option<Vec<i64>>) -> AppResult<u32> {
+    if UPDATING.swap(true, Ordering::SeqCst) {
+        return Err(AppError::Busy(
+            "Reviews are already being updated".to_string(),
+        ));
+    }
+    let result = update_apps(app_ids).await;
+    UPDATING.store(false, Ordering::SeqCst);
+    result
+}
+
+async fn update_apps(app_ids: Option<Vec<i64>>) -> AppResult<u32> {
+    // The source is fixed for the whole pass, even if another one is activated meanwhile
+    let source_id = sources::active_source_id();
+    let app_ids = {
+        let source_id = source_id.clone();
+        tokio::task::spawn_blocking(move || apps_to_update(&source_id, app_ids)).await??
+    };
+    if app_ids.is_empty() {
+        return Ok(0);
+    }
+    info!("Updating reviews of {} app(s)", app_ids.len());
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()?;
+    let mut updated = 0;
+    for (i, app_id) in app_ids.into_iter().enumerate() {
+        if i > 0 {
+            tokio::time::sleep(REQUEST_DELAY).await;
+        }
+        match update_app(&client, &source_id, app_id).await {
+            Ok(()) => updated += 1,
+            // Later apps would be rejected too; they are picked up by the next pass
+            Err(e @ AppError::RateLimited(_)) => {
+                warn!("Stopping review update after {} app(s): {}", updated, e);
+                break;
+            }
+            Err(e) => warn!("Failed to update reviews of app {}: {}", app_id, e),
+        }
+    }
+    Ok(updated)
+}
+
+/// Starts a review update without waiting for it, e.g. after a fetch.
+pub fn update_in_background() {
+    tauri::async_runtime::spawn(async {
+        match update_reviews(None).await {
+            Ok(0) | Err(AppError::Busy(_)) => {}
+            Ok(updated) => info!("Updated reviews of {} app(s)", updated),
+            Err(e) => warn!("Failed to update reviews: {}", e),
+        }
+    });
+}
+
+/// Pearson correlation coefficient; None when either series is constant.
+pub(crate) fn pearson(xs: &[f64], ys: &[f64]) -> Option<f64> {
+    let n = xs.len() as f64;
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = ys.iter().sum::<f64>() / n;
+    let mut covariance = 0.0;
+    let mut variance_x = 0.0;
+    let mut variance_y = 0.0;
+    for (x, y) in xs.iter().zip(ys) {
+        covariance += (x - mean_x) * (y - mean_y);
+        variance_x += (x - mean_x).powi(2);
+        variance_y += (y - mean_y).powi(2);
+    }
+    if variance_x == 0.0 || variance_y == 0.0 {
+        return None;
+    }
+    Some(covariance / (variance_x.sqrt() * variance_y.sqrt()))
+}
+
+/// Daily reviews of an app next to its sales. Without dates, covers the days reviews are stored
+/// for.
+pub fn get_reviews_vs_sales(
+    app_id: i64,
+    start_date: Option<Date>,
+    end_date: Option<Date>,
+) -> SqliteResult<ReviewsVsSales> {
+    let conn = get_connection()?;
+    let reviews = reviews_connection()?;
+    let source_id = sources::active_source_id();
+    let app_id_col = get_app_id_column(&conn);
+
+    let (total_positive, total_negative, review_score) = reviews
+        .query_row(
+            "SELECT total_positive, total_negative, review_score FROM reviews_daily
+         WHERE source_id = ?1 AND app_id = ?2 AND total_positive IS NOT NULL
+         ORDER BY date DESC LIMIT 1",
+            params![source_id, app_id],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+        )
+        .or_else(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => Ok((None, None, None)),
+            e => Err(e),
+        })?;
+
+    // Reviews and sales are in different databases, so the days are joined here
+    let sql = format!(
+        "SELECT date, COALESCE(SUM(net_units_sold), 0),
+            COALESCE(SUM(CAST(gross_sales_usd AS REAL)), 0)
+         FROM sales_data
+         WHERE {col} = ?1
+           AND (?2 IS NULL OR date >= ?2)
+           AND (?3 IS NULL OR date <= ?3)
+         GROUP BY date",
+        col = app_id_col
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let sales = stmt
+        .query_map(params![app_id, start_date, end_date], |r| {
+            Ok((
+                r.get::<_, String>(0)?,
+                (r.get::<_, i64>(1)?, r.get::<_, f64>(2)?),
+            ))
+        })?
+        .collect::<SqliteResult<HashMap<_, _>>>()?;
+
+    let mut stmt = reviews.prepare(
+        "SELECT date, positive, negative FROM reviews_daily
+         WHERE source_id = ?1 AND app_id = ?2
+           AND (?3 IS NULL OR date >= ?3)
+           AND (?4 IS NULL OR date <= ?4)
+         ORDER BY date",
+    )?;
+    let days = stmt
+        .query_map(params![source_id, app_id, start_date, end_date], |r| {
+            let date: String = r.get(0)?;
+            let positive: i64 = r.get(1)?;
+            let negative: i64 = r.get(2)?;
+            let (units, revenue) = sales.get(&date).copied().unwrap_or((0, 0.0));
+            Ok(ReviewDay {
+                date,
+                positive,
+                negative,
+                positive_ratio: match positive + negative {
+                    0 => None,
+                    total => Some(positive as f64 / total as f64),
+                },
+                units,
+                revenue,
+            })
+        })?
+        .collect::<SqliteResult<Vec<_>>>()?;
+
+    let correlation = if days.len() >= MIN_CORRELATION_DAYS {
+        let reviews: Vec<f64> = days
+            .iter()
+            .map(|d| (d.positive + d.negative) as f64)
+            .collect();
+        let units: Vec<f64> = days.iter().map(|d| d.units as f64).collect();
+        pearson(&reviews, &units)
+    } else {
+        None
+    };
+
+    Ok(ReviewsVsSales {
+        app_id,
+        total_positive,
+        total_negative,
+        review_score,
+        days,
+        correlation,
+    })
+}
+
+// ==================== Tauri Commands ====================
+
+/// Reads reviews from the store now, for the given apps or the active source's apps that are due.
+/// Returns the number of apps updated.
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn refresh_reviews(appIds: Option<Vec<i64>>) -> AppResult<u32> {
+    update_reviews(appIds).await
+}
+
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn query_reviews_vs_sales(
+    appId: i64,
+    startDate: Option<Date>,
+    endDate: Option<Date>,
+) -> AppResult<ReviewsVsSales> {
+    tokio::task::spawn_blocking(move || get_reviews_vs_sales(appId, startDate, endDate))
+        .await?
+        .map_err(AppError::from)
+        .and_then(presentation::present)
+}
@@ -0,0 +1,349 @@
+// Royalty and revenue-share splits
+//
+// A royalty rule gives a party (developer, publisher, composer, ...) a percentage of an app's or
+// a package's net revenue. A rule with a recoup threshold only starts paying once the product's
+// lifetime net revenue has passed the threshold, e.g. a developer royalty that starts after the
+// publisher has recouped its advance; the share then applies to the revenue above it. Whatever
+// the rules don't give away is reported as unallocated.
+//
+// Rules apply to the revenue of the product they name, so an app and one of its packages should
+// not both have rules, or that package's revenue is split twice.
+
+use crate::presentation;
+use crate::queries::{get_app_id_column, get_connection, get_write_connection};
+use crate::schema::table_exists;
+use crate::types::{AppError, AppResult, Date};
+use rusqlite::{params, Connection, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoyaltyRule {
+    pub id: i64,
+    /// Exactly one of app_id and package_id is set
+    pub app_id: Option<i64>,
+    pub package_id: Option<i64>,
+    pub party: String,
+    /// Share of net revenue, 0-100
+    pub percentage: f64,
+    /// Lifetime net revenue (USD) the product must pass before the share starts
+    pub recoup_threshold: Option<f64>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoyaltyRuleInput {
+    pub app_id: Option<i64>,
+    pub package_id: Option<i64>,
+    pub party: String,
+    pub percentage: f64,
+    pub recoup_threshold: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoyaltyPeriod {
+    pub start_date: Date,
+    pub end_date: Date,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoyaltyLine {
+    pub rule_id: i64,
+    pub party: String,
+    pub percentage: f64,
+    pub recoup_threshold: Option<f64>,
+    /// Net revenue of the period the share applies to (after recoupment)
+    pub eligible_revenue: f64,
+    pub amount: f64,
+    /// Whether the threshold had been passed by the end of the period
+    pub recouped: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProductStatement {
+    pub app_id: Option<i64>,
+    pub package_id: Option<i64>,
+    pub net_revenue: f64,
+    /// Up to the end of the period
+    pub lifetime_net_revenue: f64,
+    pub lines: Vec<RoyaltyLine>,
+    pub unallocated: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartyTotal {
+    pub party: String,
+    pub amount: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoyaltyStatement {
+    pub period: RoyaltyPeriod,
+    pub products: Vec<ProductStatement>,
+    /// Sorted by party name
+    pub parties: Vec<PartyTotal>,
+}
+
+// (app_id, package_id) of a rule
+type Product = (Option<i64>, Option<i64>);
+
+pub(crate) fn ensure_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS royalty_rules (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            app_id INTEGER,
+            package_id INTEGER,
+            party TEXT NOT NULL,
+            percentage REAL NOT NULL,
+            recoup_threshold REAL,
+            created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+        );",
+    )
+}
+
+fn validate(conn: &Connection, input: &RoyaltyRuleInput, id: Option<i64>) -> AppResult<()> {
+    if input.app_id.is_some() == input.package_id.is_some() {
+        return Err(AppError::InvalidInput(
+            "A royalty rule needs either an app or a package".to_string(),
+        ));
+    }
+    if input.party.trim().is_empty() {
+        return Err(AppError::InvalidInput(
+            "Party must not be empty".to_string(),
+        ));
+    }
+    if !(input.percentage > 0.0 && input.percentage <= 100.0) {
+        return Err(AppError::InvalidInput(
+            "percentage must be greater than 0 and at most 100".to_string(),
+        ));
+    }
+    if input
+        .recoup_threshold
+        .is_some_and(|t| t.is_nan() || t < 0.0)
+    {
+        return Err(AppError::InvalidInput(
+            "recoup_threshold must not be negative".to_string(),
+        ));
+    }
+
+    // Shares of one product can't add up to more than all of its revenue
+    let others: f64 = conn.query_row(
+        "SELECT COALESCE(SUM(percentage), 0) FROM royalty_rules
+         WHERE app_id IS ?1 AND package_id IS ?2 AND id IS NOT ?3",
+        params![input.app_id, input.package_id, id],
+        |r| r.get(0),
+    )?;
+    if others + input.percentage > 100.0 + 1e-9 {
+        return Err(AppError::InvalidInput(format!(
+            "The product's other rules already give away {}%, so at most {}% is left",
+            others,
+            100.0 - others
+        )));
+    }
+    Ok(())
+}
+
+fn row_to_rule(r: &rusqlite::Row) -> SqliteResult<RoyaltyRule> {
+    Ok(RoyaltyRule {
+        id: r.get(0)?,
+        app_id: r.get(1)?,
+        package_id: r.get(2)?,
+        party: r.get(3)?,
+        percentage: r.get(4)?,
+        recoup_threshold: r.get(5)?,
+        created_at: r.get(6)?,
+    })
+}
+
+const RULE_COLUMNS: &str =
+    "id, app_id, package_id, party, percentage, recoup_threshold, created_at";
+
+fn list_rules(conn: &Connection) -> SqliteResult<Vec<RoyaltyRule>> {
+    if !table_exists(conn, "royalty_rules") {
+        return Ok(Vec::new());
+    }
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM royalty_rules ORDER BY app_id, package_id, id",
+        RULE_COLUMNS
+    ))?;
+    let rows = stmt.query_map([], row_to_rule)?;
+    rows.collect()
+}
+
+pub fn list_royalty_rules_all() -> SqliteResult<Vec<RoyaltyRule>> {
+    let conn = get_connection()?;
+    list_rules(&conn)
+}
+
+pub fn insert_rule(input: &RoyaltyRuleInput) -> AppResult<i64> {
+    let conn = get_write_connection()?;
+    ensure_tables(&conn)?;
+    validate(&conn, input, None)?;
+    conn.execute(
+        "INSERT INTO royalty_rules (app_id, package_id, party, percentage, recoup_threshold)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            input.app_id,
+            input.package_id,
+            input.party.trim(),
+            input.percentage,
+            input.recoup_threshold
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn update_rule_by_id(id: i64, input: &RoyaltyRuleInput) -> AppResult<bool> {
+    let conn = get_write_connection()?;
+    ensure_tables(&conn)?;
+    validate(&conn, input, Some(id))?;
+    let changed = conn.execute(
+        "UPDATE royalty_rules SET app_id = ?2, package_id = ?3, party = ?4, percentage = ?5,
+            recoup_threshold = ?6
+         WHERE id = ?1",
+        params![
+            id,
+            input.app_id,
+            input.package_id,
+            input.party.trim(),
+            input.percentage,
+            input.recoup_threshold
+        ],
+    )?;
+    Ok(changed > 0)
+}
+
+pub fn delete_rule_by_id(id: i64) -> SqliteResult<bool> {
+    let conn = get_write_connection()?;
+    ensure_tables(&conn)?;
+    Ok(conn.execute("DELETE FROM royalty_rules WHERE id = ?1", [id])? > 0)
+}
+
+// Net revenue of a product before the period and during it
+fn product_revenue(
+    conn: &Connection,
+    column: &str,
+    id: i64,
+    period: RoyaltyPeriod,
+) -> SqliteResult<(f64, f64)> {
+    conn.query_row(
+        &format!(
+            "SELECT
+                COALESCE(SUM(CASE WHEN date < ?2 THEN CAST(net_sales_usd AS REAL) END), 0),
+                COALESCE(SUM(CASE WHEN date >= ?2 THEN CAST(net_sales_usd AS REAL) END), 0)
+             FROM sales_data WHERE {} = ?1 AND date <= ?3",
+            column
+        ),
+        params![id, period.start_date, period.end_date],
+        |r| Ok((r.get(0)?, r.get(1)?)),
+    )
+}
+
+pub fn generate_statement(period: RoyaltyPeriod) -> AppResult<RoyaltyStatement> {
+    if period.end_date < period.start_date {
+        return Err(AppError::InvalidInput(
+            "End date must not be before start date".to_string(),
+        ));
+    }
+    let conn = get_connection()?;
+    let app_id_col = get_app_id_column(&conn);
+
+    // Rules grouped by product, in the order they were listed
+    let mut by_product: Vec<(Product, Vec<RoyaltyRule>)> = Vec::new();
+    for rule in list_rules(&conn)? {
+        let product = (rule.app_id, rule.package_id);
+        match by_product.last_mut() {
+            Some((last, rules)) if *last == product => rules.push(rule),
+            _ => by_product.push((product, vec![rule])),
+        }
+    }
+
+    let mut products = Vec::with_capacity(by_product.len());
+    let mut parties: BTreeMap<String, f64> = BTreeMap::new();
+    for ((app_id, package_id), rules) in by_product {
+        let (column, id) = match (app_id, package_id) {
+            (Some(app_id), _) => (app_id_col.as_str(), app_id),
+            (None, Some(package_id)) => ("packageid", package_id),
+            (None, None) => continue,
+        };
+        let (before, net_revenue) = product_revenue(&conn, column, id, period)?;
+        let lifetime = before + net_revenue;
+
+        let mut lines = Vec::with_capacity(rules.len());
+        for rule in rules {
+            let threshold = rule.recoup_threshold.unwrap_or(0.0);
+            // The part of this period's revenue that lies above the threshold
+            let eligible_revenue = (lifetime - threshold).max(0.0) - (before - threshold).max(0.0);
+            let amount = eligible_revenue * rule.percentage / 100.0;
+            *parties.entry(rule.party.clone()).or_default() += amount;
+            lines.push(RoyaltyLine {
+                rule_id: rule.id,
+                party: rule.party,
+                percentage: rule.percentage,
+                recoup_threshold: rule.recoup_threshold,
+                eligible_revenue,
+                amount,
+                recouped: lifetime > threshold,
+            });
+        }
+        let allocated: f64 = lines.iter().map(|l| l.amount).sum();
+        products.push(ProductStatement {
+            app_id,
+            package_id,
+            net_revenue,
+            lifetime_net_revenue: lifetime,
+            lines,
+            unallocated: net_revenue - allocated,
+        });
+    }
+
+    Ok(RoyaltyStatement {
+        period,
+        products,
+        parties: parties
+            .into_iter()
+            .map(|(party, amount)| PartyTotal { party, amount })
+            .collect(),
+    })
+}
+
+// ==================== Tauri Commands ====================
+
+#[tauri::command]
+pub async fn list_royalty_rules() -> AppResult<Vec<RoyaltyRule>> {
+    list_royalty_rules_all().map_err(AppError::from)
+}
+
+#[tauri::command]
+pub async fn create_royalty_rule(rule: RoyaltyRuleInput) -> AppResult<i64> {
+    insert_rule(&rule)
+}
+
+#[tauri::command]
+pub async fn update_royalty_rule(id: i64, rule: RoyaltyRuleInput) -> AppResult<()> {
+    match update_rule_by_id(id, &rule)? {
+        true => Ok(()),
+        false => Err(AppError::NotFound(format!("Royalty rule {} not found", id))),
+    }
+}
+
+#[tauri::command]
+pub async fn delete_royalty_rule(id: i64) -> AppResult<()> {
+    match delete_rule_by_id(id)? {
+        true => Ok(()),
+        false => Err(AppError::NotFound(format!("Royalty rule {} not found", id))),
+    }
+}
+
+/// Splits the net revenue of every product with royalty rules in `period` between the parties.
+#[tauri::command]
+pub async fn generate_royalty_statement(period: RoyaltyPeriod) -> AppResult<RoyaltyStatement> {
+    generate_statement(period).and_then(presentation::present)
+}
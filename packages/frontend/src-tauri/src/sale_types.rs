@@ -0,0 +1,129 @@
+// Revenue by package sale type
+//
+// Steam reports every row with a package_sale_type: "Steam" for storefront sales, "Retail" for
+// keys sold through resellers and retail partners, and a few others. Key reselling has its own
+// prices and volumes, so mixing it into storefront analysis skews both. The summary splits the
+// filtered rows by sale type; the `sale_types` filter of QueryFilters keeps only some of them in
+// every other view.
+
+use crate::presentation;
+use crate::queries::{
+    build_where_clause, connection_for, get_app_id_column, get_source_column, QueryFilters,
+};
+use crate::schema::column_exists;
+use crate::types::{AppError, AppResult};
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SaleTypeSummary {
+    /// e.g. "Steam" or "Retail"; "Unknown" for rows without one
+    pub sale_type: String,
+    pub total_revenue: f64,
+    pub net_revenue: f64,
+    pub gross_units: i64,
+    pub net_units: i64,
+    /// Gross revenue per gross unit sold; None without units
+    pub average_price: Option<f64>,
+    /// Share of the gross revenue of all sale types, 0-1
+    pub revenue_share: f64,
+}
+
+pub fn sale_type_summary(
+    conn: &Connection,
+    filters: QueryFilters,
+) -> AppResult<Vec<SaleTypeSummary>> {
+    if !column_exists(conn, "sales_data", "package_sale_type") {
+        return Err(AppError::InvalidInput(
+            "The database has no package_sale_type column; fetch the data again with a current CLI"
+                .to_string(),
+        ));
+    }
+
+    // Every sale type is listed, whatever the sale type filter says
+    let filters = QueryFilters {
+        sale_types: None,
+        ..filters.complete_days_only()
+    };
+    let app_id_col = get_app_id_column(conn);
+    let where_clause = build_where_clause(&filters, &app_id_col, get_source_column(conn))
+        .sale_items_only(conn, &filters);
+
+    let mut params: Vec<Value> = Vec::new();
+    if let Some(start_date) = filters.start_date {
+        params.push(start_date.to_string().into());
+    }
+    if let Some(end_date) = filters.end_date {
+        params.push(end_date.to_string().into());
+    }
+    for app_id in filters.app_ids.iter().flatten() {
+        params.push((*app_id).into());
+    }
+    if let Some(country_code) = filters.country_code {
+        params.push(country_code.into());
+    }
+    if let Some(source) = filters.source {
+        params.push(source.into());
+    }
+
+    let sql = format!(
+        "SELECT COALESCE(NULLIF(package_sale_type, ''), 'Unknown') AS sale_type,
+            COALESCE(SUM(CAST(gross_sales_usd AS REAL)), 0),
+            COALESCE(SUM(CAST(net_sales_usd AS REAL)), 0),
+            COALESCE(SUM(gross_units_sold), 0),
+            COALESCE(SUM(net_units_sold), 0)
+        FROM sales_data {}
+        GROUP BY sale_type
+        ORDER BY 2 DESC",
+        where_clause.clause
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt
+        .query_map(params_from_iter(params), |r| {
+            Ok((
+                r.get::<_, String>(0)?,
+                r.get::<_, f64>(1)?,
+                r.get::<_, f64>(2)?,
+                r.get::<_, i64>(3)?,
+                r.get::<_, i64>(4)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let total: f64 = rows.iter().map(|row| row.1).sum();
+    Ok(rows
+        .into_iter()
+        .map(
+            |(sale_type, total_revenue, net_revenue, gross_units, net_units)| SaleTypeSummary {
+                sale_type,
+                total_revenue,
+                net_revenue,
+                gross_units,
+                net_units,
+                average_price: (gross_units > 0).then(|| total_revenue / gross_units as f64),
+                revenue_share: if total > 0.0 {
+                    total_revenue / total
+                } else {
+                    0.0
+                },
+            },
+        )
+        .collect())
+}
+
+// ==================== Tauri Commands ====================
+
+/// Revenue and units per package sale type, largest revenue first. Uses every filter except the
+/// sale types themselves.
+#[tauri::command]
+pub async fn query_sale_type_summary(filters: QueryFilters) -> AppResult<Vec<SaleTypeSummary>> {
+    let filters = filters.resolve_date_range()?;
+    tokio::task::spawn_blocking(move || {
+        let conn = connection_for(&filters)?;
+        sale_type_summary(&conn, filters)
+    })
+    .await?
+    .and_then(presentation::present)
+}
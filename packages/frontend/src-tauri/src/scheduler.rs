@@ -0,0 +1,96 @@
+// Scheduled syncs
+//
+// When the user set a sync interval, the active data source is fetched once that much time has
+// passed since the last fetch started, manual or scheduled. Nothing is started while syncing is
+// paused, a fetch or CLI update is running, or the source has not been initialized yet. After a
+// scheduled sync fails (e.g. the CLI is missing or the key was revoked), the next one waits
+// FIRST_RETRY_DELAY, doubling with every failure in a row, rather than starting on every poll.
+//
+// Scheduled report emails (email.rs) and player count samples (player_stats.rs) are checked on
+// the same poll.
+
+use crate::cli;
+use crate::email;
+use crate::player_stats;
+use crate::settings;
+use crate::sources;
+use crate::sync::{self, SyncState};
+use crate::updater;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+use tracing::{info, warn};
+
+// How often the schedule is looked at; interval changes take effect within this time
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(5 * 60);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(6 * 60 * 60);
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+// Whether a scheduled fetch should start now
+fn is_due(app: &AppHandle, interval_minutes: u32, launched_at: u64) -> bool {
+    if sync::is_sync_paused() || updater::is_update_in_progress() {
+        return false;
+    }
+    let status = app.state::<SyncState>().snapshot();
+    if status.running {
+        return false;
+    }
+    let last_started = status.started_at.unwrap_or(launched_at);
+    let interval_ms = u64::from(interval_minutes) * 60 * 1000;
+    unix_millis().saturating_sub(last_started) >= interval_ms
+}
+
+// Wait before the next scheduled sync after `failures` failed ones in a row
+fn retry_delay(failures: u32) -> Duration {
+    let factor = 2u32.saturating_pow(failures.saturating_sub(1));
+    FIRST_RETRY_DELAY
+        .saturating_mul(factor)
+        .min(MAX_RETRY_DELAY)
+}
+
+/// Starts the background scheduler.
+pub fn start(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let launched_at = unix_millis();
+        let mut failures = 0;
+        let mut retry_at = 0;
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            email::send_due_reports().await;
+            player_stats::sample_if_due().await;
+            let Some(interval) = settings::load_settings().sync_interval_minutes else {
+                continue;
+            };
+            if unix_millis() < retry_at || !is_due(&app, interval, launched_at) {
+                continue;
+            }
+            let initialized = sources::resolve_source(None)
+                .is_ok_and(|source| !source.read_only && Path::new(&source.db_path).exists());
+            if !initialized {
+                continue;
+            }
+
+            info!("Starting scheduled sync (every {} minutes)", interval);
+            match cli::fetch_data(app.clone(), None, None, None).await {
+                Ok(_) => failures = 0,
+                Err(e) => {
+                    failures += 1;
+                    let delay = retry_delay(failures);
+                    retry_at = unix_millis() + delay.as_millis() as u64;
+                    warn!(
+                        "Scheduled sync failed, trying again in {} minutes: {}",
+                        delay.as_secs() / 60,
+                        e
+                    );
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,182 @@
+// Data dictionary of the sales database
+//
+// The CLI's schema differs between versions: column names changed (primary_app_id vs appid),
+// some columns are optional (discounts, package names, source) and the lookup tables only exist
+// once the CLI has written them. The existence checks here are what the queries use to adapt,
+// and `describe_schema` reports the whole picture to the frontend and the SQL console.
+
+use crate::queries::{get_app_id_column, get_app_name_column, get_package_name_column};
+use crate::sources;
+use crate::types::{AppError, AppResult};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnSchema {
+    pub name: String,
+    /// Declared type, e.g. "INTEGER"; empty if the column was declared without one
+    pub declared_type: String,
+    pub not_null: bool,
+    pub primary_key: bool,
+    pub has_default: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableSchema {
+    pub name: String,
+    /// "table" or "view"
+    pub kind: String,
+    /// Not counted for views
+    pub row_count: Option<i64>,
+    pub columns: Vec<ColumnSchema>,
+}
+
+/// Which optional parts of the schema the database has, and the column names the queries use
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaFeatures {
+    pub app_id_column: String,
+    /// Only set if the column has any names in it
+    pub app_name_column: Option<String>,
+    pub package_name_column: Option<String>,
+    pub has_discount_column: bool,
+    pub has_source_column: bool,
+    pub has_lookup_apps: bool,
+    pub has_lookup_packages: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaDescription {
+    pub path: String,
+    /// `PRAGMA user_version`, as set by the CLI
+    pub schema_version: i64,
+    pub tables: Vec<TableSchema>,
+    pub features: SchemaFeatures,
+}
+
+pub(crate) fn table_exists(conn: &Connection, table: &str) -> bool {
+    conn.query_row(
+        "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        params![table],
+        |_| Ok(()),
+    )
+    .optional()
+    .is_ok_and(|found| found.is_some())
+}
+
+pub(crate) fn column_exists(conn: &Connection, table: &str, column: &str) -> bool {
+    conn.query_row(
+        "SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2",
+        params![table, column],
+        |_| Ok(()),
+    )
+    .optional()
+    .is_ok_and(|found| found.is_some())
+}
+
+/// The first of `candidates` that is a column of `table`
+pub(crate) fn first_existing_column(
+    conn: &Connection,
+    table: &str,
+    candidates: &[&str],
+) -> Option<String> {
+    candidates
+        .iter()
+        .find(|column| column_exists(conn, table, column))
+        .map(|column| column.to_string())
+}
+
+fn read_columns(conn: &Connection, table: &str) -> SqliteResult<Vec<ColumnSchema>> {
+    let mut stmt = conn.prepare(
+        "SELECT name, type, \"notnull\", pk, dflt_value IS NOT NULL FROM pragma_table_info(?1)",
+    )?;
+    let columns = stmt
+        .query_map(params![table], |r| {
+            Ok(ColumnSchema {
+                name: r.get(0)?,
+                declared_type: r.get(1)?,
+                not_null: r.get(2)?,
+                primary_key: r.get::<_, i64>(3)? > 0,
+                has_default: r.get(4)?,
+            })
+        })?
+        .collect();
+    columns
+}
+
+pub fn describe(conn: &Connection, path: &Path) -> SqliteResult<SchemaDescription> {
+    let mut stmt = conn.prepare(
+        "SELECT name, type FROM sqlite_master
+         WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%'
+         ORDER BY name",
+    )?;
+    let entries = stmt
+        .query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?)))?
+        .collect::<SqliteResult<Vec<_>>>()?;
+
+    let mut tables = Vec::with_capacity(entries.len());
+    for (name, kind) in entries {
+        let row_count = if kind == "table" {
+            Some(conn.query_row(
+                &format!("SELECT COUNT(*) FROM \"{}\"", name.replace('"', "\"\"")),
+                [],
+                |r| r.get(0),
+            )?)
+        } else {
+            None
+        };
+        tables.push(TableSchema {
+            columns: read_columns(conn, &name)?,
+            name,
+            kind,
+            row_count,
+        });
+    }
+
+    let has_sales_data = table_exists(conn, "sales_data");
+    let features = SchemaFeatures {
+        app_id_column: get_app_id_column(conn),
+        app_name_column: has_sales_data.then(|| get_app_name_column(conn)).flatten(),
+        package_name_column: get_package_name_column(conn),
+        has_discount_column: first_existing_column(
+            conn,
+            "sales_data",
+            &["total_discount_percentage", "discount_percentage"],
+        )
+        .is_some(),
+        has_source_column: column_exists(conn, "sales_data", "source"),
+        has_lookup_apps: table_exists(conn, "lookup_apps"),
+        has_lookup_packages: table_exists(conn, "lookup_packages"),
+    };
+
+    Ok(SchemaDescription {
+        path: path.to_string_lossy().to_string(),
+        schema_version: conn.query_row("PRAGMA user_version", [], |r| r.get(0))?,
+        tables,
+        features,
+    })
+}
+
+// ==================== Tauri Commands ====================
+
+/// Tables, columns and row counts of the active source's database, and which optional columns
+/// and lookup tables it has.
+#[tauri::command]
+pub async fn describe_schema() -> AppResult<SchemaDescription> {
+    tokio::task::spawn_blocking(|| {
+        let path = sources::active_database_path();
+        if !path.exists() {
+            return Err(AppError::NotFound(format!(
+                "Database not found at {}",
+                path.display()
+            )));
+        }
+        let conn = Connection::open_with_flags(&path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        Ok(describe(&conn, &path)?)
+    })
+    .await?
+}
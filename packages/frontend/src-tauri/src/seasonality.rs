@@ -0,0 +1,235 @@
+// Day-of-week and seasonal revenue profile
+//
+// Averages an app's daily revenue and units per day of the week and per calendar month, e.g. to
+// see the weekend lift before choosing the start day of a discount. Every calendar day in the
+// range counts, including days without sales, so a quiet weekday lowers its average instead of
+// being left out. The days are generated in SQL, so only the 7 + 12 averages leave the database.
+
+use crate::presentation;
+use crate::queries::{self, get_app_id_column, get_connection};
+use crate::types::{AppError, AppResult, Date};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+const WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileRange {
+    /// Defaults to the app's first day with sales
+    pub start_date: Option<Date>,
+    /// Defaults to the last complete day
+    pub end_date: Option<Date>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileBucket {
+    /// 0 = Sunday for weekdays, 1 = January for months
+    pub index: u32,
+    pub label: String,
+    /// Calendar days in the range that fall in this bucket
+    pub days: i64,
+    pub average_revenue: f64,
+    pub average_units: f64,
+    /// Average revenue relative to the average of all days; 1.2 is 20% above it
+    pub lift: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WeekdayProfile {
+    pub app_id: i64,
+    /// None if the app has no sales
+    pub start_date: Option<Date>,
+    pub end_date: Option<Date>,
+    pub average_daily_revenue: f64,
+    pub average_daily_units: f64,
+    /// Sunday to Saturday
+    pub weekdays: Vec<ProfileBucket>,
+    /// Months with days in the range, January to December
+    pub months: Vec<ProfileBucket>,
+}
+
+// Averages per value of the strftime `format` of the day, over every day from ?2 to ?3
+fn bucket_averages(
+    conn: &Connection,
+    app_id_col: &str,
+    format: &str,
+    app_id: i64,
+    start: Date,
+    end: Date,
+) -> AppResult<Vec<(u32, i64, f64, f64)>> {
+    let sql = format!(
+        "WITH RECURSIVE days(day) AS (
+            SELECT ?2
+            UNION ALL
+            SELECT date(day, '+1 day') FROM days WHERE day < ?3
+         ),
+         daily AS (
+            SELECT date,
+                SUM(CAST(gross_sales_usd AS REAL)) AS revenue,
+                SUM(net_units_sold) AS units
+            FROM sales_data
+            WHERE {} = ?1 AND date >= ?2 AND date <= ?3
+            GROUP BY date
+         )
+         SELECT
+            CAST(strftime('{}', day) AS INTEGER) AS bucket,
+            COUNT(*),
+            AVG(COALESCE(daily.revenue, 0)),
+            AVG(COALESCE(daily.units, 0))
+         FROM days LEFT JOIN daily ON daily.date = days.day
+         GROUP BY bucket
+         ORDER BY bucket",
+        app_id_col, format
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params![app_id, start, end], |r| {
+        Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?))
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+fn to_buckets(
+    averages: Vec<(u32, i64, f64, f64)>,
+    labels: &[&str],
+    first_index: u32,
+    average_daily_revenue: f64,
+) -> Vec<ProfileBucket> {
+    averages
+        .into_iter()
+        .map(
+            |(index, days, average_revenue, average_units)| ProfileBucket {
+                index,
+                label: labels
+                    .get((index - first_index) as usize)
+                    .unwrap_or(&"")
+                    .to_string(),
+                days,
+                average_revenue,
+                average_units,
+                lift: if average_daily_revenue > 0.0 {
+                    average_revenue / average_daily_revenue
+                } else {
+                    0.0
+                },
+            },
+        )
+        .collect()
+}
+
+pub fn get_weekday_profile(app_id: i64, range: ProfileRange) -> AppResult<WeekdayProfile> {
+    let conn = get_connection()?;
+    let app_id_col = get_app_id_column(&conn);
+
+    let (first_day, last_day): (Option<Date>, Option<Date>) = conn
+        .query_row(
+            &format!(
+                "SELECT MIN(date), MAX(date) FROM sales_data WHERE {} = ?1",
+                app_id_col
+            ),
+            [app_id],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .optional()?
+        .unwrap_or((None, None));
+    // The day Steam is still reporting would pull its weekday down
+    let last_day = match (last_day, queries::last_complete_day(None)) {
+        (Some(last), Some(complete)) => Some(last.min(complete)),
+        (last, _) => last,
+    };
+
+    if let (Some(start), Some(end)) = (range.start_date, range.end_date) {
+        if end < start {
+            return Err(AppError::InvalidInput(
+                "End date must not be before start date".to_string(),
+            ));
+        }
+    }
+    let start = range.start_date.or(first_day);
+    let end = match (range.end_date, last_day) {
+        (Some(end), Some(last)) => Some(end.min(last)),
+        (end, last) => end.or(last),
+    };
+    let (Some(start), Some(end)) = (start, end) else {
+        return Ok(empty_profile(app_id));
+    };
+    if end < start {
+        return Ok(empty_profile(app_id));
+    }
+
+    let weekdays = bucket_averages(&conn, &app_id_col, "%w", app_id, start, end)?;
+    let months = bucket_averages(&conn, &app_id_col, "%m", app_id, start, end)?;
+
+    // Every day is in exactly one weekday bucket, so their day-weighted mean is the overall mean
+    let total_days: i64 = weekdays.iter().map(|(_, days, _, _)| days).sum();
+    let (revenue, units) = weekdays
+        .iter()
+        .fold((0.0, 0.0), |(revenue, units), (_, days, r, u)| {
+            (revenue + r * *days as f64, units + u * *days as f64)
+        });
+    let (average_daily_revenue, average_daily_units) = if total_days > 0 {
+        (revenue / total_days as f64, units / total_days as f64)
+    } else {
+        (0.0, 0.0)
+    };
+
+    Ok(WeekdayProfile {
+        app_id,
+        start_date: Some(start),
+        end_date: Some(end),
+        average_daily_revenue,
+        average_daily_units,
+        weekdays: to_buckets(weekdays, &WEEKDAYS, 0, average_daily_revenue),
+        months: to_buckets(months, &MONTHS, 1, average_daily_revenue),
+    })
+}
+
+fn empty_profile(app_id: i64) -> WeekdayProfile {
+    WeekdayProfile {
+        app_id,
+        start_date: None,
+        end_date: None,
+        average_daily_revenue: 0.0,
+        average_daily_units: 0.0,
+        weekdays: Vec::new(),
+        months: Vec::new(),
+    }
+}
+
+// ==================== Tauri Commands ====================
+
+/// Average daily revenue and units of an app per day of the week and per calendar month.
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn query_weekday_profile(
+    appId: i64,
+    range: Option<ProfileRange>,
+) -> AppResult<WeekdayProfile> {
+    tokio::task::spawn_blocking(move || get_weekday_profile(appId, range.unwrap_or_default()))
+        .await?
+        .and_then(presentation::present)
+}
@@ -0,0 +1,48 @@
+// Secrets in the operating system's credential store
+//
+// Passwords and similar secrets are kept in the Keychain on macOS, the Credential Manager on
+// Windows and the Secret Service (GNOME Keyring, KWallet) on Linux, never in the app database.
+
+use crate::types::{AppError, AppResult};
+use keyring::Entry;
+
+const SERVICE: &str = "com.steamsales.analyzer";
+
+fn entry(name: &str) -> AppResult<Entry> {
+    Entry::new(SERVICE, name).map_err(|e| {
+        AppError::Internal(format!(
+            "Failed to open credential store entry '{}': {}",
+            name, e
+        ))
+    })
+}
+
+pub fn get_secret(name: &str) -> AppResult<Option<String>> {
+    match entry(name)?.get_password() {
+        Ok(secret) => Ok(Some(secret)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(AppError::Internal(format!(
+            "Failed to read '{}' from the credential store: {}",
+            name, e
+        ))),
+    }
+}
+
+pub fn set_secret(name: &str, secret: &str) -> AppResult<()> {
+    entry(name)?.set_password(secret).map_err(|e| {
+        AppError::Internal(format!(
+            "Failed to save '{}' to the credential store: {}",
+            name, e
+        ))
+    })
+}
+
+pub fn delete_secret(name: &str) -> AppResult<()> {
+    match entry(name)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(AppError::Internal(format!(
+            "Failed to delete '{}' from the credential store: {}",
+            name, e
+        ))),
+    }
+}
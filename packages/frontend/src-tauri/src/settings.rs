@@ -0,0 +1,177 @@
+// User preferences stored in the app database
+//
+// Each field of `Settings` is one row of the `settings` table, keyed by its camelCase name and
+// holding its JSON value. Rows that are missing or no longer parse fall back to the default, so
+// adding a field never needs a migration.
+
+use crate::database::get_app_connection;
+use crate::types::{AppError, AppResult, Settings};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde_json::{Map, Value};
+use tracing::warn;
+
+// Preferences that used to live in app_state, by (app_state key, settings key)
+const LEGACY_APP_STATE_KEYS: &[(&str, &str)] = &[
+    ("cli_include_prereleases", "cliIncludePrereleases"),
+    ("cli_auto_update", "cliAutoUpdate"),
+];
+
+fn ensure_table(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+        );",
+    )?;
+    migrate_app_state(conn)
+}
+
+// Moves preferences stored as app_state flags over. Their "true"/"false" values are valid JSON.
+fn migrate_app_state(conn: &Connection) -> SqliteResult<()> {
+    let has_app_state = conn
+        .query_row(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'app_state'",
+            [],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some();
+    if !has_app_state {
+        return Ok(());
+    }
+    for (old_key, new_key) in LEGACY_APP_STATE_KEYS {
+        conn.execute(
+            "INSERT OR IGNORE INTO settings (key, value)
+             SELECT ?2, value FROM app_state WHERE key = ?1",
+            params![old_key, new_key],
+        )?;
+        conn.execute("DELETE FROM app_state WHERE key = ?1", [old_key])?;
+    }
+    Ok(())
+}
+
+fn to_map(settings: &Settings) -> Map<String, Value> {
+    match serde_json::to_value(settings) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    }
+}
+
+fn from_map(map: Map<String, Value>) -> AppResult<Settings> {
+    serde_json::from_value(Value::Object(map))
+        .map_err(|e| AppError::InvalidInput(format!("Invalid setting: {}", e)))
+}
+
+fn read(conn: &Connection) -> AppResult<Settings> {
+    ensure_table(conn)?;
+    let mut stmt = conn.prepare("SELECT key, value FROM settings")?;
+    let rows = stmt
+        .query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?)))?
+        .collect::<SqliteResult<Vec<_>>>()?;
+
+    let mut map = to_map(&Settings::default());
+    for (key, raw) in rows {
+        // Keys of settings that were removed are left alone
+        if !map.contains_key(&key) {
+            continue;
+        }
+        let mut candidate = map.clone();
+        match serde_json::from_str::<Value>(&raw) {
+            Ok(value) => {
+                candidate.insert(key.clone(), value);
+            }
+            Err(e) => {
+                warn!("Ignoring unreadable setting {}: {}", key, e);
+                continue;
+            }
+        }
+        match from_map(candidate.clone()) {
+            Ok(_) => map = candidate,
+            Err(e) => warn!("Ignoring setting {}: {}", key, e),
+        }
+    }
+    from_map(map)
+}
+
+// Writes the fields that differ from what is stored
+fn write(conn: &Connection, old: &Settings, new: &Settings) -> AppResult<()> {
+    new.validate()?;
+    let old = to_map(old);
+    for (key, value) in to_map(new) {
+        if old.get(&key) == Some(&value) {
+            continue;
+        }
+        conn.execute(
+            "INSERT INTO settings (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET
+                value = excluded.value,
+                updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now')",
+            params![key, value.to_string()],
+        )?;
+    }
+    Ok(())
+}
+
+/// The current settings. Never fails: if the table can't be read, the defaults are returned.
+pub fn load_settings() -> Settings {
+    match get_app_connection()
+        .map_err(AppError::from)
+        .and_then(|conn| read(&conn))
+    {
+        Ok(settings) => settings,
+        Err(e) => {
+            warn!("Failed to read settings, using defaults: {}", e);
+            Settings::default()
+        }
+    }
+}
+
+/// Changes settings in place, e.g. `update_settings(|s| s.cli_auto_update = true)`.
+pub fn update_settings(change: impl FnOnce(&mut Settings)) -> AppResult<Settings> {
+    let conn = get_app_connection()?;
+    let old = read(&conn)?;
+    let mut new = old.clone();
+    change(&mut new);
+    write(&conn, &old, &new)?;
+    Ok(new)
+}
+
+// ==================== Tauri Commands ====================
+
+#[tauri::command]
+pub async fn get_all_settings() -> AppResult<Settings> {
+    let conn = get_app_connection()?;
+    read(&conn)
+}
+
+#[tauri::command]
+pub async fn get_setting(key: String) -> AppResult<Value> {
+    let conn = get_app_connection()?;
+    to_map(&read(&conn)?)
+        .remove(&key)
+        .ok_or_else(|| AppError::NotFound(format!("Unknown setting '{}'", key)))
+}
+
+/// Sets one setting and returns all settings. The value must have the setting's type.
+#[tauri::command]
+pub async fn set_setting(key: String, value: Value) -> AppResult<Settings> {
+    let conn = get_app_connection()?;
+    let old = read(&conn)?;
+    let mut map = to_map(&old);
+    if !map.contains_key(&key) {
+        return Err(AppError::NotFound(format!("Unknown setting '{}'", key)));
+    }
+    map.insert(key, value);
+    let new = from_map(map)?;
+    write(&conn, &old, &new)?;
+    Ok(new)
+}
+
+#[tauri::command]
+pub async fn reset_settings() -> AppResult<Settings> {
+    let conn = get_app_connection()?;
+    ensure_table(&conn)?;
+    conn.execute("DELETE FROM settings", [])?;
+    Ok(Settings::default())
+}
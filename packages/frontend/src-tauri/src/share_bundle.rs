@@ -0,0 +1,244 @@
+// Read-only sharing bundles
+//
+// A share bundle is a standalone SQLite file with only the sales rows of the selected apps and
+// date range, e.g. for an investor or a platform partner. It is a copy of the active sales
+// database (VACUUM INTO) from which everything else is removed: the rows outside the filters,
+// every table but sales_data and the lookup tables (the CLI's own tables hold the API key and
+// sync state), and all views and triggers. Names of other apps and packages go from the lookups
+// too. The file is vacuumed again at the end, so nothing removed can be read back from free pages.
+//
+// The bundle can be opened in any SQLite tool. `import_share_bundle` copies one received from
+// someone else (e.g. a publisher's consolidated numbers) into the app as a read-only data source,
+// which can be made active or queried through `data_source` in QueryFilters.
+
+use crate::database::get_database_path;
+use crate::lookup_refresh::{APP_NAMES_TABLE, PACKAGE_NAMES_TABLE};
+use crate::queries::{
+    build_where_clause, get_app_id_column, get_connection, get_source_column, QueryFilters,
+};
+use crate::schema::table_exists;
+use crate::sources::{self, DataSource};
+use crate::types::{AppError, AppResult};
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Tables kept in a bundle besides sales_data; the app and package names are pruned
+const LOOKUP_TABLES: &[&str] = &[
+    "lookup_apps",
+    "lookup_packages",
+    APP_NAMES_TABLE,
+    PACKAGE_NAMES_TABLE,
+    "lookup_countries",
+    "lookup_price_regions",
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareBundle {
+    pub path: String,
+    pub rows: i64,
+    pub apps: i64,
+    pub first_date: Option<String>,
+    pub last_date: Option<String>,
+    pub size_bytes: u64,
+}
+
+// Drops every table, view and trigger of the bundle that isn't on the list
+fn drop_other_objects(conn: &Connection) -> AppResult<()> {
+    let objects: Vec<(String, String)> = {
+        let mut stmt = conn.prepare(
+            "SELECT type, name FROM sqlite_master
+             WHERE type IN ('table', 'view', 'trigger') AND name NOT LIKE 'sqlite_%'
+             ORDER BY CASE type WHEN 'trigger' THEN 0 WHEN 'view' THEN 1 ELSE 2 END",
+        )?;
+        let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?;
+        rows.collect::<Result<Vec<_>, _>>()?
+    };
+    for (kind, name) in objects {
+        if kind == "table" && (name == "sales_data" || LOOKUP_TABLES.contains(&name.as_str())) {
+            continue;
+        }
+        conn.execute_batch(&format!(
+            "DROP {} IF EXISTS \"{}\";",
+            kind.to_uppercase(),
+            name.replace('"', "\"\"")
+        ))?;
+    }
+    Ok(())
+}
+
+// Removes the rows outside `filters` and the lookups of apps and packages without rows left
+fn prune(conn: &Connection, filters: &QueryFilters) -> AppResult<()> {
+    let app_id_col = get_app_id_column(conn);
+    let where_clause = build_where_clause(filters, &app_id_col, get_source_column(conn));
+
+    let mut params: Vec<Value> = Vec::new();
+    if let Some(start_date) = filters.start_date {
+        params.push(start_date.to_string().into());
+    }
+    if let Some(end_date) = filters.end_date {
+        params.push(end_date.to_string().into());
+    }
+    for app_id in filters.app_ids.iter().flatten() {
+        params.push((*app_id).into());
+    }
+    if let Some(country_code) = &filters.country_code {
+        params.push(country_code.clone().into());
+    }
+    if let Some(source) = &filters.source {
+        params.push(source.clone().into());
+    }
+    for sale_type in filters.sale_types.iter().flatten() {
+        params.push(sale_type.clone().into());
+    }
+
+    conn.execute(
+        &format!(
+            "DELETE FROM sales_data WHERE rowid NOT IN (SELECT rowid FROM sales_data {})",
+            where_clause.clause
+        ),
+        params_from_iter(params),
+    )?;
+    for table in ["lookup_apps", APP_NAMES_TABLE] {
+        if table_exists(conn, table) {
+            conn.execute(
+                &format!(
+                    "DELETE FROM {}
+                     WHERE appid NOT IN (SELECT {} FROM sales_data WHERE {} IS NOT NULL)",
+                    table, app_id_col, app_id_col
+                ),
+                [],
+            )?;
+        }
+    }
+    for table in ["lookup_packages", PACKAGE_NAMES_TABLE] {
+        if table_exists(conn, table) {
+            conn.execute(
+                &format!(
+                    "DELETE FROM {}
+                     WHERE packageid NOT IN (SELECT packageid FROM sales_data WHERE packageid IS NOT NULL)",
+                    table
+                ),
+                [],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn summarize(conn: &Connection, path: &Path) -> AppResult<ShareBundle> {
+    let (rows, apps, first_date, last_date) = conn.query_row(
+        &format!(
+            "SELECT COUNT(*), COUNT(DISTINCT {}), MIN(date), MAX(date) FROM sales_data",
+            get_app_id_column(conn)
+        ),
+        [],
+        |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+    )?;
+    Ok(ShareBundle {
+        path: path.to_string_lossy().to_string(),
+        rows,
+        apps,
+        first_date,
+        last_date,
+        size_bytes: fs::metadata(path)?.len(),
+    })
+}
+
+fn build_bundle(source: &Connection, filters: &QueryFilters, path: &Path) -> AppResult<()> {
+    source.execute("VACUUM INTO ?1", [path.to_string_lossy()])?;
+    let conn = Connection::open(path)?;
+    // A single file, even if the sales database uses a write-ahead log
+    conn.pragma_update(None, "journal_mode", "DELETE")?;
+    drop_other_objects(&conn)?;
+    prune(&conn, filters)?;
+    conn.execute_batch("VACUUM;")?;
+    Ok(())
+}
+
+/// Writes the rows of the active data source that match `filters` to a new database at `path`,
+/// replacing any file there. The limit, offset and sorting of the filters are ignored.
+pub fn export_bundle(filters: &QueryFilters, path: &Path) -> AppResult<ShareBundle> {
+    if path.as_os_str().is_empty() {
+        return Err(AppError::InvalidInput("No bundle path given".to_string()));
+    }
+    let source_path = get_database_path();
+    if path.exists() && fs::canonicalize(path).ok() == fs::canonicalize(&source_path).ok() {
+        return Err(AppError::InvalidInput(
+            "The bundle can't replace the sales database itself".to_string(),
+        ));
+    }
+    let source = get_connection()?;
+    if !table_exists(&source, "sales_data") {
+        return Err(AppError::NotFound("No sales data to export".to_string()));
+    }
+
+    // Built next to the destination and moved there once complete, so a failed export leaves
+    // neither a half-pruned bundle nor a full copy of the database behind
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+    let _ = fs::remove_file(&partial);
+    if let Err(e) = build_bundle(&source, filters, &partial) {
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+    fs::rename(&partial, path)?;
+
+    let conn = Connection::open(path)?;
+    summarize(&conn, path)
+}
+
+/// Copies the bundle at `path` into the app and registers it as a read-only data source named
+/// `name` (the file name if None).
+pub fn import_bundle(path: &Path, name: Option<&str>) -> AppResult<DataSource> {
+    let bundle = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| AppError::InvalidInput(format!("Not a database: {}", e)))?;
+    if !table_exists(&bundle, "sales_data") {
+        return Err(AppError::InvalidInput(
+            "The file has no sales data; is it a share bundle?".to_string(),
+        ));
+    }
+    drop(bundle);
+
+    let name = match name.map(str::trim).filter(|n| !n.is_empty()) {
+        Some(name) => name.to_string(),
+        None => path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Share bundle".to_string()),
+    };
+    // The source gets its own copy, so the received file can be moved or deleted
+    let db_path = sources::managed_database_path(&name);
+    if db_path.exists() {
+        return Err(AppError::InvalidInput(
+            "A data source with this name or database already exists".to_string(),
+        ));
+    }
+    if let Some(parent) = db_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(path, &db_path)?;
+    sources::insert_read_only_source(&name, &db_path.to_string_lossy()).inspect_err(|_| {
+        let _ = fs::remove_file(&db_path);
+    })
+}
+
+// ==================== Tauri Commands ====================
+
+/// Exports the apps and date range of `filters` as a standalone database at `path`, without the
+/// rest of the account's data or the CLI's API key.
+#[tauri::command]
+pub async fn export_share_bundle(filters: QueryFilters, path: String) -> AppResult<ShareBundle> {
+    let filters = filters.resolve_date_range()?;
+    tokio::task::spawn_blocking(move || export_bundle(&filters, Path::new(&path))).await?
+}
+
+/// Adds a share bundle received from someone else as a read-only data source.
+#[tauri::command]
+pub async fn import_share_bundle(path: String, name: Option<String>) -> AppResult<DataSource> {
+    tokio::task::spawn_blocking(move || import_bundle(Path::new(&path), name.as_deref())).await?
+}
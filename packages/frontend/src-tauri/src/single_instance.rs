@@ -0,0 +1,67 @@
+// One running app at a time
+//
+// Two instances would write the same sales database and window state concurrently. A second
+// window (e.g. started by a steamsales:// link on Windows and Linux, which launch the app with the
+// link as an argument) is stopped by tauri-plugin-single-instance: it hands its command line to
+// the running instance and exits. The running instance brings its main window to the front; links
+// reach deeplink.rs through the deep-link plugin.
+//
+// Headless runs (see headless.rs) have no Tauri app, so the plugin doesn't see them. The app and
+// a headless sync both take an exclusive lock on ~/.steamsales/app.lock instead, for as long as
+// they run, and a sync refuses to start while the app runs. The OS drops the lock when the
+// process ends, so a crashed instance never leaves a stale lock behind.
+
+use crate::tray;
+use fs4::fs_std::FileExt;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use tauri::plugin::TauriPlugin;
+use tauri::{AppHandle, Manager, Wry};
+use tracing::{error, info, warn};
+
+/// Held for as long as the app or a headless sync runs; dropping it releases the lock.
+pub struct InstanceLock {
+    _file: File,
+}
+
+/// Takes the instance lock. Returns None if another process holds it.
+pub fn lock() -> io::Result<Option<InstanceLock>> {
+    let dir = dirs::home_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No home directory"))?
+        .join(".steamsales");
+    fs::create_dir_all(&dir)?;
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(dir.join("app.lock"))?;
+    Ok(file
+        .try_lock_exclusive()?
+        .then_some(InstanceLock { _file: file }))
+}
+
+/// Plugin that hands a second window's arguments to this instance. Register it before the other
+/// plugins, so a second window exits before they start.
+pub fn plugin() -> TauriPlugin<Wry> {
+    tauri_plugin_single_instance::init(|app, _args, _cwd| {
+        info!("Another instance was started; showing this one instead");
+        tray::show_main_window(app);
+    })
+}
+
+/// Takes the instance lock for the app's lifetime. Call from `setup`. If a headless sync holds
+/// it, the app exits instead of writing the database at the same time. Without a lock file (e.g.
+/// an unwritable home directory) the app runs without the check.
+pub fn hold_lock(app: &AppHandle) {
+    match lock() {
+        Ok(Some(lock)) => {
+            app.manage(lock);
+        }
+        Ok(None) => {
+            error!("A headless sync is running; start the app again when it is done");
+            eprintln!("A headless sync is running; start the app again when it is done");
+            app.exit(1);
+        }
+        Err(e) => warn!("Failed to take the instance lock: {}", e),
+    }
+}
@@ -0,0 +1,210 @@
+// Differences between two database snapshots
+//
+// When Valve restates historical data, a fetch quietly overwrites the old rows. Comparing a copy
+// of the database from before (a backup, a share bundle, an older export) with one from after
+// shows exactly what changed: rows only in the newer snapshot (added), rows only in the older one
+// (removed), and rows in both whose units or amounts differ (changed), per date and app.
+//
+// Rows are matched on the key columns the change log uses (see change_log.rs) that both
+// snapshots have; rows with the same key are summed first. Amounts are compared in whole cents,
+// units exactly.
+
+use crate::change_log::KEY_COLUMNS;
+use crate::presentation;
+use crate::queries::get_app_id_column;
+use crate::schema::{column_exists, table_exists};
+use crate::types::{AppError, AppResult, Date};
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const UNIT_COLUMNS: &[&str] = &["gross_units_sold", "gross_units_returned", "net_units_sold"];
+const AMOUNT_COLUMNS: &[&str] = &["gross_sales_usd", "net_sales_usd", "net_tax_usd"];
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DateAppDiff {
+    pub date: Date,
+    pub app_id: Option<i64>,
+    pub rows_added: i64,
+    pub rows_removed: i64,
+    pub rows_changed: i64,
+    /// Gross revenue of the newer snapshot minus the older one
+    pub gross_revenue_change: f64,
+    pub net_revenue_change: f64,
+    pub net_units_change: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotDiff {
+    pub rows_before: i64,
+    pub rows_after: i64,
+    pub rows_added: i64,
+    pub rows_removed: i64,
+    pub rows_changed: i64,
+    /// Columns rows were matched on
+    pub key_columns: Vec<String>,
+    /// Only dates and apps with differences, oldest first
+    pub changes: Vec<DateAppDiff>,
+}
+
+fn open_snapshot(path: &Path) -> AppResult<Connection> {
+    if !path.exists() {
+        return Err(AppError::NotFound(format!(
+            "No database at {}",
+            path.display()
+        )));
+    }
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    if !table_exists(&conn, "sales_data") {
+        return Err(AppError::InvalidInput(format!(
+            "{} has no sales data",
+            path.display()
+        )));
+    }
+    Ok(conn)
+}
+
+// One row per key of a snapshot, with the app id as `app_id` and the compared columns summed
+fn keyed_rows(
+    schema: &str,
+    app_id_col: &str,
+    keys: &[&str],
+    units: &[&str],
+    amounts: &[&str],
+) -> String {
+    let key_list: Vec<String> = keys.iter().map(|c| format!("\"{}\"", c)).collect();
+    let mut columns = vec![
+        format!(
+            "json_array({}, {}) AS row_key",
+            app_id_col,
+            key_list.join(", ")
+        ),
+        "MIN(date) AS date".to_string(),
+        format!("MIN({}) AS app_id", app_id_col),
+        "COUNT(*) AS row_count".to_string(),
+    ];
+    for column in units {
+        columns.push(format!("COALESCE(SUM({c}), 0) AS {c}", c = column));
+    }
+    for column in amounts {
+        columns.push(format!(
+            "CAST(ROUND(COALESCE(SUM(CAST({c} AS REAL)), 0) * 100) AS INTEGER) AS {c}",
+            c = column
+        ));
+    }
+    format!(
+        "SELECT {} FROM {}.sales_data GROUP BY row_key",
+        columns.join(", "),
+        schema
+    )
+}
+
+/// Compares the sales rows of the databases at `before` and `after`.
+pub fn diff(before: &Path, after: &Path) -> AppResult<SnapshotDiff> {
+    let conn = open_snapshot(before)?;
+    let other = open_snapshot(after)?;
+    let in_both = |column: &&str| {
+        column_exists(&conn, "sales_data", column) && column_exists(&other, "sales_data", column)
+    };
+    let keys: Vec<&str> = KEY_COLUMNS.iter().copied().filter(in_both).collect();
+    let units: Vec<&str> = UNIT_COLUMNS.iter().copied().filter(in_both).collect();
+    let amounts: Vec<&str> = AMOUNT_COLUMNS.iter().copied().filter(in_both).collect();
+    if !keys.contains(&"date") {
+        return Err(AppError::InvalidInput(
+            "Both databases need a date column to be compared".to_string(),
+        ));
+    }
+    let before_app_col = get_app_id_column(&conn);
+    let after_app_col = get_app_id_column(&other);
+    drop(other);
+    conn.execute("ATTACH DATABASE ?1 AS newer", [after.to_string_lossy()])?;
+
+    let differs: Vec<String> = units
+        .iter()
+        .chain(amounts.iter())
+        .map(|c| format!("a.{c} != b.{c}", c = c))
+        .collect();
+    let differs = if differs.is_empty() {
+        "0".to_string()
+    } else {
+        differs.join(" OR ")
+    };
+    let change = |column: &str, scale: &str| {
+        if units.contains(&column) || amounts.contains(&column) {
+            format!(
+                "SUM(COALESCE(b.{c}, 0) - COALESCE(a.{c}, 0)){s}",
+                c = column,
+                s = scale
+            )
+        } else {
+            "0".to_string()
+        }
+    };
+    let sql = format!(
+        "WITH a AS ({before}), b AS ({after})
+        SELECT COALESCE(a.date, b.date) AS day, COALESCE(a.app_id, b.app_id) AS app,
+            SUM(a.row_key IS NULL), SUM(b.row_key IS NULL),
+            SUM(a.row_key IS NOT NULL AND b.row_key IS NOT NULL AND ({differs})),
+            {gross}, {net}, {units}
+        FROM a FULL OUTER JOIN b ON a.row_key = b.row_key
+        GROUP BY day, app
+        HAVING SUM(a.row_key IS NULL OR b.row_key IS NULL OR ({differs})) > 0
+        ORDER BY day, app",
+        before = keyed_rows("main", &before_app_col, &keys, &units, &amounts),
+        after = keyed_rows("newer", &after_app_col, &keys, &units, &amounts),
+        differs = differs,
+        gross = change("gross_sales_usd", " / 100.0"),
+        net = change("net_sales_usd", " / 100.0"),
+        units = change("net_units_sold", ""),
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let changes = stmt
+        .query_map([], |r| {
+            Ok(DateAppDiff {
+                date: r.get(0)?,
+                app_id: r.get(1)?,
+                rows_added: r.get(2)?,
+                rows_removed: r.get(3)?,
+                rows_changed: r.get(4)?,
+                gross_revenue_change: r.get(5)?,
+                net_revenue_change: r.get(6)?,
+                net_units_change: r.get(7)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let count = |schema: &str| -> AppResult<i64> {
+        Ok(conn.query_row(
+            &format!("SELECT COUNT(*) FROM {}.sales_data", schema),
+            [],
+            |r| r.get(0),
+        )?)
+    };
+    Ok(SnapshotDiff {
+        rows_before: count("main")?,
+        rows_after: count("newer")?,
+        rows_added: changes.iter().map(|c| c.rows_added).sum(),
+        rows_removed: changes.iter().map(|c| c.rows_removed).sum(),
+        rows_changed: changes.iter().map(|c| c.rows_changed).sum(),
+        key_columns: std::iter::once(after_app_col.as_str())
+            .chain(keys.iter().copied())
+            .map(str::to_string)
+            .collect(),
+        changes,
+    })
+}
+
+// ==================== Tauri Commands ====================
+
+/// Rows added, removed and changed per date and app between two copies of a sales database,
+/// e.g. a backup from before a fetch (`backupA`) and the current database (`backupB`).
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn diff_snapshots(backupA: String, backupB: String) -> AppResult<SnapshotDiff> {
+    tokio::task::spawn_blocking(move || {
+        diff(Path::new(&backupA), Path::new(&backupB)).and_then(presentation::present)
+    })
+    .await?
+}
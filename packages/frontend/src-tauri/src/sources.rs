@@ -0,0 +1,306 @@
+// Named data sources
+//
+// Each source is its own CLI database (the CLI keeps the API key inside it), so several partner
+// accounts can be fetched and viewed separately. The built-in "default" source is the original
+// ~/.steamsales/steam-financial.db. The active source decides which database the dashboard
+// queries; fetches and inits can target any source.
+//
+// Imported share bundles (see share_bundle.rs) are read-only sources: their database is a copy
+// the app owns, which is never fetched into. They can be made active like any other source, or
+// queried next to the active one through `data_source` in QueryFilters.
+
+use crate::app_state;
+use crate::database::{get_app_connection, get_default_database_path};
+use crate::schema::column_exists;
+use crate::types::{AppError, AppResult};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+pub const DEFAULT_SOURCE_ID: &str = "default";
+
+// app_state key of the active source id
+const ACTIVE_SOURCE_KEY: &str = "active_data_source";
+
+// Database path of the active source, so queries don't hit the app database every time
+static ACTIVE_DB_PATH: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataSource {
+    pub id: String,
+    pub name: String,
+    pub db_path: String,
+    /// Fingerprint of the API key this source was initialized with (see keys.rs)
+    pub key_id: Option<String>,
+    pub created_at: String,
+    /// Imported share bundle; never fetched into
+    pub read_only: bool,
+    pub active: bool,
+    pub database_exists: bool,
+}
+
+fn ensure_table(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS data_sources (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            db_path TEXT NOT NULL UNIQUE,
+            key_id TEXT,
+            created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+            read_only INTEGER NOT NULL DEFAULT 0
+        );",
+    )?;
+    if !column_exists(conn, "data_sources", "read_only") {
+        conn.execute_batch(
+            "ALTER TABLE data_sources ADD COLUMN read_only INTEGER NOT NULL DEFAULT 0;",
+        )?;
+    }
+    conn.execute(
+        "INSERT OR IGNORE INTO data_sources (id, name, db_path) VALUES (?1, 'Default', ?2)",
+        params![
+            DEFAULT_SOURCE_ID,
+            get_default_database_path().to_string_lossy()
+        ],
+    )?;
+    Ok(())
+}
+
+fn row_to_source(r: &rusqlite::Row, active_id: &str) -> SqliteResult<DataSource> {
+    let id: String = r.get(0)?;
+    let db_path: String = r.get(2)?;
+    Ok(DataSource {
+        active: id == active_id,
+        database_exists: PathBuf::from(&db_path).exists(),
+        id,
+        name: r.get(1)?,
+        db_path,
+        key_id: r.get(3)?,
+        created_at: r.get(4)?,
+        read_only: r.get(5)?,
+    })
+}
+
+// Lowercase id derived from a display name ("Partner Two" -> "partner-two")
+fn slugify(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.trim().chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_matches('-').to_string()
+}
+
+fn stored_active_id(conn: &Connection) -> SqliteResult<String> {
+    let id = app_state::get_value(ACTIVE_SOURCE_KEY)?;
+    // Fall back to the default source if the stored one was removed
+    let exists = match id.as_deref() {
+        Some(id) => conn
+            .query_row("SELECT 1 FROM data_sources WHERE id = ?1", [id], |_| Ok(()))
+            .optional()?
+            .is_some(),
+        None => false,
+    };
+    Ok(match id {
+        Some(id) if exists => id,
+        _ => DEFAULT_SOURCE_ID.to_string(),
+    })
+}
+
+pub fn list_sources() -> SqliteResult<Vec<DataSource>> {
+    let conn = get_app_connection()?;
+    ensure_table(&conn)?;
+    let active_id = stored_active_id(&conn)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT id, name, db_path, key_id, created_at, read_only FROM data_sources
+         ORDER BY id != ?1, created_at",
+    )?;
+    let rows = stmt.query_map([DEFAULT_SOURCE_ID], |r| row_to_source(r, &active_id))?;
+    rows.collect()
+}
+
+pub fn get_source(id: &str) -> SqliteResult<Option<DataSource>> {
+    let conn = get_app_connection()?;
+    ensure_table(&conn)?;
+    let active_id = stored_active_id(&conn)?;
+    conn.query_row(
+        "SELECT id, name, db_path, key_id, created_at, read_only FROM data_sources WHERE id = ?1",
+        [id],
+        |r| row_to_source(r, &active_id),
+    )
+    .optional()
+}
+
+/// The given source, or the active one when `id` is None.
+pub fn resolve_source(id: Option<&str>) -> AppResult<DataSource> {
+    let id = match id {
+        Some(id) => id.to_string(),
+        None => {
+            let conn = get_app_connection()?;
+            ensure_table(&conn)?;
+            stored_active_id(&conn)?
+        }
+    };
+    get_source(&id)?
+        .ok_or_else(|| AppError::NotFound(format!("Data source '{}' not found", id)))
+}
+
+/// The given source, or the active one, if data can be fetched into it.
+pub fn resolve_writable_source(id: Option<&str>) -> AppResult<DataSource> {
+    let source = resolve_source(id)?;
+    if source.read_only {
+        return Err(AppError::InvalidInput(format!(
+            "Data source '{}' is an imported share bundle and can't be fetched into",
+            source.name
+        )));
+    }
+    Ok(source)
+}
+
+/// Id of the active source, for data the app database keeps per source.
+pub fn active_source_id() -> String {
+    resolve_source(None)
+        .map(|source| source.id)
+        .unwrap_or_else(|_| DEFAULT_SOURCE_ID.to_string())
+}
+
+/// Database path of the active source. Falls back to the default database if the app database
+/// cannot be read, so the dashboard keeps working.
+pub fn active_database_path() -> PathBuf {
+    if let Some(path) = ACTIVE_DB_PATH.read().unwrap().as_ref() {
+        return path.clone();
+    }
+    let path = resolve_source(None)
+        .map(|source| PathBuf::from(source.db_path))
+        .unwrap_or_else(|_| get_default_database_path());
+    *ACTIVE_DB_PATH.write().unwrap() = Some(path.clone());
+    path
+}
+
+/// Points this process's queries at the database at `path` without changing the saved active
+/// source, e.g. for benchmarks run from the command line.
+pub fn use_database_for_process(path: &Path) {
+    *ACTIVE_DB_PATH.write().unwrap() = Some(path.to_path_buf());
+}
+
+/// Where the database of a source named `name` goes unless another path is given.
+pub fn managed_database_path(name: &str) -> PathBuf {
+    get_default_database_path()
+        .with_file_name("sources")
+        .join(format!("{}.db", slugify(name)))
+}
+
+pub fn insert_source(name: &str, db_path: Option<&str>) -> AppResult<DataSource> {
+    insert_source_with(name, db_path, false)
+}
+
+/// Registers a read-only source for the already existing database at `db_path`.
+pub fn insert_read_only_source(name: &str, db_path: &str) -> AppResult<DataSource> {
+    insert_source_with(name, Some(db_path), true)
+}
+
+fn insert_source_with(name: &str, db_path: Option<&str>, read_only: bool) -> AppResult<DataSource> {
+    let id = slugify(name);
+    if id.is_empty() {
+        return Err(AppError::InvalidInput(
+            "Data source name must contain letters or digits".to_string(),
+        ));
+    }
+    let db_path = match db_path.map(str::trim).filter(|p| !p.is_empty()) {
+        Some(path) => PathBuf::from(path),
+        None => managed_database_path(&id),
+    };
+    if let Some(parent) = db_path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| AppError::Io(format!("Failed to create directory: {}", e)))?;
+    }
+
+    let conn = get_app_connection()?;
+    ensure_table(&conn)?;
+    conn.execute(
+        "INSERT INTO data_sources (id, name, db_path, read_only) VALUES (?1, ?2, ?3, ?4)",
+        params![id, name.trim(), db_path.to_string_lossy(), read_only],
+    )
+    .map_err(|e| match e {
+        rusqlite::Error::SqliteFailure(err, _)
+            if err.code == rusqlite::ErrorCode::ConstraintViolation =>
+        {
+            AppError::InvalidInput(
+                "A data source with this name or database already exists".to_string(),
+            )
+        }
+        e => AppError::from(e),
+    })?;
+    resolve_source(Some(&id))
+}
+
+/// Removes a source from the registry. Its database file is left on disk.
+pub fn delete_source(id: &str) -> AppResult<()> {
+    if id == DEFAULT_SOURCE_ID {
+        return Err(AppError::InvalidInput(
+            "The default data source cannot be removed".to_string(),
+        ));
+    }
+    let conn = get_app_connection()?;
+    ensure_table(&conn)?;
+    let removed = conn
+        .execute("DELETE FROM data_sources WHERE id = ?1", [id])?;
+    if removed == 0 {
+        return Err(AppError::NotFound(format!(
+            "Data source '{}' not found",
+            id
+        )));
+    }
+    // The active source may have been the one removed
+    *ACTIVE_DB_PATH.write().unwrap() = None;
+    Ok(())
+}
+
+pub fn activate_source(id: &str) -> AppResult<DataSource> {
+    let source = resolve_source(Some(id))?;
+    app_state::set_value(ACTIVE_SOURCE_KEY, &source.id)?;
+    *ACTIVE_DB_PATH.write().unwrap() = Some(PathBuf::from(&source.db_path));
+    Ok(DataSource {
+        active: true,
+        ..source
+    })
+}
+
+/// Remembers which API key a source was initialized with.
+pub fn set_source_key(id: &str, key_id: &str) -> SqliteResult<()> {
+    let conn = get_app_connection()?;
+    ensure_table(&conn)?;
+    conn.execute(
+        "UPDATE data_sources SET key_id = ?2 WHERE id = ?1",
+        params![id, key_id],
+    )?;
+    Ok(())
+}
+
+// ==================== Tauri Commands ====================
+
+#[tauri::command]
+pub async fn list_data_sources() -> AppResult<Vec<DataSource>> {
+    list_sources().map_err(AppError::from)
+}
+
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn add_data_source(name: String, dbPath: Option<String>) -> AppResult<DataSource> {
+    insert_source(&name, dbPath.as_deref())
+}
+
+#[tauri::command]
+pub async fn remove_data_source(id: String) -> AppResult<()> {
+    delete_source(&id)
+}
+
+#[tauri::command]
+pub async fn set_active_data_source(id: String) -> AppResult<DataSource> {
+    activate_source(&id)
+}
@@ -0,0 +1,229 @@
+// Read-only SQL console
+//
+// Runs a user-written SELECT against the active source's database for questions the canned
+// queries don't answer. Safety rails:
+//
+//   - the database is opened read-only, so nothing can be written even by mistake
+//   - only a single SELECT (or WITH ... SELECT) statement is accepted
+//   - at most MAX_ROWS rows are returned
+//   - a query running longer than QUERY_TIMEOUT is interrupted
+//   - it is off in presentation mode, where amounts of money must not be shown
+
+use crate::settings;
+use crate::sources;
+use crate::types::{AppError, AppResult};
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{params_from_iter, Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+const DEFAULT_ROW_LIMIT: usize = 1000;
+const MAX_ROWS: usize = 10_000;
+const QUERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SqlColumn {
+    pub name: String,
+    /// Storage class of the column's first non-null value: "integer", "real", "text", "blob",
+    /// or "null" when every value is null
+    pub value_type: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SqlResult {
+    pub columns: Vec<SqlColumn>,
+    /// Values as JSON: numbers, strings, null, and blobs as hex strings
+    pub rows: Vec<Vec<serde_json::Value>>,
+    /// More rows matched than were returned
+    pub truncated: bool,
+    pub elapsed_ms: u64,
+}
+
+// The statement with leading whitespace and comments removed
+fn strip_leading_comments(mut sql: &str) -> &str {
+    loop {
+        sql = sql.trim_start();
+        if let Some(rest) = sql.strip_prefix("--") {
+            sql = rest.split_once('\n').map(|(_, rest)| rest).unwrap_or("");
+        } else if let Some(rest) = sql.strip_prefix("/*") {
+            sql = rest.split_once("*/").map(|(_, rest)| rest).unwrap_or("");
+        } else {
+            return sql;
+        }
+    }
+}
+
+fn validate_sql(sql: &str) -> AppResult<()> {
+    let first_word = strip_leading_comments(sql)
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    if first_word != "select" && first_word != "with" {
+        return Err(AppError::InvalidInput(
+            "Only SELECT statements can be run in the console".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn to_sql_value(value: &serde_json::Value) -> AppResult<Value> {
+    Ok(match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Integer(i64::from(*b)),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Real(n.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(s) => Value::Text(s.clone()),
+        other => {
+            return Err(AppError::InvalidInput(format!(
+                "Unsupported query parameter {}; use numbers, strings, booleans or null",
+                other
+            )))
+        }
+    })
+}
+
+fn to_json(value: ValueRef) -> (serde_json::Value, &'static str) {
+    match value {
+        ValueRef::Null => (serde_json::Value::Null, "null"),
+        ValueRef::Integer(i) => (i.into(), "integer"),
+        ValueRef::Real(f) => (
+            serde_json::Number::from_f64(f)
+                .map(serde_json::Value::Number)
+                .unwrap_or(serde_json::Value::Null),
+            "real",
+        ),
+        ValueRef::Text(t) => (String::from_utf8_lossy(t).into_owned().into(), "text"),
+        ValueRef::Blob(b) => (
+            b.iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<String>()
+                .into(),
+            "blob",
+        ),
+    }
+}
+
+fn run_query(
+    conn: &Connection,
+    sql: &str,
+    params: &[Value],
+    limit: usize,
+) -> AppResult<(Vec<SqlColumn>, Vec<Vec<serde_json::Value>>, bool)> {
+    let mut stmt = conn.prepare(sql)?;
+    if !stmt.readonly() {
+        return Err(AppError::InvalidInput(
+            "Only statements that don't change the database can be run".to_string(),
+        ));
+    }
+    let mut columns: Vec<SqlColumn> = stmt
+        .column_names()
+        .into_iter()
+        .map(|name| SqlColumn {
+            name: name.to_string(),
+            value_type: "null".to_string(),
+        })
+        .collect();
+
+    let mut rows = Vec::new();
+    let mut truncated = false;
+    let mut result = stmt.query(params_from_iter(params))?;
+    while let Some(row) = result.next()? {
+        if rows.len() == limit {
+            truncated = true;
+            break;
+        }
+        let mut values = Vec::with_capacity(columns.len());
+        for (i, column) in columns.iter_mut().enumerate() {
+            let (value, value_type) = to_json(row.get_ref(i)?);
+            if column.value_type == "null" {
+                column.value_type = value_type.to_string();
+            }
+            values.push(value);
+        }
+        rows.push(values);
+    }
+    Ok((columns, rows, truncated))
+}
+
+/// Runs one read-only SELECT against the active source's database.
+pub fn run_readonly(
+    sql: &str,
+    params: &[serde_json::Value],
+    limit: Option<usize>,
+) -> AppResult<SqlResult> {
+    validate_sql(sql)?;
+    let params = params
+        .iter()
+        .map(to_sql_value)
+        .collect::<AppResult<Vec<_>>>()?;
+    let limit = limit.unwrap_or(DEFAULT_ROW_LIMIT).clamp(1, MAX_ROWS);
+
+    let path = sources::active_database_path();
+    if !path.exists() {
+        return Err(AppError::NotFound(format!(
+            "Database not found at {}",
+            path.display()
+        )));
+    }
+    let conn = Connection::open_with_flags(
+        &path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+
+    // Interrupts the query once the timeout passes, unless it finished first
+    let interrupt = conn.get_interrupt_handle();
+    let (done_tx, done_rx) = mpsc::channel::<()>();
+    std::thread::spawn(move || {
+        if done_rx.recv_timeout(QUERY_TIMEOUT) == Err(mpsc::RecvTimeoutError::Timeout) {
+            interrupt.interrupt();
+        }
+    });
+
+    let started = Instant::now();
+    let result = run_query(&conn, sql, &params, limit);
+    let _ = done_tx.send(());
+    let elapsed = started.elapsed();
+
+    let (columns, rows, truncated) = match result {
+        Err(AppError::Database(message)) if elapsed >= QUERY_TIMEOUT => {
+            return Err(AppError::Timeout(format!(
+                "Query took longer than {} seconds and was stopped ({})",
+                QUERY_TIMEOUT.as_secs(),
+                message
+            )))
+        }
+        other => other?,
+    };
+    Ok(SqlResult {
+        columns,
+        rows,
+        truncated,
+        elapsed_ms: elapsed.as_millis() as u64,
+    })
+}
+
+// ==================== Tauri Commands ====================
+
+/// Runs a read-only SELECT with positional parameters (?1, ?2, ...). `limit` defaults to 1000
+/// rows and is capped at 10,000.
+#[tauri::command]
+pub async fn run_readonly_sql(
+    sql: String,
+    params: Option<Vec<serde_json::Value>>,
+    limit: Option<usize>,
+) -> AppResult<SqlResult> {
+    // Any column could be money, so nothing could be masked
+    if settings::load_settings().presentation_mode {
+        return Err(AppError::InvalidInput(
+            "The SQL console is not available in presentation mode".to_string(),
+        ));
+    }
+    tokio::task::spawn_blocking(move || run_readonly(&sql, &params.unwrap_or_default(), limit))
+        .await?
+}
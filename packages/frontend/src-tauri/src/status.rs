@@ -0,0 +1,125 @@
+// Aggregated app status
+//
+// `get_app_status` answers everything the frontend needs at startup (CLI, sales database, API
+// keys, sync queue) in one call, instead of several commands whose results race each other.
+
+use crate::cli;
+use crate::database::get_database_path;
+use crate::keys::{self, KeySyncStatus};
+use crate::queries::get_app_id_column;
+use crate::sources::{self, DataSource};
+use crate::sync::{SyncQueueStatus, SyncState};
+use crate::types::AppResult;
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CliInfo {
+    pub installed: bool,
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseInfo {
+    pub path: String,
+    pub exists: bool,
+    pub row_count: i64,
+    pub app_count: i64,
+    pub first_date: Option<String>,
+    pub last_date: Option<String>,
+    /// `PRAGMA user_version` of the sales database, as set by the CLI
+    pub schema_version: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppStatus {
+    pub cli: CliInfo,
+    pub database: DatabaseInfo,
+    pub active_source: Option<DataSource>,
+    /// Whether the active source has been initialized with an API key
+    pub api_key_configured: bool,
+    pub api_keys: Vec<KeySyncStatus>,
+    /// Most recent successful fetch with any key
+    pub last_sync_at: Option<String>,
+    pub sync: SyncQueueStatus,
+    /// Tasks the running fetch still has to do, if it reported a total
+    pub pending_tasks: Option<u64>,
+}
+
+fn read_database_info(db_path: &Path, exists: bool) -> DatabaseInfo {
+    let mut info = DatabaseInfo {
+        path: db_path.to_string_lossy().to_string(),
+        exists,
+        ..Default::default()
+    };
+    if !exists {
+        return info;
+    }
+    let Ok(conn) = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY) else {
+        return info;
+    };
+    info.schema_version = conn.query_row("PRAGMA user_version", [], |r| r.get(0)).ok();
+    let sql = format!(
+        "SELECT COUNT(*), COUNT(DISTINCT {}), MIN(date), MAX(date) FROM sales_data",
+        get_app_id_column(&conn)
+    );
+    if let Ok((rows, apps, first, last)) = conn.query_row(&sql, [], |r| {
+        Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?))
+    }) {
+        info.row_count = rows;
+        info.app_count = apps;
+        info.first_date = first;
+        info.last_date = last;
+    }
+    info
+}
+
+// ==================== Tauri Commands ====================
+
+#[tauri::command]
+pub async fn get_app_status(state: tauri::State<'_, SyncState>) -> AppResult<AppStatus> {
+    // Also removes a sales database that is not usable, like the startup check always did
+    let cli_status = cli::get_cli_status().await?;
+    let sync = state.snapshot();
+
+    let database_exists = cli_status.database_exists;
+    let (database, active_source, api_keys) = tokio::task::spawn_blocking(move || {
+        let database = read_database_info(&get_database_path(), database_exists);
+        let active_source = sources::resolve_source(None).ok();
+        let api_keys = keys::list_key_sync_status()?;
+        Ok::<_, rusqlite::Error>((database, active_source, api_keys))
+    })
+    .await??;
+
+    let last_sync_at = api_keys
+        .iter()
+        .filter_map(|key| key.last_sync_at.clone())
+        .max();
+    // Databases initialized before keys were tracked still hold a key
+    let api_key_configured = database.exists
+        || active_source
+            .as_ref()
+            .is_some_and(|source| source.key_id.is_some());
+    let pending_tasks = match (sync.running, sync.tasks_total, sync.tasks_done) {
+        (true, Some(total), done) => Some(total.saturating_sub(done.unwrap_or(0))),
+        _ => None,
+    };
+
+    Ok(AppStatus {
+        cli: CliInfo {
+            installed: cli_status.installed,
+            version: cli_status.version,
+        },
+        database,
+        active_source,
+        api_key_configured,
+        api_keys,
+        last_sync_at,
+        sync,
+        pending_tasks,
+    })
+}
@@ -0,0 +1,300 @@
+// Sync progress tracking for CLI fetches
+//
+// The CLI reports progress as free-form text lines. This module turns those lines into a
+// structured status (dates done / total, current date, records per second, ETA) that is kept in
+// managed state and emitted as `sync-queue-progress` events, so the frontend does not have to
+// poll or parse output itself.
+
+use crate::app_state;
+use crate::types::{AppError, AppResult};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::oneshot;
+
+// Window used for the rolling records-per-second rate
+const RATE_WINDOW: Duration = Duration::from_secs(30);
+
+// app_state key of the persisted pause flag
+const SYNC_PAUSED_KEY: &str = "sync_paused";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncQueueStatus {
+    pub running: bool,
+    pub paused: bool,
+    /// Unix timestamp (ms) of when the current or last fetch started
+    pub started_at: Option<u64>,
+    pub tasks_done: Option<u64>,
+    pub tasks_total: Option<u64>,
+    pub current_date: Option<String>,
+    pub records_processed: u64,
+    pub records_per_second: Option<f64>,
+    pub eta_seconds: Option<u64>,
+    pub last_message: Option<String>,
+}
+
+#[derive(Default)]
+struct SyncTracker {
+    status: SyncQueueStatus,
+    started: Option<Instant>,
+    // (time, records_processed) samples inside RATE_WINDOW
+    samples: VecDeque<(Instant, u64)>,
+    // Process id of the running CLI fetch (also its process group on Unix)
+    pid: Option<u32>,
+    // Signals the fetch loop to stop the running CLI process
+    cancel: Option<oneshot::Sender<()>>,
+}
+
+#[derive(Default)]
+pub struct SyncState {
+    tracker: Mutex<SyncTracker>,
+}
+
+impl SyncState {
+    /// Marks a fetch as running. Fails with Busy while another one is, since both would run the
+    /// CLI and share this status.
+    pub fn start(&self) -> AppResult<SyncQueueStatus> {
+        let mut tracker = self.tracker.lock().unwrap();
+        if tracker.status.running {
+            return Err(AppError::Busy(
+                "A fetch is already running. Wait for it to finish or cancel it.".to_string(),
+            ));
+        }
+        let now = Instant::now();
+        *tracker = SyncTracker {
+            status: SyncQueueStatus {
+                running: true,
+                started_at: Some(unix_millis()),
+                ..Default::default()
+            },
+            started: Some(now),
+            samples: VecDeque::from([(now, 0)]),
+            pid: None,
+            cancel: None,
+        };
+        Ok(tracker.status.clone())
+    }
+
+    /// Updates the status from one line of CLI output and returns the new snapshot.
+    pub fn observe_line(&self, line: &str) -> SyncQueueStatus {
+        let mut tracker = self.tracker.lock().unwrap();
+        let now = Instant::now();
+
+        if let Some(date) = find_date(line) {
+            tracker.status.current_date = Some(date);
+        }
+        if let Some((done, total)) = find_fraction(line) {
+            tracker.status.tasks_done = Some(done);
+            tracker.status.tasks_total = Some(total);
+        }
+        if let Some(records) = find_record_count(line) {
+            tracker.status.records_processed += records;
+        }
+        tracker.status.last_message = Some(line.to_string());
+
+        let records = tracker.status.records_processed;
+        tracker.samples.push_back((now, records));
+        while tracker.samples.len() > 2 && now.duration_since(tracker.samples[0].0) > RATE_WINDOW {
+            tracker.samples.pop_front();
+        }
+        tracker.status.records_per_second = tracker.samples.front().and_then(|(t, r)| {
+            let secs = now.duration_since(*t).as_secs_f64();
+            (secs > 0.0).then(|| (records - r) as f64 / secs)
+        });
+
+        tracker.status.eta_seconds = match (
+            tracker.started,
+            tracker.status.tasks_done,
+            tracker.status.tasks_total,
+        ) {
+            (Some(started), Some(done), Some(total)) if done > 0 && total >= done => {
+                let per_task = now.duration_since(started).as_secs_f64() / done as f64;
+                Some((per_task * (total - done) as f64).round() as u64)
+            }
+            _ => None,
+        };
+
+        tracker.status.clone()
+    }
+
+    /// Registers the spawned CLI process and returns the receiver that fires on `cancel_fetch`.
+    pub fn set_process(&self, pid: Option<u32>) -> oneshot::Receiver<()> {
+        let (sender, receiver) = oneshot::channel();
+        let mut tracker = self.tracker.lock().unwrap();
+        tracker.pid = pid;
+        tracker.cancel = Some(sender);
+        receiver
+    }
+
+    /// Asks the running fetch to stop. Returns false when nothing is running.
+    pub fn cancel(&self) -> bool {
+        let mut tracker = self.tracker.lock().unwrap();
+        match tracker.cancel.take() {
+            Some(sender) => {
+                tracker.status.last_message = Some("Cancelling fetch...".to_string());
+                sender.send(()).is_ok()
+            }
+            None => false,
+        }
+    }
+
+    pub fn finish(&self) -> SyncQueueStatus {
+        let mut tracker = self.tracker.lock().unwrap();
+        tracker.pid = None;
+        tracker.cancel = None;
+        tracker.status.running = false;
+        tracker.status.eta_seconds = None;
+        tracker.status.clone()
+    }
+
+    pub fn snapshot(&self) -> SyncQueueStatus {
+        let mut status = self.tracker.lock().unwrap().status.clone();
+        status.paused = is_sync_paused();
+        status
+    }
+
+    // Notes on a running fetch that it is the last one before the pause
+    fn mark_paused(&self, pause: bool) {
+        let mut tracker = self.tracker.lock().unwrap();
+        if pause && tracker.status.running {
+            tracker.status.last_message =
+                Some("Sync will pause when the current fetch is done".to_string());
+        }
+        tracker.status.paused = pause;
+    }
+}
+
+/// Whether syncing was paused by the user. Persisted so a restart does not silently resume.
+pub fn is_sync_paused() -> bool {
+    app_state::get_flag(SYNC_PAUSED_KEY)
+}
+
+/// Sends a signal to the CLI's process group, which includes any helpers it spawned.
+#[cfg(unix)]
+pub fn signal_process_group(pid: u32, signal: &str) -> AppResult<()> {
+    let status = std::process::Command::new("kill")
+        .args([&format!("-{}", signal), "--", &format!("-{}", pid)])
+        .status()
+        .map_err(|e| AppError::Io(format!("Failed to signal CLI process: {}", e)))?;
+    if !status.success() {
+        return Err(AppError::Internal(format!(
+            "Failed to signal CLI process {}",
+            pid
+        )));
+    }
+    Ok(())
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+// Finds the first YYYY-MM-DD in a line
+fn find_date(line: &str) -> Option<String> {
+    let bytes = line.as_bytes();
+    bytes.windows(10).enumerate().find_map(|(i, w)| {
+        let is_date = w.iter().enumerate().all(|(j, b)| match j {
+            4 | 7 => *b == b'-',
+            _ => b.is_ascii_digit(),
+        });
+        let standalone = i + 10 == bytes.len() || !bytes[i + 10].is_ascii_digit();
+        (is_date && standalone).then(|| line[i..i + 10].to_string())
+    })
+}
+
+// Finds progress counters written as "12/345" or "12 of 345"
+fn find_fraction(line: &str) -> Option<(u64, u64)> {
+    let words: Vec<&str> = line
+        .split(|c: char| c.is_whitespace() || c == '(' || c == ')' || c == '[' || c == ']')
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    for (i, word) in words.iter().enumerate() {
+        if let Some((a, b)) = word.trim_end_matches([',', ':']).split_once('/') {
+            if let (Ok(done), Ok(total)) = (a.parse::<u64>(), b.parse::<u64>()) {
+                if total > 0 && done <= total {
+                    return Some((done, total));
+                }
+            }
+        }
+        if *word == "of" && i > 0 && i + 1 < words.len() {
+            let done = words[i - 1].parse::<u64>();
+            let total = words[i + 1].trim_end_matches([',', ':']).parse::<u64>();
+            if let (Ok(done), Ok(total)) = (done, total) {
+                if total > 0 && done <= total {
+                    return Some((done, total));
+                }
+            }
+        }
+    }
+    None
+}
+
+// Finds counts written as "1234 records" / "1,234 rows" / "56 sales"
+fn find_record_count(line: &str) -> Option<u64> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    words.windows(2).find_map(|pair| {
+        let unit = pair[1]
+            .trim_end_matches(|c: char| !c.is_alphabetic())
+            .to_lowercase();
+        if matches!(
+            unit.as_str(),
+            "record" | "records" | "row" | "rows" | "sales"
+        ) {
+            pair[0].replace(',', "").parse::<u64>().ok()
+        } else {
+            None
+        }
+    })
+}
+
+// ==================== Tauri Commands ====================
+
+#[tauri::command]
+pub async fn get_sync_queue_status(
+    state: tauri::State<'_, SyncState>,
+) -> AppResult<SyncQueueStatus> {
+    Ok(state.snapshot())
+}
+
+#[tauri::command]
+pub async fn cancel_fetch(state: tauri::State<'_, SyncState>) -> AppResult<()> {
+    if state.cancel() {
+        Ok(())
+    } else {
+        Err(AppError::NotFound("No fetch is running".to_string()))
+    }
+}
+
+/// Pauses syncing: no CLI fetch starts until `resume_sync`. A fetch that is running is not
+/// interrupted but finishes its run, since a CLI frozen mid-request would keep its connections
+/// open and hold the database lock until it was resumed.
+#[tauri::command]
+pub async fn pause_sync(
+    app: AppHandle,
+    state: tauri::State<'_, SyncState>,
+) -> AppResult<SyncQueueStatus> {
+    app_state::set_flag(SYNC_PAUSED_KEY, true)?;
+    state.mark_paused(true);
+    let status = state.snapshot();
+    let _ = app.emit("sync-queue-progress", &status);
+    Ok(status)
+}
+
+#[tauri::command]
+pub async fn resume_sync(
+    app: AppHandle,
+    state: tauri::State<'_, SyncState>,
+) -> AppResult<SyncQueueStatus> {
+    app_state::set_flag(SYNC_PAUSED_KEY, false)?;
+    state.mark_paused(false);
+    let status = state.snapshot();
+    let _ = app.emit("sync-queue-progress", &status);
+    Ok(status)
+}
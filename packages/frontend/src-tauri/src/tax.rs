@@ -0,0 +1,215 @@
+// Sales tax by jurisdiction
+//
+// Steam collects VAT, GST and sales tax on the developer's behalf and reports it per row as
+// net_tax_usd. For tax filings the collected tax is grouped by period and jurisdiction: EU VAT
+// (the 27 member states, per the bundled country data), US sales tax, and everything else.
+// Revenue is shown both as reported (gross, tax included) and with the tax taken out.
+
+use crate::countries;
+use crate::presentation;
+use crate::queries::{
+    build_where_clause, connection_for, get_app_id_column, get_source_column, QueryFilters,
+};
+use crate::schema::column_exists;
+use crate::types::{AppError, AppResult, Date, Granularity};
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaxJurisdiction {
+    EuVat,
+    UsSalesTax,
+    Other,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CountryTax {
+    pub country_code: String,
+    pub country_name: Option<String>,
+    pub gross_revenue: f64,
+    pub tax: f64,
+    pub revenue_excluding_tax: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JurisdictionTax {
+    pub jurisdiction: TaxJurisdiction,
+    pub gross_revenue: f64,
+    pub tax: f64,
+    pub revenue_excluding_tax: f64,
+    /// Largest tax amount first
+    pub countries: Vec<CountryTax>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaxPeriod {
+    pub period_start: Date,
+    pub period_end: Date,
+    pub gross_revenue: f64,
+    pub tax: f64,
+    pub revenue_excluding_tax: f64,
+    /// In the order EU VAT, US sales tax, other; jurisdictions without sales are left out
+    pub jurisdictions: Vec<JurisdictionTax>,
+}
+
+fn jurisdiction_of(country_code: &str, eu_member: bool) -> TaxJurisdiction {
+    if eu_member {
+        TaxJurisdiction::EuVat
+    } else if country_code == "US" {
+        TaxJurisdiction::UsSalesTax
+    } else {
+        TaxJurisdiction::Other
+    }
+}
+
+fn add_row(period: &mut TaxPeriod, jurisdiction: TaxJurisdiction, country: CountryTax) {
+    period.gross_revenue += country.gross_revenue;
+    period.tax += country.tax;
+    period.revenue_excluding_tax += country.revenue_excluding_tax;
+
+    let index = match period
+        .jurisdictions
+        .iter()
+        .position(|j| j.jurisdiction == jurisdiction)
+    {
+        Some(index) => index,
+        None => {
+            period.jurisdictions.push(JurisdictionTax {
+                jurisdiction,
+                gross_revenue: 0.0,
+                tax: 0.0,
+                revenue_excluding_tax: 0.0,
+                countries: Vec::new(),
+            });
+            period.jurisdictions.len() - 1
+        }
+    };
+    let entry = &mut period.jurisdictions[index];
+    entry.gross_revenue += country.gross_revenue;
+    entry.tax += country.tax;
+    entry.revenue_excluding_tax += country.revenue_excluding_tax;
+    entry.countries.push(country);
+}
+
+pub fn tax_summary(
+    conn: &Connection,
+    period: Granularity,
+    filters: QueryFilters,
+) -> AppResult<Vec<TaxPeriod>> {
+    if !column_exists(conn, "sales_data", "net_tax_usd") {
+        return Err(AppError::NotFound(
+            "The database has no net_tax_usd column; fetch the data again with a current CLI"
+                .to_string(),
+        ));
+    }
+    countries::ensure_lookup_table(conn)?;
+
+    // The limit, sorting and country filter of the filters don't apply to a tax report
+    let filters = QueryFilters {
+        country_code: None,
+        ..filters.complete_days_only()
+    };
+    let app_id_col = get_app_id_column(conn);
+    let where_clause = build_where_clause(&filters, &app_id_col, get_source_column(conn))
+        .sale_items_only(conn, &filters);
+
+    let mut params: Vec<Value> = Vec::new();
+    if let Some(start_date) = filters.start_date {
+        params.push(start_date.to_string().into());
+    }
+    if let Some(end_date) = filters.end_date {
+        params.push(end_date.to_string().into());
+    }
+    for app_id in filters.app_ids.iter().flatten() {
+        params.push((*app_id).into());
+    }
+    if let Some(source) = filters.source {
+        params.push(source.into());
+    }
+    for sale_type in filters.sale_types.iter().flatten() {
+        params.push(sale_type.clone().into());
+    }
+
+    let sql = format!(
+        "SELECT t.period, t.country_code, c.country_name, COALESCE(c.eu_member, 0), t.gross, t.tax
+        FROM (
+            SELECT
+                {} as period,
+                COALESCE(country_code, '') as country_code,
+                COALESCE(SUM(CAST(gross_sales_usd AS REAL)), 0) as gross,
+                COALESCE(SUM(CAST(net_tax_usd AS REAL)), 0) as tax
+            FROM sales_data {}
+            GROUP BY period, 2
+        ) t
+        LEFT JOIN lookup_countries c ON c.country_code = t.country_code
+        ORDER BY t.period, t.tax DESC, t.gross DESC",
+        period.sql_period_start("date"),
+        where_clause.clause
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params_from_iter(params), |r| {
+        Ok((
+            r.get::<_, Date>(0)?,
+            r.get::<_, String>(1)?,
+            r.get::<_, Option<String>>(2)?,
+            r.get::<_, bool>(3)?,
+            r.get::<_, f64>(4)?,
+            r.get::<_, f64>(5)?,
+        ))
+    })?;
+
+    let mut periods: Vec<TaxPeriod> = Vec::new();
+    for row in rows {
+        let (period_start, country_code, country_name, eu_member, gross, tax) = row?;
+        if periods.last().map(|p| p.period_start) != Some(period_start) {
+            periods.push(TaxPeriod {
+                period_start,
+                period_end: period.period_end(period_start),
+                gross_revenue: 0.0,
+                tax: 0.0,
+                revenue_excluding_tax: 0.0,
+                jurisdictions: Vec::new(),
+            });
+        }
+        let jurisdiction = jurisdiction_of(&country_code, eu_member);
+        let current = periods.last_mut().expect("a period was just pushed");
+        add_row(
+            current,
+            jurisdiction,
+            CountryTax {
+                country_code,
+                country_name,
+                gross_revenue: gross,
+                tax,
+                revenue_excluding_tax: gross - tax,
+            },
+        );
+    }
+    for period in &mut periods {
+        period.jurisdictions.sort_by_key(|j| j.jurisdiction as u8);
+    }
+    Ok(periods)
+}
+
+// ==================== Tauri Commands ====================
+
+/// Collected tax and revenue with and without it, per `period` (e.g. "quarter") and
+/// jurisdiction. Uses the date range, apps and source of `filters`.
+#[tauri::command]
+pub async fn query_tax_summary(
+    period: Granularity,
+    filters: QueryFilters,
+) -> AppResult<Vec<TaxPeriod>> {
+    let filters = filters.resolve_date_range()?;
+    tokio::task::spawn_blocking(move || {
+        let conn = connection_for(&filters)?;
+        tax_summary(&conn, period, filters)
+    })
+    .await?
+    .and_then(presentation::present)
+}
@@ -0,0 +1,312 @@
+// Store traffic and the purchase funnel
+//
+// Steam has no Web API for store traffic or wishlists; both are only available as CSV exports on
+// the Steamworks partner site (Store traffic stats, Wishlist report). Those exports are imported
+// into `traffic_daily` in the source database, one row per app and day, and lined up with units
+// sold from sales_data:
+//
+//   impressions -> visits -> wishlist additions -> purchases
+//
+// The importer looks for a header row with a date column and any of the known metric columns, so
+// preamble lines and extra columns in the exports are skipped. Importing a file again replaces
+// the metrics it contains for its dates and leaves the others alone.
+
+use crate::annotations::is_valid_date;
+use crate::database::begin_write;
+use crate::queries::{get_app_id_column, get_connection, get_write_connection};
+use crate::schema::table_exists;
+use crate::types::{AppError, AppResult, Date};
+use rusqlite::{params, Connection, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tracing::info;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Metric {
+    Impressions,
+    Visits,
+    WishlistAdds,
+}
+
+impl Metric {
+    fn column(self) -> &'static str {
+        match self {
+            Metric::Impressions => "impressions",
+            Metric::Visits => "visits",
+            Metric::WishlistAdds => "wishlist_adds",
+        }
+    }
+
+    // Header names used by the Steamworks exports, lowercased
+    fn from_header(header: &str) -> Option<Self> {
+        match header {
+            "impressions" | "total impressions" => Some(Metric::Impressions),
+            "visits" | "page visits" | "total visits" | "store page visits" => Some(Metric::Visits),
+            "wishlist additions" | "adds" | "additions" | "wishlist adds" => {
+                Some(Metric::WishlistAdds)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrafficImportResult {
+    pub app_id: i64,
+    pub days: usize,
+    /// Metrics found in the file, e.g. ["impressions", "visits"]
+    pub metrics: Vec<String>,
+    /// Data rows without a valid date or numbers
+    pub skipped_rows: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FunnelDay {
+    pub date: String,
+    pub impressions: Option<i64>,
+    pub visits: Option<i64>,
+    pub wishlist_adds: Option<i64>,
+    pub units: i64,
+    /// Visits per impression
+    pub click_through: Option<f64>,
+    /// Wishlist additions per visit
+    pub wishlist_rate: Option<f64>,
+    /// Units sold per visit
+    pub purchase_rate: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Funnel {
+    pub app_id: i64,
+    pub total_impressions: i64,
+    pub total_visits: i64,
+    pub total_wishlist_adds: i64,
+    pub total_units: i64,
+    pub days: Vec<FunnelDay>,
+}
+
+fn ensure_table(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS traffic_daily (
+            app_id INTEGER NOT NULL,
+            date TEXT NOT NULL,
+            impressions INTEGER,
+            visits INTEGER,
+            wishlist_adds INTEGER,
+            updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+            PRIMARY KEY (app_id, date)
+        );",
+    )
+}
+
+// Splits a CSV line, honoring quotes ("1,234" is one field)
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+fn parse_count(value: &str) -> Option<i64> {
+    let cleaned: String = value.chars().filter(|c| *c != ',' && *c != ' ').collect();
+    if cleaned.is_empty() {
+        return None;
+    }
+    cleaned.parse::<f64>().ok().map(|v| v.round() as i64)
+}
+
+// (date column, metric columns) of a header row
+fn parse_header(fields: &[String]) -> Option<(usize, Vec<(usize, Metric)>)> {
+    let names: Vec<String> = fields
+        .iter()
+        .map(|f| f.trim().trim_start_matches('\u{feff}').to_lowercase())
+        .collect();
+    let date_index = names.iter().position(|n| n == "date" || n == "day")?;
+    let metrics: Vec<(usize, Metric)> = names
+        .iter()
+        .enumerate()
+        .filter_map(|(i, n)| Metric::from_header(n).map(|m| (i, m)))
+        .collect();
+    (!metrics.is_empty()).then_some((date_index, metrics))
+}
+
+/// Imports a Steamworks traffic or wishlist CSV export for an app.
+pub fn import_csv(app_id: i64, content: &str) -> AppResult<TrafficImportResult> {
+    let mut lines = content.lines();
+    let (date_index, metrics) = lines
+        .by_ref()
+        .find_map(|line| parse_header(&split_csv_line(line)))
+        .ok_or_else(|| {
+            AppError::InvalidInput(
+                "No header with a Date column and impressions, visits or wishlist additions found"
+                    .to_string(),
+            )
+        })?;
+
+    let mut rows = Vec::new();
+    let mut skipped_rows = 0;
+    for line in lines.filter(|l| !l.trim().is_empty()) {
+        let fields = split_csv_line(line);
+        let date = fields.get(date_index).map(|d| d.trim()).unwrap_or_default();
+        let values: Vec<(Metric, Option<i64>)> = metrics
+            .iter()
+            .map(|&(i, metric)| (metric, fields.get(i).and_then(|v| parse_count(v))))
+            .collect();
+        if !is_valid_date(date) || values.iter().all(|(_, v)| v.is_none()) {
+            skipped_rows += 1;
+            continue;
+        }
+        rows.push((date.to_string(), values));
+    }
+
+    let conn = get_write_connection()?;
+    ensure_table(&conn)?;
+    let tx = begin_write(&conn)?;
+    for (date, values) in &rows {
+        tx.execute(
+            "INSERT OR IGNORE INTO traffic_daily (app_id, date) VALUES (?1, ?2)",
+            params![app_id, date],
+        )?;
+        for (metric, value) in values.iter().filter(|(_, v)| v.is_some()) {
+            tx.execute(
+                &format!(
+                    "UPDATE traffic_daily SET {} = ?3,
+                        updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+                     WHERE app_id = ?1 AND date = ?2",
+                    metric.column()
+                ),
+                params![app_id, date, value],
+            )?;
+        }
+    }
+    tx.commit()?;
+
+    info!(
+        "Imported {} day(s) of traffic for app {}, skipped {} row(s)",
+        rows.len(),
+        app_id,
+        skipped_rows
+    );
+    Ok(TrafficImportResult {
+        app_id,
+        days: rows.len(),
+        metrics: metrics
+            .iter()
+            .map(|(_, m)| m.column().to_string())
+            .collect(),
+        skipped_rows,
+    })
+}
+
+fn ratio(numerator: Option<i64>, denominator: Option<i64>) -> Option<f64> {
+    match (numerator, denominator) {
+        (Some(n), Some(d)) if d > 0 => Some(n as f64 / d as f64),
+        _ => None,
+    }
+}
+
+/// Impressions, visits, wishlist additions and units sold per day for an app. Without dates,
+/// covers the days traffic was imported for.
+pub fn get_funnel(
+    app_id: i64,
+    start_date: Option<Date>,
+    end_date: Option<Date>,
+) -> SqliteResult<Funnel> {
+    let conn = get_connection()?;
+    if !table_exists(&conn, "traffic_daily") {
+        return Ok(Funnel {
+            app_id,
+            total_impressions: 0,
+            total_visits: 0,
+            total_wishlist_adds: 0,
+            total_units: 0,
+            days: Vec::new(),
+        });
+    }
+    let app_id_col = get_app_id_column(&conn);
+
+    let sql = format!(
+        "SELECT t.date, t.impressions, t.visits, t.wishlist_adds,
+            COALESCE((SELECT SUM(s.net_units_sold) FROM sales_data s
+                      WHERE s.date = t.date AND s.{} = t.app_id), 0)
+         FROM traffic_daily t
+         WHERE t.app_id = ?1
+           AND (?2 IS NULL OR t.date >= ?2)
+           AND (?3 IS NULL OR t.date <= ?3)
+         ORDER BY t.date",
+        app_id_col
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let days = stmt
+        .query_map(params![app_id, start_date, end_date], |r| {
+            let impressions: Option<i64> = r.get(1)?;
+            let visits: Option<i64> = r.get(2)?;
+            let wishlist_adds: Option<i64> = r.get(3)?;
+            let units: i64 = r.get(4)?;
+            Ok(FunnelDay {
+                date: r.get(0)?,
+                impressions,
+                visits,
+                wishlist_adds,
+                units,
+                click_through: ratio(visits, impressions),
+                wishlist_rate: ratio(wishlist_adds, visits),
+                purchase_rate: ratio(Some(units), visits),
+            })
+        })?
+        .collect::<SqliteResult<Vec<_>>>()?;
+
+    let sum = |f: fn(&FunnelDay) -> Option<i64>| days.iter().filter_map(f).sum::<i64>();
+    Ok(Funnel {
+        app_id,
+        total_impressions: sum(|d| d.impressions),
+        total_visits: sum(|d| d.visits),
+        total_wishlist_adds: sum(|d| d.wishlist_adds),
+        total_units: sum(|d| Some(d.units)),
+        days,
+    })
+}
+
+// ==================== Tauri Commands ====================
+
+/// Imports a traffic or wishlist CSV exported from the Steamworks partner site.
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn import_traffic_csv(appId: i64, path: String) -> AppResult<TrafficImportResult> {
+    if !Path::new(&path).is_file() {
+        return Err(AppError::NotFound(format!("File not found: {}", path)));
+    }
+    tokio::task::spawn_blocking(move || {
+        let content = std::fs::read_to_string(&path)?;
+        import_csv(appId, &content)
+    })
+    .await?
+}
+
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn query_funnel(
+    appId: i64,
+    startDate: Option<Date>,
+    endDate: Option<Date>,
+) -> AppResult<Funnel> {
+    tokio::task::spawn_blocking(move || get_funnel(appId, startDate, endDate))
+        .await?
+        .map_err(AppError::from)
+}
@@ -0,0 +1,169 @@
+// System tray icon
+//
+// Shows whether a sync is running, when data was last synced (in the tooltip), and offers
+// "Sync now" for every data source that has been initialized. With the close-to-tray setting on,
+// closing the window only hides it, so scheduled syncs keep running.
+
+use crate::cli;
+use crate::keys;
+use crate::settings;
+use crate::sources;
+use crate::sync::{self, SyncQueueStatus, SyncState};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::menu::{Menu, MenuItem, PredefinedMenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Listener, Manager, Wry};
+use tracing::{info, warn};
+
+const TRAY_ID: &str = "main";
+const APP_NAME: &str = "Steam Sales Analyzer";
+
+// Set once the tray icon exists; closing to the tray needs a way back
+static TRAY_READY: AtomicBool = AtomicBool::new(false);
+
+// Menu items whose text or state follow the sync status
+struct TrayItems {
+    status: MenuItem<Wry>,
+    sync_now: MenuItem<Wry>,
+}
+
+fn status_text(status: &SyncQueueStatus) -> String {
+    if status.paused {
+        "Sync paused".to_string()
+    } else if !status.running {
+        "Not syncing".to_string()
+    } else {
+        match (status.tasks_done, status.tasks_total) {
+            (Some(done), Some(total)) => format!("Syncing… {}/{} dates", done, total),
+            _ => "Syncing…".to_string(),
+        }
+    }
+}
+
+fn tooltip_text() -> String {
+    let last_sync = keys::list_key_sync_status()
+        .ok()
+        .and_then(|keys| keys.into_iter().filter_map(|key| key.last_sync_at).max());
+    match last_sync {
+        // "2024-05-01T08:12:45.123Z" -> "2024-05-01 08:12 UTC"
+        Some(at) => format!(
+            "{}\nLast sync: {} UTC",
+            APP_NAME,
+            at.get(..16).unwrap_or(&at).replace('T', " ")
+        ),
+        None => format!("{}\nNot synced yet", APP_NAME),
+    }
+}
+
+fn refresh(app: &AppHandle, status: &SyncQueueStatus) {
+    if let Some(items) = app.try_state::<TrayItems>() {
+        let _ = items.status.set_text(status_text(status));
+        let _ = items.sync_now.set_enabled(!status.running);
+    }
+    // The last sync time only changes when a fetch ends
+    if !status.running {
+        if let Some(tray) = app.tray_by_id(TRAY_ID) {
+            let _ = tray.set_tooltip(Some(tooltip_text()));
+        }
+    }
+}
+
+pub fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+/// Fetches every data source that has been initialized, one after the other. Read-only sources
+/// (imported share bundles) are left alone.
+pub(crate) async fn sync_all(app: AppHandle) {
+    let sources = match sources::list_sources() {
+        Ok(sources) => sources,
+        Err(e) => {
+            warn!("Failed to list data sources: {}", e);
+            return;
+        }
+    };
+    for source in sources {
+        if source.read_only || !Path::new(&source.db_path).exists() {
+            continue;
+        }
+        if sync::is_sync_paused() {
+            info!("Sync is paused; the remaining data sources are not fetched");
+            return;
+        }
+        info!("Tray sync of data source {}", source.id);
+        if let Err(e) = cli::fetch_data(app.clone(), None, None, Some(source.id.clone())).await {
+            warn!("Tray sync of data source {} failed: {}", source.id, e);
+        }
+    }
+}
+
+/// Whether closing the main window should hide it to the tray instead.
+pub fn should_close_to_tray() -> bool {
+    TRAY_READY.load(Ordering::SeqCst) && settings::load_settings().close_to_tray
+}
+
+/// Creates the tray icon. Call from `setup`.
+pub fn init(app: &AppHandle) -> tauri::Result<()> {
+    let status = MenuItem::with_id(app, "status", "Not syncing", false, None::<&str>)?;
+    let sync_now = MenuItem::with_id(app, "sync_now", "Sync now", true, None::<&str>)?;
+    let show = MenuItem::with_id(app, "show", "Show window", true, None::<&str>)?;
+    let quit = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
+    let menu = Menu::with_items(
+        app,
+        &[
+            &status,
+            &PredefinedMenuItem::separator(app)?,
+            &sync_now,
+            &show,
+            &PredefinedMenuItem::separator(app)?,
+            &quit,
+        ],
+    )?;
+
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .menu(&menu)
+        .tooltip(tooltip_text())
+        .show_menu_on_left_click(false)
+        .on_menu_event(|app, event| match event.id.as_ref() {
+            "sync_now" => {
+                tauri::async_runtime::spawn(sync_all(app.clone()));
+            }
+            "show" => show_main_window(app),
+            "quit" => app.exit(0),
+            _ => {}
+        })
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                show_main_window(tray.app_handle());
+            }
+        });
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+
+    app.manage(TrayItems { status, sync_now });
+    refresh(app, &app.state::<SyncState>().snapshot());
+    // Every status change of a fetch is emitted as sync-queue-progress
+    let handle = app.clone();
+    app.listen_any(
+        "sync-queue-progress",
+        move |event| match serde_json::from_str::<SyncQueueStatus>(event.payload()) {
+            Ok(status) => refresh(&handle, &status),
+            Err(e) => warn!("Unexpected sync-queue-progress payload: {}", e),
+        },
+    );
+
+    TRAY_READY.store(true, Ordering::SeqCst);
+    Ok(())
+}
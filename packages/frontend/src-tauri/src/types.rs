@@ -0,0 +1,929 @@
+// Shared types used across modules
+//
+// `AppError` is the error every Tauri command returns. It serializes to
+// `{ kind, message, retryable, context }` so the frontend can tell an invalid API key from a
+// locked database or a network outage without matching on message text.
+//
+// `Date` is a calendar day of the sales data; range math on it is done with chrono instead of
+// comparing and shifting strings in SQL.
+//
+// `Settings` holds the user's preferences; see settings.rs for how they are stored.
+//
+// `lenient_i64`, `lenient_u64` and `lenient_f64` read numbers from Steam's responses, which
+// send the same field as a JSON number in one response and as a string in the next.
+
+use chrono::{Datelike, Days, Months, NaiveDate, TimeDelta, Utc};
+use chrono_tz::Tz;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+pub type AppResult<T> = Result<T, AppError>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppError {
+    /// The request itself was invalid (bad date, empty name, ...)
+    InvalidInput(String),
+    NotFound(String),
+    CliNotInstalled,
+    /// The CLI ran but exited with an error
+    CliFailed(String),
+    /// Steam rejected the financial API key
+    InvalidApiKey(String),
+    /// SQLite reported SQLITE_BUSY / SQLITE_LOCKED, e.g. while the CLI is writing
+    DatabaseLocked(String),
+    Database(String),
+    /// The network could not be reached
+    Network(String),
+    RateLimited(String),
+    /// A download failed its checksum or signature check, or could not be checked
+    VerificationFailed(String),
+    Io(String),
+    /// Another operation is in the way (sync paused, fetch or update running)
+    Busy(String),
+    Cancelled(String),
+    Timeout(String),
+    Internal(String),
+    /// An error with a note on what was being done when it happened
+    WithContext {
+        context: String,
+        source: Box<AppError>,
+    },
+}
+
+impl AppError {
+    /// Stable identifier the frontend can switch on.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppError::InvalidInput(_) => "invalidInput",
+            AppError::NotFound(_) => "notFound",
+            AppError::CliNotInstalled => "cliNotInstalled",
+            AppError::CliFailed(_) => "cliFailed",
+            AppError::InvalidApiKey(_) => "invalidApiKey",
+            AppError::DatabaseLocked(_) => "databaseLocked",
+            AppError::Database(_) => "database",
+            AppError::Network(_) => "network",
+            AppError::RateLimited(_) => "rateLimited",
+            AppError::VerificationFailed(_) => "verificationFailed",
+            AppError::Io(_) => "io",
+            AppError::Busy(_) => "busy",
+            AppError::Cancelled(_) => "cancelled",
+            AppError::Timeout(_) => "timeout",
+            AppError::Internal(_) => "internal",
+            AppError::WithContext { source, .. } => source.kind(),
+        }
+    }
+
+    /// Whether trying the same thing again later may succeed.
+    pub fn retryable(&self) -> bool {
+        match self {
+            AppError::DatabaseLocked(_)
+            | AppError::Network(_)
+            | AppError::RateLimited(_)
+            | AppError::Busy(_)
+            | AppError::Timeout(_) => true,
+            AppError::WithContext { source, .. } => source.retryable(),
+            _ => false,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            AppError::CliNotInstalled => {
+                "CLI tool not installed. Please download it first.".to_string()
+            }
+            AppError::InvalidInput(m)
+            | AppError::NotFound(m)
+            | AppError::CliFailed(m)
+            | AppError::InvalidApiKey(m)
+            | AppError::DatabaseLocked(m)
+            | AppError::Database(m)
+            | AppError::Network(m)
+            | AppError::RateLimited(m)
+            | AppError::VerificationFailed(m)
+            | AppError::Io(m)
+            | AppError::Busy(m)
+            | AppError::Cancelled(m)
+            | AppError::Timeout(m)
+            | AppError::Internal(m) => m.clone(),
+            AppError::WithContext { source, .. } => source.message(),
+        }
+    }
+
+    /// Contexts from outermost to innermost, joined with ": ".
+    pub fn context(&self) -> Option<String> {
+        match self {
+            AppError::WithContext { context, source } => Some(match source.context() {
+                Some(inner) => format!("{}: {}", context, inner),
+                None => context.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn with_context(self, context: impl Into<String>) -> Self {
+        AppError::WithContext {
+            context: context.into(),
+            source: Box::new(self),
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.context() {
+            Some(context) => write!(f, "{}: {}", context, self.message()),
+            None => f.write_str(&self.message()),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("AppError", 4)?;
+        s.serialize_field("kind", self.kind())?;
+        s.serialize_field("message", &self.message())?;
+        s.serialize_field("retryable", &self.retryable())?;
+        s.serialize_field("context", &self.context())?;
+        s.end()
+    }
+}
+
+/// Adds context to any error convertible into `AppError`.
+pub trait ResultExt<T> {
+    fn context(self, context: impl Into<String>) -> AppResult<T>;
+}
+
+impl<T, E: Into<AppError>> ResultExt<T> for Result<T, E> {
+    fn context(self, context: impl Into<String>) -> AppResult<T> {
+        self.map_err(|e| e.into().with_context(context))
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        match &e {
+            rusqlite::Error::SqliteFailure(err, _)
+                if matches!(
+                    err.code,
+                    rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked
+                ) =>
+            {
+                AppError::DatabaseLocked(e.to_string())
+            }
+            rusqlite::Error::QueryReturnedNoRows => AppError::NotFound(e.to_string()),
+            _ => AppError::Database(e.to_string()),
+        }
+    }
+}
+
+impl From<reqwest::Error> for AppError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            AppError::Timeout(e.to_string())
+        } else if e.status() == Some(reqwest::StatusCode::TOO_MANY_REQUESTS) {
+            AppError::RateLimited(e.to_string())
+        } else if e.is_decode() {
+            AppError::Internal(e.to_string())
+        } else {
+            AppError::Network(e.to_string())
+        }
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        AppError::Io(e.to_string())
+    }
+}
+
+impl From<tokio::task::JoinError> for AppError {
+    fn from(e: tokio::task::JoinError) -> Self {
+        AppError::Internal(format!("Task failed: {}", e))
+    }
+}
+
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        AppError::Internal(message)
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        AppError::Internal(message.to_string())
+    }
+}
+
+/// Steam's financial reports end their days at midnight Pacific time
+pub const STEAM_REPORTING_TIMEZONE: Tz = chrono_tz::America::Los_Angeles;
+
+/// A calendar day, as used by sales_data (`YYYY-MM-DD`, the day Steam reported the sale).
+/// Serializes to and from that string, in JSON and in SQLite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date(NaiveDate);
+
+impl Date {
+    pub const FORMAT: &'static str = "%Y-%m-%d";
+
+    /// The date `days` days later (earlier if negative), clamped to the supported range.
+    pub fn add_days(self, days: i64) -> Date {
+        let shifted = TimeDelta::try_days(days).and_then(|d| self.0.checked_add_signed(d));
+        Date(shifted.unwrap_or(if days < 0 {
+            NaiveDate::MIN
+        } else {
+            NaiveDate::MAX
+        }))
+    }
+
+    /// Today in `timezone`, an IANA name like "America/Los_Angeles". Unknown names fall back to
+    /// Steam's reporting timezone.
+    pub fn today_in(timezone: &str) -> Date {
+        let tz = timezone.parse::<Tz>().unwrap_or(STEAM_REPORTING_TIMEZONE);
+        Date(Utc::now().with_timezone(&tz).date_naive())
+    }
+
+    /// Days from `earlier` to this date; negative if `earlier` is later.
+    pub fn days_since(self, earlier: Date) -> i64 {
+        (self.0 - earlier.0).num_days()
+    }
+
+    /// Day of the week, 0 = Monday to 6 = Sunday.
+    pub fn weekday(self) -> u32 {
+        self.0.weekday().num_days_from_monday()
+    }
+
+    /// Month and day of the month, both counted from 1.
+    pub fn month_day(self) -> (u32, u32) {
+        (self.0.month(), self.0.day())
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.format(Self::FORMAT))
+    }
+}
+
+impl FromStr for Date {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> AppResult<Self> {
+        // chrono accepts single-digit months and days; the database only has the padded form
+        let padded = s.len() == 10 && s.as_bytes()[4] == b'-' && s.as_bytes()[7] == b'-';
+        padded
+            .then(|| NaiveDate::parse_from_str(s, Self::FORMAT).ok())
+            .flatten()
+            .map(Date)
+            .ok_or_else(|| {
+                AppError::InvalidInput(format!("Invalid date '{}', expected YYYY-MM-DD", s))
+            })
+    }
+}
+
+impl Serialize for Date {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Date {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl ToSql for Date {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.to_string()))
+    }
+}
+
+impl FromSql for Date {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value
+            .as_str()?
+            .parse()
+            .map_err(|e: AppError| FromSqlError::Other(Box::new(e)))
+    }
+}
+
+// A number sent as a JSON number or a string, with thousands separators ("1,004.99"); null,
+// missing and "" are None, anything else that isn't a number is an error
+fn lenient_number<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+{
+    let text = match Option::<serde_json::Value>::deserialize(deserializer)? {
+        None | Some(serde_json::Value::Null) => return Ok(None),
+        Some(serde_json::Value::Number(n)) => n.to_string(),
+        Some(serde_json::Value::String(s)) => s.trim().replace(',', ""),
+        Some(other) => {
+            return Err(serde::de::Error::custom(format!(
+                "expected a number, got {}",
+                other
+            )))
+        }
+    };
+    if text.is_empty() {
+        return Ok(None);
+    }
+    text.parse()
+        .map(Some)
+        .map_err(|_| serde::de::Error::custom(format!("'{}' is not a valid number", text)))
+}
+
+/// For `#[serde(default, deserialize_with = "lenient_i64")]` on an `Option<i64>` field of a
+/// Steam response.
+pub fn lenient_i64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<i64>, D::Error> {
+    lenient_number(deserializer)
+}
+
+/// Like `lenient_i64`, for ids and watermarks that don't fit an `i64`.
+pub fn lenient_u64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    lenient_number(deserializer)
+}
+
+/// Like `lenient_i64`, for amounts; Steam sends USD amounts as decimal strings.
+pub fn lenient_f64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f64>, D::Error> {
+    lenient_number(deserializer)
+}
+
+/// Period that summaries are grouped by. A period is labelled by its first day; weeks are ISO
+/// weeks starting on Monday.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    #[default]
+    Day,
+    Week,
+    Month,
+    Quarter,
+    Year,
+}
+
+impl Granularity {
+    /// SQL expression for the first day of the period `column` (a YYYY-MM-DD date) falls in
+    pub fn sql_period_start(self, column: &str) -> String {
+        match self {
+            Granularity::Day => column.to_string(),
+            Granularity::Week => format!(
+                "date({c}, printf('-%d days', (CAST(strftime('%w', {c}) AS INTEGER) + 6) % 7))",
+                c = column
+            ),
+            Granularity::Month => format!("strftime('%Y-%m-01', {})", column),
+            Granularity::Quarter => format!(
+                "printf('%s-%02d-01', strftime('%Y', {c}), (CAST(strftime('%m', {c}) AS INTEGER) - 1) / 3 * 3 + 1)",
+                c = column
+            ),
+            Granularity::Year => format!("strftime('%Y-01-01', {})", column),
+        }
+    }
+
+    /// First day of the period `date` falls in; matches `sql_period_start`
+    pub fn period_start(self, date: Date) -> Date {
+        let d = date.0;
+        let start = match self {
+            Granularity::Day => Some(d),
+            Granularity::Week => {
+                Some(d - TimeDelta::days(d.weekday().num_days_from_monday() as i64))
+            }
+            Granularity::Month => d.with_day(1),
+            Granularity::Quarter => {
+                NaiveDate::from_ymd_opt(d.year(), (d.month() - 1) / 3 * 3 + 1, 1)
+            }
+            Granularity::Year => NaiveDate::from_ymd_opt(d.year(), 1, 1),
+        };
+        Date(start.unwrap_or(d))
+    }
+
+    /// Last day of the period `date` falls in
+    pub fn period_end(self, date: Date) -> Date {
+        let start = self.period_start(date).0;
+        let next = match self {
+            Granularity::Day => start.succ_opt(),
+            Granularity::Week => start.checked_add_days(Days::new(7)),
+            Granularity::Month => start.checked_add_months(Months::new(1)),
+            Granularity::Quarter => start.checked_add_months(Months::new(3)),
+            Granularity::Year => start.checked_add_months(Months::new(12)),
+        };
+        Date(next.and_then(|n| n.pred_opt()).unwrap_or(NaiveDate::MAX))
+    }
+}
+
+/// A period relative to today that filters can name instead of giving dates, e.g. "last_90d".
+/// Months and years are calendar ones; quarters count from the first month of the fiscal year,
+/// so they are calendar quarters unless the fiscal year starts elsewhere. Fiscal years are named
+/// by the calendar year they end in: with fiscal years starting in April, "fy2024" runs from
+/// 2023-04-01 to 2024-03-31.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelativeRange {
+    /// No bounds at all, e.g. to override a default range
+    All,
+    Today,
+    Yesterday,
+    /// The last n days, today included ("last_90d")
+    LastDays(u32),
+    ThisMonth,
+    LastMonth,
+    ThisQuarter,
+    LastQuarter,
+    ThisYear,
+    LastYear,
+    ThisFiscalYear,
+    LastFiscalYear,
+    /// The fiscal year ending in the given year ("fy2024")
+    FiscalYear(i32),
+}
+
+impl RelativeRange {
+    /// First and last day of the range (None where it's unbounded). `today` is today in the
+    /// reporting timezone and `fiscal_start_month` the month (1-12) fiscal years start in.
+    pub fn bounds(self, today: Date, fiscal_start_month: u32) -> (Option<Date>, Option<Date>) {
+        // The `months` long period containing today (or `back` periods before it), where
+        // periods start in `start_month`
+        let period = |months: u32, start_month: u32, back: u32| {
+            let index = today.0.year() * 12 + today.0.month0() as i32;
+            let offset = (index - (start_month as i32 - 1)).rem_euclid(months as i32);
+            month_span(index - offset - (back * months) as i32, months)
+        };
+        match self {
+            RelativeRange::All => (None, None),
+            RelativeRange::Today => (Some(today), Some(today)),
+            RelativeRange::Yesterday => {
+                let yesterday = today.add_days(-1);
+                (Some(yesterday), Some(yesterday))
+            }
+            RelativeRange::LastDays(days) => (Some(today.add_days(1 - days as i64)), Some(today)),
+            RelativeRange::ThisMonth => period(1, 1, 0),
+            RelativeRange::LastMonth => period(1, 1, 1),
+            RelativeRange::ThisQuarter => period(3, fiscal_start_month, 0),
+            RelativeRange::LastQuarter => period(3, fiscal_start_month, 1),
+            RelativeRange::ThisYear => period(12, 1, 0),
+            RelativeRange::LastYear => period(12, 1, 1),
+            RelativeRange::ThisFiscalYear => period(12, fiscal_start_month, 0),
+            RelativeRange::LastFiscalYear => period(12, fiscal_start_month, 1),
+            RelativeRange::FiscalYear(year) => {
+                // Only a fiscal year starting in January starts in the year it ends in
+                let start_year = if fiscal_start_month == 1 { year } else { year - 1 };
+                month_span(start_year * 12 + fiscal_start_month as i32 - 1, 12)
+            }
+        }
+    }
+}
+
+// First and last day of the `months` months starting with the month numbered
+// year * 12 + (month - 1)
+fn month_span(first_month: i32, months: u32) -> (Option<Date>, Option<Date>) {
+    let start = NaiveDate::from_ymd_opt(
+        first_month.div_euclid(12),
+        first_month.rem_euclid(12) as u32 + 1,
+        1,
+    );
+    let end = start
+        .and_then(|s| s.checked_add_months(Months::new(months)))
+        .and_then(|next| next.pred_opt());
+    (start.map(Date), end.map(Date))
+}
+
+impl fmt::Display for RelativeRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RelativeRange::All => write!(f, "all"),
+            RelativeRange::Today => write!(f, "today"),
+            RelativeRange::Yesterday => write!(f, "yesterday"),
+            RelativeRange::LastDays(days) => write!(f, "last_{}d", days),
+            RelativeRange::ThisMonth => write!(f, "this_month"),
+            RelativeRange::LastMonth => write!(f, "last_month"),
+            RelativeRange::ThisQuarter => write!(f, "this_quarter"),
+            RelativeRange::LastQuarter => write!(f, "last_quarter"),
+            RelativeRange::ThisYear => write!(f, "this_year"),
+            RelativeRange::LastYear => write!(f, "last_year"),
+            RelativeRange::ThisFiscalYear => write!(f, "this_fy"),
+            RelativeRange::LastFiscalYear => write!(f, "last_fy"),
+            RelativeRange::FiscalYear(year) => write!(f, "fy{}", year),
+        }
+    }
+}
+
+impl FromStr for RelativeRange {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> AppResult<Self> {
+        let range = match s {
+            "all" => Some(RelativeRange::All),
+            "today" => Some(RelativeRange::Today),
+            "yesterday" => Some(RelativeRange::Yesterday),
+            "this_month" => Some(RelativeRange::ThisMonth),
+            "last_month" => Some(RelativeRange::LastMonth),
+            "this_quarter" => Some(RelativeRange::ThisQuarter),
+            "last_quarter" => Some(RelativeRange::LastQuarter),
+            "this_year" => Some(RelativeRange::ThisYear),
+            "last_year" => Some(RelativeRange::LastYear),
+            "this_fy" => Some(RelativeRange::ThisFiscalYear),
+            "last_fy" => Some(RelativeRange::LastFiscalYear),
+            _ => {
+                let last_days = s
+                    .strip_prefix("last_")
+                    .and_then(|rest| rest.strip_suffix('d'))
+                    .and_then(|days| days.parse::<u32>().ok())
+                    .filter(|days| (1..=3660).contains(days))
+                    .map(RelativeRange::LastDays);
+                let fiscal_year = s
+                    .strip_prefix("fy")
+                    .filter(|year| year.len() == 4)
+                    .and_then(|year| year.parse::<i32>().ok())
+                    .map(RelativeRange::FiscalYear);
+                last_days.or(fiscal_year)
+            }
+        };
+        range.ok_or_else(|| {
+            AppError::InvalidInput(format!(
+                "Unknown date range '{}', expected e.g. last_90d, this_month, last_quarter, \
+                 this_year, this_fy or fy2024",
+                s
+            ))
+        })
+    }
+}
+
+impl Serialize for RelativeRange {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for RelativeRange {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Revenue figure that summaries add up. Gross is what customers paid, tax included; net is
+/// Steam's net_sales_usd, with returns taken out; net after tax also takes out the tax Steam
+/// collected (net_tax_usd).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RevenueBasis {
+    #[default]
+    Gross,
+    Net,
+    NetAfterTax,
+}
+
+/// What the sales list is sorted by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortField {
+    #[default]
+    Date,
+    /// Gross revenue
+    Revenue,
+    Units,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[serde(alias = "ASC")]
+    Asc,
+    #[default]
+    #[serde(alias = "DESC")]
+    Desc,
+}
+
+/// A URL that is POSTed to after syncs or alerts; see webhooks.rs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    /// "sync" and/or "alert"
+    pub events: Vec<String>,
+    /// Payload format: "json", "slack" or "discord"
+    pub template: String,
+    pub enabled: bool,
+}
+
+pub const WEBHOOK_EVENTS: &[&str] = &["sync", "alert"];
+pub const WEBHOOK_TEMPLATES: &[&str] = &["json", "slack", "discord"];
+/// Release channels of the app itself; "beta" includes prereleases
+pub const APP_UPDATE_CHANNELS: &[&str] = &["stable", "beta"];
+
+impl Webhook {
+    pub fn validate(&self) -> AppResult<()> {
+        let local = ["http://localhost", "http://127.0.0.1"]
+            .iter()
+            .any(|prefix| self.url.starts_with(prefix));
+        if !self.url.starts_with("https://") && !local {
+            return Err(AppError::InvalidInput(format!(
+                "Webhook URL '{}' must use https",
+                self.url
+            )));
+        }
+        if self.events.is_empty() {
+            return Err(AppError::InvalidInput(
+                "A webhook needs at least one event".to_string(),
+            ));
+        }
+        if let Some(event) = self
+            .events
+            .iter()
+            .find(|e| !WEBHOOK_EVENTS.contains(&e.as_str()))
+        {
+            return Err(AppError::InvalidInput(format!(
+                "Unknown webhook event '{}', expected one of {}",
+                event,
+                WEBHOOK_EVENTS.join(", ")
+            )));
+        }
+        if !WEBHOOK_TEMPLATES.contains(&self.template.as_str()) {
+            return Err(AppError::InvalidInput(format!(
+                "Unknown webhook template '{}', expected one of {}",
+                self.template,
+                WEBHOOK_TEMPLATES.join(", ")
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// A report emailed every `every_days` days; see email.rs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailSchedule {
+    pub id: String,
+    /// "sales", "daily", "apps" or "countries"
+    pub report_id: String,
+    pub recipients: Vec<String>,
+    /// Days between emails; each email covers the days since the last one up to yesterday
+    pub every_days: u32,
+    /// When the report was last sent successfully (ISO 8601)
+    pub last_sent_at: Option<String>,
+}
+
+impl EmailSchedule {
+    pub fn validate(&self) -> AppResult<()> {
+        crate::export::Report::parse(&self.report_id)?;
+        if self.recipients.is_empty() {
+            return Err(AppError::InvalidInput(
+                "A scheduled report needs at least one recipient".to_string(),
+            ));
+        }
+        if !(1..=366).contains(&self.every_days) {
+            return Err(AppError::InvalidInput(
+                "Scheduled reports are sent every 1 to 366 days".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// User preferences. Fields missing from storage take their default.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    /// Parallel requests the CLI makes while fetching; None leaves it to the CLI
+    pub fetch_concurrency: Option<u32>,
+    /// Seconds a fetch may go without CLI output before it is considered hung
+    pub fetch_inactivity_timeout_secs: u64,
+    /// Minutes between automatic syncs; None only syncs when asked to
+    pub sync_interval_minutes: Option<u32>,
+    /// ISO 4217 code amounts are shown in
+    pub default_currency: String,
+    /// Directory exports are written to; None asks every time
+    pub export_directory: Option<String>,
+    pub notify_on_sync_complete: bool,
+    pub notify_on_alerts: bool,
+    /// Closing the window hides it to the tray instead of quitting
+    pub close_to_tray: bool,
+    /// Offer prerelease CLI versions in update checks and downloads
+    pub cli_include_prereleases: bool,
+    /// Install CLI updates without asking
+    pub cli_auto_update: bool,
+    /// Release channel app updates come from, "stable" or "beta"
+    pub app_update_channel: String,
+    /// Download app updates in the background and install them when the app quits
+    pub app_auto_update: bool,
+    /// Serve the query API on localhost
+    pub api_server_enabled: bool,
+    pub api_server_port: u16,
+    pub webhooks: Vec<Webhook>,
+    /// Record peak player counts of the active source's apps (SteamSpy and the Steam Web API)
+    pub player_stats_enabled: bool,
+    pub email_schedules: Vec<EmailSchedule>,
+    /// IANA timezone whose "today" is the day Steam is still reporting; Steam uses Pacific time
+    pub reporting_timezone: String,
+    /// Leave the unfinished current day out of stats and comparisons by default
+    pub exclude_partial_day: bool,
+    /// Record changed sales rows in `sales_changes` for external exports; see change_log.rs
+    pub sales_change_log_enabled: bool,
+    /// Time queries and fetches into `perf_log` for the performance report; see perf.rs
+    pub perf_log_enabled: bool,
+    /// Seconds a dashboard query may run before it is stopped; see query_control.rs
+    pub query_timeout_secs: u64,
+    /// Fill the query cache with the heaviest views after a sync; see precompute.rs
+    pub precompute_after_sync: bool,
+    /// Read a copy of the database while a fetch writes to it; see read_snapshot.rs
+    pub snapshot_reads_during_sync: bool,
+    /// Month (1-12) the fiscal year starts in, for fiscal quarters and years in date ranges
+    pub fiscal_year_start_month: u32,
+    /// Range queries and exports cover when they are given no dates; None covers all data
+    pub default_date_range: Option<RelativeRange>,
+    /// Record every batch written to sales_data in a hash chain; see ledger.rs
+    pub ledger_enabled: bool,
+    /// Index amounts of money in query results for screen-sharing; see presentation.rs
+    pub presentation_mode: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            fetch_concurrency: None,
+            fetch_inactivity_timeout_secs: 10 * 60,
+            sync_interval_minutes: None,
+            default_currency: "USD".to_string(),
+            export_directory: None,
+            notify_on_sync_complete: true,
+            notify_on_alerts: true,
+            close_to_tray: false,
+            cli_include_prereleases: false,
+            cli_auto_update: false,
+            app_update_channel: "stable".to_string(),
+            app_auto_update: true,
+            api_server_enabled: false,
+            api_server_port: 8787,
+            webhooks: Vec::new(),
+            player_stats_enabled: false,
+            email_schedules: Vec::new(),
+            reporting_timezone: STEAM_REPORTING_TIMEZONE.name().to_string(),
+            exclude_partial_day: false,
+            sales_change_log_enabled: false,
+            perf_log_enabled: false,
+            query_timeout_secs: 120,
+            precompute_after_sync: true,
+            snapshot_reads_during_sync: false,
+            fiscal_year_start_month: 1,
+            default_date_range: None,
+            ledger_enabled: false,
+            presentation_mode: false,
+        }
+    }
+}
+
+impl Settings {
+    pub fn validate(&self) -> AppResult<()> {
+        if self.fetch_concurrency.is_some_and(|n| !(1..=32).contains(&n)) {
+            return Err(AppError::InvalidInput(
+                "Fetch concurrency must be between 1 and 32".to_string(),
+            ));
+        }
+        if self.fetch_inactivity_timeout_secs < 30 {
+            return Err(AppError::InvalidInput(
+                "Fetch inactivity timeout must be at least 30 seconds".to_string(),
+            ));
+        }
+        if !(5..=3600).contains(&self.query_timeout_secs) {
+            return Err(AppError::InvalidInput(
+                "Query timeout must be between 5 and 3600 seconds".to_string(),
+            ));
+        }
+        if !(1..=12).contains(&self.fiscal_year_start_month) {
+            return Err(AppError::InvalidInput(
+                "Fiscal year start month must be between 1 and 12".to_string(),
+            ));
+        }
+        if self.sync_interval_minutes.is_some_and(|m| m < 15) {
+            return Err(AppError::InvalidInput(
+                "Sync interval must be at least 15 minutes".to_string(),
+            ));
+        }
+        if self.default_currency.len() != 3
+            || !self.default_currency.chars().all(|c| c.is_ascii_uppercase())
+        {
+            return Err(AppError::InvalidInput(format!(
+                "Invalid currency '{}', expected a code like USD",
+                self.default_currency
+            )));
+        }
+        if self.reporting_timezone.parse::<Tz>().is_err() {
+            return Err(AppError::InvalidInput(format!(
+                "Unknown timezone '{}', expected a name like America/Los_Angeles",
+                self.reporting_timezone
+            )));
+        }
+        if !APP_UPDATE_CHANNELS.contains(&self.app_update_channel.as_str()) {
+            return Err(AppError::InvalidInput(format!(
+                "Unknown update channel '{}', expected one of {}",
+                self.app_update_channel,
+                APP_UPDATE_CHANNELS.join(", ")
+            )));
+        }
+        if self.api_server_port < 1024 {
+            return Err(AppError::InvalidInput(
+                "API server port must be 1024 or higher".to_string(),
+            ));
+        }
+        for webhook in &self.webhooks {
+            webhook.validate()?;
+        }
+        for schedule in &self.email_schedules {
+            schedule.validate()?;
+        }
+        if let Some(dir) = &self.export_directory {
+            if !std::path::Path::new(dir).is_absolute() {
+                return Err(AppError::InvalidInput(format!(
+                    "Export directory '{}' must be an absolute path",
+                    dir
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> Date {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn date_parses_only_padded_calendar_days() {
+        assert_eq!(date("2024-02-29").to_string(), "2024-02-29");
+        for invalid in [
+            "",
+            "2024-2-9",
+            "2024-02-9",
+            "2024/02/29",
+            "2023-02-29",
+            "2024-13-01",
+            "2024-02-29T00:00",
+            " 2024-02-29",
+            "2é4-01-01",
+        ] {
+            let err = invalid.parse::<Date>().unwrap_err();
+            assert_eq!(err.kind(), "invalidInput", "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn date_arithmetic_across_month_and_year_ends() {
+        assert_eq!(date("2024-02-28").add_days(1), date("2024-02-29"));
+        assert_eq!(date("2024-03-01").add_days(-1), date("2024-02-29"));
+        assert_eq!(date("2024-12-31").add_days(1), date("2025-01-01"));
+        assert_eq!(date("2024-01-01").add_days(0), date("2024-01-01"));
+        assert_eq!(date("2025-01-01").days_since(date("2024-01-01")), 366);
+        assert_eq!(date("2024-01-01").days_since(date("2024-01-08")), -7);
+    }
+
+    #[test]
+    fn date_add_days_clamps_to_supported_range() {
+        assert_eq!(date("2024-01-01").add_days(i64::MAX), Date(NaiveDate::MAX));
+        assert_eq!(date("2024-01-01").add_days(i64::MIN), Date(NaiveDate::MIN));
+        assert_eq!(Date(NaiveDate::MAX).add_days(1), Date(NaiveDate::MAX));
+    }
+
+    #[test]
+    fn date_weekday_and_month_day() {
+        assert_eq!(date("2024-01-01").weekday(), 0);
+        assert_eq!(date("2024-01-07").weekday(), 6);
+        assert_eq!(date("2024-02-29").month_day(), (2, 29));
+    }
+
+    #[test]
+    fn today_in_unknown_timezone_uses_steam_reporting_time() {
+        assert_eq!(
+            Date::today_in("Not/A_Zone"),
+            Date::today_in("America/Los_Angeles")
+        );
+    }
+
+    #[test]
+    fn date_round_trips_through_json_and_sqlite() {
+        let day = date("2024-02-29");
+        assert_eq!(serde_json::to_string(&day).unwrap(), "\"2024-02-29\"");
+        assert_eq!(serde_json::from_str::<Date>("\"2024-02-29\"").unwrap(), day);
+        assert!(serde_json::from_str::<Date>("\"2024-02-30\"").is_err());
+        assert!(serde_json::from_str::<Date>("20240229").is_err());
+
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        let stored: Date = conn.query_row("SELECT ?1", [day], |r| r.get(0)).unwrap();
+        assert_eq!(stored, day);
+        assert!(conn
+            .query_row("SELECT '2024-02-30'", [], |r| r.get::<_, Date>(0))
+            .is_err());
+        assert!(conn
+            .query_row("SELECT 20240229", [], |r| r.get::<_, Date>(0))
+            .is_err());
+    }
+}
@@ -0,0 +1,187 @@
+// Background CLI updater
+//
+// Checks for a newer CLI on an interval and emits `update-available` once per new version. If
+// the user opted in to auto-update, the new version is installed as soon as no fetch is running.
+// Every install, automatic or manual, is recorded in the app database's update history.
+
+use crate::cli;
+use crate::database::get_app_connection;
+use crate::logging::new_operation_id;
+use crate::settings;
+use crate::sync::SyncState;
+use crate::types::{AppError, AppResult};
+use rusqlite::{params, Connection, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::{error, info, warn};
+
+// Delay before the first check, so startup isn't slowed down
+const INITIAL_CHECK_DELAY: Duration = Duration::from_secs(60);
+const CHECK_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+// Set while an automatic install runs; fetches are refused meanwhile
+static UPDATE_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CliUpdateRecord {
+    pub id: i64,
+    pub timestamp: String,
+    pub from_version: Option<String>,
+    pub to_version: String,
+    /// "auto" or "manual"
+    pub trigger: String,
+    pub result: String,
+    pub error: Option<String>,
+}
+
+fn ensure_table(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS cli_update_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            timestamp TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+            from_version TEXT,
+            to_version TEXT NOT NULL,
+            trigger TEXT NOT NULL,
+            result TEXT NOT NULL,
+            error TEXT
+        );",
+    )
+}
+
+pub fn is_update_in_progress() -> bool {
+    UPDATE_IN_PROGRESS.load(Ordering::SeqCst)
+}
+
+pub fn is_auto_update_enabled() -> bool {
+    settings::load_settings().cli_auto_update
+}
+
+/// Appends an install to the update history. Failures are only logged.
+pub fn record_update<T>(
+    from_version: Option<&str>,
+    to_version: &str,
+    trigger: &str,
+    result: &AppResult<T>,
+) {
+    let recorded = get_app_connection().and_then(|conn| {
+        ensure_table(&conn)?;
+        let (status, error) = match result {
+            Ok(_) => ("ok", None),
+            Err(e) => ("error", Some(e.to_string())),
+        };
+        conn.execute(
+            "INSERT INTO cli_update_history (from_version, to_version, trigger, result, error)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![from_version, to_version, trigger, status, error],
+        )
+    });
+    if let Err(e) = recorded {
+        warn!("Failed to record CLI update: {}", e);
+    }
+}
+
+pub fn get_update_history(limit: u32) -> SqliteResult<Vec<CliUpdateRecord>> {
+    let conn = get_app_connection()?;
+    ensure_table(&conn)?;
+    let mut stmt = conn.prepare(
+        "SELECT id, timestamp, from_version, to_version, trigger, result, error
+         FROM cli_update_history
+         ORDER BY id DESC
+         LIMIT ?1",
+    )?;
+    let rows = stmt.query_map([limit as i64], |r| {
+        Ok(CliUpdateRecord {
+            id: r.get(0)?,
+            timestamp: r.get(1)?,
+            from_version: r.get(2)?,
+            to_version: r.get(3)?,
+            trigger: r.get(4)?,
+            result: r.get(5)?,
+            error: r.get(6)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// One update check. Returns the version it notified about, so each version is only announced
+/// once per run of the app.
+#[tracing::instrument(skip_all, fields(op_id = %new_operation_id("update")))]
+async fn check_once(app: &AppHandle, last_notified: Option<&str>) -> Option<String> {
+    let check = match cli::check_cli_update().await {
+        Ok(check) => check,
+        Err(e) => {
+            warn!("Update check failed: {}", e);
+            return None;
+        }
+    };
+    // Installing the CLI the first time is the setup flow's job
+    if !check.update_available || check.current_version.is_none() {
+        return None;
+    }
+    let latest = check.latest_version.clone();
+    if last_notified != Some(latest.as_str()) {
+        let _ = app.emit("update-available", &check);
+    }
+
+    if !is_auto_update_enabled() {
+        return Some(latest);
+    }
+    // Claim the flag before looking at the sync state, so no fetch can start in between
+    UPDATE_IN_PROGRESS.store(true, Ordering::SeqCst);
+    if app.state::<SyncState>().snapshot().running {
+        // Try again on the next check rather than swapping the binary under a fetch
+        UPDATE_IN_PROGRESS.store(false, Ordering::SeqCst);
+        info!(
+            "Fetch running, postponing auto-update to v{}",
+            latest
+        );
+        return Some(latest);
+    }
+
+    let result = cli::install_cli_release(app, &latest).await;
+    UPDATE_IN_PROGRESS.store(false, Ordering::SeqCst);
+    record_update(check.current_version.as_deref(), &latest, "auto", &result);
+    match result {
+        Ok(_) => {
+            info!("Auto-updated CLI to v{}", latest);
+            let _ = app.emit("cli-updated", &latest);
+        }
+        Err(e) => error!("Auto-update to v{} failed: {}", latest, e),
+    }
+    Some(latest)
+}
+
+/// Starts the background update loop.
+pub fn start(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(INITIAL_CHECK_DELAY).await;
+        let mut last_notified: Option<String> = None;
+        loop {
+            if let Some(version) = check_once(&app, last_notified.as_deref()).await {
+                last_notified = Some(version);
+            }
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
+
+// ==================== Tauri Commands ====================
+
+#[tauri::command]
+pub async fn get_cli_auto_update() -> AppResult<bool> {
+    Ok(is_auto_update_enabled())
+}
+
+#[tauri::command]
+pub async fn set_cli_auto_update(enabled: bool) -> AppResult<()> {
+    settings::update_settings(|s| s.cli_auto_update = enabled)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_cli_update_history(limit: Option<u32>) -> AppResult<Vec<CliUpdateRecord>> {
+    get_update_history(limit.unwrap_or(50)).map_err(AppError::from)
+}
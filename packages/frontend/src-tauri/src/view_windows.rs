@@ -0,0 +1,108 @@
+// Extra view windows
+//
+// Besides "main", a view can be opened in a window of its own, e.g. a product's details on a
+// second monitor while browsing the dashboard in the main window. Each view has its own window
+// label ("product-440", "raw-data-2024-05-01"), so size and position are saved per view just like
+// the main window's, and opening the same view again focuses its window. Views are described as
+// steamsales:// links (see deeplink.rs); a new window picks up its view with `take_window_view`
+// once its frontend has loaded.
+
+use crate::annotations::is_valid_date;
+use crate::deeplink::{self, NavigationRequest};
+use crate::types::{AppError, AppResult};
+use crate::window_state;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{
+    AppHandle, Manager, Url, WebviewUrl, WebviewWindow, WebviewWindowBuilder, WindowEvent,
+};
+
+/// Views of windows whose frontend has not asked for them yet, by window label.
+#[derive(Default)]
+pub struct PendingWindowViews(Mutex<HashMap<String, NavigationRequest>>);
+
+// Forgets the view of a window that closes before its frontend asked for it
+fn forget_view_on_close(app: &AppHandle, window: &WebviewWindow) {
+    let app_handle = app.clone();
+    let label = window.label().to_string();
+    window.on_window_event(move |event| {
+        if let WindowEvent::Destroyed = event {
+            app_handle
+                .state::<PendingWindowViews>()
+                .0
+                .lock()
+                .unwrap()
+                .remove(&label);
+        }
+    });
+}
+
+/// Shows the view of `link` in the window `label`, opening the window if it isn't open yet.
+pub fn open_view_window(app: &AppHandle, label: &str, title: &str, link: &str) -> AppResult<()> {
+    if let Some(window) = app.get_webview_window(label) {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+        return Ok(());
+    }
+    let url = Url::parse(link)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid link {}: {}", link, e)))?;
+    let view = deeplink::route(&url)?;
+    app.state::<PendingWindowViews>()
+        .0
+        .lock()
+        .unwrap()
+        .insert(label.to_string(), view);
+
+    let window = WebviewWindowBuilder::new(app, label, WebviewUrl::default())
+        .title(format!("{} - Steam Sales Analyzer", title))
+        .inner_size(1000.0, 700.0)
+        .min_inner_size(800.0, 600.0)
+        .visible(false)
+        .build()
+        .map_err(|e| AppError::Internal(format!("Failed to open window: {}", e)))?;
+    forget_view_on_close(app, &window);
+    window_state::track(&window);
+    Ok(())
+}
+
+// ==================== Tauri Commands ====================
+
+/// Opens an app's product details in a window of its own.
+#[tauri::command]
+#[allow(non_snake_case)]
+pub async fn open_product_window(app: AppHandle, appId: i64) -> AppResult<()> {
+    open_view_window(
+        &app,
+        &format!("product-{}", appId),
+        &format!("App {}", appId),
+        &format!("{}://app/{}", deeplink::SCHEME, appId),
+    )
+}
+
+/// Opens the raw data browser for one day in a window of its own.
+#[tauri::command]
+pub async fn open_raw_data_window(app: AppHandle, date: String) -> AppResult<()> {
+    if !is_valid_date(&date) {
+        return Err(AppError::InvalidInput(format!(
+            "Invalid date '{}', expected YYYY-MM-DD",
+            date
+        )));
+    }
+    open_view_window(
+        &app,
+        &format!("raw-data-{}", date),
+        &format!("Sales of {}", date),
+        &format!("{}://raw?date={}", deeplink::SCHEME, date),
+    )
+}
+
+/// The view the calling window was opened for, if it has not picked it up yet. None for the
+/// main window.
+#[tauri::command]
+pub async fn take_window_view(
+    window: WebviewWindow,
+    state: tauri::State<'_, PendingWindowViews>,
+) -> AppResult<Option<NavigationRequest>> {
+    Ok(state.0.lock().unwrap().remove(window.label()))
+}
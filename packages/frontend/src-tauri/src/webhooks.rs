@@ -0,0 +1,307 @@
+// Webhook notifications
+//
+// Webhooks are kept in the settings. After a successful fetch, hooks subscribed to "sync" get a
+// summary of what changed: dates that are new, dates whose data changed, and the net revenue
+// difference. Hooks subscribed to "alert" get newly detected anomalies. The payload is plain
+// JSON, or a message for Slack or Discord incoming webhooks. Delivery failures are only logged.
+
+use crate::anomalies::Alert;
+use crate::settings;
+use crate::sources::DataSource;
+use crate::types::{AppError, AppResult, Webhook};
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+use tracing::{info, warn};
+
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+// Dates listed by name in chat messages; the rest are counted
+const MAX_LISTED_DATES: usize = 10;
+
+/// Rows and net revenue per date, taken before a fetch to find out what it changed.
+pub struct DateTotals(HashMap<String, (i64, f64)>);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncSummary {
+    pub source_id: String,
+    pub source_name: String,
+    pub new_dates: Vec<String>,
+    pub changed_dates: Vec<String>,
+    /// Net revenue (USD) of the new and changed dates, minus what the changed dates had before
+    pub net_revenue_change: f64,
+}
+
+fn read_totals(db_path: &str) -> rusqlite::Result<DateTotals> {
+    let mut totals = HashMap::new();
+    if !Path::new(db_path).exists() {
+        return Ok(DateTotals(totals));
+    }
+    let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut stmt = conn.prepare(
+        "SELECT date, COUNT(*), COALESCE(SUM(CAST(net_sales_usd AS REAL)), 0)
+         FROM sales_data GROUP BY date",
+    )?;
+    let rows = stmt.query_map([], |r| Ok((r.get(0)?, (r.get(1)?, r.get(2)?))))?;
+    for row in rows {
+        let (date, total) = row?;
+        totals.insert(date, total);
+    }
+    Ok(DateTotals(totals))
+}
+
+fn subscribers(event: &str) -> Vec<Webhook> {
+    settings::load_settings()
+        .webhooks
+        .into_iter()
+        .filter(|hook| hook.enabled && hook.events.iter().any(|e| e == event))
+        .collect()
+}
+
+/// Totals of the source's database, if any webhook wants a sync summary.
+pub fn snapshot_before_sync(db_path: &str) -> Option<DateTotals> {
+    if subscribers("sync").is_empty() {
+        return None;
+    }
+    match read_totals(db_path) {
+        Ok(totals) => Some(totals),
+        Err(e) => {
+            warn!("Failed to read totals before sync: {}", e);
+            None
+        }
+    }
+}
+
+fn summarize(source: &DataSource, before: &DateTotals, after: &DateTotals) -> SyncSummary {
+    let mut summary = SyncSummary {
+        source_id: source.id.clone(),
+        source_name: source.name.clone(),
+        new_dates: Vec::new(),
+        changed_dates: Vec::new(),
+        net_revenue_change: 0.0,
+    };
+    for (date, &(rows, net)) in &after.0 {
+        match before.0.get(date) {
+            None => {
+                summary.new_dates.push(date.clone());
+                summary.net_revenue_change += net;
+            }
+            Some(&(old_rows, old_net)) if old_rows != rows || (old_net - net).abs() > 0.005 => {
+                summary.changed_dates.push(date.clone());
+                summary.net_revenue_change += net - old_net;
+            }
+            Some(_) => {}
+        }
+    }
+    summary.new_dates.sort();
+    summary.changed_dates.sort();
+    summary
+}
+
+fn list_dates(dates: &[String]) -> String {
+    let mut listed = dates
+        .iter()
+        .take(MAX_LISTED_DATES)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(", ");
+    if dates.len() > MAX_LISTED_DATES {
+        listed.push_str(&format!(" and {} more", dates.len() - MAX_LISTED_DATES));
+    }
+    listed
+}
+
+fn sync_message(summary: &SyncSummary) -> String {
+    let mut lines = vec![format!(
+        "Steam sales synced for {}: {} new day(s), {} updated, {}{:.2} USD net revenue",
+        summary.source_name,
+        summary.new_dates.len(),
+        summary.changed_dates.len(),
+        if summary.net_revenue_change < 0.0 {
+            "-"
+        } else {
+            "+"
+        },
+        summary.net_revenue_change.abs()
+    )];
+    if !summary.new_dates.is_empty() {
+        lines.push(format!("New: {}", list_dates(&summary.new_dates)));
+    }
+    if !summary.changed_dates.is_empty() {
+        lines.push(format!("Updated: {}", list_dates(&summary.changed_dates)));
+    }
+    lines.join("\n")
+}
+
+fn alert_message(alerts: &[Alert]) -> String {
+    let mut lines = vec![format!("{} new sales alert(s)", alerts.len())];
+    for alert in alerts.iter().take(MAX_LISTED_DATES) {
+        lines.push(format!(
+            "{} app {}: {} {:.2} vs usual {:.2} ({})",
+            alert.date,
+            alert.app_id,
+            alert.metric,
+            alert.value,
+            alert.baseline_mean,
+            alert.severity
+        ));
+    }
+    lines.join("\n")
+}
+
+fn payload(
+    hook: &Webhook,
+    event: &str,
+    message: &str,
+    data: &serde_json::Value,
+) -> serde_json::Value {
+    match hook.template.as_str() {
+        "slack" => json!({ "text": message }),
+        "discord" => json!({ "content": message }),
+        _ => json!({ "event": event, "message": message, "data": data }),
+    }
+}
+
+async fn deliver(hook: &Webhook, body: &serde_json::Value) -> AppResult<()> {
+    let response = reqwest::Client::builder()
+        .timeout(DELIVERY_TIMEOUT)
+        .build()?
+        .post(&hook.url)
+        .json(body)
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(AppError::Network(format!(
+            "Webhook returned HTTP {}",
+            response.status()
+        )));
+    }
+    Ok(())
+}
+
+async fn notify(event: &str, message: String, data: serde_json::Value) {
+    for hook in subscribers(event) {
+        match deliver(&hook, &payload(&hook, event, &message, &data)).await {
+            Ok(()) => info!("Delivered {} webhook {}", event, hook.id),
+            Err(e) => warn!("Failed to deliver {} webhook {}: {}", event, hook.id, e),
+        }
+    }
+}
+
+/// Sends the sync summary to subscribed webhooks. Nothing is sent if nothing changed.
+pub async fn notify_after_sync(source: &DataSource, before: Option<DateTotals>) {
+    let Some(before) = before else {
+        return;
+    };
+    let db_path = source.db_path.clone();
+    let after = match tokio::task::spawn_blocking(move || read_totals(&db_path)).await {
+        Ok(Ok(after)) => after,
+        Ok(Err(e)) => {
+            warn!("Failed to read totals after sync: {}", e);
+            return;
+        }
+        Err(e) => {
+            warn!("Failed to read totals after sync task: {}", e);
+            return;
+        }
+    };
+    let summary = summarize(source, &before, &after);
+    if summary.new_dates.is_empty() && summary.changed_dates.is_empty() {
+        return;
+    }
+    let data = serde_json::to_value(&summary).unwrap_or_default();
+    notify("sync", sync_message(&summary), data).await;
+}
+
+/// Sends newly detected alerts to subscribed webhooks.
+pub async fn notify_alerts(alerts: &[Alert]) {
+    if alerts.is_empty() {
+        return;
+    }
+    let data = serde_json::to_value(alerts).unwrap_or_default();
+    notify("alert", alert_message(alerts), data).await;
+}
+
+fn new_webhook_id() -> AppResult<String> {
+    let mut bytes = [0u8; 6];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|e| AppError::Internal(format!("Failed to generate webhook id: {}", e)))?;
+    Ok(format!(
+        "wh-{}",
+        bytes
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>()
+    ))
+}
+
+// ==================== Tauri Commands ====================
+
+#[tauri::command]
+pub async fn list_webhooks() -> AppResult<Vec<Webhook>> {
+    Ok(settings::load_settings().webhooks)
+}
+
+/// Adds a webhook. `template` defaults to "json".
+#[tauri::command]
+pub async fn add_webhook(
+    url: String,
+    events: Vec<String>,
+    template: Option<String>,
+) -> AppResult<Webhook> {
+    let webhook = Webhook {
+        id: new_webhook_id()?,
+        url: url.trim().to_string(),
+        events,
+        template: template.unwrap_or_else(|| "json".to_string()),
+        enabled: true,
+    };
+    webhook.validate()?;
+    let added = webhook.clone();
+    settings::update_settings(|s| s.webhooks.push(added))?;
+    Ok(webhook)
+}
+
+#[tauri::command]
+pub async fn set_webhook_enabled(id: String, enabled: bool) -> AppResult<()> {
+    let mut found = false;
+    settings::update_settings(|s| {
+        for hook in s.webhooks.iter_mut().filter(|hook| hook.id == id) {
+            hook.enabled = enabled;
+            found = true;
+        }
+    })?;
+    match found {
+        true => Ok(()),
+        false => Err(AppError::NotFound(format!("Webhook {} not found", id))),
+    }
+}
+
+#[tauri::command]
+pub async fn remove_webhook(id: String) -> AppResult<()> {
+    let mut found = false;
+    settings::update_settings(|s| {
+        let before = s.webhooks.len();
+        s.webhooks.retain(|hook| hook.id != id);
+        found = s.webhooks.len() != before;
+    })?;
+    match found {
+        true => Ok(()),
+        false => Err(AppError::NotFound(format!("Webhook {} not found", id))),
+    }
+}
+
+/// Sends a test message, so a URL can be checked when it is added.
+#[tauri::command]
+pub async fn test_webhook(id: String) -> AppResult<()> {
+    let hook = settings::load_settings()
+        .webhooks
+        .into_iter()
+        .find(|hook| hook.id == id)
+        .ok_or_else(|| AppError::NotFound(format!("Webhook {} not found", id)))?;
+    let message = "Test message from Steam Sales Analyzer".to_string();
+    deliver(&hook, &payload(&hook, "test", &message, &json!({}))).await
+}
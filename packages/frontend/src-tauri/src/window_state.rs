@@ -0,0 +1,235 @@
+// Window size and position
+//
+// Every window (main and the view windows) reopens where it was closed. Its geometry is kept per
+// window label: the inner size in logical pixels, the outer position in physical pixels, the
+// scale factor of its monitor at the time, and whether it was maximized. While maximized, the
+// size and position it had before are kept, so unmaximizing after a restart goes back to them.
+//
+// Windows are created hidden and the geometry is applied before they are shown, so they don't
+// jump into place. Saved positions are checked against the monitors connected now, since the
+// monitor a window was on may be gone (an undocked laptop, a changed layout): a window comes back
+// on the monitor most of it was on, moved inside that monitor's work area if it stuck out and
+// shrunk if it no longer fits. A window that was on none of the current monitors is centered on
+// the primary one.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tauri::{
+    AppHandle, LogicalSize, Manager, Monitor, PhysicalPosition, WebviewWindow, WindowEvent,
+};
+use tracing::warn;
+
+const STATE_FILE: &str = "window-geometry.json";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WindowGeometry {
+    /// Inner size in logical pixels
+    width: f64,
+    height: f64,
+    /// Outer position in physical pixels
+    x: i32,
+    y: i32,
+    scale_factor: f64,
+    maximized: bool,
+}
+
+/// The geometry of every window seen so far, by window label.
+pub struct WindowStates {
+    path: Option<PathBuf>,
+    windows: Mutex<HashMap<String, WindowGeometry>>,
+    // Set by a factory reset, which deletes the file
+    stopped: AtomicBool,
+}
+
+impl WindowStates {
+    /// Reads the geometry saved by earlier runs. Call from `setup` and manage the result.
+    pub fn load(app: &AppHandle) -> Self {
+        let path = app
+            .path()
+            .app_data_dir()
+            .ok()
+            .map(|dir| dir.join(STATE_FILE));
+        let windows = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        WindowStates {
+            path,
+            windows: Mutex::new(windows),
+            stopped: AtomicBool::new(false),
+        }
+    }
+
+    /// Writes the geometry of every window to disk.
+    pub fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if self.stopped.load(Ordering::Relaxed) {
+            return;
+        }
+        let content = serde_json::json!(*self.windows.lock().unwrap()).to_string();
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(path, content));
+        if let Err(e) = written {
+            warn!("Failed to save window state: {}", e);
+        }
+    }
+
+    /// Makes `save` a no-op for the rest of the run.
+    pub fn stop_saving(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+}
+
+// Physical pixels of a rect (x, y, width, height) that are on the monitor's work area
+fn overlap(monitor: &Monitor, x: f64, y: f64, width: f64, height: f64) -> f64 {
+    let area = monitor.work_area();
+    let (left, top) = (area.position.x as f64, area.position.y as f64);
+    let (right, bottom) = (left + area.size.width as f64, top + area.size.height as f64);
+    let visible_width = (x + width).min(right) - x.max(left);
+    let visible_height = (y + height).min(bottom) - y.max(top);
+    if visible_width > 0.0 && visible_height > 0.0 {
+        visible_width * visible_height
+    } else {
+        0.0
+    }
+}
+
+// Applies the saved geometry of the window, moved and shrunk to fit the current monitors
+fn restore(window: &WebviewWindow, geometry: WindowGeometry) -> tauri::Result<()> {
+    let monitors = window.available_monitors()?;
+    // The monitor most of the window was on, if it is still there
+    let on_screen = monitors
+        .iter()
+        .map(|monitor| {
+            let visible = overlap(
+                monitor,
+                geometry.x as f64,
+                geometry.y as f64,
+                geometry.width * geometry.scale_factor,
+                geometry.height * geometry.scale_factor,
+            );
+            (monitor, visible)
+        })
+        .filter(|(_, visible)| *visible > 0.0)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(monitor, _)| monitor.clone());
+    let monitor = match on_screen.clone() {
+        Some(monitor) => Some(monitor),
+        None => window
+            .primary_monitor()?
+            .or_else(|| monitors.first().cloned()),
+    };
+    let Some(monitor) = monitor else {
+        // Nothing to check against (some Linux setups don't report monitors)
+        window.set_position(PhysicalPosition::new(geometry.x, geometry.y))?;
+        window.set_size(LogicalSize::new(geometry.width, geometry.height))?;
+        return Ok(());
+    };
+
+    // No larger than the work area, and inside it: over the edges of the monitor the window is
+    // mostly on, or centered on the primary monitor if it is on none of them
+    let area = monitor.work_area();
+    let scale_factor = monitor.scale_factor();
+    let width = geometry.width.min(area.size.width as f64 / scale_factor);
+    let height = geometry.height.min(area.size.height as f64 / scale_factor);
+    let free_width = area.size.width as i32 - (width * scale_factor).round() as i32;
+    let free_height = area.size.height as i32 - (height * scale_factor).round() as i32;
+    let (x, y) = if on_screen.is_some() {
+        (
+            geometry
+                .x
+                .clamp(area.position.x, area.position.x + free_width.max(0)),
+            geometry
+                .y
+                .clamp(area.position.y, area.position.y + free_height.max(0)),
+        )
+    } else {
+        (
+            area.position.x + free_width / 2,
+            area.position.y + free_height / 2,
+        )
+    };
+    // Position first: a logical size is converted with the scale factor of the window's monitor
+    window.set_position(PhysicalPosition::new(x, y))?;
+    window.set_size(LogicalSize::new(width, height))?;
+    if geometry.maximized {
+        window.maximize()?;
+    }
+    Ok(())
+}
+
+// Remembers the current geometry of the window; minimized windows keep the last one
+fn record(window: &WebviewWindow) -> tauri::Result<()> {
+    if window.is_minimized()? {
+        return Ok(());
+    }
+    let states = window.state::<WindowStates>();
+    let mut windows = states.windows.lock().unwrap();
+    let maximized = window.is_maximized()?;
+    if maximized {
+        if let Some(geometry) = windows.get_mut(window.label()) {
+            geometry.maximized = true;
+            return Ok(());
+        }
+    }
+    let scale_factor = window.scale_factor()?;
+    let size = window.inner_size()?.to_logical::<f64>(scale_factor);
+    let position = window.outer_position()?;
+    windows.insert(
+        window.label().to_string(),
+        WindowGeometry {
+            width: size.width,
+            height: size.height,
+            x: position.x,
+            y: position.y,
+            scale_factor,
+            maximized,
+        },
+    );
+    Ok(())
+}
+
+/// Restores the window's saved geometry, shows it, and keeps track of its geometry from then on.
+/// The window should have been created hidden.
+pub fn track(window: &WebviewWindow) {
+    let saved = window
+        .state::<WindowStates>()
+        .windows
+        .lock()
+        .unwrap()
+        .get(window.label())
+        .copied();
+    if let Some(geometry) = saved {
+        if let Err(e) = restore(window, geometry) {
+            warn!(
+                "Failed to restore window state of {}: {}",
+                window.label(),
+                e
+            );
+        }
+    }
+    let _ = window.show();
+
+    let handle = window.clone();
+    window.on_window_event(move |event| match event {
+        WindowEvent::Moved(_)
+        | WindowEvent::Resized(_)
+        | WindowEvent::ScaleFactorChanged { .. } => {
+            if let Err(e) = record(&handle) {
+                warn!("Failed to read window state of {}: {}", handle.label(), e);
+            }
+        }
+        WindowEvent::CloseRequested { .. } => handle.state::<WindowStates>().save(),
+        _ => {}
+    });
+}
@@ -1,6 +1,9 @@
 // Tauri API client for CLI management
 
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+import { toAppCommandError } from './errors';
+import type { DashboardStats, RelativeRange } from './query-client';
 
 // Check if running in Tauri
 function isTauri(): boolean {
@@ -25,15 +28,9 @@ async function safeInvoke<T>(command: string, args?: Record<string, unknown>): P
     console.log(`[Tauri] Command ${command} succeeded:`, result);
     return result;
   } catch (error) {
-    let errorMsg = 'Unknown Tauri error';
-    if (error instanceof Error && error.message) errorMsg = error.message;
-    else if (typeof error === 'string') errorMsg = error;
-    else if (error && typeof error === 'object' && 'message' in error) {
-      const m = (error as { message: unknown }).message;
-      if (typeof m === 'string') errorMsg = m;
-    } else if (error != null) errorMsg = String(error);
-    console.error(`[Tauri] Command ${command} failed:`, errorMsg, error);
-    throw new Error(errorMsg);
+    const appError = toAppCommandError(error);
+    console.error(`[Tauri] Command ${command} failed:`, appError.kind, appError.message, error);
+    throw appError;
   }
 }
 
@@ -57,10 +54,24 @@ export async function getCliStatus(): Promise<CliStatus> {
 
 // ==================== CLI Version Check ====================
 
+export interface VersionInfo {
+  version: string;
+  major: number;
+  minor: number;
+  patch: number;
+  prerelease: string | null;
+  build: string | null;
+}
+
 export interface VersionCheck {
   currentVersion: string | null;
   latestVersion: string;
   updateAvailable: boolean;
+  channel: 'stable' | 'prerelease';
+  current: VersionInfo | null;
+  latest: VersionInfo | null;
+  // latestVersion is a cached value (GitHub rate-limited or unreachable); information may be stale
+  stale: boolean;
 }
 
 export async function checkCliUpdate(): Promise<VersionCheck> {
@@ -68,19 +79,97 @@ export async function checkCliUpdate(): Promise<VersionCheck> {
     current_version: string | null;
     latest_version: string;
     update_available: boolean;
+    channel: 'stable' | 'prerelease';
+    current: VersionInfo | null;
+    latest: VersionInfo | null;
+    stale: boolean;
   }>('check_cli_update');
   return {
     currentVersion: result.current_version,
     latestVersion: result.latest_version,
     updateAvailable: result.update_available,
+    channel: result.channel,
+    current: result.current,
+    latest: result.latest,
+    stale: result.stale,
   };
 }
 
+// Opt-in to prerelease CLI versions for update checks and downloads
+export async function getCliIncludePrereleases(): Promise<boolean> {
+  return safeInvoke<boolean>('get_cli_include_prereleases');
+}
+
+export async function setCliIncludePrereleases(enabled: boolean): Promise<{ success: boolean }> {
+  await safeInvoke('set_cli_include_prereleases', { enabled });
+  return { success: true };
+}
+
 // Get just the latest version from GitHub (no local version check)
 export async function getLatestGithubVersion(): Promise<string> {
   return safeInvoke<string>('get_latest_github_version');
 }
 
+// ==================== CLI Auto-Update ====================
+
+export interface CliUpdateRecord {
+  id: number;
+  timestamp: string;
+  fromVersion: string | null;
+  toVersion: string;
+  trigger: 'auto' | 'manual';
+  result: 'ok' | 'error';
+  error: string | null;
+}
+
+export async function getCliAutoUpdate(): Promise<boolean> {
+  return safeInvoke<boolean>('get_cli_auto_update');
+}
+
+// User consent for installing CLI updates in the background
+export async function setCliAutoUpdate(enabled: boolean): Promise<{ success: boolean }> {
+  await safeInvoke('set_cli_auto_update', { enabled });
+  return { success: true };
+}
+
+export async function getCliUpdateHistory(limit?: number): Promise<CliUpdateRecord[]> {
+  return safeInvoke<CliUpdateRecord[]>('get_cli_update_history', { limit: limit ?? null });
+}
+
+// ==================== App Updates ====================
+
+export interface AppUpdateCheck {
+  currentVersion: string;
+  latestVersion: string | null;
+  updateAvailable: boolean;
+  channel: 'stable' | 'beta';
+  notes: string | null;
+  // False if the update has to be downloaded from the releases page
+  canInstall: boolean;
+  // Downloaded and verified; installed on the next restart
+  readyVersion: string | null;
+}
+
+// Looks for a newer app version on the channel of the appUpdateChannel setting
+export async function checkAppUpdate(): Promise<AppUpdateCheck> {
+  return safeInvoke<AppUpdateCheck>('check_app_update');
+}
+
+// Downloads and verifies the update now; returns its version
+export async function downloadAppUpdate(): Promise<string> {
+  return safeInvoke<string>('download_app_update');
+}
+
+// Installs the downloaded update and starts the app again
+export async function restartToUpdate(): Promise<void> {
+  return safeInvoke<void>('restart_to_update');
+}
+
+// Calls `handler` with the version once an update is downloaded and waiting for a restart
+export async function onAppUpdateReady(handler: (version: string) => void): Promise<() => void> {
+  return listen<string>('app-update-ready', (event) => handler(event.payload));
+}
+
 // ==================== CLI Download ====================
 
 export async function downloadCli(version?: string): Promise<{ success: boolean; path: string }> {
@@ -88,20 +177,1118 @@ export async function downloadCli(version?: string): Promise<{ success: boolean;
   return { success: true, path };
 }
 
+// Install from a locally downloaded release zip or binary (no GitHub access needed)
+export async function installCliFromFile(filePath: string): Promise<{ success: boolean; path: string }> {
+  const path = await safeInvoke<string>('install_cli_from_file', { path: filePath });
+  return { success: true, path };
+}
+
 // ==================== CLI Init ====================
 
-export async function initCli(apiKey: string): Promise<{ success: boolean }> {
-  await safeInvoke('init_cli', { apiKey });
+export async function initCli(apiKey: string, sourceId?: string): Promise<{ success: boolean }> {
+  await safeInvoke('init_cli', { apiKey, sourceId: sourceId ?? null });
   return { success: true };
 }
 
 // ==================== CLI Fetch ====================
 
-export async function fetchData(options: { force?: boolean } = {}): Promise<{ success: boolean }> {
-  await safeInvoke('fetch_data', { force: options.force || false });
+export async function fetchData(
+  options: { force?: boolean; inactivityTimeoutSecs?: number; sourceId?: string } = {}
+): Promise<{ success: boolean }> {
+  await safeInvoke('fetch_data', {
+    force: options.force || false,
+    inactivityTimeoutSecs: options.inactivityTimeoutSecs ?? null,
+    sourceId: options.sourceId ?? null,
+  });
+  return { success: true };
+}
+
+// Stops a running fetch; the pending fetchData call rejects with a 'cancelled' AppCommandError
+export async function cancelFetch(): Promise<{ success: boolean }> {
+  await safeInvoke('cancel_fetch');
   return { success: true };
 }
 
+// ==================== Data Sources ====================
+
+export interface DataSource {
+  id: string;
+  name: string;
+  dbPath: string;
+  keyId: string | null;
+  createdAt: string;
+  active: boolean;
+  databaseExists: boolean;
+  /** Imported share bundle; can't be fetched into */
+  readOnly: boolean;
+}
+
+export async function listDataSources(): Promise<DataSource[]> {
+  return safeInvoke<DataSource[]>('list_data_sources');
+}
+
+export async function addDataSource(name: string, dbPath?: string): Promise<DataSource> {
+  return safeInvoke<DataSource>('add_data_source', { name, dbPath: dbPath ?? null });
+}
+
+export async function removeDataSource(id: string): Promise<{ success: boolean }> {
+  await safeInvoke('remove_data_source', { id });
+  return { success: true };
+}
+
+// Switches which source's database the dashboard queries
+export async function setActiveDataSource(id: string): Promise<DataSource> {
+  return safeInvoke<DataSource>('set_active_data_source', { id });
+}
+
+// Copies a received share bundle into the app as a read-only source, named after the file
+export async function importShareBundle(path: string, name?: string): Promise<DataSource> {
+  return safeInvoke<DataSource>('import_share_bundle', { path, name: name ?? null });
+}
+
+// small: 2 games over a year; standard: 4 games over three years; large: 12 games over five
+// years (about 430,000 rows), for timing queries
+export type DemoProfile = 'small' | 'standard' | 'large';
+
+// Adds a read-only source with made-up sales, "Demo (standard)" unless named; the same profile
+// and end date (yesterday by default) always give the same rows
+export async function generateDemoData(
+  profile?: DemoProfile,
+  name?: string,
+  endDate?: string
+): Promise<DataSource> {
+  return safeInvoke<DataSource>('generate_demo_data', {
+    profile: profile ?? null,
+    name: name ?? null,
+    endDate: endDate ?? null,
+  });
+}
+
+// ==================== Diagnostics ====================
+
+export type CheckStatus = 'ok' | 'warning' | 'error' | 'skipped';
+
+export interface DiagnosticCheck {
+  name: string;
+  status: CheckStatus;
+  message: string;
+  details: string | null;
+}
+
+export interface DiagnosticsReport {
+  platform: string;
+  arch: string;
+  binaryPath: string;
+  databasePath: string;
+  healthy: boolean;
+  checks: DiagnosticCheck[];
+}
+
+export async function diagnoseCli(): Promise<DiagnosticsReport> {
+  return safeInvoke<DiagnosticsReport>('diagnose_cli');
+}
+
+// ==================== Logs ====================
+
+export interface LogEntry {
+  timestamp: string;
+  level: 'TRACE' | 'DEBUG' | 'INFO' | 'WARN' | 'ERROR';
+  target: string;
+  message: string;
+  operationId: string | null;
+  fields: Record<string, unknown>;
+}
+
+// Newest first; `level` is the minimum level to include
+export async function getRecentLogs(
+  level: 'trace' | 'debug' | 'info' | 'warn' | 'error' = 'info',
+  limit = 200
+): Promise<LogEntry[]> {
+  return safeInvoke<LogEntry[]>('get_recent_logs', { level, limit });
+}
+
+export async function openLogFolder(): Promise<string> {
+  return safeInvoke<string>('open_log_folder');
+}
+
+// ==================== Performance Log ====================
+
+export interface CommandTiming {
+  command: string;
+  count: number;
+  errors: number;
+  p50Ms: number;
+  p95Ms: number;
+  maxMs: number;
+  avgRows: number | null;
+  lastAt: string;
+}
+
+export interface PerformanceReport {
+  enabled: boolean;
+  days: number | null;
+  // Slowest p95 first
+  commands: CommandTiming[];
+}
+
+// Omitting `days` covers the whole log (the last 30 days)
+export async function getPerformanceReport(days?: number): Promise<PerformanceReport> {
+  return safeInvoke<PerformanceReport>('get_performance_report', { days });
+}
+
+export async function setPerfLogEnabled(enabled: boolean): Promise<void> {
+  return safeInvoke<void>('set_perf_log_enabled', { enabled });
+}
+
+export async function clearPerformanceLog(): Promise<void> {
+  return safeInvoke<void>('clear_performance_log');
+}
+
+// ==================== Precomputed Views ====================
+
+export interface PrecomputeSummary {
+  computed: number;
+  failed: number;
+  elapsedMs: number;
+}
+
+// Calls `handler` once the heavy views were computed in the background after a sync
+export async function onPrecomputeComplete(
+  handler: (summary: PrecomputeSummary) => void
+): Promise<() => void> {
+  return listen<PrecomputeSummary>('precompute-complete', (event) => handler(event.payload));
+}
+
+// ==================== Database Changes ====================
+
+export interface DatabaseUpdated {
+  databasePath: string;
+  stats: DashboardStats;
+}
+
+// Calls `handler` when the active database changed outside the dashboard, e.g. the CLI was run
+// from cron, with the new stats. Changes that leave the stats as they were are not reported.
+export async function onDatabaseUpdated(
+  handler: (update: DatabaseUpdated) => void
+): Promise<() => void> {
+  return listen<DatabaseUpdated>('database-updated', (event) => handler(event.payload));
+}
+
+// ==================== Read Snapshot ====================
+
+export interface ReadSnapshot {
+  databasePath: string;
+  takenAt: string;
+}
+
+// The copy queries read while a sync runs (snapshotReadsDuringSync), or null
+export async function getReadSnapshot(): Promise<ReadSnapshot | null> {
+  return safeInvoke<ReadSnapshot | null>('get_read_snapshot');
+}
+
+// ==================== App Status ====================
+
+export interface KeySyncStatus {
+  id: string;
+  keyHint: string;
+  lastSyncAt: string | null;
+  lastFetchAt: string | null;
+  lastFetchResult: string | null;
+}
+
+export interface SyncQueueStatus {
+  running: boolean;
+  paused: boolean;
+  startedAt: number | null;
+  tasksDone: number | null;
+  tasksTotal: number | null;
+  currentDate: string | null;
+  recordsProcessed: number;
+  recordsPerSecond: number | null;
+  etaSeconds: number | null;
+  lastMessage: string | null;
+}
+
+export interface DatabaseInfo {
+  path: string;
+  exists: boolean;
+  rowCount: number;
+  appCount: number;
+  firstDate: string | null;
+  lastDate: string | null;
+  schemaVersion: number | null;
+}
+
+export interface AppStatus {
+  cli: { installed: boolean; version: string | null };
+  database: DatabaseInfo;
+  activeSource: DataSource | null;
+  apiKeyConfigured: boolean;
+  apiKeys: KeySyncStatus[];
+  lastSyncAt: string | null;
+  sync: SyncQueueStatus;
+  pendingTasks: number | null;
+}
+
+// Everything the startup flow needs, in one call
+export async function getAppStatus(): Promise<AppStatus> {
+  return safeInvoke<AppStatus>('get_app_status');
+}
+
+// ==================== Settings ====================
+
+export interface Settings {
+  fetchConcurrency: number | null;
+  fetchInactivityTimeoutSecs: number;
+  syncIntervalMinutes: number | null;
+  defaultCurrency: string;
+  exportDirectory: string | null;
+  notifyOnSyncComplete: boolean;
+  notifyOnAlerts: boolean;
+  closeToTray: boolean;
+  cliIncludePrereleases: boolean;
+  cliAutoUpdate: boolean;
+  appUpdateChannel: 'stable' | 'beta';
+  // Download app updates in the background and install them when the app quits
+  appAutoUpdate: boolean;
+  apiServerEnabled: boolean;
+  apiServerPort: number;
+  webhooks: Webhook[];
+  playerStatsEnabled: boolean;
+  emailSchedules: EmailSchedule[];
+  // IANA timezone whose "today" Steam is still reporting (America/Los_Angeles)
+  reportingTimezone: string;
+  excludePartialDay: boolean;
+  salesChangeLogEnabled: boolean;
+  // Time queries and fetches for getPerformanceReport; set with setPerfLogEnabled
+  perfLogEnabled: boolean;
+  // A dashboard query running longer than this is stopped with a 'timeout' error (5-3600)
+  queryTimeoutSecs: number;
+  // Compute the launch comparison, portfolio and product stats in the background after a sync
+  precomputeAfterSync: boolean;
+  // Read a copy of the database while a sync writes to it, instead of half-written days
+  snapshotReadsDuringSync: boolean;
+  // Month (1-12) the fiscal year starts in; quarters and 'this_fy'/'fy2024' ranges count from it
+  fiscalYearStartMonth: number;
+  // Range queries and exports cover when given no dates, e.g. 'last_90d'; null covers all data
+  defaultDateRange: RelativeRange | null;
+  // Record every fetch and itch.io sync in a hash chain that verifyLedger checks
+  ledgerEnabled: boolean;
+  // Query results show money indexed to the average day's gross revenue (= 100), for
+  // screen-sharing; units, counts and shares stay real
+  presentationMode: boolean;
+}
+
+export async function getAllSettings(): Promise<Settings> {
+  return safeInvoke<Settings>('get_all_settings');
+}
+
+export async function getSetting<K extends keyof Settings>(key: K): Promise<Settings[K]> {
+  return safeInvoke<Settings[K]>('get_setting', { key });
+}
+
+// Returns all settings after the change
+export async function setSetting<K extends keyof Settings>(
+  key: K,
+  value: Settings[K]
+): Promise<Settings> {
+  return safeInvoke<Settings>('set_setting', { key, value });
+}
+
+export async function resetSettings(): Promise<Settings> {
+  return safeInvoke<Settings>('reset_settings');
+}
+
+// ==================== Local API Server ====================
+
+export interface ApiServerInfo {
+  enabled: boolean;
+  running: boolean;
+  url: string;
+  token: string;
+}
+
+export async function getApiServerInfo(): Promise<ApiServerInfo> {
+  return safeInvoke<ApiServerInfo>('get_api_server_info');
+}
+
+export async function setApiServerEnabled(enabled: boolean, port?: number): Promise<ApiServerInfo> {
+  return safeInvoke<ApiServerInfo>('set_api_server_enabled', { enabled, port });
+}
+
+export async function regenerateApiServerToken(): Promise<ApiServerInfo> {
+  return safeInvoke<ApiServerInfo>('regenerate_api_server_token');
+}
+
+// ==================== Webhooks ====================
+
+export type WebhookEvent = 'sync' | 'alert';
+export type WebhookTemplate = 'json' | 'slack' | 'discord';
+
+export interface Webhook {
+  id: string;
+  url: string;
+  events: WebhookEvent[];
+  template: WebhookTemplate;
+  enabled: boolean;
+}
+
+export async function listWebhooks(): Promise<Webhook[]> {
+  return safeInvoke<Webhook[]>('list_webhooks');
+}
+
+export async function addWebhook(
+  url: string,
+  events: WebhookEvent[],
+  template: WebhookTemplate = 'json'
+): Promise<Webhook> {
+  return safeInvoke<Webhook>('add_webhook', { url, events, template });
+}
+
+export async function setWebhookEnabled(id: string, enabled: boolean): Promise<void> {
+  await safeInvoke('set_webhook_enabled', { id, enabled });
+}
+
+export async function removeWebhook(id: string): Promise<void> {
+  await safeInvoke('remove_webhook', { id });
+}
+
+// Sends a test message; throws if the URL did not accept it
+export async function testWebhook(id: string): Promise<void> {
+  await safeInvoke('test_webhook', { id });
+}
+
+// ==================== Email Reports ====================
+
+export type ReportId = 'sales' | 'daily' | 'apps' | 'countries';
+export type SmtpSecurity = 'tls' | 'starttls' | 'none';
+
+export interface SmtpSettings {
+  host: string;
+  port: number;
+  username: string | null;
+  // Null keeps the stored password
+  password: string | null;
+  from: string;
+  security: SmtpSecurity;
+}
+
+export interface SmtpSettingsInfo {
+  host: string;
+  port: number;
+  username: string | null;
+  from: string;
+  security: SmtpSecurity;
+  hasPassword: boolean;
+}
+
+export interface EmailSchedule {
+  id: string;
+  reportId: ReportId;
+  recipients: string[];
+  everyDays: number;
+  lastSentAt: string | null;
+}
+
+export async function getSmtpSettings(): Promise<SmtpSettingsInfo | null> {
+  return safeInvoke<SmtpSettingsInfo | null>('get_smtp_settings');
+}
+
+export async function setSmtpSettings(settings: SmtpSettings): Promise<void> {
+  await safeInvoke('set_smtp_settings', { settings });
+}
+
+export async function clearSmtpSettings(): Promise<void> {
+  await safeInvoke('clear_smtp_settings');
+}
+
+// Returns the number of rows in the attached CSV
+export async function emailReport(
+  reportId: ReportId,
+  recipients: string[],
+  startDate?: string,
+  endDate?: string
+): Promise<number> {
+  return safeInvoke<number>('email_report', { reportId, recipients, startDate, endDate });
+}
+
+export async function listEmailSchedules(): Promise<EmailSchedule[]> {
+  return safeInvoke<EmailSchedule[]>('list_email_schedules');
+}
+
+export async function addEmailSchedule(
+  reportId: ReportId,
+  recipients: string[],
+  everyDays: number
+): Promise<EmailSchedule> {
+  return safeInvoke<EmailSchedule>('add_email_schedule', { reportId, recipients, everyDays });
+}
+
+export async function removeEmailSchedule(id: string): Promise<void> {
+  await safeInvoke('remove_email_schedule', { id });
+}
+
+// ==================== itch.io ====================
+
+export interface ItchSyncResult {
+  sourceId: string;
+  games: number;
+  newPurchases: number;
+  totalPurchases: number;
+  /** Stored purchases not in USD, which the sales figures leave out */
+  skippedPurchases: number;
+  /** Their currencies, e.g. ["EUR", "GBP"] */
+  skippedCurrencies: string[];
+}
+
+export interface ItchGameLink {
+  gameId: number;
+  title: string;
+  /** Steam app the game's sales count under */
+  appId: number | null;
+}
+
+export async function hasItchApiKey(): Promise<boolean> {
+  return safeInvoke<boolean>('has_itch_api_key');
+}
+
+// Checks the key with itch.io before saving it
+export async function setItchApiKey(apiKey: string): Promise<void> {
+  await safeInvoke('set_itch_api_key', { apiKey });
+}
+
+export async function clearItchApiKey(): Promise<void> {
+  await safeInvoke('clear_itch_api_key');
+}
+
+// Fetches itch.io purchases for a data source (the active one by default)
+export async function syncItch(sourceId?: string, force = false): Promise<ItchSyncResult> {
+  return safeInvoke<ItchSyncResult>('sync_itch', { sourceId, force });
+}
+
+export async function listItchGames(sourceId?: string): Promise<ItchGameLink[]> {
+  return safeInvoke<ItchGameLink[]>('list_itch_games', { sourceId });
+}
+
+// Counts a game's sales under a Steam app, or under none with appId null
+export async function setItchGameApp(
+  gameId: number,
+  appId: number | null,
+  sourceId?: string
+): Promise<void> {
+  await safeInvoke('set_itch_game_app', { gameId, appId, sourceId });
+}
+
+// ==================== App Metadata ====================
+
+// Steam store details of an app, cached for a week
+export interface AppMetadata {
+  appId: number;
+  // False when the store has no page for the app
+  available: boolean;
+  name: string | null;
+  headerImage: string | null;
+  releaseDate: string | null;
+  comingSoon: boolean;
+  genres: string[];
+  isFree: boolean;
+  // US store price
+  priceCurrency: string | null;
+  priceInitialCents: number | null;
+  priceFinalCents: number | null;
+  discountPercent: number | null;
+  fetchedAt: string;
+}
+
+export async function getAppMetadata(appIds?: number[]): Promise<AppMetadata[]> {
+  return safeInvoke<AppMetadata[]>('get_app_metadata', { appIds });
+}
+
+// Returns the number of apps updated
+export async function refreshAppMetadata(force = false): Promise<number> {
+  return safeInvoke<number>('refresh_app_metadata', { force });
+}
+
+// ==================== Reviews ====================
+
+export interface ReviewDay {
+  date: string;
+  positive: number;
+  negative: number;
+  // Null on days without reviews
+  positiveRatio: number | null;
+  units: number;
+  revenue: number;
+}
+
+export interface ReviewsVsSales {
+  appId: number;
+  totalPositive: number | null;
+  totalNegative: number | null;
+  reviewScore: string | null;
+  days: ReviewDay[];
+  // Pearson correlation of reviews per day with units sold; null with too few days
+  correlation: number | null;
+}
+
+// Returns the number of apps updated
+export async function refreshReviews(appIds?: number[]): Promise<number> {
+  return safeInvoke<number>('refresh_reviews', { appIds });
+}
+
+export async function queryReviewsVsSales(
+  appId: number,
+  startDate?: string,
+  endDate?: string
+): Promise<ReviewsVsSales> {
+  return safeInvoke<ReviewsVsSales>('query_reviews_vs_sales', { appId, startDate, endDate });
+}
+
+// ==================== Player Stats ====================
+
+export interface EngagementDay {
+  date: string;
+  peakPlayers: number;
+  revenue: number;
+  units: number;
+  // Null when no players were recorded
+  revenuePerPlayer: number | null;
+}
+
+export interface EngagementVsRevenue {
+  appId: number;
+  days: EngagementDay[];
+  // Pearson correlation of peak players with revenue; null with too few days
+  correlation: number | null;
+}
+
+// Player counts are only recorded while `playerStatsEnabled` is on in the settings
+export async function queryEngagementVsRevenue(
+  appId: number,
+  startDate?: string,
+  endDate?: string
+): Promise<EngagementVsRevenue> {
+  return safeInvoke<EngagementVsRevenue>('query_engagement_vs_revenue', {
+    appId,
+    startDate,
+    endDate,
+  });
+}
+
+// ==================== Store Traffic ====================
+
+export interface TrafficImportResult {
+  appId: number;
+  days: number;
+  metrics: string[];
+  skippedRows: number;
+}
+
+export interface FunnelDay {
+  date: string;
+  impressions: number | null;
+  visits: number | null;
+  wishlistAdds: number | null;
+  units: number;
+  clickThrough: number | null;
+  wishlistRate: number | null;
+  purchaseRate: number | null;
+}
+
+export interface Funnel {
+  appId: number;
+  totalImpressions: number;
+  totalVisits: number;
+  totalWishlistAdds: number;
+  totalUnits: number;
+  days: FunnelDay[];
+}
+
+// Imports a traffic or wishlist CSV exported from the Steamworks partner site
+export async function importTrafficCsv(appId: number, path: string): Promise<TrafficImportResult> {
+  return safeInvoke<TrafficImportResult>('import_traffic_csv', { appId, path });
+}
+
+export async function queryFunnel(
+  appId: number,
+  startDate?: string,
+  endDate?: string
+): Promise<Funnel> {
+  return safeInvoke<Funnel>('query_funnel', { appId, startDate, endDate });
+}
+
+// ==================== Schema ====================
+
+export interface ColumnSchema {
+  name: string;
+  declaredType: string;
+  notNull: boolean;
+  primaryKey: boolean;
+  hasDefault: boolean;
+}
+
+export interface TableSchema {
+  name: string;
+  kind: 'table' | 'view';
+  rowCount: number | null;
+  columns: ColumnSchema[];
+}
+
+export interface SchemaFeatures {
+  appIdColumn: string;
+  appNameColumn: string | null;
+  packageNameColumn: string | null;
+  hasDiscountColumn: boolean;
+  hasSourceColumn: boolean;
+  hasLookupApps: boolean;
+  hasLookupPackages: boolean;
+}
+
+export interface SchemaDescription {
+  path: string;
+  schemaVersion: number;
+  tables: TableSchema[];
+  features: SchemaFeatures;
+}
+
+// Tables, columns and row counts of the active source's database
+export async function describeSchema(): Promise<SchemaDescription> {
+  return safeInvoke<SchemaDescription>('describe_schema');
+}
+
+// ==================== SQL Console ====================
+
+export interface SqlColumn {
+  name: string;
+  // Storage class of the first non-null value: integer, real, text, blob, or null
+  valueType: 'integer' | 'real' | 'text' | 'blob' | 'null';
+}
+
+export interface SqlResult {
+  columns: SqlColumn[];
+  rows: unknown[][];
+  truncated: boolean;
+  elapsedMs: number;
+}
+
+// Runs a read-only SELECT against the active source's database. Parameters bind to ?1, ?2, ...
+export async function runReadonlySql(
+  sql: string,
+  params?: (string | number | boolean | null)[],
+  limit?: number
+): Promise<SqlResult> {
+  return safeInvoke<SqlResult>('run_readonly_sql', { sql, params, limit });
+}
+
+// ==================== Quarantine ====================
+
+export interface QuarantinedRow {
+  id: number;
+  date: string | null;
+  reason: string;
+  // All columns of the row as they are in sales_data
+  rawJson: Record<string, unknown>;
+  quarantinedAt: string;
+}
+
+export interface ReprocessResult {
+  restored: number;
+  stillInvalid: number;
+}
+
+// Rows the dashboard leaves out after a fetch because of malformed dates, units or amounts
+export async function getQuarantinedRows(limit?: number): Promise<QuarantinedRow[]> {
+  return safeInvoke<QuarantinedRow[]>('get_quarantined_rows', { limit });
+}
+
+// Checks the rows again; quarantined rows that pass now are counted again
+export async function reprocessQuarantine(): Promise<ReprocessResult> {
+  return safeInvoke<ReprocessResult>('reprocess_quarantine');
+}
+
+// ==================== Reconciliation ====================
+
+export interface ReconcileTotals {
+  grossSalesUsd: number;
+  netSalesUsd: number;
+  netUnitsSold: number;
+  rows: number;
+}
+
+export interface AppReconciliation {
+  appId: number | null;
+  appName: string | null;
+  steam: ReconcileTotals;
+  local: ReconcileTotals;
+}
+
+export interface DateReconciliation {
+  date: string;
+  steam: ReconcileTotals;
+  local: ReconcileTotals;
+  matches: boolean;
+  mismatchedApps: AppReconciliation[];
+}
+
+export interface ReconcileReport {
+  startDate: string;
+  endDate: string;
+  dates: DateReconciliation[];
+  mismatchedDates: number;
+}
+
+// Compares the stored Steam rows with what Steam reports now, per date and app (at most 92 days).
+// apiKey is only needed if the data source's key isn't saved on this computer.
+export async function reconcile(
+  startDate: string,
+  endDate: string,
+  apiKey?: string,
+  sourceId?: string
+): Promise<ReconcileReport> {
+  return safeInvoke<ReconcileReport>('reconcile', { startDate, endDate, apiKey, sourceId });
+}
+
+// ==================== Product Names ====================
+
+export interface LookupRefreshSummary {
+  startDate: string;
+  endDate: string;
+  appsSeen: number;
+  packagesSeen: number;
+  appsUpdated: number;
+  packagesUpdated: number;
+  sources: string[];
+}
+
+// Fetches the app and package names Steam reports for the last `days` days (7 by default, at
+// most 92) and stores new and renamed ones in every data source synced with the key. Without
+// apiKeyId, the key the CLI was last initialized with is used.
+export async function refreshLookups(
+  apiKeyId?: string,
+  days?: number
+): Promise<LookupRefreshSummary> {
+  return safeInvoke<LookupRefreshSummary>('refresh_lookups', { apiKeyId, days });
+}
+
+// ==================== Ledger ====================
+
+export interface LedgerVerification {
+  sourceId: string;
+  entries: number;
+  // False if an entry was altered, removed or inserted after it was recorded
+  chainIntact: boolean;
+  brokenAtEntry: number | null;
+  // Dates whose rows changed outside a recorded fetch or sync
+  modifiedDates: string[];
+  lastRecordedAt: string | null;
+  // Keep this outside the database to also catch the whole ledger being rewritten
+  lastEntryHash: string | null;
+}
+
+// Checks the ledger of a data source (the active one by default)
+export async function verifyLedger(sourceId?: string): Promise<LedgerVerification> {
+  return safeInvoke<LedgerVerification>('verify_ledger', { sourceId });
+}
+
+// Records the data source's current data, e.g. right after turning the ledger on; returns the
+// new entry's id, or null if nothing changed since the last entry
+export async function recordLedgerEntry(sourceId?: string): Promise<number | null> {
+  return safeInvoke<number | null>('record_ledger_entry', { sourceId });
+}
+
+// ==================== Factory Reset ====================
+
+export interface ResetArtifact {
+  kind: 'file' | 'directory' | 'credential';
+  // Path, or the name of the credential store entry
+  location: string;
+  description: string;
+  bytes: number;
+  // null in a dry run
+  removed: boolean | null;
+  error: string | null;
+}
+
+export interface FactoryResetReport {
+  dryRun: boolean;
+  artifacts: ResetArtifact[];
+  totalBytes: number;
+  // Whether nothing listed exists any more; null in a dry run
+  verified: boolean | null;
+  remaining: string[];
+}
+
+// Removes every file, directory and credential the app created, or with dryRun only lists
+// them. Quit the app right after a reset, before it recreates anything.
+export async function factoryReset(dryRun: boolean): Promise<FactoryResetReport> {
+  return safeInvoke<FactoryResetReport>('factory_reset', { dryRun });
+}
+
+// ==================== Royalties ====================
+
+export interface RoyaltyRuleInput {
+  // Exactly one of appId and packageId
+  appId: number | null;
+  packageId: number | null;
+  party: string;
+  // Share of net revenue, 0-100
+  percentage: number;
+  // Lifetime net revenue (USD) the product must pass before the share starts
+  recoupThreshold: number | null;
+}
+
+export interface RoyaltyRule extends RoyaltyRuleInput {
+  id: number;
+  createdAt: string;
+}
+
+export interface RoyaltyPeriod {
+  startDate: string;
+  endDate: string;
+}
+
+export interface RoyaltyLine {
+  ruleId: number;
+  party: string;
+  percentage: number;
+  recoupThreshold: number | null;
+  eligibleRevenue: number;
+  amount: number;
+  recouped: boolean;
+}
+
+export interface ProductStatement {
+  appId: number | null;
+  packageId: number | null;
+  netRevenue: number;
+  lifetimeNetRevenue: number;
+  lines: RoyaltyLine[];
+  unallocated: number;
+}
+
+export interface RoyaltyStatement {
+  period: RoyaltyPeriod;
+  products: ProductStatement[];
+  parties: { party: string; amount: number }[];
+}
+
+export async function listRoyaltyRules(): Promise<RoyaltyRule[]> {
+  return safeInvoke<RoyaltyRule[]>('list_royalty_rules');
+}
+
+export async function createRoyaltyRule(rule: RoyaltyRuleInput): Promise<number> {
+  return safeInvoke<number>('create_royalty_rule', { rule });
+}
+
+export async function updateRoyaltyRule(id: number, rule: RoyaltyRuleInput): Promise<void> {
+  return safeInvoke<void>('update_royalty_rule', { id, rule });
+}
+
+export async function deleteRoyaltyRule(id: number): Promise<void> {
+  return safeInvoke<void>('delete_royalty_rule', { id });
+}
+
+// Splits the net revenue of every product with rules in the period between the parties
+export async function generateRoyaltyStatement(period: RoyaltyPeriod): Promise<RoyaltyStatement> {
+  return safeInvoke<RoyaltyStatement>('generate_royalty_statement', { period });
+}
+
+// ==================== Product Groups ====================
+
+export interface ProductGroupInput {
+  name: string;
+  // In order; the first app stands for the group, e.g. the full game
+  appIds: number[];
+  packageIds: number[];
+}
+
+export interface ProductGroup extends ProductGroupInput {
+  id: number;
+  createdAt: string;
+}
+
+export async function listProductGroups(): Promise<ProductGroup[]> {
+  return safeInvoke<ProductGroup[]>('list_product_groups');
+}
+
+export async function createProductGroup(group: ProductGroupInput): Promise<number> {
+  return safeInvoke<number>('create_product_group', { group });
+}
+
+export async function updateProductGroup(id: number, group: ProductGroupInput): Promise<void> {
+  return safeInvoke<void>('update_product_group', { id, group });
+}
+
+export async function deleteProductGroup(id: number): Promise<void> {
+  return safeInvoke<void>('delete_product_group', { id });
+}
+
+// ==================== Custom Metrics ====================
+
+export interface CustomMetricInput {
+  // Lowercase letters, digits and underscores, starting with a letter
+  name: string;
+  // Arithmetic over sales_data columns, e.g. "net_sales_usd / net_units_sold"
+  expression: string;
+  description: string | null;
+}
+
+export interface CustomMetric extends CustomMetricInput {
+  id: number;
+  createdAt: string;
+}
+
+export async function listCustomMetrics(): Promise<CustomMetric[]> {
+  return safeInvoke<CustomMetric[]>('list_custom_metrics');
+}
+
+export async function createCustomMetric(metric: CustomMetricInput): Promise<number> {
+  return safeInvoke<number>('create_custom_metric', { metric });
+}
+
+export async function updateCustomMetric(id: number, metric: CustomMetricInput): Promise<void> {
+  return safeInvoke<void>('update_custom_metric', { id, metric });
+}
+
+export async function deleteCustomMetric(id: number): Promise<void> {
+  return safeInvoke<void>('delete_custom_metric', { id });
+}
+
+// ==================== Sales Change Log ====================
+
+export interface SalesChange {
+  id: number;
+  op: 'insert' | 'update' | 'delete';
+  /** rowid of the row in sales_data */
+  rowId: number;
+  /** Key columns of the row (before the change for deletes) */
+  key: Record<string, unknown> | null;
+  /** UTC, e.g. 2024-05-01T12:00:00.000Z */
+  changedAt: string;
+}
+
+export interface SalesChanges {
+  changes: SalesChange[];
+  /** Pass the last change's id as afterId to get the rest */
+  hasMore: boolean;
+}
+
+export async function getChangesSince(
+  since: string,
+  afterId?: number,
+  limit?: number
+): Promise<SalesChanges> {
+  return safeInvoke<SalesChanges>('get_changes_since', { since, afterId, limit });
+}
+
+export async function setSalesChangeLogEnabled(enabled: boolean): Promise<void> {
+  return safeInvoke<void>('set_sales_change_log_enabled', { enabled });
+}
+
+// ==================== Manual Adjustments ====================
+
+export type AdjustableColumn =
+  | 'gross_units_sold'
+  | 'gross_units_returned'
+  | 'net_units_sold'
+  | 'gross_sales_usd'
+  | 'net_sales_usd'
+  | 'net_tax_usd';
+
+export interface Adjustment {
+  id: number;
+  /** SalesRecord.rowId of the adjusted row */
+  recordId: number;
+  rowKey: Record<string, unknown> | null;
+  fields: Partial<Record<AdjustableColumn, number>>;
+  /** Values before the adjustment */
+  original: Partial<Record<AdjustableColumn, number | null>>;
+  reason: string;
+  createdAt: string;
+  revertedAt: string | null;
+}
+
+// Replaces an earlier adjustment of the row still in effect, keeping the columns it set
+export async function applyManualAdjustment(
+  recordId: number,
+  fields: Partial<Record<AdjustableColumn, number>>,
+  reason: string
+): Promise<Adjustment> {
+  return safeInvoke<Adjustment>('apply_manual_adjustment', { recordId, fields, reason });
+}
+
+export async function revertManualAdjustment(adjustmentId: number): Promise<void> {
+  return safeInvoke<void>('revert_manual_adjustment', { adjustmentId });
+}
+
+export async function listManualAdjustments(
+  recordId?: number,
+  includeReverted = false
+): Promise<Adjustment[]> {
+  return safeInvoke<Adjustment[]>('list_manual_adjustments', {
+    recordId: recordId ?? null,
+    includeReverted,
+  });
+}
+
+// ==================== Deep Links ====================
+
+export interface NavigationRequest {
+  tab: 'charts' | 'rawDataBrowser' | 'launchComparison' | 'packageMetrics';
+  appId: number | null;
+  packageId: number | null;
+  range: string | null;
+  startDate: string | null;
+  endDate: string | null;
+  url: string;
+}
+
+// The steamsales:// link the frontend has not handled yet, if any. Taking it clears it.
+export async function takePendingDeepLink(): Promise<NavigationRequest | null> {
+  return safeInvoke<NavigationRequest | null>('take_pending_deep_link');
+}
+
+// Calls `handler` for every steamsales:// link, including the one that opened the app
+export async function onDeepLink(
+  handler: (request: NavigationRequest) => void
+): Promise<() => void> {
+  const handlePending = async () => {
+    const request = await takePendingDeepLink();
+    if (request) handler(request);
+  };
+  const unlisten = await listen('deep-link-navigate', () => {
+    handlePending().catch((err) => console.warn('[Tauri] Failed to handle deep link:', err));
+  });
+  await handlePending();
+  return unlisten;
+}
+
+// ==================== App Menu ====================
+
+export type MenuAction = 'refresh';
+
+// Calls `handler` for the app menu items the frontend carries out (View → Refresh)
+export async function onMenuAction(handler: (action: MenuAction) => void): Promise<() => void> {
+  return listen<MenuAction>('menu-action', (event) => handler(event.payload));
+}
+
+// ==================== View Windows ====================
+
+// Opens an app's product details in a window of its own, or focuses it if already open
+export async function openProductWindow(appId: number): Promise<void> {
+  return safeInvoke<void>('open_product_window', { appId });
+}
+
+// Opens the raw data browser for one day (YYYY-MM-DD) in a window of its own
+export async function openRawDataWindow(date: string): Promise<void> {
+  return safeInvoke<void>('open_raw_data_window', { date });
+}
+
+// The view this window was opened for; null in the main window or once taken
+export async function takeWindowView(): Promise<NavigationRequest | null> {
+  return safeInvoke<NavigationRequest | null>('take_window_view');
+}
+
 // ==================== Delete Database ====================
 
 export async function deleteDatabase(): Promise<{ success: boolean }> {
@@ -0,0 +1,48 @@
+// Typed errors returned by Tauri commands (see AppError in src-tauri/src/types.rs)
+
+export type AppErrorKind =
+  | 'invalidInput'
+  | 'notFound'
+  | 'cliNotInstalled'
+  | 'cliFailed'
+  | 'invalidApiKey'
+  | 'databaseLocked'
+  | 'database'
+  | 'network'
+  | 'rateLimited'
+  | 'verificationFailed'
+  | 'io'
+  | 'busy'
+  | 'cancelled'
+  | 'timeout'
+  | 'internal';
+
+export class AppCommandError extends Error {
+  readonly kind: AppErrorKind;
+  readonly retryable: boolean;
+  readonly context: string | null;
+
+  constructor(kind: AppErrorKind, message: string, retryable = false, context: string | null = null) {
+    super(message);
+    this.name = 'AppCommandError';
+    this.kind = kind;
+    this.retryable = retryable;
+    this.context = context;
+  }
+}
+
+// Turns whatever `invoke` rejected with into an AppCommandError
+export function toAppCommandError(error: unknown): AppCommandError {
+  if (error instanceof AppCommandError) return error;
+  if (error && typeof error === 'object' && 'kind' in error && 'message' in error) {
+    const e = error as { kind: AppErrorKind; message: string; retryable?: boolean; context?: string | null };
+    return new AppCommandError(e.kind, e.message, e.retryable ?? false, e.context ?? null);
+  }
+  if (error instanceof Error && error.message) return new AppCommandError('internal', error.message);
+  if (typeof error === 'string') return new AppCommandError('internal', error);
+  return new AppCommandError('internal', error == null ? 'Unknown Tauri error' : String(error));
+}
+
+export function isAppErrorKind(error: unknown, kind: AppErrorKind): boolean {
+  return error instanceof AppCommandError && error.kind === kind;
+}
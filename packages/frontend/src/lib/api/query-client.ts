@@ -1,22 +1,14 @@
 // Tauri API client for database queries
 
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+import { toAppCommandError } from './errors';
 
 // Check if running in Tauri
 function isTauri(): boolean {
   return typeof window !== 'undefined' && '__TAURI_INTERNALS__' in window;
 }
 
-function getInvokeErrorMessage(error: unknown): string {
-  if (error instanceof Error && error.message) return error.message;
-  if (typeof error === 'string') return error;
-  if (error && typeof error === 'object' && 'message' in error) {
-    const m = (error as { message: unknown }).message;
-    if (typeof m === 'string') return m;
-  }
-  return String(error);
-}
-
 // Helper to handle Tauri errors gracefully
 async function safeInvoke<T>(command: string, args?: Record<string, unknown>): Promise<T> {
   if (!isTauri()) {
@@ -25,27 +17,69 @@ async function safeInvoke<T>(command: string, args?: Record<string, unknown>): P
   try {
     return await invoke<T>(command, args);
   } catch (error) {
-    const errorMessage = getInvokeErrorMessage(error);
+    const appError = toAppCommandError(error);
     console.error(`[query-client] Error in ${command}:`, error);
-    console.error(`[query-client] Error message:`, errorMessage);
+    console.error(`[query-client] Error:`, appError.kind, appError.message);
     console.error(`[query-client] Args:`, args);
-    throw new Error(errorMessage);
+    throw appError;
   }
 }
 
 // ==================== Query Parameters ====================
 
+// Storefront of a sales row
+export type SalesSource = 'steam' | 'itch';
+
+// Named period resolved by the backend with the fiscal year from the settings: 'all', 'today',
+// 'yesterday', 'last_<n>d', 'this_month', 'last_month', 'this_quarter', 'last_quarter',
+// 'this_year', 'last_year', 'this_fy', 'last_fy' or 'fy<year>' (the fiscal year ending in it)
+export type RelativeRange = string;
+
+// The backend also takes these keys in camelCase (startDate, appIds, ...), as its results use
 export interface QueryFilters {
   start_date?: string;
   end_date?: string;
+  // Instead of start_date/end_date; with none of them, the settings' default range applies
+  date_range?: RelativeRange;
   app_ids?: number[];
   country_code?: string;
+  source?: SalesSource;
   limit?: number;
   offset?: number;
-  sort_by?: string;
-  sort_order?: string;
+  sort_by?: 'date' | 'revenue' | 'units';
+  sort_order?: 'asc' | 'desc';
+  // Leave out the day Steam is still reporting; defaults to the setting
+  exclude_partial_day?: boolean;
+  // Period daily summaries are grouped by; defaults to day
+  granularity?: Granularity;
+  // Merge the apps of each product group into one app summary
+  group_products?: boolean;
+  // Custom metrics (by name) to evaluate per daily, app and country summary
+  metrics?: string[];
+  // Count grant and activation line items in summaries; left out by default
+  include_non_sale_items?: boolean;
+  // Revenue figure summaries add up; defaults to gross
+  revenue_basis?: RevenueBasis;
+  // Only rows with these package_sale_type values, e.g. ['Steam'] to leave retail keys out
+  sale_types?: string[];
+  // Id of the data source to query instead of the active one, e.g. an imported share bundle
+  data_source?: string;
+  // Query the data source synced with this API key instead of the active one (see
+  // querySources); with data_source, that source must be synced with the key
+  api_key_id?: string;
+  // Overlay manual adjustments on the rows; off by default
+  apply_adjustments?: boolean;
 }
 
+// Gross includes tax; net is Steam's net with returns taken out; net_after_tax also takes out tax
+export type RevenueBasis = 'gross' | 'net' | 'net_after_tax';
+
+// Values of the requested custom metrics by name; null where a division by zero left no value
+export type MetricValues = Record<string, number | null>;
+
+// Weeks are ISO weeks (Monday to Sunday); each period is labelled by its first day
+export type Granularity = 'day' | 'week' | 'month' | 'quarter' | 'year';
+
 // ==================== Response Types ====================
 
 export interface SalesRecord {
@@ -67,16 +101,28 @@ export interface SalesRecord {
   grossSalesUsd: number;
   netSalesUsd: number;
   discountPercentage: number | null;
+  lineItemCategory: LineItemCategory;
+  /** rowid in sales_data; manual adjustments refer to it. null for itch.io rows */
+  rowId: number | null;
 }
 
+// Grants are rows another partner shares; activations are keys activated without a sale
+export type LineItemCategory = 'sale' | 'return' | 'grant' | 'activation';
+
 export interface SalesResponse {
   records: SalesRecord[];
   pagination: {
     total: number;
     limit: number;
     offset: number;
+    hasMore: boolean;
+    /** @deprecated Use `hasMore`. */
     has_more: boolean;
   };
+  // While a sync runs with snapshot reads on: when the snapshot read was taken (ISO 8601)
+  dataAsOf: string | null;
+  /** @deprecated Use `dataAsOf`. */
+  data_as_of: string | null;
 }
 
 export interface DashboardStats {
@@ -86,13 +132,19 @@ export interface DashboardStats {
   appCount: number;
   countryCount: number;
   dateRange: { min: string; max: string } | null;
+  // While a sync runs with snapshot reads on: when the snapshot read was taken (ISO 8601)
+  dataAsOf: string | null;
 }
 
 export interface DailySummary {
+  // The day, or the first day of the period when grouped by week, month, quarter or year
   date: string;
   totalRevenue: number;
   totalUnits: number;
   recordCount: number;
+  // The day Steam is still reporting; its totals will grow
+  partial: boolean;
+  metrics?: MetricValues;
 }
 
 export interface AppSummary {
@@ -103,15 +155,21 @@ export interface AppSummary {
   recordCount: number;
   firstSale: string;
   lastSale: string;
+  // Set when the summary merges the apps of a product group
+  groupId: number | null;
+  metrics?: MetricValues;
 }
 
 export interface CountrySummary {
   countryCode: string;
   countryName: string | null;
   region: string | null;
+  subregion: string | null;
+  euMember: boolean;
   totalRevenue: number;
   totalUnits: number;
   recordCount: number;
+  metrics?: MetricValues;
 }
 
 export interface AppLookup {
@@ -123,6 +181,8 @@ export interface CountryLookup {
   countryCode: string;
   countryName: string;
   region: string | null;
+  subregion: string | null;
+  euMember: boolean;
 }
 
 export interface PackageLookup {
@@ -130,6 +190,27 @@ export interface PackageLookup {
   packageName: string;
 }
 
+// A data source queries can be scoped to with data_source or api_key_id
+export interface QuerySource {
+  id: string;
+  name: string;
+  // API key the source is synced with; null for imported and itch.io-only sources
+  apiKeyId: string | null;
+  keyHint: string | null;
+  active: boolean;
+  readOnly: boolean;
+  // null if the database doesn't exist yet or has no sales
+  dateRange: { min: string; max: string } | null;
+  recordCount: number;
+}
+
+export interface ProductMatch {
+  id: number;
+  productType: 'app' | 'package' | 'bundle';
+  name: string;
+  score: number;
+}
+
 export interface PlatformSummary {
   platform: string | null;
   totalRevenue: number;
@@ -158,30 +239,199 @@ export interface LaunchComparisonApp {
   packageId: number | null;
   appName: string | null;
   packageName: string | null;
+  // Set when the series merges the members of a product group
+  groupId: number | null;
+  // Set on a saved benchmark, which is frozen instead of computed from the current data
+  benchmarkId: number | null;
+  benchmarkName: string | null;
   launchDate: string;
   days: LaunchDay[];
 }
 
+export type TaxJurisdiction = 'eu_vat' | 'us_sales_tax' | 'other';
+
+export interface CountryTax {
+  countryCode: string;
+  countryName: string | null;
+  grossRevenue: number;
+  tax: number;
+  revenueExcludingTax: number;
+}
+
+export interface JurisdictionTax {
+  jurisdiction: TaxJurisdiction;
+  grossRevenue: number;
+  tax: number;
+  revenueExcludingTax: number;
+  countries: CountryTax[];
+}
+
+export interface TaxPeriod {
+  periodStart: string;
+  periodEnd: string;
+  grossRevenue: number;
+  tax: number;
+  revenueExcludingTax: number;
+  jurisdictions: JurisdictionTax[];
+}
+
+export type CohortBy = 'country' | 'region' | 'discount_tier';
+
+export interface Cohort {
+  /** Country code, region name or discount tier */
+  key: string;
+  label: string;
+  totalRevenue: number;
+  /** Gross revenue per week since launch; index 0 is the launch week */
+  revenueByWeek: number[];
+  unitsByWeek: number[];
+}
+
+export interface CohortMatrix {
+  appId: number;
+  cohortBy: CohortBy;
+  launchDate: string | null;
+  weeks: number;
+  cohorts: Cohort[];
+}
+
+export interface ProfileRange {
+  /** Defaults to the app's first day with sales */
+  startDate?: string;
+  /** Defaults to the last complete day */
+  endDate?: string;
+}
+
+export interface ProfileBucket {
+  /** 0 = Sunday for weekdays, 1 = January for months */
+  index: number;
+  label: string;
+  days: number;
+  averageRevenue: number;
+  averageUnits: number;
+  /** Average revenue relative to the average of all days; 1.2 is 20% above it */
+  lift: number;
+}
+
+export interface WeekdayProfile {
+  appId: number;
+  startDate: string | null;
+  endDate: string | null;
+  averageDailyRevenue: number;
+  averageDailyUnits: number;
+  weekdays: ProfileBucket[];
+  months: ProfileBucket[];
+}
+
+export type HeatmapMetric = 'revenue' | 'units';
+
+export interface HeatmapDay {
+  date: string;
+  value: number;
+  /** Share of the year's days with sales that sold at most as much, 0-1; 0 without sales */
+  percentile: number;
+  /** 0 without sales, otherwise 1-4 by quarter of the percentile */
+  level: number;
+}
+
+export interface CalendarHeatmap {
+  year: number;
+  appId: number | null;
+  metric: HeatmapMetric;
+  total: number;
+  maxValue: number;
+  days: HeatmapDay[];
+}
+
+export interface PriceRegionSummary {
+  /** e.g. "EUR" or "LATAM - USD"; "Unknown" for rows without a known country */
+  priceRegion: string;
+  currency: string | null;
+  /** Countries of the region with sales, largest first */
+  countries: string[];
+  totalRevenue: number;
+  totalUnits: number;
+  /** Gross revenue per net unit */
+  averagePrice: number | null;
+  /** Share of the revenue of all regions, 0-1 */
+  revenueShare: number;
+}
+
+export interface CompareSelector {
+  /** Shown in the legend; defaults to the product and dates */
+  label?: string;
+  /** At most one of appId and packageId; neither compares all products */
+  appId?: number;
+  packageId?: number;
+  startDate?: string;
+  endDate?: string;
+  /** Instead of start and end date: the product's first days, from its first day with revenue */
+  launchDays?: number;
+}
+
+export interface CompareDay {
+  /** Days since the start of the series */
+  offset: number;
+  date: string;
+  revenue: number;
+  units: number;
+}
+
+export interface CompareSeries {
+  label: string;
+  appId: number | null;
+  packageId: number | null;
+  startDate: string | null;
+  endDate: string | null;
+  totalRevenue: number;
+  totalUnits: number;
+  /** Up to the end date or the last complete day, whichever is earlier */
+  days: CompareDay[];
+}
+
 // ==================== Query Functions ====================
 
-export async function getStats(filters: QueryFilters = {}): Promise<DashboardStats> {
-  return safeInvoke<DashboardStats>('query_stats', { filters });
+// Functions taking a `queryId` can be stopped with `cancelQuery(queryId)` while they run; they
+// then fail with a 'cancelled' error. Every query fails with a 'timeout' error once it has run
+// longer than the queryTimeoutSecs setting.
+
+export async function getStats(
+  filters: QueryFilters = {},
+  queryId?: string
+): Promise<DashboardStats> {
+  return safeInvoke<DashboardStats>('query_stats', { filters, queryId });
 }
 
-export async function getSales(filters: QueryFilters = {}): Promise<SalesResponse> {
-  return safeInvoke<SalesResponse>('query_sales', { filters });
+export async function getSales(
+  filters: QueryFilters = {},
+  queryId?: string
+): Promise<SalesResponse> {
+  const response = await safeInvoke<SalesResponse>('query_sales', { filters, queryId });
+  // The backend used to send these in snake_case; kept until every caller reads the new names
+  response.pagination.has_more = response.pagination.hasMore;
+  response.data_as_of = response.dataAsOf;
+  return response;
 }
 
-export async function getDailySummaries(filters: QueryFilters = {}): Promise<DailySummary[]> {
-  return safeInvoke<DailySummary[]>('query_daily_summaries', { filters });
+export async function getDailySummaries(
+  filters: QueryFilters = {},
+  queryId?: string
+): Promise<DailySummary[]> {
+  return safeInvoke<DailySummary[]>('query_daily_summaries', { filters, queryId });
 }
 
-export async function getAppSummaries(filters: QueryFilters = {}): Promise<AppSummary[]> {
-  return safeInvoke<AppSummary[]>('query_app_summaries', { filters });
+export async function getAppSummaries(
+  filters: QueryFilters = {},
+  queryId?: string
+): Promise<AppSummary[]> {
+  return safeInvoke<AppSummary[]>('query_app_summaries', { filters, queryId });
 }
 
-export async function getCountrySummaries(filters: QueryFilters = {}): Promise<CountrySummary[]> {
-  return safeInvoke<CountrySummary[]>('query_country_summaries', { filters });
+export async function getCountrySummaries(
+  filters: QueryFilters = {},
+  queryId?: string
+): Promise<CountrySummary[]> {
+  return safeInvoke<CountrySummary[]>('query_country_summaries', { filters, queryId });
 }
 
 export async function getAppsLookup(): Promise<AppLookup[]> {
@@ -196,8 +446,52 @@ export async function getDatesList(): Promise<string[]> {
   return safeInvoke<string[]>('query_dates_list');
 }
 
-export async function getRawDataByDate(date: string): Promise<SalesRecord[]> {
-  return safeInvoke<SalesRecord[]>('query_raw_data_by_date', { date });
+export async function getRawDataByDate(date: string, queryId?: string): Promise<SalesRecord[]> {
+  return safeInvoke<SalesRecord[]>('query_raw_data_by_date', { date, queryId });
+}
+
+interface RawDataChunk {
+  requestId: string;
+  chunkIndex: number;
+  records: SalesRecord[];
+  done: boolean;
+}
+
+export interface RawDataStream {
+  totalRecords: number;
+  chunks: number;
+}
+
+/**
+ * Loads the records of `date` in chunks, calling `onChunk` as each arrives, so large days
+ * don't arrive as one payload that blocks the UI. `signal` aborting cancels the query.
+ */
+export async function streamRawDataByDate(
+  date: string,
+  onChunk: (records: SalesRecord[]) => void,
+  chunkSize?: number,
+  signal?: AbortSignal
+): Promise<RawDataStream> {
+  const requestId = crypto.randomUUID();
+  const unlisten = await listen<RawDataChunk>('raw-data-chunk', (event) => {
+    if (event.payload.requestId === requestId) onChunk(event.payload.records);
+  });
+  const cancel = () => cancelQuery(requestId).catch(() => {});
+  signal?.addEventListener('abort', cancel);
+  try {
+    return await safeInvoke<RawDataStream>('stream_raw_data_by_date', {
+      date,
+      requestId,
+      chunkSize,
+    });
+  } finally {
+    signal?.removeEventListener('abort', cancel);
+    unlisten();
+  }
+}
+
+export async function querySources(): Promise<QuerySource[]> {
+  return safeInvoke<QuerySource[]>('query_sources');
 }
 
 export async function getPackagesLookup(): Promise<PackageLookup[]> {
@@ -208,19 +502,450 @@ export async function getPackagesByApp(appId: number): Promise<PackageLookup[]>
   return safeInvoke<PackageLookup[]>('query_packages_by_app', { appId });
 }
 
+// Apps, packages and bundles by name (typos allowed) or id, best match first
+export async function searchProducts(query: string, limit?: number): Promise<ProductMatch[]> {
+  return safeInvoke<ProductMatch[]>('search_products', { query, limit });
+}
+
+// productType 'group' takes a product group id
 export async function getProductStats(
-  productType: 'app' | 'package',
-  productId: number
+  productType: 'app' | 'package' | 'group',
+  productId: number,
+  revenueBasis?: RevenueBasis,
+  queryId?: string
 ): Promise<ProductStats> {
   return safeInvoke<ProductStats>('query_product_stats', {
     productType,
     productId,
+    revenueBasis,
+    queryId,
   });
 }
 
-export async function getLaunchComparison(maxDays: number, productType: 'app' | 'package' = 'app'): Promise<LaunchComparisonApp[]> {
+// `productIds` (app or package ids, by productType) limits the comparison to those products.
+// With includeBenchmarks, the saved benchmarks of the product type and revenue basis follow the
+// live series.
+export async function getLaunchComparison(
+  maxDays: number,
+  productType: 'app' | 'package' = 'app',
+  excludePartialDay?: boolean,
+  groupProducts?: boolean,
+  revenueBasis?: RevenueBasis,
+  productIds?: number[],
+  queryId?: string,
+  includeBenchmarks?: boolean
+): Promise<LaunchComparisonApp[]> {
   return safeInvoke<LaunchComparisonApp[]>('query_launch_comparison', {
     maxDays,
     productType,
+    excludePartialDay,
+    groupProducts,
+    revenueBasis,
+    productIds,
+    includeBenchmarks,
+    queryId,
   });
 }
+
+export interface Benchmark {
+  id: number;
+  name: string;
+  productType: 'app' | 'package';
+  productId: number;
+  productName: string | null;
+  revenueBasis: RevenueBasis;
+  launchDate: string;
+  daysCount: number;
+  createdAt: string;
+  days: LaunchDay[];
+}
+
+export async function listBenchmarks(): Promise<Benchmark[]> {
+  return safeInvoke<Benchmark[]>('list_benchmarks');
+}
+
+// Freezes the product's first maxDays days since launch under `name`
+export async function saveBenchmark(
+  name: string,
+  productType: 'app' | 'package',
+  productId: number,
+  maxDays: number,
+  revenueBasis?: RevenueBasis
+): Promise<Benchmark> {
+  return safeInvoke<Benchmark>('save_benchmark', {
+    name,
+    productType,
+    productId,
+    maxDays,
+    revenueBasis,
+  });
+}
+
+export async function deleteBenchmark(id: number): Promise<void> {
+  return safeInvoke<void>('delete_benchmark', { id });
+}
+
+// Stops the query that was called with `queryId`; rejects with 'notFound' if it already finished
+export async function cancelQuery(queryId: string): Promise<void> {
+  return safeInvoke<void>('cancel_query', { queryId });
+}
+
+export async function getTaxSummary(
+  period: Granularity = 'quarter',
+  filters: QueryFilters = {}
+): Promise<TaxPeriod[]> {
+  return safeInvoke<TaxPeriod[]>('query_tax_summary', { period, filters });
+}
+
+export async function queryCohorts(
+  appId: number,
+  cohortBy: CohortBy = 'country',
+  maxWeeks?: number
+): Promise<CohortMatrix> {
+  return safeInvoke<CohortMatrix>('query_cohorts', { appId, cohortBy, maxWeeks });
+}
+
+export async function queryWeekdayProfile(
+  appId: number,
+  range?: ProfileRange
+): Promise<WeekdayProfile> {
+  return safeInvoke<WeekdayProfile>('query_weekday_profile', { appId, range });
+}
+
+export async function queryCalendarHeatmap(
+  year: number,
+  appId?: number,
+  metric: HeatmapMetric = 'revenue'
+): Promise<CalendarHeatmap> {
+  return safeInvoke<CalendarHeatmap>('query_calendar_heatmap', { year, appId, metric });
+}
+
+export async function getPriceRegionSummaries(
+  filters: QueryFilters = {}
+): Promise<PriceRegionSummary[]> {
+  return safeInvoke<PriceRegionSummary[]>('query_by_price_region', { filters });
+}
+
+export async function queryCompare(selectors: CompareSelector[]): Promise<CompareSeries[]> {
+  return safeInvoke<CompareSeries[]>('query_compare', { selectors });
+}
+
+export interface DecayWeek {
+  /** Weeks since launch; week 0 starts on the launch date */
+  week: number;
+  revenue: number;
+  /** Revenue per the fitted curve; null for week 0 and without a fit */
+  fitted: number | null;
+}
+
+export interface PowerLawFit {
+  /** a in revenue = a * week^-b */
+  coefficient: number;
+  /** b in revenue = a * week^-b; larger decays faster */
+  exponent: number;
+  rSquared: number;
+  fittedWeeks: number;
+}
+
+export interface DecayCurve {
+  appId: number;
+  launchDate: string | null;
+  lifetimeRevenue: number;
+  /** Complete weeks since launch */
+  weeks: DecayWeek[];
+  fit: PowerLawFit | null;
+  /** Weeks until weekly revenue halves from the latest week, per the fit */
+  currentHalfLifeWeeks: number | null;
+  /** Weeks since launch it took to earn half of the revenue so far */
+  weeksToHalfRevenue: number | null;
+  /** Revenue of the next 52 weeks per the fit */
+  projectedNextYear: number | null;
+}
+
+export async function queryDecayCurve(appId: number): Promise<DecayCurve> {
+  return safeInvoke<DecayCurve>('query_decay_curve', { appId });
+}
+
+export interface PortfolioPeriod {
+  /** Defaults to 30 days before the end date */
+  startDate?: string;
+  /** Defaults to the last complete day */
+  endDate?: string;
+}
+
+export interface PortfolioTitle {
+  appId: number;
+  appName: string | null;
+  revenue: number;
+  units: number;
+  /** Share of the portfolio's revenue in the period, 0-1 */
+  revenueShare: number;
+  rank: number;
+  previousRevenue: number;
+  /** null if the title had no revenue in the previous period */
+  previousRank: number | null;
+  /** Places moved up since the previous period; negative if down */
+  rankChange: number | null;
+}
+
+export interface PortfolioConcentration {
+  topTitleShare: number;
+  topThreeShare: number;
+  /** Sum of squared revenue shares; 1 when a single title earns everything */
+  herfindahlIndex: number;
+  titlesForHalfRevenue: number;
+}
+
+export interface Portfolio {
+  startDate: string;
+  endDate: string;
+  /** The period of the same length just before */
+  previousStartDate: string;
+  previousEndDate: string;
+  totalRevenue: number;
+  previousTotalRevenue: number;
+  /** e.g. 0.1 for +10%; null without previous revenue */
+  revenueChange: number | null;
+  titles: PortfolioTitle[];
+  concentration: PortfolioConcentration;
+}
+
+export async function queryPortfolio(period?: PortfolioPeriod): Promise<Portfolio> {
+  return safeInvoke<Portfolio>('query_portfolio', { period });
+}
+
+export interface SalesPage {
+  /** Raw sales_data rows after the cursor, as column name to value, with their rowid */
+  rows: Record<string, unknown>[];
+  /** Pass as the cursor of the next page */
+  nextCursor: number;
+  hasMore: boolean;
+  /** Rows in sales_data; fewer than a replica holds means rows were deleted */
+  totalRows: number;
+}
+
+export async function querySalesSince(rowidCursor: number, limit?: number): Promise<SalesPage> {
+  return safeInvoke<SalesPage>('query_sales_since', { rowidCursor, limit });
+}
+
+export interface DiscountTierEconomics {
+  tier: 'none' | '1-24' | '25-49' | '50-74' | '75+';
+  label: string;
+  grossUnits: number;
+  netUnits: number;
+  revenue: number;
+  /** Gross revenue per gross unit sold; null without units */
+  averagePrice: number | null;
+  /** Share of the package's gross units, 0-1 */
+  unitShare: number;
+}
+
+export interface RegionEconomics {
+  priceRegion: string;
+  currency: string | null;
+  grossUnits: number;
+  revenue: number;
+  averagePrice: number | null;
+  /** Share of the package's revenue, 0-1 */
+  revenueShare: number;
+}
+
+export interface PackageEconomics {
+  packageId: number;
+  packageName: string | null;
+  firstSale: string | null;
+  lastSale: string | null;
+  totalRevenue: number;
+  netRevenue: number;
+  grossUnits: number;
+  returnedUnits: number;
+  netUnits: number;
+  averagePrice: number | null;
+  /** Returned units per gross unit sold, 0-1 */
+  returnRate: number | null;
+  /** Empty if the database has no discount column */
+  byDiscount: DiscountTierEconomics[];
+  byRegion: RegionEconomics[];
+}
+
+export async function queryPackageEconomics(packageId: number): Promise<PackageEconomics> {
+  return safeInvoke<PackageEconomics>('query_package_economics', { packageId });
+}
+
+export interface DiscountPeriod {
+  packageId: number;
+  appId: number | null;
+  startDate: string;
+  /** Last day with sales at this depth */
+  endDate: string;
+  /** Whole percent off */
+  discountPercentage: number;
+  grossUnits: number;
+  revenue: number;
+}
+
+/** Past discount periods of an app's packages, oldest first */
+export async function queryDiscountCalendar(appId: number): Promise<DiscountPeriod[]> {
+  return safeInvoke<DiscountPeriod[]>('query_discount_calendar', { appId });
+}
+
+/** Reconstructs the discount calendar from the sales rows; returns the number of periods */
+export async function rebuildDiscountCalendar(): Promise<number> {
+  return safeInvoke<number>('rebuild_discount_calendar');
+}
+
+export interface PlatformShare {
+  platform: string;
+  units: number;
+  /** Share of the period's gross units, 0-1 */
+  share: number;
+}
+
+export interface PlatformSharePeriod {
+  periodStart: string;
+  totalUnits: number;
+  /** In the order of PlatformShareTrend.platforms */
+  shares: PlatformShare[];
+}
+
+export interface PlatformShareTrend {
+  appId: number;
+  granularity: Granularity;
+  /** Largest first over the whole range */
+  platforms: string[];
+  periods: PlatformSharePeriod[];
+}
+
+export async function queryPlatformShareTrend(
+  appId: number,
+  granularity: Granularity = 'month'
+): Promise<PlatformShareTrend> {
+  return safeInvoke<PlatformShareTrend>('query_platform_share_trend', { appId, granularity });
+}
+
+export interface SaleTypeSummary {
+  /** e.g. "Steam" or "Retail"; "Unknown" for rows without one */
+  saleType: string;
+  totalRevenue: number;
+  netRevenue: number;
+  grossUnits: number;
+  netUnits: number;
+  averagePrice: number | null;
+  /** Share of the gross revenue of all sale types, 0-1 */
+  revenueShare: number;
+}
+
+/** Revenue per package sale type; `filters.sale_types` is ignored */
+export async function querySaleTypeSummary(
+  filters: QueryFilters = {}
+): Promise<SaleTypeSummary[]> {
+  return safeInvoke<SaleTypeSummary[]>('query_sale_type_summary', { filters });
+}
+
+export interface ShareBundle {
+  path: string;
+  rows: number;
+  apps: number;
+  firstDate: string | null;
+  lastDate: string | null;
+  sizeBytes: number;
+}
+
+/**
+ * Writes the apps and date range of `filters` to a standalone SQLite file at `path`, without the
+ * rest of the account's data or the CLI's API key. Replaces any file at `path`.
+ */
+export async function exportShareBundle(filters: QueryFilters, path: string): Promise<ShareBundle> {
+  return safeInvoke<ShareBundle>('export_share_bundle', { filters, path });
+}
+
+export interface DashboardJson {
+  formatVersion: number;
+  generatedAt: string;
+  /** The filters the numbers are for, with a named date range resolved to dates */
+  filters: QueryFilters;
+  stats: DashboardStats;
+  /** One entry per day, or per period with `granularity` in the filters */
+  daily: DailySummary[];
+  apps: AppSummary[];
+  countries: CountrySummary[];
+  platforms: PlatformSummary[];
+}
+
+export interface DashboardJsonFile {
+  path: string;
+  sizeBytes: number;
+}
+
+/**
+ * Writes the stats, series and app, country and platform totals of `filters` to `path` as one
+ * `DashboardJson` document, e.g. for a static report generator. Replaces any file at `path`.
+ */
+export async function exportDashboardJson(
+  filters: QueryFilters,
+  path: string
+): Promise<DashboardJsonFile> {
+  return safeInvoke<DashboardJsonFile>('export_dashboard_json', { filters, path });
+}
+
+export interface ReportingViews {
+  databasePath: string;
+  views: string[];
+}
+
+/**
+ * Creates the v_sales, v_daily and v_by_app views in the active sales database, with typed
+ * columns and resolved names for BI tools that open the file. Safe to run again.
+ */
+export async function createReportingViews(): Promise<ReportingViews> {
+  return safeInvoke<ReportingViews>('create_reporting_views');
+}
+
+export type ClipboardReport = 'daily' | 'apps' | 'countries';
+export type ClipboardFormat = 'tsv' | 'markdown';
+
+/**
+ * Copies a summary with `filters` to the clipboard as TSV (for spreadsheets) or a Markdown table
+ * (for Slack, Notion, ...) and returns how many rows were copied. Amounts are masked in
+ * presentation mode, like the dashboard's.
+ */
+export async function copyQueryToClipboard(
+  kind: ClipboardReport,
+  filters: QueryFilters,
+  format: ClipboardFormat
+): Promise<number> {
+  return safeInvoke<number>('copy_query_to_clipboard', { kind, filters, format });
+}
+
+export interface DateAppDiff {
+  date: string;
+  appId: number | null;
+  rowsAdded: number;
+  rowsRemoved: number;
+  rowsChanged: number;
+  /** Newer snapshot minus the older one */
+  grossRevenueChange: number;
+  netRevenueChange: number;
+  netUnitsChange: number;
+}
+
+export interface SnapshotDiff {
+  rowsBefore: number;
+  rowsAfter: number;
+  rowsAdded: number;
+  rowsRemoved: number;
+  rowsChanged: number;
+  keyColumns: string[];
+  /** Only dates and apps with differences, oldest first */
+  changes: DateAppDiff[];
+}
+
+/** Compares the sales rows of two database files, the older one first */
+export async function diffSnapshots(backupA: string, backupB: string): Promise<SnapshotDiff> {
+  return safeInvoke<SnapshotDiff>('diff_snapshots', { backupA, backupB });
+}
+
+/** Drops cached stats and summaries; they are also dropped after a fetch or any database change */
+export async function clearQueryCache(): Promise<void> {
+  return safeInvoke<void>('clear_query_cache');
+}
@@ -478,9 +478,21 @@ export function getAppsLookup(): AppLookup[] {
     `SELECT DISTINCT appid as appId FROM sales_data WHERE appid IS NOT NULL ORDER BY appId`
   );
 
+  // Names refreshed from Steam by the desktop app win over the ones the CLI stored
+  const tables = getTableNames();
+  const names = new Map<number, string>();
+  for (const table of ['lookup_apps', 'lookup_app_names']) {
+    if (!tables.includes(table)) continue;
+    for (const row of query<{ appId: number; appName: string | null }>(
+      `SELECT appid as appId, app_name as appName FROM ${table}`
+    )) {
+      if (row.appName) names.set(row.appId, row.appName);
+    }
+  }
+
   return apps.map(a => ({
     appId: a.appId,
-    appName: `App ${a.appId}`, // CLI tool may not store app names
+    appName: names.get(a.appId) ?? `App ${a.appId}`, // CLI tool may not store app names
   }));
 }
 
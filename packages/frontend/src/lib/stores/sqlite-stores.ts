@@ -17,6 +17,7 @@ import type {
   AppLookup,
   CountryLookup,
   QueryFilters,
+  SalesSource,
 } from '$lib/api/query-client';
 
 // ==================== Database Loaded State ====================
@@ -42,6 +43,7 @@ export interface Filters {
   endDate?: string;
   appIds?: number[];
   countryCode?: string;
+  source?: SalesSource;
 }
 
 // Convert Filters to QueryFilters
@@ -51,6 +53,7 @@ function toQueryFilters(filters: Filters, additional: Partial<QueryFilters> = {}
     end_date: filters.endDate,
     app_ids: filters.appIds,
     country_code: filters.countryCode,
+    source: filters.source,
     ...additional,
   };
 }
@@ -74,11 +77,18 @@ function createFilterStore() {
     setCountry(countryCode?: string) {
       update(f => ({ ...f, countryCode }));
     },
+    setSource(source?: SalesSource) {
+      update(f => ({ ...f, source }));
+    },
   };
 }
 
 export const filterStore = createFilterStore();
 
+// Product the Product Details tab should select, e.g. from a steamsales://package link.
+// The tab clears it once it has selected the product.
+export const productFocus = writable<{ type: 'app' | 'package'; id: number } | null>(null);
+
 // ==================== Stats Store ====================
 
 function createStatsStore() {
@@ -226,7 +236,7 @@ function createSalesRecordsStore() {
           sort_order: sortOrder,
         }));
         records.set(response.records);
-        pagination.set({ total: response.pagination.total, hasMore: response.pagination.has_more });
+        pagination.set({ total: response.pagination.total, hasMore: response.pagination.hasMore });
       } catch (e) {
         error.set(e instanceof Error ? e.message : 'Failed to load sales records');
       } finally {
@@ -247,7 +257,7 @@ function createSalesRecordsStore() {
           limit: 1000,
         }));
         records.update(r => [...r, ...response.records]);
-        pagination.set({ total: response.pagination.total, hasMore: response.pagination.has_more });
+        pagination.set({ total: response.pagination.total, hasMore: response.pagination.hasMore });
       } catch (e) {
         error.set(e instanceof Error ? e.message : 'Failed to load more records');
       } finally {
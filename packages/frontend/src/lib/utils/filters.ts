@@ -2,6 +2,7 @@
 // Used by stores, workers, and database queries
 
 import type { Filters } from '$lib/stores/sqlite-stores';
+import type { NavigationRequest } from '$lib/api/cli-client';
 
 /** Minimal record shape used for filtering (compatible with query-client and services/types) */
 type FilterableRecord = {
@@ -92,3 +93,26 @@ export function filterStoreToFilters(filterStoreValue: Filters): Filters {
     endDate: filterStoreValue.endDate,
   };
 }
+
+/**
+ * Filters after following a steamsales:// link.
+ *
+ * A link with a range or dates replaces the date range ("all" clears it); without, the current
+ * one is kept. An app link filters by that app. The backend has already turned ranges like
+ * "30d" into dates.
+ *
+ * @example
+ * navigationFilters({ appId: 440, range: '30d', startDate: '2024-03-02', endDate: '2024-03-31', ... }, {})
+ * // Returns: { appIds: [440], startDate: '2024-03-02', endDate: '2024-03-31' }
+ */
+export function navigationFilters(request: NavigationRequest, current: Filters): Filters {
+  const filters = { ...current };
+  if (request.range || request.startDate || request.endDate) {
+    filters.startDate = request.startDate ?? undefined;
+    filters.endDate = request.endDate ?? undefined;
+  }
+  if (request.appId != null) {
+    filters.appIds = [request.appId];
+  }
+  return filters;
+}